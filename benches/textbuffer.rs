@@ -0,0 +1,107 @@
+// Compares the TextBuffer line storages on big files.
+// run with: cargo bench --bench textbuffer
+#![allow(dead_code)]
+
+#[path = "../src/textlines.rs"]
+mod textlines;
+#[path = "../src/textbuffer.rs"]
+mod textbuffer;
+#[path = "../src/tokenizer.rs"]
+mod tokenizer;
+#[path = "../src/languagemode.rs"]
+mod languagemode;
+
+use std::hint::black_box;
+use std::time::Instant;
+use crate::textlines::*;
+use crate::textbuffer::*;
+
+// small deterministic random so both storages see the same edits
+struct Lcg(u64);
+
+impl Lcg{
+    fn next(&mut self, max:usize)->usize{
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % max.max(1)
+    }
+}
+
+fn many_lines_file(lines:usize)->Vec<u8>{
+    let mut out = String::new();
+    for i in 0..lines{
+        out.push_str(&format!("    let value_{} = some_function(arg, {});\n", i, i * 7));
+    }
+    out.into_bytes()
+}
+
+fn single_line_file(bytes:usize)->Vec<u8>{
+    let mut out = String::new();
+    while out.len() < bytes{
+        out.push_str("{\"key\":[1,2,3],\"value\":\"text\"},");
+    }
+    out.into_bytes()
+}
+
+fn time_it<F>(name:&str, iters:usize, mut f:F)->f64
+where F: FnMut()
+{
+    let start = Instant::now();
+    for _ in 0..iters{
+        f();
+    }
+    let elapsed = start.elapsed();
+    let per_iter = elapsed.as_secs_f64() * 1_000_000.0 / (iters as f64);
+    println!("    {:<28} {:>12.2} us/iter", name, per_iter);
+    per_iter
+}
+
+// returns the us per typed char
+fn bench_storage<L>(storage_name:&str, file_name:&str, data:&Vec<u8>)->f64
+where L: TextLines
+{
+    println!("{} / {}", storage_name, file_name);
+    let mut text_buffer = TextBuffer::<L>{..Default::default()};
+    time_it("load_buffer", 1, ||{
+        text_buffer.load_buffer(data);
+    });
+
+    let mut rng = Lcg(1);
+    time_it("offset_len_to_text_pos", 1000, ||{
+        let offset = rng.next(text_buffer.get_char_count());
+        black_box(text_buffer.offset_len_to_text_pos(offset, rng.next(100)));
+    });
+
+    let mut rng = Lcg(2);
+    time_it("get_range_as_string", 1000, ||{
+        let mut out = String::new();
+        let offset = rng.next(text_buffer.get_char_count());
+        text_buffer.get_range_as_string(offset, 80, &mut out);
+        black_box(out);
+    });
+
+    let mut rng = Lcg(3);
+    let per_char = time_it("replace_with_string char", 1000, ||{
+        let offset = rng.next(text_buffer.get_char_count());
+        text_buffer.replace_with_string(offset, 0, "x");
+    });
+
+    let mut rng = Lcg(4);
+    time_it("replace_with_string lines", 1000, ||{
+        let offset = rng.next(text_buffer.get_char_count());
+        let len = rng.next(200).min(text_buffer.get_char_count() - offset);
+        text_buffer.replace_with_string(offset, len, "a\nb\nc");
+    });
+    per_char
+}
+
+fn main(){
+    let many_lines = many_lines_file(200_000);
+    bench_storage::<FlatLines>("FlatLines", "200k lines", &many_lines);
+    bench_storage::<RopeLines>("RopeLines", "200k lines", &many_lines);
+
+    let single_line = single_line_file(4_000_000);
+    bench_storage::<FlatLines>("FlatLines", "4mb single line", &single_line);
+    let per_char = bench_storage::<RopeLines>("RopeLines", "4mb single line", &single_line);
+    // typing in a minified file has to keep up with the keyboard, the flat line takes ~400us
+    assert!(per_char < 50.0, "typing a char in a 4mb single line took {:.2}us, expected < 50us", per_char);
+}
@@ -1,5 +1,12 @@
 use widgets::*;
+use std::cmp::Ordering;
 use crate::textbuffer::*;
+use crate::findbar::*;
+use crate::tokenizer::*;
+use crate::languagemode::*;
+use crate::codestructure::*;
+use crate::editorcommand::*;
+use crate::vim::*;
 
 #[derive(Clone)]
 pub struct CodeEditor{
@@ -8,14 +15,29 @@ pub struct CodeEditor{
     pub bg: Quad,
     pub cursor: Quad,
     pub marker: Quad,
+    pub search_marker: Quad,
     pub tab:Quad,
+    pub fold_marker:Quad,
+    pub bracket_marker:Quad,
+    pub line_highlight:Quad,
     pub text: Text,
+    pub line_number_text:Text,
+    pub line_number_highlight:Color,
+    pub draw_line_numbers:bool,
     pub cursors:CursorSet,
+    pub find_bar:FindBar,
+    pub syntax:LanguageSyntax,
+    pub keymap:KeyMap<EditorCommand>,
+    // vim emulation, off when None
+    pub vim:Option<Vim>,
+    pub mode_bg:Quad,
+    pub mode_text:Text,
     pub _hit_state:HitState,
     pub _bg_area:Area,
     pub _text_inst:Option<AlignedInstance>,
     pub _text_area:Area,
     pub _scroll_pos:Vec2,
+    pub _view_size:Vec2,
     pub _last_finger_move:Option<Vec2>,
     pub _line_geometry:Vec<LineGeom>,
     pub _token_chunks:Vec<TokenChunk>,
@@ -23,6 +45,31 @@ pub struct CodeEditor{
     pub _visibility_margin:Margin,
     pub _select_scroll:Option<SelectScroll>,
     pub _grid_select_corner:Option<TextPos>,
+    pub _search_start:usize,
+    pub _search_cursors:Vec<Cursor>,
+    pub _draw_search:DrawCursor,
+    pub _jump_to:Option<(TextPos, TextPos)>,
+    // a TextCopy came in for the key being pressed, its cut needs no copy of its own
+    pub _text_copied:bool,
+    pub _structure:CodeStructure,
+    // the rows folded blocks start on, everything up to their closing row is hidden
+    pub _folded_rows:Vec<usize>,
+    pub _folds_line_edit_version:u64,
+    pub _hidden_rows:Vec<bool>,
+    // the longest line and its row, it sets how far the view scrolls sideways
+    pub _max_line_len:usize,
+    pub _max_line_row:usize,
+    pub _max_line_edit_version:Option<u64>,
+    pub _gutter_width:f32,
+    pub _fold_marker_width:f32,
+    pub _line_number_digits:usize,
+    // (x, y, row) of the visible line numbers, drawn after the text
+    pub _line_numbers:Vec<(f32, f32, usize)>,
+    // rows with a cursor on them, sorted
+    pub _cursor_rows:Vec<usize>,
+    // offsets of the brackets matching up at the cursors
+    pub _bracket_marks:Vec<usize>,
+    pub _bracket_sels:Vec<DrawSel>,
 
     pub _monospace_size:Vec2,
     pub _instance_count:usize,
@@ -36,6 +83,43 @@ pub struct LineGeom{
     font_size:f32
 }
 
+#[derive(Clone, Copy, Default)]
+pub struct TokenColors{
+    pub whitespace:Color,
+    pub keyword:Color,
+    pub flow_keyword:Color,
+    pub identifier:Color,
+    pub operator:Color,
+    pub function:Color,
+    pub type_name:Color,
+    pub number:Color,
+    pub comment:Color,
+    pub doc_comment:Color,
+    pub paren:Color,
+    pub string:Color,
+    pub delim:Color
+}
+
+impl TokenColors{
+    pub fn get(&self, token_type:TokenType)->Color{
+        match token_type{
+            TokenType::Whitespace=>self.whitespace,
+            TokenType::Keyword=>self.keyword,
+            TokenType::Flow=>self.flow_keyword,
+            TokenType::Identifier=>self.identifier,
+            TokenType::Call=>self.function,
+            TokenType::TypeName=>self.type_name,
+            TokenType::Number=>self.number,
+            TokenType::String=>self.string,
+            TokenType::Comment=>self.comment,
+            TokenType::DocComment=>self.doc_comment,
+            TokenType::Operator=>self.operator,
+            TokenType::Paren=>self.paren,
+            TokenType::Delimiter=>self.delim
+        }
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct SelectScroll{
     pub margin:Margin,
@@ -53,13 +137,41 @@ impl Style for CodeEditor{
         let tab_sh = Self::def_tab_shader(cx);
         let marker_sh = Self::def_marker_shader(cx);
         let cursor_sh = Self::def_cursor_shader(cx);
+        let fold_marker_sh = Self::def_fold_marker_shader(cx);
+        let line_highlight_sh = Self::def_cursor_shader(cx);
+        let marker_shader_id = cx.add_shader(marker_sh, "Editor.marker");
         let code_editor = Self{
             cursors:CursorSet::new(),
+            find_bar:FindBar{
+                ..Style::style(cx)
+            },
+            syntax:LanguageSyntax{
+                ..Default::default()
+            },
+            keymap:EditorCommand::code_editor_keymap(cx.is_mac_platform()),
+            vim:None,
+            mode_bg:Quad{
+                color:color256(50,50,50),
+                do_scroll:false,
+                ..Style::style(cx)
+            },
+            mode_text:Text{
+                font_id:cx.load_font(&cx.font("mono_font")),
+                font_size:11.0,
+                brightness:1.05,
+                color:color256(200,200,200),
+                ..Style::style(cx)
+            },
             tab:Quad{
                 color:color("#5"),
                 shader_id:cx.add_shader(tab_sh, "Editor.tab"),
                 ..Style::style(cx)
             },
+            fold_marker:Quad{
+                color:color("#8"),
+                shader_id:cx.add_shader(fold_marker_sh, "Editor.fold_marker"),
+                ..Style::style(cx)
+            },
             view:View{
                 scroll_h:Some(ScrollBar{
                     ..Style::style(cx)
@@ -77,9 +189,24 @@ impl Style for CodeEditor{
             },
             marker:Quad{
                 color:color256(42,78,117),
-                shader_id:cx.add_shader(marker_sh, "Editor.marker"),
+                shader_id:marker_shader_id,
                 ..Style::style(cx)
-            }, 
+            },
+            search_marker:Quad{
+                color:color256(80,70,30),
+                shader_id:marker_shader_id,
+                ..Style::style(cx)
+            },
+            bracket_marker:Quad{
+                color:color256(70,70,70),
+                shader_id:marker_shader_id,
+                ..Style::style(cx)
+            },
+            line_highlight:Quad{
+                color:color256(40,40,40),
+                shader_id:cx.add_shader(line_highlight_sh, "Editor.line_highlight"),
+                ..Style::style(cx)
+            },
             cursor:Quad{
                 color:color256(136,136,136),
                 shader_id:cx.add_shader(cursor_sh, "Editor.cursor"),
@@ -100,16 +227,45 @@ impl Style for CodeEditor{
                 wrapping:Wrapping::Line,
                 ..Style::style(cx)
             },
+            line_number_text:Text{
+                font_id:cx.load_font(&cx.font("mono_font")),
+                font_size:11.0,
+                brightness:1.05,
+                color:color256(90,90,90),
+                ..Style::style(cx)
+            },
+            line_number_highlight:color256(200,200,200),
+            draw_line_numbers:true,
             _hit_state:HitState{no_scrolling:true, ..Default::default()},
             _monospace_size:Vec2::zero(),
             _last_finger_move:None,
             _first_on_line:true,
             _scroll_pos:Vec2::zero(),
+            _view_size:Vec2::zero(),
             _visibility_margin:Margin::zero(),
             _visible_lines:0, 
             _line_geometry:Vec::new(),
             _token_chunks:Vec::new(),
             _grid_select_corner:None,
+            _search_start:0,
+            _search_cursors:Vec::new(),
+            _draw_search:DrawCursor::new(),
+            _jump_to:None,
+            _text_copied:false,
+            _structure:CodeStructure{..Default::default()},
+            _folded_rows:Vec::new(),
+            _folds_line_edit_version:0,
+            _hidden_rows:Vec::new(),
+            _max_line_len:0,
+            _max_line_row:0,
+            _max_line_edit_version:None,
+            _gutter_width:0.,
+            _fold_marker_width:0.,
+            _line_number_digits:0,
+            _line_numbers:Vec::new(),
+            _cursor_rows:Vec::new(),
+            _bracket_marks:Vec::new(),
+            _bracket_sels:Vec::new(),
             _bg_area:Area::Empty,
             _text_inst:None,
             _text_area:Area::Empty,
@@ -125,7 +281,8 @@ impl Style for CodeEditor{
 #[derive(Clone, PartialEq)]
 pub enum CodeEditorEvent{
     None,
-    Change
+    Change,
+    Save
 }
 
 impl CodeEditor{
@@ -153,6 +310,30 @@ impl CodeEditor{
         sh
     }
 
+    pub fn def_fold_marker_shader(cx:&mut Cx)->Shader{
+        let mut sh = Quad::def_quad_shader(cx);
+        sh.add_ast(shader_ast!({
+            let folded:float<Instance>;
+            fn pixel()->vec4{ // points down when open, right when folded
+                df_viewport(pos * vec2(w, h));
+                let c:vec2 = vec2(w,h) * 0.5;
+                let s:float = min(w, h) * 0.2;
+                if folded > 0.5{
+                    df_move_to(c.x - 0.5*s, c.y - s);
+                    df_line_to(c.x + 0.5*s, c.y);
+                    df_line_to(c.x - 0.5*s, c.y + s);
+                }
+                else{
+                    df_move_to(c.x - s, c.y - 0.5*s);
+                    df_line_to(c.x, c.y + 0.5*s);
+                    df_line_to(c.x + s, c.y - 0.5*s);
+                }
+                return df_stroke(color, 1.);
+            }
+        }));
+        sh
+    }
+
     pub fn def_marker_shader(cx:&mut Cx)->Shader{
         let mut sh = Quad::def_quad_shader(cx);
         sh.add_ast(shader_ast!({
@@ -192,6 +373,11 @@ impl CodeEditor{
     }
 
     pub fn handle_code_editor(&mut self, cx:&mut Cx, event:&mut Event, text_buffer:&mut TextBuffer)->CodeEditorEvent{
+        let mut ret = CodeEditorEvent::None;
+        match self.find_bar.handle_find_bar(cx, event){
+            FindBarEvent::None=>(),
+            find_bar_event=>self.handle_find_bar_event(cx, find_bar_event, text_buffer)
+        }
         match self.view.handle_scroll_bars(cx, event){
             (_,ScrollBarEvent::Scroll{..}) | (ScrollBarEvent::Scroll{..},_)=>{
                 if let Some(last_finger_move) = self._last_finger_move{
@@ -219,8 +405,29 @@ impl CodeEditor{
                 cx.set_down_mouse_cursor(MouseCursor::Text);
                 // give us the focus
                 cx.set_key_focus(self._bg_area);
-                let offset = self.text.find_closest_offset(cx, &self._text_area, fe.abs);
+                if let Some(vim) = &mut self.vim{ // clicking ends a visual selection
+                    if vim.mode.is_visual(){
+                        vim.set_mode(VimMode::Normal, &mut self.cursors, text_buffer);
+                    }
+                }
+                let rel = self._bg_area.abs_to_rel_scrolled(cx, fe.abs);
+                let in_gutter = rel.x < self._gutter_width && self._line_geometry.len() > 0;
+                let offset = if in_gutter{
+                    let pos = self.compute_grid_text_pos_from_abs(cx, fe.abs);
+                    if rel.x >= self._gutter_width - self._fold_marker_width{ // the fold markers
+                        self.toggle_fold(cx, pos.row, text_buffer);
+                        return ret
+                    }
+                    text_buffer.text_pos_to_offset(TextPos{row:pos.row, col:0})
+                }
+                else{
+                    self.text.find_closest_offset(cx, &self._text_area, fe.abs)
+                };
                 match fe.tap_count{
+                    _ if in_gutter=>{ // line numbers select whole lines, dragging adds more
+                        let range = text_buffer.get_nearest_line_range(offset);
+                        self.cursors.set_last_clamp_range(range);
+                    },
                     2=>{
                         let range = self.get_nearest_token_chunk_range(offset);
                         self.cursors.set_last_clamp_range(range);
@@ -334,124 +541,260 @@ impl CodeEditor{
                 }
             },
             Event::KeyDown(ke)=>{
-                let cursor_moved = match ke.key_code{
-                    KeyCode::ArrowUp=>{
-                        self.cursors.move_up(1, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::ArrowDown=>{
-                        self.cursors.move_down(1, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::ArrowLeft=>{
-                        if ke.modifiers.logo{ // token skipping
-                            self.cursors.move_left_nearest_token(ke.modifiers.shift, &self._token_chunks, text_buffer)
-                        }
-                        else{
-                            self.cursors.move_left(1, ke.modifiers.shift, text_buffer);
-                        }
-                        true
-                    },
-                    KeyCode::ArrowRight=>{
-                        if ke.modifiers.logo{ // token skipping
-                            self.cursors.move_right_nearest_token(ke.modifiers.shift, &self._token_chunks, text_buffer)
-                        }
-                        else{
-                            self.cursors.move_right(1, ke.modifiers.shift, text_buffer);
-                        }
-                        true
-                    },
-                    KeyCode::PageUp=>{
-                        
-                        self.cursors.move_up(self._visible_lines.max(5) - 4, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::PageDown=>{
-                        self.cursors.move_down(self._visible_lines.max(5) - 4, ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::Home=>{
-                        self.cursors.move_home(ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::End=>{
-                        self.cursors.move_end(ke.modifiers.shift, text_buffer);
-                        true
-                    },
-                    KeyCode::Backspace=>{
-                        self.cursors.backspace(text_buffer);
-                        true
-                    },
-                    KeyCode::Delete=>{
-                        self.cursors.delete(text_buffer);
-                        true
-                    },
-                    KeyCode::KeyZ=>{
-                        if ke.modifiers.logo || ke.modifiers.control{
-                            if ke.modifiers.shift{ // redo
-                                text_buffer.redo(true, &mut self.cursors);
-                                true
-                            }
-                            else{ // undo
-                                text_buffer.undo(true, &mut self.cursors);
-                                true
-                            }
-                        }
-                        else{
-                            false
-                        }
-                    },
-                    KeyCode::KeyX=>{ // cut
-                        if ke.modifiers.logo || ke.modifiers.control{ // cut
-                            self.cursors.replace_text("", text_buffer);
-                            true
-                        }
-                        else{
-                            false
-                        }
-                    },
-                    KeyCode::KeyA=>{ // select all
-                        if ke.modifiers.logo || ke.modifiers.control{ // cut
-                            self.cursors.select_all(text_buffer);
-                            // don't scroll!
-                            self.view.redraw_view_area(cx);
-                            false
-                        }
-                        else{
-                            false
-                        }
+                if !self.handle_vim_key(cx, &ke, text_buffer){
+                    if let KeyMapResult::Command(command) = self.keymap.handle_key(&ke){
+                        ret = self.exec_command(cx, command, text_buffer);
                     }
-                    _=>false
-                };
-                if cursor_moved{
-                    self.scroll_last_cursor_visible(cx, text_buffer);
-                    self.view.redraw_view_area(cx);
                 }
+                // the TextCopy a platform sends goes with this key only
+                self._text_copied = false;
             },
             Event::TextInput(te)=>{
+                if let Some(vim) = &mut self.vim{
+                    // outside insert mode typed text is commands, inside it vim records it for the . key
+                    if vim.mode != VimMode::Insert || !te.replace_last && te.input.chars().count() == 1{
+                        for ch in te.input.chars(){
+                            vim.handle_key(VimKey::Char(ch), &mut self.cursors, &self.syntax, text_buffer);
+                        }
+                        self.vim_changed(cx, text_buffer);
+                        return ret
+                    }
+                }
                 if te.replace_last{
                     text_buffer.undo(false, &mut self.cursors);
                 }
-                self.cursors.replace_text(&te.input, text_buffer);
+                let mut chars = te.input.chars();
+                match (chars.next(), chars.next()){
+                    (Some(ch), None) if !te.replace_last=>self.cursors.type_char(ch, &self.syntax, text_buffer),
+                    _=>self.cursors.replace_text(&te.input, text_buffer)
+                }
+                self.scroll_last_cursor_visible(cx, text_buffer);
+                self.view.redraw_view_area(cx);
+            },
+            Event::Paste(pe)=>{
+                if let Some(vim) = &self.vim{ // vim pastes from its registers with p
+                    if vim.mode != VimMode::Insert{
+                        return ret
+                    }
+                }
+                // a copy from as many cursors pastes back one line per cursor
+                let lines:Vec<&str> = pe.text.lines().collect();
+                if self.cursors.set.len() > 1 && lines.len() == self.cursors.set.len(){
+                    self.cursors.replace_lines(&lines, text_buffer);
+                }
+                else{
+                    self.cursors.replace_text(&pe.text, text_buffer);
+                }
                 self.scroll_last_cursor_visible(cx, text_buffer);
                 self.view.redraw_view_area(cx);
             },
             Event::TextCopy(_)=>match event{ // access the original event
                 Event::TextCopy(req)=>{
                     req.response = Some(self.cursors.get_all_as_string(text_buffer));
+                    self._text_copied = true;
                 },
                 _=>()
             },
             _=>()
         };
-        CodeEditorEvent::None
+        ret
    }
 
+    // vim gets the keys it knows first, outside insert mode plain keys never reach the keymap
+    fn handle_vim_key(&mut self, cx:&mut Cx, ke:&KeyEvent, text_buffer:&mut TextBuffer)->bool{
+        let vim = if let Some(vim) = &mut self.vim{vim}else{return false};
+        let handled = if let Some(key) = VimKey::from_key_event(ke){
+            vim.handle_key(key, &mut self.cursors, &self.syntax, text_buffer)
+        }
+        else{
+            false
+        };
+        if handled{
+            self.vim_changed(cx, text_buffer);
+            return true
+        }
+        let m = &ke.modifiers;
+        vim.mode != VimMode::Insert && !m.control && !m.logo && !m.alt && ke.key_code != KeyCode::Escape
+    }
+
+    fn vim_changed(&mut self, cx:&mut Cx, text_buffer:&TextBuffer){
+        if let Some(vim) = &mut self.vim{
+            if let Some(text) = vim._clipboard.take(){
+                cx.copy_to_clipboard(&text);
+            }
+        }
+        self.scroll_last_cursor_visible(cx, text_buffer);
+        self.view.redraw_view_area(cx);
+    }
+
+    // the default keys of the editor and its find bar for the platform
+    pub fn reset_keymap(&mut self, is_mac:bool){
+        self.keymap = EditorCommand::code_editor_keymap(is_mac);
+        self.find_bar.keymap = EditorCommand::find_bar_keymap(is_mac);
+    }
+
+    // applies the user keymap file to the editor and its find bar
+    pub fn load_keymap(&mut self, json:&str)->Result<(), String>{
+        self.keymap.load_json(json, "code_editor", true)?;
+        self.find_bar.keymap.load_json(json, "find_bar", false)
+    }
+
+    // runs a command as if its keys were pressed, commands the editor doesn't know do nothing
+    pub fn exec_command(&mut self, cx:&mut Cx, command:EditorCommand, text_buffer:&mut TextBuffer)->CodeEditorEvent{
+        let page = self._visible_lines.max(5) - 4;
+        let cursor_moved = match command{
+            EditorCommand::CursorUp | EditorCommand::SelectUp=>{
+                self.cursors.move_up(1, command == EditorCommand::SelectUp, text_buffer);
+                true
+            },
+            EditorCommand::CursorDown | EditorCommand::SelectDown=>{
+                self.cursors.move_down(1, command == EditorCommand::SelectDown, text_buffer);
+                true
+            },
+            EditorCommand::CursorLeft | EditorCommand::SelectLeft=>{
+                self.cursors.move_left(1, command == EditorCommand::SelectLeft, text_buffer);
+                true
+            },
+            EditorCommand::CursorRight | EditorCommand::SelectRight=>{
+                self.cursors.move_right(1, command == EditorCommand::SelectRight, text_buffer);
+                true
+            },
+            EditorCommand::WordLeft | EditorCommand::SelectWordLeft=>{ // token skipping
+                self.cursors.move_left_nearest_token(command == EditorCommand::SelectWordLeft, &self._token_chunks, text_buffer);
+                true
+            },
+            EditorCommand::WordRight | EditorCommand::SelectWordRight=>{
+                self.cursors.move_right_nearest_token(command == EditorCommand::SelectWordRight, &self._token_chunks, text_buffer);
+                true
+            },
+            EditorCommand::PageUp | EditorCommand::SelectPageUp=>{
+                self.cursors.move_up(page, command == EditorCommand::SelectPageUp, text_buffer);
+                true
+            },
+            EditorCommand::PageDown | EditorCommand::SelectPageDown=>{
+                self.cursors.move_down(page, command == EditorCommand::SelectPageDown, text_buffer);
+                true
+            },
+            EditorCommand::Home | EditorCommand::SelectHome=>{
+                self.cursors.move_home(command == EditorCommand::SelectHome, text_buffer);
+                true
+            },
+            EditorCommand::End | EditorCommand::SelectEnd=>{
+                self.cursors.move_end(command == EditorCommand::SelectEnd, text_buffer);
+                true
+            },
+            EditorCommand::Backspace=>{
+                self.cursors.backspace(text_buffer);
+                true
+            },
+            EditorCommand::Delete=>{
+                self.cursors.delete(text_buffer);
+                true
+            },
+            EditorCommand::Tab=>{
+                if self.cursors.spans_rows(text_buffer){
+                    self.cursors.indent_rows(false, text_buffer);
+                }
+                else{
+                    self.cursors.insert_tab(text_buffer);
+                }
+                true
+            },
+            EditorCommand::Outdent=>{
+                self.cursors.indent_rows(true, text_buffer);
+                true
+            },
+            EditorCommand::Undo=>{
+                text_buffer.undo(true, &mut self.cursors);
+                true
+            },
+            EditorCommand::Redo=>{
+                text_buffer.redo(true, &mut self.cursors);
+                true
+            },
+            EditorCommand::Cut=>{
+                // the platforms copy for the cut shortcuts with a TextCopy before the KeyDown
+                if !self._text_copied{
+                    cx.copy_to_clipboard(&self.cursors.get_all_as_string(text_buffer));
+                }
+                self.cursors.replace_text("", text_buffer);
+                true
+            },
+            EditorCommand::SelectAll=>{
+                self.cursors.select_all(text_buffer);
+                // don't scroll!
+                self.view.redraw_view_area(cx);
+                false
+            },
+            EditorCommand::Find | EditorCommand::FindReplace=>{
+                self.open_find_bar(cx, command == EditorCommand::FindReplace, text_buffer);
+                false
+            },
+            EditorCommand::CloseFind=>{
+                if self.find_bar.is_open(){
+                    self.find_bar.close(cx);
+                }
+                false
+            },
+            EditorCommand::Fold | EditorCommand::Unfold=>{
+                self.fold_at_cursor(cx, command == EditorCommand::Fold, text_buffer);
+                false
+            },
+            EditorCommand::Save=>{ // our owner knows where the file goes
+                return CodeEditorEvent::Save
+            },
+            EditorCommand::Duplicate=>{
+                self.cursors.duplicate(text_buffer);
+                true
+            },
+            EditorCommand::MoveRowsUp | EditorCommand::MoveRowsDown=>{
+                self.cursors.move_rows(command == EditorCommand::MoveRowsUp, text_buffer);
+                true
+            },
+            EditorCommand::JoinRows=>{
+                self.cursors.join_rows(text_buffer);
+                true
+            },
+            EditorCommand::DeleteRows=>{
+                self.cursors.delete_rows(text_buffer);
+                true
+            },
+            EditorCommand::ToggleComment=>{
+                self.cursors.toggle_comment(&self.syntax, text_buffer);
+                true
+            },
+            EditorCommand::ToggleVimMode=>{
+                if self.vim.take().is_none(){
+                    let vim = Vim::new();
+                    vim.clamp_cursors(&mut self.cursors, text_buffer);
+                    self.vim = Some(vim);
+                }
+                self.view.redraw_view_area(cx);
+                false
+            },
+            _=>false
+        };
+        if cursor_moved{
+            self.scroll_last_cursor_visible(cx, text_buffer);
+            self.view.redraw_view_area(cx);
+        }
+        CodeEditorEvent::None
+    }
+
     pub fn begin_code_editor(&mut self, cx:&mut Cx, text_buffer:&TextBuffer)->bool{
         // pull the bg color from our animation system, uses 'default' value otherwise
         // self.bg.color = self.animator.last_vec4("bg.color");
         // push the 2 vars we added to bg shader
         //self.text.color = self.animator.last_vec4("text.color");
+        if self.find_bar.is_open(){
+            self.find_bar.refresh_matches(text_buffer);
+            if let Some(cursor) = self.cursors.set.get(self.cursors.last_cursor){
+                let (start, end) = cursor.order();
+                self.find_bar.current_match = self.find_bar.find_exact_match(start, end);
+            }
+            self.find_bar.draw_find_bar(cx);
+            cx.turtle_new_line();
+        }
         self.view.begin_view(cx, &Layout{..Default::default()});
         //   return false
         //}
@@ -469,11 +812,13 @@ impl CodeEditor{
         }
         else{
 
-            let bg_inst = self.bg.draw_quad(cx, Rect{x:0.,y:0., w:cx.width_total(false), h:cx.height_total(false)});
+            self._view_size = Vec2{x:cx.width_total(false), y:cx.height_total(false)};
+            let bg_inst = self.bg.draw_quad(cx, Rect{x:0.,y:0., w:self._view_size.x, h:self._view_size.y});
             let bg_area = bg_inst.into_area();
             cx.update_area_refs(self._bg_area, bg_area);
             self._bg_area = bg_area;
-            // makers before text
+            // line highlights and markers before text
+            cx.new_instance_layer(self.line_highlight.shader_id, 0);
             cx.new_instance_layer(self.marker.shader_id, 0);
 
             self._text_inst = Some(self.text.begin_text(cx));
@@ -489,13 +834,39 @@ impl CodeEditor{
             };
 
             self._monospace_size = self.text.get_monospace_size(cx, None);
+            // line numbers right aligned, then room for the fold markers
+            self._fold_marker_width = self._monospace_size.x * 2.;
+            self._line_number_digits = format!("{}", text_buffer.get_line_count()).len();
+            self._gutter_width = self._fold_marker_width + if self.draw_line_numbers{
+                self._monospace_size.x * (self._line_number_digits + 1) as f32
+            }
+            else{
+                0.
+            };
+            self._line_numbers.truncate(0);
+            self._bracket_sels.truncate(0);
             self._line_geometry.truncate(0);
             self._token_chunks.truncate(0);
             self._draw_cursor = DrawCursor::new();
+            // vim draws a block over the char in normal mode
+            if let Some(vim) = &self.vim{
+                if vim.mode == VimMode::Normal{
+                    self._draw_cursor.block_cursor = Some(self._monospace_size.x);
+                }
+            }
             self._first_on_line = true;
             self._visible_lines = 0;
             // prime the next cursor
             self._draw_cursor.set_next(&self.cursors.set);
+            // and the find matches, these use the same selection drawing
+            self._search_cursors.truncate(0);
+            if self.find_bar.is_open(){
+                for (start, end) in &self.find_bar.matches{
+                    self._search_cursors.push(Cursor{head:*end, tail:*start, max:0});
+                }
+            }
+            self._draw_search = DrawCursor::new();
+            self._draw_search.set_next(&self._search_cursors);
             // cursor after text
             cx.new_instance_layer(self.cursor.shader_id, 0);
             
@@ -514,35 +885,51 @@ impl CodeEditor{
         let pos = cx.turtle_origin();
         cx.new_instance_layer(self.cursor.shader_id, 0);
 
-        // draw the cursors    
+        // draw the cursors, a block cursor is see through so the char under it shows
+        let cursor_color = self.cursor.color;
+        if self._draw_cursor.block_cursor.is_some(){
+            self.cursor.color.a = 0.5;
+        }
         for rc in &self._draw_cursor.cursors{
            self.cursor.draw_quad(cx, Rect{x:rc.x - pos.x, y:rc.y - pos.y, w:rc.w, h:rc.h});
         }
+        self.cursor.color = cursor_color;
 
         
         self._text_area = self._text_inst.take().unwrap().inst.into_area();
 
-        // draw selections
-        let sel = &self._draw_cursor.selections;
-        for i in 0..sel.len(){
-            let cur = &sel[i];
-            let mk_inst = self.marker.draw_quad(cx, Rect{x:cur.rc.x - pos.x, y:cur.rc.y - pos.y, w:cur.rc.w, h:cur.rc.h});
-            // do we have a prev?
-            if i > 0 && sel[i-1].index == cur.index{
-                let p_rc = &sel[i-1].rc;
-                mk_inst.push_vec2(cx, Vec2{x:p_rc.x - cur.rc.x, y:p_rc.w}); // prev_x, prev_w
-            }
-            else{
-                mk_inst.push_vec2(cx, Vec2{x:0., y:-1.}); // prev_x, prev_w
-            }
-            // do we have a next
-            if i < sel.len() - 1 && sel[i+1].index == cur.index{
-                let n_rc = &sel[i+1].rc;
-                mk_inst.push_vec2(cx, Vec2{x:n_rc.x - cur.rc.x, y:n_rc.w}); // prev_x, prev_w
-            }
-            else{
-                mk_inst.push_vec2(cx, Vec2{x:0., y:-1.}); // prev_x, prev_w
+        // line numbers go in their own instance so they don't count as text offsets
+        if self._line_numbers.len() > 0{
+            let mut line_number_inst = self.line_number_text.begin_text(cx);
+            let default_color = self.line_number_text.color;
+            for (x, y, row) in &self._line_numbers{
+                let number:Vec<char> = format!("{}", row + 1).chars().collect();
+                let x = x + self._monospace_size.x * (self._line_number_digits - number.len()) as f32;
+                self.line_number_text.color = if self._cursor_rows.binary_search(row).is_ok(){
+                    self.line_number_highlight
+                }
+                else{
+                    default_color
+                };
+                self.line_number_text.add_text(cx, x, *y, 0, &mut line_number_inst, &number, |_, _, _, _| 0.0);
             }
+            self.line_number_text.color = default_color;
+            self.line_number_text.end_text(cx, &line_number_inst);
+        }
+
+        // draw find matches below the selections
+        Self::draw_selections(cx, &mut self.search_marker, &self._draw_search.selections, pos);
+        Self::draw_selections(cx, &mut self.bracket_marker, &self._bracket_sels, pos);
+        Self::draw_selections(cx, &mut self.marker, &self._draw_cursor.selections, pos);
+
+        // select a jump target now that we have line geometry to scroll to
+        if let Some((start_pos, end_pos)) = self._jump_to.take(){
+            let start = text_buffer.text_pos_to_offset(start_pos);
+            let end = text_buffer.text_pos_to_offset(end_pos);
+            self.cursors.select_ranges(&[(start, end)], 0, text_buffer);
+            self.scroll_last_cursor_visible(cx, text_buffer);
+            cx.set_key_focus(self._bg_area);
+            self.view.redraw_view_area(cx);
         }
 
         // do select scrolling
@@ -568,6 +955,8 @@ impl CodeEditor{
             }
         }
 
+        self.draw_vim_status(cx, pos);
+
         self.view.end_view(cx);
 
         // place the IME
@@ -583,6 +972,47 @@ impl CodeEditor{
         }
     }
 
+    // the vim mode in the bottom right corner, it stays put when scrolling
+    fn draw_vim_status(&mut self, cx:&mut Cx, origin:Vec2){
+        let status:Vec<char> = if let Some(vim) = &self.vim{vim.status().chars().collect()}else{return};
+        let pad = 4.;
+        let w = self._monospace_size.x * status.len() as f32 + 2. * pad;
+        let h = self._monospace_size.y;
+        let x = self._view_size.x - w - pad;
+        let y = self._view_size.y - h - pad;
+        cx.new_instance_layer(self.mode_bg.shader_id, 0);
+        self.mode_bg.draw_quad(cx, Rect{x:x, y:y, w:w, h:h});
+        // text always scrolls, so it gets the scroll added back
+        let mut inst = self.mode_text.begin_text_layer(cx);
+        let text_x = origin.x + self._scroll_pos.x + x + pad;
+        let text_y = origin.y + self._scroll_pos.y + y;
+        self.mode_text.add_text(cx, text_x, text_y, 0, &mut inst, &status, |_, _, _, _| 0.0);
+        self.mode_text.end_text(cx, &inst);
+    }
+
+    fn draw_selections(cx:&mut Cx, marker:&mut Quad, sel:&Vec<DrawSel>, pos:Vec2){
+        for i in 0..sel.len(){
+            let cur = &sel[i];
+            let mk_inst = marker.draw_quad(cx, Rect{x:cur.rc.x - pos.x, y:cur.rc.y - pos.y, w:cur.rc.w, h:cur.rc.h});
+            // do we have a prev?
+            if i > 0 && sel[i-1].index == cur.index{
+                let p_rc = &sel[i-1].rc;
+                mk_inst.push_vec2(cx, Vec2{x:p_rc.x - cur.rc.x, y:p_rc.w}); // prev_x, prev_w
+            }
+            else{
+                mk_inst.push_vec2(cx, Vec2{x:0., y:-1.}); // prev_x, prev_w
+            }
+            // do we have a next
+            if i < sel.len() - 1 && sel[i+1].index == cur.index{
+                let n_rc = &sel[i+1].rc;
+                mk_inst.push_vec2(cx, Vec2{x:n_rc.x - cur.rc.x, y:n_rc.w}); // prev_x, prev_w
+            }
+            else{
+                mk_inst.push_vec2(cx, Vec2{x:0., y:-1.}); // prev_x, prev_w
+            }
+        }
+    }
+
     pub fn draw_tab_lines(&mut self, cx:&mut Cx, tabs:usize){
         let walk = cx.get_turtle_walk();
         let tab_width = self._monospace_size.x*4.;
@@ -605,13 +1035,6 @@ impl CodeEditor{
     }
 
     pub fn new_line(&mut self, cx:&mut Cx){
-        // line geometry is used for scrolling look up of cursors
-        self._line_geometry.push(
-            LineGeom{
-                walk:cx.get_rel_turtle_walk(),
-                font_size:self.text.font_size
-            }
-        );
         // add a bit of room to the right
         cx.walk_turtle(
             Bounds::Fix(self._monospace_size.x * 3.), 
@@ -626,6 +1049,11 @@ impl CodeEditor{
            draw_cursor.emit_selection(true);
            draw_cursor.first = true;
         }
+        let draw_search = &mut self._draw_search;
+        if !draw_search.first{
+           draw_search.emit_selection(true);
+           draw_search.first = true;
+        }
     }
 
     pub fn draw_text(&mut self, cx:&mut Cx, chunk:&Vec<char>, end_offset:usize, is_whitespace:bool, color:Color){
@@ -652,38 +1080,23 @@ impl CodeEditor{
                     self._visible_lines += 1;
                 }
 
+                if chunk.len() == 1 && self._bracket_marks.contains(&(end_offset - 2)){
+                    self._bracket_sels.push(DrawSel{index:self._bracket_sels.len(), rc:geom});
+                }
+
                 self.text.color = color;
                 // we need to find the next cursor point we need to do something at
                 let cursors = &self.cursors.set;
                 let last_cursor = self.cursors.last_cursor;
                 let draw_cursor = &mut self._draw_cursor;
+                let search_cursors = &self._search_cursors;
+                let draw_search = &mut self._draw_search;
                 let height = self._monospace_size.y;
 
                 self.text.add_text(cx, geom.x, geom.y, end_offset - chunk.len() - 1, self._text_inst.as_mut().unwrap(), &chunk, |unicode, offset, x, w|{
-                    // check if we need to skip cursors
-                    while offset >= draw_cursor.end{ // jump to next cursor
-                        if offset == draw_cursor.end{ // process the last bit here
-                             draw_cursor.process_geom(last_cursor, offset, x, geom.y, w, height);
-                            draw_cursor.emit_selection(false);
-                        }
-                        if !draw_cursor.set_next(cursors){ // cant go further
-                            return 0.0
-                        }
-                    }
-                    // in current cursor range, update values
-                    if offset >= draw_cursor.start && offset <= draw_cursor.end{
-                        draw_cursor.process_geom(last_cursor, offset, x, geom.y, w, height);
-                        if offset == draw_cursor.end{
-                            draw_cursor.emit_selection(false);
-                        }
-                        if unicode == 10{
-                            return 0.0
-                        }
-                        else if unicode == 32 && offset < draw_cursor.end{
-                            return 2.0
-                        }
-                    }
-                    return 0.0
+                    // find matches never have a last cursor, and don't mark whitespace
+                    draw_search.mark_text(search_cursors, std::usize::MAX, unicode, offset, x, geom.y, w, height);
+                    draw_cursor.mark_text(cursors, last_cursor, unicode, offset, x, geom.y, w, height)
                 });
             }
 
@@ -691,6 +1104,437 @@ impl CodeEditor{
         }
     }
 
+    // draws the cached tokens of the rows in view, the rows above and below it are walked over in one go
+    pub fn draw_tokens<T>(&mut self, cx:&mut Cx, text_buffer:&TextBuffer, token_cache:&TokenCache<T>, colors:&TokenColors)
+    where T: LineTokenizer
+    {
+        self._structure.update(text_buffer, token_cache, &self.syntax);
+        self.update_folds(text_buffer);
+        self.update_bracket_marks();
+        self.update_max_line_len(text_buffer);
+        self._cursor_rows = self.cursors.set.iter().map(|cursor| text_buffer.offset_to_text_pos(cursor.head).row).collect();
+        self._cursor_rows.sort();
+        self._cursor_rows.dedup();
+
+        let line_count = text_buffer.get_line_count();
+        self.update_line_geometry(cx, line_count);
+        let (first_row, last_row) = self.get_visible_rows();
+        self.walk_rows(cx, 0, first_row);
+
+        let mut chunk = Vec::new();
+        let mut offset = text_buffer.text_pos_to_offset(TextPos{row:first_row, col:0});
+        let mut last_tabs = self.get_tabs_above(first_row, text_buffer, token_cache);
+        for row in first_row..last_row{
+            let line = text_buffer.get_line(row);
+            if self.is_row_hidden(row){
+                offset += line.len() + 1;
+                continue;
+            }
+            let tokens = token_cache.get_tokens(row);
+            let is_last_line = row == line_count - 1;
+            let indent = Self::get_indent(tokens);
+
+            let walk = cx.get_turtle_walk();
+            let line_width = self._monospace_size.x * (line.len() as f32);
+            if !cx.visible_in_turtle(
+                Rect{x:walk.x, y:walk.y, w:self._gutter_width + line_width + self._monospace_size.x, h:self._monospace_size.y},
+                self._visibility_margin,
+                self._scroll_pos
+            ){
+                // scrolled out of view sideways, keep the turtle walking so the scroll extents stay right
+                cx.walk_turtle(Bounds::Fix(self._gutter_width + line_width), Bounds::Fix(self._monospace_size.y), Margin::zero(), None);
+                if line.len() > 0{
+                    last_tabs = indent >> 2;
+                }
+            }
+            else{
+                self.draw_gutter(cx, row);
+                if indent > 0{
+                    self.draw_tab_lines(cx, indent >> 2);
+                }
+                let mut col = 0;
+                let mut rest = line;
+                for token in tokens{
+                    chunk.extend(rest.slice(0..token.len).iter());
+                    rest = rest.slice(token.len..);
+                    col += token.len;
+                    self.draw_text(cx, &chunk, offset + col + 1, token.token_type == TokenType::Whitespace, colors.get(token.token_type));
+                    chunk.truncate(0);
+                }
+                if line.len() > 0{
+                    last_tabs = indent >> 2;
+                }
+                if !is_last_line{
+                    // empty and whitespace only lines continue the indent guides
+                    if indent == line.len(){
+                        self.draw_tab_lines(cx, last_tabs);
+                    }
+                    chunk.push('\n');
+                    self.draw_text(cx, &chunk, offset + line.len() + 2, true, colors.whitespace);
+                    chunk.truncate(0);
+                }
+            }
+            if !is_last_line{
+                self.new_line(cx);
+            }
+            offset += line.len() + 1;
+        }
+        self.walk_rows(cx, last_row, line_count);
+    }
+
+    fn get_indent(tokens:&[Token])->usize{
+        if let Some(token) = tokens.first(){
+            if token.token_type == TokenType::Whitespace{
+                return token.len
+            }
+        }
+        0
+    }
+
+    // the indent guides an empty first row in view continues, from the last row with text above it
+    fn get_tabs_above<T>(&self, row:usize, text_buffer:&TextBuffer, token_cache:&TokenCache<T>)->usize
+    where T: LineTokenizer
+    {
+        for row in (0..row).rev(){
+            if !self.is_row_hidden(row) && !text_buffer.get_line(row).is_empty(){
+                return Self::get_indent(token_cache.get_tokens(row)) >> 2
+            }
+        }
+        0
+    }
+
+    // every row is a line high, so their geometry is laid out without walking them.
+    // folded rows share the geometry of the row they fold into
+    fn update_line_geometry(&mut self, cx:&Cx, line_count:usize){
+        let start = cx.get_rel_turtle_walk();
+        let mut y = start.y;
+        self._line_geometry.truncate(0);
+        for row in 0..line_count{
+            if row > 0 && !self.is_row_hidden(row){
+                y += self._monospace_size.y;
+            }
+            self._line_geometry.push(LineGeom{
+                walk:Vec2{x:start.x, y:y},
+                font_size:self.text.font_size
+            });
+        }
+    }
+
+    // the rows first..last that overlap the view, a row past the view ends the range
+    fn get_visible_rows(&self)->(usize, usize){
+        let top = self._scroll_pos.y - self._visibility_margin.t;
+        let bottom = self._scroll_pos.y + self._view_size.y + self._visibility_margin.b;
+        let line_height = self._monospace_size.y;
+        let find_row = |above:&dyn Fn(&LineGeom)->bool|{
+            match self._line_geometry.binary_search_by(|geom| if above(geom){Ordering::Less}else{Ordering::Greater}){
+                Ok(row)=>row,
+                Err(row)=>row
+            }
+        };
+        let first_row = find_row(&|geom| geom.walk.y + line_height <= top);
+        let last_row = find_row(&|geom| geom.walk.y < bottom);
+        (first_row, last_row.max(first_row))
+    }
+
+    // walks the turtle over rows out of view in one go, as wide as the longest line
+    fn walk_rows(&mut self, cx:&mut Cx, from_row:usize, to_row:usize){
+        if from_row >= to_row{
+            return
+        }
+        let geometry = &self._line_geometry;
+        let end_y = if to_row < geometry.len(){
+            geometry[to_row].walk.y
+        }
+        else{
+            geometry[to_row - 1].walk.y + self._monospace_size.y
+        };
+        // new_line adds 3 chars of room to the right
+        let width = self._gutter_width + self._monospace_size.x * (self._max_line_len + 3) as f32;
+        cx.walk_turtle(Bounds::Fix(width), Bounds::Fix(end_y - geometry[from_row].walk.y), Margin::zero(), None);
+        // the last row doesn't end in a new line
+        if to_row < geometry.len(){
+            cx.turtle_new_line();
+        }
+    }
+
+    // keeps the longest line up to date from the line edits, only rescanning the
+    // whole buffer when the longest line got shorter
+    fn update_max_line_len(&mut self, text_buffer:&TextBuffer){
+        let version = text_buffer.get_line_edit_version();
+        if self._max_line_edit_version == Some(version){
+            return
+        }
+        let line_edits = self._max_line_edit_version.and_then(|version| text_buffer.get_line_edits_since(version));
+        self._max_line_edit_version = Some(version);
+        let line_count = text_buffer.get_line_count();
+        if let Some(line_edits) = line_edits{
+            // the rows the edits wrote, moved along by the edits after them
+            let mut edited:Vec<(usize, usize)> = Vec::new();
+            let mut max_row_edited = false;
+            for line_edit in line_edits{
+                let old_end = line_edit.row + line_edit.old_rows;
+                let new_end = line_edit.row + line_edit.new_rows;
+                let move_row = |row:usize| if row >= old_end{row + line_edit.new_rows - line_edit.old_rows}else{row};
+                for (start, end) in &mut edited{
+                    if *end <= line_edit.row || *start >= old_end{
+                        *start = move_row(*start);
+                        *end = move_row(*end);
+                    }
+                    else{ // overlaps, so it grows to cover the edit
+                        *start = (*start).min(line_edit.row);
+                        *end = move_row((*end).max(old_end));
+                    }
+                }
+                if self._max_line_row >= line_edit.row && self._max_line_row < old_end{
+                    max_row_edited = true;
+                    self._max_line_row = line_edit.row;
+                }
+                else{
+                    self._max_line_row = move_row(self._max_line_row);
+                }
+                edited.push((line_edit.row, new_end));
+            }
+            let old_max = self._max_line_len;
+            if max_row_edited{
+                self._max_line_len = 0;
+            }
+            for (start, end) in edited{
+                for row in start..end.min(line_count){
+                    let len = text_buffer.get_line(row).len();
+                    if len > self._max_line_len{
+                        self._max_line_len = len;
+                        self._max_line_row = row;
+                    }
+                }
+            }
+            if !max_row_edited || self._max_line_len >= old_max{
+                return
+            }
+        }
+        self._max_line_len = 0;
+        self._max_line_row = 0;
+        for row in 0..line_count{
+            let len = text_buffer.get_line(row).len();
+            if len > self._max_line_len{
+                self._max_line_len = len;
+                self._max_line_row = row;
+            }
+        }
+    }
+
+    // the gutter left of the text, with the line number and a fold marker on rows that start a block
+    fn draw_gutter(&mut self, cx:&mut Cx, row:usize){
+        let walk = cx.get_turtle_walk();
+        if self._cursor_rows.binary_search(&row).is_ok(){ // current line highlight over the full width
+            let origin = cx.turtle_origin();
+            self.line_highlight.draw_quad(cx, Rect{
+                x:0.,
+                y:walk.y - origin.y,
+                w:cx.width_total(false) + self._scroll_pos.x,
+                h:self._monospace_size.y
+            });
+        }
+        if self.draw_line_numbers{
+            self._line_numbers.push((walk.x, walk.y, row));
+            cx.walk_turtle(Bounds::Fix(self._gutter_width - self._fold_marker_width), Bounds::Fix(self._monospace_size.y), Margin::zero(), None);
+        }
+        if self._structure.get_fold(row).is_some(){
+            let is_folded = self._folded_rows.contains(&row);
+            let inst = self.fold_marker.draw_quad_walk(cx, Bounds::Fix(self._fold_marker_width), Bounds::Fix(self._monospace_size.y), Margin::zero());
+            inst.push_float(cx, if is_folded{1.}else{0.});
+        }
+        else{
+            cx.walk_turtle(Bounds::Fix(self._fold_marker_width), Bounds::Fix(self._monospace_size.y), Margin::zero(), None);
+        }
+    }
+
+    fn is_row_hidden(&self, row:usize)->bool{
+        row < self._hidden_rows.len() && self._hidden_rows[row]
+    }
+
+    // keeps the folds on their rows as lines are inserted and removed, and works out which rows they hide
+    fn update_folds(&mut self, text_buffer:&TextBuffer){
+        if let Some(line_edits) = text_buffer.get_line_edits_since(self._folds_line_edit_version){
+            for line_edit in line_edits{
+                self._folded_rows = self._folded_rows.iter().filter_map(|row|{
+                    if *row <= line_edit.row{
+                        Some(*row)
+                    }
+                    else if *row >= line_edit.row + line_edit.old_rows{
+                        Some(*row - line_edit.old_rows + line_edit.new_rows)
+                    }
+                    else{ // the line went away
+                        None
+                    }
+                }).collect();
+            }
+        }
+        else{
+            self._folded_rows.truncate(0);
+        }
+        self._folds_line_edit_version = text_buffer.get_line_edit_version();
+
+        // unfold blocks that went away, or that the cursor moved into
+        let cursor_row = text_buffer.offset_to_text_pos(self.cursors.set[self.cursors.last_cursor].head).row;
+        let structure = &self._structure;
+        self._folded_rows.retain(|row|{
+            if let Some(close_row) = structure.get_fold(*row){
+                cursor_row <= *row || cursor_row >= close_row
+            }
+            else{
+                false
+            }
+        });
+
+        self._hidden_rows.truncate(0);
+        if self._folded_rows.len() > 0{
+            self._hidden_rows.resize(text_buffer.get_line_count(), false);
+            for row in &self._folded_rows{
+                let close_row = self._structure.get_fold(*row).unwrap();
+                for hidden_row in row + 1..close_row{
+                    self._hidden_rows[hidden_row] = true;
+                }
+            }
+        }
+    }
+
+    pub fn toggle_fold(&mut self, cx:&mut Cx, row:usize, text_buffer:&TextBuffer){
+        if let Some(index) = self._folded_rows.iter().position(|folded_row| *folded_row == row){
+            self._folded_rows.remove(index);
+        }
+        else if let Some(close_row) = self._structure.get_fold(row){
+            self._folded_rows.push(row);
+            // a cursor in the hidden rows would unfold it again, so it moves onto the fold
+            let cursor_row = text_buffer.offset_to_text_pos(self.cursors.set[self.cursors.last_cursor].head).row;
+            if cursor_row > row && cursor_row < close_row{
+                let offset = text_buffer.text_pos_to_offset(TextPos{row:row, col:text_buffer.get_line(row).len()});
+                self.cursors.clear_and_set_last_cursor_head_and_tail(offset, text_buffer);
+            }
+        }
+        self.view.redraw_view_area(cx);
+    }
+
+    // folds the innermost block around the cursor, or unfolds the one on its row
+    fn fold_at_cursor(&mut self, cx:&mut Cx, fold:bool, text_buffer:&TextBuffer){
+        let cursor_row = text_buffer.offset_to_text_pos(self.cursors.set[self.cursors.last_cursor].head).row;
+        let is_folded = self._folded_rows.contains(&cursor_row);
+        if fold && !is_folded{
+            if let Some((open_row, _)) = self._structure.get_enclosing_fold(cursor_row){
+                self.toggle_fold(cx, open_row, text_buffer);
+            }
+        }
+        else if !fold && is_folded{
+            self.toggle_fold(cx, cursor_row, text_buffer);
+        }
+    }
+
+    // a bracket right of an empty cursor, or else left of it, gets marked with its match
+    fn update_bracket_marks(&mut self){
+        self._bracket_marks.truncate(0);
+        for cursor in &self.cursors.set{
+            if cursor.has_selection(){
+                continue;
+            }
+            let mut offsets = vec![cursor.head];
+            if cursor.head > 0{
+                offsets.push(cursor.head - 1);
+            }
+            for offset in offsets{
+                if let Some(other) = self._structure.get_matching_bracket(offset){
+                    self._bracket_marks.push(offset);
+                    self._bracket_marks.push(other);
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn has_key_focus(&self, cx:&Cx)->bool{
+        self._bg_area == cx.key_focus
+    }
+
+    // selects the range once the buffer is loaded and drawn, used to open search results
+    pub fn jump_to(&mut self, cx:&mut Cx, start:TextPos, end:TextPos){
+        let structure = &self._structure;
+        self._folded_rows.retain(|row| start.row <= *row || start.row >= structure.get_fold(*row).unwrap_or(0));
+        self._jump_to = Some((start, end));
+        self.view.redraw_view_area(cx);
+    }
+
+    fn open_find_bar(&mut self, cx:&mut Cx, show_replace:bool, text_buffer:&TextBuffer){
+        // a selection within one line becomes the query
+        let (start, end) = self.cursors.set[self.cursors.last_cursor].order();
+        let mut query = String::new();
+        text_buffer.get_range_as_string(start, end - start, &mut query);
+        let initial_query = if query.len() > 0 && query.find('\n').is_none(){Some(query)}else{None};
+        self._search_start = start;
+        self.find_bar.open(cx, show_replace, initial_query);
+    }
+
+    fn select_find_match(&mut self, cx:&mut Cx, index:usize, text_buffer:&TextBuffer){
+        let range = self.find_bar.matches[index];
+        self.cursors.select_ranges(&[range], 0, text_buffer);
+        self.find_bar.current_match = Some(index);
+        self.scroll_last_cursor_visible(cx, text_buffer);
+    }
+
+    fn handle_find_bar_event(&mut self, cx:&mut Cx, find_bar_event:FindBarEvent, text_buffer:&mut TextBuffer){
+        self.find_bar.refresh_matches(text_buffer);
+        let (start, end) = self.cursors.set[self.cursors.last_cursor].order();
+        match find_bar_event{
+            FindBarEvent::Changed=>{ // incremental search from where the find started
+                if let Some(index) = self.find_bar.find_match(self._search_start, false){
+                    self.select_find_match(cx, index, text_buffer);
+                }
+            },
+            FindBarEvent::Next=>{
+                if let Some(index) = self.find_bar.find_match(end, false){
+                    self.select_find_match(cx, index, text_buffer);
+                }
+            },
+            FindBarEvent::Prev=>{
+                if let Some(index) = self.find_bar.find_match(start, true){
+                    self.select_find_match(cx, index, text_buffer);
+                }
+            },
+            FindBarEvent::SelectAll=>{ // every match becomes a cursor, and typing goes to the editor
+                if self.find_bar.matches.len() > 0{
+                    let last = self.find_bar.find_match(start, false).unwrap_or(0);
+                    let matches = self.find_bar.matches.clone();
+                    self.cursors.select_ranges(&matches, last, text_buffer);
+                    cx.set_key_focus(self._bg_area);
+                }
+            },
+            FindBarEvent::Replace=>{
+                // replace the match we have selected, then move on to the next one
+                if let Some(index) = self.find_bar.find_exact_match(start, end){
+                    let replacement = self.find_bar.get_replacement(index);
+                    self.cursors.replace_ranges(&[(start, end)], &[replacement], text_buffer);
+                    self.find_bar.refresh_matches(text_buffer);
+                }
+                let (_, end) = self.cursors.set[self.cursors.last_cursor].order();
+                if let Some(index) = self.find_bar.find_match(end, false){
+                    self.select_find_match(cx, index, text_buffer);
+                }
+            },
+            FindBarEvent::ReplaceAll=>{
+                if self.find_bar.matches.len() > 0{
+                    let replacements:Vec<String> = (0..self.find_bar.matches.len()).map(
+                        |index| self.find_bar.get_replacement(index)
+                    ).collect();
+                    let matches = self.find_bar.matches.clone();
+                    self.cursors.replace_ranges(&matches, &replacements, text_buffer);
+                    self.find_bar.refresh_matches(text_buffer);
+                }
+            },
+            FindBarEvent::Close=>{
+                cx.set_key_focus(self._bg_area);
+            },
+            FindBarEvent::None=>()
+        }
+        self.view.redraw_view_area(cx);
+    }
+
     fn scroll_last_cursor_visible(&mut self, cx:&mut Cx, text_buffer:&TextBuffer){
         // so we have to compute (approximately) the rect of our cursor
         if self.cursors.last_cursor >= self.cursors.set.len(){
@@ -703,7 +1547,7 @@ impl CodeEditor{
             let geom = &self._line_geometry[pos.row];
             let mono_size = self.text.get_monospace_size(cx, Some(geom.font_size));
             let rect = Rect{
-                x:(pos.col as f32) * mono_size.x,
+                x:self._gutter_width + (pos.col as f32) * mono_size.x,
                 y:geom.walk.y - mono_size.y * 1.,
                 w:mono_size.x * 4.,
                 h:mono_size.y * 3.
@@ -721,12 +1565,12 @@ impl CodeEditor{
             //let geom = &self._line_geometry[pos.row];
             mono_size = self.text.get_monospace_size(cx, Some(geom.font_size));
             if rel.y < geom.walk.y || rel.y >= geom.walk.y && rel.y <= geom.walk.y + mono_size.y{ // its on the right line
-                let col = (rel.x.max(0.) / mono_size.x) as usize; // do a dumb calc
+                let col = ((rel.x - self._gutter_width).max(0.) / mono_size.x) as usize; // do a dumb calc
                 return TextPos{row:row, col:col};
             }
         }
         // otherwise the file is too short, lets use the last line
-        TextPos{row:self._line_geometry.len() - 1, col: (rel.x.max(0.) / mono_size.x) as usize}
+        TextPos{row:self._line_geometry.len() - 1, col: ((rel.x - self._gutter_width).max(0.) / mono_size.x) as usize}
     }
 
     fn get_nearest_token_chunk_range(&self, offset:usize)->(usize, usize){
@@ -770,7 +1614,9 @@ pub struct DrawCursor{
     pub empty:bool,
     pub cursors:Vec<Rect>,
     pub last_cursor:Option<usize>,
-    pub selections:Vec<DrawSel>
+    pub selections:Vec<DrawSel>,
+    // the width of a block over the char under the cursor, instead of a line in front of it
+    pub block_cursor:Option<f32>
 }
 
 impl DrawCursor{
@@ -787,7 +1633,8 @@ impl DrawCursor{
             last_w:0.0,
             cursors:Vec::new(),
             selections:Vec::new(),
-            last_cursor:None
+            last_cursor:None,
+            block_cursor:None
         }
     }
 
@@ -814,7 +1661,7 @@ impl DrawCursor{
         self.cursors.push(Rect{
             x:x,
             y:y,
-            w:1.5,
+            w:self.block_cursor.unwrap_or(1.5),
             h:h
         })
     }
@@ -836,6 +1683,34 @@ impl DrawCursor{
         }
     }
 
+    // called for every drawn char, returns the whitespace marker for the text shader
+    pub fn mark_text(&mut self, cursors:&Vec<Cursor>, last_cursor:usize, unicode:usize, offset:usize, x:f32, y:f32, w:f32, h:f32)->f32{
+        // check if we need to skip cursors
+        while offset >= self.end{ // jump to next cursor
+            if offset == self.end{ // process the last bit here
+                self.process_geom(last_cursor, offset, x, y, w, h);
+                self.emit_selection(false);
+            }
+            if !self.set_next(cursors){ // cant go further
+                return 0.0
+            }
+        }
+        // in current cursor range, update values
+        if offset >= self.start && offset <= self.end{
+            self.process_geom(last_cursor, offset, x, y, w, h);
+            if offset == self.end{
+                self.emit_selection(false);
+            }
+            if unicode == 10{
+                return 0.0
+            }
+            else if unicode == 32 && offset < self.end{
+                return 2.0
+            }
+        }
+        return 0.0
+    }
+
     pub fn process_geom(&mut self, last_cursor:usize, offset:usize, x:f32, y:f32, w:f32, h:f32){
         if offset == self.head{ // emit a cursor
             if self.next_index - 1 == last_cursor{
@@ -0,0 +1,312 @@
+use crate::textbuffer::*;
+use crate::textlines::*;
+use crate::tokenizer::*;
+use crate::languagemode::*;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OutlineItem{
+    pub kind:String,
+    pub name:String,
+    pub row:usize,
+    pub col:usize,
+    // how many outline items this one is nested in
+    pub depth:usize
+}
+
+// The structure of a file as far as the token stream tells us,
+// matched brackets, the blocks that can fold and the items for the outline.
+// Like the TokenCache it keeps what it found per line, and edits only rescan
+// from the first touched line until the state converges with the cached one.
+#[derive(Clone, Default)]
+pub struct CodeStructure{
+    // (open, close) offsets, sorted on open
+    pub bracket_pairs:Vec<(usize, usize)>,
+    // (open_row, close_row) of multi line blocks, sorted on open_row
+    pub folds:Vec<(usize, usize)>,
+    pub outline:Vec<OutlineItem>,
+    pub lines:Vec<Option<StructureLine>>,
+    // the same pairs as (close, open), sorted on close
+    pub _pairs_by_close:Vec<(usize, usize)>,
+    pub _line_edit_version:Option<u64>,
+    // how many lines the last update had to scan
+    pub _rescanned_lines:usize
+}
+
+// An open bracket carried from line to line. Its row is counted back from the
+// current line, so lines inserted above it don't keep the rescan from converging.
+#[derive(Clone, PartialEq)]
+pub struct OpenBracket{
+    open:char,
+    rows_back:usize,
+    col:usize,
+    // this brace is the body of an outline item
+    is_item_body:bool
+}
+
+// what a line leaves open for the lines after it
+#[derive(Clone, PartialEq, Default)]
+pub struct StructureState{
+    stack:Vec<OpenBracket>,
+    // an outline keyword waiting for its name
+    pending_kind:Option<String>,
+    // the bracket depth the last outline item waits at for its { body or ;
+    pending_body:Option<usize>
+}
+
+#[derive(Clone)]
+pub struct StructureLine{
+    pub start_state:StructureState,
+    pub end_state:StructureState,
+    // the tokens this was scanned from, see TokenCache::get_lex_id
+    pub lex_id:u64,
+    // (open rows back, open col, close col) of the pairs closing on this line
+    pub closes:Vec<(usize, usize, usize)>,
+    // the items starting on this line, their row is set when collected
+    pub outline:Vec<OutlineItem>
+}
+
+impl CodeStructure{
+
+    // rescans the lines that changed since the last update
+    pub fn update<T>(&mut self, text_buffer:&TextBuffer, token_cache:&TokenCache<T>, syntax:&LanguageSyntax)
+    where T: LineTokenizer
+    {
+        let version = text_buffer.get_line_edit_version();
+        if self._line_edit_version == Some(version){
+            return
+        }
+        let line_count = text_buffer.get_line_count();
+        let mut first_dirty = None;
+        if self.lines.len() > 0{
+            if let Some(line_edits) = self._line_edit_version.and_then(|version| text_buffer.get_line_edits_since(version)){
+                for line_edit in line_edits{
+                    self.lines.splice(line_edit.row..line_edit.row + line_edit.old_rows, (0..line_edit.new_rows).map(|_| None));
+                    first_dirty = Some(first_dirty.map_or(line_edit.row, |row:usize| row.min(line_edit.row)));
+                }
+            }
+            else{
+                self.lines.truncate(0);
+            }
+        }
+        if self.lines.len() != line_count{
+            self.lines = (0..line_count).map(|_| None).collect();
+            first_dirty = Some(0);
+        }
+        self._line_edit_version = Some(version);
+        self._rescanned_lines = 0;
+
+        let mut row = if let Some(row) = first_dirty{row}else{return};
+        let mut state = if row > 0{
+            self.lines[row - 1].as_ref().unwrap().end_state.clone()
+        }
+        else{
+            StructureState::default()
+        };
+        // lines the token cache lexed again after the edit need a rescan too
+        let is_dirty = |lines:&Vec<Option<StructureLine>>, row:usize|{
+            if let Some(line) = &lines[row]{line.lex_id != token_cache.get_lex_id(row)}else{true}
+        };
+        while row < line_count{
+            if !is_dirty(&self.lines, row) && self.lines[row].as_ref().unwrap().start_state == state{
+                // converged, skip ahead to the next dirty line
+                if let Some(skip) = (row..line_count).position(|row| is_dirty(&self.lines, row)){
+                    row += skip;
+                    state = self.lines[row - 1].as_ref().unwrap().end_state.clone();
+                }
+                else{
+                    break;
+                }
+            }
+            let start_state = state.clone();
+            let mut line = StructureLine{
+                start_state:start_state,
+                end_state:StructureState::default(),
+                lex_id:token_cache.get_lex_id(row),
+                closes:Vec::new(),
+                outline:Vec::new()
+            };
+            Self::scan_line(&mut state, text_buffer.get_line(row), token_cache.get_tokens(row), syntax, &mut line);
+            line.end_state = state.clone();
+            self.lines[row] = Some(line);
+            self._rescanned_lines += 1;
+            row += 1;
+        }
+        self.collect(text_buffer);
+    }
+
+    // the pairs, folds and outline of the whole file from what the lines found
+    fn collect(&mut self, text_buffer:&TextBuffer){
+        self.bracket_pairs.truncate(0);
+        self.folds.truncate(0);
+        self.outline.truncate(0);
+        for (row, line) in self.lines.iter().enumerate(){
+            let line = if let Some(line) = line{line}else{continue};
+            if line.closes.len() > 0{
+                let offset = text_buffer.text_pos_to_offset(TextPos{row:row, col:0});
+                for (rows_back, open_col, close_col) in &line.closes{
+                    let open_row = row - rows_back;
+                    let open_offset = text_buffer.text_pos_to_offset(TextPos{row:open_row, col:0}) + open_col;
+                    self.bracket_pairs.push((open_offset, offset + close_col));
+                    if row > open_row + 1{
+                        self.folds.push((open_row, row));
+                    }
+                }
+            }
+            for item in &line.outline{
+                self.outline.push(OutlineItem{row:row, ..item.clone()});
+            }
+        }
+        self._pairs_by_close = self.bracket_pairs.iter().map(|(open, close)| (*close, *open)).collect();
+        self.bracket_pairs.sort();
+        // pairs closing on the same row as others are pushed inner first, keep the outermost per row
+        self.folds.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        self.folds.dedup_by_key(|fold| fold.0);
+    }
+
+    fn scan_line(state:&mut StructureState, line:Line, tokens:&[Token], syntax:&LanguageSyntax, out:&mut StructureLine){
+        // items only start a line, after modifiers like pub(crate) or unsafe
+        let mut at_item_start = true;
+        let mut col = 0;
+        // sliced along with the tokens, so a long line isn't searched from its start every time
+        let mut rest = line;
+        for token in tokens{
+            let chunk = rest.slice(0..token.len);
+            rest = rest.slice(token.len..);
+            let token_col = col;
+            col += token.len;
+            if token.token_type == TokenType::Whitespace || token.token_type == TokenType::Comment || token.token_type == TokenType::DocComment{
+                continue;
+            }
+
+            if let Some(kind) = state.pending_kind.take(){
+                match token.token_type{
+                    TokenType::Identifier | TokenType::TypeName | TokenType::Call=>{
+                        Self::push_outline_item(kind, chunk.iter().collect(), token_col, state, out);
+                        continue;
+                    },
+                    _=>() // like the fn in fn(usize)->bool, not an item
+                }
+            }
+
+            if token.token_type == TokenType::Paren && token.len == 1{
+                let c = chunk[0];
+                if let Some((open, _)) = syntax.get_bracket_pair(c){
+                    if c == open{
+                        let mut is_item_body = false;
+                        if let Some(depth) = state.pending_body{
+                            if c == '{' && depth == state.stack.len(){
+                                is_item_body = true;
+                                state.pending_body = None;
+                            }
+                        }
+                        state.stack.push(OpenBracket{open:open, rows_back:0, col:token_col, is_item_body:is_item_body});
+                    }
+                    else if let Some(pos) = state.stack.iter().rposition(|b| b.open == open){
+                        // brackets left open inside this pair are unmatched
+                        state.stack.truncate(pos + 1);
+                        let bracket = state.stack.pop().unwrap();
+                        out.closes.push((bracket.rows_back, bracket.col, token_col));
+                        if let Some(depth) = state.pending_body{
+                            if depth > state.stack.len(){
+                                state.pending_body = None;
+                            }
+                        }
+                    }
+                }
+                if at_item_start && (c == '(' || c == ')'){ // pub(crate)
+                    continue;
+                }
+            }
+            else if token.token_type == TokenType::Delimiter && chunk.len() == 1 && chunk[0] == ';'{
+                if let Some(depth) = state.pending_body{
+                    if depth == state.stack.len(){
+                        state.pending_body = None;
+                    }
+                }
+            }
+            else if token.token_type == TokenType::Keyword && at_item_start{
+                let word:String = chunk.iter().collect();
+                if syntax.outline_keywords.iter().any(|kw| *kw == word){
+                    if word == "impl"{ // impls are named by what follows up to the body
+                        let name = Self::get_impl_name(rest);
+                        Self::push_outline_item(word, name, token_col, state, out);
+                    }
+                    else{
+                        state.pending_kind = Some(word);
+                    }
+                    at_item_start = false;
+                }
+                continue;
+            }
+            else if token.token_type == TokenType::String && at_item_start{ // extern "C"
+                continue;
+            }
+            at_item_start = false;
+        }
+        // the next line counts one more row back to the open brackets
+        for bracket in &mut state.stack{
+            bracket.rows_back += 1;
+        }
+    }
+
+    fn push_outline_item(kind:String, name:String, col:usize, state:&mut StructureState, out:&mut StructureLine){
+        state.pending_body = Some(state.stack.len());
+        out.outline.push(OutlineItem{
+            kind:kind,
+            name:name,
+            row:0,
+            col:col,
+            depth:state.stack.iter().filter(|b| b.is_item_body).count()
+        });
+    }
+
+    // impl<T> Trait for Type<T> where ... { becomes Trait for Type<T>
+    fn get_impl_name(rest:Line)->String{
+        let mut chars = rest.iter().skip_while(|c| c.is_whitespace()).peekable();
+        let mut name = String::new();
+        if chars.peek() == Some(&&'<'){
+            let mut depth = 0;
+            while let Some(c) = chars.next(){
+                if *c == '<'{depth += 1}
+                else if *c == '>'{
+                    depth -= 1;
+                    if depth == 0{break}
+                }
+            }
+        }
+        for c in chars{
+            if *c == '{'{
+                break;
+            }
+            name.push(*c);
+        }
+        if let Some(pos) = name.find(" where"){
+            name.truncate(pos);
+        }
+        name.trim().to_string()
+    }
+
+    // the offset of the bracket matching the one at offset
+    pub fn get_matching_bracket(&self, offset:usize)->Option<usize>{
+        if let Ok(index) = self.bracket_pairs.binary_search_by_key(&offset, |(open, _)| *open){
+            return Some(self.bracket_pairs[index].1)
+        }
+        if let Ok(index) = self._pairs_by_close.binary_search_by_key(&offset, |(close, _)| *close){
+            return Some(self._pairs_by_close[index].1)
+        }
+        None
+    }
+
+    // the close row of the block that folds at row
+    pub fn get_fold(&self, row:usize)->Option<usize>{
+        if let Ok(index) = self.folds.binary_search_by_key(&row, |(open_row, _)| *open_row){
+            return Some(self.folds[index].1)
+        }
+        None
+    }
+
+    // the innermost fold with row in it, or starting on it
+    pub fn get_enclosing_fold(&self, row:usize)->Option<(usize, usize)>{
+        self.folds.iter().filter(|(open_row, close_row)| *open_row <= row && row < *close_row).last().cloned()
+    }
+}
@@ -0,0 +1,224 @@
+use widgets::*;
+
+// everything the editor and its find bar do from the keyboard, typed text is not a command
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditorCommand{
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    SelectUp,
+    SelectDown,
+    SelectLeft,
+    SelectRight,
+    WordLeft,
+    WordRight,
+    SelectWordLeft,
+    SelectWordRight,
+    PageUp,
+    PageDown,
+    SelectPageUp,
+    SelectPageDown,
+    Home,
+    End,
+    SelectHome,
+    SelectEnd,
+    Backspace,
+    Delete,
+    Tab,
+    Outdent,
+    Undo,
+    Redo,
+    Cut,
+    SelectAll,
+    Find,
+    FindReplace,
+    CloseFind,
+    FindNext,
+    FindPrev,
+    SelectAllMatches,
+    ReplaceAll,
+    NextField,
+    ToggleCaseSensitive,
+    ToggleWholeWord,
+    ToggleRegex,
+    SearchInFiles,
+    Fold,
+    Unfold,
+    Save,
+    Duplicate,
+    MoveRowsUp,
+    MoveRowsDown,
+    JoinRows,
+    DeleteRows,
+    ToggleComment,
+    ToggleVimMode
+}
+
+const EDITOR_COMMANDS:[(EditorCommand, &'static str);50] = [
+    (EditorCommand::CursorUp, "cursor_up"),
+    (EditorCommand::CursorDown, "cursor_down"),
+    (EditorCommand::CursorLeft, "cursor_left"),
+    (EditorCommand::CursorRight, "cursor_right"),
+    (EditorCommand::SelectUp, "select_up"),
+    (EditorCommand::SelectDown, "select_down"),
+    (EditorCommand::SelectLeft, "select_left"),
+    (EditorCommand::SelectRight, "select_right"),
+    (EditorCommand::WordLeft, "word_left"),
+    (EditorCommand::WordRight, "word_right"),
+    (EditorCommand::SelectWordLeft, "select_word_left"),
+    (EditorCommand::SelectWordRight, "select_word_right"),
+    (EditorCommand::PageUp, "page_up"),
+    (EditorCommand::PageDown, "page_down"),
+    (EditorCommand::SelectPageUp, "select_page_up"),
+    (EditorCommand::SelectPageDown, "select_page_down"),
+    (EditorCommand::Home, "home"),
+    (EditorCommand::End, "end"),
+    (EditorCommand::SelectHome, "select_home"),
+    (EditorCommand::SelectEnd, "select_end"),
+    (EditorCommand::Backspace, "backspace"),
+    (EditorCommand::Delete, "delete"),
+    (EditorCommand::Tab, "tab"),
+    (EditorCommand::Outdent, "outdent"),
+    (EditorCommand::Undo, "undo"),
+    (EditorCommand::Redo, "redo"),
+    (EditorCommand::Cut, "cut"),
+    (EditorCommand::SelectAll, "select_all"),
+    (EditorCommand::Find, "find"),
+    (EditorCommand::FindReplace, "find_replace"),
+    (EditorCommand::CloseFind, "close_find"),
+    (EditorCommand::FindNext, "find_next"),
+    (EditorCommand::FindPrev, "find_prev"),
+    (EditorCommand::SelectAllMatches, "select_all_matches"),
+    (EditorCommand::ReplaceAll, "replace_all"),
+    (EditorCommand::NextField, "next_field"),
+    (EditorCommand::ToggleCaseSensitive, "toggle_case_sensitive"),
+    (EditorCommand::ToggleWholeWord, "toggle_whole_word"),
+    (EditorCommand::ToggleRegex, "toggle_regex"),
+    (EditorCommand::SearchInFiles, "search_in_files"),
+    (EditorCommand::Fold, "fold"),
+    (EditorCommand::Unfold, "unfold"),
+    (EditorCommand::Save, "save"),
+    (EditorCommand::Duplicate, "duplicate"),
+    (EditorCommand::MoveRowsUp, "move_rows_up"),
+    (EditorCommand::MoveRowsDown, "move_rows_down"),
+    (EditorCommand::JoinRows, "join_rows"),
+    (EditorCommand::DeleteRows, "delete_rows"),
+    (EditorCommand::ToggleComment, "toggle_comment"),
+    (EditorCommand::ToggleVimMode, "toggle_vim_mode")
+];
+
+impl KeyCommand for EditorCommand{
+    fn name(&self)->&'static str{
+        EDITOR_COMMANDS.iter().find(|(command, _)| command == self).unwrap().1
+    }
+
+    fn from_name(name:&str)->Option<Self>{
+        EDITOR_COMMANDS.iter().find(|(_, command_name)| *command_name == name).map(|(command, _)| *command)
+    }
+}
+
+impl EditorCommand{
+    // the keys of the code editor. primary is cmd on macOS and ctrl elsewhere,
+    // the rest follows what text fields do on each platform
+    pub fn code_editor_keymap(is_mac:bool)->KeyMap<EditorCommand>{
+        let mut keymap = KeyMap::new(is_mac);
+        let mut bindings = vec![
+            ("up", EditorCommand::CursorUp),
+            ("down", EditorCommand::CursorDown),
+            ("left", EditorCommand::CursorLeft),
+            ("right", EditorCommand::CursorRight),
+            ("shift+up", EditorCommand::SelectUp),
+            ("shift+down", EditorCommand::SelectDown),
+            ("shift+left", EditorCommand::SelectLeft),
+            ("shift+right", EditorCommand::SelectRight),
+            ("pageup", EditorCommand::PageUp),
+            ("pagedown", EditorCommand::PageDown),
+            ("shift+pageup", EditorCommand::SelectPageUp),
+            ("shift+pagedown", EditorCommand::SelectPageDown),
+            ("home", EditorCommand::Home),
+            ("end", EditorCommand::End),
+            ("shift+home", EditorCommand::SelectHome),
+            ("shift+end", EditorCommand::SelectEnd),
+            ("backspace", EditorCommand::Backspace),
+            ("shift+backspace", EditorCommand::Backspace),
+            ("delete", EditorCommand::Delete),
+            ("tab", EditorCommand::Tab),
+            ("shift+tab", EditorCommand::Outdent),
+            ("primary+z", EditorCommand::Undo),
+            ("primary+shift+z", EditorCommand::Redo),
+            ("primary+x", EditorCommand::Cut),
+            ("primary+a", EditorCommand::SelectAll),
+            ("primary+f", EditorCommand::Find),
+            ("primary+h", EditorCommand::FindReplace),
+            ("escape", EditorCommand::CloseFind),
+            ("primary+alt+lbracket", EditorCommand::Fold),
+            ("primary+alt+rbracket", EditorCommand::Unfold),
+            ("primary+s", EditorCommand::Save),
+            ("primary+shift+d", EditorCommand::Duplicate),
+            ("alt+up", EditorCommand::MoveRowsUp),
+            ("alt+down", EditorCommand::MoveRowsDown),
+            ("primary+j", EditorCommand::JoinRows),
+            ("primary+shift+k", EditorCommand::DeleteRows),
+            ("primary+slash", EditorCommand::ToggleComment),
+            ("primary+alt+v", EditorCommand::ToggleVimMode)
+        ];
+        if is_mac{
+            bindings.extend_from_slice(&[
+                ("alt+left", EditorCommand::WordLeft),
+                ("alt+right", EditorCommand::WordRight),
+                ("alt+shift+left", EditorCommand::SelectWordLeft),
+                ("alt+shift+right", EditorCommand::SelectWordRight),
+                ("cmd+left", EditorCommand::Home),
+                ("cmd+right", EditorCommand::End),
+                ("cmd+shift+left", EditorCommand::SelectHome),
+                ("cmd+shift+right", EditorCommand::SelectEnd)
+            ]);
+        }
+        else{
+            bindings.extend_from_slice(&[
+                ("ctrl+left", EditorCommand::WordLeft),
+                ("ctrl+right", EditorCommand::WordRight),
+                ("ctrl+shift+left", EditorCommand::SelectWordLeft),
+                ("ctrl+shift+right", EditorCommand::SelectWordRight),
+                ("ctrl+y", EditorCommand::Redo),
+                ("shift+delete", EditorCommand::Cut)
+            ]);
+        }
+        for (keys, command) in bindings{
+            keymap.bind(keys, command).unwrap();
+        }
+        keymap
+    }
+
+    // the keys of the find bar, its text field takes the typed text
+    pub fn find_bar_keymap(is_mac:bool)->KeyMap<EditorCommand>{
+        let mut keymap = KeyMap::new(is_mac);
+        for (keys, command) in &[
+            ("escape", EditorCommand::CloseFind),
+            ("return", EditorCommand::FindNext),
+            ("numpadenter", EditorCommand::FindNext),
+            ("shift+return", EditorCommand::FindPrev),
+            ("alt+return", EditorCommand::SelectAllMatches),
+            ("primary+shift+l", EditorCommand::SelectAllMatches),
+            ("primary+return", EditorCommand::ReplaceAll),
+            ("primary+alt+return", EditorCommand::ReplaceAll),
+            ("tab", EditorCommand::NextField),
+            ("backspace", EditorCommand::Backspace),
+            ("alt+c", EditorCommand::ToggleCaseSensitive),
+            ("alt+w", EditorCommand::ToggleWholeWord),
+            ("alt+r", EditorCommand::ToggleRegex),
+            ("primary+h", EditorCommand::FindReplace)
+        ]{
+            keymap.bind(keys, *command).unwrap();
+        }
+        keymap
+    }
+
+    // the keys that work wherever the key focus is
+    pub fn app_keymap(is_mac:bool)->KeyMap<EditorCommand>{
+        let mut keymap = KeyMap::new(is_mac);
+        keymap.bind("primary+shift+f", EditorCommand::SearchInFiles).unwrap();
+        keymap
+    }
+}
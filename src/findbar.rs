@@ -0,0 +1,468 @@
+use widgets::*;
+use regex::{Regex, RegexBuilder};
+use crate::textbuffer::*;
+use crate::textlines::*;
+use crate::editorcommand::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FindField{
+    Find,
+    Replace
+}
+
+#[derive(Clone)]
+pub struct FindBar{
+    pub view:View<NoScrollBar>,
+    pub bg:Quad,
+    pub bg_layout:Layout,
+    pub text:Text,
+    pub query:String,
+    pub replace:String,
+    pub case_sensitive:bool,
+    pub whole_word:bool,
+    pub use_regex:bool,
+    pub show_replace:bool,
+    // the project search panel keeps its bar open and shows its own counts
+    pub closeable:bool,
+    pub show_match_count:bool,
+    pub keymap:KeyMap<EditorCommand>,
+    // (start, end) char offsets of every match, in order
+    pub matches:Vec<(usize, usize)>,
+    pub current_match:Option<usize>,
+    // in regex mode the replace field expanded against the captures of every match
+    pub _replacements:Vec<String>,
+
+    pub _is_open:bool,
+    pub _field:FindField,
+    pub _regex:Option<Regex>,
+    pub _regex_error:bool,
+    pub _hit_state:HitState,
+    pub _bg_area:Area,
+    pub _focus_on_draw:bool,
+    pub _last_input_len:usize,
+    // what the buffer looked like when we last matched, so edits refresh the matches
+    pub _matched_undo_id:u64,
+    pub _matched_char_count:usize,
+    pub _matches_dirty:bool
+}
+
+impl ElementLife for FindBar{
+    fn construct(&mut self, _cx:&mut Cx){}
+    fn destruct(&mut self, _cx:&mut Cx){}
+}
+
+impl Style for FindBar{
+    fn style(cx:&mut Cx)->Self{
+        Self{
+            view:View{
+                ..Style::style(cx)
+            },
+            bg:Quad{
+                color:color256(45,45,45),
+                ..Style::style(cx)
+            },
+            bg_layout:Layout{
+                width:Bounds::Fill,
+                height:Bounds::Compute,
+                margin:Margin::all(0.),
+                padding:Padding{l:8.0,t:6.0,r:8.0,b:6.0},
+                ..Default::default()
+            },
+            text:Text{
+                font_id:cx.load_font(&cx.font("mono_font")),
+                font_size:11.0,
+                wrapping:Wrapping::Line,
+                ..Style::style(cx)
+            },
+            query:String::new(),
+            replace:String::new(),
+            case_sensitive:false,
+            whole_word:false,
+            use_regex:false,
+            show_replace:false,
+            closeable:true,
+            show_match_count:true,
+            keymap:EditorCommand::find_bar_keymap(cx.is_mac_platform()),
+            matches:Vec::new(),
+            current_match:None,
+            _replacements:Vec::new(),
+            _is_open:false,
+            _field:FindField::Find,
+            _regex:None,
+            _regex_error:false,
+            _hit_state:HitState{..Default::default()},
+            _bg_area:Area::Empty,
+            _focus_on_draw:false,
+            _last_input_len:0,
+            _matched_undo_id:0,
+            _matched_char_count:0,
+            _matches_dirty:true
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum FindBarEvent{
+    None,
+    Changed,
+    Next,
+    Prev,
+    SelectAll,
+    Replace,
+    ReplaceAll,
+    Close
+}
+
+impl FindBar{
+
+    pub fn is_open(&self)->bool{
+        self._is_open
+    }
+
+    pub fn open(&mut self, cx:&mut Cx, show_replace:bool, initial_query:Option<String>){
+        if let Some(query) = initial_query{
+            self.query = query;
+            self.compile_query();
+        }
+        self._is_open = true;
+        self.show_replace = self.show_replace || show_replace;
+        self._field = if show_replace{FindField::Replace}else{FindField::Find};
+        self._last_input_len = 0;
+        self._focus_on_draw = true;
+        self._matches_dirty = true;
+        // the bar changes the editor layout
+        cx.redraw_area(Area::All);
+    }
+
+    pub fn close(&mut self, cx:&mut Cx){
+        self._is_open = false;
+        self.show_replace = false;
+        self.matches.truncate(0);
+        self.current_match = None;
+        cx.redraw_area(Area::All);
+    }
+
+    pub fn compile_query(&mut self){
+        self._matches_dirty = true;
+        self._regex_error = false;
+        if self.query.len() == 0{
+            self._regex = None;
+            return
+        }
+        // plain searches go through the same engine with the query escaped
+        let pattern = if self.use_regex{self.query.clone()}else{regex::escape(&self.query)};
+        let pattern = if self.whole_word{format!("\\b(?:{})\\b", pattern)}else{pattern};
+        match RegexBuilder::new(&pattern).case_insensitive(!self.case_sensitive).build(){
+            Ok(regex)=>{
+                self._regex = Some(regex);
+            },
+            Err(_)=>{
+                self._regex = None;
+                self._regex_error = true;
+            }
+        }
+    }
+
+    // only rescans the buffer if the query or the text changed since last time
+    pub fn refresh_matches(&mut self, text_buffer:&TextBuffer){
+        if !self._matches_dirty
+            && self._matched_undo_id == text_buffer.get_undo_id()
+            && self._matched_char_count == text_buffer.get_char_count(){
+            return
+        }
+        self._matches_dirty = false;
+        self._matched_undo_id = text_buffer.get_undo_id();
+        self._matched_char_count = text_buffer.get_char_count();
+        self.matches.truncate(0);
+        self._replacements.truncate(0);
+        self.current_match = None;
+        // matches don't span lines, that keeps char offset conversion per line
+        let mut line_matches = Vec::new();
+        let mut replacements = Vec::new();
+        let mut line_offset = 0;
+        for row in 0..text_buffer.get_line_count(){
+            let chars = text_buffer.get_line(row);
+            line_matches.truncate(0);
+            if self.use_regex{
+                self.find_in_line_expand(chars, &mut line_matches, Some(&mut replacements));
+            }
+            else{
+                self.find_in_line(chars, &mut line_matches);
+            }
+            for (start_col, end_col) in &line_matches{
+                self.matches.push((line_offset + start_col, line_offset + end_col));
+            }
+            line_offset += chars.len() + 1;
+        }
+        self._replacements = replacements;
+    }
+
+    // pushes the (start, end) columns of all matches in a line
+    pub fn find_in_line(&self, chars:Line, out:&mut Vec<(usize, usize)>){
+        self.find_in_line_expand(chars, out, None)
+    }
+
+    // same, and when given replacements it expands $1 style references with the captures of each match
+    fn find_in_line_expand(&self, chars:Line, out:&mut Vec<(usize, usize)>, mut replacements:Option<&mut Vec<String>>){
+        if let Some(regex) = &self._regex{
+            let line:String = chars.iter().collect();
+            let mut last_byte = 0;
+            let mut last_char = 0;
+            for caps in regex.captures_iter(&line){
+                let m = caps.get(0).unwrap();
+                if m.start() == m.end(){ // skip empty matches like ^ or a*
+                    continue;
+                }
+                let start_char = last_char + line[last_byte..m.start()].chars().count();
+                let end_char = start_char + line[m.start()..m.end()].chars().count();
+                out.push((start_char, end_char));
+                last_byte = m.end();
+                last_char = end_char;
+                if let Some(replacements) = &mut replacements{
+                    let mut replacement = String::new();
+                    caps.expand(&self.replace, &mut replacement);
+                    replacements.push(replacement);
+                }
+            }
+        }
+    }
+
+    // index of the first match at or after offset, or the last one before it when going back
+    pub fn find_match(&self, offset:usize, reverse:bool)->Option<usize>{
+        if self.matches.len() == 0{
+            return None
+        }
+        if reverse{
+            for (index, (_start, end)) in self.matches.iter().enumerate().rev(){
+                if *end <= offset{
+                    return Some(index)
+                }
+            }
+            Some(self.matches.len() - 1)
+        }
+        else{
+            for (index, (start, _end)) in self.matches.iter().enumerate(){
+                if *start >= offset{
+                    return Some(index)
+                }
+            }
+            Some(0)
+        }
+    }
+
+    pub fn find_exact_match(&self, start:usize, end:usize)->Option<usize>{
+        if let Ok(index) = self.matches.binary_search(&(start, end)){
+            Some(index)
+        }
+        else{
+            None
+        }
+    }
+
+    pub fn get_replacement(&self, index:usize)->String{
+        if !self.use_regex{
+            return self.replace.clone()
+        }
+        self._replacements[index].clone()
+    }
+
+    fn get_field_mut(&mut self)->&mut String{
+        match self._field{
+            FindField::Find=>&mut self.query,
+            FindField::Replace=>&mut self.replace
+        }
+    }
+
+    fn field_changed(&mut self, cx:&mut Cx)->FindBarEvent{
+        self.view.redraw_view_area(cx);
+        if self._field == FindField::Find{
+            self.compile_query();
+            FindBarEvent::Changed
+        }
+        else{
+            // the replacements are expanded while matching
+            if self.use_regex{
+                self._matches_dirty = true;
+            }
+            FindBarEvent::None
+        }
+    }
+
+    pub fn handle_find_bar(&mut self, cx:&mut Cx, event:&mut Event)->FindBarEvent{
+        if !self._is_open{
+            return FindBarEvent::None
+        }
+        match event.hits(cx, self._bg_area, &mut self._hit_state){
+            Event::FingerDown(_fe)=>{
+                cx.set_key_focus(self._bg_area);
+            },
+            Event::FingerHover(_fe)=>{
+                cx.set_hover_mouse_cursor(MouseCursor::Text);
+            },
+            Event::KeyDown(ke)=>{
+                let command = match self.keymap.handle_key(&ke){
+                    KeyMapResult::Command(command)=>command,
+                    _=>return FindBarEvent::None
+                };
+                match command{
+                    EditorCommand::CloseFind=>{
+                        if self.closeable{
+                            self.close(cx);
+                        }
+                        return FindBarEvent::Close
+                    },
+                    EditorCommand::FindNext | EditorCommand::FindPrev=>{
+                        if self._field == FindField::Replace{
+                            return FindBarEvent::Replace
+                        }
+                        if command == EditorCommand::FindPrev{
+                            return FindBarEvent::Prev
+                        }
+                        return FindBarEvent::Next
+                    },
+                    EditorCommand::SelectAllMatches=>{
+                        return FindBarEvent::SelectAll
+                    },
+                    EditorCommand::ReplaceAll=>{
+                        if self.show_replace{
+                            return FindBarEvent::ReplaceAll
+                        }
+                        return FindBarEvent::Next
+                    },
+                    EditorCommand::NextField=>{
+                        if self.show_replace{
+                            self._field = if self._field == FindField::Find{FindField::Replace}else{FindField::Find};
+                            self._last_input_len = 0;
+                            self.view.redraw_view_area(cx);
+                        }
+                    },
+                    EditorCommand::Backspace=>{
+                        self._last_input_len = 0;
+                        if self.get_field_mut().pop().is_some(){
+                            return self.field_changed(cx)
+                        }
+                    },
+                    EditorCommand::ToggleCaseSensitive | EditorCommand::ToggleWholeWord | EditorCommand::ToggleRegex=>{
+                        match command{
+                            EditorCommand::ToggleCaseSensitive=>self.case_sensitive = !self.case_sensitive,
+                            EditorCommand::ToggleWholeWord=>self.whole_word = !self.whole_word,
+                            _=>self.use_regex = !self.use_regex
+                        }
+                        self.compile_query();
+                        self.view.redraw_view_area(cx);
+                        return FindBarEvent::Changed
+                    },
+                    EditorCommand::FindReplace=>{
+                        self.show_replace = true;
+                        self._field = FindField::Replace;
+                        self._last_input_len = 0;
+                        cx.redraw_area(Area::All);
+                    },
+                    _=>()
+                }
+            },
+            Event::TextInput(te)=>{
+                // control chars like return and tab come in as keys, not as text
+                let input:String = te.input.chars().filter(|c| *c != '\n' && *c != '\r' && *c != '\t').collect();
+                if te.replace_last{
+                    let last_input_len = self._last_input_len;
+                    let field = self.get_field_mut();
+                    for _ in 0..last_input_len{
+                        field.pop();
+                    }
+                }
+                self._last_input_len = input.chars().count();
+                if input.len() > 0 || te.replace_last{
+                    self.get_field_mut().push_str(&input);
+                    return self.field_changed(cx)
+                }
+            },
+            Event::Paste(pe)=>{
+                let input:String = pe.text.chars().filter(|c| *c != '\n' && *c != '\r' && *c != '\t').collect();
+                self._last_input_len = 0;
+                if input.len() > 0{
+                    self.get_field_mut().push_str(&input);
+                    return self.field_changed(cx)
+                }
+            },
+            _=>()
+        }
+        FindBarEvent::None
+    }
+
+    fn draw_field(&mut self, cx:&mut Cx, label:&str, value:&str, active:bool, focus:bool){
+        self.text.color = color("#888");
+        self.text.draw_text(cx, label);
+        self.text.color = if active{color("#e")}else{color("#a")};
+        if active && focus{
+            self.text.draw_text(cx, &format!("{}|", value));
+        }
+        else{
+            self.text.draw_text(cx, value);
+        }
+    }
+
+    fn draw_toggle(&mut self, cx:&mut Cx, label:&str, on:bool){
+        self.text.color = if on{color("#e")}else{color("#5")};
+        self.text.draw_text(cx, label);
+    }
+
+    pub fn draw_find_bar(&mut self, cx:&mut Cx){
+        if !self._is_open{
+            return
+        }
+        self.view.begin_view(cx, &Layout{
+            width:Bounds::Fill,
+            height:Bounds::Compute,
+            ..Default::default()
+        });
+
+        let bg_inst = self.bg.begin_quad(cx, &self.bg_layout);
+        let focus = self._bg_area == cx.key_focus || self._focus_on_draw;
+
+        let query = self.query.clone();
+        self.draw_field(cx, "Find:    ", &query, self._field == FindField::Find, focus);
+        self.text.draw_text(cx, "   ");
+        self.draw_toggle(cx, "Aa ", self.case_sensitive);
+        self.draw_toggle(cx, "W ", self.whole_word);
+        self.draw_toggle(cx, ".* ", self.use_regex);
+
+        self.text.color = color("#888");
+        if self._regex_error{
+            self.text.color = color("#c55");
+            self.text.draw_text(cx, "  invalid regex");
+        }
+        else if self.query.len() > 0 && self.show_match_count{
+            if let Some(current_match) = self.current_match{
+                self.text.draw_text(cx, &format!("  {} of {}", current_match + 1, self.matches.len()));
+            }
+            else if self.matches.len() > 0{
+                self.text.draw_text(cx, &format!("  {} matches", self.matches.len()));
+            }
+            else{
+                self.text.draw_text(cx, "  no results");
+            }
+        }
+
+        if self.show_replace{
+            cx.turtle_new_line();
+            let replace = self.replace.clone();
+            self.draw_field(cx, "Replace: ", &replace, self._field == FindField::Replace, focus);
+        }
+
+        let bg_area = self.bg.end_quad(cx, &bg_inst);
+        cx.update_area_refs(self._bg_area, bg_area);
+        self._bg_area = bg_area;
+
+        if self._focus_on_draw{
+            self._focus_on_draw = false;
+            cx.set_key_focus(self._bg_area);
+        }
+        if self._bg_area == cx.key_focus{
+            let rect = self._bg_area.get_rect_scrolled(cx);
+            cx.show_text_ime(rect.x, rect.y);
+        }
+
+        self.view.end_view(cx);
+    }
+}
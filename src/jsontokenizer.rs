@@ -0,0 +1,90 @@
+use crate::tokenizer::*;
+use crate::textlines::*;
+use crate::languagemode::*;
+
+#[derive(Clone, Default)]
+pub struct JsonTokenizer{
+}
+
+impl LineTokenizer for JsonTokenizer{
+    // nothing in json spans lines
+    type State = ();
+
+    fn tokenize_line(&self, _state:&mut (), line:Line, tokens:&mut Vec<Token>){
+        let mut chunk = Vec::new();
+        let mut state = TokenizerState::new(line);
+
+        loop{
+            let token_type;
+            state.advance_with_cur();
+
+            match state.cur{
+                '\0'=>{ // end of line
+                    break;
+                },
+                ' ' | '\t'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c == ' ' || c == '\t');
+                    token_type = TokenType::Whitespace;
+                },
+                '"'=>{
+                    chunk.push(state.cur);
+                    state.eat_string_tail(&mut chunk, '"');
+                    // a string followed by a : is an object key
+                    let mut rest = state.clone();
+                    rest.eat_while(&mut Vec::new(), |c| c == ' ' || c == '\t');
+                    if rest.next == ':'{
+                        token_type = TokenType::Identifier;
+                    }
+                    else{
+                        token_type = TokenType::String;
+                    }
+                },
+                '-' | '0'..='9'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c >= '0' && c <= '9' || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-');
+                    token_type = TokenType::Number;
+                },
+                '{' | '}' | '[' | ']'=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Paren;
+                },
+                ':' | ','=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Delimiter;
+                },
+                'a'..='z'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c >= 'a' && c <= 'z');
+                    if chunk == ['t','r','u','e'] || chunk == ['f','a','l','s','e'] || chunk == ['n','u','l','l']{
+                        token_type = TokenType::Keyword;
+                    }
+                    else{
+                        token_type = TokenType::Identifier;
+                    }
+                },
+                _=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Identifier;
+                }
+            }
+            tokens.push(Token{
+                len:chunk.len(),
+                token_type:token_type
+            });
+            chunk.truncate(0);
+        }
+    }
+}
+
+impl LanguageMode for JsonTokenizer{
+    fn get_syntax(&self)->LanguageSyntax{
+        LanguageSyntax{
+            name:"JSON",
+            bracket_pairs:&[('[', ']'), ('{', '}')],
+            indent_pairs:&[('[', ']'), ('{', '}')],
+            auto_close_pairs:&[('[', ']'), ('{', '}'), ('"', '"')],
+            ..Default::default()
+        }
+    }
+}
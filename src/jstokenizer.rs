@@ -0,0 +1,266 @@
+use crate::tokenizer::*;
+use crate::textlines::*;
+use crate::languagemode::*;
+
+#[derive(Clone, Default)]
+pub struct JsTokenizer{
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum JsTokenizerState{
+    Code,
+    BlockComment,
+    // `template ${strings}` can span lines
+    TemplateString
+}
+
+impl Default for JsTokenizerState{
+    fn default()->Self{
+        JsTokenizerState::Code
+    }
+}
+
+impl LineTokenizer for JsTokenizer{
+    type State = JsTokenizerState;
+
+    fn tokenize_line(&self, js_state:&mut JsTokenizerState, line:Line, tokens:&mut Vec<Token>){
+        let mut chunk = Vec::new();
+        let mut state = TokenizerState::new(line);
+        // a / starts a regex where a value is expected, and divides after one
+        let mut regex_allowed = true;
+
+        if *js_state != JsTokenizerState::Code{
+            let (token_type, is_closed) = if *js_state == JsTokenizerState::BlockComment{
+                (TokenType::Comment, state.eat_until(&mut chunk, "*/"))
+            }
+            else{
+                (TokenType::String, state.eat_string_tail(&mut chunk, '`'))
+            };
+            if is_closed{
+                *js_state = JsTokenizerState::Code;
+                regex_allowed = token_type == TokenType::Comment;
+            }
+            if chunk.len() > 0{
+                tokens.push(Token{
+                    len:chunk.len(),
+                    token_type:token_type
+                });
+                chunk.truncate(0);
+            }
+        }
+
+        loop{
+            let token_type;
+            state.advance_with_cur();
+
+            match state.cur{
+                '\0'=>{ // end of line
+                    break;
+                },
+                ' ' | '\t'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c == ' ' || c == '\t');
+                    token_type = TokenType::Whitespace;
+                },
+                '/' if state.next == '/'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |_| true);
+                    token_type = TokenType::Comment;
+                },
+                '/' if state.next == '*'=>{
+                    chunk.push(state.cur);
+                    chunk.push(state.next);
+                    state.advance();
+                    if !state.eat_until(&mut chunk, "*/"){
+                        *js_state = JsTokenizerState::BlockComment;
+                    }
+                    token_type = TokenType::Comment;
+                },
+                '/'=>{
+                    if regex_allowed && Self::parse_js_regex_tail(&mut state, &mut chunk){
+                        token_type = TokenType::String;
+                    }
+                    else{
+                        chunk.push(state.cur);
+                        state.eat_while(&mut chunk, |c| c == '=');
+                        token_type = TokenType::Operator;
+                    }
+                },
+                '"' | '\''=>{
+                    let quote = state.cur;
+                    chunk.push(quote);
+                    state.eat_string_tail(&mut chunk, quote);
+                    token_type = TokenType::String;
+                },
+                '`'=>{
+                    chunk.push(state.cur);
+                    if !state.eat_string_tail(&mut chunk, '`'){
+                        *js_state = JsTokenizerState::TemplateString;
+                    }
+                    token_type = TokenType::String;
+                },
+                '.' if state.next_is_digit()=>{
+                    chunk.push(state.cur);
+                    Self::parse_js_number_tail(&mut state, &mut chunk);
+                    token_type = TokenType::Number;
+                },
+                '0'..='9'=>{
+                    chunk.push(state.cur);
+                    Self::parse_js_number_tail(&mut state, &mut chunk);
+                    token_type = TokenType::Number;
+                },
+                '(' | ')' | '[' | ']' | '{' | '}'=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Paren;
+                },
+                ';' | ','=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Delimiter;
+                },
+                'a'..='z' | 'A'..='Z' | '_' | '$'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+                    let word:String = chunk.iter().collect();
+                    token_type = if Self::is_js_flow_keyword(&word){
+                        TokenType::Flow
+                    }
+                    else if Self::is_js_keyword(&word){
+                        TokenType::Keyword
+                    }
+                    else if state.next == '('{
+                        TokenType::Call
+                    }
+                    else if chunk[0] >= 'A' && chunk[0] <= 'Z'{
+                        TokenType::TypeName
+                    }
+                    else{
+                        TokenType::Identifier
+                    };
+                },
+                _=>{
+                    chunk.push(state.cur);
+                    if Self::is_js_operator(state.cur){
+                        state.eat_while(&mut chunk, |c| Self::is_js_operator(c) && c != '/' && c != '.');
+                        token_type = TokenType::Operator;
+                    }
+                    else{
+                        token_type = TokenType::Identifier;
+                    }
+                }
+            }
+            regex_allowed = match token_type{
+                TokenType::Whitespace | TokenType::Comment=>regex_allowed,
+                TokenType::Operator | TokenType::Delimiter | TokenType::Flow=>true,
+                TokenType::Keyword=>chunk != ['t','h','i','s'] && chunk != ['s','u','p','e','r'],
+                TokenType::Paren=>chunk[0] == '(' || chunk[0] == '[' || chunk[0] == '{',
+                _=>false
+            };
+            tokens.push(Token{
+                len:chunk.len(),
+                token_type:token_type
+            });
+            chunk.truncate(0);
+        }
+    }
+}
+
+impl JsTokenizer{
+    // only takes the regex when it closes on this line, otherwise the / is left alone
+    fn parse_js_regex_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->bool{
+        let mut regex_state = state.clone();
+        let mut regex = vec!['/'];
+        let mut in_class = false;
+        loop{
+            let c = regex_state.next;
+            if c == '\0'{
+                return false
+            }
+            regex.push(c);
+            regex_state.advance();
+            if c == '\\'{
+                if regex_state.next == '\0'{
+                    return false
+                }
+                regex.push(regex_state.next);
+                regex_state.advance();
+            }
+            else if c == '['{
+                in_class = true;
+            }
+            else if c == ']'{
+                in_class = false;
+            }
+            else if c == '/' && !in_class{
+                break;
+            }
+        }
+        // flags
+        regex_state.eat_while(&mut regex, |c| c.is_ascii_alphabetic());
+        *state = regex_state;
+        chunk.extend(regex);
+        true
+    }
+
+    fn parse_js_number_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>){
+        if chunk[0] == '0' && (state.next == 'x' || state.next == 'X' || state.next == 'b' || state.next == 'B' || state.next == 'o' || state.next == 'O'){
+            chunk.push(state.next);
+            state.advance();
+            state.eat_while(chunk, |c| c.is_ascii_hexdigit() || c == '_');
+        }
+        else{
+            state.eat_while(chunk, |c| c.is_ascii_digit() || c == '.' || c == '_');
+            let after_e = state.peek_next();
+            if (state.next == 'e' || state.next == 'E') && (after_e.is_ascii_digit() || after_e == '+' || after_e == '-'){
+                chunk.push(state.next);
+                state.advance();
+                chunk.push(state.next);
+                state.advance();
+                state.eat_while(chunk, |c| c.is_ascii_digit());
+            }
+        }
+        // bigints
+        if state.next == 'n'{
+            chunk.push(state.next);
+            state.advance();
+        }
+    }
+
+    fn is_js_operator(c:char)->bool{
+        match c{
+            '+' | '-' | '*' | '/' | '%' | '=' | '<' | '>' | '!' | '&' | '|' | '^' | '~' | '?' | ':' | '.'=>true,
+            _=>false
+        }
+    }
+
+    fn is_js_flow_keyword(word:&str)->bool{
+        match word{
+            "if" | "else" | "for" | "while" | "do" | "return" | "break" | "continue" | "switch" |
+            "case" | "default" | "try" | "catch" | "finally" | "throw" | "yield" | "await"=>true,
+            _=>false
+        }
+    }
+
+    fn is_js_keyword(word:&str)->bool{
+        match word{
+            "var" | "let" | "const" | "function" | "class" | "extends" | "new" | "delete" | "typeof" |
+            "instanceof" | "in" | "of" | "this" | "super" | "null" | "undefined" | "true" | "false" |
+            "import" | "export" | "from" | "async" | "static" | "get" | "set" | "void" | "debugger" |
+            "with"=>true,
+            _=>false
+        }
+    }
+}
+
+impl LanguageMode for JsTokenizer{
+    fn get_syntax(&self)->LanguageSyntax{
+        LanguageSyntax{
+            name:"JavaScript",
+            line_comment:Some("//"),
+            block_comment:Some(("/*", "*/")),
+            bracket_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            indent_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            auto_close_pairs:&[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\''), ('`', '`')],
+            outline_keywords:&["function", "class"]
+        }
+    }
+}
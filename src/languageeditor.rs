@@ -0,0 +1,87 @@
+use widgets::*;
+use crate::textbuffer::*;
+use crate::codeeditor::*;
+use crate::tokenizer::*;
+use crate::languagemode::*;
+use crate::rusttokenizer::*;
+use crate::jsontokenizer::*;
+use crate::tomltokenizer::*;
+use crate::shadertokenizer::*;
+use crate::jstokenizer::*;
+
+pub type RustEditor = LanguageEditor<RustTokenizer>;
+pub type JsonEditor = LanguageEditor<JsonTokenizer>;
+pub type TomlEditor = LanguageEditor<TomlTokenizer>;
+pub type ShaderEditor = LanguageEditor<ShaderTokenizer>;
+pub type JsEditor = LanguageEditor<JsTokenizer>;
+pub type PlainEditor = LanguageEditor<PlainTokenizer>;
+
+// a CodeEditor highlighted by a language mode
+#[derive(Clone)]
+pub struct LanguageEditor<M>
+where M: LanguageMode
+{
+    pub path:String,
+    pub code_editor:CodeEditor,
+    pub colors:TokenColors,
+    pub _token_cache:TokenCache<M>
+}
+
+impl<M> ElementLife for LanguageEditor<M>
+where M: LanguageMode
+{
+    fn construct(&mut self, _cx:&mut Cx){}
+    fn destruct(&mut self, _cx:&mut Cx){}
+}
+
+impl<M> Style for LanguageEditor<M>
+where M: LanguageMode
+{
+    fn style(cx:&mut Cx)->Self{
+        let mode = M::default();
+        let language_editor = Self{
+            path:"".to_string(),
+            code_editor:CodeEditor{
+                syntax:mode.get_syntax(),
+                ..Style::style(cx)
+            },
+            // syntax highlighting colors
+            colors:TokenColors{
+                whitespace:color256(110,110,110),
+                keyword:color256(91,155,211),
+                flow_keyword:color256(196,133,190),
+                identifier:color256(212,212,212),
+                operator:color256(212,212,212),
+                function:color256(220,220,174),
+                type_name:color256(86,201,177),
+                number:color256(182,206,170),
+                comment:color256(99,141,84),
+                doc_comment:color256(138,171,122),
+                paren:color256(212,212,212),
+                string:color256(204,145,123),
+                delim:color256(212,212,212)
+            },
+            _token_cache:TokenCache::new(mode)
+        };
+        //tab.animator.default = tab.anim_default(cx);
+        language_editor
+    }
+}
+
+impl<M> LanguageEditor<M>
+where M: LanguageMode
+{
+    pub fn handle_language_editor(&mut self, cx:&mut Cx, event:&mut Event, text_buffer:&mut TextBuffer)->CodeEditorEvent{
+        self.code_editor.handle_code_editor(cx, event, text_buffer)
+    }
+
+    pub fn draw_language_editor(&mut self, cx:&mut Cx, text_buffer:&TextBuffer){
+        if !self.code_editor.begin_code_editor(cx, text_buffer){
+            return
+        }
+        // only re-lexes the lines edited since the last draw
+        self._token_cache.update(text_buffer);
+        self.code_editor.draw_tokens(cx, text_buffer, &self._token_cache, &self.colors);
+        self.code_editor.end_code_editor(cx, text_buffer);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::tokenizer::*;
+use crate::textlines::*;
+
+// the editor indents with spaces, its tab lines are drawn every 4
+pub const INDENT:&'static str = "    ";
+
+// What the editor needs to know about a language besides its tokens.
+#[derive(Clone, Default)]
+pub struct LanguageSyntax{
+    pub name:&'static str,
+    pub line_comment:Option<&'static str>,
+    pub block_comment:Option<(&'static str, &'static str)>,
+    pub bracket_pairs:&'static [(char, char)],
+    // a line ending in an opener indents the next line, a line starting with a closer dedents
+    pub indent_pairs:&'static [(char, char)],
+    // typing the opener inserts the closer too, typing the closer in front of one steps over it
+    pub auto_close_pairs:&'static [(char, char)],
+    // keywords that start an item worth listing in the outline
+    pub outline_keywords:&'static [&'static str]
+}
+
+impl LanguageSyntax{
+    pub fn opens_indent(&self, line:Line)->bool{
+        if let Some(last) = line.iter().rev().find(|c| !c.is_whitespace()){
+            return self.indent_pairs.iter().any(|(open, _)| open == last)
+        }
+        false
+    }
+
+    pub fn closes_indent(&self, line:Line)->bool{
+        if let Some(first) = line.iter().find(|c| !c.is_whitespace()){
+            return self.indent_pairs.iter().any(|(_, close)| close == first)
+        }
+        false
+    }
+
+    pub fn get_auto_close_pair(&self, open:char)->Option<(char, char)>{
+        self.auto_close_pairs.iter().find(|(o, _)| *o == open).cloned()
+    }
+
+    pub fn is_auto_closer(&self, c:char)->bool{
+        self.auto_close_pairs.iter().any(|(_, close)| *close == c)
+    }
+
+    pub fn get_bracket_pair(&self, c:char)->Option<(char, char)>{
+        self.bracket_pairs.iter().find(|(open, close)| *open == c || *close == c).cloned()
+    }
+}
+
+// A language mode tokenizes lines for the highlighter and describes its syntax.
+pub trait LanguageMode: LineTokenizer + Clone + Default{
+    fn get_syntax(&self)->LanguageSyntax;
+}
+
+// for files we have no mode for, only tells whitespace from text
+#[derive(Clone, Default)]
+pub struct PlainTokenizer{
+}
+
+impl LineTokenizer for PlainTokenizer{
+    type State = ();
+
+    fn tokenize_line(&self, _state:&mut (), line:Line, tokens:&mut Vec<Token>){
+        let mut chunk = Vec::new();
+        let mut state = TokenizerState::new(line);
+        loop{
+            state.advance_with_cur();
+            if state.cur == '\0'{
+                break;
+            }
+            chunk.push(state.cur);
+            let token_type = if state.cur == ' ' || state.cur == '\t'{
+                state.eat_while(&mut chunk, |c| c == ' ' || c == '\t');
+                TokenType::Whitespace
+            }
+            else{
+                state.eat_while(&mut chunk, |c| c != ' ' && c != '\t');
+                TokenType::Identifier
+            };
+            tokens.push(Token{
+                len:chunk.len(),
+                token_type:token_type
+            });
+            chunk.truncate(0);
+        }
+    }
+}
+
+impl LanguageMode for PlainTokenizer{
+    fn get_syntax(&self)->LanguageSyntax{
+        LanguageSyntax{
+            name:"Plain Text",
+            ..Default::default()
+        }
+    }
+}
@@ -3,31 +3,70 @@
 use widgets::*;
 use std::collections::HashMap;
 
+mod textlines;
+pub use crate::textlines::*;
 mod textbuffer;
 pub use crate::textbuffer::*;
+mod tokenizer;
+pub use crate::tokenizer::*;
+mod codestructure;
+pub use crate::codestructure::*;
+mod editorcommand;
+pub use crate::editorcommand::*;
+mod vim;
+pub use crate::vim::*;
+mod findbar;
+pub use crate::findbar::*;
 mod codeeditor;
 pub use crate::codeeditor::*;
-mod rusteditor;
-pub use crate::rusteditor::*;
+mod projectsearch;
+pub use crate::projectsearch::*;
+mod outline;
+pub use crate::outline::*;
+mod languagemode;
+pub use crate::languagemode::*;
+mod rusttokenizer;
+pub use crate::rusttokenizer::*;
+mod jsontokenizer;
+pub use crate::jsontokenizer::*;
+mod tomltokenizer;
+pub use crate::tomltokenizer::*;
+mod shadertokenizer;
+pub use crate::shadertokenizer::*;
+mod jstokenizer;
+pub use crate::jstokenizer::*;
+mod languageeditor;
+pub use crate::languageeditor::*;
 
 #[derive(Clone)]
 enum Panel{
     Color(Color), 
     FileTree,
     FileEditorTarget,
-    FileEditor{path:String, editor_id:u64}
+    FileEditor{path:String, editor_id:u64},
+    ProjectSearch,
+    Outline
 }
 
 struct App{
     view:View<ScrollBar>,
     dock:Dock<Panel>,
     file_tree:FileTree,
+    project_search:ProjectSearch,
+    outline:Outline,
+    // the (path, editor_id) of the editor the outline shows
+    outline_editor:Option<(String, u64)>,
+    // a search result to select once its editor is drawn
+    pending_jump:Option<(String, TextPos, TextPos)>,
 
     file_editors:Elements<u64, FileEditor, FileEditorTemplates>,
     file_editor_id_alloc:u64,
 
     text_buffers:HashMap<String, TextBuffer>,
     tree_load_id:u64,
+    keymap_load_id:u64,
+    keymap:KeyMap<EditorCommand>,
+    close_warned:bool,
     quad:Quad
 }
 
@@ -36,6 +75,10 @@ main_app!(App, "Makepad");
 impl Style for App{
     fn style(cx:&mut Cx)->Self{
         set_dark_style(cx);
+        // tweak widget shaders live by putting <shader name>.shader files in this dir
+        if let Ok(dir) = std::env::var("MAKEPAD_SHADER_DIR"){
+            cx.watch_shader_dir(&dir);
+        }
         Self{
             text_buffers:HashMap::new(),
             file_editor_id_alloc:10,
@@ -54,9 +97,25 @@ impl Style for App{
             file_tree:FileTree{
                 ..Style::style(cx)
             },
+            project_search:ProjectSearch{
+                ..Style::style(cx)
+            },
+            outline:Outline{
+                ..Style::style(cx)
+            },
+            outline_editor:None,
+            pending_jump:None,
             tree_load_id:0,
+            keymap_load_id:0,
+            keymap:EditorCommand::app_keymap(cx.is_mac_platform()),
+            close_warned:false,
             file_editors:Elements::new(FileEditorTemplates{
-                rust_editor:RustEditor{..Style::style(cx)}
+                rust_editor:RustEditor{..Style::style(cx)},
+                json_editor:JsonEditor{..Style::style(cx)},
+                toml_editor:TomlEditor{..Style::style(cx)},
+                shader_editor:ShaderEditor{..Style::style(cx)},
+                js_editor:JsEditor{..Style::style(cx)},
+                plain_editor:PlainEditor{..Style::style(cx)}
             }),
             dock:Dock{
                 dock_items:Some(DockItem::Splitter{
@@ -68,8 +127,15 @@ impl Style for App{
                         tabs:vec![
                             DockTab{
                                 closeable:false,
+                                dirty:false,
                                 title:"Files".to_string(),
                                 item:Panel::FileTree
+                            },
+                            DockTab{
+                                closeable:false,
+                                dirty:false,
+                                title:"Outline".to_string(),
+                                item:Panel::Outline
                             }
                         ]
                     }),
@@ -82,11 +148,13 @@ impl Style for App{
                             tabs:vec![
                                 DockTab{
                                     closeable:false,
+                                    dirty:false,
                                     title:"Edit".to_string(),
                                     item:Panel::FileEditorTarget
                                 },
                                 DockTab{
                                     closeable:true,
+                                    dirty:false,
                                     title:"button.rs".to_string(),
                                     item:Panel::FileEditor{path:"/widgets/src/button.rs".to_string(), editor_id:1}
                                 }
@@ -97,8 +165,15 @@ impl Style for App{
                             tabs:vec![
                                 DockTab{
                                     closeable:true,
+                                    dirty:false,
                                     title:"Log".to_string(),
                                     item:Panel::Color(color256(30,30,30))
+                                },
+                                DockTab{
+                                    closeable:false,
+                                    dirty:false,
+                                    title:"Search".to_string(),
+                                    item:Panel::ProjectSearch
                                 }
                             ]
                         })
@@ -123,9 +198,22 @@ impl App{
     fn handle_app(&mut self, cx:&mut Cx, event:&mut Event){
         match event{
             Event::Construct=>{
+                // a wasm build only knows its platform from here on
+                self.reset_keymaps(cx.is_mac_platform());
                 self.tree_load_id = cx.read_file("./index.json");
+                // user key bindings go over the defaults, see apply_keymap
+                self.keymap_load_id = cx.read_file("./keymap.json");
             },
             Event::FileRead(fr)=>{
+                if fr.id == self.keymap_load_id{
+                    if let Ok(str_data) = &fr.data{
+                        if let Ok(utf8_data) = std::str::from_utf8(&str_data){
+                            if let Err(err) = self.apply_keymap(utf8_data){
+                                cx.log(&format!("Error in keymap.json: {}\n", err));
+                            }
+                        }
+                    }
+                }
                 // lets see which file we loaded
                 if fr.id == self.tree_load_id{
                     if let Ok(str_data) = &fr.data{
@@ -143,7 +231,62 @@ impl App{
                         }
                     }
                 }
-            }
+            },
+            Event::FileWrite(fw)=>{
+                for (path, text_buffer) in &mut self.text_buffers{
+                    if text_buffer.save_id == fw.id{
+                        if let Some(error) = &fw.error{
+                            text_buffer.save_id = 0;
+                            cx.log(&format!("Error saving {}: {}\n", path, error));
+                        }
+                        else{
+                            text_buffer.mark_saved();
+                            // a save resolves what the close warning was about
+                            self.close_warned = false;
+                        }
+                        cx.redraw_area(Area::All);
+                    }
+                }
+            },
+            Event::CloseRequested=>{
+                // refuse the first close if we'd lose changes, a second one goes through
+                if !self.close_warned{
+                    let mut dirty_paths = Vec::new();
+                    for (path, text_buffer) in &self.text_buffers{
+                        if text_buffer.is_dirty(){
+                            dirty_paths.push(path.clone());
+                        }
+                    }
+                    if dirty_paths.len() > 0{
+                        self.close_warned = true;
+                        cx.cancel_close();
+                        cx.log(&format!("Unsaved changes in {}, close again to discard them\n", dirty_paths.join(", ")));
+                    }
+                }
+            },
+            Event::KeyDown(ke)=>{
+                // search in files works from wherever the key focus is
+                if let KeyMapResult::Command(EditorCommand::SearchInFiles) = self.keymap.handle_key(&ke){
+                    self.focus_project_search_tab(cx);
+                    self.project_search.focus_project_search(cx);
+                }
+            },
+            _=>()
+        }
+
+        // the search panel streams file reads in the background so it sees every event
+        match self.project_search.handle_project_search(cx, event, &self.text_buffers){
+            ProjectSearchEvent::StartSearch=>{
+                let paths = self.file_tree.get_all_file_paths();
+                self.project_search.start_search(cx, paths);
+            },
+            ProjectSearchEvent::OpenResult{path, start, end}=>{
+                if let Some(target_ctrl_id) = self.focus_editor_or_find_editor_target(cx, &path){
+                    self.open_new_editor_in_target_ctrl(cx, target_ctrl_id, &path);
+                }
+                self.pending_jump = Some((path, start, end));
+                cx.redraw_area(Area::All);
+            },
             _=>()
         }
 
@@ -151,10 +294,15 @@ impl App{
         
         let mut dock_walker =  self.dock.walker();
         let mut file_tree_event = FileTreeEvent::None;
+        let mut outline_event = OutlineEvent::None;
         while let Some(item) = dock_walker.walk_handle_dock(cx, event){
             match item{
                 Panel::Color(_)=>{}
                 Panel::FileEditorTarget=>{},
+                Panel::ProjectSearch=>{},
+                Panel::Outline=>{
+                    outline_event = self.outline.handle_outline(cx, event);
+                },
                 Panel::FileTree=>{
                     file_tree_event = self.file_tree.handle_file_tree(cx, event);
                 },
@@ -162,12 +310,45 @@ impl App{
                     if let Some(file_editor) = &mut self.file_editors.get(*editor_id){
                         let text_buffer = self.text_buffers.get_mut(path);
                         if let Some(text_buffer) = text_buffer{
-                            file_editor.handle_file_editor(cx, event, text_buffer);
+                            let was_dirty = text_buffer.is_dirty();
+                            match file_editor.handle_file_editor(cx, event, text_buffer){
+                                FileEditorEvent::Save=>{
+                                    text_buffer.save_buffer(cx, &format!(".{}",path));
+                                },
+                                _=>()
+                            }
+                            // the tab titles show the dirty marker
+                            if was_dirty != text_buffer.is_dirty(){
+                                // new unsaved changes get their own close warning
+                                if text_buffer.is_dirty(){
+                                    self.close_warned = false;
+                                }
+                                cx.redraw_area(Area::All);
+                            }
+                            // the outline follows the editor with the focus
+                            if file_editor.get_code_editor().has_key_focus(cx){
+                                let outline_editor = Some((path.clone(), *editor_id));
+                                if self.outline_editor != outline_editor{
+                                    self.outline_editor = outline_editor;
+                                    cx.redraw_area(Area::All);
+                                }
+                            }
                         }
                     }
                 }
             }
         }
+        match outline_event{
+            OutlineEvent::SelectItem{path, row, col}=>{
+                if let Some(target_ctrl_id) = self.focus_editor_or_find_editor_target(cx, &path){
+                    self.open_new_editor_in_target_ctrl(cx, target_ctrl_id, &path);
+                }
+                let pos = TextPos{row:row, col:col};
+                self.pending_jump = Some((path, pos, pos));
+                cx.redraw_area(Area::All);
+            },
+            _=>()
+        }
         match file_tree_event{
             FileTreeEvent::DragMove{fe, ..}=>{
                 self.dock.dock_drag_move(cx, fe);
@@ -209,6 +390,8 @@ impl App{
 
         self.view.begin_view(cx, &Layout{..Default::default()});
 
+        self.update_dirty_tabs();
+
         self.dock.draw_dock(cx);
 
         let mut dock_walker = self.dock.walker();
@@ -223,6 +406,12 @@ impl App{
                 Panel::FileTree=>{
                     self.file_tree.draw_file_tree(cx);
                 },
+                Panel::ProjectSearch=>{
+                    self.project_search.draw_project_search(cx);
+                },
+                Panel::Outline=>{
+                    self.outline.draw_outline(cx);
+                },
                 Panel::FileEditor{path, editor_id}=>{
                     //let text_buffer = self.text_buffers.get_mut(path).unwrap();
                     let text_buffer = self.text_buffers.entry(path.to_string()).or_insert_with(||{
@@ -231,20 +420,95 @@ impl App{
                             ..Default::default()
                         }
                     });
-                    self.file_editors.get_draw(cx, *editor_id, |_cx, tmpl|{
+                    let file_editor = self.file_editors.get_draw(cx, *editor_id, |_cx, tmpl|{
                         FileEditor::create_file_editor_for_path(path, tmpl)
-                    }).draw_file_editor(cx, text_buffer);
+                    });
+                    if let Some((jump_path, start, end)) = &self.pending_jump{
+                        if jump_path == path{
+                            file_editor.jump_to(cx, *start, *end);
+                            self.pending_jump = None;
+                        }
+                    }
+                    file_editor.draw_file_editor(cx, text_buffer);
+                    if self.outline_editor.is_none(){
+                        self.outline_editor = Some((path.clone(), *editor_id));
+                    }
+                    if self.outline_editor == Some((path.clone(), *editor_id)){
+                        self.outline.set_outline(cx, path, &file_editor.get_code_editor()._structure.outline);
+                    }
                 }
             }
         }
         self.view.end_view(cx);
     }
 
+    fn reset_keymaps(&mut self, is_mac:bool){
+        self.keymap = EditorCommand::app_keymap(is_mac);
+        let template = &mut self.file_editors.template;
+        template.rust_editor.code_editor.reset_keymap(is_mac);
+        template.json_editor.code_editor.reset_keymap(is_mac);
+        template.toml_editor.code_editor.reset_keymap(is_mac);
+        template.shader_editor.code_editor.reset_keymap(is_mac);
+        template.js_editor.code_editor.reset_keymap(is_mac);
+        template.plain_editor.code_editor.reset_keymap(is_mac);
+        for file_editor in self.file_editors.iter(){
+            file_editor.get_code_editor().reset_keymap(is_mac);
+        }
+    }
+
+    // keymap.json is a list of {"key":"primary+shift+d", "command":"duplicate"}, a "when" of
+    // "find_bar" or "app" puts the binding in those keymaps instead of the code editor's
+    fn apply_keymap(&mut self, json:&str)->Result<(), String>{
+        self.keymap.load_json(json, "app", false)?;
+        let template = &mut self.file_editors.template;
+        template.rust_editor.code_editor.load_keymap(json)?;
+        template.json_editor.code_editor.load_keymap(json)?;
+        template.toml_editor.code_editor.load_keymap(json)?;
+        template.shader_editor.code_editor.load_keymap(json)?;
+        template.js_editor.code_editor.load_keymap(json)?;
+        template.plain_editor.code_editor.load_keymap(json)?;
+        for file_editor in self.file_editors.iter(){
+            file_editor.get_code_editor().load_keymap(json)?;
+        }
+        Ok(())
+    }
+
+    fn update_dirty_tabs(&mut self){
+        let mut dock_walker = self.dock.walker();
+        while let Some(dock_item) = dock_walker.walk_dock_item(){
+            if let DockItem::TabControl{tabs, ..} = dock_item{
+                for tab in tabs.iter_mut(){
+                    if let Panel::FileEditor{path, ..} = &tab.item{
+                        tab.dirty = if let Some(text_buffer) = self.text_buffers.get(path){
+                            text_buffer.is_dirty()
+                        }
+                        else{
+                            false
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    fn focus_project_search_tab(&mut self, cx:&mut Cx){
+        let mut dock_walker = self.dock.walker();
+        while let Some(dock_item) = dock_walker.walk_dock_item(){
+            if let DockItem::TabControl{current, tabs} = dock_item{
+                if let Some(id) = tabs.iter().position(|tab| if let Panel::ProjectSearch = tab.item{true}else{false}){
+                    *current = id;
+                    cx.redraw_area(Area::All);
+                }
+            }
+        }
+    }
+
     fn new_file_editor_tab(&mut self, path:&str)->DockTab<Panel>{
         let editor_id = self.file_editor_id_alloc;
         self.file_editor_id_alloc += 1;
         DockTab{
             closeable:true,
+            dirty:false,
             title:path_file_name(&path),
             item:Panel::FileEditor{path:path.to_string(), editor_id:editor_id}
         }
@@ -307,51 +571,122 @@ impl App{
 
 struct FileEditorTemplates{
     rust_editor:RustEditor,
+    json_editor:JsonEditor,
+    toml_editor:TomlEditor,
+    shader_editor:ShaderEditor,
+    js_editor:JsEditor,
+    plain_editor:PlainEditor
 }
 
 #[derive(Clone)]
 enum FileEditor{
-    Rust(RustEditor)
+    Rust(RustEditor),
+    Json(JsonEditor),
+    Toml(TomlEditor),
+    Shader(ShaderEditor),
+    Js(JsEditor),
+    Plain(PlainEditor)
 }
 
 impl ElementLife for FileEditor{
     fn construct(&mut self, cx:&mut Cx){
         match self{
-            FileEditor::Rust(re)=>re.construct(cx),
+            FileEditor::Rust(le)=>le.construct(cx),
+            FileEditor::Json(le)=>le.construct(cx),
+            FileEditor::Toml(le)=>le.construct(cx),
+            FileEditor::Shader(le)=>le.construct(cx),
+            FileEditor::Js(le)=>le.construct(cx),
+            FileEditor::Plain(le)=>le.construct(cx),
         }
     }
     fn destruct(&mut self, cx:&mut Cx){
         match self{
-            FileEditor::Rust(re)=>re.destruct(cx),
+            FileEditor::Rust(le)=>le.destruct(cx),
+            FileEditor::Json(le)=>le.destruct(cx),
+            FileEditor::Toml(le)=>le.destruct(cx),
+            FileEditor::Shader(le)=>le.destruct(cx),
+            FileEditor::Js(le)=>le.destruct(cx),
+            FileEditor::Plain(le)=>le.destruct(cx),
         }
     }
 }
 
 enum FileEditorEvent{
-    None
+    None,
+    Save
 }
 
 impl FileEditor{
     fn handle_file_editor(&mut self, cx:&mut Cx, event:&mut Event, text_buffer:&mut TextBuffer)->FileEditorEvent{
+        let code_editor_event = match self{
+            FileEditor::Rust(le)=>le.handle_language_editor(cx, event, text_buffer),
+            FileEditor::Json(le)=>le.handle_language_editor(cx, event, text_buffer),
+            FileEditor::Toml(le)=>le.handle_language_editor(cx, event, text_buffer),
+            FileEditor::Shader(le)=>le.handle_language_editor(cx, event, text_buffer),
+            FileEditor::Js(le)=>le.handle_language_editor(cx, event, text_buffer),
+            FileEditor::Plain(le)=>le.handle_language_editor(cx, event, text_buffer),
+        };
+        match code_editor_event{
+            CodeEditorEvent::Save=>FileEditorEvent::Save,
+            _=>FileEditorEvent::None
+        }
+    }
+
+    fn get_code_editor(&mut self)->&mut CodeEditor{
         match self{
-            FileEditor::Rust(re)=>{
-                re.handle_rust_editor(cx, event, text_buffer);
-                FileEditorEvent::None
-            },
+            FileEditor::Rust(le)=>&mut le.code_editor,
+            FileEditor::Json(le)=>&mut le.code_editor,
+            FileEditor::Toml(le)=>&mut le.code_editor,
+            FileEditor::Shader(le)=>&mut le.code_editor,
+            FileEditor::Js(le)=>&mut le.code_editor,
+            FileEditor::Plain(le)=>&mut le.code_editor,
         }
     }
 
+    fn jump_to(&mut self, cx:&mut Cx, start:TextPos, end:TextPos){
+        self.get_code_editor().jump_to(cx, start, end)
+    }
+
     fn draw_file_editor(&mut self, cx:&mut Cx, text_buffer:&mut TextBuffer){
         match self{
-            FileEditor::Rust(re)=>re.draw_rust_editor(cx, text_buffer),
+            FileEditor::Rust(le)=>le.draw_language_editor(cx, text_buffer),
+            FileEditor::Json(le)=>le.draw_language_editor(cx, text_buffer),
+            FileEditor::Toml(le)=>le.draw_language_editor(cx, text_buffer),
+            FileEditor::Shader(le)=>le.draw_language_editor(cx, text_buffer),
+            FileEditor::Js(le)=>le.draw_language_editor(cx, text_buffer),
+            FileEditor::Plain(le)=>le.draw_language_editor(cx, text_buffer),
         }
     }
 
     fn create_file_editor_for_path(path:&str, template:&FileEditorTemplates)->FileEditor{
         // check which file extension we have to spawn a new editor
-        FileEditor::Rust(RustEditor{
-            path:path.to_string(),
-            ..template.rust_editor.clone()
-        })
+        let ext = if let Some(dot) = path.rfind('.'){path[dot+1..].to_lowercase()}else{"".to_string()};
+        match ext.as_ref(){
+            // .shader files hold shader_ast! bodies, which are rust syntax
+            "rs" | "shader"=>FileEditor::Rust(RustEditor{
+                path:path.to_string(),
+                ..template.rust_editor.clone()
+            }),
+            "json"=>FileEditor::Json(JsonEditor{
+                path:path.to_string(),
+                ..template.json_editor.clone()
+            }),
+            "toml"=>FileEditor::Toml(TomlEditor{
+                path:path.to_string(),
+                ..template.toml_editor.clone()
+            }),
+            "glsl" | "vert" | "frag" | "metal" | "msl"=>FileEditor::Shader(ShaderEditor{
+                path:path.to_string(),
+                ..template.shader_editor.clone()
+            }),
+            "js"=>FileEditor::Js(JsEditor{
+                path:path.to_string(),
+                ..template.js_editor.clone()
+            }),
+            _=>FileEditor::Plain(PlainEditor{
+                path:path.to_string(),
+                ..template.plain_editor.clone()
+            })
+        }
     }
-}
\ No newline at end of file
+}
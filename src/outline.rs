@@ -0,0 +1,141 @@
+use widgets::*;
+use crate::codestructure::*;
+
+// Lists the items of the file in the editor that last had focus
+#[derive(Clone)]
+pub struct Outline{
+    pub view:View<ScrollBar>,
+    pub row_bg:Quad,
+    pub kind_text:Text,
+    pub name_text:Text,
+    pub row_height:f32,
+    pub indent_width:f32,
+    pub path:String,
+    pub items:Vec<OutlineItem>,
+
+    pub _hit_states:Vec<HitState>,
+    pub _bg_areas:Vec<Area>,
+    pub _selected:Option<usize>
+}
+
+impl ElementLife for Outline{
+    fn construct(&mut self, _cx:&mut Cx){}
+    fn destruct(&mut self, _cx:&mut Cx){}
+}
+
+impl Style for Outline{
+    fn style(cx:&mut Cx)->Self{
+        Self{
+            view:View{
+                scroll_h:Some(ScrollBar{
+                    ..Style::style(cx)
+                }),
+                scroll_v:Some(ScrollBar{
+                    smoothing:Some(0.25),
+                    ..Style::style(cx)
+                }),
+                ..Style::style(cx)
+            },
+            row_bg:Quad{
+                ..Style::style(cx)
+            },
+            kind_text:Text{
+                font_id:cx.load_font(&cx.font("mono_font")),
+                font_size:11.0,
+                color:color256(91,155,211),
+                ..Style::style(cx)
+            },
+            name_text:Text{
+                font_id:cx.load_font(&cx.font("mono_font")),
+                font_size:11.0,
+                color:cx.color("text_selected_focus"),
+                ..Style::style(cx)
+            },
+            row_height:20.,
+            indent_width:12.,
+            path:"".to_string(),
+            items:Vec::new(),
+            _hit_states:Vec::new(),
+            _bg_areas:Vec::new(),
+            _selected:None
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum OutlineEvent{
+    None,
+    SelectItem{path:String, row:usize, col:usize}
+}
+
+impl Outline{
+
+    // only redraws when the items actually changed
+    pub fn set_outline(&mut self, cx:&mut Cx, path:&str, items:&Vec<OutlineItem>){
+        if self.path == path && self.items == *items{
+            return
+        }
+        if self.path != path{
+            self._selected = None;
+            self.path = path.to_string();
+        }
+        self.items = items.clone();
+        self._hit_states.resize(self.items.len(), HitState{..Default::default()});
+        self._bg_areas.resize(self.items.len(), Area::Empty);
+        self.view.redraw_view_area(cx);
+    }
+
+    pub fn handle_outline(&mut self, cx:&mut Cx, event:&mut Event)->OutlineEvent{
+        self.view.handle_scroll_bars(cx, event);
+
+        for index in 0..self._bg_areas.len(){
+            match event.hits(cx, self._bg_areas[index], &mut self._hit_states[index]){
+                Event::FingerDown(_fe)=>{
+                    self._selected = Some(index);
+                    self.view.redraw_view_area(cx);
+                    let item = &self.items[index];
+                    return OutlineEvent::SelectItem{
+                        path:self.path.clone(),
+                        row:item.row,
+                        col:item.col
+                    }
+                },
+                Event::FingerHover(_fe)=>{
+                    cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                },
+                _=>()
+            }
+        }
+        OutlineEvent::None
+    }
+
+    pub fn draw_outline(&mut self, cx:&mut Cx){
+        self.view.begin_view(cx, &Layout{..Default::default()});
+
+        let bg_even = cx.color("bg_selected");
+        let bg_odd = cx.color("bg_odd");
+        let bg_marked = cx.color("bg_marked");
+
+        for (index, item) in self.items.iter().enumerate(){
+            self.row_bg.color = if self._selected == Some(index){
+                bg_marked
+            }
+            else if index&1 == 0{bg_even}else{bg_odd};
+            let inst = self.row_bg.begin_quad(cx, &Layout{
+                width:Bounds::Fill,
+                height:Bounds::Fix(self.row_height),
+                align:Align::left_center(),
+                padding:Padding{l:5. + self.indent_width * item.depth as f32, t:0., r:0., b:0.},
+                ..Default::default()
+            });
+            self.kind_text.draw_text(cx, &format!("{} ", item.kind));
+            self.name_text.draw_text(cx, &item.name);
+            let bg_area = self.row_bg.end_quad(cx, &inst);
+            cx.update_area_refs(self._bg_areas[index], bg_area);
+            self._bg_areas[index] = bg_area;
+            cx.turtle_new_line();
+        }
+
+        self.view.end_view(cx);
+    }
+}
@@ -0,0 +1,323 @@
+use widgets::*;
+use std::collections::HashMap;
+use crate::textbuffer::*;
+use crate::textlines::*;
+use crate::findbar::*;
+
+#[derive(Clone)]
+pub struct SearchResult{
+    pub path:String,
+    pub start:TextPos,
+    pub end:TextPos,
+    // the matched line with leading whitespace stripped
+    pub line:String,
+    pub _hit_state:HitState,
+    pub _bg_area:Area
+}
+
+#[derive(Clone)]
+pub struct ProjectSearch{
+    pub view:View<ScrollBar>,
+    pub find_bar:FindBar,
+    pub row_bg:Quad,
+    pub path_text:Text,
+    pub line_text:Text,
+    pub row_height:f32,
+    pub max_results:usize,
+    pub files_per_frame:usize,
+    pub max_pending_reads:usize,
+    pub results:Vec<SearchResult>,
+
+    pub _search_id:u64,
+    // paths still to search, popped from the back
+    pub _queue:Vec<String>,
+    // (read id, path, search id) of files we are waiting on
+    pub _pending_reads:Vec<(u64, String, u64)>,
+    pub _files_searched:usize,
+    pub _files_total:usize,
+    pub _selected:Option<usize>,
+    pub _bg_area:Area
+}
+
+impl ElementLife for ProjectSearch{
+    fn construct(&mut self, _cx:&mut Cx){}
+    fn destruct(&mut self, _cx:&mut Cx){}
+}
+
+impl Style for ProjectSearch{
+    fn style(cx:&mut Cx)->Self{
+        Self{
+            view:View{
+                scroll_h:Some(ScrollBar{
+                    ..Style::style(cx)
+                }),
+                scroll_v:Some(ScrollBar{
+                    smoothing:Some(0.25),
+                    ..Style::style(cx)
+                }),
+                ..Style::style(cx)
+            },
+            find_bar:FindBar{
+                closeable:false,
+                show_match_count:false,
+                _is_open:true,
+                ..Style::style(cx)
+            },
+            row_bg:Quad{
+                ..Style::style(cx)
+            },
+            path_text:Text{
+                color:cx.color("text_selected_focus"),
+                ..Style::style(cx)
+            },
+            line_text:Text{
+                font_id:cx.load_font(&cx.font("mono_font")),
+                font_size:11.0,
+                color:cx.color("text_deselected_focus"),
+                wrapping:Wrapping::Line,
+                ..Style::style(cx)
+            },
+            row_height:20.,
+            max_results:5000,
+            files_per_frame:50,
+            max_pending_reads:16,
+            results:Vec::new(),
+            _search_id:0,
+            _queue:Vec::new(),
+            _pending_reads:Vec::new(),
+            _files_searched:0,
+            _files_total:0,
+            _selected:None,
+            _bg_area:Area::Empty
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum ProjectSearchEvent{
+    None,
+    StartSearch,
+    OpenResult{path:String, start:TextPos, end:TextPos}
+}
+
+impl ProjectSearch{
+
+    pub fn focus_project_search(&mut self, cx:&mut Cx){
+        self.find_bar.open(cx, false, None);
+    }
+
+    pub fn is_searching(&self)->bool{
+        self._queue.len() > 0 || self._pending_reads.iter().any(|(_, _, search_id)| *search_id == self._search_id)
+    }
+
+    // searches the given files, open text buffers win over what is on disk
+    // and everything else is read again so edits outside the editor show up
+    pub fn start_search(&mut self, cx:&mut Cx, paths:Vec<String>){
+        self._search_id += 1;
+        self.results.truncate(0);
+        self._selected = None;
+        self._files_searched = 0;
+        self._files_total = 0;
+        self._queue.truncate(0);
+        if self.find_bar._regex.is_some(){
+            self._files_total = paths.len();
+            self._queue = paths;
+            self._queue.reverse();
+            cx.next_frame(self._bg_area);
+        }
+        self.view.redraw_view_area(cx);
+    }
+
+    fn search_lines<'a, I>(&mut self, path:&str, lines:I)
+    where I: Iterator<Item = Line<'a>>
+    {
+        self._files_searched += 1;
+        let mut line_matches = Vec::new();
+        for (row, chars) in lines.enumerate(){
+            if self.results.len() >= self.max_results{
+                return
+            }
+            line_matches.truncate(0);
+            self.find_bar.find_in_line(chars, &mut line_matches);
+            // one result row per line is plenty, it jumps to the first match
+            if let Some((start_col, end_col)) = line_matches.first(){
+                let line:String = chars.iter().collect();
+                self.results.push(SearchResult{
+                    path:path.to_string(),
+                    start:TextPos{row:row, col:*start_col},
+                    end:TextPos{row:row, col:*end_col},
+                    line:line.trim_start().chars().take(200).collect(),
+                    _hit_state:HitState{..Default::default()},
+                    _bg_area:Area::Empty
+                });
+            }
+        }
+    }
+
+    // does a slice of the queued files per frame so the ui stays responsive
+    fn search_queue(&mut self, cx:&mut Cx, text_buffers:&HashMap<String, TextBuffer>){
+        let mut searched = 0;
+        while searched < self.files_per_frame && self._pending_reads.len() < self.max_pending_reads{
+            // past the maximum nothing we read would show up anyway
+            if self.results.len() >= self.max_results{
+                self._queue.truncate(0);
+                break;
+            }
+            let path = if let Some(path) = self._queue.pop(){path}else{break};
+            searched += 1;
+            if let Some(text_buffer) = text_buffers.get(&path){
+                if text_buffer.load_id == 0{
+                    self.search_lines(&path, (0..text_buffer.get_line_count()).map(|row| text_buffer.get_line(row)));
+                    continue;
+                }
+            }
+            let read_id = cx.read_file(&format!(".{}", path));
+            self._pending_reads.push((read_id, path, self._search_id));
+        }
+        if self._queue.len() > 0{
+            cx.next_frame(self._bg_area);
+        }
+        self.view.redraw_view_area(cx);
+    }
+
+    pub fn handle_project_search(&mut self, cx:&mut Cx, event:&mut Event, text_buffers:&HashMap<String, TextBuffer>)->ProjectSearchEvent{
+        match self.find_bar.handle_find_bar(cx, event){
+            FindBarEvent::Changed | FindBarEvent::Next=>{
+                return ProjectSearchEvent::StartSearch
+            },
+            _=>()
+        }
+        self.view.handle_scroll_bars(cx, event);
+
+        match event{
+            Event::Frame(_)=>{
+                if self._queue.len() > 0{
+                    self.search_queue(cx, text_buffers);
+                }
+            },
+            Event::FileRead(fr)=>{
+                if let Some(index) = self._pending_reads.iter().position(|(read_id, _, _)| *read_id == fr.id){
+                    let (_, path, search_id) = self._pending_reads.remove(index);
+                    // reads for a search that was restarted since are dropped
+                    if search_id == self._search_id{
+                        let mut lines = None;
+                        if let Ok(data) = &fr.data{
+                            if let Ok(utf8_data) = std::str::from_utf8(&data){
+                                lines = Some(utf8_data.replace("\r\n", "\n").split("\n").map(|s| s.chars().collect()).collect::<Vec<Vec<char>>>());
+                            }
+                        }
+                        if let Some(lines) = &lines{
+                            self.search_lines(&path, lines.iter().map(Line::from));
+                        }
+                        else{ // unreadable files still count as done
+                            self._files_searched += 1;
+                        }
+                        // a free read slot, keep the queue going
+                        if self._queue.len() > 0{
+                            cx.next_frame(self._bg_area);
+                        }
+                        self.view.redraw_view_area(cx);
+                    }
+                }
+            },
+            _=>()
+        }
+
+        for (index, result) in self.results.iter_mut().enumerate(){
+            match event.hits(cx, result._bg_area, &mut result._hit_state){
+                Event::FingerDown(_fe)=>{
+                    self._selected = Some(index);
+                    self.view.redraw_view_area(cx);
+                    return ProjectSearchEvent::OpenResult{
+                        path:result.path.clone(),
+                        start:result.start,
+                        end:result.end
+                    }
+                },
+                Event::FingerHover(_fe)=>{
+                    cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                },
+                _=>()
+            }
+        }
+        ProjectSearchEvent::None
+    }
+
+    pub fn draw_project_search(&mut self, cx:&mut Cx){
+        self.find_bar.draw_find_bar(cx);
+        cx.turtle_new_line();
+
+        self.view.begin_view(cx, &Layout{..Default::default()});
+
+        let bg_even = cx.color("bg_selected");
+        let bg_odd = cx.color("bg_odd");
+        let bg_marked = cx.color("bg_marked");
+
+        // status row
+        self.row_bg.color = bg_odd;
+        let inst = self.row_bg.begin_quad(cx, &Layout{
+            width:Bounds::Fill,
+            height:Bounds::Fix(self.row_height),
+            align:Align::left_center(),
+            padding:Padding{l:5.,t:0.,r:0.,b:0.},
+            ..Default::default()
+        });
+        let status = if self.find_bar._regex_error{
+            "invalid regex".to_string()
+        }
+        else if self._files_total == 0{
+            "type to search all files, enter to search again".to_string()
+        }
+        else{
+            format!("{} results in {} of {} files{}{}",
+                self.results.len(), self._files_searched, self._files_total,
+                if self.is_searching(){", searching..."}else{""},
+                if self.results.len() >= self.max_results{", stopped at the maximum"}else{""}
+            )
+        };
+        self.path_text.draw_text(cx, &status);
+        let bg_area = self.row_bg.end_quad(cx, &inst);
+        cx.update_area_refs(self._bg_area, bg_area);
+        self._bg_area = bg_area;
+        cx.turtle_new_line();
+
+        let mut last_path = String::new();
+        let mut counter = 0;
+        for (index, result) in self.results.iter_mut().enumerate(){
+            if result.path != last_path{ // file header
+                last_path = result.path.clone();
+                self.row_bg.color = if counter&1 == 0{bg_even}else{bg_odd};
+                let inst = self.row_bg.begin_quad(cx, &Layout{
+                    width:Bounds::Fill,
+                    height:Bounds::Fix(self.row_height),
+                    align:Align::left_center(),
+                    padding:Padding{l:5.,t:0.,r:0.,b:0.},
+                    ..Default::default()
+                });
+                self.path_text.draw_text(cx, &result.path);
+                self.row_bg.end_quad(cx, &inst);
+                cx.turtle_new_line();
+                counter += 1;
+            }
+            self.row_bg.color = if self._selected == Some(index){
+                bg_marked
+            }
+            else if counter&1 == 0{bg_even}else{bg_odd};
+            let inst = self.row_bg.begin_quad(cx, &Layout{
+                width:Bounds::Fill,
+                height:Bounds::Fix(self.row_height),
+                align:Align::left_center(),
+                padding:Padding{l:20.,t:0.,r:0.,b:0.},
+                ..Default::default()
+            });
+            self.line_text.draw_text(cx, &format!("{}: {}", result.start.row + 1, result.line));
+            let bg_area = self.row_bg.end_quad(cx, &inst);
+            cx.update_area_refs(result._bg_area, bg_area);
+            result._bg_area = bg_area;
+            cx.turtle_new_line();
+            counter += 1;
+        }
+
+        self.view.end_view(cx);
+    }
+}
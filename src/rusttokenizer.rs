@@ -1,204 +1,121 @@
-use widgets::*;
-use crate::textbuffer::*;
-use crate::codeeditor::*;
+use crate::tokenizer::*;
+use crate::textlines::*;
+use crate::languagemode::*;
 
-#[derive(Clone)]
-pub struct RustEditor{
-    pub path:String,
-    pub code_editor:CodeEditor,
-    pub col_whitespace:Color,
-    pub col_keyword:Color,
-    pub col_flow_keyword:Color,
-    pub col_identifier:Color,
-    pub col_operator:Color,
-    pub col_function:Color,
-    pub col_number:Color,
-    pub col_paren:Color,
-    pub col_comment:Color,
-    pub col_string:Color,
-    pub col_delim:Color,
-    pub col_type:Color
+#[derive(Clone, Default)]
+pub struct RustTokenizer{
 }
 
-impl ElementLife for RustEditor{
-    fn construct(&mut self, _cx:&mut Cx){}
-    fn destruct(&mut self, _cx:&mut Cx){}
+// what a line can leave open for the next one
+#[derive(Clone, PartialEq, Debug)]
+pub enum RustTokenizerState{
+    Code,
+    // block comments nest in rust
+    BlockComment{depth:usize, is_doc:bool},
+    // normal, byte and c strings can all span lines
+    String,
+    RawString{hashes:usize}
 }
 
-impl Style for RustEditor{
-    fn style(cx:&mut Cx)->Self{
-        let rust_editor = Self{
-            path:"".to_string(),
-            code_editor:CodeEditor{
-                ..Style::style(cx)
-            },
-            // syntax highlighting colors
-            col_whitespace:color256(110,110,110),
-            col_keyword:color256(91,155,211),
-            col_flow_keyword:color256(196,133,190),
-            col_identifier:color256(212,212,212),
-            col_operator:color256(212,212,212),
-            col_function:color256(220,220,174),
-            col_type:color256(86,201,177),
-            col_number:color256(182,206,170),
-            col_comment:color256(99,141,84),
-            col_paren:color256(212,212,212),
-            col_string:color256(204,145,123),
-            col_delim:color256(212,212,212)
-        };
-        //tab.animator.default = tab.anim_default(cx);
-        rust_editor
+impl Default for RustTokenizerState{
+    fn default()->Self{
+        RustTokenizerState::Code
     }
 }
 
-#[derive(Clone, PartialEq)]
-pub enum RustEditorEvent{
-    None,
-    Change
-}
-
+impl LineTokenizer for RustTokenizer{
+    type State = RustTokenizerState;
 
-impl RustEditor{
-    pub fn handle_rust_editor(&mut self, cx:&mut Cx, event:&mut Event, text_buffer:&mut TextBuffer)->CodeEditorEvent{
-        match self.code_editor.handle_code_editor(cx, event, text_buffer){
-            _=>()
-        }
-        CodeEditorEvent::None
-    }
+    fn tokenize_line(&self, rust_state:&mut RustTokenizerState, line:Line, tokens:&mut Vec<Token>){
+        let mut chunk = Vec::new();
+        let mut state = TokenizerState::new(line);
+        let mut after_newline = true;
 
-    pub fn draw_rust_editor(&mut self, cx:&mut Cx, text_buffer:&TextBuffer){
-        if !self.code_editor.begin_code_editor(cx, text_buffer){
-            return
+        // first finish whatever the previous line left open
+        if let Some(token_type) = Self::continue_rust_token(rust_state, &mut state, &mut chunk){
+            if chunk.len() > 0{
+                tokens.push(Token{
+                    len:chunk.len(),
+                    token_type:token_type
+                });
+                chunk.truncate(0);
+                after_newline = false;
+            }
         }
 
-        let mut chunk = Vec::new();
-        let mut state = TokenizerState::new(text_buffer);
-        
-        let mut after_newline = true; 
-        let mut last_tabs = 0;
-        let mut newline_tabs = 0;
-
         loop{
-            //let bit = rust_colorizer.next(&mut chunk, &self.rust_colors);
-            let mut do_newline = false;
-            let mut is_whitespace = false;
-            let color;
+            let token_type;
             state.advance_with_cur();
-            
+
             match state.cur{
-                '\0'=>{ // eof
+                '\0'=>{ // end of line
                     break;
                 },
-                '\n'=>{
-                    color = self.col_whitespace;
-                    // do a newline
-                    if after_newline{
-                        self.code_editor.draw_tab_lines(cx, last_tabs);
-                    }
-                    else {
-                        last_tabs = newline_tabs;
-                    }
-                    chunk.push('\n');
-                    do_newline = true;
-                    after_newline = true;
-                    is_whitespace = true;
-                    newline_tabs = 0;
-                    // spool up the next char
-                },
                 ' ' | '\t'=>{ // eat as many spaces as possible
-                    color = self.col_whitespace;
-                    if after_newline{ // consume spaces in groups of 4
-                        chunk.push(state.cur);
-                        
-                        let mut counter = 1;
+                    token_type = TokenType::Whitespace;
+                    chunk.push(state.cur);
+                    if after_newline{ // the indent, tabs included
                         while state.next == ' ' || state.next == '\t'{
                             chunk.push(state.next);
-                            counter += 1;
                             state.advance();
                         }
-                        let tabs = counter >> 2;
-                        last_tabs = tabs;
-                        newline_tabs = tabs;
-                        self.code_editor.draw_tab_lines(cx, tabs);
                     }
                     else{
-                        chunk.push(state.cur);
                         while state.next == ' '{
                             chunk.push(state.next);
                             state.advance();
                         }
                     }
-                    is_whitespace = true;
                 },
                 '/'=>{ // parse comment
                     after_newline = false;
                     chunk.push(state.cur);
                     if state.next == '/'{
-                        while state.next != '\n' && state.next != '\0'{
+                        chunk.push(state.next);
+                        state.advance();
+                        // /// and //! are doc comments, //// is a plain comment again
+                        let is_doc = state.next == '!' || state.next == '/' && state.peek_next() != '/';
+                        while state.next != '\0'{
                             chunk.push(state.next);
                             state.advance();
                         }
-                        color = self.col_comment;
+                        token_type = if is_doc{TokenType::DocComment}else{TokenType::Comment};
+                    }
+                    else if state.next == '*'{
+                        chunk.push(state.next);
+                        state.advance();
+                        // same for /** and /*!, but /*** and /**/ are not
+                        let is_doc = state.next == '!' || state.next == '*' && state.peek_next() != '*' && state.peek_next() != '/';
+                        let mut depth = 1;
+                        if !Self::parse_rust_block_comment_tail(&mut state, &mut chunk, &mut depth){
+                            *rust_state = RustTokenizerState::BlockComment{depth:depth, is_doc:is_doc};
+                        }
+                        token_type = if is_doc{TokenType::DocComment}else{TokenType::Comment};
                     }
                     else{
                         if state.next == '='{
                             chunk.push(state.next);
                             state.advance();
                         }
-                        color = self.col_operator;
+                        token_type = TokenType::Operator;
                     }
                 },
                 '\''=>{ // parse char literal or lifetime annotation
-
                     after_newline = false;
                     chunk.push(state.cur);
-
-                    if Self::parse_rust_escape_char(&mut state, &mut chunk){ // escape char or unicode
-                        if state.next == '\''{ // parsed to closing '
-                            chunk.push(state.next);
-                            state.advance();
-                            color = self.col_string;
-                        }
-                        else{
-                            color = self.col_comment;
-                        }
-                    }
-                    else{ // parse a single char or lifetime
-                        let offset = state.offset;
-                        if Self::parse_rust_ident_tail(&mut state, &mut chunk) && ((state.offset - offset) > 1 || state.next != '\''){
-                            color = self.col_keyword;
-                        }
-                        else if state.next != '\n'{
-                            if (state.offset - offset) == 0{ // not an identifier char
-                                chunk.push(state.next);
-                                state.advance();
-                            }
-                            if state.next == '\''{ // lifetime identifier
-                                chunk.push(state.next);
-                                state.advance();
-                            }
-                            color = self.col_string;
-                        }
-                        else{
-                            color = self.col_string;                            
-                        }
-                    }
+                    token_type = Self::parse_rust_char_tail(&mut state, &mut chunk);
                 },
                 '"'=>{ // parse string
                     after_newline = false;
                     chunk.push(state.cur);
-                    state.prev = '\0';
-                    while state.next != '\0' && state.next!='\n' && (state.next != '"' || state.prev != '\\' && state.cur == '\\' && state.next == '"'){
-                        chunk.push(state.next);
-                        state.advance_with_prev();
-                    };
-                    chunk.push(state.next);
-                    state.advance();
-                    color = self.col_string;
+                    if !Self::parse_rust_string_tail(&mut state, &mut chunk){
+                        *rust_state = RustTokenizerState::String;
+                    }
+                    token_type = TokenType::String;
                 },
                 '0'...'9'=>{ // try to parse numbers
                     after_newline = false;
-                    color = self.col_number;
+                    token_type = TokenType::Number;
                     chunk.push(state.cur);
                     Self::parse_rust_number_tail(&mut state, &mut chunk);
                 },
@@ -209,7 +126,7 @@ impl RustEditor{
                         chunk.push(state.next);
                         state.advance();
                     }
-                    color = self.col_operator;
+                    token_type = TokenType::Operator;
                 },
                 '*'=>{
                     after_newline = false;
@@ -217,8 +134,8 @@ impl RustEditor{
                     if state.next == '='{
                         chunk.push(state.next);
                         state.advance();
-                    }                    
-                    color = self.col_operator;
+                    }
+                    token_type = TokenType::Operator;
                 },
                 '+'=>{
                     after_newline = false;
@@ -227,7 +144,7 @@ impl RustEditor{
                         chunk.push(state.next);
                         state.advance();
                     }
-                    color = self.col_operator;
+                    token_type = TokenType::Operator;
                 },
                 '-'=>{
                     after_newline = false;
@@ -236,7 +153,7 @@ impl RustEditor{
                         chunk.push(state.next);
                         state.advance();
                     }
-                    color = self.col_operator;
+                    token_type = TokenType::Operator;
                 },
                 '='=>{
                     after_newline = false;
@@ -245,7 +162,7 @@ impl RustEditor{
                         chunk.push(state.next);
                         state.advance();
                     }
-                    color = self.col_operator;
+                    token_type = TokenType::Operator;
                 },
                 '.'=>{
                     after_newline = false;
@@ -254,28 +171,73 @@ impl RustEditor{
                         chunk.push(state.next);
                         state.advance();
                     }
-                    color = self.col_operator;
+                    token_type = TokenType::Operator;
                 },
                 '(' | ')'=>{
                     after_newline = false;
                     chunk.push(state.cur);
-                    color = self.col_paren;
+                    token_type = TokenType::Paren;
                 },
                 '{' | '}'=>{
                     after_newline = false;
                     chunk.push(state.cur);
-                    color = self.col_paren;
+                    token_type = TokenType::Paren;
                 },
                 '[' | ']'=>{
                     after_newline = false;
                     chunk.push(state.cur);
-                    color = self.col_paren;
+                    token_type = TokenType::Paren;
                 },
                 '_'=>{
                     after_newline = false;
                     chunk.push(state.cur);
                     Self::parse_rust_ident_tail(&mut state, &mut chunk);
-                    color = self.col_identifier;
+                    token_type = TokenType::Identifier;
+                },
+                'b' | 'c' | 'r' if Self::next_is_rust_literal(&state)=>{ // byte, c and raw strings, byte chars
+                    after_newline = false;
+                    chunk.push(state.cur);
+                    if state.cur != 'r' && state.next == 'r'{
+                        chunk.push(state.next);
+                        state.advance();
+                    }
+                    if state.next == '\''{
+                        chunk.push(state.next);
+                        state.advance();
+                        token_type = Self::parse_rust_char_tail(&mut state, &mut chunk);
+                    }
+                    else if chunk[chunk.len() - 1] == 'r'{
+                        let mut hashes = 0;
+                        while state.next == '#'{
+                            chunk.push(state.next);
+                            state.advance();
+                            hashes += 1;
+                        }
+                        if state.next == '"'{
+                            chunk.push(state.next);
+                            state.advance();
+                            if !Self::parse_rust_raw_string_tail(&mut state, &mut chunk, hashes){
+                                *rust_state = RustTokenizerState::RawString{hashes:hashes};
+                            }
+                        }
+                        token_type = TokenType::String;
+                    }
+                    else{
+                        chunk.push(state.next);
+                        state.advance();
+                        if !Self::parse_rust_string_tail(&mut state, &mut chunk){
+                            *rust_state = RustTokenizerState::String;
+                        }
+                        token_type = TokenType::String;
+                    }
+                },
+                'r' if state.next == '#' && (state.peek_next().is_ascii_alphabetic() || state.peek_next() == '_')=>{ // raw identifier
+                    after_newline = false;
+                    chunk.push(state.cur);
+                    chunk.push(state.next);
+                    state.advance();
+                    Self::parse_rust_ident_tail(&mut state, &mut chunk);
+                    token_type = TokenType::Identifier;
                 },
                 'a'...'z'=>{ // try to parse keywords or identifiers
                     after_newline = false;
@@ -287,17 +249,17 @@ impl RustEditor{
                     }
                     match keyword_type{
                         KeywordType::Normal=>{
-                            color = self.col_keyword;
+                            token_type = TokenType::Keyword;
                         },
                         KeywordType::Flow=>{
-                            color = self.col_flow_keyword;
+                            token_type = TokenType::Flow;
                         },
                         KeywordType::None=>{
                             if state.next == '(' || state.next == '!'{
-                                color = self.col_function;
+                                token_type = TokenType::Call;
                             }
                             else{
-                                color = self.col_identifier;
+                                token_type = TokenType::Identifier;
                             }
                         }
                     }
@@ -315,27 +277,78 @@ impl RustEditor{
                         is_keyword = false;
                     }
                     if is_keyword{
-                        color = self.col_keyword;
+                        token_type = TokenType::Keyword;
                     }
                     else{
-                        color = self.col_type;
+                        token_type = TokenType::TypeName;
                     }
                 },
                 _=>{
                     after_newline = false;
                     chunk.push(state.cur);
                     // unknown type
-                    color = self.col_identifier;
+                    token_type = TokenType::Identifier;
                 }
             }
-            self.code_editor.draw_text(cx, &chunk, state.offset, is_whitespace, color);
+            tokens.push(Token{
+                len:chunk.len(),
+                token_type:token_type
+            });
             chunk.truncate(0);
-            if do_newline{
-                self.code_editor.new_line(cx);
-            }
         }
-        
-        self.code_editor.end_code_editor(cx, text_buffer);
+    }
+}
+
+impl LanguageMode for RustTokenizer{
+    fn get_syntax(&self)->LanguageSyntax{
+        LanguageSyntax{
+            name:"Rust",
+            line_comment:Some("//"),
+            block_comment:Some(("/*", "*/")),
+            bracket_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            indent_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            auto_close_pairs:&[('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')],
+            outline_keywords:&["fn", "struct", "enum", "impl", "trait", "mod"]
+        }
+    }
+}
+
+impl RustTokenizer{
+
+    // lexes the rest of a comment or string carried over from the line above
+    fn continue_rust_token<'a>(rust_state:&mut RustTokenizerState, state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->Option<TokenType>{
+        let (token_type, is_closed) = match rust_state{
+            RustTokenizerState::Code=>{
+                return None
+            },
+            RustTokenizerState::BlockComment{depth, is_doc}=>(
+                if *is_doc{TokenType::DocComment}else{TokenType::Comment},
+                Self::parse_rust_block_comment_tail(state, chunk, depth)
+            ),
+            RustTokenizerState::String=>(
+                TokenType::String,
+                Self::parse_rust_string_tail(state, chunk)
+            ),
+            RustTokenizerState::RawString{hashes}=>(
+                TokenType::String,
+                Self::parse_rust_raw_string_tail(state, chunk, *hashes)
+            )
+        };
+        if is_closed{
+            *rust_state = RustTokenizerState::Code;
+        }
+        Some(token_type)
+    }
+
+    // b" b' br" c" cr" r" and r#" all start a literal instead of an identifier
+    fn next_is_rust_literal<'a>(state:&TokenizerState<'a>)->bool{
+        let raw_next = state.next == 'r' && (state.peek_next() == '"' || state.peek_next() == '#');
+        match state.cur{
+            'b'=>state.next == '"' || state.next == '\'' || raw_next,
+            'c'=>state.next == '"' || raw_next,
+            'r'=>state.next == '"' || state.next == '#' && (state.peek_next() == '"' || state.peek_next() == '#'),
+            _=>false
+        }
     }
 
     fn parse_rust_ident_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->bool{
@@ -348,6 +361,110 @@ impl RustEditor{
         ret
     }
 
+    // returns true when the comment got closed on this line
+    fn parse_rust_block_comment_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>, depth:&mut usize)->bool{
+        while state.next != '\0'{
+            if state.next == '*'{
+                chunk.push(state.next);
+                state.advance();
+                if state.next == '/'{
+                    chunk.push(state.next);
+                    state.advance();
+                    *depth -= 1;
+                    if *depth == 0{
+                        return true
+                    }
+                }
+            }
+            else if state.next == '/'{
+                chunk.push(state.next);
+                state.advance();
+                if state.next == '*'{
+                    chunk.push(state.next);
+                    state.advance();
+                    *depth += 1;
+                }
+            }
+            else{
+                chunk.push(state.next);
+                state.advance();
+            }
+        }
+        false
+    }
+
+    // returns true when the closing quote is on this line
+    fn parse_rust_string_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->bool{
+        while state.next != '\0'{
+            if state.next == '\\'{
+                chunk.push(state.next);
+                state.advance();
+                // a \ at the end of the line just continues the string
+                if state.next != '\0'{
+                    chunk.push(state.next);
+                    state.advance();
+                }
+            }
+            else if state.next == '"'{
+                chunk.push(state.next);
+                state.advance();
+                return true
+            }
+            else{
+                chunk.push(state.next);
+                state.advance();
+            }
+        }
+        false
+    }
+
+    // raw strings have no escapes, they end at a quote followed by the same number of #
+    fn parse_rust_raw_string_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>, hashes:usize)->bool{
+        while state.next != '\0'{
+            let is_quote = state.next == '"';
+            chunk.push(state.next);
+            state.advance();
+            if is_quote{
+                let mut count = 0;
+                while count < hashes && state.next == '#'{
+                    chunk.push(state.next);
+                    state.advance();
+                    count += 1;
+                }
+                if count == hashes{
+                    return true
+                }
+            }
+        }
+        false
+    }
+
+    // after the opening ', tells a char literal from a lifetime or label
+    fn parse_rust_char_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->TokenType{
+        if Self::parse_rust_escape_char(state, chunk){ // escape char or unicode
+            if state.next == '\''{ // parsed to closing '
+                chunk.push(state.next);
+                state.advance();
+            }
+            return TokenType::String
+        }
+        let offset = state.offset;
+        if Self::parse_rust_ident_tail(state, chunk) && ((state.offset - offset) > 1 || state.next != '\''){
+            return TokenType::Keyword
+        }
+        if state.next != '\0'{
+            if (state.offset - offset) == 0{ // not an identifier char
+                chunk.push(state.next);
+                state.advance();
+            }
+            if state.next == '\''{
+                chunk.push(state.next);
+                state.advance();
+            }
+        }
+        TokenType::String
+    }
+
     fn parse_rust_escape_char<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->bool{
         if state.next == '\\'{
             chunk.push(state.next);
@@ -368,7 +485,17 @@ impl RustEditor{
                     }
                 }
             }
-            else{
+            else if state.next == 'x'{ // \x7f
+                chunk.push(state.next);
+                state.advance();
+                for _ in 0..2{
+                    if state.next_is_hex(){
+                        chunk.push(state.next);
+                        state.advance();
+                    }
+                }
+            }
+            else if state.next != '\0'{
                 // its a single char escape TODO limit this to valid escape chars
                 chunk.push(state.next);
                 state.advance();
@@ -379,7 +506,7 @@ impl RustEditor{
     }
 
     fn parse_rust_number_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>){
-        if state.next == 'x'{ // parse a hex number
+        if state.cur == '0' && (state.next == 'x' || state.next == 'o' || state.next == 'b'){ // hex, octal or binary
             chunk.push(state.next);
             state.advance();
             while state.next_is_hex() || state.next == '_'{
@@ -387,49 +514,37 @@ impl RustEditor{
                 state.advance();
             }
         }
-        else if state.next == 'b'{ // parse a binary
-            chunk.push(state.next);
-            state.advance();
-            while state.next == '0' || state.next =='1' || state.next == '_'{
-                chunk.push(state.next);
-                state.advance();
-            }
-        }
         else{
             while state.next_is_digit() || state.next == '_'{
                 chunk.push(state.next);
                 state.advance();
             }
-            if state.next == 'u' || state.next == 'i'{
+            // a fraction, but not a range like 1..2 or a method call like 1.max(2)
+            let after_dot = state.peek_next();
+            if state.next == '.' && after_dot != '.' && after_dot != '_' && !after_dot.is_alphabetic(){
                 chunk.push(state.next);
                 state.advance();
-                if state.keyword(chunk, "8"){
-                }
-                else if state.keyword(chunk, "16"){
-                }
-                else if state.keyword(chunk,"32"){
-                }
-                else if state.keyword(chunk,"64"){
+                while state.next_is_digit() || state.next == '_'{
+                    chunk.push(state.next);
+                    state.advance();
                 }
             }
-            else if state.next == '.'{
+            let after_e = state.peek_next();
+            if (state.next == 'e' || state.next == 'E') && (after_e >= '0' && after_e <= '9' || after_e == '+' || after_e == '-'){
                 chunk.push(state.next);
                 state.advance();
-                // again eat as many numbers as possible
-                while state.next_is_digit() || state.next == '_'{
+                if state.next == '+' || state.next == '-'{
                     chunk.push(state.next);
                     state.advance();
                 }
-                if state.next == 'f' { // the f32, f64 postfix
+                while state.next_is_digit() || state.next == '_'{
                     chunk.push(state.next);
                     state.advance();
-                    if state.keyword(chunk,"32"){
-                    }
-                    else if state.keyword(chunk,"64"){
-                    }
                 }
             }
         }
+        // type suffixes like u8, usize and f32
+        Self::parse_rust_ident_tail(state, chunk);
     }
 
     fn parse_rust_lc_keyword<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>)->KeywordType{
@@ -550,7 +665,7 @@ impl RustEditor{
                     return KeywordType::Normal
                 }
                 else if state.keyword(chunk,"r"){
-                    if state.keyword(chunk,"rait"){
+                    if state.keyword(chunk,"ait"){
                         return KeywordType::Normal
                     }
                     else if state.keyword(chunk,"ue"){
@@ -586,4 +701,4 @@ enum KeywordType{
     None,
     Normal,
     Flow,
-}
\ No newline at end of file
+}
@@ -0,0 +1,219 @@
+use crate::tokenizer::*;
+use crate::textlines::*;
+use crate::languagemode::*;
+
+// highlights GLSL and Metal shading language, they share most of their C syntax
+#[derive(Clone, Default)]
+pub struct ShaderTokenizer{
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ShaderTokenizerState{
+    Code,
+    BlockComment
+}
+
+impl Default for ShaderTokenizerState{
+    fn default()->Self{
+        ShaderTokenizerState::Code
+    }
+}
+
+impl LineTokenizer for ShaderTokenizer{
+    type State = ShaderTokenizerState;
+
+    fn tokenize_line(&self, shader_state:&mut ShaderTokenizerState, line:Line, tokens:&mut Vec<Token>){
+        let mut chunk = Vec::new();
+        let mut state = TokenizerState::new(line);
+
+        if *shader_state == ShaderTokenizerState::BlockComment{
+            if state.eat_until(&mut chunk, "*/"){
+                *shader_state = ShaderTokenizerState::Code;
+            }
+            if chunk.len() > 0{
+                tokens.push(Token{
+                    len:chunk.len(),
+                    token_type:TokenType::Comment
+                });
+                chunk.truncate(0);
+            }
+        }
+
+        loop{
+            let token_type;
+            state.advance_with_cur();
+
+            match state.cur{
+                '\0'=>{ // end of line
+                    break;
+                },
+                ' ' | '\t'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c == ' ' || c == '\t');
+                    token_type = TokenType::Whitespace;
+                },
+                '/' if state.next == '/'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |_| true);
+                    token_type = TokenType::Comment;
+                },
+                '/' if state.next == '*'=>{
+                    chunk.push(state.cur);
+                    chunk.push(state.next);
+                    state.advance();
+                    if !state.eat_until(&mut chunk, "*/"){
+                        *shader_state = ShaderTokenizerState::BlockComment;
+                    }
+                    token_type = TokenType::Comment;
+                },
+                '#'=>{ // preprocessor directive
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c == ' ' || c == '\t');
+                    state.eat_while(&mut chunk, |c| c.is_ascii_alphabetic());
+                    token_type = TokenType::Keyword;
+                },
+                '"'=>{ // only really shows up in #include
+                    chunk.push(state.cur);
+                    state.eat_string_tail(&mut chunk, '"');
+                    token_type = TokenType::String;
+                },
+                '.' if state.next_is_digit()=>{
+                    chunk.push(state.cur);
+                    Self::parse_shader_number_tail(&mut state, &mut chunk);
+                    token_type = TokenType::Number;
+                },
+                '0'..='9'=>{
+                    chunk.push(state.cur);
+                    Self::parse_shader_number_tail(&mut state, &mut chunk);
+                    token_type = TokenType::Number;
+                },
+                '(' | ')' | '[' | ']' | '{' | '}'=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Paren;
+                },
+                ';' | ','=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Delimiter;
+                },
+                'a'..='z' | 'A'..='Z' | '_'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c.is_ascii_alphanumeric() || c == '_');
+                    let word:String = chunk.iter().collect();
+                    token_type = if Self::is_shader_flow_keyword(&word){
+                        TokenType::Flow
+                    }
+                    else if Self::is_shader_keyword(&word){
+                        TokenType::Keyword
+                    }
+                    else if Self::is_shader_type(&word){
+                        TokenType::TypeName
+                    }
+                    else if state.next == '('{
+                        TokenType::Call
+                    }
+                    else{
+                        TokenType::Identifier
+                    };
+                },
+                _=>{
+                    chunk.push(state.cur);
+                    if Self::is_shader_operator(state.cur){
+                        state.eat_while(&mut chunk, |c| Self::is_shader_operator(c) && c != '/' && c != '.');
+                        token_type = TokenType::Operator;
+                    }
+                    else{
+                        token_type = TokenType::Identifier;
+                    }
+                }
+            }
+            tokens.push(Token{
+                len:chunk.len(),
+                token_type:token_type
+            });
+            chunk.truncate(0);
+        }
+    }
+}
+
+impl ShaderTokenizer{
+    fn parse_shader_number_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>){
+        if chunk[0] == '0' && (state.next == 'x' || state.next == 'X'){
+            chunk.push(state.next);
+            state.advance();
+            state.eat_while(chunk, |c| c.is_ascii_hexdigit());
+        }
+        else{
+            state.eat_while(chunk, |c| c.is_ascii_digit() || c == '.');
+            let after_e = state.peek_next();
+            if (state.next == 'e' || state.next == 'E') && (after_e.is_ascii_digit() || after_e == '+' || after_e == '-'){
+                chunk.push(state.next);
+                state.advance();
+                chunk.push(state.next);
+                state.advance();
+                state.eat_while(chunk, |c| c.is_ascii_digit());
+            }
+        }
+        // float, unsigned and half suffixes
+        state.eat_while(chunk, |c| c == 'f' || c == 'F' || c == 'u' || c == 'U' || c == 'h' || c == 'H' || c == 'l' || c == 'L');
+    }
+
+    fn is_shader_operator(c:char)->bool{
+        match c{
+            '+' | '-' | '*' | '/' | '%' | '=' | '<' | '>' | '!' | '&' | '|' | '^' | '~' | '?' | ':' | '.'=>true,
+            _=>false
+        }
+    }
+
+    fn is_shader_flow_keyword(word:&str)->bool{
+        match word{
+            "if" | "else" | "for" | "while" | "do" | "return" | "break" | "continue" | "discard" |
+            "discard_fragment" | "switch" | "case" | "default"=>true,
+            _=>false
+        }
+    }
+
+    fn is_shader_keyword(word:&str)->bool{
+        match word{
+            // glsl
+            "uniform" | "attribute" | "varying" | "in" | "out" | "inout" | "const" | "precision" |
+            "highp" | "mediump" | "lowp" | "layout" | "struct" | "flat" | "smooth" | "noperspective" |
+            "invariant" | "centroid" | "true" | "false" |
+            // metal
+            "kernel" | "vertex" | "fragment" | "device" | "constant" | "thread" | "threadgroup" |
+            "using" | "namespace" | "template" | "typedef" | "static" | "inline" | "constexpr" |
+            "typename" | "metal"=>true,
+            _=>false
+        }
+    }
+
+    fn is_shader_type(word:&str)->bool{
+        match word{
+            "sampler2D" | "sampler3D" | "samplerCube" | "sampler" | "texture2d" | "texture3d" |
+            "texturecube" | "depth2d" | "size_t"=>return true,
+            _=>()
+        }
+        // vec4, ivec2, mat4x4, float3, half4, float4x4 and friends
+        let base = word.trim_end_matches(|c:char| c.is_ascii_digit() || c == 'x');
+        let is_vector = base.len() < word.len() && word.chars().last().map_or(false, |c| c.is_ascii_digit());
+        match base{
+            "void" | "bool" | "int" | "uint" | "float" | "double" | "half" | "short" | "ushort" |
+            "char" | "uchar"=>true,
+            "vec" | "ivec" | "uvec" | "bvec" | "dvec" | "mat" | "dmat"=>is_vector,
+            _=>false
+        }
+    }
+}
+
+impl LanguageMode for ShaderTokenizer{
+    fn get_syntax(&self)->LanguageSyntax{
+        LanguageSyntax{
+            name:"Shader",
+            line_comment:Some("//"),
+            block_comment:Some(("/*", "*/")),
+            bracket_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            indent_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            auto_close_pairs:&[('(', ')'), ('[', ']'), ('{', '}')],
+            outline_keywords:&["struct"]
+        }
+    }
+}
@@ -1,20 +1,36 @@
-//use widgets::*;
+use widgets::*;
+use crate::textlines::*;
+use crate::languagemode::*;
 
 #[derive(Clone, Default)]
-pub struct TextBuffer{
-    // Vec<Vec<char>> was chosen because, for all practical use (code) most lines are short
-    // Concatenating the total into a utf8 string is trivial, and O(1) for windowing into the lines is handy.
-    // Also inserting lines is pretty cheap even approaching 100k lines.
-    // If you want to load a 100 meg single line file or something with >100k lines
-    // other options are better. But these are not usecases for this editor.
-    pub lines: Vec<Vec<char>>,
+pub struct TextBuffer<L=RopeLines>{
+    // Lines are still Vec<char> because for code most lines are short, and O(1) windowing into
+    // a line is handy. They are stored in blocks (RopeLines) with a cached line start index so
+    // 100k+ line files stay interactive, FlatLines is the old single Vec<Vec<char>> storage.
+    pub lines: L,
     pub undo_stack: Vec<TextUndo>,
     pub redo_stack: Vec<TextUndo>,
     pub load_id: u64,
-    pub _char_count: usize
+    pub save_id: u64,
+    pub is_crlf: bool,
+    // the undo id that was on top of the undo stack when we last saved
+    pub clean_undo_id: u64,
+    pub _save_undo_id: u64,
+    pub _undo_id_alloc: u64,
+    // rows touched by edits, so token caches only have to re-lex what changed
+    pub _line_edits: Vec<LineEdit>,
+    pub _line_edits_base: u64
 }
 
+// an edit swapped out old_rows lines at row for new_rows lines
 #[derive(Clone, Copy)]
+pub struct LineEdit{
+    pub row:usize,
+    pub old_rows:usize,
+    pub new_rows:usize
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct TextPos{
     pub row:usize,
     pub col:usize
@@ -65,7 +81,8 @@ impl TextUndoGrouping{
 pub struct TextUndo{
     ops:Vec<TextOp>,
     grouping:TextUndoGrouping,
-    cursors:CursorSet
+    cursors:CursorSet,
+    id:u64
 }
 
 #[derive(Clone)]
@@ -75,162 +92,121 @@ pub struct TextOp{
     lines:Vec<Vec<char>>,
 }
 
-impl TextBuffer{
+impl<L> TextBuffer<L>
+where L: TextLines
+{
 
     pub fn offset_to_text_pos(&self, char_offset:usize)->TextPos{
-        let mut char_count = 0;
-        for (row,line) in self.lines.iter().enumerate(){
-            let next_char_count = char_count + line.len() + 1;
-            if next_char_count > char_offset{
-                return TextPos{row:row, col:char_offset - char_count}
-            }
-            char_count = next_char_count;
-        }
-        TextPos{row:0, col:0}
+        self.lines.offset_to_text_pos(char_offset)
     }
 
     pub fn offset_len_to_text_pos(&self, char_offset:usize, len:usize)->(TextPos, TextPos){
-        let mut char_count = 0;
-        let mut start_row = 0;
-        let mut start_col = 0;
-        let mut begin_found = false;
-        for (row,line) in self.lines.iter().enumerate(){
-            let next_char_count = char_count + line.len() + 1;
-            if !begin_found && next_char_count > char_offset{
-                start_row = row;
-                start_col = char_offset - char_count;
-                begin_found = true;
-            }
-            if begin_found && next_char_count > char_offset + len{
-                return (
-                    TextPos{row:start_row, col:start_col}, 
-                    TextPos{row:row, col: (char_offset + len) - char_count}
-                );
-            }
-            char_count = next_char_count;
+        if char_offset + len > self.lines.get_char_count(){
+            return (TextPos{row:0, col:0},TextPos{row:0, col:0})
         }
-        (TextPos{row:0, col:0},TextPos{row:0, col:0})
+        (
+            self.lines.offset_to_text_pos(char_offset),
+            self.lines.offset_to_text_pos(char_offset + len)
+        )
     }
 
-
     pub fn text_pos_to_offset(&self, pos:TextPos)->usize{
-        let mut char_count = 0;
-        if pos.row >= self.lines.len(){
-            return self._char_count
-        }
-        for (ln_row, line) in self.lines.iter().enumerate(){
-            if ln_row == pos.row{
-                return char_count + line.len().min(pos.col);
-            }
-            char_count += line.len() + 1;
-        }
-        0
+        self.lines.text_pos_to_offset(pos)
     }
 
     pub fn get_nearest_line_range(&self, offset:usize)->(usize, usize){
         let pos = self.offset_to_text_pos(offset);
-        let line = &self.lines[pos.row];
-        return (offset - pos.col, line.len() + if pos.row < line.len()-1{1}else{0})
+        let line = self.lines.get_line(pos.row);
+        // the newline belongs to the line, except on the last one
+        return (offset - pos.col, line.len() + if pos.row + 1 < self.get_line_count(){1}else{0})
     }
 
     pub fn get_char_count(&self)->usize{
-        self._char_count
+        self.lines.get_char_count()
     }
 
     pub fn get_line_count(&self)->usize{
-        self.lines.len()
+        self.lines.get_line_count()
+    }
+
+    pub fn get_line(&self, row:usize)->Line<'_>{
+        self.lines.get_line(row)
     }
 
-    fn get_range_as_string(&self, start:usize, len:usize, ret:&mut String){
+    pub fn get_range_as_string(&self, start:usize, len:usize, ret:&mut String){
         let mut pos = self.offset_to_text_pos(start);
-        for _ in 0..len{
-            let line = &self.lines[pos.row];
-            if pos.col >= line.len(){
-                ret.push('\n');
-                pos.col = 0;
-                pos.row += 1;
-                if pos.row >= self.lines.len(){
-                    return;
-                }
+        let mut left = len;
+        while left > 0{
+            let line = self.lines.get_line(pos.row);
+            let col = pos.col.min(line.len());
+            let take = (line.len() - col).min(left);
+            ret.extend(line.slice(col..col + take).iter());
+            left -= take;
+            if left == 0{
+                return;
             }
-            else{
-                ret.push(line[pos.col]);
-                pos.col += 1;
+            ret.push('\n');
+            left -= 1;
+            pos.col = 0;
+            pos.row += 1;
+            if pos.row >= self.lines.get_line_count(){
+                return;
             }
-        };
+        }
     }
 
     fn replace_range(&mut self, start:usize, len:usize, mut rep_lines:Vec<Vec<char>>)->Vec<Vec<char>>{
 
         let (start_pos, end_pos) = self.offset_len_to_text_pos(start, len);
 
+        self.push_line_edit(LineEdit{
+            row:start_pos.row,
+            old_rows:end_pos.row - start_pos.row + 1,
+            new_rows:rep_lines.len()
+        });
+
         if start_pos.row == end_pos.row && rep_lines.len() == 1{ // replace in one line
             let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-            let line = self.lines[start_pos.row].splice(start_pos.col..end_pos.col, rep_line_zero).collect();
+            let line = self.lines.splice_line(start_pos.row, start_pos.col, end_pos.col, rep_line_zero);
             return vec![line];
         }
-        else{
-            if rep_lines.len() == 1{ // we are replacing multiple lines with one line
-                // drain first line
-                let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-
-                // replace it in the first
-                let first = self.lines[start_pos.row].splice(start_pos.col.., rep_line_zero).collect();
-
-                // collect the middle ones
-                let mut middle:Vec<Vec<char>> = self.lines.drain((start_pos.row+1)..(end_pos.row)).collect();
+        else{ // swap out whole lines, carrying over the untouched head of the first and tail of the last line
+            let head:Vec<char> = self.lines.get_line(start_pos.row).slice(0..start_pos.col).to_vec();
+            let tail:Vec<char> = self.lines.get_line(end_pos.row).slice(end_pos.col..).to_vec();
 
-                // cut out the last bit
-                let last:Vec<char> = self.lines[start_pos.row+1].drain(0..end_pos.col).collect();
-                
-                // last line bit
-                let mut last_line = self.lines.drain((start_pos.row+1)..(start_pos.row+2)).next().unwrap();
+            rep_lines[0].splice(0..0, head);
+            let rep_lines_last = rep_lines.len() - 1;
+            rep_lines[rep_lines_last].extend(tail);
 
-                // merge start_row+1 into start_row
-                self.lines[start_pos.row].append(&mut last_line);
+            let mut lines = self.lines.splice_lines(start_pos.row, end_pos.row + 1, rep_lines);
 
-                // concat it all together
-                middle.insert(0, first);
-                middle.push(last);
-
-                return middle
-            }
-            else if start_pos.row == end_pos.row{ // replacing single line with multiple lines
-                let mut last_bit:Vec<char> =  self.lines[start_pos.row].drain(end_pos.col..).collect();// but we have co drain end_col..
-
-                // replaced first line
-                let rep_lines_len = rep_lines.len();
-                let rep_line_first:Vec<char> = rep_lines.drain(0..1).next().unwrap();
-                let line = self.lines[start_pos.row].splice(start_pos.col.., rep_line_first).collect();
-
-                // splice in middle rest
-                let rep_line_mid = rep_lines.drain(0..(rep_lines.len()));
-                self.lines.splice( (start_pos.row+1)..(start_pos.row+1), rep_line_mid);
-                
-                // append last bit
-                self.lines[start_pos.row + rep_lines_len-1].append(&mut last_bit);
-
-                return vec![line];
-            }
-            else{ // replaceing multiple lines with multiple lines
-                // drain and replace last line
-                let rep_line_last = rep_lines.drain((rep_lines.len()-1)..(rep_lines.len())).next().unwrap();
-                let last = self.lines[end_pos.row].splice(..end_pos.col, rep_line_last).collect();
+            // cut the old lines down to what we actually replaced
+            let lines_last = lines.len() - 1;
+            lines[lines_last].truncate(end_pos.col);
+            lines[0].drain(0..start_pos.col);
+            return lines
+        }
+    }
 
-                // swap out middle lines and drain them
-                let rep_line_mid = rep_lines.drain(1..(rep_lines.len()));
-                let mut middle:Vec<Vec<char>> = self.lines.splice( (start_pos.row+1)..end_pos.row, rep_line_mid).collect();
+    fn push_line_edit(&mut self, line_edit:LineEdit){
+        // keep the log bounded, caches that fell behind just re-lex everything
+        if self._line_edits.len() >= 4096{
+            self._line_edits.drain(0..2048);
+            self._line_edits_base += 2048;
+        }
+        self._line_edits.push(line_edit);
+    }
 
-                // drain and replace first line
-                let rep_line_zero = rep_lines.drain(0..1).next().unwrap();
-                let first = self.lines[start_pos.row].splice(start_pos.col.., rep_line_zero).collect();
+    pub fn get_line_edit_version(&self)->u64{
+        self._line_edits_base + self._line_edits.len() as u64
+    }
 
-                // concat it all together
-                middle.insert(0, first);
-                middle.push(last);
-                return middle
-            }
+    // the edits made after version, None if they are no longer known
+    pub fn get_line_edits_since(&self, version:u64)->Option<&[LineEdit]>{
+        if version < self._line_edits_base || version > self.get_line_edit_version(){
+            return None
         }
+        Some(&self._line_edits[(version - self._line_edits_base) as usize..])
     }
 
     fn split_string_to_lines(string:&str)->Vec<Vec<char>>{
@@ -250,8 +226,6 @@ impl TextBuffer{
         let rep_lines = Self::split_string_to_lines(string);
         let rep_lines_chars = Self::compute_char_count(&rep_lines);
         let lines = self.replace_range(start, len, rep_lines);
-        // ok now we have to replace start, len with data
-        self._char_count = Self::compute_char_count(&self.lines);
         TextOp{
             start:start,
             len:rep_lines_chars,
@@ -262,7 +236,6 @@ impl TextBuffer{
     pub fn replace_with_textop(&mut self, text_op:TextOp)->TextOp{
         let rep_lines_chars = Self::compute_char_count(&text_op.lines);
         let lines = self.replace_range(text_op.start, text_op.len, text_op.lines);
-        self._char_count = Self::compute_char_count(&self.lines);
         TextOp{
             start:text_op.start,
             len:rep_lines_chars,
@@ -270,17 +243,86 @@ impl TextBuffer{
         }
     }
 
-    pub fn save_buffer(&mut self){
-        //let out = self.lines.join("\n");
+    pub fn get_as_string(&self)->String{
+        // put back the line endings the file came in with
+        let newline = if self.is_crlf{"\r\n"}else{"\n"};
+        let mut ret = String::new();
+        for row in 0..self.lines.get_line_count(){
+            if row > 0{
+                ret.push_str(newline);
+            }
+            ret.extend(self.lines.get_line(row).iter());
+        }
+        ret
+    }
+
+    pub fn save_buffer(&mut self, cx:&mut Cx, path:&str){
+        let data = self.get_as_string();
+        self.save_id = cx.write_file(path, data.as_bytes());
+        self._save_undo_id = self.get_undo_id();
+    }
+
+    // call this when the FileWriteEvent for save_id came back without error
+    pub fn mark_saved(&mut self){
+        self.save_id = 0;
+        self.clean_undo_id = self._save_undo_id;
+    }
+
+    pub fn get_undo_id(&self)->u64{
+        if let Some(text_undo) = self.undo_stack.last(){
+            text_undo.id
+        }
+        else{
+            0
+        }
+    }
+
+    pub fn is_dirty(&self)->bool{
+        self.get_undo_id() != self.clean_undo_id
+    }
+
+    pub fn push_undo(&mut self, ops:Vec<TextOp>, grouping:TextUndoGrouping, cursors:CursorSet){
+        self._undo_id_alloc += 1;
+        self.redo_stack.truncate(0);
+        self.undo_stack.push(TextUndo{
+            ops:ops,
+            grouping:grouping,
+            cursors:cursors,
+            id:self._undo_id_alloc
+        })
+    }
+
+    // folds the undo steps pushed since the stack was undo_len long into one, so an edit
+    // made of several steps like a change and the typing after it undoes at once
+    pub fn merge_undo_since(&mut self, undo_len:usize){
+        if self.undo_stack.len() <= undo_len{
+            return
+        }
+        let mut merged = self.undo_stack.remove(undo_len);
+        for text_undo in self.undo_stack.drain(undo_len..){
+            merged.ops.extend(text_undo.ops);
+            merged.id = text_undo.id;
+        }
+        merged.grouping = TextUndoGrouping::Block;
+        self.undo_stack.push(merged);
     }
 
     pub fn load_buffer(&mut self, data:&Vec<u8>){
         // alright we have to load it and split it on newlines
         if let Ok(utf8_data) = std::str::from_utf8(&data){
-            self.lines = Self::split_string_to_lines(&utf8_data.to_string());
+            // we edit with \n only, and remember to write \r\n back out
+            self.is_crlf = utf8_data.find("\r\n").is_some();
+            if self.is_crlf{
+                self.lines = L::from_lines(Self::split_string_to_lines(&utf8_data.replace("\r\n", "\n")));
+            }
+            else{
+                self.lines = L::from_lines(Self::split_string_to_lines(&utf8_data.to_string()));
+            }
+            // every line changed, move the version past anything a cache could have seen
+            self._line_edits_base = self.get_line_edit_version() + 1;
+            self._line_edits.truncate(0);
             // lets be lazy and redraw all
         }
-        self._char_count = Self::compute_char_count(&self.lines);
     }
 
     pub fn undoredo(&mut self, mut text_undo:TextUndo, cursor_set:&mut CursorSet)->TextUndo{
@@ -292,7 +334,8 @@ impl TextBuffer{
         let text_undo_inverse = TextUndo{
             ops:ops,
             grouping:text_undo.grouping,
-            cursors:cursor_set.clone()
+            cursors:cursor_set.clone(),
+            id:text_undo.id
         };
         cursor_set.set = text_undo.cursors.set.clone();
         cursor_set.last_cursor = text_undo.cursors.last_cursor;
@@ -314,6 +357,10 @@ impl TextBuffer{
             if self.undo_stack.last().unwrap().grouping != last_grouping && !first{
                 break
             }
+            // a group stops at the saved state, so undoing back to it clears dirty
+            if !first && self.undo_stack.last().unwrap().id == self.clean_undo_id{
+                break
+            }
             first = false;
             let text_undo = self.undo_stack.pop().unwrap();
             let wants_grouping = text_undo.grouping.wants_grouping();
@@ -343,8 +390,9 @@ impl TextBuffer{
             let wants_grouping = text_redo.grouping.wants_grouping();
             last_grouping = text_redo.grouping.clone();
             let text_undo = self.undoredo(text_redo, cursor_set);
+            let at_clean = text_undo.id == self.clean_undo_id;
             self.undo_stack.push(text_undo);
-            if !wants_grouping{
+            if !wants_grouping || at_clean{
                 break;
             }
         }
@@ -352,95 +400,6 @@ impl TextBuffer{
 
 }
 
-pub struct TokenizerState<'a>{
-    pub prev:char,
-    pub cur:char,
-    pub next:char,
-    pub text_buffer:&'a TextBuffer,
-    pub line_counter:usize,
-    pub offset:usize,
-    iter:std::slice::Iter<'a, char>
-}
-
-impl<'a> TokenizerState<'a>{
-    pub fn new(text_buffer:&'a TextBuffer)->Self{
-        let mut ret = Self{
-            text_buffer:text_buffer,
-            line_counter:0,
-            offset:0,
-            prev:'\0',
-            cur:'\0',
-            next:'\0',
-            iter:text_buffer.lines[0].iter()
-        };
-        ret.advance_with_cur();
-        ret
-    }
-
-    pub fn advance(&mut self){
-        if let Some(next) = self.iter.next(){
-            self.next = *next;
-            self.offset += 1;
-        }
-        else{
-            if self.line_counter < self.text_buffer.lines.len() - 1{
-                self.line_counter += 1;
-                self.offset += 1;
-                self.iter = self.text_buffer.lines[self.line_counter].iter();
-                self.next = '\n'
-            }
-            else{
-                self.offset += 1;
-                self.next = '\0'
-            }
-        }
-    }
-
-    pub fn next_is_digit(&self)->bool{
-        self.next >= '0' && self.next <='9'
-    }
-
-    pub fn next_is_letter(&self)->bool{
-        self.next >= 'a' && self.next <='z' || self.next >= 'A' && self.next <='Z'
-    }
-
-    pub fn next_is_lowercase_letter(&self)->bool{
-        self.next >= 'a' && self.next <='z' 
-    }
-
-    pub fn next_is_uppercase_letter(&self)->bool{
-        self.next >= 'A' && self.next <='Z' 
-    }
-
-    pub fn next_is_hex(&self)->bool{
-        self.next >= '0' && self.next <='9' || self.next >= 'a' && self.next <= 'f' || self.next >= 'A' && self.next <='F'
-    }
-
-    pub fn advance_with_cur(&mut self){
-        self.cur = self.next;
-        self.advance();
-    }
-
-    pub fn advance_with_prev(&mut self){
-        self.prev = self.cur;
-        self.cur = self.next;
-        self.advance();
-    }
-
-    pub fn keyword(&mut self, chunk:&mut Vec<char>, word:&str)->bool{
-        for m in word.chars(){
-            if m == self.next{ 
-                chunk.push(m);
-                self.advance();
-            }
-            else{
-                return false
-            }
-        }
-        return true
-    }
-}
-
 #[derive(Clone)]
 pub struct Cursor{
     pub head:usize,
@@ -496,7 +455,7 @@ impl Cursor{
         let pos = text_buffer.offset_to_text_pos(self.head);
 
         // alright lets walk the line from the left till its no longer 9 or 32
-        for (index,ch) in text_buffer.lines[pos.row].iter().enumerate(){
+        for (index,ch) in text_buffer.get_line(pos.row).iter().enumerate(){
             if *ch != '\t' && *ch != ' '{
                 self.head = text_buffer.text_pos_to_offset(TextPos{row:pos.row, col:index});
                 self.calc_max(text_buffer);
@@ -508,7 +467,7 @@ impl Cursor{
     pub fn move_end(&mut self, text_buffer:&TextBuffer){
         let pos = text_buffer.offset_to_text_pos(self.head);
         // alright lets walk the line from the left till its no longer 9 or 32
-        self.head = text_buffer.text_pos_to_offset(TextPos{row:pos.row, col:text_buffer.lines[pos.row].len()});
+        self.head = text_buffer.text_pos_to_offset(TextPos{row:pos.row, col:text_buffer.get_line(pos.row).len()});
         self.calc_max(text_buffer);
     }
 
@@ -555,6 +514,16 @@ impl Cursor{
     }
 }
 
+// what a smart edit does at one cursor, start..end is replaced with text
+// and the cursor ends up at tail..head counted in chars into that text
+pub struct CursorEdit{
+    pub start:usize,
+    pub end:usize,
+    pub text:String,
+    pub tail:usize,
+    pub head:usize
+}
+
 #[derive(Clone)]
 pub struct CursorSet{
     pub set:Vec<Cursor>,
@@ -571,9 +540,13 @@ impl CursorSet{
         }
     }
 
+    // multiple cursors are joined with newlines, so a paste can hand them back out
     pub fn get_all_as_string(&self, text_buffer:&TextBuffer)->String{
         let mut ret = String::new();
-        for cursor in &self.set{
+        for (index, cursor) in self.set.iter().enumerate(){
+            if index > 0{
+                ret.push('\n');
+            }
             let (start, end) = cursor.order();
             text_buffer.get_range_as_string(start, end-start, &mut ret);
         }
@@ -720,7 +693,7 @@ impl CursorSet{
         // lets start the cursor gen
         let mut offset = text_buffer.text_pos_to_offset(TextPos{row:top, col:0});
         for row in top..(bottom+1){
-            let line = &text_buffer.lines[row];
+            let line = text_buffer.get_line(row);
             if left < line.len(){
                 if start_pos.col < end_pos.col{
                     self.set.push(Cursor{
@@ -781,12 +754,21 @@ impl CursorSet{
             delta += cursor.collapse(start, end, op.len);
             ops.push(op);
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo{
-            ops:ops,
-            grouping:grouping,
-            cursors:cursors_clone
-        })
+        text_buffer.push_undo(ops, grouping, cursors_clone)
+    }
+
+    // replaces each cursor with its own line, lines past the last cursor are dropped
+    pub fn replace_lines(&mut self, lines:&[&str], text_buffer:&mut TextBuffer){
+        let mut delta:isize = 0; // rolling delta to displace cursors 
+        let mut ops = Vec::new();
+        let cursors_clone = self.clone();
+        for (cursor, line) in self.set.iter_mut().zip(lines){
+            let (start, end) = cursor.delta(delta);
+            let op = text_buffer.replace_with_string(start, end-start, line);
+            delta += cursor.collapse(start, end, op.len);
+            ops.push(op);
+        }
+        text_buffer.push_undo(ops, TextUndoGrouping::Block, cursors_clone)
     }
 
     pub fn delete(&mut self, text_buffer:&mut TextBuffer){
@@ -806,12 +788,7 @@ impl CursorSet{
                 delta += cursor.collapse(start, end, 0);
             }
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo{
-            ops:ops,
-            grouping:TextUndoGrouping::Delete,
-            cursors:cursors_clone
-        })
+        text_buffer.push_undo(ops, TextUndoGrouping::Delete, cursors_clone)
     }
 
     pub fn backspace(&mut self, text_buffer:&mut TextBuffer){
@@ -831,14 +808,502 @@ impl CursorSet{
                 delta += cursor.collapse(start, end, 0);
             }
         }
-        text_buffer.redo_stack.truncate(0);
-        text_buffer.undo_stack.push(TextUndo{
-            ops:ops,
-            grouping:TextUndoGrouping::Backspace,
-            cursors:cursors_clone
+        text_buffer.push_undo(ops, TextUndoGrouping::Backspace, cursors_clone)
+    }
+
+    // runs an edit at every cursor as one undo step, the closure gets the buffer and the cursor range
+    pub fn replace_each<F>(&mut self, grouping:TextUndoGrouping, text_buffer:&mut TextBuffer, mut edit_fn:F)
+    where F: FnMut(&TextBuffer, usize, usize)->CursorEdit
+    {
+        let mut delta:isize = 0; // rolling delta to displace cursors 
+        let mut last_end = 0;
+        let mut ops = Vec::new();
+        let cursors_clone = self.clone();
+        for cursor in &mut self.set{
+            let (start, end) = cursor.delta(delta);
+            let edit = edit_fn(text_buffer, start, end);
+            // an edit reaching back to the line start can't eat into the one before
+            let edit_start = edit.start.max(last_end);
+            let op = text_buffer.replace_with_string(edit_start, edit.end - edit_start, &edit.text);
+            delta += (op.len as isize) - ((edit.end - edit_start) as isize);
+            cursor.tail = edit_start + edit.tail;
+            cursor.head = edit_start + edit.head;
+            cursor.calc_max(text_buffer);
+            last_end = edit_start + op.len;
+            ops.push(op);
+        }
+        text_buffer.push_undo(ops, grouping, cursors_clone)
+    }
+
+    // types a char with the auto-indent and auto-closing pairs of the language
+    pub fn type_char(&mut self, ch:char, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer){
+        if ch == '\n'{
+            return self.insert_newline(syntax, text_buffer)
+        }
+        let grouping = if ch == ' '{TextUndoGrouping::Space}else{TextUndoGrouping::Character};
+        self.replace_each(grouping, text_buffer, |text_buffer, start, end|{
+            let pos = text_buffer.offset_to_text_pos(start);
+            let line = text_buffer.get_line(pos.row);
+            let prev = if pos.col > 0{Some(line[pos.col - 1])}else{None};
+            let next = if pos.col < line.len(){Some(line[pos.col])}else{None};
+            // step over the closer that is already there
+            if start == end && next == Some(ch) && syntax.is_auto_closer(ch){
+                return CursorEdit{start:start, end:start + 1, text:ch.to_string(), tail:1, head:1}
+            }
+            if let Some((open, close)) = syntax.get_auto_close_pair(ch){
+                if start != end{ // wrap the selection
+                    let mut text = String::new();
+                    text.push(open);
+                    text_buffer.get_range_as_string(start, end - start, &mut text);
+                    text.push(close);
+                    let len = end - start;
+                    return CursorEdit{start:start, end:end, text:text, tail:1, head:1 + len}
+                }
+                // only in front of whitespace or punctuation, and quotes not right after a word
+                let next_ok = if let Some(next) = next{next.is_whitespace() || ";:,.=)]}>".contains(next)}else{true};
+                let prev_ok = open != close || if let Some(prev) = prev{!prev.is_alphanumeric() && prev != open}else{true};
+                if next_ok && prev_ok{
+                    let text:String = [open, close].iter().collect();
+                    return CursorEdit{start:start, end:end, text:text, tail:1, head:1}
+                }
+            }
+            // a closer typed into the indentation lines up with the line of its opener
+            if start == end && pos.col > 0 && line.slice(0..pos.col).iter().all(|c| c.is_whitespace()){
+                if let Some((open, _)) = syntax.indent_pairs.iter().find(|(_, close)| *close == ch){
+                    if let Some(open_row) = Self::find_open_row(*open, ch, start, text_buffer){
+                        let mut text = Self::get_indent(text_buffer.get_line(open_row), usize::max_value());
+                        text.push(ch);
+                        let len = text.chars().count();
+                        return CursorEdit{start:start - pos.col, end:end, text:text, tail:len, head:len}
+                    }
+                }
+            }
+            CursorEdit{start:start, end:end, text:ch.to_string(), tail:1, head:1}
         })
     }
 
+    // the newline keeps the indentation, and adds a level after an opener
+    pub fn insert_newline(&mut self, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer){
+        self.replace_each(TextUndoGrouping::Newline, text_buffer, |text_buffer, start, end|{
+            let pos = text_buffer.offset_to_text_pos(start);
+            let line = text_buffer.get_line(pos.row);
+            let indent = Self::get_indent(line, pos.col);
+            if !syntax.opens_indent(line.slice(0..pos.col)){
+                let text = format!("\n{}", indent);
+                let len = text.chars().count();
+                return CursorEdit{start:start, end:end, text:text, tail:len, head:len}
+            }
+            let inner = format!("\n{}{}", indent, INDENT);
+            let len = inner.chars().count();
+            // between a pair the closer goes on a line of its own
+            let end_pos = text_buffer.offset_to_text_pos(end);
+            if syntax.closes_indent(text_buffer.get_line(end_pos.row).slice(end_pos.col..)){
+                return CursorEdit{start:start, end:end, text:format!("{}\n{}", inner, indent), tail:len, head:len}
+            }
+            CursorEdit{start:start, end:end, text:inner, tail:len, head:len}
+        })
+    }
+
+    // the leading whitespace of a line, up to col
+    fn get_indent(line:Line, col:usize)->String{
+        line.iter().take(col).take_while(|c| **c == ' ' || **c == '\t').collect()
+    }
+
+    // walks back from offset to the row of the unclosed open bracket
+    fn find_open_row(open:char, close:char, offset:usize, text_buffer:&TextBuffer)->Option<usize>{
+        let pos = text_buffer.offset_to_text_pos(offset);
+        let mut depth = 0;
+        for row in (0..=pos.row).rev(){
+            let line = text_buffer.get_line(row);
+            let col = if row == pos.row{pos.col}else{line.len()};
+            for c in line.slice(0..col).iter().rev(){
+                if *c == close{
+                    depth += 1;
+                }
+                else if *c == open{
+                    if depth == 0{
+                        return Some(row)
+                    }
+                    depth -= 1;
+                }
+            }
+        }
+        None
+    }
+
+    // true if a selection covers more than one row, tab indents the rows then
+    pub fn spans_rows(&self, text_buffer:&TextBuffer)->bool{
+        self.set.iter().any(|cursor|{
+            let (start, end) = cursor.order();
+            text_buffer.offset_to_text_pos(start).row != text_buffer.offset_to_text_pos(end).row
+        })
+    }
+
+    // pads every cursor to the next indent stop
+    pub fn insert_tab(&mut self, text_buffer:&mut TextBuffer){
+        self.replace_each(TextUndoGrouping::Space, text_buffer, |text_buffer, start, end|{
+            let col = text_buffer.offset_to_text_pos(start).col;
+            let len = INDENT.len() - col % INDENT.len();
+            CursorEdit{start:start, end:end, text:INDENT[0..len].to_string(), tail:len, head:len}
+        })
+    }
+
+    // the first and last row of a cursor, a selection ending at a row start leaves that row out
+    fn get_row_range(cursor:&Cursor, text_buffer:&TextBuffer)->(usize, usize){
+        let (start, end) = cursor.order();
+        let start_row = text_buffer.offset_to_text_pos(start).row;
+        let end_pos = text_buffer.offset_to_text_pos(end);
+        let end_row = if end_pos.col == 0 && end_pos.row > start_row{end_pos.row - 1}else{end_pos.row};
+        (start_row, end_row)
+    }
+
+    fn get_cursor_rows(&self, text_buffer:&TextBuffer)->Vec<usize>{
+        let mut rows = Vec::new();
+        for cursor in &self.set{
+            let (start_row, end_row) = Self::get_row_range(cursor, text_buffer);
+            for row in start_row..=end_row{
+                rows.push(row);
+            }
+        }
+        rows.sort();
+        rows.dedup();
+        rows
+    }
+
+    // indents or dedents every row a cursor is on by one level, as one undo step
+    pub fn indent_rows(&mut self, dedent:bool, text_buffer:&mut TextBuffer){
+        let rows = self.get_cursor_rows(text_buffer);
+        // (offset, removed, inserted) in the offsets from before the edit
+        let mut edits = Vec::new();
+        for row in &rows{
+            let line = text_buffer.get_line(*row);
+            let offset = text_buffer.text_pos_to_offset(TextPos{row:*row, col:0});
+            if dedent{
+                let remove = if line.first() == Some(&'\t'){1}else{
+                    line.iter().take(INDENT.len()).take_while(|c| **c == ' ').count()
+                };
+                if remove > 0{
+                    edits.push((offset, remove, 0));
+                }
+            }
+            else if line.len() > 0 || rows.len() == 1{ // empty rows in a block stay empty
+                edits.push((offset, 0, INDENT.len()));
+            }
+        }
+        if edits.len() == 0{
+            return
+        }
+        let cursors_clone = self.clone();
+        let mut ops = Vec::new();
+        // back to front so the offsets stay valid
+        for (offset, remove, insert) in edits.iter().rev(){
+            ops.push(text_buffer.replace_with_string(*offset, *remove, &INDENT[0..*insert]));
+        }
+        // a selection starting at a row start keeps the new indent inside it
+        let map_offset = |pos:usize, is_sel_start:bool|->usize{
+            let mut new_pos = pos as isize;
+            for (offset, remove, insert) in &edits{
+                if pos == *offset && is_sel_start{
+                    continue
+                }
+                if pos >= offset + remove{
+                    new_pos += (*insert as isize) - (*remove as isize);
+                }
+                else if pos > *offset{ // inside the removed whitespace
+                    new_pos -= (pos - offset) as isize;
+                }
+            }
+            new_pos as usize
+        };
+        for cursor in &mut self.set{
+            let (start, end) = cursor.order();
+            cursor.head = map_offset(cursor.head, start != end && cursor.head == start);
+            cursor.tail = map_offset(cursor.tail, start != end && cursor.tail == start);
+            cursor.calc_max(text_buffer);
+        }
+        text_buffer.push_undo(ops, TextUndoGrouping::Block, cursors_clone)
+    }
+
+    // rewrites the rows under the cursors block by block, as one undo step. touching blocks are merged,
+    // edit_fn gets the first and last row of a block and the head and tail positions in it, and
+    // returns the rows to replace with new lines after moving the positions to rows counted from
+    // the first replaced one
+    fn edit_rows<F>(&mut self, text_buffer:&mut TextBuffer, mut edit_fn:F)
+    where F: FnMut(&TextBuffer, usize, usize, &mut [TextPos])->Option<(usize, usize, Vec<String>)>
+    {
+        let mut blocks:Vec<(usize, usize, Vec<usize>)> = Vec::new();
+        for (index, cursor) in self.set.iter().enumerate(){
+            let (first, last) = Self::get_row_range(cursor, text_buffer);
+            if let Some(block) = blocks.last_mut(){
+                if first <= block.1 + 1{
+                    block.1 = block.1.max(last);
+                    block.2.push(index);
+                    continue
+                }
+            }
+            blocks.push((first, last, vec![index]));
+        }
+        // every head and tail as (first row of its region, position in the region)
+        let mut positions:Vec<(usize, TextPos)> = Vec::new();
+        for cursor in &self.set{
+            for offset in &[cursor.head, cursor.tail]{
+                let pos = text_buffer.offset_to_text_pos(*offset);
+                positions.push((pos.row, TextPos{row:0, col:pos.col}));
+            }
+        }
+        let mut edits = Vec::new();
+        for (first, last, indices) in blocks{
+            let mut block_pos:Vec<TextPos> = Vec::new();
+            for index in &indices{
+                for (row, pos) in &positions[index*2..index*2+2]{
+                    block_pos.push(TextPos{row:row + pos.row, col:pos.col});
+                }
+            }
+            if let Some((start_row, end_row, lines)) = edit_fn(text_buffer, first, last, &mut block_pos){
+                for (j, index) in indices.iter().enumerate(){
+                    positions[index*2] = (start_row, block_pos[j*2]);
+                    positions[index*2+1] = (start_row, block_pos[j*2+1]);
+                }
+                edits.push((start_row, end_row, lines));
+            }
+        }
+        if edits.len() == 0{
+            return
+        }
+        let cursors_clone = self.clone();
+        let mut ops = Vec::new();
+        // (end row, rows added) of every edit, to shift the rows below it
+        let mut row_deltas = Vec::new();
+        // back to front so the offsets stay valid
+        for (start_row, end_row, lines) in edits.iter().rev(){
+            let line_count = text_buffer.get_line_count();
+            let start = text_buffer.text_pos_to_offset(TextPos{row:*start_row, col:0});
+            let end = text_buffer.text_pos_to_offset(TextPos{row:*end_row, col:text_buffer.get_line(*end_row).len()});
+            let old_rows = (end_row - start_row + 1) as isize;
+            if lines.len() == 0 && end_row + 1 < line_count{ // removing rows takes their newline along
+                ops.push(text_buffer.replace_with_string(start, end + 1 - start, ""));
+                row_deltas.push((*end_row, -old_rows));
+            }
+            else if lines.len() == 0 && *start_row > 0{
+                ops.push(text_buffer.replace_with_string(start - 1, end + 1 - start, ""));
+                row_deltas.push((*end_row, -old_rows));
+            }
+            else{ // an emptied buffer keeps its one empty row
+                ops.push(text_buffer.replace_with_string(start, end - start, &lines.join("\n")));
+                row_deltas.push((*end_row, lines.len().max(1) as isize - old_rows));
+            }
+        }
+        let line_count = text_buffer.get_line_count();
+        let mut offsets = Vec::new();
+        for (start_row, pos) in &positions{
+            let mut row = (start_row + pos.row) as isize;
+            for (end_row, rows_added) in &row_deltas{
+                if end_row < start_row{
+                    row += rows_added;
+                }
+            }
+            let row = (row.max(0) as usize).min(line_count - 1);
+            let col = pos.col.min(text_buffer.get_line(row).len());
+            offsets.push(text_buffer.text_pos_to_offset(TextPos{row:row, col:col}));
+        }
+        for (index, cursor) in self.set.iter_mut().enumerate(){
+            cursor.head = offsets[index*2];
+            cursor.tail = offsets[index*2+1];
+            cursor.calc_max(text_buffer);
+        }
+        self.fuse_adjacent(text_buffer);
+        text_buffer.push_undo(ops, TextUndoGrouping::Block, cursors_clone)
+    }
+
+    fn get_rows_as_strings(text_buffer:&TextBuffer, first:usize, last:usize)->Vec<String>{
+        (first..=last).map(|row| text_buffer.get_line(row).iter().collect()).collect()
+    }
+
+    // duplicates selections within a row, or else the rows under the cursors
+    pub fn duplicate(&mut self, text_buffer:&mut TextBuffer){
+        let in_row = self.set.iter().all(|cursor| cursor.has_selection() && {
+            let (first, last) = Self::get_row_range(cursor, text_buffer);
+            first == last
+        });
+        if in_row{
+            return self.replace_each(TextUndoGrouping::Block, text_buffer, |text_buffer, start, end|{
+                let mut text = String::new();
+                text_buffer.get_range_as_string(start, end - start, &mut text);
+                CursorEdit{start:end, end:end, text:text, tail:0, head:end - start}
+            })
+        }
+        self.edit_rows(text_buffer, |text_buffer, first, last, positions|{
+            let mut lines = Self::get_rows_as_strings(text_buffer, first, last);
+            lines.extend(lines.clone());
+            // the cursors go along with the copy
+            for pos in positions{
+                pos.row = pos.row - first + (last - first + 1);
+            }
+            Some((first, last, lines))
+        })
+    }
+
+    // moves the rows under the cursors past the row above or below them
+    pub fn move_rows(&mut self, up:bool, text_buffer:&mut TextBuffer){
+        self.edit_rows(text_buffer, |text_buffer, first, last, positions|{
+            if up{
+                if first == 0{
+                    return None
+                }
+                let mut lines = Self::get_rows_as_strings(text_buffer, first, last);
+                lines.push(text_buffer.get_line(first - 1).iter().collect());
+                for pos in positions{
+                    pos.row -= first;
+                }
+                Some((first - 1, last, lines))
+            }
+            else{
+                if last + 1 >= text_buffer.get_line_count(){
+                    return None
+                }
+                let mut lines = vec![text_buffer.get_line(last + 1).iter().collect()];
+                lines.extend(Self::get_rows_as_strings(text_buffer, first, last));
+                for pos in positions{
+                    pos.row = pos.row - first + 1;
+                }
+                Some((first, last + 1, lines))
+            }
+        })
+    }
+
+    pub fn delete_rows(&mut self, text_buffer:&mut TextBuffer){
+        self.edit_rows(text_buffer, |_text_buffer, first, last, positions|{
+            for pos in positions{
+                pos.row = 0;
+            }
+            Some((first, last, Vec::new()))
+        })
+    }
+
+    // joins the selected rows, or the row with the next one, on a single space
+    pub fn join_rows(&mut self, text_buffer:&mut TextBuffer){
+        self.edit_rows(text_buffer, |text_buffer, first, last, positions|{
+            let last = if first == last{last + 1}else{last};
+            if last >= text_buffer.get_line_count(){
+                return None
+            }
+            let mut line:String = text_buffer.get_line(first).iter().collect();
+            let mut join_col = 0;
+            for row in first+1..=last{
+                let next:String = text_buffer.get_line(row).iter().collect();
+                let next = next.trim_start();
+                line = line.trim_end().to_string();
+                join_col = line.chars().count();
+                if line.len() > 0 && next.len() > 0{
+                    line.push(' ');
+                }
+                line.push_str(next);
+            }
+            for pos in positions{
+                pos.row = 0;
+                pos.col = join_col;
+            }
+            Some((first, last, vec![line]))
+        })
+    }
+
+    // comments out the rows under the cursors with the line comment of the language,
+    // or uncomments them when every row with text in a block is commented already
+    pub fn toggle_comment(&mut self, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer){
+        let comment:Vec<char> = if let Some(comment) = syntax.line_comment{comment.chars().collect()}else{return};
+        self.edit_rows(text_buffer, |text_buffer, first, last, positions|{
+            let rows:Vec<Vec<char>> = (first..=last).map(|row| text_buffer.get_line(row).to_vec()).collect();
+            let indent_of = |line:&Vec<char>| line.iter().take_while(|c| c.is_whitespace()).count();
+            let text_rows:Vec<&Vec<char>> = rows.iter().filter(|line| indent_of(line) < line.len()).collect();
+            if text_rows.len() == 0{
+                return None
+            }
+            let uncomment = text_rows.iter().all(|line| line[indent_of(line)..].starts_with(&comment));
+            let min_indent = text_rows.iter().map(|line| indent_of(line)).min().unwrap();
+            // (col, chars removed or added) per row
+            let mut col_edits = Vec::new();
+            let mut lines = Vec::new();
+            for line in &rows{
+                let mut line = line.clone();
+                let indent = indent_of(&line);
+                if indent == line.len(){
+                    col_edits.push((0, 0));
+                }
+                else if uncomment{
+                    let mut len = comment.len();
+                    if line.get(indent + len) == Some(&' '){
+                        len += 1;
+                    }
+                    line.drain(indent..indent + len);
+                    col_edits.push((indent, -(len as isize)));
+                }
+                else{
+                    let mut insert = comment.clone();
+                    insert.push(' ');
+                    col_edits.push((min_indent, insert.len() as isize));
+                    line.splice(min_indent..min_indent, insert);
+                }
+                lines.push(line.into_iter().collect());
+            }
+            for pos in positions{
+                let (col, delta) = col_edits[pos.row - first];
+                if delta > 0 && pos.col >= col{
+                    pos.col += delta as usize;
+                }
+                else if delta < 0 && pos.col > col{
+                    pos.col -= (pos.col - col).min((-delta) as usize);
+                }
+                pos.row -= first;
+            }
+            Some((first, last, lines))
+        })
+    }
+
+    // replaces each range with its own string as a single undo step, leaving the new text selected
+    pub fn replace_ranges(&mut self, ranges:&[(usize, usize)], strings:&[String], text_buffer:&mut TextBuffer){
+        let mut delta:isize = 0; // rolling delta to displace ranges
+        let mut ops = Vec::new();
+        let cursors_clone = self.clone();
+        self.set.truncate(0);
+        for (index, (start, end)) in ranges.iter().enumerate(){
+            let start_delta = ((*start as isize) + delta) as usize;
+            let op = text_buffer.replace_with_string(start_delta, end - start, &strings[index]);
+            delta += (op.len as isize) - ((end - start) as isize);
+            let mut cursor = Cursor{
+                head:start_delta + op.len,
+                tail:start_delta,
+                max:0
+            };
+            cursor.calc_max(text_buffer);
+            self.set.push(cursor);
+            ops.push(op);
+        }
+        if self.set.len() == 0{
+            self.set = cursors_clone.set;
+            return
+        }
+        self.last_cursor = self.set.len() - 1;
+        text_buffer.push_undo(ops, TextUndoGrouping::Block, cursors_clone)
+    }
+
+    // selects every (start, end) range, used for find matches
+    pub fn select_ranges(&mut self, ranges:&[(usize, usize)], last_cursor:usize, text_buffer:&TextBuffer){
+        if ranges.len() == 0{
+            return
+        }
+        self.set.truncate(0);
+        for (start, end) in ranges{
+            let mut cursor = Cursor{
+                head:*end,
+                tail:*start,
+                max:0
+            };
+            cursor.calc_max(text_buffer);
+            self.set.push(cursor);
+        }
+        self.last_cursor = last_cursor.min(self.set.len() - 1);
+    }
+
     pub fn select_all(&mut self, text_buffer:&mut TextBuffer){
         self.set.truncate(0);
         let mut cursor = Cursor{
@@ -927,7 +1392,8 @@ impl CursorSet{
                 }
             }
         };
-        0
+        // only drawn lines have chunks, stay put if we are not on one
+        offset
     }
 
     pub fn move_left_nearest_token(&mut self, only_head:bool, token_chunks:&Vec<TokenChunk>, text_buffer:&TextBuffer){
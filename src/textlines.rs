@@ -0,0 +1,603 @@
+use std::ops::{Bound, Index, RangeBounds};
+use crate::textbuffer::*;
+
+// Line storage behind a TextBuffer. Offsets count one invisible newline char between lines.
+pub trait TextLines: Clone + Default{
+    fn from_lines(lines:Vec<Vec<char>>)->Self;
+    fn get_line_count(&self)->usize;
+    fn get_char_count(&self)->usize;
+    fn get_line(&self, row:usize)->Line<'_>;
+    fn offset_to_text_pos(&self, char_offset:usize)->TextPos;
+    fn text_pos_to_offset(&self, pos:TextPos)->usize;
+    // replaces start_col..end_col in a single line, returns what was removed
+    fn splice_line(&mut self, row:usize, start_col:usize, end_col:usize, rep_line:Vec<char>)->Vec<char>;
+    // replaces the lines start_row..end_row, returns the removed lines
+    fn splice_lines(&mut self, start_row:usize, end_row:usize, rep_lines:Vec<Vec<char>>)->Vec<Vec<char>>;
+}
+
+// A line, or a part of one, as the chunks it is stored in. Its cheap to copy and to
+// slice, and indexes and iterates like a &[char].
+#[derive(Clone, Copy)]
+pub struct Line<'a>{
+    chunks:&'a [Vec<char>],
+    // where the line starts in the first chunk
+    start:usize,
+    len:usize
+}
+
+impl<'a> Line<'a>{
+    pub fn new(chunks:&'a [Vec<char>])->Line<'a>{
+        Line{
+            chunks:chunks,
+            start:0,
+            len:chunks.iter().map(|chunk| chunk.len()).sum()
+        }
+    }
+
+    pub fn len(&self)->usize{
+        self.len
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.len == 0
+    }
+
+    pub fn get(&self, col:usize)->Option<&'a char>{
+        if col >= self.len{
+            return None
+        }
+        let mut col = col + self.start;
+        for chunk in self.chunks{
+            if col < chunk.len(){
+                return Some(&chunk[col])
+            }
+            col -= chunk.len();
+        }
+        None
+    }
+
+    pub fn first(&self)->Option<&'a char>{
+        self.get(0)
+    }
+
+    pub fn last(&self)->Option<&'a char>{
+        if self.len == 0{
+            return None
+        }
+        self.get(self.len - 1)
+    }
+
+    // the part of the line in range, panics like slicing a &[char] would
+    pub fn slice<R>(&self, range:R)->Line<'a>
+    where R: RangeBounds<usize>
+    {
+        let start = match range.start_bound(){
+            Bound::Included(start)=>*start,
+            Bound::Excluded(start)=>*start + 1,
+            Bound::Unbounded=>0
+        };
+        let end = match range.end_bound(){
+            Bound::Included(end)=>*end + 1,
+            Bound::Excluded(end)=>*end,
+            Bound::Unbounded=>self.len
+        };
+        if start > end || end > self.len{
+            panic!("range {}..{} out of range for line of length {}", start, end, self.len);
+        }
+        let mut index = 0;
+        let mut col = self.start + start;
+        while index + 1 < self.chunks.len() && col >= self.chunks[index].len(){
+            col -= self.chunks[index].len();
+            index += 1;
+        }
+        Line{
+            chunks:&self.chunks[index.min(self.chunks.len())..],
+            start:col,
+            len:end - start
+        }
+    }
+
+    pub fn iter(&self)->LineIter<'a>{
+        let empty:&'a [char] = &[];
+        if self.len == 0{
+            return LineIter{front:empty.iter(), middle:&[], back:empty.iter(), len:0}
+        }
+        let first = &self.chunks[0];
+        if self.start + self.len <= first.len(){
+            return LineIter{front:first[self.start..self.start + self.len].iter(), middle:&[], back:empty.iter(), len:self.len}
+        }
+        // find the chunk the line ends in
+        let mut index = 1;
+        let mut end = self.start + self.len - first.len();
+        while end > self.chunks[index].len(){
+            end -= self.chunks[index].len();
+            index += 1;
+        }
+        LineIter{
+            front:first[self.start..].iter(),
+            middle:&self.chunks[1..index],
+            back:self.chunks[index][..end].iter(),
+            len:self.len
+        }
+    }
+
+    pub fn to_vec(self)->Vec<char>{
+        self.iter().cloned().collect()
+    }
+
+    pub fn starts_with(&self, needle:&[char])->bool{
+        needle.len() <= self.len && self.iter().zip(needle).all(|(a, b)| a == b)
+    }
+}
+
+impl<'a> From<&'a Vec<char>> for Line<'a>{
+    fn from(line:&'a Vec<char>)->Line<'a>{
+        Line{
+            chunks:std::slice::from_ref(line),
+            start:0,
+            len:line.len()
+        }
+    }
+}
+
+impl<'a> Index<usize> for Line<'a>{
+    type Output = char;
+    fn index(&self, col:usize)->&char{
+        match self.get(col){
+            Some(c)=>c,
+            None=>panic!("index {} out of range for line of length {}", col, self.len)
+        }
+    }
+}
+
+// walks the chars of a Line from both ends
+#[derive(Clone)]
+pub struct LineIter<'a>{
+    front:std::slice::Iter<'a, char>,
+    middle:&'a [Vec<char>],
+    back:std::slice::Iter<'a, char>,
+    len:usize
+}
+
+impl<'a> Iterator for LineIter<'a>{
+    type Item = &'a char;
+
+    fn next(&mut self)->Option<&'a char>{
+        loop{
+            if let Some(c) = self.front.next(){
+                self.len -= 1;
+                return Some(c)
+            }
+            if let Some((chunk, rest)) = self.middle.split_first(){
+                self.front = chunk.iter();
+                self.middle = rest;
+                continue;
+            }
+            let c = self.back.next();
+            if c.is_some(){
+                self.len -= 1;
+            }
+            return c
+        }
+    }
+
+    fn size_hint(&self)->(usize, Option<usize>){
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for LineIter<'a>{
+    fn next_back(&mut self)->Option<&'a char>{
+        loop{
+            if let Some(c) = self.back.next_back(){
+                self.len -= 1;
+                return Some(c)
+            }
+            if let Some((chunk, rest)) = self.middle.split_last(){
+                self.back = chunk.iter();
+                self.middle = rest;
+                continue;
+            }
+            let c = self.front.next_back();
+            if c.is_some(){
+                self.len -= 1;
+            }
+            return c
+        }
+    }
+}
+
+impl<'a> ExactSizeIterator for LineIter<'a>{}
+
+// The original storage, one Vec<char> per line and linear scans for all offset lookups.
+// Its fine for most code files, we keep it around to benchmark against.
+#[derive(Clone, Default)]
+pub struct FlatLines{
+    pub lines:Vec<Vec<char>>,
+    pub _char_count:usize
+}
+
+impl FlatLines{
+    fn compute_char_count(&mut self){
+        let mut char_count = 0;
+        for line in &self.lines{
+            char_count += line.len()
+        }
+        if self.lines.len() > 0{
+            char_count += self.lines.len() - 1; // invisible newline chars
+        }
+        self._char_count = char_count
+    }
+}
+
+impl TextLines for FlatLines{
+    fn from_lines(lines:Vec<Vec<char>>)->Self{
+        let mut flat = FlatLines{
+            lines:lines,
+            _char_count:0
+        };
+        flat.compute_char_count();
+        flat
+    }
+
+    fn get_line_count(&self)->usize{
+        self.lines.len()
+    }
+
+    fn get_char_count(&self)->usize{
+        self._char_count
+    }
+
+    fn get_line(&self, row:usize)->Line<'_>{
+        Line::from(&self.lines[row])
+    }
+
+    fn offset_to_text_pos(&self, char_offset:usize)->TextPos{
+        let mut char_count = 0;
+        for (row,line) in self.lines.iter().enumerate(){
+            let next_char_count = char_count + line.len() + 1;
+            if next_char_count > char_offset{
+                return TextPos{row:row, col:char_offset - char_count}
+            }
+            char_count = next_char_count;
+        }
+        TextPos{row:0, col:0}
+    }
+
+    fn text_pos_to_offset(&self, pos:TextPos)->usize{
+        let mut char_count = 0;
+        if pos.row >= self.lines.len(){
+            return self._char_count
+        }
+        for (ln_row, line) in self.lines.iter().enumerate(){
+            if ln_row == pos.row{
+                return char_count + line.len().min(pos.col);
+            }
+            char_count += line.len() + 1;
+        }
+        0
+    }
+
+    fn splice_line(&mut self, row:usize, start_col:usize, end_col:usize, rep_line:Vec<char>)->Vec<char>{
+        let line = self.lines[row].splice(start_col..end_col, rep_line).collect();
+        self.compute_char_count();
+        line
+    }
+
+    fn splice_lines(&mut self, start_row:usize, end_row:usize, rep_lines:Vec<Vec<char>>)->Vec<Vec<char>>{
+        let lines = self.lines.splice(start_row..end_row, rep_lines).collect();
+        self.compute_char_count();
+        lines
+    }
+}
+
+// blocks are split when they grow past this, and merged with a neighbour when they get very small
+const ROPE_BLOCK_MAX_LINES:usize = 512;
+const ROPE_BLOCK_MIN_LINES:usize = 64;
+
+// lines longer than this are kept in chunks of half of it, and an edit that leaves a
+// chunk smaller than the min takes a neighbour along
+const LINE_CHUNK_MAX:usize = 4096;
+const LINE_CHUNK_MIN:usize = 512;
+
+// A line of the rope, a normal line is a single chunk.
+#[derive(Clone, Default)]
+pub struct RopeLine{
+    pub chunks:Vec<Vec<char>>,
+    pub _len:usize
+}
+
+impl RopeLine{
+    fn new(line:Vec<char>)->RopeLine{
+        let len = line.len();
+        RopeLine{
+            chunks:Self::split_chunks(line),
+            _len:len
+        }
+    }
+
+    fn split_chunks(line:Vec<char>)->Vec<Vec<char>>{
+        if line.len() <= LINE_CHUNK_MAX{
+            return vec![line]
+        }
+        line.chunks(LINE_CHUNK_MAX / 2).map(|chunk| chunk.to_vec()).collect()
+    }
+
+    fn into_vec(mut self)->Vec<char>{
+        if self.chunks.len() == 1{
+            return self.chunks.pop().unwrap()
+        }
+        let mut line = Vec::with_capacity(self._len);
+        for chunk in self.chunks{
+            line.extend(chunk);
+        }
+        line
+    }
+
+    fn len(&self)->usize{
+        self._len
+    }
+
+    // the chunk col is in and where that chunk starts, a col on the end of a chunk stays in it
+    fn find_chunk(&self, col:usize)->(usize, usize){
+        let mut start = 0;
+        for (index, chunk) in self.chunks.iter().enumerate(){
+            if col <= start + chunk.len(){
+                return (index, start)
+            }
+            start += chunk.len();
+        }
+        let last = self.chunks.len() - 1;
+        (last, start - self.chunks[last].len())
+    }
+
+    // only joins and resplits the chunks the edit touches
+    fn splice(&mut self, start_col:usize, end_col:usize, rep_line:Vec<char>)->Vec<char>{
+        let (mut first, mut first_start) = self.find_chunk(start_col);
+        let (mut last, last_start) = self.find_chunk(end_col);
+        let rep_len = rep_line.len();
+        let old_len = last_start + self.chunks[last].len() - first_start;
+        if old_len - (end_col - start_col) + rep_len < LINE_CHUNK_MIN{
+            if last + 1 < self.chunks.len(){
+                last += 1;
+            }
+            else if first > 0{
+                first -= 1;
+                first_start -= self.chunks[first].len();
+            }
+        }
+        let mut joined = if first == last{
+            std::mem::take(&mut self.chunks[first])
+        }
+        else{
+            let mut joined = Vec::new();
+            for chunk in &self.chunks[first..=last]{
+                joined.extend_from_slice(chunk);
+            }
+            joined
+        };
+        let removed:Vec<char> = joined.splice((start_col - first_start)..(end_col - first_start), rep_line).collect();
+        self._len = self._len + rep_len - removed.len();
+        self.chunks.splice(first..=last, Self::split_chunks(joined));
+        removed
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct RopeBlock{
+    pub lines:Vec<RopeLine>,
+    // chars in this block counting a newline after every line
+    pub char_count:usize
+}
+
+impl RopeBlock{
+    fn new(lines:Vec<RopeLine>)->RopeBlock{
+        let mut block = RopeBlock{
+            lines:lines,
+            char_count:0
+        };
+        block.compute_char_count();
+        block
+    }
+
+    fn compute_char_count(&mut self){
+        let mut char_count = 0;
+        for line in &self.lines{
+            char_count += line.len() + 1;
+        }
+        self.char_count = char_count;
+    }
+}
+
+// A rope of line blocks. Each block caches its char count and the index caches
+// where every block starts, so offset lookups are a binary search plus a scan
+// of a single block, and edits only recount the block they touch.
+// Long lines are chunked too, so typing in a 4mb single line is ~3us instead of
+// the ~400us of moving the whole line, see cargo bench --bench textbuffer.
+// Splitting or joining such a line still copies it.
+#[derive(Clone, Default)]
+pub struct RopeLines{
+    pub blocks:Vec<RopeBlock>,
+    // (first row, first char offset) of every block
+    pub _block_starts:Vec<(usize, usize)>,
+    pub _line_count:usize,
+    pub _char_count:usize
+}
+
+impl RopeLines{
+    fn update_block_starts(&mut self, from_block:usize){
+        self._block_starts.truncate(from_block);
+        let (mut row, mut offset) = if from_block > 0{
+            let (row, offset) = self._block_starts[from_block - 1];
+            let prev = &self.blocks[from_block - 1];
+            (row + prev.lines.len(), offset + prev.char_count)
+        }
+        else{
+            (0, 0)
+        };
+        for block in &self.blocks[from_block..]{
+            self._block_starts.push((row, offset));
+            row += block.lines.len();
+            offset += block.char_count;
+        }
+        self._line_count = row;
+        // the last line has no newline
+        self._char_count = if offset > 0{offset - 1}else{0};
+    }
+
+    fn find_block_by_row(&self, row:usize)->usize{
+        match self._block_starts.binary_search_by(|(start_row, _)| start_row.cmp(&row)){
+            Ok(index)=>index,
+            Err(index)=>index - 1
+        }
+    }
+
+    fn find_block_by_offset(&self, offset:usize)->usize{
+        match self._block_starts.binary_search_by(|(_, start_offset)| start_offset.cmp(&offset)){
+            Ok(index)=>index,
+            Err(index)=>index - 1
+        }
+    }
+
+    // splits up large blocks and merges small ones in the range we just edited
+    fn rebalance(&mut self, first_block:usize, last_block:usize){
+        let mut index = first_block;
+        let mut end = last_block;
+        let mut lowest = first_block;
+        while index <= end && index < self.blocks.len(){
+            let len = self.blocks[index].lines.len();
+            if len > ROPE_BLOCK_MAX_LINES{
+                let tail = self.blocks[index].lines.split_off(len / 2);
+                self.blocks[index].compute_char_count();
+                self.blocks.insert(index + 1, RopeBlock::new(tail));
+                end += 1;
+                continue;
+            }
+            if len < ROPE_BLOCK_MIN_LINES && self.blocks.len() > 1{
+                // merge the next block into us, or for the last block merge into the previous one
+                if index + 1 < self.blocks.len(){
+                    let mut next = self.blocks.remove(index + 1);
+                    self.blocks[index].lines.append(&mut next.lines);
+                    if end > index{
+                        end -= 1;
+                    }
+                }
+                else{
+                    let mut last = self.blocks.remove(index);
+                    index -= 1;
+                    self.blocks[index].lines.append(&mut last.lines);
+                    lowest = lowest.min(index);
+                }
+                self.blocks[index].compute_char_count();
+                continue;
+            }
+            index += 1;
+        }
+        self.update_block_starts(lowest.min(self.blocks.len()));
+    }
+}
+
+impl TextLines for RopeLines{
+    fn from_lines(lines:Vec<Vec<char>>)->Self{
+        let mut rope = RopeLines{..Default::default()};
+        let mut lines_iter = lines.into_iter().peekable();
+        while lines_iter.peek().is_some(){
+            let block_lines:Vec<RopeLine> = lines_iter.by_ref().take(ROPE_BLOCK_MAX_LINES / 2).map(RopeLine::new).collect();
+            rope.blocks.push(RopeBlock::new(block_lines));
+        }
+        rope.update_block_starts(0);
+        rope
+    }
+
+    fn get_line_count(&self)->usize{
+        self._line_count
+    }
+
+    fn get_char_count(&self)->usize{
+        self._char_count
+    }
+
+    fn get_line(&self, row:usize)->Line<'_>{
+        let block_index = self.find_block_by_row(row);
+        let (start_row, _) = self._block_starts[block_index];
+        let line = &self.blocks[block_index].lines[row - start_row];
+        Line{
+            chunks:&line.chunks,
+            start:0,
+            len:line.len()
+        }
+    }
+
+    fn offset_to_text_pos(&self, char_offset:usize)->TextPos{
+        if self.blocks.len() == 0 || char_offset > self._char_count{
+            return TextPos{row:0, col:0}
+        }
+        let block_index = self.find_block_by_offset(char_offset);
+        let (mut row, mut char_count) = self._block_starts[block_index];
+        for line in &self.blocks[block_index].lines{
+            let next_char_count = char_count + line.len() + 1;
+            if next_char_count > char_offset{
+                return TextPos{row:row, col:char_offset - char_count}
+            }
+            char_count = next_char_count;
+            row += 1;
+        }
+        TextPos{row:0, col:0}
+    }
+
+    fn text_pos_to_offset(&self, pos:TextPos)->usize{
+        if pos.row >= self._line_count{
+            return self._char_count
+        }
+        let block_index = self.find_block_by_row(pos.row);
+        let (start_row, mut char_count) = self._block_starts[block_index];
+        let lines = &self.blocks[block_index].lines;
+        for line in &lines[0..(pos.row - start_row)]{
+            char_count += line.len() + 1;
+        }
+        char_count + lines[pos.row - start_row].len().min(pos.col)
+    }
+
+    fn splice_line(&mut self, row:usize, start_col:usize, end_col:usize, rep_line:Vec<char>)->Vec<char>{
+        let block_index = self.find_block_by_row(row);
+        let (start_row, _) = self._block_starts[block_index];
+        let block = &mut self.blocks[block_index];
+        let line = block.lines[row - start_row].splice(start_col, end_col, rep_line);
+        block.compute_char_count();
+        self.update_block_starts(block_index);
+        line
+    }
+
+    fn splice_lines(&mut self, start_row:usize, end_row:usize, rep_lines:Vec<Vec<char>>)->Vec<Vec<char>>{
+        if self.blocks.len() == 0{
+            self.blocks.push(RopeBlock::new(Vec::new()));
+        }
+        let first_block = if start_row >= self._line_count{self.blocks.len() - 1}else{self.find_block_by_row(start_row)};
+        let (first_row, _) = self._block_starts[first_block];
+
+        // drain the removed lines out of as many blocks as they span
+        let mut removed = Vec::new();
+        let mut remaining = end_row - start_row;
+        let mut block_index = first_block;
+        let mut row_in_block = start_row - first_row;
+        while remaining > 0 && block_index < self.blocks.len(){
+            let block = &mut self.blocks[block_index];
+            let end = (row_in_block + remaining).min(block.lines.len());
+            remaining -= end - row_in_block;
+            removed.extend(block.lines.drain(row_in_block..end).map(RopeLine::into_vec));
+            block.compute_char_count();
+            block_index += 1;
+            row_in_block = 0;
+        }
+        let last_block = block_index.max(first_block + 1) - 1;
+
+        // and splice the new ones in where the first removed line was
+        let block = &mut self.blocks[first_block];
+        let at = (start_row - first_row).min(block.lines.len());
+        block.lines.splice(at..at, rep_lines.into_iter().map(RopeLine::new));
+        block.compute_char_count();
+
+        self.rebalance(first_block, last_block);
+        removed
+    }
+}
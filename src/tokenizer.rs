@@ -0,0 +1,272 @@
+use crate::textbuffer::*;
+use crate::textlines::*;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TokenType{
+    Whitespace,
+    Keyword,
+    Flow,
+    Identifier,
+    Call,
+    TypeName,
+    Number,
+    String,
+    Comment,
+    DocComment,
+    Operator,
+    Paren,
+    Delimiter
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Token{
+    pub len:usize,
+    pub token_type:TokenType
+}
+
+// A tokenizer works one line at a time, whatever it needs to carry over
+// into the next line (like being inside a block comment) goes in State.
+pub trait LineTokenizer{
+    type State: Clone + PartialEq + Default;
+    fn tokenize_line(&self, state:&mut Self::State, line:Line, tokens:&mut Vec<Token>);
+}
+
+#[derive(Clone)]
+pub struct TokenLine<S>{
+    pub start_state:S,
+    pub end_state:S,
+    pub tokens:Vec<Token>,
+    // changes every time the line is lexed, so users of the tokens can tell it did
+    pub lex_id:u64
+}
+
+// Caches the tokens and the tokenizer state at every line start, edits only
+// re-lex from the first touched line until the state converges with the cache.
+#[derive(Clone)]
+pub struct TokenCache<T>
+where T: LineTokenizer
+{
+    pub tokenizer:T,
+    pub lines:Vec<Option<TokenLine<T::State>>>,
+    pub _line_edit_version:u64,
+    // how many lines the last update had to tokenize
+    pub _relexed_lines:usize,
+    pub _next_lex_id:u64
+}
+
+impl<T> TokenCache<T>
+where T: LineTokenizer
+{
+    pub fn new(tokenizer:T)->Self{
+        Self{
+            tokenizer:tokenizer,
+            lines:Vec::new(),
+            _line_edit_version:0,
+            _relexed_lines:0,
+            _next_lex_id:1
+        }
+    }
+
+    pub fn update(&mut self, text_buffer:&TextBuffer){
+        let line_count = text_buffer.get_line_count();
+        let mut first_dirty = None;
+        if self.lines.len() > 0{
+            if let Some(line_edits) = text_buffer.get_line_edits_since(self._line_edit_version){
+                for line_edit in line_edits{
+                    self.lines.splice(line_edit.row..line_edit.row + line_edit.old_rows, (0..line_edit.new_rows).map(|_| None));
+                    first_dirty = Some(first_dirty.map_or(line_edit.row, |row:usize| row.min(line_edit.row)));
+                }
+            }
+            else{
+                self.lines.truncate(0);
+            }
+        }
+        if self.lines.len() != line_count{ // never seen this buffer, or we lost track of it
+            self.lines = (0..line_count).map(|_| None).collect();
+            first_dirty = Some(0);
+        }
+        self._line_edit_version = text_buffer.get_line_edit_version();
+        self._relexed_lines = 0;
+
+        let mut row = if let Some(row) = first_dirty{row}else{return};
+        // edits only ever clear lines at or after their row, so the line above is valid
+        let mut state = if row > 0{
+            self.lines[row - 1].as_ref().unwrap().end_state.clone()
+        }
+        else{
+            T::State::default()
+        };
+        while row < line_count{
+            if let Some(line) = &self.lines[row]{
+                if line.start_state == state{ // converged, skip ahead to the next cleared line
+                    if let Some(skip) = self.lines[row..].iter().position(|line| line.is_none()){
+                        row += skip;
+                        state = self.lines[row - 1].as_ref().unwrap().end_state.clone();
+                    }
+                    else{
+                        break;
+                    }
+                }
+            }
+            let start_state = state.clone();
+            let mut tokens = Vec::new();
+            self.tokenizer.tokenize_line(&mut state, text_buffer.get_line(row), &mut tokens);
+            self.lines[row] = Some(TokenLine{
+                start_state:start_state,
+                end_state:state.clone(),
+                tokens:tokens,
+                lex_id:self._next_lex_id
+            });
+            self._next_lex_id += 1;
+            self._relexed_lines += 1;
+            row += 1;
+        }
+    }
+
+    pub fn get_lex_id(&self, row:usize)->u64{
+        if let Some(Some(line)) = self.lines.get(row){
+            line.lex_id
+        }
+        else{
+            0
+        }
+    }
+
+    pub fn get_tokens(&self, row:usize)->&[Token]{
+        if let Some(Some(line)) = self.lines.get(row){
+            &line.tokens
+        }
+        else{
+            &[]
+        }
+    }
+}
+
+// walks the chars of a single line, next is '\0' past the end of it
+#[derive(Clone)]
+pub struct TokenizerState<'a>{
+    pub prev:char,
+    pub cur:char,
+    pub next:char,
+    pub offset:usize,
+    iter:LineIter<'a>
+}
+
+impl<'a> TokenizerState<'a>{
+    pub fn new(line:Line<'a>)->Self{
+        let mut ret = Self{
+            offset:0,
+            prev:'\0',
+            cur:'\0',
+            next:'\0',
+            iter:line.iter()
+        };
+        ret.advance_with_cur();
+        ret
+    }
+
+    pub fn advance(&mut self){
+        if let Some(next) = self.iter.next(){
+            self.next = *next;
+            self.offset += 1;
+        }
+        else{
+            self.offset += 1;
+            self.next = '\0'
+        }
+    }
+
+    // the char after next, for the few places one char of lookahead isn't enough
+    pub fn peek_next(&self)->char{
+        if let Some(c) = self.iter.clone().next(){*c}else{'\0'}
+    }
+
+    pub fn next_is_digit(&self)->bool{
+        self.next >= '0' && self.next <='9'
+    }
+
+    pub fn next_is_letter(&self)->bool{
+        self.next >= 'a' && self.next <='z' || self.next >= 'A' && self.next <='Z'
+    }
+
+    pub fn next_is_lowercase_letter(&self)->bool{
+        self.next >= 'a' && self.next <='z'
+    }
+
+    pub fn next_is_uppercase_letter(&self)->bool{
+        self.next >= 'A' && self.next <='Z'
+    }
+
+    pub fn next_is_hex(&self)->bool{
+        self.next >= '0' && self.next <='9' || self.next >= 'a' && self.next <= 'f' || self.next >= 'A' && self.next <='F'
+    }
+
+    pub fn advance_with_cur(&mut self){
+        self.cur = self.next;
+        self.advance();
+    }
+
+    pub fn advance_with_prev(&mut self){
+        self.prev = self.cur;
+        self.cur = self.next;
+        self.advance();
+    }
+
+    pub fn eat_while<F>(&mut self, chunk:&mut Vec<char>, f:F)->bool
+    where F: Fn(char)->bool
+    {
+        let mut ret = false;
+        while self.next != '\0' && f(self.next){
+            ret = true;
+            chunk.push(self.next);
+            self.advance();
+        }
+        ret
+    }
+
+    // a string with backslash escapes, true when the closing quote is on this line
+    pub fn eat_string_tail(&mut self, chunk:&mut Vec<char>, quote:char)->bool{
+        while self.next != '\0'{
+            let c = self.next;
+            chunk.push(c);
+            self.advance();
+            if c == '\\'{
+                if self.next != '\0'{
+                    chunk.push(self.next);
+                    self.advance();
+                }
+            }
+            else if c == quote{
+                return true
+            }
+        }
+        false
+    }
+
+    // eats up to and including end, true when it was found on this line
+    pub fn eat_until(&mut self, chunk:&mut Vec<char>, end:&str)->bool{
+        let end:Vec<char> = end.chars().collect();
+        let start = chunk.len();
+        while self.next != '\0'{
+            chunk.push(self.next);
+            self.advance();
+            if chunk.len() - start >= end.len() && chunk.ends_with(&end){
+                return true
+            }
+        }
+        false
+    }
+
+    pub fn keyword(&mut self, chunk:&mut Vec<char>, word:&str)->bool{
+        for m in word.chars(){
+            if m == self.next{
+                chunk.push(m);
+                self.advance();
+            }
+            else{
+                return false
+            }
+        }
+        return true
+    }
+}
@@ -0,0 +1,200 @@
+use crate::tokenizer::*;
+use crate::textlines::*;
+use crate::languagemode::*;
+
+#[derive(Clone, Default)]
+pub struct TomlTokenizer{
+}
+
+// only the """ and ''' strings span lines
+#[derive(Clone, PartialEq, Debug)]
+pub enum TomlTokenizerState{
+    Code,
+    BasicString,
+    LiteralString
+}
+
+impl Default for TomlTokenizerState{
+    fn default()->Self{
+        TomlTokenizerState::Code
+    }
+}
+
+impl LineTokenizer for TomlTokenizer{
+    type State = TomlTokenizerState;
+
+    fn tokenize_line(&self, toml_state:&mut TomlTokenizerState, line:Line, tokens:&mut Vec<Token>){
+        let mut chunk = Vec::new();
+        let mut state = TokenizerState::new(line);
+        // everything before the = is part of the key, lines without one are values
+        // inside a multiline array. [headers] only come first on a line
+        let mut is_key = line.iter().any(|c| *c == '=');
+        let mut is_first = true;
+
+        if *toml_state != TomlTokenizerState::Code{ // inside a multiline string
+            let quote = if *toml_state == TomlTokenizerState::BasicString{'"'}else{'\''};
+            let is_closed = Self::parse_toml_multiline_tail(&mut state, &mut chunk, quote);
+            if chunk.len() > 0{
+                tokens.push(Token{
+                    len:chunk.len(),
+                    token_type:TokenType::String
+                });
+                chunk.truncate(0);
+            }
+            if !is_closed{
+                return
+            }
+            *toml_state = TomlTokenizerState::Code;
+            is_key = false;
+            is_first = false;
+        }
+
+        loop{
+            let token_type;
+            state.advance_with_cur();
+
+            match state.cur{
+                '\0'=>{ // end of line
+                    break;
+                },
+                ' ' | '\t'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c == ' ' || c == '\t');
+                    token_type = TokenType::Whitespace;
+                },
+                '#'=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |_| true);
+                    token_type = TokenType::Comment;
+                },
+                '[' if is_first=>{ // [table] or [[array of tables]]
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, |c| c != ']' && c != '#');
+                    state.eat_while(&mut chunk, |c| c == ']');
+                    token_type = TokenType::TypeName;
+                },
+                '"' | '\''=>{
+                    let quote = state.cur;
+                    chunk.push(state.cur);
+                    if state.next == quote && state.peek_next() == quote{ // multiline string
+                        chunk.push(state.next);
+                        state.advance();
+                        chunk.push(state.next);
+                        state.advance();
+                        if !Self::parse_toml_multiline_tail(&mut state, &mut chunk, quote){
+                            *toml_state = if quote == '"'{
+                                TomlTokenizerState::BasicString
+                            }
+                            else{
+                                TomlTokenizerState::LiteralString
+                            };
+                        }
+                    }
+                    else if quote == '"'{
+                        state.eat_string_tail(&mut chunk, '"');
+                    }
+                    else{ // literal strings have no escapes
+                        state.eat_until(&mut chunk, "'");
+                    }
+                    token_type = if is_key{TokenType::Identifier}else{TokenType::String};
+                },
+                '='=>{
+                    chunk.push(state.cur);
+                    is_key = false;
+                    token_type = TokenType::Operator;
+                },
+                '{'=>{ // inline tables start with keys again
+                    chunk.push(state.cur);
+                    is_key = true;
+                    token_type = TokenType::Paren;
+                },
+                '}' | '[' | ']'=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Paren;
+                },
+                ','=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Delimiter;
+                },
+                '.'=>{
+                    chunk.push(state.cur);
+                    token_type = TokenType::Operator;
+                },
+                _=>{
+                    chunk.push(state.cur);
+                    state.eat_while(&mut chunk, Self::is_bare_char);
+                    if is_key{
+                        token_type = TokenType::Identifier;
+                    }
+                    else if chunk == ['t','r','u','e'] || chunk == ['f','a','l','s','e']{
+                        token_type = TokenType::Keyword;
+                    }
+                    else if chunk[0] >= '0' && chunk[0] <= '9' || chunk[0] == '+' || chunk[0] == '-' || chunk == ['i','n','f'] || chunk == ['n','a','n']{
+                        // numbers, dates and times
+                        state.eat_while(&mut chunk, |c| Self::is_bare_char(c) || c == '.' || c == ':' || c == '+');
+                        token_type = TokenType::Number;
+                    }
+                    else{
+                        token_type = TokenType::Identifier;
+                    }
+                }
+            }
+            if token_type != TokenType::Whitespace{
+                is_first = false;
+            }
+            tokens.push(Token{
+                len:chunk.len(),
+                token_type:token_type
+            });
+            chunk.truncate(0);
+        }
+    }
+}
+
+impl TomlTokenizer{
+    fn is_bare_char(c:char)->bool{
+        c >= 'a' && c <= 'z' || c >= 'A' && c <= 'Z' || c >= '0' && c <= '9' || c == '_' || c == '-'
+    }
+
+    // true when the closing triple quote is on this line
+    fn parse_toml_multiline_tail<'a>(state:&mut TokenizerState<'a>, chunk:&mut Vec<char>, quote:char)->bool{
+        let mut quotes = 0;
+        while state.next != '\0'{
+            let c = state.next;
+            chunk.push(c);
+            state.advance();
+            if c == '\\' && quote == '"'{ // skip escapes so \" can't close it
+                if state.next != '\0'{
+                    chunk.push(state.next);
+                    state.advance();
+                }
+                quotes = 0;
+            }
+            else if c == quote{
+                quotes += 1;
+                if quotes == 3{
+                    // up to two more quotes still belong to the string
+                    state.eat_while(chunk, |c| c == quote);
+                    return true
+                }
+            }
+            else{
+                quotes = 0;
+            }
+        }
+        false
+    }
+}
+
+impl LanguageMode for TomlTokenizer{
+    fn get_syntax(&self)->LanguageSyntax{
+        LanguageSyntax{
+            name:"TOML",
+            line_comment:Some("#"),
+            bracket_pairs:&[('[', ']'), ('{', '}')],
+            indent_pairs:&[('[', ']'), ('{', '}')],
+            auto_close_pairs:&[('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            ..Default::default()
+        }
+    }
+}
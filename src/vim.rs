@@ -0,0 +1,1452 @@
+use widgets::*;
+use std::collections::HashMap;
+use crate::textbuffer::*;
+use crate::languagemode::*;
+
+// Vim emulation on top of a CursorSet. Normal mode keys collect until they parse as a full
+// command like 2d3w or "ayi(, which then runs at every cursor. Every change is one undo step
+// and the last one from normal mode is kept, with what was typed after it, for the . key.
+#[derive(Clone)]
+pub struct Vim{
+    pub mode:VimMode,
+    pub registers:HashMap<char, VimRegister>,
+    // keys of the command being typed
+    pub _keys:Vec<VimKey>,
+    pub _visual_anchor:usize,
+    pub _visual_pos:Cursor,
+    // (char, forward, till) of the last f, F, t or T
+    pub _last_find:Option<(char, bool, bool)>,
+    pub _last_change:Option<(VimCommand, Vec<VimKey>)>,
+    // the change waiting for insert mode to end, and what got typed
+    pub _insert_change:Option<VimCommand>,
+    pub _insert_keys:Vec<VimKey>,
+    pub _insert_count:usize,
+    // an insert from visual mode goes back to the first cursor when it ends
+    pub _visual_insert:bool,
+    pub _change_undo_len:usize,
+    pub _replaying:bool,
+    // text yanked into the + and * registers, for the editor to put on the clipboard
+    pub _clipboard:Option<String>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimMode{
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+    VisualBlock
+}
+
+// typed text arrives as chars, enter as '\n'
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimKey{
+    Char(char),
+    Escape,
+    Backspace,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+    Ctrl(char)
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct VimRegister{
+    pub text:String,
+    pub linewise:bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VimCommand{
+    pub count:Option<usize>,
+    pub register:Option<char>,
+    pub action:VimAction
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimAction{
+    Move(VimMotion),
+    Operate(VimOperator, VimTarget),
+    Insert(VimInsert),
+    Paste{after:bool},
+    Join,
+    Replace(char),
+    ToggleCase,
+    Undo,
+    Redo,
+    Repeat,
+    Visual(VimMode),
+    // the rest only work in the visual modes
+    SelectObject(VimObject),
+    SwapEnds,
+    Case{upper:bool},
+    BlockInsert{append:bool}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimOperator{
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Dedent
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimTarget{
+    Motion(VimMotion),
+    Object(VimObject),
+    // dd, cc and friends work on count lines
+    Lines
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimInsert{
+    Before,
+    After,
+    LineStart,
+    LineEnd,
+    LineBelow,
+    LineAbove
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimMotion{
+    Left,
+    Right,
+    Up,
+    Down,
+    NextLine,
+    PrevLine,
+    WordStart{big:bool},
+    WordBack{big:bool},
+    WordEnd{big:bool},
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    Find{ch:char, forward:bool, till:bool},
+    RepeatFind{reverse:bool},
+    MatchPair,
+    FileStart,
+    FileEnd
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VimObject{
+    Word{big:bool, around:bool},
+    Pair{open:char, close:char, around:bool},
+    Quote{quote:char, around:bool}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MotionKind{
+    Exclusive,
+    Inclusive,
+    Linewise
+}
+
+struct MotionTarget{
+    offset:usize,
+    max:usize,
+    kind:MotionKind
+}
+
+enum ParseError{
+    Incomplete,
+    Invalid
+}
+
+struct KeyReader<'a>{
+    keys:&'a [VimKey],
+    pos:usize
+}
+
+impl<'a> KeyReader<'a>{
+    fn next(&mut self)->Result<VimKey, ParseError>{
+        if self.pos >= self.keys.len(){
+            return Err(ParseError::Incomplete)
+        }
+        self.pos += 1;
+        Ok(self.keys[self.pos - 1])
+    }
+
+    fn next_char(&mut self)->Result<char, ParseError>{
+        match self.next()?{
+            VimKey::Char(ch)=>Ok(ch),
+            _=>Err(ParseError::Invalid)
+        }
+    }
+
+    // a count can't start with 0, thats the line start motion
+    fn count(&mut self)->Option<usize>{
+        let mut count = None;
+        while let Some(VimKey::Char(ch)) = self.keys.get(self.pos){
+            match ch.to_digit(10){
+                Some(digit) if digit > 0 || count.is_some()=>{
+                    count = Some(count.unwrap_or(0usize).saturating_mul(10).saturating_add(digit as usize));
+                    self.pos += 1;
+                },
+                _=>break
+            }
+        }
+        count
+    }
+}
+
+fn mul_counts(a:Option<usize>, b:Option<usize>)->Option<usize>{
+    match (a, b){
+        (Some(a), Some(b))=>Some(a.saturating_mul(b)),
+        (a, None)=>a,
+        (None, b)=>b
+    }
+}
+
+impl VimKey{
+    // the keys vim wants from a KeyDown, typed chars come in as TextInput
+    pub fn from_key_event(ke:&KeyEvent)->Option<VimKey>{
+        let m = &ke.modifiers;
+        if m.logo || m.alt{
+            return None
+        }
+        if m.control{
+            if ke.key_code == KeyCode::LBracket{
+                return Some(VimKey::Escape)
+            }
+            let name = format!("{:?}", ke.key_code);
+            if name.len() == 4 && name.starts_with("Key"){
+                return name.chars().nth(3).map(|ch| VimKey::Ctrl(ch.to_ascii_lowercase()))
+            }
+            return None
+        }
+        match ke.key_code{
+            KeyCode::Escape=>Some(VimKey::Escape),
+            KeyCode::Backspace=>Some(VimKey::Backspace),
+            KeyCode::Tab if !m.shift=>Some(VimKey::Tab),
+            KeyCode::ArrowUp if !m.shift=>Some(VimKey::Up),
+            KeyCode::ArrowDown if !m.shift=>Some(VimKey::Down),
+            KeyCode::ArrowLeft if !m.shift=>Some(VimKey::Left),
+            KeyCode::ArrowRight if !m.shift=>Some(VimKey::Right),
+            _=>None
+        }
+    }
+}
+
+impl VimMode{
+    pub fn is_visual(&self)->bool{
+        match self{
+            VimMode::Visual | VimMode::VisualLine | VimMode::VisualBlock=>true,
+            _=>false
+        }
+    }
+}
+
+// what the motions see of the buffer, line ends read as '\n' and past the end as '\0'
+fn char_at(text_buffer:&TextBuffer, offset:usize)->char{
+    if offset >= text_buffer.get_char_count(){
+        return '\0'
+    }
+    let pos = text_buffer.offset_to_text_pos(offset);
+    text_buffer.get_line(pos.row).get(pos.col).cloned().unwrap_or('\n')
+}
+
+fn char_class(ch:char, big:bool)->u8{
+    if ch.is_whitespace() || ch == '\0'{
+        0
+    }
+    else if big || ch.is_alphanumeric() || ch == '_'{
+        1
+    }
+    else{
+        2
+    }
+}
+
+fn is_empty_line(text_buffer:&TextBuffer, offset:usize)->bool{
+    char_at(text_buffer, offset) == '\n' && (offset == 0 || char_at(text_buffer, offset - 1) == '\n')
+}
+
+fn row_start(text_buffer:&TextBuffer, row:usize)->usize{
+    text_buffer.text_pos_to_offset(TextPos{row:row, col:0})
+}
+
+fn row_end(text_buffer:&TextBuffer, row:usize)->usize{
+    text_buffer.text_pos_to_offset(TextPos{row:row, col:text_buffer.get_line(row).len()})
+}
+
+fn first_non_blank(text_buffer:&TextBuffer, row:usize)->usize{
+    let line = text_buffer.get_line(row);
+    row_start(text_buffer, row) + line.iter().take_while(|ch| **ch == ' ' || **ch == '\t').count()
+}
+
+// the start of the first row up to past the newline of the last, the last row takes the newline before it
+fn rows_range(text_buffer:&TextBuffer, first:usize, last:usize)->(usize, usize){
+    if last + 1 < text_buffer.get_line_count(){
+        (row_start(text_buffer, first), row_start(text_buffer, last + 1))
+    }
+    else if first > 0{
+        (row_start(text_buffer, first) - 1, text_buffer.get_char_count())
+    }
+    else{
+        (0, text_buffer.get_char_count())
+    }
+}
+
+fn rows_text(text_buffer:&TextBuffer, first:usize, last:usize)->String{
+    let mut text = String::new();
+    for row in first..=last{
+        text.extend(text_buffer.get_line(row).iter());
+        text.push('\n');
+    }
+    text
+}
+
+fn range_text(text_buffer:&TextBuffer, start:usize, end:usize)->String{
+    let mut text = String::new();
+    text_buffer.get_range_as_string(start, end - start, &mut text);
+    text
+}
+
+fn toggle_case(ch:char)->String{
+    if ch.is_uppercase(){ch.to_lowercase().collect()}else{ch.to_uppercase().collect()}
+}
+
+// sorts and merges overlapping ranges, so they can go into one edit
+fn merge_ranges(mut ranges:Vec<(usize, usize)>)->Vec<(usize, usize)>{
+    ranges.sort();
+    let mut merged:Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges{
+        if let Some(last) = merged.last_mut(){
+            if start <= last.1{
+                last.1 = last.1.max(end);
+                continue
+            }
+        }
+        merged.push((start, end));
+    }
+    merged
+}
+
+fn set_heads(cursors:&mut CursorSet, heads:&[usize], text_buffer:&TextBuffer){
+    cursors.set.truncate(0);
+    for head in heads{
+        let mut cursor = Cursor{head:*head, tail:*head, max:0};
+        cursor.calc_max(text_buffer);
+        cursors.set.push(cursor);
+    }
+    if cursors.set.len() == 0{
+        cursors.set.push(Cursor{head:0, tail:0, max:0});
+    }
+    cursors.last_cursor = cursors.set.len() - 1;
+}
+
+impl Vim{
+    pub fn new()->Vim{
+        Vim{
+            mode:VimMode::Normal,
+            registers:HashMap::new(),
+            _keys:Vec::new(),
+            _visual_anchor:0,
+            _visual_pos:Cursor{head:0, tail:0, max:0},
+            _last_find:None,
+            _last_change:None,
+            _insert_change:None,
+            _insert_keys:Vec::new(),
+            _insert_count:1,
+            _visual_insert:false,
+            _change_undo_len:0,
+            _replaying:false,
+            _clipboard:None
+        }
+    }
+
+    // the mode and the keys typed so far, for the status line
+    pub fn status(&self)->String{
+        let mode = match self.mode{
+            VimMode::Normal=>"NORMAL",
+            VimMode::Insert=>"-- INSERT --",
+            VimMode::Visual=>"-- VISUAL --",
+            VimMode::VisualLine=>"-- VISUAL LINE --",
+            VimMode::VisualBlock=>"-- VISUAL BLOCK --"
+        };
+        let mut status = mode.to_string();
+        if self._keys.len() > 0{
+            status.push_str("  ");
+            for key in &self._keys{
+                match key{
+                    VimKey::Char(ch)=>status.push(*ch),
+                    VimKey::Ctrl(ch)=>{
+                        status.push('^');
+                        status.push(ch.to_ascii_uppercase());
+                    },
+                    _=>()
+                }
+            }
+        }
+        status
+    }
+
+    pub fn get_register(&self, name:char)->Option<&VimRegister>{
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+
+    // returns false for keys the editor should handle itself
+    pub fn handle_key(&mut self, key:VimKey, cursors:&mut CursorSet, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer)->bool{
+        if self.mode == VimMode::Insert{
+            return self.handle_insert_key(key, cursors, syntax, text_buffer)
+        }
+        // ctrl keys vim doesn't use stay shortcuts
+        if let VimKey::Ctrl(ch) = key{
+            if ch != 'r' && ch != 'v'{
+                return false
+            }
+        }
+        // escape with nothing typed falls through, so it can still close the find bar
+        if key == VimKey::Escape && self._keys.len() == 0 && self.mode == VimMode::Normal{
+            return false
+        }
+        self._keys.push(key);
+        let keys = self._keys.clone();
+        let parsed = if self.mode.is_visual(){Self::parse_visual(&keys)}else{Self::parse_normal(&keys)};
+        match parsed{
+            Err(ParseError::Incomplete)=>(),
+            Err(ParseError::Invalid)=>{
+                self._keys.truncate(0);
+                if key == VimKey::Escape && self.mode.is_visual(){
+                    self.set_mode(VimMode::Normal, cursors, text_buffer);
+                }
+            },
+            Ok(command)=>{
+                self._keys.truncate(0);
+                if self.mode.is_visual(){
+                    self.run_visual(command, cursors, text_buffer);
+                }
+                else{
+                    self.run_normal(command, cursors, syntax, text_buffer);
+                }
+            }
+        }
+        true
+    }
+
+    fn handle_insert_key(&mut self, key:VimKey, cursors:&mut CursorSet, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer)->bool{
+        match key{
+            VimKey::Escape=>{
+                self.end_insert(cursors, syntax, text_buffer);
+                return true
+            },
+            VimKey::Char(ch)=>cursors.type_char(ch, syntax, text_buffer),
+            VimKey::Backspace=>cursors.backspace(text_buffer),
+            VimKey::Tab=>cursors.insert_tab(text_buffer),
+            _=>{ // arrows and shortcuts end what . repeats
+                self._insert_change = None;
+                return false
+            }
+        }
+        if !self._replaying{
+            self._insert_keys.push(key);
+        }
+        true
+    }
+
+    fn end_insert(&mut self, cursors:&mut CursorSet, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer){
+        // 3ifoo<esc> types foo three times
+        let insert_keys = self._insert_keys.clone();
+        for _ in 1..self._insert_count{
+            for key in &insert_keys{
+                self.handle_insert_key(*key, cursors, syntax, text_buffer);
+            }
+        }
+        self._insert_count = 1;
+        text_buffer.merge_undo_since(self._change_undo_len);
+        if let Some(command) = self._insert_change.take(){
+            if !self._replaying{
+                self._last_change = Some((command, insert_keys));
+            }
+        }
+        self._insert_keys.truncate(0);
+        self.mode = VimMode::Normal;
+        if self._visual_insert{
+            self._visual_insert = false;
+            cursors.set.truncate(1);
+            cursors.last_cursor = 0;
+        }
+        // the cursor steps back onto the last typed char
+        for cursor in &mut cursors.set{
+            let pos = text_buffer.offset_to_text_pos(cursor.head);
+            if pos.col > 0 && !cursor.has_selection(){
+                cursor.head -= 1;
+            }
+        }
+        self.clamp_cursors(cursors, text_buffer);
+    }
+
+    pub fn set_mode(&mut self, mode:VimMode, cursors:&mut CursorSet, text_buffer:&TextBuffer){
+        self._keys.truncate(0);
+        if mode.is_visual() && !self.mode.is_visual(){
+            // visual modes select from the last cursor
+            let cursor = cursors.set[cursors.last_cursor].clone();
+            self._visual_anchor = cursor.head;
+            self._visual_pos = Cursor{head:cursor.head, tail:cursor.head, max:cursor.max};
+        }
+        let was_visual = self.mode.is_visual();
+        self.mode = mode;
+        if mode.is_visual(){
+            self.show_visual(cursors, text_buffer);
+        }
+        else if was_visual{
+            set_heads(cursors, &[self._visual_pos.head], text_buffer);
+            self.clamp_cursors(cursors, text_buffer);
+        }
+        else if mode == VimMode::Normal{
+            self.clamp_cursors(cursors, text_buffer);
+        }
+    }
+
+    // normal mode cursors sit on a char, never past the end of the line
+    pub fn clamp_cursors(&self, cursors:&mut CursorSet, text_buffer:&TextBuffer){
+        let mut heads = Vec::new();
+        for cursor in &cursors.set{
+            let pos = text_buffer.offset_to_text_pos(cursor.head.min(text_buffer.get_char_count()));
+            let len = text_buffer.get_line(pos.row).len();
+            let col = if len > 0{pos.col.min(len - 1)}else{0};
+            heads.push(text_buffer.text_pos_to_offset(TextPos{row:pos.row, col:col}));
+        }
+        let last = heads[cursors.last_cursor.min(heads.len() - 1)];
+        let maxes:Vec<usize> = cursors.set.iter().map(|cursor| cursor.max).collect();
+        cursors.set.truncate(0);
+        for (head, max) in heads.iter().zip(maxes){
+            if cursors.set.iter().any(|cursor| cursor.head == *head){
+                continue
+            }
+            cursors.set.push(Cursor{head:*head, tail:*head, max:max});
+        }
+        cursors.set.sort_by_key(|cursor| cursor.head);
+        cursors.last_cursor = cursors.set.iter().position(|cursor| cursor.head == last).unwrap_or(0);
+    }
+
+    fn parse_normal(keys:&[VimKey])->Result<VimCommand, ParseError>{
+        let mut reader = KeyReader{keys:keys, pos:0};
+        let mut count = reader.count();
+        let mut register = None;
+        if reader.keys.get(reader.pos) == Some(&VimKey::Char('"')){
+            reader.pos += 1;
+            register = Some(reader.next_char()?);
+            count = mul_counts(count, reader.count());
+        }
+        let key = reader.next()?;
+        let action = match key{
+            VimKey::Char(ch) if "dcy<>".contains(ch)=>{
+                let operator = match ch{
+                    'd'=>VimOperator::Delete,
+                    'c'=>VimOperator::Change,
+                    'y'=>VimOperator::Yank,
+                    '>'=>VimOperator::Indent,
+                    _=>VimOperator::Dedent
+                };
+                count = mul_counts(count, reader.count());
+                let target = match reader.next()?{
+                    VimKey::Char(next) if next == ch=>VimTarget::Lines,
+                    VimKey::Char('i')=>VimTarget::Object(Self::parse_object(&mut reader, false)?),
+                    VimKey::Char('a')=>VimTarget::Object(Self::parse_object(&mut reader, true)?),
+                    key=>VimTarget::Motion(Self::parse_motion(&mut reader, key)?)
+                };
+                VimAction::Operate(operator, target)
+            },
+            VimKey::Char('x')=>VimAction::Operate(VimOperator::Delete, VimTarget::Motion(VimMotion::Right)),
+            VimKey::Char('X')=>VimAction::Operate(VimOperator::Delete, VimTarget::Motion(VimMotion::Left)),
+            VimKey::Char('s')=>VimAction::Operate(VimOperator::Change, VimTarget::Motion(VimMotion::Right)),
+            VimKey::Char('S')=>VimAction::Operate(VimOperator::Change, VimTarget::Lines),
+            VimKey::Char('D')=>VimAction::Operate(VimOperator::Delete, VimTarget::Motion(VimMotion::LineEnd)),
+            VimKey::Char('C')=>VimAction::Operate(VimOperator::Change, VimTarget::Motion(VimMotion::LineEnd)),
+            VimKey::Char('Y')=>VimAction::Operate(VimOperator::Yank, VimTarget::Lines),
+            VimKey::Char('i')=>VimAction::Insert(VimInsert::Before),
+            VimKey::Char('a')=>VimAction::Insert(VimInsert::After),
+            VimKey::Char('I')=>VimAction::Insert(VimInsert::LineStart),
+            VimKey::Char('A')=>VimAction::Insert(VimInsert::LineEnd),
+            VimKey::Char('o')=>VimAction::Insert(VimInsert::LineBelow),
+            VimKey::Char('O')=>VimAction::Insert(VimInsert::LineAbove),
+            VimKey::Char('p')=>VimAction::Paste{after:true},
+            VimKey::Char('P')=>VimAction::Paste{after:false},
+            VimKey::Char('J')=>VimAction::Join,
+            VimKey::Char('r')=>VimAction::Replace(reader.next_char()?),
+            VimKey::Char('~')=>VimAction::ToggleCase,
+            VimKey::Char('u')=>VimAction::Undo,
+            VimKey::Ctrl('r')=>VimAction::Redo,
+            VimKey::Char('.')=>VimAction::Repeat,
+            VimKey::Char('v')=>VimAction::Visual(VimMode::Visual),
+            VimKey::Char('V')=>VimAction::Visual(VimMode::VisualLine),
+            VimKey::Ctrl('v')=>VimAction::Visual(VimMode::VisualBlock),
+            key=>VimAction::Move(Self::parse_motion(&mut reader, key)?)
+        };
+        Ok(VimCommand{count:count, register:register, action:action})
+    }
+
+    fn parse_visual(keys:&[VimKey])->Result<VimCommand, ParseError>{
+        let mut reader = KeyReader{keys:keys, pos:0};
+        let mut count = reader.count();
+        let mut register = None;
+        if reader.keys.get(reader.pos) == Some(&VimKey::Char('"')){
+            reader.pos += 1;
+            register = Some(reader.next_char()?);
+            count = mul_counts(count, reader.count());
+        }
+        let operate = |operator, lines|{
+            VimAction::Operate(operator, if lines{VimTarget::Lines}else{VimTarget::Motion(VimMotion::Right)})
+        };
+        // the target of an operator in visual mode is the selection, Lines makes it linewise
+        let action = match reader.next()?{
+            VimKey::Char('d') | VimKey::Char('x')=>operate(VimOperator::Delete, false),
+            VimKey::Char('D') | VimKey::Char('X')=>operate(VimOperator::Delete, true),
+            VimKey::Char('c') | VimKey::Char('s')=>operate(VimOperator::Change, false),
+            VimKey::Char('C') | VimKey::Char('S') | VimKey::Char('R')=>operate(VimOperator::Change, true),
+            VimKey::Char('y')=>operate(VimOperator::Yank, false),
+            VimKey::Char('Y')=>operate(VimOperator::Yank, true),
+            VimKey::Char('>')=>operate(VimOperator::Indent, true),
+            VimKey::Char('<')=>operate(VimOperator::Dedent, true),
+            VimKey::Char('i')=>VimAction::SelectObject(Self::parse_object(&mut reader, false)?),
+            VimKey::Char('a')=>VimAction::SelectObject(Self::parse_object(&mut reader, true)?),
+            VimKey::Char('I')=>VimAction::BlockInsert{append:false},
+            VimKey::Char('A')=>VimAction::BlockInsert{append:true},
+            VimKey::Char('o')=>VimAction::SwapEnds,
+            VimKey::Char('J')=>VimAction::Join,
+            VimKey::Char('~')=>VimAction::ToggleCase,
+            VimKey::Char('u')=>VimAction::Case{upper:false},
+            VimKey::Char('U')=>VimAction::Case{upper:true},
+            VimKey::Char('p') | VimKey::Char('P')=>VimAction::Paste{after:true},
+            VimKey::Char('v')=>VimAction::Visual(VimMode::Visual),
+            VimKey::Char('V')=>VimAction::Visual(VimMode::VisualLine),
+            VimKey::Ctrl('v')=>VimAction::Visual(VimMode::VisualBlock),
+            VimKey::Escape=>return Err(ParseError::Invalid),
+            key=>VimAction::Move(Self::parse_motion(&mut reader, key)?)
+        };
+        Ok(VimCommand{count:count, register:register, action:action})
+    }
+
+    fn parse_motion(reader:&mut KeyReader, key:VimKey)->Result<VimMotion, ParseError>{
+        Ok(match key{
+            VimKey::Char('h') | VimKey::Left | VimKey::Backspace=>VimMotion::Left,
+            VimKey::Char('l') | VimKey::Right | VimKey::Char(' ')=>VimMotion::Right,
+            VimKey::Char('k') | VimKey::Up=>VimMotion::Up,
+            VimKey::Char('j') | VimKey::Down=>VimMotion::Down,
+            VimKey::Char('\n') | VimKey::Char('+')=>VimMotion::NextLine,
+            VimKey::Char('-')=>VimMotion::PrevLine,
+            VimKey::Char('w')=>VimMotion::WordStart{big:false},
+            VimKey::Char('W')=>VimMotion::WordStart{big:true},
+            VimKey::Char('b')=>VimMotion::WordBack{big:false},
+            VimKey::Char('B')=>VimMotion::WordBack{big:true},
+            VimKey::Char('e')=>VimMotion::WordEnd{big:false},
+            VimKey::Char('E')=>VimMotion::WordEnd{big:true},
+            VimKey::Char('0')=>VimMotion::LineStart,
+            VimKey::Char('^')=>VimMotion::FirstNonBlank,
+            VimKey::Char('$')=>VimMotion::LineEnd,
+            VimKey::Char('f')=>VimMotion::Find{ch:reader.next_char()?, forward:true, till:false},
+            VimKey::Char('F')=>VimMotion::Find{ch:reader.next_char()?, forward:false, till:false},
+            VimKey::Char('t')=>VimMotion::Find{ch:reader.next_char()?, forward:true, till:true},
+            VimKey::Char('T')=>VimMotion::Find{ch:reader.next_char()?, forward:false, till:true},
+            VimKey::Char(';')=>VimMotion::RepeatFind{reverse:false},
+            VimKey::Char(',')=>VimMotion::RepeatFind{reverse:true},
+            VimKey::Char('%')=>VimMotion::MatchPair,
+            VimKey::Char('g')=>match reader.next_char()?{
+                'g'=>VimMotion::FileStart,
+                _=>return Err(ParseError::Invalid)
+            },
+            VimKey::Char('G')=>VimMotion::FileEnd,
+            _=>return Err(ParseError::Invalid)
+        })
+    }
+
+    fn parse_object(reader:&mut KeyReader, around:bool)->Result<VimObject, ParseError>{
+        Ok(match reader.next_char()?{
+            'w'=>VimObject::Word{big:false, around:around},
+            'W'=>VimObject::Word{big:true, around:around},
+            '(' | ')' | 'b'=>VimObject::Pair{open:'(', close:')', around:around},
+            '{' | '}' | 'B'=>VimObject::Pair{open:'{', close:'}', around:around},
+            '[' | ']'=>VimObject::Pair{open:'[', close:']', around:around},
+            '<' | '>'=>VimObject::Pair{open:'<', close:'>', around:around},
+            quote @ '"' | quote @ '\'' | quote @ '`'=>VimObject::Quote{quote:quote, around:around},
+            _=>return Err(ParseError::Invalid)
+        })
+    }
+
+    fn is_change(action:&VimAction)->bool{
+        match action{
+            VimAction::Operate(VimOperator::Yank, _)=>false,
+            VimAction::Operate(_, _) | VimAction::Insert(_) | VimAction::Paste{..} | VimAction::Join |
+            VimAction::Replace(_) | VimAction::ToggleCase=>true,
+            _=>false
+        }
+    }
+
+    fn run_normal(&mut self, command:VimCommand, cursors:&mut CursorSet, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer){
+        let count = command.count.unwrap_or(1);
+        let is_change = Self::is_change(&command.action);
+        if is_change{
+            self._change_undo_len = text_buffer.undo_stack.len();
+        }
+        match command.action{
+            VimAction::Move(motion)=>{
+                for cursor in &mut cursors.set{
+                    if let Some(target) = self.eval_motion(motion, command.count, cursor, false, text_buffer){
+                        cursor.head = target.offset;
+                        cursor.tail = target.offset;
+                        cursor.max = target.max;
+                    }
+                }
+            },
+            VimAction::Operate(operator, target)=>{
+                let mut ranges = Vec::new();
+                let mut linewise = false;
+                for cursor in &cursors.set{
+                    if let Some((start, end, is_linewise)) = self.eval_target(operator, target, command.count, cursor, text_buffer){
+                        ranges.push((start, end));
+                        linewise = is_linewise;
+                    }
+                }
+                self.operate(operator, merge_ranges(ranges), linewise, command.register, count, cursors, text_buffer);
+            },
+            VimAction::Insert(insert)=>{
+                self.start_insert(insert, cursors, syntax, text_buffer);
+            },
+            VimAction::Paste{after}=>{
+                self.paste(after, command.register, count, cursors, text_buffer);
+            },
+            VimAction::Join=>{
+                for cursor in &mut cursors.set{
+                    let row = text_buffer.offset_to_text_pos(cursor.head).row;
+                    let last_row = (row + count.max(2) - 1).min(text_buffer.get_line_count() - 1);
+                    cursor.tail = row_start(text_buffer, row);
+                    cursor.head = row_end(text_buffer, last_row);
+                }
+                cursors.join_rows(text_buffer);
+            },
+            VimAction::Replace(ch)=>{
+                let fits = cursors.set.iter().all(|cursor|{
+                    let pos = text_buffer.offset_to_text_pos(cursor.head);
+                    pos.col + count <= text_buffer.get_line(pos.row).len()
+                });
+                if fits{
+                    let text = if ch == '\n'{"\n".to_string()}else{ch.to_string().repeat(count)};
+                    let head = if ch == '\n'{1}else{count - 1};
+                    cursors.replace_each(TextUndoGrouping::Block, text_buffer, |_text_buffer, start, _end|{
+                        CursorEdit{start:start, end:start + count, text:text.clone(), tail:head, head:head}
+                    });
+                }
+            },
+            VimAction::ToggleCase=>{
+                cursors.replace_each(TextUndoGrouping::Block, text_buffer, |text_buffer, start, _end|{
+                    let pos = text_buffer.offset_to_text_pos(start);
+                    let line = text_buffer.get_line(pos.row);
+                    let end_col = (pos.col + count).min(line.len());
+                    let text:String = line.slice(pos.col.min(end_col)..end_col).iter().map(|ch| toggle_case(*ch)).collect();
+                    let len = text.chars().count();
+                    CursorEdit{start:start, end:start + end_col - pos.col.min(end_col), text:text, tail:len, head:len}
+                });
+            },
+            VimAction::Undo | VimAction::Redo=>{
+                for _ in 0..count{
+                    if command.action == VimAction::Undo{
+                        text_buffer.undo(true, cursors);
+                    }
+                    else{
+                        text_buffer.redo(true, cursors);
+                    }
+                }
+                // land on the start of what changed, with one cursor like vim
+                let start = cursors.set[0].order().0;
+                set_heads(cursors, &[start], text_buffer);
+            },
+            VimAction::Repeat=>{
+                if let Some((mut change, insert_keys)) = self._last_change.clone(){
+                    if command.count.is_some(){
+                        change.count = command.count;
+                    }
+                    self._replaying = true;
+                    self.run_normal(change, cursors, syntax, text_buffer);
+                    if self.mode == VimMode::Insert{
+                        for key in &insert_keys{
+                            self.handle_insert_key(*key, cursors, syntax, text_buffer);
+                        }
+                        self._insert_keys = insert_keys;
+                        self.end_insert(cursors, syntax, text_buffer);
+                    }
+                    self._replaying = false;
+                }
+                return
+            },
+            VimAction::Visual(mode)=>{
+                self.set_mode(mode, cursors, text_buffer);
+                return
+            },
+            _=>()
+        }
+        if self.mode == VimMode::Insert{
+            if is_change{
+                self._insert_change = Some(command.clone());
+                self._insert_count = count;
+            }
+            return
+        }
+        if is_change{
+            text_buffer.merge_undo_since(self._change_undo_len);
+            if !self._replaying{
+                self._last_change = Some((command, Vec::new()));
+            }
+        }
+        self.clamp_cursors(cursors, text_buffer);
+    }
+
+    fn run_visual(&mut self, command:VimCommand, cursors:&mut CursorSet, text_buffer:&mut TextBuffer){
+        let count = command.count.unwrap_or(1);
+        match command.action{
+            VimAction::Move(motion)=>{
+                let mut pos = self._visual_pos.clone();
+                if let Some(target) = self.eval_motion(motion, command.count, &pos, false, text_buffer){
+                    pos.head = target.offset;
+                    pos.max = target.max;
+                    self._visual_pos = pos;
+                }
+                self.show_visual(cursors, text_buffer);
+            },
+            VimAction::SelectObject(object)=>{
+                if let Some((start, end)) = Self::eval_object(object, self._visual_pos.head, text_buffer){
+                    if end > start{
+                        self._visual_anchor = start;
+                        self._visual_pos.head = end - 1;
+                        if self.mode == VimMode::VisualBlock{
+                            self.mode = VimMode::Visual;
+                        }
+                        self.show_visual(cursors, text_buffer);
+                    }
+                }
+            },
+            VimAction::SwapEnds=>{
+                std::mem::swap(&mut self._visual_anchor, &mut self._visual_pos.head);
+                self._visual_pos.max = text_buffer.offset_to_text_pos(self._visual_pos.head).col;
+                self.show_visual(cursors, text_buffer);
+            },
+            VimAction::Visual(mode)=>{
+                self.set_mode(if mode == self.mode{VimMode::Normal}else{mode}, cursors, text_buffer);
+            },
+            VimAction::Operate(operator, target)=>{
+                self._change_undo_len = text_buffer.undo_stack.len();
+                let (ranges, linewise) = self.visual_ranges(target == VimTarget::Lines || operator == VimOperator::Indent || operator == VimOperator::Dedent, cursors, text_buffer);
+                self.mode = VimMode::Normal;
+                self.operate(operator, ranges, linewise, command.register, count, cursors, text_buffer);
+                self._visual_insert = self.mode == VimMode::Insert;
+                if self.mode != VimMode::Insert{
+                    // back to the one cursor at the start of the selection
+                    cursors.set.truncate(1);
+                    cursors.last_cursor = 0;
+                    text_buffer.merge_undo_since(self._change_undo_len);
+                    self.clamp_cursors(cursors, text_buffer);
+                }
+            },
+            VimAction::BlockInsert{append}=>{
+                self._change_undo_len = text_buffer.undo_stack.len();
+                let (ranges, _) = self.visual_ranges(false, cursors, text_buffer);
+                let heads:Vec<usize> = ranges.iter().map(|(start, end)| if append{*end}else{*start}).collect();
+                set_heads(cursors, &heads, text_buffer);
+                self.mode = VimMode::Insert;
+                self._visual_insert = true;
+            },
+            VimAction::Join=>{
+                let (ranges, _) = self.visual_ranges(true, cursors, text_buffer);
+                self.mode = VimMode::Normal;
+                let heads:Vec<usize> = ranges.iter().map(|(start, _)| *start).collect();
+                set_heads(cursors, &heads, text_buffer);
+                for (cursor, (_, end)) in cursors.set.iter_mut().zip(&ranges){
+                    cursor.head = (*end).max(cursor.tail + 1) - 1;
+                }
+                cursors.join_rows(text_buffer);
+                self.clamp_cursors(cursors, text_buffer);
+            },
+            VimAction::ToggleCase | VimAction::Case{..}=>{
+                let (ranges, _) = self.visual_ranges(self.mode == VimMode::VisualLine, cursors, text_buffer);
+                let strings:Vec<String> = ranges.iter().map(|(start, end)|{
+                    let text = range_text(text_buffer, *start, *end);
+                    match command.action{
+                        VimAction::Case{upper:true}=>text.to_uppercase(),
+                        VimAction::Case{upper:false}=>text.to_lowercase(),
+                        _=>text.chars().map(toggle_case).collect()
+                    }
+                }).collect();
+                cursors.replace_ranges(&ranges, &strings, text_buffer);
+                let heads:Vec<usize> = ranges.iter().map(|(start, _)| *start).collect();
+                self.mode = VimMode::Normal;
+                set_heads(cursors, &heads, text_buffer);
+                self.clamp_cursors(cursors, text_buffer);
+            },
+            VimAction::Paste{..}=>{
+                let register = self.get_register(command.register.unwrap_or('"')).cloned().unwrap_or_default();
+                let (ranges, _) = self.visual_ranges(self.mode == VimMode::VisualLine, cursors, text_buffer);
+                // the replaced text goes into the unnamed register, like a delete
+                let replaced:Vec<String> = ranges.iter().map(|(start, end)| range_text(text_buffer, *start, *end)).collect();
+                let strings:Vec<String> = ranges.iter().map(|_| register.text.clone()).collect();
+                cursors.replace_ranges(&ranges, &strings, text_buffer);
+                self.set_register(None, replaced.join("\n"), self.mode == VimMode::VisualLine, false);
+                let heads:Vec<usize> = cursors.set.iter().map(|cursor| cursor.order().0).collect();
+                self.mode = VimMode::Normal;
+                set_heads(cursors, &heads, text_buffer);
+                self.clamp_cursors(cursors, text_buffer);
+            },
+            _=>()
+        }
+    }
+
+    // the selection as ranges, block mode has one per row
+    fn visual_ranges(&self, lines:bool, cursors:&CursorSet, text_buffer:&TextBuffer)->(Vec<(usize, usize)>, bool){
+        if lines || self.mode == VimMode::VisualLine{
+            let first = text_buffer.offset_to_text_pos(self._visual_anchor.min(self._visual_pos.head)).row;
+            let last = text_buffer.offset_to_text_pos(self._visual_anchor.max(self._visual_pos.head)).row;
+            return (vec![rows_range(text_buffer, first, last)], true)
+        }
+        (merge_ranges(cursors.set.iter().map(|cursor| cursor.order()).collect()), false)
+    }
+
+    // puts the visual selection in the cursors so it draws, selections include the char under the cursor
+    fn show_visual(&self, cursors:&mut CursorSet, text_buffer:&TextBuffer){
+        let anchor = self._visual_anchor;
+        let pos = self._visual_pos.head;
+        let char_count = text_buffer.get_char_count();
+        cursors.set.truncate(0);
+        match self.mode{
+            VimMode::VisualLine=>{
+                let anchor_row = text_buffer.offset_to_text_pos(anchor).row;
+                let pos_row = text_buffer.offset_to_text_pos(pos).row;
+                let (tail, head) = if pos_row >= anchor_row{
+                    (row_start(text_buffer, anchor_row), (row_end(text_buffer, pos_row) + 1).min(char_count))
+                }
+                else{
+                    ((row_end(text_buffer, anchor_row) + 1).min(char_count), row_start(text_buffer, pos_row))
+                };
+                cursors.set.push(Cursor{head:head, tail:tail, max:0});
+            },
+            VimMode::VisualBlock=>{
+                let a = text_buffer.offset_to_text_pos(anchor);
+                let p = text_buffer.offset_to_text_pos(pos);
+                let (a_col, p_col) = if p.col >= a.col{(a.col, p.col + 1)}else{(a.col + 1, p.col)};
+                cursors.grid_select(TextPos{row:a.row, col:a_col}, TextPos{row:p.row, col:p_col}, text_buffer);
+                if cursors.set.len() == 0{
+                    cursors.set.push(Cursor{head:pos, tail:pos, max:0});
+                }
+            },
+            _=>{
+                let (tail, head) = if pos >= anchor{(anchor, (pos + 1).min(char_count))}else{((anchor + 1).min(char_count), pos)};
+                cursors.set.push(Cursor{head:head, tail:tail, max:0});
+            }
+        }
+        cursors.last_cursor = cursors.set.len() - 1;
+    }
+
+    fn set_register(&mut self, register:Option<char>, text:String, linewise:bool, is_yank:bool){
+        let name = register.unwrap_or('"');
+        if name == '_'{
+            return
+        }
+        if name.is_ascii_uppercase(){ // appends to the lowercase one
+            let reg = self.registers.entry(name.to_ascii_lowercase()).or_default();
+            reg.text.push_str(&text);
+            reg.linewise |= linewise;
+        }
+        else if name != '"'{
+            self.registers.insert(name, VimRegister{text:text.clone(), linewise:linewise});
+        }
+        if name == '+' || name == '*'{
+            self._clipboard = Some(text.clone());
+        }
+        if is_yank && register.is_none(){
+            self.registers.insert('0', VimRegister{text:text.clone(), linewise:linewise});
+        }
+        self.registers.insert('"', VimRegister{text:text, linewise:linewise});
+    }
+
+    fn operate(&mut self, operator:VimOperator, ranges:Vec<(usize, usize)>, linewise:bool, register:Option<char>, count:usize, cursors:&mut CursorSet, text_buffer:&mut TextBuffer){
+        if ranges.len() == 0{
+            return
+        }
+        let text = if linewise{
+            ranges.iter().map(|(start, end)|{
+                let first = text_buffer.offset_to_text_pos(*start + if *start > 0 && char_at(text_buffer, *start) == '\n' && *end == text_buffer.get_char_count(){1}else{0}).row;
+                let last = text_buffer.offset_to_text_pos((*end).max(*start + 1) - 1).row;
+                rows_text(text_buffer, first, last.max(first))
+            }).collect::<Vec<String>>().join("")
+        }
+        else{
+            ranges.iter().map(|(start, end)| range_text(text_buffer, *start, *end)).collect::<Vec<String>>().join("\n")
+        };
+        match operator{
+            VimOperator::Yank=>{
+                self.set_register(register, text, linewise, true);
+                let heads:Vec<usize> = ranges.iter().map(|(start, _)|{
+                    if linewise{first_non_blank(text_buffer, text_buffer.offset_to_text_pos(*start).row)}else{*start}
+                }).collect();
+                let last = heads.last().cloned();
+                // yanking lines down keeps the cursor where it is
+                if !linewise || last.map(|head| head < cursors.set[cursors.last_cursor].head).unwrap_or(false){
+                    set_heads(cursors, &heads, text_buffer);
+                }
+            },
+            VimOperator::Delete=>{
+                self.set_register(register, text, linewise, false);
+                let strings:Vec<String> = ranges.iter().map(|_| String::new()).collect();
+                cursors.replace_ranges(&ranges, &strings, text_buffer);
+                if linewise{
+                    let heads:Vec<usize> = cursors.set.iter().map(|cursor|{
+                        let row = text_buffer.offset_to_text_pos(cursor.head.min(text_buffer.get_char_count())).row;
+                        first_non_blank(text_buffer, row)
+                    }).collect();
+                    set_heads(cursors, &heads, text_buffer);
+                }
+                else{
+                    let heads:Vec<usize> = cursors.set.iter().map(|cursor| cursor.head).collect();
+                    set_heads(cursors, &heads, text_buffer);
+                }
+            },
+            VimOperator::Change=>{
+                self.set_register(register, text, linewise, false);
+                if linewise{
+                    // the rows become one row with the indent of the first
+                    let mut line_ranges = Vec::new();
+                    let mut strings = Vec::new();
+                    for (start, end) in &ranges{
+                        let first = text_buffer.offset_to_text_pos(*start + if char_at(text_buffer, *start) == '\n' && *start > 0{1}else{0}).row;
+                        let last = text_buffer.offset_to_text_pos((*end).max(*start + 1) - 1).row.max(first);
+                        let line = text_buffer.get_line(first);
+                        let indent:String = line.iter().take_while(|ch| **ch == ' ' || **ch == '\t').collect();
+                        line_ranges.push((row_start(text_buffer, first), row_end(text_buffer, last)));
+                        strings.push(indent);
+                    }
+                    cursors.replace_ranges(&line_ranges, &strings, text_buffer);
+                    let heads:Vec<usize> = cursors.set.iter().map(|cursor| cursor.head).collect();
+                    set_heads(cursors, &heads, text_buffer);
+                }
+                else{
+                    let strings:Vec<String> = ranges.iter().map(|_| String::new()).collect();
+                    cursors.replace_ranges(&ranges, &strings, text_buffer);
+                    let heads:Vec<usize> = cursors.set.iter().map(|cursor| cursor.head).collect();
+                    set_heads(cursors, &heads, text_buffer);
+                }
+                self.mode = VimMode::Insert;
+                self._insert_keys.truncate(0);
+            },
+            VimOperator::Indent | VimOperator::Dedent=>{
+                let mut heads = Vec::new();
+                cursors.set.truncate(0);
+                for (start, end) in &ranges{
+                    let first = text_buffer.offset_to_text_pos(*start + if char_at(text_buffer, *start) == '\n' && *start > 0{1}else{0}).row;
+                    let last = text_buffer.offset_to_text_pos((*end).max(*start + 1) - 1).row.max(first);
+                    heads.push(first);
+                    cursors.set.push(Cursor{tail:row_start(text_buffer, first), head:row_end(text_buffer, last), max:0});
+                }
+                cursors.last_cursor = cursors.set.len() - 1;
+                for _ in 0..count.min(20){
+                    cursors.indent_rows(operator == VimOperator::Dedent, text_buffer);
+                }
+                let heads:Vec<usize> = heads.iter().map(|row| first_non_blank(text_buffer, *row)).collect();
+                set_heads(cursors, &heads, text_buffer);
+            }
+        }
+    }
+
+    fn start_insert(&mut self, insert:VimInsert, cursors:&mut CursorSet, syntax:&LanguageSyntax, text_buffer:&mut TextBuffer){
+        self._insert_keys.truncate(0);
+        let heads:Vec<usize> = cursors.set.iter().map(|cursor|{
+            let pos = text_buffer.offset_to_text_pos(cursor.head);
+            match insert{
+                VimInsert::After=>cursor.head + if pos.col < text_buffer.get_line(pos.row).len(){1}else{0},
+                VimInsert::LineStart=>first_non_blank(text_buffer, pos.row),
+                VimInsert::LineEnd | VimInsert::LineBelow=>row_end(text_buffer, pos.row),
+                VimInsert::LineAbove=>row_start(text_buffer, pos.row),
+                VimInsert::Before=>cursor.head
+            }
+        }).collect();
+        set_heads(cursors, &heads, text_buffer);
+        match insert{
+            VimInsert::LineBelow=>cursors.insert_newline(syntax, text_buffer),
+            VimInsert::LineAbove=>{
+                cursors.replace_each(TextUndoGrouping::Newline, text_buffer, |text_buffer, start, _end|{
+                    let line = text_buffer.get_line(text_buffer.offset_to_text_pos(start).row);
+                    let indent:String = line.iter().take_while(|ch| **ch == ' ' || **ch == '\t').collect();
+                    let len = indent.len();
+                    CursorEdit{start:start, end:start, text:format!("{}\n", indent), tail:len, head:len}
+                });
+            },
+            _=>()
+        }
+        self.mode = VimMode::Insert;
+    }
+
+    fn paste(&mut self, after:bool, register:Option<char>, count:usize, cursors:&mut CursorSet, text_buffer:&mut TextBuffer){
+        let register = match self.get_register(register.unwrap_or('"')){
+            Some(register)=>register.clone(),
+            None=>return
+        };
+        let text:String = register.text.repeat(count);
+        if text.len() == 0{
+            return
+        }
+        let char_count = text.chars().count();
+        cursors.replace_each(TextUndoGrouping::Block, text_buffer, |text_buffer, start, _end|{
+            let pos = text_buffer.offset_to_text_pos(start);
+            let line_len = text_buffer.get_line(pos.row).len();
+            if register.linewise{
+                // lands on the first non blank of the first pasted row
+                let indent = text.chars().take_while(|ch| *ch == ' ' || *ch == '\t').count();
+                if !after{
+                    let at = row_start(text_buffer, pos.row);
+                    return CursorEdit{start:at, end:at, text:text.clone(), tail:indent, head:indent}
+                }
+                if pos.row + 1 < text_buffer.get_line_count(){
+                    let at = row_start(text_buffer, pos.row + 1);
+                    return CursorEdit{start:at, end:at, text:text.clone(), tail:indent, head:indent}
+                }
+                let at = row_end(text_buffer, pos.row);
+                let text = format!("\n{}", text.trim_end_matches('\n'));
+                return CursorEdit{start:at, end:at, text:text, tail:indent + 1, head:indent + 1}
+            }
+            let at = if after && pos.col < line_len{start + 1}else{start};
+            CursorEdit{start:at, end:at, text:text.clone(), tail:char_count - 1, head:char_count - 1}
+        });
+    }
+
+    // where an operator works, (start, end, linewise)
+    fn eval_target(&mut self, operator:VimOperator, target:VimTarget, count:Option<usize>, cursor:&Cursor, text_buffer:&TextBuffer)->Option<(usize, usize, bool)>{
+        let head = cursor.head;
+        match target{
+            VimTarget::Lines=>{
+                let row = text_buffer.offset_to_text_pos(head).row;
+                let last = (row + count.unwrap_or(1) - 1).min(text_buffer.get_line_count() - 1);
+                let (start, end) = rows_range(text_buffer, row, last);
+                Some((start, end, true))
+            },
+            VimTarget::Object(object)=>{
+                Self::eval_object(object, head, text_buffer).map(|(start, end)| (start, end, false))
+            },
+            VimTarget::Motion(motion)=>{
+                // cw changes to the end of the word, like ce
+                let motion = match motion{
+                    VimMotion::WordStart{big} if operator == VimOperator::Change && char_class(char_at(text_buffer, head), big) != 0=>{
+                        VimMotion::WordEnd{big:big}
+                    },
+                    motion=>motion
+                };
+                let target = self.eval_motion(motion, count, cursor, true, text_buffer)?;
+                match target.kind{
+                    MotionKind::Linewise=>{
+                        let row = text_buffer.offset_to_text_pos(head).row;
+                        let target_row = text_buffer.offset_to_text_pos(target.offset).row;
+                        let (start, end) = rows_range(text_buffer, row.min(target_row), row.max(target_row));
+                        Some((start, end, true))
+                    },
+                    MotionKind::Inclusive=>{
+                        let end = (head.max(target.offset) + 1).min(text_buffer.get_char_count());
+                        Some((head.min(target.offset), end, false))
+                    },
+                    MotionKind::Exclusive=>{
+                        if head == target.offset{
+                            return None
+                        }
+                        Some((head.min(target.offset), head.max(target.offset), false))
+                    }
+                }
+            }
+        }
+    }
+
+    fn eval_motion(&mut self, motion:VimMotion, count:Option<usize>, cursor:&Cursor, for_operator:bool, text_buffer:&TextBuffer)->Option<MotionTarget>{
+        let n = count.unwrap_or(1);
+        let char_count = text_buffer.get_char_count();
+        let head = cursor.head;
+        let pos = text_buffer.offset_to_text_pos(head);
+        let last_row = text_buffer.get_line_count() - 1;
+        let exclusive = |offset:usize| Some(MotionTarget{offset:offset, max:text_buffer.offset_to_text_pos(offset).col, kind:MotionKind::Exclusive});
+        let inclusive = |offset:usize| Some(MotionTarget{offset:offset, max:text_buffer.offset_to_text_pos(offset).col, kind:MotionKind::Inclusive});
+        let linewise = |row:usize, offset:usize, max:usize| Some(MotionTarget{offset:offset, max:max, kind:MotionKind::Linewise}).filter(|_| row <= last_row);
+        match motion{
+            VimMotion::Left=>exclusive(head - pos.col.min(n)),
+            VimMotion::Right=>{
+                let len = text_buffer.get_line(pos.row).len();
+                exclusive(head + (len - pos.col.min(len)).min(n))
+            },
+            VimMotion::Up | VimMotion::Down=>{
+                let row = if motion == VimMotion::Up{pos.row.saturating_sub(n)}else{(pos.row + n).min(last_row)};
+                if row == pos.row && !for_operator{
+                    return None
+                }
+                linewise(row, text_buffer.text_pos_to_offset(TextPos{row:row, col:cursor.max}), cursor.max)
+            },
+            VimMotion::NextLine | VimMotion::PrevLine=>{
+                let row = if motion == VimMotion::PrevLine{pos.row.saturating_sub(n)}else{(pos.row + n).min(last_row)};
+                let offset = first_non_blank(text_buffer, row);
+                linewise(row, offset, text_buffer.offset_to_text_pos(offset).col)
+            },
+            VimMotion::WordStart{big}=>{
+                let mut offset = head;
+                let mut prev = head;
+                for _ in 0..n{
+                    prev = offset;
+                    let class = char_class(char_at(text_buffer, offset), big);
+                    if class != 0{
+                        while offset < char_count && char_class(char_at(text_buffer, offset), big) == class{
+                            offset += 1;
+                        }
+                    }
+                    while offset < char_count && char_class(char_at(text_buffer, offset), big) == 0{
+                        offset += 1;
+                        // empty lines count as words
+                        if is_empty_line(text_buffer, offset){
+                            break
+                        }
+                    }
+                }
+                // dw on the last word of a line stops at the line end
+                if for_operator && text_buffer.offset_to_text_pos(offset.min(char_count)).row > text_buffer.offset_to_text_pos(prev).row{
+                    offset = row_end(text_buffer, text_buffer.offset_to_text_pos(prev).row).max(prev);
+                }
+                exclusive(offset.min(char_count))
+            },
+            VimMotion::WordBack{big}=>{
+                let mut offset = head;
+                for _ in 0..n{
+                    if offset == 0{
+                        break
+                    }
+                    offset -= 1;
+                    while offset > 0 && char_class(char_at(text_buffer, offset), big) == 0 && !is_empty_line(text_buffer, offset){
+                        offset -= 1;
+                    }
+                    let class = char_class(char_at(text_buffer, offset), big);
+                    if class != 0{
+                        while offset > 0 && char_class(char_at(text_buffer, offset - 1), big) == class{
+                            offset -= 1;
+                        }
+                    }
+                }
+                exclusive(offset)
+            },
+            VimMotion::WordEnd{big}=>{
+                let mut offset = head;
+                for _ in 0..n{
+                    offset += 1;
+                    while offset < char_count && char_class(char_at(text_buffer, offset), big) == 0{
+                        offset += 1;
+                    }
+                    let class = char_class(char_at(text_buffer, offset), big);
+                    while offset + 1 < char_count && char_class(char_at(text_buffer, offset + 1), big) == class{
+                        offset += 1;
+                    }
+                }
+                inclusive(offset.min(char_count.max(1) - 1))
+            },
+            VimMotion::LineStart=>exclusive(head - pos.col),
+            VimMotion::FirstNonBlank=>exclusive(first_non_blank(text_buffer, pos.row)),
+            VimMotion::LineEnd=>{
+                let row = (pos.row + n - 1).min(last_row);
+                let end = row_end(text_buffer, row);
+                let offset = if end > row_start(text_buffer, row){end - 1}else{end};
+                Some(MotionTarget{offset:offset, max:std::usize::MAX, kind:MotionKind::Inclusive})
+            },
+            VimMotion::Find{ch, forward, till}=>{
+                self._last_find = Some((ch, forward, till));
+                Self::find_in_line(ch, forward, till, false, n, head, text_buffer)
+            },
+            VimMotion::RepeatFind{reverse}=>{
+                let (ch, forward, till) = self._last_find?;
+                Self::find_in_line(ch, forward != reverse, till, true, n, head, text_buffer)
+            },
+            VimMotion::MatchPair=>{
+                let line = text_buffer.get_line(pos.row);
+                let col = (pos.col..line.len()).find(|col| "()[]{}".contains(line[*col]))?;
+                let start = head - pos.col + col;
+                inclusive(Self::find_match(start, text_buffer)?)
+            },
+            VimMotion::FileStart | VimMotion::FileEnd=>{
+                let row = match count{
+                    Some(count)=>(count.max(1) - 1).min(last_row),
+                    None=>if motion == VimMotion::FileStart{0}else{last_row}
+                };
+                let offset = first_non_blank(text_buffer, row);
+                linewise(row, offset, text_buffer.offset_to_text_pos(offset).col)
+            }
+        }
+    }
+
+    fn find_in_line(ch:char, forward:bool, till:bool, repeat:bool, count:usize, head:usize, text_buffer:&TextBuffer)->Option<MotionTarget>{
+        let pos = text_buffer.offset_to_text_pos(head);
+        let line = text_buffer.get_line(pos.row);
+        let mut col = pos.col;
+        // repeating a t search skips the char its standing in front of
+        let skip = if till && repeat{2}else{1};
+        for i in 0..count{
+            let step = if i == 0{skip}else{1};
+            if forward{
+                col = (col + step..line.len()).find(|c| line[*c] == ch)?;
+            }
+            else{
+                col = (0..(col + 1).saturating_sub(step)).rev().find(|c| line[*c] == ch)?;
+            }
+        }
+        if till{
+            col = if forward{col - 1}else{col + 1};
+        }
+        let offset = head - pos.col + col;
+        Some(MotionTarget{offset:offset, max:col, kind:if forward{MotionKind::Inclusive}else{MotionKind::Exclusive}})
+    }
+
+    // the bracket matching the one at offset
+    fn find_match(offset:usize, text_buffer:&TextBuffer)->Option<usize>{
+        let ch = char_at(text_buffer, offset);
+        let (open, close, forward) = match ch{
+            '('=>('(', ')', true),
+            '['=>('[', ']', true),
+            '{'=>('{', '}', true),
+            ')'=>('(', ')', false),
+            ']'=>('[', ']', false),
+            '}'=>('{', '}', false),
+            _=>return None
+        };
+        let char_count = text_buffer.get_char_count();
+        let mut depth = 0;
+        let mut at = offset;
+        loop{
+            let ch = char_at(text_buffer, at);
+            if ch == open{
+                if forward{depth += 1}else{depth -= 1}
+            }
+            else if ch == close{
+                if forward{depth -= 1}else{depth += 1}
+            }
+            if depth == 0{
+                return Some(at)
+            }
+            if forward{
+                at += 1;
+                if at >= char_count{
+                    return None
+                }
+            }
+            else{
+                if at == 0{
+                    return None
+                }
+                at -= 1;
+            }
+        }
+    }
+
+    // the (start, end) range of a text object around offset
+    pub fn eval_object(object:VimObject, offset:usize, text_buffer:&TextBuffer)->Option<(usize, usize)>{
+        let pos = text_buffer.offset_to_text_pos(offset);
+        let line = text_buffer.get_line(pos.row);
+        let line_start = offset - pos.col;
+        match object{
+            VimObject::Word{big, around}=>{
+                if line.len() == 0{
+                    return None
+                }
+                let col = pos.col.min(line.len() - 1);
+                let class = char_class(line[col], big);
+                let mut start = col;
+                while start > 0 && char_class(line[start - 1], big) == class{
+                    start -= 1;
+                }
+                let mut end = col + 1;
+                while end < line.len() && char_class(line[end], big) == class{
+                    end += 1;
+                }
+                if around{
+                    if class == 0{ // whitespace takes the word after it
+                        let next = if end < line.len(){char_class(line[end], big)}else{0};
+                        while end < line.len() && char_class(line[end], big) == next && next != 0{
+                            end += 1;
+                        }
+                    }
+                    else if end < line.len() && char_class(line[end], big) == 0{
+                        while end < line.len() && char_class(line[end], big) == 0{
+                            end += 1;
+                        }
+                    }
+                    else{
+                        while start > 0 && char_class(line[start - 1], big) == 0{
+                            start -= 1;
+                        }
+                    }
+                }
+                Some((line_start + start, line_start + end))
+            },
+            VimObject::Pair{open, close, around}=>{
+                // walk back to the opener we are in
+                let mut depth = 0;
+                let mut at = offset;
+                let start = loop{
+                    let ch = char_at(text_buffer, at);
+                    if ch == open{
+                        if depth == 0{
+                            break at
+                        }
+                        depth -= 1;
+                    }
+                    else if ch == close && at != offset{
+                        depth += 1;
+                    }
+                    if at == 0{
+                        return None
+                    }
+                    at -= 1;
+                };
+                let end = Self::find_pair_end(start, open, close, text_buffer)?;
+                if around{
+                    return Some((start, end + 1))
+                }
+                let mut inner_start = start + 1;
+                let mut inner_end = end;
+                // a block over several rows keeps the rows of its brackets
+                if char_at(text_buffer, inner_start) == '\n'{
+                    inner_start += 1;
+                    let end_pos = text_buffer.offset_to_text_pos(end);
+                    let end_line = text_buffer.get_line(end_pos.row);
+                    if end_line.slice(0..end_pos.col).iter().all(|ch| ch.is_whitespace()) && inner_end - end_pos.col > inner_start{
+                        inner_end -= end_pos.col;
+                    }
+                }
+                Some((inner_start.min(inner_end), inner_end))
+            },
+            VimObject::Quote{quote, around}=>{
+                let quotes:Vec<usize> = (0..line.len()).filter(|col|{
+                    line[*col] == quote && (*col == 0 || line[*col - 1] != '\\')
+                }).collect();
+                // quotes pair up from the line start, use the pair around or else after the cursor
+                let pair = quotes.chunks(2).filter(|pair| pair.len() == 2).find(|pair| pos.col <= pair[1])?;
+                let (open, close) = (pair[0], pair[1]);
+                if around{
+                    let mut end = close + 1;
+                    while end < line.len() && (line[end] == ' ' || line[end] == '\t'){
+                        end += 1;
+                    }
+                    return Some((line_start + open, line_start + end))
+                }
+                Some((line_start + open + 1, line_start + close))
+            }
+        }
+    }
+
+    fn find_pair_end(start:usize, open:char, close:char, text_buffer:&TextBuffer)->Option<usize>{
+        let char_count = text_buffer.get_char_count();
+        let mut depth = 0;
+        for at in start..char_count{
+            let ch = char_at(text_buffer, at);
+            if ch == open{
+                depth += 1;
+            }
+            else if ch == close{
+                depth -= 1;
+                if depth == 0{
+                    return Some(at)
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,86 @@
+// Auto-indent, auto-closing pairs and block indenting as typed into the rust mode.
+// run with: cargo test --test autoindent
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+fn type_text(source:&str, input:&str)->String{
+    let syntax = RustTokenizer{..Default::default()}.get_syntax();
+    let (mut text_buffer, mut cursors) = load_cursors(source);
+    for ch in input.chars(){
+        cursors.type_char(ch, &syntax, &mut text_buffer);
+    }
+    show(&text_buffer, &cursors)
+}
+
+#[test]
+fn newline_keeps_and_adds_indent(){
+    assert_eq!(type_text("    let a = 1;|", "\n"), "    let a = 1;\n    |");
+    assert_eq!(type_text("fn a(){|", "\n"), "fn a(){\n    |");
+    assert_eq!(type_text("    if a{|", "\n"), "    if a{\n        |");
+    // between a pair the closer moves down
+    assert_eq!(type_text("fn a(){|}", "\n"), "fn a(){\n    |\n}");
+    assert_eq!(type_text("  |  a", "\n"), "  \n  |  a");
+}
+
+#[test]
+fn closer_lines_up_with_its_opener(){
+    assert_eq!(type_text("fn a(){\n    if b{\n        c();\n        |", "}"), "fn a(){\n    if b{\n        c();\n    }|");
+    assert_eq!(type_text("fn a(){\n    |", "}"), "fn a(){\n}|");
+    // after text it is just typed
+    assert_eq!(type_text("fn a(){\n    b|", "}"), "fn a(){\n    b}|");
+}
+
+#[test]
+fn pairs_auto_close_and_step_over(){
+    assert_eq!(type_text("a|", "("), "a(|)");
+    assert_eq!(type_text("a|", "(b)"), "a(b)|");
+    assert_eq!(type_text("|", "\"s\""), "\"s\"|");
+    // not in front of a word, and quotes not right after one
+    assert_eq!(type_text("|b", "("), "(|b");
+    assert_eq!(type_text("b|", "\""), "b\"|");
+    // the rust mode leaves lifetimes alone
+    assert_eq!(type_text("&|", "'"), "&'|");
+    assert_eq!(type_text("fn a()|", "{\n"), "fn a(){\n    |\n}");
+}
+
+#[test]
+fn pairs_wrap_the_selection(){
+    let syntax = RustTokenizer{..Default::default()}.get_syntax();
+    let (mut text_buffer, mut cursors) = load_cursors("let a = b + c;");
+    cursors.set = vec![Cursor{head:13, tail:8, max:0}];
+    cursors.type_char('(', &syntax, &mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), "let a = ([b + c]);");
+}
+
+#[test]
+fn tab_indents_selected_rows_of_every_cursor(){
+    let (mut text_buffer, mut cursors) = load_cursors("a\nb\n\nc\nd");
+    // one cursor over a..b, the second on d
+    cursors.set = vec![Cursor{head:0, tail:3, max:0}, Cursor{head:7, tail:7, max:0}];
+    assert!(cursors.spans_rows(&text_buffer));
+    cursors.indent_rows(false, &mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), "[    a\n    b]\n\nc\n    |d");
+
+    cursors.indent_rows(true, &mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), "[a\nb]\n\nc\n|d");
+
+    // both directions were a single undo step
+    text_buffer.undo(true, &mut cursors);
+    assert_eq!(text_buffer.get_as_string(), "    a\n    b\n\nc\n    d");
+    text_buffer.undo(true, &mut cursors);
+    assert_eq!(show(&text_buffer, &cursors), "[a\nb]\n\nc\n|d");
+}
+
+#[test]
+fn tab_pads_to_the_next_stop(){
+    let (mut text_buffer, mut cursors) = load_cursors("ab|c\n|");
+    assert!(!cursors.spans_rows(&text_buffer));
+    cursors.insert_tab(&mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), "ab  |c\n    |");
+    // shift tab dedents partial indents too
+    let (mut text_buffer, mut cursors) = load_cursors("  |a");
+    cursors.indent_rows(true, &mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), "|a");
+}
@@ -0,0 +1,42 @@
+// Copying from and pasting into multiple cursors.
+// run with: cargo test --test clipboard
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+fn cursors(ranges:&[(usize, usize)])->CursorSet{
+    let mut cursors = CursorSet::new();
+    cursors.set = ranges.iter().map(|(tail, head)| Cursor{head:*head, tail:*tail, max:0}).collect();
+    cursors
+}
+
+#[test]
+fn copy_joins_cursors_with_newlines(){
+    let text_buffer = load_buffer("one two\nthree");
+    assert_eq!(cursors(&[(0, 3)]).get_all_as_string(&text_buffer), "one");
+    assert_eq!(cursors(&[(0, 3), (4, 7), (8, 13)]).get_all_as_string(&text_buffer), "one\ntwo\nthree");
+}
+
+#[test]
+fn paste_a_line_per_cursor(){
+    let mut text_buffer = load_buffer("a = 1;\nb = 2;");
+    let mut set = cursors(&[(4, 5), (11, 12)]);
+    set.replace_lines(&["10", "20"], &mut text_buffer);
+    assert_eq!(text_buffer.get_as_string(), "a = 10;\nb = 20;");
+    assert_eq!(set.set.iter().map(|c| (c.tail, c.head)).collect::<Vec<_>>(), vec![(6, 6), (14, 14)]);
+
+    // the paste is one undo step
+    text_buffer.undo(true, &mut set);
+    assert_eq!(text_buffer.get_as_string(), "a = 1;\nb = 2;");
+}
+
+#[test]
+fn copy_then_paste_round_trips(){
+    let mut text_buffer = load_buffer("x y");
+    let mut set = cursors(&[(0, 1), (2, 3)]);
+    let copied = set.get_all_as_string(&text_buffer);
+    let lines:Vec<&str> = copied.lines().collect();
+    set.replace_lines(&lines, &mut text_buffer);
+    assert_eq!(text_buffer.get_as_string(), "x y");
+}
@@ -0,0 +1,150 @@
+// Bracket matching, folding and the outline as built from the rust token stream.
+// run with: cargo test --test codestructure
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+fn load(source:&str)->(TextBuffer, TokenCache<RustTokenizer>, CodeStructure){
+    let (text_buffer, token_cache) = load_tokens(source, RustTokenizer{..Default::default()});
+    let syntax = token_cache.tokenizer.get_syntax();
+    let mut structure = CodeStructure{..Default::default()};
+    structure.update(&text_buffer, &token_cache, &syntax);
+    (text_buffer, token_cache, structure)
+}
+
+fn get_outline(source:&str)->Vec<(String, String, usize)>{
+    let (_, _, structure) = load(source);
+    structure.outline.iter().map(|item| (item.kind.clone(), item.name.clone(), item.depth)).collect()
+}
+
+fn item(kind:&str, name:&str, depth:usize)->(String, String, usize){
+    (kind.to_string(), name.to_string(), depth)
+}
+
+#[test]
+fn matching_brackets(){
+    let source = "fn a(b:[u8;2]){\n    c(\"(\", ')');\n}";
+    let (_, _, structure) = load(source);
+    let find = |pat:&str| source.find(pat).unwrap();
+    assert_eq!(structure.get_matching_bracket(find("(b")), Some(find("){")));
+    assert_eq!(structure.get_matching_bracket(find("){")), Some(find("(b")));
+    assert_eq!(structure.get_matching_bracket(find("[u8")), Some(find("])") ));
+    assert_eq!(structure.get_matching_bracket(find("{\n")), Some(source.len() - 1));
+    // brackets in strings and chars don't count
+    assert_eq!(structure.get_matching_bracket(find("c(") + 1), Some(find(");")));
+    assert_eq!(structure.get_matching_bracket(find("u8")), None);
+}
+
+#[test]
+fn unmatched_brackets(){
+    let source = "a(b[c)d]";
+    let (_, _, structure) = load(source);
+    // the [ left open inside the () pair is dropped, the stray ] matches nothing
+    assert_eq!(structure.get_matching_bracket(1), Some(5));
+    assert_eq!(structure.get_matching_bracket(3), None);
+    assert_eq!(structure.get_matching_bracket(7), None);
+}
+
+#[test]
+fn folds(){
+    let source = "fn a(){\n    b(c, d{\n        e\n    });\n    f{}\n}\nfn g(){\n}";
+    let (_, _, structure) = load(source);
+    assert_eq!(structure.folds, vec![(0, 5), (1, 3)]);
+    assert_eq!(structure.get_fold(0), Some(5));
+    assert_eq!(structure.get_fold(4), None);
+    // a block with nothing to hide doesn't fold
+    assert_eq!(structure.get_fold(6), None);
+    assert_eq!(structure.get_enclosing_fold(2), Some((1, 3)));
+    assert_eq!(structure.get_enclosing_fold(4), Some((0, 5)));
+    assert_eq!(structure.get_enclosing_fold(5), None);
+}
+
+#[test]
+fn outline_items(){
+    let outline = get_outline("
+mod a{
+    pub struct B<T>{
+        c:fn(usize)->bool
+    }
+    pub(crate) enum C{D}
+    trait E{
+        fn f(&self);
+        fn g(&self){}
+    }
+    impl<T> Style for B<T>
+    where T: Clone
+    {
+        unsafe fn h(x:[u8;4])->impl Iterator<Item=u8>{
+            let i = |j| j;
+        }
+    }
+}
+fn k<T>(){}
+");
+    assert_eq!(outline, vec![
+        item("mod", "a", 0),
+        item("struct", "B", 1),
+        item("enum", "C", 1),
+        item("trait", "E", 1),
+        item("fn", "f", 2),
+        item("fn", "g", 2),
+        item("impl", "Style for B<T>", 1),
+        item("fn", "h", 2),
+        item("fn", "k", 0),
+    ]);
+}
+
+#[test]
+fn rebuilds_after_edits(){
+    let (mut text_buffer, mut token_cache, mut structure) = load("fn a(){\n}");
+    assert_eq!(structure.outline.len(), 1);
+    text_buffer.replace_with_string(0, 0, "struct B;\n");
+    token_cache.update(&text_buffer);
+    structure.update(&text_buffer, &token_cache, &RustTokenizer{..Default::default()}.get_syntax());
+    assert_eq!(structure.outline.iter().map(|item| item.row).collect::<Vec<_>>(), vec![0, 1]);
+    assert_eq!(structure.get_matching_bracket(14), Some(15));
+}
+
+fn update(text_buffer:&TextBuffer, token_cache:&mut TokenCache<RustTokenizer>, structure:&mut CodeStructure){
+    token_cache.update(text_buffer);
+    structure.update(text_buffer, token_cache, &RustTokenizer{..Default::default()}.get_syntax());
+}
+
+#[test]
+fn rescans_until_it_converges(){
+    let source:String = (0..100).map(|i| format!("fn f{}(){{\n    g({});\n}}\n", i, i)).collect();
+    let (mut text_buffer, mut token_cache, mut structure) = load(&source);
+    assert_eq!(structure._rescanned_lines, 300 + 1);
+    // typing inside a line only rescans that line
+    text_buffer.replace_with_string(source.find("g(50)").unwrap(), 0, "x");
+    update(&text_buffer, &mut token_cache, &mut structure);
+    assert_eq!(structure._rescanned_lines, 1);
+    // a new line rescans up to where its block closes
+    text_buffer.replace_with_string(source.find("g(50)").unwrap(), 0, "\n");
+    update(&text_buffer, &mut token_cache, &mut structure);
+    assert_eq!(structure._rescanned_lines, 3);
+    assert_eq!(structure.outline.iter().find(|item| item.name == "f51").unwrap().row, 154);
+    // the lines a block comment swallows change their tokens, not their text
+    text_buffer.replace_with_string(0, 0, "/*");
+    update(&text_buffer, &mut token_cache, &mut structure);
+    assert_eq!(structure.outline.len(), 0);
+}
+
+#[test]
+fn edits_match_a_rebuild(){
+    let source:String = (0..20).map(|i| format!("impl A{}{{\n    fn f(){{\n        g(\"{{\");\n    }}\n}}\n", i)).collect();
+    let (mut text_buffer, mut token_cache, mut structure) = load(&source);
+    let pieces = ["{", "}", "(", ")", ";", "\n", "fn a", "struct B;", "/*", "*/", "\"", "x"];
+    let mut rng = Lcg(9);
+    for _ in 0..300{
+        let offset = rng.next(text_buffer.get_char_count());
+        let len = rng.next(4).min(text_buffer.get_char_count() - offset);
+        text_buffer.replace_with_string(offset, len, pieces[rng.next(pieces.len())]);
+        update(&text_buffer, &mut token_cache, &mut structure);
+        let (_, _, rebuilt) = load(&text_buffer.get_as_string());
+        assert_eq!(structure.bracket_pairs, rebuilt.bracket_pairs);
+        assert_eq!(structure.folds, rebuilt.folds);
+        assert_eq!(structure.outline, rebuilt.outline);
+    }
+}
@@ -0,0 +1,137 @@
+// The editor sources the tests build on, and the fixtures they share.
+// a test file starts with: mod common; use common::*;
+#![allow(dead_code, unused_imports)]
+
+#[path = "../../src/textlines.rs"]
+pub mod textlines;
+#[path = "../../src/textbuffer.rs"]
+pub mod textbuffer;
+#[path = "../../src/tokenizer.rs"]
+pub mod tokenizer;
+#[path = "../../src/languagemode.rs"]
+pub mod languagemode;
+#[path = "../../src/rusttokenizer.rs"]
+pub mod rusttokenizer;
+#[path = "../../src/jsontokenizer.rs"]
+pub mod jsontokenizer;
+#[path = "../../src/tomltokenizer.rs"]
+pub mod tomltokenizer;
+#[path = "../../src/shadertokenizer.rs"]
+pub mod shadertokenizer;
+#[path = "../../src/jstokenizer.rs"]
+pub mod jstokenizer;
+#[path = "../../src/codestructure.rs"]
+pub mod codestructure;
+#[path = "../../src/editorcommand.rs"]
+pub mod editorcommand;
+#[path = "../../src/vim.rs"]
+pub mod vim;
+#[path = "../../src/findbar.rs"]
+pub mod findbar;
+#[path = "../../src/codeeditor.rs"]
+pub mod codeeditor;
+
+pub use self::textlines::*;
+pub use self::textbuffer::*;
+pub use self::tokenizer::*;
+pub use self::languagemode::*;
+pub use self::rusttokenizer::*;
+pub use self::jsontokenizer::*;
+pub use self::tomltokenizer::*;
+pub use self::shadertokenizer::*;
+pub use self::jstokenizer::*;
+pub use self::codestructure::*;
+pub use self::editorcommand::*;
+pub use self::vim::*;
+pub use self::findbar::*;
+pub use self::codeeditor::*;
+
+pub fn load_buffer(source:&str)->TextBuffer{
+    let mut text_buffer:TextBuffer = TextBuffer{..Default::default()};
+    text_buffer.load_buffer(&source.as_bytes().to_vec());
+    text_buffer
+}
+
+// | marks a cursor, [ and ] a selection
+pub fn load_cursors(source:&str)->(TextBuffer, CursorSet){
+    let mut cursors = CursorSet::new();
+    cursors.set.truncate(0);
+    let mut text = String::new();
+    let mut offset = 0;
+    let mut tail = 0;
+    for c in source.chars(){
+        match c{
+            '|'=>cursors.set.push(Cursor{head:offset, tail:offset, max:0}),
+            '['=>tail = offset,
+            ']'=>cursors.set.push(Cursor{head:offset, tail:tail, max:0}),
+            _=>{
+                text.push(c);
+                offset += 1;
+            }
+        }
+    }
+    (load_buffer(&text), cursors)
+}
+
+// the text with the cursors marked the way load_cursors reads them
+pub fn show(text_buffer:&TextBuffer, cursors:&CursorSet)->String{
+    let mut text:Vec<char> = text_buffer.get_as_string().chars().collect();
+    for cursor in cursors.set.iter().rev(){
+        let (start, end) = cursor.order();
+        if start != end{
+            text.insert(end, ']');
+            text.insert(start, '[');
+        }
+        else{
+            text.insert(start, '|');
+        }
+    }
+    text.into_iter().collect()
+}
+
+pub fn load_tokens<T>(source:&str, tokenizer:T)->(TextBuffer, TokenCache<T>)
+where T: LineTokenizer
+{
+    let text_buffer = load_buffer(source);
+    let mut token_cache = TokenCache::new(tokenizer);
+    token_cache.update(&text_buffer);
+    (text_buffer, token_cache)
+}
+
+// the non whitespace tokens of all lines, a token spanning lines shows up once per line
+pub fn get_tokens<T>(text_buffer:&TextBuffer, token_cache:&TokenCache<T>)->Vec<(String, TokenType)>
+where T: LineTokenizer
+{
+    let mut ret = Vec::new();
+    for row in 0..text_buffer.get_line_count(){
+        let line = text_buffer.get_line(row);
+        let mut col = 0;
+        for token in token_cache.get_tokens(row){
+            if token.token_type != TokenType::Whitespace{
+                ret.push((line.slice(col..col + token.len).iter().collect(), token.token_type));
+            }
+            col += token.len;
+        }
+        assert_eq!(col, line.len(), "tokens don't cover line {}", row);
+    }
+    ret
+}
+
+pub fn check_tokens<T>(tokenizer:T, source:&str, expected:&[(&str, TokenType)])
+where T: LineTokenizer
+{
+    let (text_buffer, token_cache) = load_tokens(source, tokenizer);
+    let tokens = get_tokens(&text_buffer, &token_cache);
+    let expected:Vec<(String, TokenType)> = expected.iter().map(|(text, token_type)| (text.to_string(), *token_type)).collect();
+    assert_eq!(tokens, expected, "\nsource: {}", source);
+}
+
+// small deterministic random, so a failing run fails the same way again
+pub struct Lcg(pub u64);
+
+impl Lcg{
+    pub fn next(&mut self, max:usize)->usize{
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % max.max(1)
+    }
+}
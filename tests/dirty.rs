@@ -0,0 +1,43 @@
+// A buffer is dirty when its undo stack is away from the saved state.
+// run with: cargo test --test dirty
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+fn type_text(text:&str, cursors:&mut CursorSet, text_buffer:&mut TextBuffer){
+    for c in text.chars(){
+        cursors.replace_text(&c.to_string(), text_buffer);
+    }
+}
+
+// what save_buffer and the FileWriteEvent that comes back do
+fn save(text_buffer:&mut TextBuffer){
+    text_buffer._save_undo_id = text_buffer.get_undo_id();
+    text_buffer.mark_saved();
+}
+
+#[test]
+fn grouped_undo_stops_at_the_saved_state(){
+    let mut text_buffer = load_buffer("");
+    let mut cursors = CursorSet::new();
+    type_text("abc", &mut cursors, &mut text_buffer);
+    save(&mut text_buffer);
+    type_text("def", &mut cursors, &mut text_buffer);
+    assert!(text_buffer.is_dirty());
+
+    text_buffer.undo(true, &mut cursors);
+    assert_eq!(text_buffer.get_as_string(), "abc");
+    assert!(!text_buffer.is_dirty());
+    // the next undo goes on past it
+    text_buffer.undo(true, &mut cursors);
+    assert_eq!(text_buffer.get_as_string(), "");
+    assert!(text_buffer.is_dirty());
+
+    text_buffer.redo(true, &mut cursors);
+    assert_eq!(text_buffer.get_as_string(), "abc");
+    assert!(!text_buffer.is_dirty());
+    text_buffer.redo(true, &mut cursors);
+    assert_eq!(text_buffer.get_as_string(), "abcdef");
+    assert!(text_buffer.is_dirty());
+}
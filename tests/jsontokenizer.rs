@@ -0,0 +1,40 @@
+// JSON snippets and the token kinds the highlighter should give them.
+// run with: cargo test --test jsontokenizer
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+use common::TokenType as T;
+
+fn check(source:&str, expected:&[(&str, TokenType)]){
+    check_tokens(JsonTokenizer{..Default::default()}, source, expected);
+}
+
+#[test]
+fn strings_and_keys(){
+    check(r#"{"key": "value"}"#, &[("{", T::Paren), (r#""key""#, T::Identifier), (":", T::Delimiter), (r#""value""#, T::String), ("}", T::Paren)]);
+    check(r#""spaced" : 1"#, &[(r#""spaced""#, T::Identifier), (":", T::Delimiter), ("1", T::Number)]);
+    check(r#"["a \" b", "\\"]"#, &[("[", T::Paren), (r#""a \" b""#, T::String), (",", T::Delimiter), (r#""\\""#, T::String), ("]", T::Paren)]);
+}
+
+#[test]
+fn numbers(){
+    check("[-1.5e+3, 0, 2E8]", &[
+        ("[", T::Paren), ("-1.5e+3", T::Number), (",", T::Delimiter), ("0", T::Number), (",", T::Delimiter), ("2E8", T::Number), ("]", T::Paren)
+    ]);
+}
+
+#[test]
+fn keywords(){
+    check("[true, false, null, nope]", &[
+        ("[", T::Paren), ("true", T::Keyword), (",", T::Delimiter), ("false", T::Keyword), (",", T::Delimiter),
+        ("null", T::Keyword), (",", T::Delimiter), ("nope", T::Identifier), ("]", T::Paren)
+    ]);
+}
+
+#[test]
+fn no_comments(){
+    // json has no comments, slashes stay plain text
+    check("// x", &[("/", T::Identifier), ("/", T::Identifier), ("x", T::Identifier)]);
+    check(r#""// in a string""#, &[(r#""// in a string""#, T::String)]);
+}
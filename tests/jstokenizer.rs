@@ -0,0 +1,51 @@
+// JavaScript snippets and the token kinds the highlighter should give them.
+// run with: cargo test --test jstokenizer
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+use common::TokenType as T;
+
+fn check(source:&str, expected:&[(&str, TokenType)]){
+    check_tokens(JsTokenizer{..Default::default()}, source, expected);
+}
+
+#[test]
+fn strings(){
+    check(r#"f("a \" b", 'c')"#, &[("f", T::Call), ("(", T::Paren), (r#""a \" b""#, T::String), (",", T::Delimiter), ("'c'", T::String), (")", T::Paren)]);
+    check("`one ${x}\ntwo` y", &[("`one ${x}", T::String), ("two`", T::String), ("y", T::Identifier)]);
+    check("'// not a comment'", &[("'// not a comment'", T::String)]);
+}
+
+#[test]
+fn comments(){
+    check("a // line", &[("a", T::Identifier), ("// line", T::Comment)]);
+    check("a /* c */ b", &[("a", T::Identifier), ("/* c */", T::Comment), ("b", T::Identifier)]);
+    check("/* one\ntwo */ b", &[("/* one", T::Comment), ("two */", T::Comment), ("b", T::Identifier)]);
+}
+
+#[test]
+fn regex_or_divide(){
+    check("x = a / b / c", &[
+        ("x", T::Identifier), ("=", T::Operator), ("a", T::Identifier), ("/", T::Operator), ("b", T::Identifier), ("/", T::Operator), ("c", T::Identifier)
+    ]);
+    check(r"s.match(/a\/b/g)", &[("s", T::Identifier), (".", T::Operator), ("match", T::Call), ("(", T::Paren), (r"/a\/b/g", T::String), (")", T::Paren)]);
+    check("x /= 2", &[("x", T::Identifier), ("/=", T::Operator), ("2", T::Number)]);
+}
+
+#[test]
+fn numbers(){
+    check("0xff 1_000 1.5e-3 .5 10n", &[
+        ("0xff", T::Number), ("1_000", T::Number), ("1.5e-3", T::Number), (".5", T::Number), ("10n", T::Number)
+    ]);
+    check("a.b", &[("a", T::Identifier), (".", T::Operator), ("b", T::Identifier)]);
+}
+
+#[test]
+fn keywords(){
+    check("const x = new Foo(); return this", &[
+        ("const", T::Keyword), ("x", T::Identifier), ("=", T::Operator), ("new", T::Keyword), ("Foo", T::Call),
+        ("(", T::Paren), (")", T::Paren), (";", T::Delimiter), ("return", T::Flow), ("this", T::Keyword)
+    ]);
+    check("class Bar extends Baz", &[("class", T::Keyword), ("Bar", T::TypeName), ("extends", T::Keyword), ("Baz", T::TypeName)]);
+}
@@ -0,0 +1,122 @@
+// Key chords and sequences resolving to editor commands, per platform and with user overrides.
+// run with: cargo test --test keymap
+#![allow(dead_code)]
+
+mod common;
+use widgets::*;
+use common::*;
+
+fn key(key_code:KeyCode, shift:bool, control:bool, alt:bool, logo:bool)->KeyEvent{
+    KeyEvent{
+        key_code:key_code,
+        key_char:'\0',
+        is_repeat:false,
+        modifiers:KeyModifiers{shift:shift, control:control, alt:alt, logo:logo},
+        time:0.
+    }
+}
+
+fn plain(key_code:KeyCode)->KeyEvent{
+    key(key_code, false, false, false, false)
+}
+
+fn ctrl(key_code:KeyCode)->KeyEvent{
+    key(key_code, false, true, false, false)
+}
+
+fn cmd(key_code:KeyCode)->KeyEvent{
+    key(key_code, false, false, false, true)
+}
+
+#[test]
+fn platform_defaults(){
+    let mut linux = EditorCommand::code_editor_keymap(false);
+    let mut mac = EditorCommand::code_editor_keymap(true);
+    assert_eq!(linux.handle_key(&ctrl(KeyCode::KeyZ)), KeyMapResult::Command(EditorCommand::Undo));
+    assert_eq!(linux.handle_key(&cmd(KeyCode::KeyZ)), KeyMapResult::None);
+    assert_eq!(mac.handle_key(&cmd(KeyCode::KeyZ)), KeyMapResult::Command(EditorCommand::Undo));
+    assert_eq!(mac.handle_key(&ctrl(KeyCode::KeyZ)), KeyMapResult::None);
+
+    assert_eq!(linux.handle_key(&ctrl(KeyCode::ArrowLeft)), KeyMapResult::Command(EditorCommand::WordLeft));
+    assert_eq!(mac.handle_key(&key(KeyCode::ArrowLeft, false, false, true, false)), KeyMapResult::Command(EditorCommand::WordLeft));
+    assert_eq!(mac.handle_key(&key(KeyCode::ArrowRight, true, false, false, true)), KeyMapResult::Command(EditorCommand::SelectEnd));
+    assert_eq!(linux.handle_key(&key(KeyCode::KeyD, true, true, false, false)), KeyMapResult::Command(EditorCommand::Duplicate));
+    assert_eq!(linux.handle_key(&plain(KeyCode::ArrowUp)), KeyMapResult::Command(EditorCommand::CursorUp));
+    assert_eq!(linux.handle_key(&plain(KeyCode::KeyD)), KeyMapResult::None);
+}
+
+#[test]
+fn parse_chords(){
+    let keymap:KeyMap<EditorCommand> = KeyMap::new(false);
+    let err = keymap.parse_keys("Primary+Shift+d alt+[").err().unwrap();
+    assert!(err.contains("["));
+    let chords = keymap.parse_keys("primary+shift+d alt+lbracket f5 pageup 1").unwrap();
+    assert_eq!(chords.len(), 5);
+    assert_eq!(chords[0], KeyChord{key_code:KeyCode::KeyD, modifiers:KeyModifiers{shift:true, control:true, ..Default::default()}});
+    assert_eq!(chords[1], KeyChord{key_code:KeyCode::LBracket, modifiers:KeyModifiers{alt:true, ..Default::default()}});
+    assert_eq!(chords[2].key_code, KeyCode::F5);
+    assert_eq!(chords[3].key_code, KeyCode::PageUp);
+    assert_eq!(chords[4].key_code, KeyCode::Key1);
+    assert!(keymap.parse_keys("ctrl+shift").is_err());
+    assert!(keymap.parse_keys("").is_err());
+
+    for command in &[EditorCommand::CursorUp, EditorCommand::SearchInFiles, EditorCommand::ToggleComment]{
+        assert_eq!(EditorCommand::from_name(command.name()), Some(*command));
+    }
+    assert_eq!(EditorCommand::from_name("move_rows_up"), Some(EditorCommand::MoveRowsUp));
+    assert_eq!(EditorCommand::from_name("fly"), None);
+}
+
+#[test]
+fn key_sequences(){
+    let mut keymap = EditorCommand::code_editor_keymap(false);
+    keymap.bind("ctrl+k ctrl+c", EditorCommand::ToggleComment).unwrap();
+    keymap.bind("ctrl+k ctrl+k", EditorCommand::DeleteRows).unwrap();
+
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyK)), KeyMapResult::Pending);
+    // letting go of and pressing ctrl again in between doesn't break the sequence
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::LeftControl)), KeyMapResult::Pending);
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyC)), KeyMapResult::Command(EditorCommand::ToggleComment));
+
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyK)), KeyMapResult::Pending);
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyK)), KeyMapResult::Command(EditorCommand::DeleteRows));
+
+    // a key that doesn't continue the sequence is taken on its own
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyK)), KeyMapResult::Pending);
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyZ)), KeyMapResult::Command(EditorCommand::Undo));
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyK)), KeyMapResult::Pending);
+    assert_eq!(keymap.handle_key(&plain(KeyCode::KeyQ)), KeyMapResult::None);
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyZ)), KeyMapResult::Command(EditorCommand::Undo));
+}
+
+#[test]
+fn user_overrides(){
+    let json = r#"[
+        {"key":"ctrl+d", "command":"duplicate"},
+        {"key":"ctrl+shift+d", "command":"-duplicate"},
+        {"key":"ctrl+z", "command":"redo"},
+        {"key":"ctrl+g", "command":"find_next", "when":"find_bar"},
+        {"key":"ctrl+alt+f", "command":"search_in_files", "when":"app"}
+    ]"#;
+    let mut keymap = EditorCommand::code_editor_keymap(false);
+    keymap.load_json(json, "code_editor", true).unwrap();
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyD)), KeyMapResult::Command(EditorCommand::Duplicate));
+    assert_eq!(keymap.handle_key(&key(KeyCode::KeyD, true, true, false, false)), KeyMapResult::None);
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyZ)), KeyMapResult::Command(EditorCommand::Redo));
+    assert_eq!(keymap.handle_key(&ctrl(KeyCode::KeyG)), KeyMapResult::None);
+    assert_eq!(keymap.get_keys(&EditorCommand::Duplicate).unwrap()[0].key_code, KeyCode::KeyD);
+
+    let mut find_bar = EditorCommand::find_bar_keymap(false);
+    find_bar.load_json(json, "find_bar", false).unwrap();
+    assert_eq!(find_bar.handle_key(&ctrl(KeyCode::KeyG)), KeyMapResult::Command(EditorCommand::FindNext));
+    assert_eq!(find_bar.handle_key(&ctrl(KeyCode::KeyD)), KeyMapResult::None);
+
+    let mut app = EditorCommand::app_keymap(false);
+    app.load_json(json, "app", false).unwrap();
+    assert_eq!(app.handle_key(&key(KeyCode::KeyF, false, true, true, false)), KeyMapResult::Command(EditorCommand::SearchInFiles));
+    assert_eq!(app.handle_key(&key(KeyCode::KeyF, true, true, false, false)), KeyMapResult::Command(EditorCommand::SearchInFiles));
+
+    assert!(keymap.load_json(r#"[{"key":"ctrl+d", "command":"fly"}]"#, "code_editor", true).is_err());
+    assert!(keymap.load_json(r#"[{"key":"ctrl+nope", "command":"save"}]"#, "code_editor", true).is_err());
+    assert!(keymap.load_json("{", "code_editor", true).is_err());
+}
@@ -0,0 +1,64 @@
+// Duplicating, moving, joining, deleting and commenting rows with multiple cursors.
+// run with: cargo test --test lineops
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+// runs the op, checks the result and that it undoes in one step
+fn check<F>(source:&str, result:&str, op:F)
+where F: Fn(&mut CursorSet, &mut TextBuffer)
+{
+    let (mut text_buffer, mut cursors) = load_cursors(source);
+    op(&mut cursors, &mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), result);
+    text_buffer.undo(true, &mut cursors);
+    assert_eq!(show(&text_buffer, &cursors), source);
+}
+
+#[test]
+fn duplicate(){
+    check("a|b\nc", "ab\na|b\nc", |c, t| c.duplicate(t));
+    check("a|\nb|\nc", "a\nb\na|\nb|\nc", |c, t| c.duplicate(t));
+    // touching rows are one block
+    check("[a\nb]\nc|", "a\nb\nc\n[a\nb]\nc|", |c, t| c.duplicate(t));
+    // a selection in a row duplicates just the selection
+    check("x [ab] y", "x ab[ab] y", |c, t| c.duplicate(t));
+}
+
+#[test]
+fn move_rows(){
+    check("a\nb|\nc", "b|\na\nc", |c, t| c.move_rows(true, t));
+    check("a\nb|\nc", "a\nc\nb|", |c, t| c.move_rows(false, t));
+    check("a|\nb\nc|\nd\ne", "b\na|\nd\nc|\ne", |c, t| c.move_rows(false, t));
+    check("a\n[b\nc]\nd", "[b\nc]\na\nd", |c, t| c.move_rows(true, t));
+    // rows at the edge stay, the others still move
+    check("a|\nb\nc|\nd", "a|\nc|\nb\nd", |c, t| c.move_rows(true, t));
+}
+
+#[test]
+fn delete_rows(){
+    check("a\nb|b\nc", "a\nc|", |c, t| c.delete_rows(t));
+    check("a|\nb\nc\nd|", "b|\nc|", |c, t| c.delete_rows(t));
+    check("[a\nb]", "|", |c, t| c.delete_rows(t));
+}
+
+#[test]
+fn join_rows(){
+    check("fn a(){|\n    b();\n}", "fn a(){| b();\n}", |c, t| c.join_rows(t));
+    check("[a\n  b\n  c]\nd", "a b| c\nd", |c, t| c.join_rows(t));
+    check("a|\n\nb", "a|\nb", |c, t| c.join_rows(t));
+}
+
+#[test]
+fn toggle_comment(){
+    let syntax = RustTokenizer{..Default::default()}.get_syntax();
+    check("fn a(){\n    [b();\n\n  c();]\n}", "fn a(){\n  //   [b();\n\n  // c();]\n}", |c, t| c.toggle_comment(&syntax, t));
+    check("    // a|\n    //b|", "    a|\n    b|", |c, t| c.toggle_comment(&syntax, t));
+    // a block with an uncommented row gets commented again
+    check("// a|\nb|", "// // a|\n// b|", |c, t| c.toggle_comment(&syntax, t));
+    let json = LanguageSyntax{..Default::default()};
+    let (mut text_buffer, mut cursors) = load_cursors("a|");
+    cursors.toggle_comment(&json, &mut text_buffer);
+    assert_eq!(show(&text_buffer, &cursors), "a|");
+}
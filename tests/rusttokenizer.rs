@@ -0,0 +1,148 @@
+// Tricky rust snippets and the token kinds the highlighter should give them.
+// run with: cargo test --test rusttokenizer
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+use common::TokenType as T;
+
+fn check(source:&str, expected:&[(&str, TokenType)]){
+    check_tokens(RustTokenizer{..Default::default()}, source, expected);
+}
+
+#[test]
+fn line_comments(){
+    check("a // plain", &[("a", T::Identifier), ("// plain", T::Comment)]);
+    check("/// outer doc", &[("/// outer doc", T::DocComment)]);
+    check("//! inner doc", &[("//! inner doc", T::DocComment)]);
+    check("//// not a doc comment", &[("//// not a doc comment", T::Comment)]);
+    check("///", &[("///", T::DocComment)]);
+    check("a /= 2", &[("a", T::Identifier), ("/=", T::Operator), ("2", T::Number)]);
+}
+
+#[test]
+fn block_comments(){
+    check("a /* b */ c", &[("a", T::Identifier), ("/* b */", T::Comment), ("c", T::Identifier)]);
+    check("/* a /* nested */ still */ d", &[("/* a /* nested */ still */", T::Comment), ("d", T::Identifier)]);
+    check("/* one\n  two\n*/ x", &[("/* one", T::Comment), ("  two", T::Comment), ("*/", T::Comment), ("x", T::Identifier)]);
+    check("/* a /*\n*/ b\n*/ c", &[("/* a /*", T::Comment), ("*/ b", T::Comment), ("*/", T::Comment), ("c", T::Identifier)]);
+    check("/*/ still open\n*/", &[("/*/ still open", T::Comment), ("*/", T::Comment)]);
+    check("/* empty lines\n\n*/", &[("/* empty lines", T::Comment), ("*/", T::Comment)]);
+}
+
+#[test]
+fn doc_block_comments(){
+    check("/** doc */", &[("/** doc */", T::DocComment)]);
+    check("/*! inner\n doc */", &[("/*! inner", T::DocComment), (" doc */", T::DocComment)]);
+    check("/**/ x", &[("/**/", T::Comment), ("x", T::Identifier)]);
+    check("/*** not doc */", &[("/*** not doc */", T::Comment)]);
+}
+
+#[test]
+fn strings(){
+    check(r#"f("a \" b")"#, &[("f", T::Call), ("(", T::Paren), (r#""a \" b""#, T::String), (")", T::Paren)]);
+    check(r#""\\" x"#, &[(r#""\\""#, T::String), ("x", T::Identifier)]);
+    check("\"one\ntwo\" x", &[("\"one", T::String), ("two\"", T::String), ("x", T::Identifier)]);
+    check("\"one \\\n    two\"", &[("\"one \\", T::String), ("    two\"", T::String)]);
+    check("\"// not a comment\"", &[("\"// not a comment\"", T::String)]);
+    check("\"/* not a comment\"\nx", &[("\"/* not a comment\"", T::String), ("x", T::Identifier)]);
+}
+
+#[test]
+fn raw_strings(){
+    check(r#"r"a\b" x"#, &[(r#"r"a\b""#, T::String), ("x", T::Identifier)]);
+    check(r###"r#"has "quotes""# x"###, &[(r###"r#"has "quotes""#"###, T::String), ("x", T::Identifier)]);
+    check(r####"r##"a "# b"## x"####, &[(r####"r##"a "# b"##"####, T::String), ("x", T::Identifier)]);
+    check("r#\"one\n\"two\n\"# x", &[("r#\"one", T::String), ("\"two", T::String), ("\"#", T::String), ("x", T::Identifier)]);
+    check(r##"r"\" x"##, &[(r##"r"\""##, T::String), ("x", T::Identifier)]);
+}
+
+#[test]
+fn byte_and_c_strings(){
+    check(r#"b"bytes\n" x"#, &[(r#"b"bytes\n""#, T::String), ("x", T::Identifier)]);
+    check(r#"br"raw\" x"#, &[(r#"br"raw\""#, T::String), ("x", T::Identifier)]);
+    check(r###"br#"a"b"# x"###, &[(r###"br#"a"b"#"###, T::String), ("x", T::Identifier)]);
+    check("b'x' b'\\'' b'\\x7f'", &[("b'x'", T::String), ("b'\\''", T::String), ("b'\\x7f'", T::String)]);
+    check(r##"c"c string" cr#"raw"#"##, &[(r#"c"c string""#, T::String), (r##"cr#"raw"#"##, T::String)]);
+    check("b\"one\ntwo\"", &[("b\"one", T::String), ("two\"", T::String)]);
+}
+
+#[test]
+fn chars_and_lifetimes(){
+    check("'a' '\\n' '\\u{1F600}' '\\'' '\"'", &[
+        ("'a'", T::String), ("'\\n'", T::String), ("'\\u{1F600}'", T::String), ("'\\''", T::String), ("'\"'", T::String)
+    ]);
+    check("'(' 'é'", &[("'('", T::String), ("'é'", T::String)]);
+    check("&'a str", &[("&", T::Identifier), ("'a", T::Keyword), ("str", T::Identifier)]);
+    check("&'static str", &[("&", T::Identifier), ("'static", T::Keyword), ("str", T::Identifier)]);
+    check("'outer: loop", &[("'outer", T::Keyword), (":", T::Operator), ("loop", T::Flow)]);
+}
+
+#[test]
+fn numbers(){
+    check("1_000u32", &[("1_000u32", T::Number)]);
+    check("0xffu8 0o77 0b1010_1010", &[("0xffu8", T::Number), ("0o77", T::Number), ("0b1010_1010", T::Number)]);
+    check("1.5e-3f64 1e10 2.", &[("1.5e-3f64", T::Number), ("1e10", T::Number), ("2.", T::Number)]);
+    check("1..2", &[("1", T::Number), ("..", T::Operator), ("2", T::Number)]);
+    check("1.max(2)", &[("1", T::Number), (".", T::Operator), ("max", T::Call), ("(", T::Paren), ("2", T::Number), (")", T::Paren)]);
+    check("t.0.1", &[("t", T::Identifier), (".", T::Operator), ("0.1", T::Number)]);
+}
+
+#[test]
+fn identifiers_that_look_like_literals(){
+    check("r#match", &[("r#match", T::Identifier)]);
+    check("break crate rb b c r", &[
+        ("break", T::Flow), ("crate", T::Keyword), ("rb", T::Identifier), ("b", T::Identifier), ("c", T::Identifier), ("r", T::Identifier)
+    ]);
+    check("bytes(b)", &[("bytes", T::Call), ("(", T::Paren), ("b", T::Identifier), (")", T::Paren)]);
+}
+
+#[test]
+fn edits_relex_until_the_state_converges(){
+    let source = "fn a(){}\nfn b(){}\nfn c(){}\nfn d(){}";
+    let (mut text_buffer, mut token_cache) = load_tokens(source, RustTokenizer{..Default::default()});
+    let code = get_tokens(&text_buffer, &token_cache);
+
+    // opening a block comment on the first line turns everything below into comment
+    text_buffer.replace_with_string(0, 0, "/*");
+    token_cache.update(&text_buffer);
+    assert_eq!(token_cache._relexed_lines, 4);
+    assert!(get_tokens(&text_buffer, &token_cache).iter().all(|(_, token_type)| *token_type == T::Comment));
+
+    // closing it on the second line turns the lines below back into code
+    let offset = text_buffer.text_pos_to_offset(TextPos{row:1, col:8});
+    text_buffer.replace_with_string(offset, 0, "*/");
+    token_cache.update(&text_buffer);
+    assert_eq!(token_cache._relexed_lines, 3);
+
+    // and taking the comment out again gives the original tokens back
+    text_buffer.replace_with_string(offset, 2, "");
+    text_buffer.replace_with_string(0, 2, "");
+    token_cache.update(&text_buffer);
+    assert_eq!(get_tokens(&text_buffer, &token_cache), code);
+
+    // an edit that keeps the state only relexes its own line
+    text_buffer.replace_with_string(3, 0, "_x");
+    token_cache.update(&text_buffer);
+    assert_eq!(token_cache._relexed_lines, 1);
+}
+
+#[test]
+fn incremental_matches_full_relex(){
+    let source = "fn main(){\n    let s = \"a\";\n    /* c */\n    let r = r#\"x\"#;\n}\n";
+    let (mut text_buffer, mut token_cache) = load_tokens(source, RustTokenizer{..Default::default()});
+    let edits = ["\"", "/*", "*/", "r#\"", "\"#", "\n", "'", "b\"", "x", ""];
+    let mut seed:u64 = 1;
+    for i in 0..2000{
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        let char_count = text_buffer.get_char_count();
+        let offset = ((seed >> 33) as usize) % (char_count + 1);
+        let len = ((seed >> 20) as usize % 4).min(char_count - offset);
+        text_buffer.replace_with_string(offset, len, edits[(seed >> 40) as usize % edits.len()]);
+        if i % 3 == 0{
+            token_cache.update(&text_buffer);
+            let (_, full_cache) = load_tokens(&text_buffer.get_as_string(), RustTokenizer{..Default::default()});
+            assert_eq!(get_tokens(&text_buffer, &token_cache), get_tokens(&text_buffer, &full_cache));
+        }
+    }
+}
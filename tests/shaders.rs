@@ -0,0 +1,442 @@
+// Assembles every shader of the widgets and the editor for each target, checks the
+// generated source and compares it against the snapshots in tests/shaders.
+// run with: cargo test --test shaders
+// a missing or changed snapshot fails, set MAKEPAD_UPDATE_GOLDEN=1 to record them all again
+#![allow(dead_code)]
+
+mod common;
+use widgets::*;
+use common::*;
+use std::fs;
+use std::path::Path;
+
+fn all_shaders()->Vec<Shader>{
+    let mut cx = Cx{..Default::default()};
+    set_dark_style(&mut cx);
+    let _ = Quad{..Style::style(&mut cx)};
+    let _ = Blit{..Style::style(&mut cx)};
+    let _ = Text{..Style::style(&mut cx)};
+    let _ = ScrollBar{..Style::style(&mut cx)};
+    let _ = Splitter{..Style::style(&mut cx)};
+    let _ = Tab{..Style::style(&mut cx)};
+    let _ = TabClose{..Style::style(&mut cx)};
+    let _ = Button{..Style::style(&mut cx)};
+    let _ = FileTree{..Style::style(&mut cx)};
+    let _ = CodeEditor{..Style::style(&mut cx)};
+    cx.shaders.clone()
+}
+
+fn check_snapshot(path:&str, source:&str)->Result<(), String>{
+    if std::env::var("MAKEPAD_UPDATE_GOLDEN").is_ok(){
+        fs::create_dir_all("tests/shaders").map_err(|err| err.to_string())?;
+        return fs::write(path, source).map_err(|err| format!("Cannot write {}: {}", path, err))
+    }
+    let golden = fs::read_to_string(path).map_err(|err| format!("Cannot read {}: {}, set MAKEPAD_UPDATE_GOLDEN=1 to record it", path, err))?;
+    // git can check text files out with \r\n
+    if golden.replace("\r\n", "\n") != source.replace("\r\n", "\n"){
+        let actual_path = format!("{}.actual", path);
+        fs::write(&actual_path, source).map_err(|err| format!("Cannot write {}: {}", actual_path, err))?;
+        return Err(format!("{} changed, see {}", path, actual_path))
+    }
+    Ok(())
+}
+
+#[test]
+fn covers_every_shader(){
+    let names:Vec<String> = all_shaders().iter().map(|sh| sh.name.clone()).collect();
+    // the editor cursor is the same shader as the line highlight, so it shares that entry
+    for name in &["Quad", "Blit", "Text", "ScrollBar.sb", "Splitter.split", "Tab.bg", "TabClose.bg", "Button.bg",
+        "FileTree.filler", "Editor.tab", "Editor.marker", "Editor.fold_marker", "Editor.line_highlight"]{
+        assert!(names.iter().any(|n| n == name), "{} is not among {:?}", name, names);
+    }
+}
+
+#[test]
+fn glsl(){
+    let mut errors = Vec::new();
+    for sh in &all_shaders(){
+        // the desktop target gets the snapshot, the others only differ in the header
+        for (shtype, snapshot) in vec![(GLShaderType::OpenGLNoPartialDeriv, false), (GLShaderType::WebGL1, false), (GLShaderType::OpenGL, true)]{
+            let ash = match Cx::gl_assemble_shader(sh, shtype){
+                Ok(ash)=>ash,
+                Err(err)=>{
+                    errors.push(format!("{}: {}", sh.name, err.msg));
+                    continue
+                }
+            };
+            for (stage, source) in &[("vertex", &ash.vertex), ("fragment", &ash.fragment)]{
+                if let Err(err) = Cx::gl_check_shader(source){
+                    errors.push(format!("{} {}: {}", sh.name, stage, err.msg));
+                }
+            }
+            if !snapshot{
+                continue;
+            }
+            let source = format!("// vertex\n{}\n// fragment\n{}", ash.vertex, ash.fragment);
+            if let Err(err) = check_snapshot(&format!("tests/shaders/{}.glsl", sh.name), &source){
+                errors.push(err);
+            }
+        }
+    }
+    assert!(errors.len() == 0, "\n{}", errors.join("\n"));
+}
+
+#[test]
+fn metal(){
+    let mut errors = Vec::new();
+    for sh in &all_shaders(){
+        match Cx::mtl_assemble_shader(sh){
+            Ok(ash)=>{
+                if let Err(err) = Cx::mtl_check_shader(&ash.mtlsl){
+                    errors.push(format!("{}: {}", sh.name, err.msg));
+                }
+                if let Err(err) = check_snapshot(&format!("tests/shaders/{}.metal", sh.name), &ash.mtlsl){
+                    errors.push(err);
+                }
+            },
+            Err(err)=>errors.push(format!("{}: {}", sh.name, err.msg))
+        }
+    }
+    assert!(errors.len() == 0, "\n{}", errors.join("\n"));
+}
+
+#[test]
+fn wgsl(){
+    let mut errors = Vec::new();
+    for sh in &all_shaders(){
+        match Cx::wgsl_assemble_shader(sh){
+            Ok(ash)=>{
+                if let Err(err) = Cx::wgsl_check_shader(&ash.wgsl){
+                    errors.push(format!("{}: {}", sh.name, err.msg));
+                }
+                if let Err(err) = check_snapshot(&format!("tests/shaders/{}.wgsl", sh.name), &ash.wgsl){
+                    errors.push(err);
+                }
+            },
+            Err(err)=>errors.push(format!("{}: {}", sh.name, err.msg))
+        }
+    }
+    assert!(errors.len() == 0, "\n{}", errors.join("\n"));
+}
+
+#[test]
+fn type_check(){
+    let mut errors = Vec::new();
+    for sh in &all_shaders(){
+        if let Err(diags) = sh.type_check(){
+            for diag in diags{
+                errors.push(format!("{}: {}", sh.name, diag));
+            }
+        }
+    }
+    assert!(errors.len() == 0, "\n{}", errors.join("\n"));
+}
+
+#[test]
+fn type_check_reports_rust_source(){
+    let mut cx = Cx{..Default::default()};
+    let mut sh = Quad::def_quad_shader(&mut cx);
+    sh.add_ast(shader_ast!({
+        let tint:vec4<Uniform>;
+        let fade:vec2<Varying>;
+        fn vertex()->vec4{
+            tint = vec4(1.);
+            fade = vec2(0.);
+            return vec4(geom, 0.);
+        }
+        fn pixel()->vec4{
+            fade = vec2(1.);
+            // geom comes from the quad block so shader_ast! can't see this one
+            let f:float = geom.xyz;
+            return vec4(fade.x) * tint;
+        }
+    }));
+    let diags = sh.type_check().err().unwrap();
+    let msgs:Vec<String> = diags.iter().map(|diag| diag.msg.clone()).collect();
+    assert_eq!(msgs, vec![
+        "Uniform variable tint is read only",
+        "vec4 has 4 components, got 3",
+        "Varying fade can only be written by the vertex shader",
+        "Swizzle xyz reads past the end of vec2",
+    ]);
+    // every diagnostic points at the statement in this file
+    for diag in &diags{
+        assert!(diag.span.file.ends_with("shaders.rs"), "{}", diag);
+    }
+    assert_eq!(diags[3].in_fn, "pixel");
+    assert_eq!(diags[3].span.src, "let f:float = geom.xyz;");
+}
+
+#[test]
+fn type_errors_fail_assembly(){
+    let mut cx = Cx{..Default::default()};
+    let mut sh = Quad::def_quad_shader(&mut cx);
+    sh.add_ast(ShAst::parse("a.shader", "fn pixel()->vec4{\n    return vec4(geom.xyz, 1.);\n}").unwrap_or_else(|diag| panic!("{}", diag)));
+    let err = Cx::gl_assemble_shader(&sh, GLShaderType::OpenGL).err().unwrap();
+    assert!(err.msg.contains("Swizzle xyz reads past the end of vec2"), "{}", err.msg);
+    assert!(Cx::mtl_assemble_shader(&sh).is_err());
+    assert!(Cx::wgsl_assemble_shader(&sh).is_err());
+}
+
+// the shader with its own shader_ast! block replaced by a .shader file
+fn shader_from_file(sh:&Shader, path:&str)->Shader{
+    let source = fs::read_to_string(path).unwrap();
+    let ast = ShAst::parse(path, &source).unwrap_or_else(|diag| panic!("{}", diag));
+    let mut file_sh = sh.clone();
+    file_sh.asts.pop();
+    file_sh.asts.push(ast);
+    file_sh
+}
+
+#[test]
+fn shader_files_parse_like_shader_ast(){
+    for sh in &all_shaders(){
+        let path = format!("tests/shaders/{}.shader", sh.name);
+        if !Path::new(&path).exists(){
+            continue;
+        }
+        let file_sh = shader_from_file(sh, &path);
+        assert!(file_sh.type_check().is_ok(), "{}", path);
+        let ash = Cx::gl_assemble_shader(sh, GLShaderType::OpenGL).ok().unwrap();
+        let file_ash = Cx::gl_assemble_shader(&file_sh, GLShaderType::OpenGL).ok().unwrap();
+        assert!(ash.vertex == file_ash.vertex && ash.fragment == file_ash.fragment, "{}", path);
+        assert!(Cx::mtl_assemble_shader(sh).ok().unwrap().mtlsl == Cx::mtl_assemble_shader(&file_sh).ok().unwrap().mtlsl, "{}", path);
+        assert!(Cx::wgsl_assemble_shader(sh).ok().unwrap().wgsl == Cx::wgsl_assemble_shader(&file_sh).ok().unwrap().wgsl, "{}", path);
+    }
+}
+
+#[test]
+fn shader_file_errors_point_at_the_file(){
+    let diag = ShAst::parse("a.shader", "let x:float<Instance>;\nfn pixel()->vec4{\n    let c:vec5 = x;\n}\n").err().unwrap();
+    assert_eq!(diag.msg, "Unknown type vec5, use one of float, int, bool, vec2, vec3, vec4, mat2, mat3, mat4, texture2d or a struct");
+    assert_eq!((diag.span.line, diag.in_fn.as_ref(), diag.span.src.as_ref()), (3, "pixel", "let c:vec5 = x;"));
+    let diag = ShAst::parse("a.shader", "let x:float<Instanse>;").err().unwrap();
+    assert!(diag.msg.starts_with("Unknown storage Instanse"));
+    let diag = ShAst::parse("a.shader", "fn pixel()->vec4{\n    return color.xyba;\n}").err().unwrap();
+    assert_eq!((diag.span.line, diag.msg.as_ref()), (2, "Swizzle xyba mixes xyzw and rgba"));
+    let diag = ShAst::parse("a.shader", "fn pixel()->vec4{\n    let a:float = 1.\n    return vec4(a);\n}").err().unwrap();
+    assert_eq!((diag.span.line, diag.msg.as_ref()), (3, "Expected ;"));
+}
+
+#[test]
+fn reload_shader_files(){
+    let mut cx = Cx{..Default::default()};
+    let quad = Quad{..Style::style(&mut cx)};
+    // the platform compiled them already
+    cx.compiled_shaders.resize(cx.shaders.len(), CompiledShader{..Default::default()});
+
+    let dir = std::env::temp_dir().join(format!("makepad_shaders_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = fs::read_to_string("tests/shaders/Quad.shader").unwrap();
+    let red = source.replace("return vec4(color.rgb*color.a, color.a);", "return vec4(color.a, 0., 0., color.a);");
+    fs::write(dir.join("Quad.shader"), &red).unwrap();
+    cx.watch_shader_dir(dir.to_str().unwrap());
+
+    let mut compiled = Vec::new();
+    assert!(cx.reload_shader_files(|_cx, shader_id|{
+        compiled.push(shader_id);
+        Ok(CompiledShader{..Default::default()})
+    }));
+    assert_eq!(compiled, vec![quad.shader_id]);
+    assert_eq!(cx.compiled_shaders[quad.shader_id].shader_id, quad.shader_id);
+    assert!(cx.redraw_areas.len() != 0);
+    let pixel = cx.shaders[quad.shader_id].find_fn("pixel").unwrap();
+    assert_eq!(pixel.block.as_ref().unwrap().spans[0].src, "return vec4(color.a, 0., 0., color.a);");
+
+    // an unchanged file is left alone
+    assert!(!cx.reload_shader_files(|_cx, _shader_id| Ok(CompiledShader{..Default::default()})));
+
+    // the draw calls have instance data laid out for the running shader
+    let moved = red.replace("let color:vec4<Instance>;", "let color:vec4<Instance>;\nlet glow:float<Instance>;");
+    let diags = cx.reload_shader_source(quad.shader_id, "Quad.shader", &moved).err().unwrap();
+    assert_eq!(diags[0].msg, "The Instance variables changed, restart the app to change them");
+    let broken = red.replace("color.a, 0., 0.", "color.a, 0.");
+    assert!(cx.reload_shader_source(quad.shader_id, "Quad.shader", &broken).is_err());
+    assert_eq!(cx.shaders[quad.shader_id].find_fn("pixel").unwrap().block.as_ref().unwrap().spans[0].src, "return vec4(color.a, 0., 0., color.a);");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn checker_catches_codegen_mistakes(){
+    assert!(Cx::gl_check_shader("void main(){\n gl_Position = vec4(sample2d(a, b).x);\n}").is_err());
+    assert!(Cx::gl_check_shader("void main(){\n float4 a = vec4(1.);\n}").is_err());
+    assert!(Cx::gl_check_shader("void main(){\n if(a){\n}").is_err());
+    assert!(Cx::gl_check_shader("float f(float x){return x;}\nvoid main(){\n gl_FragColor = vec4(f(1.));\n}").is_ok());
+    assert!(Cx::mtl_check_shader("float4 f(){\n return vec4(1.);\n}").is_err());
+    assert!(Cx::mtl_check_shader("float4 f(thread float2 &a){\n return float4(a.x, dfdx(a).y, 0., 1.);\n}").is_ok());
+    assert!(Cx::wgsl_check_shader("fn f(a: vec2)->vec4<f32>{\n var b: float = a.x;\n return vec4<f32>(b);\n}").is_err());
+    assert!(Cx::wgsl_check_shader("fn f(a: vec2<f32>)->vec4<f32>{\n return vec4<f32>(dfdx(a), 0., 1.);\n}").is_err());
+    assert!(Cx::wgsl_check_shader("fn f(a: vec2<f32>)->vec4<f32>{\n var b: f32 = a.x;\n return vec4<f32>(dpdx(a), b, 1.);\n}").is_ok());
+}
+
+const RICH_SHADER:&str = "struct Ray{
+    origin:vec2,
+    dir:vec2
+}
+impl Ray{
+    fn at(&self, t:float)->vec2{
+        return self.origin + self.dir * t;
+    }
+    fn new(dir:vec2)->Ray{
+        return Ray{origin:vec2(0.), dir:dir};
+    }
+}
+fn pixel()->vec4{
+    let ray:Ray = Ray::new(vec2(1., 0.));
+    let weights:[float;3] = [0.25, 0.5, 0.25];
+    let m:mat2 = mat2(1., 0., 0., 1.) * 2.;
+    let p:vec2 = m * ray.at(weights[1]);
+    let mut c:float = if p.x > 0.5 {1.} else {0.};
+    let mode:int = 2;
+    match mode{
+        0 | 1=>{
+            c = c * 0.5;
+        }
+        _=>{
+            c = c + weights[2];
+        }
+    }
+    let a:float = match mode{
+        2=>0.5,
+        _=>1.
+    };
+    return vec4(c, p.y, m[0].x, a);
+}
+";
+
+// the structs, arrays, matrices and match of RICH_SHADER, in a shader_ast! block
+fn rich_shader(cx:&mut Cx)->Shader{
+    let mut sh = Quad::def_quad_shader(cx);
+    sh.add_ast(shader_ast!({
+        struct Ray{
+            origin:vec2,
+            dir:vec2
+        }
+        impl Ray{
+            fn at(&self, t:float)->vec2{
+                return self.origin + self.dir * t;
+            }
+            fn new(dir:vec2)->Ray{
+                return Ray{origin:vec2(0.), dir:dir};
+            }
+        }
+        fn pixel()->vec4{
+            let ray:Ray = Ray::new(vec2(1., 0.));
+            let weights:[float;3] = [0.25, 0.5, 0.25];
+            let m:mat2 = mat2(1., 0., 0., 1.) * 2.;
+            let p:vec2 = m * ray.at(weights[1]);
+            let mut c:float = if p.x > 0.5 {1.} else {0.};
+            let mode:int = 2;
+            match mode{
+                0 | 1=>{
+                    c = c * 0.5;
+                }
+                _=>{
+                    c = c + weights[2];
+                }
+            }
+            let a:float = match mode{
+                2=>0.5,
+                _=>1.
+            };
+            return vec4(c, p.y, m[0].x, a);
+        }
+    }));
+    sh
+}
+
+#[test]
+fn structs_arrays_and_match(){
+    let mut cx = Cx{..Default::default()};
+    let sh = rich_shader(&mut cx);
+    if let Err(diags) = sh.type_check(){
+        panic!("{}", diags.iter().map(|diag| diag.to_string()).collect::<Vec<String>>().join("\n"));
+    }
+    let mut file_sh = sh.clone();
+    file_sh.asts.pop();
+    file_sh.asts.push(ShAst::parse("rich.shader", RICH_SHADER).unwrap_or_else(|diag| panic!("{}", diag)));
+
+    for webgl in vec![true, false]{
+        let shtype = || if webgl{GLShaderType::WebGL1}else{GLShaderType::OpenGL};
+        let ash = Cx::gl_assemble_shader(&sh, shtype()).map_err(|err| err.msg).unwrap();
+        assert!(ash.fragment.contains("struct Ray{"));
+        assert!(ash.fragment.contains("float weights[3];"));
+        Cx::gl_check_shader(&ash.vertex).map_err(|err| err.msg).unwrap();
+        Cx::gl_check_shader(&ash.fragment).map_err(|err| err.msg).unwrap();
+        let file_ash = Cx::gl_assemble_shader(&file_sh, shtype()).map_err(|err| err.msg).unwrap();
+        assert!(ash.fragment == file_ash.fragment);
+    }
+    let mtlsl = Cx::mtl_assemble_shader(&sh).map_err(|err| err.msg).unwrap().mtlsl;
+    assert!(mtlsl.contains("switch("));
+    Cx::mtl_check_shader(&mtlsl).map_err(|err| err.msg).unwrap();
+    assert!(mtlsl == Cx::mtl_assemble_shader(&file_sh).map_err(|err| err.msg).unwrap().mtlsl);
+    let wgsl = Cx::wgsl_assemble_shader(&sh).map_err(|err| err.msg).unwrap().wgsl;
+    assert!(wgsl.contains("select("));
+    Cx::wgsl_check_shader(&wgsl).map_err(|err| err.msg).unwrap();
+    assert!(wgsl == Cx::wgsl_assemble_shader(&file_sh).map_err(|err| err.msg).unwrap().wgsl);
+}
+
+#[test]
+fn structs_arrays_and_match_errors(){
+    let check = |source:&str|->String{
+        let mut cx = Cx{..Default::default()};
+        let mut sh = Quad::def_quad_shader(&mut cx);
+        match ShAst::parse("a.shader", source){
+            Err(diag)=>return diag.msg,
+            Ok(ast)=>sh.add_ast(ast)
+        }
+        sh.type_check().err().unwrap()[0].msg.clone()
+    };
+    assert_eq!(check("struct P{a:float}\nfn pixel()->vec4{\n    let p:P = P{b:1.};\n    return vec4(p.a);\n}"), "P has no field b");
+    assert_eq!(check("fn pixel()->vec4{\n    let i:int = 1;\n    let a:float = match i{\n        0=>1.\n    };\n    return vec4(a);\n}"), "A match on an int needs a _ arm");
+    assert_eq!(check("fn pixel()->vec4{\n    let a:float = if true {1.};\n    return vec4(a);\n}"), "An if used as a value needs an else");
+    assert_eq!(check("struct P{a:float}\nimpl P{\n    fn f(mut self)->P{\n        return self;\n    }\n}"), "Methods can't change self, return the changed struct instead");
+    assert_eq!(check("fn pixel()->vec4{\n    let i:int = 1;\n    match i{\n        0 if i > 0=>{}\n        _=>{}\n    }\n    return vec4(1.);\n}"), "Match guards are not supported");
+}
+
+#[test]
+fn optimize_folds_inlines_and_strips(){
+    let mut cx = Cx{..Default::default()};
+    let mut sh = Quad::def_quad_shader(&mut cx);
+    sh.add_ast(shader_ast!({
+        const SCALE:float = 2.;
+        const HALF:float = SCALE * 0.25;
+        let unused_glow:vec4<Instance>;
+        let tint:float<Instance>;
+        fn brighten(c:vec4, f:float)->vec4{
+            return c * f;
+        }
+        fn never_called()->float{
+            return 1.;
+        }
+        fn pixel()->vec4{
+            if 1 > 2 {
+                return vec4(never_called());
+            }
+            let s:float = if HALF > 1. {1.} else {-1.};
+            return brighten(color, tint * HALF + 1.) * s;
+        }
+    }));
+    assert!(sh.type_check().is_ok());
+    let ash = Cx::gl_assemble_shader(&sh, GLShaderType::OpenGL).map_err(|err| err.msg).unwrap();
+    assert!(ash.fragment.contains("float s = (-1.0);"), "{}", ash.fragment);
+    assert!(ash.fragment.contains("return (color*(tint*0.5+1.0))*s;"), "{}", ash.fragment);
+    for name in &["never_called", "brighten", "SCALE", "HALF", "unused_glow", "df_antialias", "df_start_pos"]{
+        assert!(!ash.fragment.contains(name) && !ash.vertex.contains(name), "{} is left in", name);
+    }
+    Cx::gl_check_shader(&ash.vertex).map_err(|err| err.msg).unwrap();
+    Cx::gl_check_shader(&ash.fragment).map_err(|err| err.msg).unwrap();
+    Cx::mtl_check_shader(&Cx::mtl_assemble_shader(&sh).map_err(|err| err.msg).unwrap().mtlsl).map_err(|err| err.msg).unwrap();
+    Cx::wgsl_check_shader(&Cx::wgsl_assemble_shader(&sh).map_err(|err| err.msg).unwrap().wgsl).map_err(|err| err.msg).unwrap();
+
+    // widgets still write unused_glow, it is packed out before the gpu gets the instances
+    let packing = &ash.instance_packing;
+    assert_eq!((ash.instance_slots, packing.total_slots, packing.ranges.clone()), (9, 13, vec![(0, 8), (12, 1)]));
+    let data:Vec<f32> = (0..26).map(|i| i as f32).collect();
+    let mut packed = Vec::new();
+    packing.pack(&data, &mut packed);
+    assert_eq!(packed, vec![0., 1., 2., 3., 4., 5., 6., 7., 12., 13., 14., 15., 16., 17., 18., 19., 20., 25.]);
+    let tint = ash.named_instance_props.props.iter().find(|prop| prop.name == "tint").unwrap();
+    assert_eq!(tint.offset, 12);
+}
@@ -0,0 +1,85 @@
+// vertex
+#version 100
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform float draw_list_do_scroll;
+//Texture slots
+uniform sampler2D texture;
+// Varyings
+varying vec3 varying0;
+// Geometry attributes
+attribute vec2 geomattr0;
+vec2 geom;
+// Instance attributes
+attribute vec4 instattr0;
+attribute float instattr1;
+float x;
+float y;
+float w;
+float h;
+float alpha;
+vec2 pos;
+//Vertex shader
+vec4 vertex(){
+  vec2 shift = -draw_list_scroll*draw_list_do_scroll;
+  vec2 clipped = clamp(geom*vec2(w, h)+vec2(x, y)+shift, draw_list_clip.xy, draw_list_clip.zw);
+  pos = (clipped-shift-vec2(x, y))/vec2(w, h);
+  return vec4(clipped, 0.0, 1.0)*camera_projection;
+}
+//Main function
+void main(){
+    geom=geomattr0.xy;
+    x=instattr0.x;
+    y=instattr0.y;
+    w=instattr0.z;
+    h=instattr0.w;
+    alpha=instattr1;
+
+    gl_Position = vertex();
+
+    // Varying packing
+    varying0.xy =pos;
+    varying0.z = alpha;
+
+}
+
+// fragment
+#version 100
+#extension GL_OES_standard_derivatives : enable
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform float draw_list_do_scroll;
+//Texture slots
+uniform sampler2D texture;
+// Varyings
+varying vec3 varying0;
+vec2 pos;
+float alpha;
+//Pixel shader
+vec4 pixel(){
+  return texture2D(texture, pos.xy)*alpha;
+}
+//Main function
+void main(){
+
+    // Varying unpacking
+    pos=varying0.xy;
+    alpha=varying0.z;
+
+    gl_FragColor = pixel();
+
+}
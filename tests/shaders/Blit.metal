@@ -0,0 +1,68 @@
+#include <metal_stdlib>
+using namespace metal;
+struct _Geom{
+  packed_float2 geom;
+};
+
+struct _Inst{
+  float x;
+  float y;
+  float w;
+  float h;
+  float alpha;
+};
+
+struct _UniCx{
+  float4x4 camera_projection;
+};
+
+struct _UniDl{
+  packed_float2 draw_list_scroll;
+  packed_float4 draw_list_clip;
+};
+
+struct _UniDr{
+  float draw_list_do_scroll;
+};
+
+struct _Loc{
+};
+
+struct _Tex{
+texture2d<float> texture [[texture(0)]];
+};
+
+struct _Vary{
+  float4 mtl_position [[position]];
+  float2 pos;
+  float alpha;
+};
+
+//Vertex shader
+float4 _vertex(_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, thread _Geom &_geom, thread _Inst &_inst, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float2 shift = -float2(_uni_dl.draw_list_scroll)*float(_uni_dr.draw_list_do_scroll);
+  float2 clipped = clamp(float2(_geom.geom)*float2(float(_inst.w), float(_inst.h))+float2(float(_inst.x), float(_inst.y))+shift, float4(_uni_dl.draw_list_clip).xy, float4(_uni_dl.draw_list_clip).zw);
+  _vary.pos = (clipped-shift-float2(float(_inst.x), float(_inst.y)))/float2(float(_inst.w), float(_inst.h));
+  return float4(clipped, 0.0, 1.0)*float4x4(_uni_cx.camera_projection);
+}
+//Pixel shader
+float4 _pixel(_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  return _tex.texture.sample(sampler(mag_filter::linear,min_filter::linear),_vary.pos.xy)*_vary.alpha;
+}
+vertex _Vary _vertex_shader(_Tex _tex, device _Geom *in_geometries [[buffer(0)]], device _Inst *in_instances [[buffer(1)]],
+  device _UniCx &_uni_cx [[buffer(2)]], device _UniDl &_uni_dl [[buffer(3)]], device _UniDr &_uni_dr [[buffer(4)]],
+  uint vtx_id [[vertex_id]], uint inst_id [[instance_id]]){
+  _Loc _loc;
+  _Vary _vary;
+  _Geom _geom = in_geometries[vtx_id];
+  _Inst _inst = in_instances[inst_id];
+  _vary.mtl_position = _vertex(_tex, _loc, _vary, _geom, _inst, _uni_cx, _uni_dl, _uni_dr);
+
+       _vary.alpha = _inst.alpha;
+       return _vary;
+};
+fragment float4 _fragment_shader(_Vary _vary[[stage_in]],_Tex _tex,
+  device _UniCx &_uni_cx [[buffer(0)]], device _UniDl &_uni_dl [[buffer(1)]], device _UniDr &_uni_dr [[buffer(2)]]){
+  _Loc _loc;
+  return _pixel(_tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+};
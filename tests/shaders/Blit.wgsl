@@ -0,0 +1,94 @@
+struct _GeomAttr{
+  @location(0) geomattr0: vec2<f32>,
+};
+
+struct _InstAttr{
+  @location(1) instattr0: vec4<f32>,
+  @location(2) instattr1: f32,
+};
+
+struct _Geom{
+  geom: vec2<f32>,
+};
+
+var<private> _geom: _Geom;
+
+struct _Inst{
+  x: f32,
+  y: f32,
+  w: f32,
+  h: f32,
+  alpha: f32,
+};
+
+var<private> _inst: _Inst;
+
+struct _UniCx{
+  camera_projection: mat4x4<f32>,
+};
+
+var<private> _uni_cx: _UniCx;
+
+struct _UniDl{
+  draw_list_scroll: vec2<f32>,
+  draw_list_clip: vec4<f32>,
+};
+
+var<private> _uni_dl: _UniDl;
+
+struct _UniDr{
+  draw_list_do_scroll: f32,
+};
+
+var<private> _uni_dr: _UniDr;
+
+@group(0) @binding(0) var<storage, read> _uni_cx_buf: array<f32>;
+@group(0) @binding(1) var<storage, read> _uni_dl_buf: array<f32>;
+@group(0) @binding(2) var<storage, read> _uni_dr_buf: array<f32>;
+@group(1) @binding(0) var _sampler: sampler;
+@group(1) @binding(1) var texture: texture_2d<f32>;
+
+struct _Vary{
+  @builtin(position) wgsl_position: vec4<f32>,
+  @location(0) pos: vec2<f32>,
+  @location(1) alpha: f32,
+};
+
+var<private> _vary: _Vary;
+
+//Vertex shader
+fn _vertex()->vec4<f32>{
+  var shift:vec2<f32> = -_uni_dl.draw_list_scroll*_uni_dr.draw_list_do_scroll;
+  var clipped:vec2<f32> = clamp(_geom.geom*vec2<f32>(_inst.w, _inst.h)+vec2<f32>(_inst.x, _inst.y)+shift, _uni_dl.draw_list_clip.xy, _uni_dl.draw_list_clip.zw);
+  _vary.pos = (clipped-shift-vec2<f32>(_inst.x, _inst.y))/vec2<f32>(_inst.w, _inst.h);
+  return vec4<f32>(clipped, 0.0, 1.0)*_uni_cx.camera_projection;
+}
+//Pixel shader
+fn _pixel()->vec4<f32>{
+  return textureSampleLevel(texture, _sampler, _vary.pos.xy, 0.0)*_vary.alpha;
+}
+@vertex
+fn _vertex_shader(_geom_attr: _GeomAttr, _inst_attr: _InstAttr)->_Vary{
+  _geom.geom = vec2<f32>(_geom_attr.geomattr0.x, _geom_attr.geomattr0.y);
+  _inst.x = _inst_attr.instattr0.x;
+  _inst.y = _inst_attr.instattr0.y;
+  _inst.w = _inst_attr.instattr0.z;
+  _inst.h = _inst_attr.instattr0.w;
+  _inst.alpha = _inst_attr.instattr1;
+  _uni_cx.camera_projection = mat4x4<f32>(_uni_cx_buf[0], _uni_cx_buf[1], _uni_cx_buf[2], _uni_cx_buf[3], _uni_cx_buf[4], _uni_cx_buf[5], _uni_cx_buf[6], _uni_cx_buf[7], _uni_cx_buf[8], _uni_cx_buf[9], _uni_cx_buf[10], _uni_cx_buf[11], _uni_cx_buf[12], _uni_cx_buf[13], _uni_cx_buf[14], _uni_cx_buf[15]);
+  _uni_dl.draw_list_scroll = vec2<f32>(_uni_dl_buf[0], _uni_dl_buf[1]);
+  _uni_dl.draw_list_clip = vec4<f32>(_uni_dl_buf[2], _uni_dl_buf[3], _uni_dl_buf[4], _uni_dl_buf[5]);
+  _uni_dr.draw_list_do_scroll = _uni_dr_buf[0];
+  _vary.wgsl_position = _vertex();
+  _vary.alpha = _inst.alpha;
+  return _vary;
+}
+@fragment
+fn _fragment_shader(_vary_in: _Vary)->@location(0) vec4<f32>{
+  _vary = _vary_in;
+  _uni_cx.camera_projection = mat4x4<f32>(_uni_cx_buf[0], _uni_cx_buf[1], _uni_cx_buf[2], _uni_cx_buf[3], _uni_cx_buf[4], _uni_cx_buf[5], _uni_cx_buf[6], _uni_cx_buf[7], _uni_cx_buf[8], _uni_cx_buf[9], _uni_cx_buf[10], _uni_cx_buf[11], _uni_cx_buf[12], _uni_cx_buf[13], _uni_cx_buf[14], _uni_cx_buf[15]);
+  _uni_dl.draw_list_scroll = vec2<f32>(_uni_dl_buf[0], _uni_dl_buf[1]);
+  _uni_dl.draw_list_clip = vec4<f32>(_uni_dl_buf[2], _uni_dl_buf[3], _uni_dl_buf[4], _uni_dl_buf[5]);
+  _uni_dr.draw_list_do_scroll = _uni_dr_buf[0];
+  return _pixel();
+}
@@ -0,0 +1,139 @@
+#include <metal_stdlib>
+using namespace metal;
+struct _Geom{
+  packed_float2 geom;
+};
+
+struct _Inst{
+  float x;
+  float y;
+  float w;
+  float h;
+  packed_float4 color;
+  float folded;
+};
+
+struct _UniCx{
+  float4x4 camera_projection;
+};
+
+struct _UniDl{
+  packed_float2 draw_list_scroll;
+  packed_float4 draw_list_clip;
+};
+
+struct _UniDr{
+  float draw_list_do_scroll;
+};
+
+struct _Loc{
+  float2 df_pos;
+  float4 df_result;
+  float2 df_last_pos;
+  float2 df_start_pos;
+  float df_shape;
+  float df_old_shape;
+  float df_blur;
+  float df_aa;
+  float df_scale;
+  float df_field;
+};
+
+struct _Tex{
+};
+
+struct _Vary{
+  float4 mtl_position [[position]];
+  float2 pos;
+  float w;
+  float h;
+  float folded;
+  float4 color;
+};
+
+//Vertex shader
+float4 _vertex(_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, thread _Geom &_geom, thread _Inst &_inst, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float2 shift = -float2(_uni_dl.draw_list_scroll)*float(_uni_dr.draw_list_do_scroll);
+  float2 clipped = clamp(float2(_geom.geom)*float2(float(_inst.w), float(_inst.h))+float2(float(_inst.x), float(_inst.y))+shift, float4(_uni_dl.draw_list_clip).xy, float4(_uni_dl.draw_list_clip).zw);
+  _vary.pos = (clipped-shift-float2(float(_inst.x), float(_inst.y)))/float2(float(_inst.w), float(_inst.h));
+  return float4(clipped, 0.0, 1.0)*float4x4(_uni_cx.camera_projection);
+}
+//Pixel shader
+float _df_calc_blur(float w, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float wa = clamp(-w*_loc.df_aa, 0.0, 1.0);
+  float wb = 1.0;
+  if(_loc.df_blur>0.0001){
+    wb = clamp(-w/_loc.df_blur, 0.0, 1.0);
+};
+  return wa*wb;
+}
+float4 _df_stroke_keep(float4 color, float width, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float f = _df_calc_blur(abs(_loc.df_shape)-width/_loc.df_scale, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+  float4 source = float4(color.rgb*color.a, color.a);
+  float4 dest = _loc.df_result;
+  _loc.df_result = source*f+dest*(1.0-source.a*f);
+  return _loc.df_result;
+}
+float4 _df_stroke(float4 color, float width, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  _df_stroke_keep(color, width, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+  _loc.df_old_shape = _loc.df_shape = 100000000000000000000.0;
+  return _loc.df_result;
+}
+void _df_line_to(float x, float y, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float2 p = float2(x, y);
+  float2 pa = _loc.df_pos-_loc.df_last_pos;
+  float2 ba = p-_loc.df_last_pos;
+  float h = clamp(dot(pa, ba)/dot(ba, ba), 0.0, 1.0);
+  _loc.df_field = length(pa-ba*h)/_loc.df_scale;
+  _loc.df_old_shape = _loc.df_shape;
+  _loc.df_shape = min(_loc.df_shape, _loc.df_field);
+  _loc.df_last_pos = p;
+}
+void _df_move_to(float x, float y, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  _loc.df_last_pos = _loc.df_start_pos = float2(x, y);
+}
+float2 _df_viewport(float2 pos, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  _loc.df_pos = pos;
+  _loc.df_result = float4(0.0);
+  _loc.df_old_shape = _loc.df_shape = 100000000000000000000.0;
+  _loc.df_blur = 0.00001;
+  _loc.df_aa = (1.0/length(float2(length(dfdx(pos)), length(dfdy(pos)))));
+  _loc.df_scale = 1.0;
+  _loc.df_field = 0.0;
+  return _loc.df_pos;
+}
+float4 _pixel(_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  _df_viewport(_vary.pos*float2(_vary.w, _vary.h), _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+  float2 c = float2(_vary.w, _vary.h)*0.5;
+  float s = min(_vary.w, _vary.h)*0.2;
+  if(_vary.folded>0.5){
+    _df_move_to(c.x-0.5*s, c.y-s, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    _df_line_to(c.x+0.5*s, c.y, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    _df_line_to(c.x-0.5*s, c.y+s, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+}else {
+    _df_move_to(c.x-s, c.y-0.5*s, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    _df_line_to(c.x, c.y+0.5*s, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    _df_line_to(c.x+s, c.y-0.5*s, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+};
+  return _df_stroke(_vary.color, 1.0, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+}
+vertex _Vary _vertex_shader(_Tex _tex, device _Geom *in_geometries [[buffer(0)]], device _Inst *in_instances [[buffer(1)]],
+  device _UniCx &_uni_cx [[buffer(2)]], device _UniDl &_uni_dl [[buffer(3)]], device _UniDr &_uni_dr [[buffer(4)]],
+  uint vtx_id [[vertex_id]], uint inst_id [[instance_id]]){
+  _Loc _loc;
+  _Vary _vary;
+  _Geom _geom = in_geometries[vtx_id];
+  _Inst _inst = in_instances[inst_id];
+  _vary.mtl_position = _vertex(_tex, _loc, _vary, _geom, _inst, _uni_cx, _uni_dl, _uni_dr);
+
+       _vary.w = _inst.w;
+       _vary.h = _inst.h;
+       _vary.folded = _inst.folded;
+       _vary.color = _inst.color;
+       return _vary;
+};
+fragment float4 _fragment_shader(_Vary _vary[[stage_in]],_Tex _tex,
+  device _UniCx &_uni_cx [[buffer(0)]], device _UniDl &_uni_dl [[buffer(1)]], device _UniDr &_uni_dr [[buffer(2)]]){
+  _Loc _loc;
+  return _pixel(_tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+};
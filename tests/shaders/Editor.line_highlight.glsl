@@ -0,0 +1,88 @@
+// vertex
+#version 100
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform float draw_list_do_scroll;
+//Texture slots
+// Varyings
+varying vec4 varying0;
+varying vec2 varying1;
+// Geometry attributes
+attribute vec2 geomattr0;
+vec2 geom;
+// Instance attributes
+attribute vec4 instattr0;
+attribute vec4 instattr1;
+float x;
+float y;
+float w;
+float h;
+vec4 color;
+vec2 pos;
+//Vertex shader
+vec4 vertex(){
+  vec2 shift = -draw_list_scroll*draw_list_do_scroll;
+  vec2 clipped = clamp(geom*vec2(w, h)+vec2(x, y)+shift, draw_list_clip.xy, draw_list_clip.zw);
+  pos = (clipped-shift-vec2(x, y))/vec2(w, h);
+  return vec4(clipped, 0.0, 1.0)*camera_projection;
+}
+//Main function
+void main(){
+    geom=geomattr0.xy;
+    x=instattr0.x;
+    y=instattr0.y;
+    w=instattr0.z;
+    h=instattr0.w;
+    color=instattr1.xyzw;
+
+    gl_Position = vertex();
+
+    // Varying packing
+    varying0.xy =pos;
+    varying0.z = color.x;
+    varying0.w = color.y;
+    varying1.x = color.z;
+    varying1.y = color.w;
+
+}
+
+// fragment
+#version 100
+#extension GL_OES_standard_derivatives : enable
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform float draw_list_do_scroll;
+//Texture slots
+// Varyings
+varying vec4 varying0;
+varying vec2 varying1;
+vec2 pos;
+vec4 color;
+//Pixel shader
+vec4 pixel(){
+  return vec4(color.rgb*color.a, color.a);
+}
+//Main function
+void main(){
+
+    // Varying unpacking
+    pos=varying0.xy;
+    color=vec4(varying0.z, varying0.w, varying1.x, varying1.y);
+
+    gl_FragColor = pixel();
+
+}
@@ -0,0 +1,182 @@
+struct _GeomAttr{
+  @location(0) geomattr0: vec2<f32>,
+};
+
+struct _InstAttr{
+  @location(1) instattr0: vec4<f32>,
+  @location(2) instattr1: vec4<f32>,
+  @location(3) instattr2: vec4<f32>,
+};
+
+struct _Geom{
+  geom: vec2<f32>,
+};
+
+var<private> _geom: _Geom;
+
+struct _Inst{
+  x: f32,
+  y: f32,
+  w: f32,
+  h: f32,
+  color: vec4<f32>,
+  prev_x: f32,
+  prev_w: f32,
+  next_x: f32,
+  next_w: f32,
+};
+
+var<private> _inst: _Inst;
+
+struct _UniCx{
+  camera_projection: mat4x4<f32>,
+};
+
+var<private> _uni_cx: _UniCx;
+
+struct _UniDl{
+  draw_list_scroll: vec2<f32>,
+  draw_list_clip: vec4<f32>,
+};
+
+var<private> _uni_dl: _UniDl;
+
+struct _UniDr{
+  draw_list_do_scroll: f32,
+};
+
+var<private> _uni_dr: _UniDr;
+
+struct _Loc{
+  df_pos: vec2<f32>,
+  df_result: vec4<f32>,
+  df_shape: f32,
+  df_old_shape: f32,
+  df_blur: f32,
+  df_aa: f32,
+  df_scale: f32,
+  df_field: f32,
+};
+
+var<private> _loc: _Loc;
+
+@group(0) @binding(0) var<storage, read> _uni_cx_buf: array<f32>;
+@group(0) @binding(1) var<storage, read> _uni_dl_buf: array<f32>;
+@group(0) @binding(2) var<storage, read> _uni_dr_buf: array<f32>;
+@group(1) @binding(0) var _sampler: sampler;
+
+struct _Vary{
+  @builtin(position) wgsl_position: vec4<f32>,
+  @location(0) pos: vec2<f32>,
+  @location(1) w: f32,
+  @location(2) h: f32,
+  @location(3) prev_w: f32,
+  @location(4) prev_x: f32,
+  @location(5) next_w: f32,
+  @location(6) next_x: f32,
+  @location(7) color: vec4<f32>,
+};
+
+var<private> _vary: _Vary;
+
+//Vertex shader
+fn _vertex()->vec4<f32>{
+  var shift:vec2<f32> = -_uni_dl.draw_list_scroll*_uni_dr.draw_list_do_scroll;
+  var clipped:vec2<f32> = clamp(_geom.geom*vec2<f32>(_inst.w+16.0, _inst.h)+vec2<f32>(_inst.x, _inst.y)+shift-vec2<f32>(8.0, 0.0), _uni_dl.draw_list_clip.xy, _uni_dl.draw_list_clip.zw);
+  _vary.pos = (clipped-shift-vec2<f32>(_inst.x, _inst.y))/vec2<f32>(_inst.w, _inst.h);
+  return vec4<f32>(clipped, 0.0, 1.0)*_uni_cx.camera_projection;
+}
+//Pixel shader
+fn _df_calc_blur(w:f32)->f32{
+  var wa:f32 = clamp(-w*_loc.df_aa, 0.0, 1.0);
+  var wb:f32 = 1.0;
+  if(_loc.df_blur>0.0001){
+    wb = clamp(-w/_loc.df_blur, 0.0, 1.0);
+};
+  return wa*wb;
+}
+fn _df_fill_keep(color:vec4<f32>)->vec4<f32>{
+  var f:f32 = _df_calc_blur(_loc.df_shape);
+  var source:vec4<f32> = vec4<f32>(color.rgb*color.a, color.a);
+  var dest:vec4<f32> = _loc.df_result;
+  _loc.df_result = source*f+dest*(1.0-source.a*f);
+  return _loc.df_result;
+}
+fn _df_fill(color:vec4<f32>)->vec4<f32>{
+  _df_fill_keep(color);
+  _loc.df_shape = 100000000000000000000.0;
+  _loc.df_old_shape = _loc.df_shape;
+  return _loc.df_result;
+}
+fn _df_gloop(k:f32){
+  var h:f32 = clamp(0.5+0.5*(_loc.df_old_shape-_loc.df_field)/k, 0.0, 1.0);
+  _loc.df_shape = mix(_loc.df_old_shape, _loc.df_field, h)-k*h*(1.0-h);
+  _loc.df_old_shape = _loc.df_shape;
+}
+fn _df_box(x:f32, y:f32, w:f32, h:f32, r:f32){
+  var p:vec2<f32> = _loc.df_pos-vec2<f32>(x, y);
+  var size:vec2<f32> = vec2<f32>(0.5*w, 0.5*h);
+  var bp:vec2<f32> = max(abs(p-size.xy)-(size.xy-vec2<f32>(2.0*r).xy), vec2<f32>(0.0));
+  _loc.df_field = (length(bp)-2.0*r)/_loc.df_scale;
+  _loc.df_old_shape = _loc.df_shape;
+  _loc.df_shape = min(_loc.df_shape, _loc.df_field);
+}
+fn _df_viewport(pos:vec2<f32>)->vec2<f32>{
+  _loc.df_pos = pos;
+  _loc.df_result = vec4<f32>(0.0);
+  _loc.df_shape = 100000000000000000000.0;
+  _loc.df_old_shape = _loc.df_shape;
+  _loc.df_blur = 0.00001;
+  _loc.df_aa = (1.0/length(vec2<f32>(length(dpdx(pos)), length(dpdy(pos)))));
+  _loc.df_scale = 1.0;
+  _loc.df_field = 0.0;
+  return _loc.df_pos;
+}
+fn _pixel()->vec4<f32>{
+  _df_viewport(_vary.pos*vec2<f32>(_vary.w, _vary.h));
+  _df_box(0.0, 0.0, _vary.w, _vary.h, 2.0);
+  if(_vary.prev_w>0.0){
+    _df_box(_vary.prev_x, -_vary.h, _vary.prev_w, _vary.h, 2.0);
+    _df_gloop(8.0);
+};
+  if(_vary.next_w>0.0){
+    _df_box(_vary.next_x, _vary.h, _vary.next_w, _vary.h, 2.0);
+    _df_gloop(8.0);
+};
+  return _df_fill(_vary.color);
+}
+@vertex
+fn _vertex_shader(_geom_attr: _GeomAttr, _inst_attr: _InstAttr)->_Vary{
+  _geom.geom = vec2<f32>(_geom_attr.geomattr0.x, _geom_attr.geomattr0.y);
+  _inst.x = _inst_attr.instattr0.x;
+  _inst.y = _inst_attr.instattr0.y;
+  _inst.w = _inst_attr.instattr0.z;
+  _inst.h = _inst_attr.instattr0.w;
+  _inst.color = vec4<f32>(_inst_attr.instattr1.x, _inst_attr.instattr1.y, _inst_attr.instattr1.z, _inst_attr.instattr1.w);
+  _inst.prev_x = _inst_attr.instattr2.x;
+  _inst.prev_w = _inst_attr.instattr2.y;
+  _inst.next_x = _inst_attr.instattr2.z;
+  _inst.next_w = _inst_attr.instattr2.w;
+  _uni_cx.camera_projection = mat4x4<f32>(_uni_cx_buf[0], _uni_cx_buf[1], _uni_cx_buf[2], _uni_cx_buf[3], _uni_cx_buf[4], _uni_cx_buf[5], _uni_cx_buf[6], _uni_cx_buf[7], _uni_cx_buf[8], _uni_cx_buf[9], _uni_cx_buf[10], _uni_cx_buf[11], _uni_cx_buf[12], _uni_cx_buf[13], _uni_cx_buf[14], _uni_cx_buf[15]);
+  _uni_dl.draw_list_scroll = vec2<f32>(_uni_dl_buf[0], _uni_dl_buf[1]);
+  _uni_dl.draw_list_clip = vec4<f32>(_uni_dl_buf[2], _uni_dl_buf[3], _uni_dl_buf[4], _uni_dl_buf[5]);
+  _uni_dr.draw_list_do_scroll = _uni_dr_buf[0];
+  _vary.wgsl_position = _vertex();
+  _vary.w = _inst.w;
+  _vary.h = _inst.h;
+  _vary.prev_w = _inst.prev_w;
+  _vary.prev_x = _inst.prev_x;
+  _vary.next_w = _inst.next_w;
+  _vary.next_x = _inst.next_x;
+  _vary.color = _inst.color;
+  return _vary;
+}
+@fragment
+fn _fragment_shader(_vary_in: _Vary)->@location(0) vec4<f32>{
+  _vary = _vary_in;
+  _uni_cx.camera_projection = mat4x4<f32>(_uni_cx_buf[0], _uni_cx_buf[1], _uni_cx_buf[2], _uni_cx_buf[3], _uni_cx_buf[4], _uni_cx_buf[5], _uni_cx_buf[6], _uni_cx_buf[7], _uni_cx_buf[8], _uni_cx_buf[9], _uni_cx_buf[10], _uni_cx_buf[11], _uni_cx_buf[12], _uni_cx_buf[13], _uni_cx_buf[14], _uni_cx_buf[15]);
+  _uni_dl.draw_list_scroll = vec2<f32>(_uni_dl_buf[0], _uni_dl_buf[1]);
+  _uni_dl.draw_list_clip = vec4<f32>(_uni_dl_buf[2], _uni_dl_buf[3], _uni_dl_buf[4], _uni_dl_buf[5]);
+  _uni_dr.draw_list_do_scroll = _uni_dr_buf[0];
+  return _pixel();
+}
@@ -0,0 +1,147 @@
+// vertex
+#version 100
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform float draw_list_do_scroll;
+//Texture slots
+// Varyings
+varying vec4 varying0;
+varying vec4 varying1;
+vec2 df_pos;
+vec4 df_result;
+float df_shape;
+float df_old_shape;
+float df_blur;
+float df_aa;
+float df_scale;
+float df_field;
+// Geometry attributes
+attribute vec2 geomattr0;
+vec2 geom;
+// Instance attributes
+attribute vec4 instattr0;
+attribute vec4 instattr1;
+float x;
+float y;
+float w;
+float h;
+vec4 color;
+vec2 pos;
+//Vertex shader
+vec4 vertex(){
+  vec2 shift = -draw_list_scroll*draw_list_do_scroll;
+  vec2 clipped = clamp(geom*vec2(w, h)+vec2(x, y)+shift, draw_list_clip.xy, draw_list_clip.zw);
+  pos = (clipped-shift-vec2(x, y))/vec2(w, h);
+  return vec4(clipped, 0.0, 1.0)*camera_projection;
+}
+//Main function
+void main(){
+    geom=geomattr0.xy;
+    x=instattr0.x;
+    y=instattr0.y;
+    w=instattr0.z;
+    h=instattr0.w;
+    color=instattr1.xyzw;
+
+    gl_Position = vertex();
+
+    // Varying packing
+    varying0.xy =pos;
+    varying0.z = w;
+    varying0.w = h;
+    varying1.xyzw =color;
+
+}
+
+// fragment
+#version 100
+#extension GL_OES_standard_derivatives : enable
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform float draw_list_do_scroll;
+//Texture slots
+// Varyings
+varying vec4 varying0;
+varying vec4 varying1;
+vec2 df_pos;
+vec4 df_result;
+float df_shape;
+float df_old_shape;
+float df_blur;
+float df_aa;
+float df_scale;
+float df_field;
+vec2 pos;
+float w;
+float h;
+vec4 color;
+//Pixel shader
+float df_calc_blur(float w){
+  float wa = clamp(-w*df_aa, 0.0, 1.0);
+  float wb = 1.0;
+  if(df_blur>0.0001){
+    wb = clamp(-w/df_blur, 0.0, 1.0);
+};
+  return wa*wb;
+}
+vec4 df_fill_keep(vec4 color){
+  float f = df_calc_blur(df_shape);
+  vec4 source = vec4(color.rgb*color.a, color.a);
+  vec4 dest = df_result;
+  df_result = source*f+dest*(1.0-source.a*f);
+  return df_result;
+}
+vec4 df_fill(vec4 color){
+  df_fill_keep(color);
+  df_old_shape = df_shape = 100000000000000000000.0;
+  return df_result;
+}
+void df_box(float x, float y, float w, float h, float r){
+  vec2 p = df_pos-vec2(x, y);
+  vec2 size = vec2(0.5*w, 0.5*h);
+  vec2 bp = max(abs(p-size.xy)-(size.xy-vec2(2.0*r).xy), vec2(0.0));
+  df_field = (length(bp)-2.0*r)/df_scale;
+  df_old_shape = df_shape;
+  df_shape = min(df_shape, df_field);
+}
+vec2 df_viewport(vec2 pos){
+  df_pos = pos;
+  df_result = vec4(0.0);
+  df_old_shape = df_shape = 100000000000000000000.0;
+  df_blur = 0.00001;
+  df_aa = (1.0/length(vec2(length(dFdx(pos)), length(dFdy(pos)))));
+  df_scale = 1.0;
+  df_field = 0.0;
+  return df_pos;
+}
+vec4 pixel(){
+  df_viewport(pos*vec2(w, h));
+  df_box(0.0, 0.0, w, h, 2.0);
+  return df_fill(color);
+}
+//Main function
+void main(){
+
+    // Varying unpacking
+    pos=varying0.xy;
+    w=varying0.z;
+    h=varying0.w;
+    color=varying1.xyzw;
+
+    gl_FragColor = pixel();
+
+}
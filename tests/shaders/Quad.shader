@@ -0,0 +1,25 @@
+// the shader_ast! body of widgets/render/src/quad.rs
+let geom:vec2<Geometry>;
+let x:float<Instance>;
+let y:float<Instance>;
+let w:float<Instance>;
+let h:float<Instance>;
+let color:vec4<Instance>;
+let pos:vec2<Varying>;
+let draw_list_do_scroll:float<Uniform>;
+
+fn vertex()->vec4{
+    let shift:vec2 = -draw_list_scroll * draw_list_do_scroll;
+    let clipped:vec2 = clamp(
+        geom*vec2(w, h) + vec2(x, y) + shift,
+        draw_list_clip.xy,
+        draw_list_clip.zw
+    );
+    pos = (clipped - shift - vec2(x,y)) / vec2(w, h);
+    // only pass the clipped position forward
+    return vec4(clipped,0.,1.) * camera_projection;
+}
+
+fn pixel()->vec4{
+    return vec4(color.rgb*color.a, color.a);
+}
@@ -0,0 +1,28 @@
+// the shader_ast! body of widgets/src/scrollbar.rs
+let is_vertical:float<Instance>;
+
+let norm_handle:float<Instance>;
+let norm_scroll:float<Instance>;
+
+const border_radius:float = 1.5;
+
+fn vertex()->vec4{
+    let clipped:vec2 = clamp(
+        geom*vec2(w, h) + vec2(x, y),
+        draw_list_clip.xy,
+        draw_list_clip.zw
+    );
+    pos = (clipped - vec2(x,y)) / vec2(w, h);
+    return vec4(clipped,0.,1.) * camera_projection;
+}
+
+fn pixel()->vec4{
+    df_viewport(pos * vec2(w, h));
+    if is_vertical > 0.5{
+        df_box(1., h*norm_scroll, w*0.5, h*norm_handle, border_radius);
+    }
+    else{
+        df_box(w*norm_scroll, 1., w*norm_handle, h*0.5, border_radius);
+    }
+    return df_fill_keep(color);
+}
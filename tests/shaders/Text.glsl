@@ -0,0 +1,181 @@
+// vertex
+#version 100
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform vec2 tex_size;
+uniform float brightness;
+//Texture slots
+uniform sampler2D texture;
+// Varyings
+varying vec4 varying0;
+varying vec4 varying1;
+varying vec4 varying2;
+varying float varying3;
+vec2 df_pos;
+vec4 df_result;
+float df_shape;
+float df_old_shape;
+float df_blur;
+float df_aa;
+float df_scale;
+float df_field;
+// Geometry attributes
+attribute vec2 geomattr0;
+vec2 geom;
+// Instance attributes
+attribute vec4 instattr0;
+attribute vec4 instattr1;
+attribute vec4 instattr2;
+attribute vec4 instattr3;
+vec4 font_geom;
+vec4 font_tc;
+vec4 color;
+float x;
+float y;
+float font_size;
+float marker;
+vec2 tex_coord;
+vec2 clipped;
+vec4 rect;
+//Vertex shader
+vec4 vertex(){
+  vec2 shift = -draw_list_scroll;
+  vec2 min_pos = vec2(x+font_size*font_geom.x, y-font_size*font_geom.y+font_size);
+  vec2 max_pos = vec2(x+font_size*font_geom.z, y-font_size*font_geom.w+font_size);
+  clipped = clamp(mix(min_pos, max_pos, geom)+shift, draw_list_clip.xy, draw_list_clip.zw);
+  vec2 normalized = (clipped-min_pos-shift)/(max_pos-min_pos);
+  rect = vec4(min_pos.x, min_pos.y, max_pos.x, max_pos.y)+shift.xyxy;
+  tex_coord = mix(font_tc.xy, font_tc.zw, normalized.xy);
+  return vec4(clipped, 0.0, 1.0)*camera_projection;
+}
+//Main function
+void main(){
+    geom=geomattr0.xy;
+    font_geom=instattr0.xyzw;
+    font_tc=instattr1.xyzw;
+    color=instattr2.xyzw;
+    x=instattr3.x;
+    y=instattr3.y;
+    font_size=instattr3.z;
+    marker=instattr3.w;
+
+    gl_Position = vertex();
+
+    // Varying packing
+    varying0.xy =tex_coord;
+    varying0.zw =clipped;
+    varying1.xyzw =rect;
+    varying2.x = marker;
+    varying2.y = color.x;
+    varying2.z = color.y;
+    varying2.w = color.z;
+    varying3 = color.w;
+
+}
+
+// fragment
+#version 100
+#extension GL_OES_standard_derivatives : enable
+precision highp float;
+precision highp int;
+// Consts
+//Context uniforms
+uniform mat4 camera_projection;
+//DrawList uniforms
+uniform vec2 draw_list_scroll;
+uniform vec4 draw_list_clip;
+//Draw uniforms
+uniform vec2 tex_size;
+uniform float brightness;
+//Texture slots
+uniform sampler2D texture;
+// Varyings
+varying vec4 varying0;
+varying vec4 varying1;
+varying vec4 varying2;
+varying float varying3;
+vec2 df_pos;
+vec4 df_result;
+float df_shape;
+float df_old_shape;
+float df_blur;
+float df_aa;
+float df_scale;
+float df_field;
+vec2 tex_coord;
+vec2 clipped;
+vec4 rect;
+float marker;
+vec4 color;
+//Pixel shader
+float df_calc_blur(float w){
+  float wa = clamp(-w*df_aa, 0.0, 1.0);
+  float wb = 1.0;
+  if(df_blur>0.0001){
+    wb = clamp(-w/df_blur, 0.0, 1.0);
+};
+  return wa*wb;
+}
+vec4 df_fill_keep(vec4 color){
+  float f = df_calc_blur(df_shape);
+  vec4 source = vec4(color.rgb*color.a, color.a);
+  vec4 dest = df_result;
+  df_result = source*f+dest*(1.0-source.a*f);
+  return df_result;
+}
+vec4 df_fill(vec4 color){
+  df_fill_keep(color);
+  df_old_shape = df_shape = 100000000000000000000.0;
+  return df_result;
+}
+vec2 df_viewport(vec2 pos){
+  df_pos = pos;
+  df_result = vec4(0.0);
+  df_old_shape = df_shape = 100000000000000000000.0;
+  df_blur = 0.00001;
+  df_aa = (1.0/length(vec2(length(dFdx(pos)), length(dFdy(pos)))));
+  df_scale = 1.0;
+  df_field = 0.0;
+  return df_pos;
+}
+void df_circle(float x, float y, float r){
+  vec2 c = df_pos-vec2(x, y);
+  df_field = (length(c.xy)-r)/df_scale;
+  df_old_shape = df_shape;
+  df_shape = min(df_shape, df_field);
+}
+vec4 pixel(){
+  if(marker>0.5){
+    df_viewport(clipped);
+    vec2 center = (rect.xy+rect.zw)*0.5;
+    df_circle(center.x, center.y, 1.0);
+    return df_fill(color);
+}else {
+    vec4 s = texture2D(texture, tex_coord.xy);
+    float sig_dist = max(min(s.r, s.g), min(max(s.r, s.g), s.b))-0.5;
+    float scale = pow((1.0/length(vec2(length(dFdx(clipped)), length(dFdy(clipped)))))*0.002, 0.5);
+    df_viewport(tex_coord*tex_size*0.1);
+    df_shape = -sig_dist-0.5/df_aa;
+    return df_fill(color*brightness);
+};
+}
+//Main function
+void main(){
+
+    // Varying unpacking
+    tex_coord=varying0.xy;
+    clipped=varying0.zw;
+    rect=varying1.xyzw;
+    marker=varying2.x;
+    color=vec4(varying2.y, varying2.z, varying2.w, varying3);
+
+    gl_FragColor = pixel();
+
+}
@@ -0,0 +1,135 @@
+#include <metal_stdlib>
+using namespace metal;
+struct _Geom{
+  packed_float2 geom;
+};
+
+struct _Inst{
+  packed_float4 font_geom;
+  packed_float4 font_tc;
+  packed_float4 color;
+  float x;
+  float y;
+  float font_size;
+  float marker;
+};
+
+struct _UniCx{
+  float4x4 camera_projection;
+};
+
+struct _UniDl{
+  packed_float2 draw_list_scroll;
+  packed_float4 draw_list_clip;
+};
+
+struct _UniDr{
+  packed_float2 tex_size;
+  float brightness;
+};
+
+struct _Loc{
+  float2 df_pos;
+  float4 df_result;
+  float df_shape;
+  float df_old_shape;
+  float df_blur;
+  float df_aa;
+  float df_scale;
+  float df_field;
+};
+
+struct _Tex{
+texture2d<float> texture [[texture(0)]];
+};
+
+struct _Vary{
+  float4 mtl_position [[position]];
+  float2 tex_coord;
+  float2 clipped;
+  float4 rect;
+  float marker;
+  float4 color;
+};
+
+//Vertex shader
+float4 _vertex(_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, thread _Geom &_geom, thread _Inst &_inst, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float2 shift = -float2(_uni_dl.draw_list_scroll);
+  float2 min_pos = float2(float(_inst.x)+float(_inst.font_size)*float4(_inst.font_geom).x, float(_inst.y)-float(_inst.font_size)*float4(_inst.font_geom).y+float(_inst.font_size));
+  float2 max_pos = float2(float(_inst.x)+float(_inst.font_size)*float4(_inst.font_geom).z, float(_inst.y)-float(_inst.font_size)*float4(_inst.font_geom).w+float(_inst.font_size));
+  _vary.clipped = clamp(mix(min_pos, max_pos, float2(_geom.geom))+shift, float4(_uni_dl.draw_list_clip).xy, float4(_uni_dl.draw_list_clip).zw);
+  float2 normalized = (_vary.clipped-min_pos-shift)/(max_pos-min_pos);
+  _vary.rect = float4(min_pos.x, min_pos.y, max_pos.x, max_pos.y)+shift.xyxy;
+  _vary.tex_coord = mix(float4(_inst.font_tc).xy, float4(_inst.font_tc).zw, normalized.xy);
+  return float4(_vary.clipped, 0.0, 1.0)*float4x4(_uni_cx.camera_projection);
+}
+//Pixel shader
+float _df_calc_blur(float w, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float wa = clamp(-w*_loc.df_aa, 0.0, 1.0);
+  float wb = 1.0;
+  if(_loc.df_blur>0.0001){
+    wb = clamp(-w/_loc.df_blur, 0.0, 1.0);
+};
+  return wa*wb;
+}
+float4 _df_fill_keep(float4 color, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float f = _df_calc_blur(_loc.df_shape, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+  float4 source = float4(color.rgb*color.a, color.a);
+  float4 dest = _loc.df_result;
+  _loc.df_result = source*f+dest*(1.0-source.a*f);
+  return _loc.df_result;
+}
+float4 _df_fill(float4 color, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  _df_fill_keep(color, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+  _loc.df_old_shape = _loc.df_shape = 100000000000000000000.0;
+  return _loc.df_result;
+}
+float2 _df_viewport(float2 pos, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  _loc.df_pos = pos;
+  _loc.df_result = float4(0.0);
+  _loc.df_old_shape = _loc.df_shape = 100000000000000000000.0;
+  _loc.df_blur = 0.00001;
+  _loc.df_aa = (1.0/length(float2(length(dfdx(pos)), length(dfdy(pos)))));
+  _loc.df_scale = 1.0;
+  _loc.df_field = 0.0;
+  return _loc.df_pos;
+}
+void _df_circle(float x, float y, float r, _Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  float2 c = _loc.df_pos-float2(x, y);
+  _loc.df_field = (length(c.xy)-r)/_loc.df_scale;
+  _loc.df_old_shape = _loc.df_shape;
+  _loc.df_shape = min(_loc.df_shape, _loc.df_field);
+}
+float4 _pixel(_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr){
+  if(_vary.marker>0.5){
+    _df_viewport(_vary.clipped, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    float2 center = (_vary.rect.xy+_vary.rect.zw)*0.5;
+    _df_circle(center.x, center.y, 1.0, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    return _df_fill(_vary.color, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+}else {
+    float4 s = _tex.texture.sample(sampler(mag_filter::linear,min_filter::linear),_vary.tex_coord.xy);
+    float sig_dist = max(min(s.r, s.g), min(max(s.r, s.g), s.b))-0.5;
+    float scale = pow((1.0/length(float2(length(dfdx(_vary.clipped)), length(dfdy(_vary.clipped)))))*0.002, 0.5);
+    _df_viewport(_vary.tex_coord*float2(_uni_dr.tex_size)*0.1, _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+    _loc.df_shape = -sig_dist-0.5/_loc.df_aa;
+    return _df_fill(_vary.color*float(_uni_dr.brightness), _tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+};
+}
+vertex _Vary _vertex_shader(_Tex _tex, device _Geom *in_geometries [[buffer(0)]], device _Inst *in_instances [[buffer(1)]],
+  device _UniCx &_uni_cx [[buffer(2)]], device _UniDl &_uni_dl [[buffer(3)]], device _UniDr &_uni_dr [[buffer(4)]],
+  uint vtx_id [[vertex_id]], uint inst_id [[instance_id]]){
+  _Loc _loc;
+  _Vary _vary;
+  _Geom _geom = in_geometries[vtx_id];
+  _Inst _inst = in_instances[inst_id];
+  _vary.mtl_position = _vertex(_tex, _loc, _vary, _geom, _inst, _uni_cx, _uni_dl, _uni_dr);
+
+       _vary.marker = _inst.marker;
+       _vary.color = _inst.color;
+       return _vary;
+};
+fragment float4 _fragment_shader(_Vary _vary[[stage_in]],_Tex _tex,
+  device _UniCx &_uni_cx [[buffer(0)]], device _UniDl &_uni_dl [[buffer(1)]], device _UniDr &_uni_dr [[buffer(2)]]){
+  _Loc _loc;
+  return _pixel(_tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr);
+};
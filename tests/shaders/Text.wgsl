@@ -0,0 +1,174 @@
+struct _GeomAttr{
+  @location(0) geomattr0: vec2<f32>,
+};
+
+struct _InstAttr{
+  @location(1) instattr0: vec4<f32>,
+  @location(2) instattr1: vec4<f32>,
+  @location(3) instattr2: vec4<f32>,
+  @location(4) instattr3: vec4<f32>,
+};
+
+struct _Geom{
+  geom: vec2<f32>,
+};
+
+var<private> _geom: _Geom;
+
+struct _Inst{
+  font_geom: vec4<f32>,
+  font_tc: vec4<f32>,
+  color: vec4<f32>,
+  x: f32,
+  y: f32,
+  font_size: f32,
+  marker: f32,
+};
+
+var<private> _inst: _Inst;
+
+struct _UniCx{
+  camera_projection: mat4x4<f32>,
+};
+
+var<private> _uni_cx: _UniCx;
+
+struct _UniDl{
+  draw_list_scroll: vec2<f32>,
+  draw_list_clip: vec4<f32>,
+};
+
+var<private> _uni_dl: _UniDl;
+
+struct _UniDr{
+  tex_size: vec2<f32>,
+  brightness: f32,
+};
+
+var<private> _uni_dr: _UniDr;
+
+struct _Loc{
+  df_pos: vec2<f32>,
+  df_result: vec4<f32>,
+  df_shape: f32,
+  df_old_shape: f32,
+  df_blur: f32,
+  df_aa: f32,
+  df_scale: f32,
+  df_field: f32,
+};
+
+var<private> _loc: _Loc;
+
+@group(0) @binding(0) var<storage, read> _uni_cx_buf: array<f32>;
+@group(0) @binding(1) var<storage, read> _uni_dl_buf: array<f32>;
+@group(0) @binding(2) var<storage, read> _uni_dr_buf: array<f32>;
+@group(1) @binding(0) var _sampler: sampler;
+@group(1) @binding(1) var texture: texture_2d<f32>;
+
+struct _Vary{
+  @builtin(position) wgsl_position: vec4<f32>,
+  @location(0) tex_coord: vec2<f32>,
+  @location(1) clipped: vec2<f32>,
+  @location(2) rect: vec4<f32>,
+  @location(3) marker: f32,
+  @location(4) color: vec4<f32>,
+};
+
+var<private> _vary: _Vary;
+
+//Vertex shader
+fn _vertex()->vec4<f32>{
+  var shift:vec2<f32> = -_uni_dl.draw_list_scroll;
+  var min_pos:vec2<f32> = vec2<f32>(_inst.x+_inst.font_size*_inst.font_geom.x, _inst.y-_inst.font_size*_inst.font_geom.y+_inst.font_size);
+  var max_pos:vec2<f32> = vec2<f32>(_inst.x+_inst.font_size*_inst.font_geom.z, _inst.y-_inst.font_size*_inst.font_geom.w+_inst.font_size);
+  _vary.clipped = clamp(mix(min_pos, max_pos, _geom.geom)+shift, _uni_dl.draw_list_clip.xy, _uni_dl.draw_list_clip.zw);
+  var normalized:vec2<f32> = (_vary.clipped-min_pos-shift)/(max_pos-min_pos);
+  _vary.rect = vec4<f32>(min_pos.x, min_pos.y, max_pos.x, max_pos.y)+shift.xyxy;
+  _vary.tex_coord = mix(_inst.font_tc.xy, _inst.font_tc.zw, normalized.xy);
+  return vec4<f32>(_vary.clipped, 0.0, 1.0)*_uni_cx.camera_projection;
+}
+//Pixel shader
+fn _df_calc_blur(w:f32)->f32{
+  var wa:f32 = clamp(-w*_loc.df_aa, 0.0, 1.0);
+  var wb:f32 = 1.0;
+  if(_loc.df_blur>0.0001){
+    wb = clamp(-w/_loc.df_blur, 0.0, 1.0);
+};
+  return wa*wb;
+}
+fn _df_fill_keep(color:vec4<f32>)->vec4<f32>{
+  var f:f32 = _df_calc_blur(_loc.df_shape);
+  var source:vec4<f32> = vec4<f32>(color.rgb*color.a, color.a);
+  var dest:vec4<f32> = _loc.df_result;
+  _loc.df_result = source*f+dest*(1.0-source.a*f);
+  return _loc.df_result;
+}
+fn _df_fill(color:vec4<f32>)->vec4<f32>{
+  _df_fill_keep(color);
+  _loc.df_shape = 100000000000000000000.0;
+  _loc.df_old_shape = _loc.df_shape;
+  return _loc.df_result;
+}
+fn _df_viewport(pos:vec2<f32>)->vec2<f32>{
+  _loc.df_pos = pos;
+  _loc.df_result = vec4<f32>(0.0);
+  _loc.df_shape = 100000000000000000000.0;
+  _loc.df_old_shape = _loc.df_shape;
+  _loc.df_blur = 0.00001;
+  _loc.df_aa = (1.0/length(vec2<f32>(length(dpdx(pos)), length(dpdy(pos)))));
+  _loc.df_scale = 1.0;
+  _loc.df_field = 0.0;
+  return _loc.df_pos;
+}
+fn _df_circle(x:f32, y:f32, r:f32){
+  var c:vec2<f32> = _loc.df_pos-vec2<f32>(x, y);
+  _loc.df_field = (length(c.xy)-r)/_loc.df_scale;
+  _loc.df_old_shape = _loc.df_shape;
+  _loc.df_shape = min(_loc.df_shape, _loc.df_field);
+}
+fn _pixel()->vec4<f32>{
+  if(_vary.marker>0.5){
+    _df_viewport(_vary.clipped);
+    var center:vec2<f32> = (_vary.rect.xy+_vary.rect.zw)*0.5;
+    _df_circle(center.x, center.y, 1.0);
+    return _df_fill(_vary.color);
+}else {
+    var s:vec4<f32> = textureSampleLevel(texture, _sampler, _vary.tex_coord.xy, 0.0);
+    var sig_dist:f32 = max(min(s.r, s.g), min(max(s.r, s.g), s.b))-0.5;
+    var scale:f32 = pow((1.0/length(vec2<f32>(length(dpdx(_vary.clipped)), length(dpdy(_vary.clipped)))))*0.002, 0.5);
+    _df_viewport(_vary.tex_coord*_uni_dr.tex_size*0.1);
+    _loc.df_shape = -sig_dist-0.5/_loc.df_aa;
+    return _df_fill(_vary.color*_uni_dr.brightness);
+};
+}
+@vertex
+fn _vertex_shader(_geom_attr: _GeomAttr, _inst_attr: _InstAttr)->_Vary{
+  _geom.geom = vec2<f32>(_geom_attr.geomattr0.x, _geom_attr.geomattr0.y);
+  _inst.font_geom = vec4<f32>(_inst_attr.instattr0.x, _inst_attr.instattr0.y, _inst_attr.instattr0.z, _inst_attr.instattr0.w);
+  _inst.font_tc = vec4<f32>(_inst_attr.instattr1.x, _inst_attr.instattr1.y, _inst_attr.instattr1.z, _inst_attr.instattr1.w);
+  _inst.color = vec4<f32>(_inst_attr.instattr2.x, _inst_attr.instattr2.y, _inst_attr.instattr2.z, _inst_attr.instattr2.w);
+  _inst.x = _inst_attr.instattr3.x;
+  _inst.y = _inst_attr.instattr3.y;
+  _inst.font_size = _inst_attr.instattr3.z;
+  _inst.marker = _inst_attr.instattr3.w;
+  _uni_cx.camera_projection = mat4x4<f32>(_uni_cx_buf[0], _uni_cx_buf[1], _uni_cx_buf[2], _uni_cx_buf[3], _uni_cx_buf[4], _uni_cx_buf[5], _uni_cx_buf[6], _uni_cx_buf[7], _uni_cx_buf[8], _uni_cx_buf[9], _uni_cx_buf[10], _uni_cx_buf[11], _uni_cx_buf[12], _uni_cx_buf[13], _uni_cx_buf[14], _uni_cx_buf[15]);
+  _uni_dl.draw_list_scroll = vec2<f32>(_uni_dl_buf[0], _uni_dl_buf[1]);
+  _uni_dl.draw_list_clip = vec4<f32>(_uni_dl_buf[2], _uni_dl_buf[3], _uni_dl_buf[4], _uni_dl_buf[5]);
+  _uni_dr.tex_size = vec2<f32>(_uni_dr_buf[0], _uni_dr_buf[1]);
+  _uni_dr.brightness = _uni_dr_buf[2];
+  _vary.wgsl_position = _vertex();
+  _vary.marker = _inst.marker;
+  _vary.color = _inst.color;
+  return _vary;
+}
+@fragment
+fn _fragment_shader(_vary_in: _Vary)->@location(0) vec4<f32>{
+  _vary = _vary_in;
+  _uni_cx.camera_projection = mat4x4<f32>(_uni_cx_buf[0], _uni_cx_buf[1], _uni_cx_buf[2], _uni_cx_buf[3], _uni_cx_buf[4], _uni_cx_buf[5], _uni_cx_buf[6], _uni_cx_buf[7], _uni_cx_buf[8], _uni_cx_buf[9], _uni_cx_buf[10], _uni_cx_buf[11], _uni_cx_buf[12], _uni_cx_buf[13], _uni_cx_buf[14], _uni_cx_buf[15]);
+  _uni_dl.draw_list_scroll = vec2<f32>(_uni_dl_buf[0], _uni_dl_buf[1]);
+  _uni_dl.draw_list_clip = vec4<f32>(_uni_dl_buf[2], _uni_dl_buf[3], _uni_dl_buf[4], _uni_dl_buf[5]);
+  _uni_dr.tex_size = vec2<f32>(_uni_dr_buf[0], _uni_dr_buf[1]);
+  _uni_dr.brightness = _uni_dr_buf[2];
+  return _pixel();
+}
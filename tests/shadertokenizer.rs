@@ -0,0 +1,44 @@
+// GLSL and Metal snippets and the token kinds the highlighter should give them.
+// run with: cargo test --test shadertokenizer
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+use common::TokenType as T;
+
+fn check(source:&str, expected:&[(&str, TokenType)]){
+    check_tokens(ShaderTokenizer{..Default::default()}, source, expected);
+}
+
+#[test]
+fn comments(){
+    check("a // line", &[("a", T::Identifier), ("// line", T::Comment)]);
+    check("/* one\ntwo */ b", &[("/* one", T::Comment), ("two */", T::Comment), ("b", T::Identifier)]);
+}
+
+#[test]
+fn preprocessor_and_strings(){
+    check("#version 330", &[("#version", T::Keyword), ("330", T::Number)]);
+    check(r#"#include "lib.metal""#, &[("#include", T::Keyword), (r#""lib.metal""#, T::String)]);
+}
+
+#[test]
+fn numbers(){
+    check("1.0 .5 1e-3 2.0f 0xffu 3u", &[
+        ("1.0", T::Number), (".5", T::Number), ("1e-3", T::Number), ("2.0f", T::Number), ("0xffu", T::Number), ("3u", T::Number)
+    ]);
+    check("p.xy", &[("p", T::Identifier), (".", T::Operator), ("xy", T::Identifier)]);
+}
+
+#[test]
+fn keywords_and_types(){
+    check("uniform vec4 color;", &[("uniform", T::Keyword), ("vec4", T::TypeName), ("color", T::Identifier), (";", T::Delimiter)]);
+    check("float4x4 m = float4x4(1.0);", &[
+        ("float4x4", T::TypeName), ("m", T::Identifier), ("=", T::Operator), ("float4x4", T::TypeName),
+        ("(", T::Paren), ("1.0", T::Number), (")", T::Paren), (";", T::Delimiter)
+    ]);
+    check("if (x) discard;", &[("if", T::Flow), ("(", T::Paren), ("x", T::Identifier), (")", T::Paren), ("discard", T::Flow), (";", T::Delimiter)]);
+    check("vertex Out main0(vec v)", &[
+        ("vertex", T::Keyword), ("Out", T::Identifier), ("main0", T::Call), ("(", T::Paren), ("vec", T::Identifier), ("v", T::Identifier), (")", T::Paren)
+    ]);
+}
@@ -0,0 +1,72 @@
+// RopeLines against FlatLines, on lines long enough to be stored in chunks.
+// run with: cargo test --test textlines
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+fn long_line(len:usize)->String{
+    (0..len).map(|i| (b'a' + (i % 26) as u8) as char).collect()
+}
+
+fn check_same(flat:&TextBuffer<FlatLines>, rope:&TextBuffer<RopeLines>){
+    assert_eq!(flat.get_line_count(), rope.get_line_count());
+    assert_eq!(flat.get_char_count(), rope.get_char_count());
+    for row in 0..flat.get_line_count(){
+        assert_eq!(flat.get_line(row).to_vec(), rope.get_line(row).to_vec(), "row {}", row);
+    }
+}
+
+#[test]
+fn edits_in_a_long_line_match_flat_lines(){
+    let text = format!("short\n{}\nend", long_line(50_000));
+    let mut flat = TextBuffer::<FlatLines>{..Default::default()};
+    let mut rope = TextBuffer::<RopeLines>{..Default::default()};
+    flat.load_buffer(&text.as_bytes().to_vec());
+    rope.load_buffer(&text.as_bytes().to_vec());
+    let mut rng = Lcg(7);
+    for i in 0..2000{
+        let offset = rng.next(flat.get_char_count());
+        let len = rng.next(if i % 3 == 0{9000}else{20}).min(flat.get_char_count() - offset);
+        let rep = match rng.next(4){
+            0=>String::new(),
+            1=>"x".to_string(),
+            2=>"a\nb".to_string(),
+            _=>long_line(rng.next(6000))
+        };
+        let flat_op = flat.replace_with_string(offset, len, &rep);
+        let rope_op = rope.replace_with_string(offset, len, &rep);
+        // the removed text comes back out when undone
+        if i % 10 == 0{
+            flat.replace_with_textop(flat_op);
+            rope.replace_with_textop(rope_op);
+        }
+    }
+    check_same(&flat, &rope);
+    assert_eq!(flat.get_as_string(), rope.get_as_string());
+    for _ in 0..200{
+        let offset = rng.next(flat.get_char_count());
+        assert!(flat.offset_to_text_pos(offset) == rope.offset_to_text_pos(offset));
+    }
+}
+
+#[test]
+fn line_slices_cross_chunks(){
+    let text = long_line(20_000);
+    let chars:Vec<char> = text.chars().collect();
+    let mut rope = TextBuffer::<RopeLines>{..Default::default()};
+    rope.load_buffer(&text.as_bytes().to_vec());
+    let line = rope.get_line(0);
+    assert_eq!(line.len(), chars.len());
+    let mut rng = Lcg(3);
+    for _ in 0..200{
+        let start = rng.next(chars.len());
+        let end = start + rng.next(chars.len() - start);
+        let slice = line.slice(start..end);
+        assert_eq!(slice.to_vec(), &chars[start..end]);
+        assert_eq!(slice.iter().rev().cloned().collect::<Vec<char>>(), chars[start..end].iter().rev().cloned().collect::<Vec<char>>());
+        let col = rng.next(chars.len());
+        assert_eq!(line[col], chars[col]);
+        assert_eq!(line.slice(start..).get(end - start), chars.get(end));
+    }
+}
@@ -0,0 +1,50 @@
+// TOML snippets and the token kinds the highlighter should give them.
+// run with: cargo test --test tomltokenizer
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+use common::TokenType as T;
+
+fn check(source:&str, expected:&[(&str, TokenType)]){
+    check_tokens(TomlTokenizer{..Default::default()}, source, expected);
+}
+
+#[test]
+fn strings(){
+    check(r#"key = "value""#, &[("key", T::Identifier), ("=", T::Operator), (r#""value""#, T::String)]);
+    check(r#""quoted key" = 'no \ escapes'"#, &[(r#""quoted key""#, T::Identifier), ("=", T::Operator), (r#"'no \ escapes'"#, T::String)]);
+    check(r#"a = "say \"hi\"""#, &[("a", T::Identifier), ("=", T::Operator), (r#""say \"hi\"""#, T::String)]);
+    check("s = \"\"\"one\ntwo\"\"\" # done", &[
+        ("s", T::Identifier), ("=", T::Operator), ("\"\"\"one", T::String), ("two\"\"\"", T::String), ("# done", T::Comment)
+    ]);
+    check("s = '''one\n\"\"\"\n'''", &[("s", T::Identifier), ("=", T::Operator), ("'''one", T::String), ("\"\"\"", T::String), ("'''", T::String)]);
+}
+
+#[test]
+fn comments_and_tables(){
+    check("# a comment", &[("# a comment", T::Comment)]);
+    check("key = 1 # trailing", &[("key", T::Identifier), ("=", T::Operator), ("1", T::Number), ("# trailing", T::Comment)]);
+    check("[table.sub] # c", &[("[table.sub]", T::TypeName), ("# c", T::Comment)]);
+    check("[[array]]", &[("[[array]]", T::TypeName)]);
+}
+
+#[test]
+fn numbers(){
+    check("a = 1_000", &[("a", T::Identifier), ("=", T::Operator), ("1_000", T::Number)]);
+    check("b = 3.14", &[("b", T::Identifier), ("=", T::Operator), ("3.14", T::Number)]);
+    check("c = -inf", &[("c", T::Identifier), ("=", T::Operator), ("-inf", T::Number)]);
+    check("d = 1979-05-27T07:32:00Z", &[("d", T::Identifier), ("=", T::Operator), ("1979-05-27T07:32:00Z", T::Number)]);
+}
+
+#[test]
+fn keywords(){
+    check("e = true", &[("e", T::Identifier), ("=", T::Operator), ("true", T::Keyword)]);
+    check("f = [true, false]", &[
+        ("f", T::Identifier), ("=", T::Operator), ("[", T::Paren), ("true", T::Keyword), (",", T::Delimiter), ("false", T::Keyword), ("]", T::Paren)
+    ]);
+    // inline tables start with keys again
+    check("g = {true = 1}", &[
+        ("g", T::Identifier), ("=", T::Operator), ("{", T::Paren), ("true", T::Identifier), ("=", T::Operator), ("1", T::Number), ("}", T::Paren)
+    ]);
+}
@@ -0,0 +1,222 @@
+// Vim emulation: motions, operators with counts and text objects, registers, . repeat, undo and the visual modes.
+// run with: cargo test --test vim
+#![allow(dead_code)]
+
+mod common;
+use common::*;
+
+struct VimTest{
+    vim:Vim,
+    text_buffer:TextBuffer,
+    cursors:CursorSet,
+    syntax:LanguageSyntax
+}
+
+// | marks the cursor, brackets are text here
+fn load(source:&str)->VimTest{
+    let mut cursors = CursorSet::new();
+    cursors.set.truncate(0);
+    let mut text = String::new();
+    let mut offset = 0;
+    for c in source.chars(){
+        if c == '|'{
+            cursors.set.push(Cursor{head:offset, tail:offset, max:0});
+        }
+        else{
+            text.push(c);
+            offset += 1;
+        }
+    }
+    let text_buffer = load_buffer(&text);
+    for cursor in &mut cursors.set{
+        cursor.calc_max(&text_buffer);
+    }
+    VimTest{vim:Vim::new(), text_buffer:text_buffer, cursors:cursors, syntax:LanguageSyntax{..Default::default()}}
+}
+
+impl VimTest{
+    // \x1b is escape, \x08 backspace, \x12 ctrl-r and \x16 ctrl-v
+    fn keys(&mut self, keys:&str)->&mut Self{
+        for ch in keys.chars(){
+            let key = match ch{
+                '\x1b'=>VimKey::Escape,
+                '\x08'=>VimKey::Backspace,
+                '\x12'=>VimKey::Ctrl('r'),
+                '\x16'=>VimKey::Ctrl('v'),
+                ch=>VimKey::Char(ch)
+            };
+            self.vim.handle_key(key, &mut self.cursors, &self.syntax, &mut self.text_buffer);
+        }
+        self
+    }
+
+    fn show(&self)->String{
+        show(&self.text_buffer, &self.cursors)
+    }
+}
+
+fn check(source:&str, keys:&str, result:&str){
+    let mut test = load(source);
+    test.keys(keys);
+    assert_eq!(test.show(), result, "{:?} on {:?}", keys, source);
+}
+
+#[test]
+fn motions(){
+    check("|abc def.ghi", "w", "abc |def.ghi");
+    check("|abc def.ghi", "2w", "abc def|.ghi");
+    check("|abc def.ghi", "W", "abc |def.ghi");
+    check("abc def.gh|i", "b", "abc def.|ghi");
+    check("abc def.gh|i", "B", "abc |def.ghi");
+    check("|abc def", "e", "ab|c def");
+    check("|abc def", "ee", "abc de|f");
+    check("  a|bc", "0", "|  abc");
+    check("  a|bc", "^", "  |abc");
+    check("|abc", "$", "ab|c");
+    check("|a,b,c", "f,", "a|,b,c");
+    check("|a,b,c", "2f,", "a,b|,c");
+    check("|a,b,c", "t,;", "a,|b,c");
+    check("a,b,|c", "F,,", "a,b|,c");
+    check("|a(b[c]d)", "%", "a(b[c]d|)");
+    check("a(b[c]d|)", "%", "a|(b[c]d)");
+    check("a\n  b\nc|", "gg", "|a\n  b\nc");
+    check("|a\n  b\nc", "G", "a\n  b\n|c");
+    check("|a\n  b\nc", "2G", "a\n  |b\nc");
+    // the column is kept going over shorter lines, and normal mode stays on a char
+    check("ab|cd\nx\nabcd", "jj", "abcd\nx\nab|cd");
+    check("ab|cd\nx\nabcd", "j", "abcd\n|x\nabcd");
+    check("|abc", "l$h", "a|bc");
+    // empty lines count as words
+    check("|a\n\nb", "w", "a\n|\nb");
+}
+
+#[test]
+fn operators(){
+    check("|abc def ghi", "dw", "|def ghi");
+    check("|abc def ghi", "d2w", "|ghi");
+    check("|abc def ghi", "2dw", "|ghi");
+    check("a|bc def", "de", "a| def");
+    check("a|bc def", "D", "|a");
+    check("a|bcd", "2x", "a|d");
+    check("ab|c", "X", "a|c");
+    // dw on the last word leaves the line end
+    check("a |bc\nd", "dw", "a| \nd");
+    check("a\nb|\nc\nd", "dd", "a\n|c\nd");
+    check("a\nb|\nc\nd", "2dd", "a\n|d");
+    check("a\nb\nc|", "dd", "a\n|b");
+    check("a\nb|\nc\nd", "dj", "a\n|d");
+    check("a\nb\n|c", "dgg", "|");
+    check("|abc def", "cwx\x1b", "|x def");
+    check("  a|bc\nd", "ccx\x1b", "  |x\nd");
+    check("a(b, |c)", "di(", "a(|)");
+    check("a(b, |c)", "da(", "|a");
+    check("fn a(){\n    |b();\n}", "di{", "fn a(){\n|}");
+    check("x = \"a|b c\";", "ci\"y\x1b", "x = \"|y\";");
+    check("one t|wo three", "daw", "one |three");
+    check("one t|wo three", "diw", "one | three");
+    check("a|\nb", ">>", "    |a\nb");
+    check("    a|\n    b", "<j", "|a\nb");
+    check("a|\n  b\nc", "J", "a| b\nc");
+    check("a|\nb\nc", "3J", "a b| c");
+    check("a|bc", "rx", "a|xc");
+    check("a|bc", "2~", "aB|C");
+    check("a|bc", "ix\x1b", "a|xbc");
+    check("a|bc", "ax\x1b", "ab|xc");
+    check("  a|bc", "Ix\x1b", "  |xabc");
+    check("a|bc", "Ax\x1b", "abc|x");
+    check("a|bc\nd", "ox\x1b", "abc\n|x\nd");
+    check("a|bc\nd", "Ox\x1b", "|x\nabc\nd");
+    check("a|bc", "3ix\x1b", "axx|xbc");
+}
+
+#[test]
+fn registers(){
+    check("a|bc\nd", "yyp", "abc\n|abc\nd");
+    check("a|bc\nd", "yyP", "|abc\nabc\nd");
+    check("a|bc d", "ywP", "abc| bc d");
+    check("a|bc d", "xp", "ac|b d");
+    check("abc\n|d", "yyjp", "abc\nd\n|d");
+
+    let mut test = load("|one two\nthree");
+    test.keys("\"ayiw").keys("w\"bdw").keys("j\"ap").keys("\"bP");
+    assert_eq!(test.show(), "one \nthreeontw|oe");
+    assert_eq!(test.vim.get_register('a').unwrap().text, "one");
+    assert_eq!(test.vim.get_register('b').unwrap().text, "two");
+    // uppercase appends, 0 has the last unnamed yank, _ drops it
+    test.keys("\"Ayl").keys("0yl");
+    assert_eq!(test.vim.get_register('a').unwrap().text, "oneo");
+    assert_eq!(test.vim.get_register('0').unwrap().text, "t");
+    test.keys("\"_dd");
+    assert_eq!(test.vim.get_register('"').unwrap().text, "t");
+    // yanks into + go to the clipboard
+    test.keys("\"+yy");
+    assert_eq!(test.vim._clipboard.take(), Some("one \n".to_string()));
+    assert!(test.vim.get_register('+').unwrap().linewise);
+}
+
+#[test]
+fn repeat(){
+    check("|a b c d", "dw.", "|c d");
+    check("|a b c d", "dw2.", "|d");
+    check("|abcdef", "2x.", "|ef");
+    check("|ab ab ab", "cwxy\x1bw.", "xy x|y ab");
+    check("|a\nb\nc", "Ax;\x1bj.j.", "ax;\nbx;\ncx|;");
+    check("|a\nb", "ox\x1b.", "a\nx\n|x\nb");
+    // motions and yanks don't replace what . repeats
+    check("|abcd", "xlyl.", "b|d");
+}
+
+#[test]
+fn undo(){
+    let mut test = load("|a");
+    test.keys("ifoo\x1b").keys("abar\x1b");
+    assert_eq!(test.show(), "fooba|ra");
+    test.keys("u");
+    assert_eq!(test.show(), "foo|a");
+    test.keys("u");
+    assert_eq!(test.show(), "|a");
+    test.keys("\x12");
+    assert_eq!(test.show(), "foo|a");
+    test.keys("2\x12");
+    assert_eq!(test.show(), "fooba|ra");
+
+    // a change with typing undoes at once
+    let mut test = load("a|bc def");
+    test.keys("cwxy\x08z\x1b");
+    assert_eq!(test.show(), "ax|z def");
+    test.keys("u");
+    assert_eq!(test.show(), "a|bc def");
+    check("|a b c", "dwdw2u", "|a b c");
+}
+
+#[test]
+fn visual(){
+    check("a|bc def", "v", "a[b]c def");
+    check("a|bc def", "vl", "a[bc] def");
+    check("a|bc def", "ved", "a| def");
+    check("abc d|ef", "vbd", "abc |f");
+    check("a|bc def", "vwy", "a|bc def");
+    check("a|bc def", "vecx\x1b", "a|x def");
+    check("a|bc\nd\ne", "Vjd", "|e");
+    check("a|bc\nd\ne", "Vj", "[abc\nd\n]e");
+    check("a|bc\nd\ne", "Vjy", "|abc\nd\ne");
+    check("a(b |c)", "vi(d", "a(|)");
+    check("a|bc", "vlU", "a|BC");
+    check("a|bc", "vlov", "a|bc");
+    check("a|bc", "v\x1b", "a|bc");
+    check("a|\nb", "Vj>", "    |a\n    b");
+    check("a|\nb", "vjJ", "a| b");
+    check("a|b\nc", "ylvp", "a|b\nc");
+    check("a|b\nc", "ylvjp", "a|b");
+}
+
+#[test]
+fn visual_block(){
+    check("a|bcd\nefgh\nij", "\x16jl", "a[bc]d\ne[fg]h\nij");
+    check("a|bcd\nefgh\nij", "\x16jld", "a|d\neh\nij");
+    check("a|bcd\nefgh\nij", "\x16jjd", "a|cd\negh\ni");
+    check("a|bcd\nefgh", "\x16jIx\x1b", "a|xbcd\nexfgh");
+    check("a|bcd\nefgh", "\x16jAx\x1b", "ab|xcd\nefxgh");
+    // a block insert undoes as one step
+    check("a|bcd\nefgh", "\x16jIxy\x1bu", "a|bcd\nefgh");
+}
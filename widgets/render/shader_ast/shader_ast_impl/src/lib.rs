@@ -16,8 +16,8 @@
 // }
 // The subset of Rust syntax we support is directly related to
 // a mapping of GLSL.
-// types have to be simple names like float or vec4
-// we support for loops only with integer ranges
+// types are simple names like float or vec4, a struct name or an array like [float;4]
+// we support for loops only with integer ranges, match only on integers
 // think of the subset as how you would write GLSL with a Rust syntax
 // not as what you can write in Rust that has no direct
 // word for word match in GLSL.
@@ -29,18 +29,98 @@ use proc_macro2::TokenStream;
 use proc_macro2::Span;
 use syn::{
     Expr, Type, Pat, Stmt, PathArguments, GenericArgument, 
-    Item, Local, ItemFn, ItemConst, ItemStruct,
-    Lit, Block, FnArg, BinOp, UnOp, Ident, ReturnType, Member
+    Item, Local, ItemConst, ItemStruct, ItemImpl, ImplItem, FnDecl,
+    Lit, Block, FnArg, BinOp, UnOp, Ident, ReturnType, Member, Fields
 };
 use quote::quote;
 use quote::quote_spanned;
 use syn::spanned::Spanned;
 
+// the type rules, render includes the same file
+mod tyrules;
+use crate::tyrules::*;
+
 fn error(span:Span, msg: &str)->TokenStream{
     let fmsg = format!("shader_ast: {}", msg);
     quote_spanned!(span=>compile_error!(#fmsg))
 }
 
+// the types and storage classes of the shader language, checked here so a typo
+// is reported on the rust source instead of when the shader compiles
+const TYPES:[&str;10] = ["float", "int", "bool", "vec2", "vec3", "vec4", "mat2", "mat3", "mat4", "texture2d"];
+const STORES:[&str;8] = ["Uniform", "UniformDl", "UniformCx", "Instance", "Geometry", "Texture", "Local", "Varying"];
+
+// struct names start with a capital, the type checker finds out if the struct exists
+fn check_type(ident:&Ident)->Result<String, TokenStream>{
+    let ty = ident.to_string();
+    if !TYPES.contains(&ty.as_ref()) && !ty.starts_with(|c:char| c.is_ascii_uppercase()){
+        return Err(error(ident.span(), &format!("Unknown type {}, use one of {} or a struct", ty, TYPES.join(", "))))
+    }
+    Ok(ty)
+}
+
+// the type of a let, fn arg, const or struct field
+fn generate_type(ty:&Type)->Result<String, TokenStream>{
+    match ty{
+        Type::Path(typath)=>{
+            if typath.path.segments.len() != 1{
+                return Err(error(typath.span(), "Only simple typenames such as float or vec4 are supported"));
+            }
+            check_type(&typath.path.segments[0].ident)
+        }
+        Type::Array(tyarr)=>{
+            let elem = generate_type(&tyarr.elem)?;
+            if elem.starts_with('['){
+                return Err(error(tyarr.elem.span(), "Arrays of arrays are not supported"));
+            }
+            let len = match &tyarr.len{
+                Expr::Lit(lit)=>if let Lit::Int(lit) = &lit.lit{lit.value()}else{0},
+                _=>0
+            };
+            if len == 0{
+                return Err(error(tyarr.len.span(), "Array length has to be an integer above 0"));
+            }
+            Ok(format!("[{};{}]", elem, len))
+        }
+        _=>Err(error(ty.span(), "Only simple typenames such as float or vec4 are supported"))
+    }
+}
+
+// a member made of only swizzle letters has to be a valid swizzle
+fn check_swizzle(member:&str)->Result<(), String>{
+    if !member.chars().all(|c| "xyzwrgba".contains(c)){
+        return Ok(())
+    }
+    if !member.chars().all(|c| "xyzw".contains(c)) && !member.chars().all(|c| "rgba".contains(c)){
+        return Err(format!("Swizzle {} mixes xyzw and rgba", member))
+    }
+    if member.len() > 4{
+        return Err(format!("Swizzle {} is longer than 4", member))
+    }
+    Ok(())
+}
+
+// the rust source of a statement for diagnostics, blocks are shown by their own statements
+fn tidy_src(src:String)->String{
+    let mut out = src;
+    for (from, to) in &[(" . ", "."), (" ,", ","), (" ;", ";"), (" (", "("), ("( ", "("), (" )", ")"), (" : ", ":")]{
+        out = out.replace(from, to);
+    }
+    if let Some(pos) = out.find('{'){
+        out.truncate(pos);
+        out.push_str("{...}");
+    }
+    out
+}
+
+// where a statement is, for the type checker in shader.rs
+fn generate_span(span:Span, src:String)->TokenStream{
+    let line = span.start().line as u32;
+    // without span locations we only know the line shader_ast! was invoked on
+    let line = if line == 0{quote!{line!()}}else{quote!{#line}};
+    quote!{ShSpan{file:file!().to_string(), line:#line, src:#src.to_string()}}
+}
+
 // generate the ShVar definitions from a let statement
 fn generate_shvar_defs(stmt:Local)->TokenStream{
     // lets define a local with storage specified
@@ -54,7 +134,10 @@ fn generate_shvar_defs(stmt:Local)->TokenStream{
                     return error(typath.span(), "Only simple typenames such as float or vec4 are supported");
                 }
                 let seg = &typath.path.segments[0];
-                found_type = seg.ident.to_string();
+                found_type = match check_type(&seg.ident){
+                    Ok(ty)=>ty,
+                    Err(err)=>return err
+                };
                 // lets read the path args
                 if let PathArguments::AngleBracketed(angle) = &seg.arguments{
                     if angle.args.len() != 1{
@@ -67,6 +150,9 @@ fn generate_shvar_defs(stmt:Local)->TokenStream{
                                 return error(typath.span(), "Only simple typenames such as float or vec4 are supported");
                             }
                             let seg = &typath.path.segments[0];
+                            if !STORES.contains(&seg.ident.to_string().as_ref()){
+                                return error(seg.ident.span(), &format!("Unknown storage {}, use one of {}", seg.ident, STORES.join(", ")));
+                            }
                             store = seg.ident.clone();
                         }
                         else{
@@ -101,29 +187,23 @@ fn generate_shvar_defs(stmt:Local)->TokenStream{
     }
 }
 
-// generate the ShFn definitions from a rust fn statement
-fn generate_fn_def(item:ItemFn)->TokenStream{
+// generate the ShFn definitions from a rust fn statement, methods of an impl
+// become Type_method with self passed as the first arg _self
+fn generate_fn_def(ident:&Ident, decl:&FnDecl, block:Block, self_ty:Option<&str>)->TokenStream{
     // alright lets do a function
     // and then incrementally add all supported ast nodes
-    let name = item.ident.to_string();
+    let name = if let Some(self_ty) = self_ty{format!("{}_{}", self_ty, ident)}else{ident.to_string()};
        let mut args = Vec::new();
     // lets process the fnargs
-    for arg in &item.decl.inputs{
+    for arg in &decl.inputs{
         if let FnArg::Captured(arg) = arg{
             // lets look at pat and ty
             if let Pat::Ident(pat) = &arg.pat{
                 let name =  pat.ident.to_string();
-                let found_type;
-                if let Type::Path(typath) = &arg.ty{
-                    if typath.path.segments.len() != 1{
-                        return error(typath.span(), "arg type not simple");
-                    }
-                    let seg = &typath.path.segments[0];
-                    found_type = seg.ident.to_string();
-                }
-                else{
-                    return error(arg.span(), "arg type not simple");
-                }
+                let found_type = match generate_type(&arg.ty){
+                    Ok(ty)=>ty,
+                    Err(err)=>return err
+                };
                 args.push(quote!{
                     ShFnArg::new(#name, #found_type)
                 })
@@ -132,28 +212,38 @@ fn generate_fn_def(item:ItemFn)->TokenStream{
                 return error(arg.span(), "arg pattern not simple identifier")
             }
         }
-        else{
-             return error(arg.span(), "arg pattern not simple identifier")
+        else if let (FnArg::SelfRef(arg), Some(self_ty)) = (arg, self_ty){
+            if arg.mutability.is_some(){
+                return error(arg.span(), "Methods can't change self, return the changed struct instead")
+            }
+            args.push(quote!{
+                ShFnArg::new("_self", #self_ty)
+            })
         }
-    }
-    let return_type;
-    if let ReturnType::Type(_, ty) = item.decl.output{
-        if let Type::Path(typath) = *ty{
-            if typath.path.segments.len() != 1{
-                return error(typath.span(), "return type not simple");
+        else if let (FnArg::SelfValue(arg), Some(self_ty)) = (arg, self_ty){
+            if arg.mutability.is_some(){
+                return error(arg.span(), "Methods can't change self, return the changed struct instead")
             }
-            let seg = &typath.path.segments[0];
-            return_type = seg.ident.to_string();
+            args.push(quote!{
+                ShFnArg::new("_self", #self_ty)
+            })
         }
         else{
-            return error(ty.span(), "return type not simple");
+             return error(arg.span(), "arg pattern not simple identifier")
         }
+    }
+    let return_type;
+    if let ReturnType::Type(_, ty) = &decl.output{
+        return_type = match generate_type(ty){
+            Ok(ty)=>ty,
+            Err(err)=>return err
+        };
     }   
     else{
         return_type = "void".to_string();
         //return error(item.span(), "function needs to specify return type")
     }
-    let block = generate_block(*item.block);
+    let block = generate_block(block);
     quote!{
         ShFn{
             name:#name.to_string(),
@@ -171,16 +261,10 @@ fn generate_let(local:Local)->TokenStream{
         let name =  pat.ident.to_string();
         let found_type;
         if let Some((_tok, ty)) = local.ty.clone(){
-            if let Type::Path(typath) = *ty{
-                if typath.path.segments.len() != 1{
-                    return error(typath.span(), "type not simple");
-                }
-                let seg = &typath.path.segments[0];
-                found_type = seg.ident.to_string();
-            }
-            else{
-                return error(local.span(), "type missing or malformed");
-            }
+            found_type = match generate_type(&ty){
+                Ok(ty)=>ty,
+                Err(err)=>return err
+            };
         }
         else{
             found_type = "".to_string();
@@ -209,7 +293,9 @@ fn generate_let(local:Local)->TokenStream{
 // generate a { } block AST 
 fn generate_block(block:Block)->TokenStream{
     let mut stmts = Vec::new();
+    let mut spans = Vec::new();
     for stmt in block.stmts{
+        spans.push(generate_span(stmt.span(), tidy_src(quote!{#stmt}.to_string())));
         match stmt{
             Stmt::Local(stmt)=>{
                 let letstmt = generate_let(stmt);
@@ -236,7 +322,8 @@ fn generate_block(block:Block)->TokenStream{
     }
     return quote!{
         ShBlock{
-            stmts:{let mut v=Vec::new();#(v.push(Box::new(#stmts));)*v}
+            stmts:{let mut v=Vec::new();#(v.push(Box::new(#stmts));)*v},
+            spans:{let mut v=Vec::new();#(v.push(#spans);)*v}
         }
     }
 }
@@ -280,10 +367,12 @@ fn generate_expr(expr:Expr)->TokenStream{
     match expr{
         Expr::Call(expr)=>{
             if let Expr::Path(func) = *expr.func{
-                if func.path.segments.len() != 1{
-                    return error(func.span(), "call identifier not simple");
-                }
-                let seg = &func.path.segments[0].ident.to_string();
+                // Type::fn(..) calls a fn of an impl without self
+                let seg = match func.path.segments.len(){
+                    1=>func.path.segments[0].ident.to_string(),
+                    2=>format!("{}_{}", func.path.segments[0].ident, func.path.segments[1].ident),
+                    _=>return error(func.span(), "call identifier not simple")
+                };
                 // lets get all fn args
                 let mut args = Vec::new();
                 for arg in expr.args{
@@ -427,6 +516,9 @@ fn generate_expr(expr:Expr)->TokenStream{
             let member;
             if let Member::Named(ident) = expr.member{
                 member = ident.to_string();
+                if let Err(msg) = check_swizzle(&member){
+                    return error(ident.span(), &msg)
+                }
             }
             else{
                 return error(expr.span(), "No unnamed members supported")
@@ -444,8 +536,94 @@ fn generate_expr(expr:Expr)->TokenStream{
                 return error(expr.span(), "type not simple");
             }
             let seg = &expr.path.segments[0].ident.to_string();
+            // self is a reserved word in some shader languages
+            let seg = if seg == "self"{"_self"}else{seg};
             return quote!{ShExpr::ShId(ShId{name:#seg.to_string()})}
         }
+        Expr::MethodCall(expr)=>{
+            if expr.turbofish.is_some(){
+                return error(expr.span(), "Generic methods are not supported");
+            }
+            let method = expr.method.to_string();
+            let base = generate_expr(*expr.receiver);
+            let mut args = Vec::new();
+            for arg in expr.args{
+                args.push(generate_expr(arg));
+            }
+            return quote!{ShExpr::ShMethodCall(ShMethodCall{base:Box::new(#base), method:#method.to_string(), args:{let mut v=Vec::new();#(v.push(Box::new(#args));)*v}})}
+        }
+        Expr::Struct(expr)=>{
+            if expr.path.segments.len() != 1{
+                return error(expr.path.span(), "Struct name not simple");
+            }
+            if let Some(rest) = expr.rest{
+                return error(rest.span(), "Struct update syntax is not supported, give every field");
+            }
+            let ty = expr.path.segments[0].ident.to_string();
+            let mut fields = Vec::new();
+            for field in expr.fields{
+                let name = if let Member::Named(ident) = &field.member{ident.to_string()}else{
+                    return error(field.span(), "No unnamed members supported")
+                };
+                let expr = generate_expr(field.expr);
+                fields.push(quote!{ShStructLitField{name:#name.to_string(), expr:Box::new(#expr)}});
+            }
+            return quote!{ShExpr::ShStructLit(ShStructLit{ty:#ty.to_string(), fields:{let mut v=Vec::new();#(v.push(#fields);)*v}})}
+        }
+        Expr::Array(expr)=>{
+            let mut elems = Vec::new();
+            for elem in expr.elems{
+                elems.push(generate_expr(elem));
+            }
+            return quote!{ShExpr::ShArrayLit(ShArrayLit{elems:{let mut v=Vec::new();#(v.push(Box::new(#elems));)*v}})}
+        }
+        Expr::Repeat(expr)=>{
+            // [x;4] is written out as [x,x,x,x]
+            let len = match &*expr.len{
+                Expr::Lit(lit)=>if let Lit::Int(lit) = &lit.lit{lit.value() as usize}else{0},
+                _=>0
+            };
+            if len == 0{
+                return error(expr.len.span(), "Array length has to be an integer above 0");
+            }
+            let elem = generate_expr(*expr.expr);
+            let elems = vec![elem; len];
+            return quote!{ShExpr::ShArrayLit(ShArrayLit{elems:{let mut v=Vec::new();#(v.push(Box::new(#elems));)*v}})}
+        }
+        Expr::Match(expr)=>{
+            let scrutinee = generate_expr(*expr.expr);
+            let mut arms = Vec::new();
+            for arm in expr.arms{
+                if let Some((_, guard)) = arm.guard{
+                    return error(guard.span(), "Match guards are not supported");
+                }
+                let mut pats = Vec::new();
+                for pat in &arm.pats{
+                    match pat{
+                        Pat::Wild(_)=>(),
+                        Pat::Lit(lit)=>match generate_int_pat(&lit.expr){
+                            Some(value)=>pats.push(value),
+                            None=>return error(pat.span(), "Match patterns have to be integers or _")
+                        },
+                        _=>return error(pat.span(), "Match patterns have to be integers or _")
+                    }
+                }
+                if pats.len() != 0 && pats.len() != arm.pats.len(){
+                    return error(arm.pats.span(), "Don't mix _ with other patterns");
+                }
+                // an arm without a block is a block with the expression as value
+                let body = match *arm.body{
+                    Expr::Block(block)=>generate_block(block.block),
+                    body=>{
+                        let span = generate_span(body.span(), tidy_src(quote!{#body}.to_string()));
+                        let body = generate_expr(body);
+                        quote!{ShBlock{stmts:vec![Box::new(ShStmt::ShExpr(#body))], spans:vec![#span]}}
+                    }
+                };
+                arms.push(quote!{ShMatchArm{pats:vec![#(#pats),*], body:#body}});
+            }
+            return quote!{ShExpr::ShMatch(ShMatch{expr:Box::new(#scrutinee), arms:{let mut v=Vec::new();#(v.push(#arms);)*v}})}
+        }
         Expr::Paren(expr)=>{
             let expr = generate_expr(*expr.expr);
             return quote!{ShExpr::ShParen(ShParen{expr:Box::new(#expr)})}
@@ -474,21 +652,22 @@ fn generate_expr(expr:Expr)->TokenStream{
     }
 }
 
+// an integer match pattern like 2 or -1
+fn generate_int_pat(expr:&Expr)->Option<i64>{
+    match expr{
+        Expr::Lit(lit)=>if let Lit::Int(lit) = &lit.lit{Some(lit.value() as i64)}else{None},
+        Expr::Unary(unary)=>if let UnOp::Neg(_) = unary.op{generate_int_pat(&unary.expr).map(|v| -v)}else{None},
+        _=>None
+    }
+}
+
 // generate the ShConst defs
 fn generate_const_def(item:ItemConst)->TokenStream{
     let name = item.ident.to_string();
-    let ty;
-
-    if let Type::Path(typath) = *item.ty{
-        if typath.path.segments.len() != 1{
-            return error(typath.span(), "const type not a basic identifie");
-        }
-        let seg = &typath.path.segments[0];
-        ty = seg.ident.to_string();
-    }
-    else{
-        return error(item.ty.span(), "const type not a basic identifier");
-    }
+    let ty = match generate_type(&item.ty){
+        Ok(ty)=>ty,
+        Err(err)=>return err
+    };
 
     let expr = generate_expr(*item.expr);
     quote!{
@@ -500,13 +679,433 @@ fn generate_const_def(item:ItemConst)->TokenStream{
     }
 }
 
-// generate the ShStruct defs
-fn generate_struct_def(_item:ItemStruct)->TokenStream{
-    TokenStream::new()
+// generate the ShType defs of structs
+fn generate_struct_def(item:ItemStruct)->TokenStream{
+    let name = item.ident.to_string();
+    if let Err(err) = check_type(&item.ident){
+        return err
+    }
+    let mut fields = Vec::new();
+    if let Fields::Named(named) = &item.fields{
+        for field in &named.named{
+            let field_name = if let Some(ident) = &field.ident{ident.to_string()}else{continue};
+            let ty = match generate_type(&field.ty){
+                Ok(ty)=>ty,
+                Err(err)=>return err
+            };
+            fields.push(quote!{ShTypeField::new(#field_name, #ty)});
+        }
+    }
+    else{
+        return error(item.span(), "Structs need named fields");
+    }
+    // structs can't be instance or uniform data so they take no slots
+    quote!{
+        ShType{
+            name:#name.to_string(),
+            slots:0,
+            prim:false,
+            fields:{let mut v=Vec::new();#(v.push(#fields);)*v}
+        }
+    }
+}
+
+// generate the ShFn defs of the methods in an impl
+fn generate_impl_def(item:ItemImpl, fns:&mut Vec<TokenStream>)->Result<(), TokenStream>{
+    if let Some((_, path, _)) = &item.trait_{
+        return Err(error(path.span(), "Trait impls are not supported"));
+    }
+    let self_ty = match generate_type(&item.self_ty){
+        Ok(ty)=>ty,
+        Err(err)=>return Err(err)
+    };
+    for impl_item in item.items{
+        if let ImplItem::Method(method) = impl_item{
+            fns.push(generate_fn_def(&method.sig.ident, &method.sig.decl, method.block, Some(&self_ty)));
+        }
+        else{
+            return Err(error(impl_item.span(), "Only methods are supported in an impl"));
+        }
+    }
+    Ok(())
+}
+
+// a type check of the fns and consts of one shader_ast! block so mistakes are reported
+// on the rust source. it only knows what the block declares itself, whatever comes from
+// other blocks or the builtins is left to Shader::type_check when the shader is compiled
+struct LocalCx{
+    // consts and vars of the block
+    ids:Vec<(String, String)>,
+    structs:Vec<(String, Vec<(String, String)>)>,
+    // fns with their args and return type
+    fns:Vec<(String, Vec<(String, String)>, String)>,
+    scope:Vec<(String, Option<String>)>,
+    in_fn:String,
+    ret:String
+}
+
+// a shared rule as the result of the local check
+fn rule(span:Span, ty:Result<String, String>)->Result<Option<String>, TokenStream>{
+    match ty{
+        Ok(ty)=>Ok(Some(ty)),
+        Err(msg)=>Err(error(span, &msg))
+    }
+}
+
+impl LocalCx{
+    fn find(&self, name:&str)->Option<String>{
+        if let Some((_, ty)) = self.scope.iter().rev().find(|(n, _)| n == name){
+            return ty.clone()
+        }
+        self.ids.iter().find(|(n, _)| n == name).map(|(_, ty)| ty.clone())
+    }
+
+    fn check_fn(&mut self, name:String, decl:&FnDecl, block:&Block, self_ty:Option<&str>)->Result<(), TokenStream>{
+        self.in_fn = name;
+        self.ret = match &decl.output{
+            ReturnType::Type(_, ty)=>generate_type(ty).unwrap_or_default(),
+            ReturnType::Default=>"void".to_string()
+        };
+        self.scope.clear();
+        for arg in &decl.inputs{
+            match arg{
+                FnArg::Captured(arg)=>if let Pat::Ident(pat) = &arg.pat{
+                    self.scope.push((pat.ident.to_string(), generate_type(&arg.ty).ok()));
+                },
+                FnArg::SelfRef(_) | FnArg::SelfValue(_)=>{
+                    self.scope.push(("self".to_string(), self_ty.map(|ty| ty.to_string())));
+                },
+                _=>()
+            }
+        }
+        self.check_block(block)
+    }
+
+    fn check_block(&mut self, block:&Block)->Result<(), TokenStream>{
+        let scope_len = self.scope.len();
+        for stmt in &block.stmts{
+            match stmt{
+                Stmt::Local(local)=>self.check_let(local)?,
+                Stmt::Expr(expr) | Stmt::Semi(expr, _)=>{
+                    self.expr(expr)?;
+                }
+                Stmt::Item(_)=>()
+            }
+        }
+        self.scope.truncate(scope_len);
+        Ok(())
+    }
+
+    fn check_let(&mut self, local:&Local)->Result<(), TokenStream>{
+        let name = if let Pat::Ident(pat) = &local.pats[0]{pat.ident.to_string()}else{return Ok(())};
+        let ty = local.ty.as_ref().and_then(|(_, ty)| generate_type(ty).ok());
+        let mut init_ty = None;
+        if let Some((_, init)) = &local.init{
+            init_ty = self.expr(init)?;
+            if let Some(init_ty) = &init_ty{
+                if init_ty == "void"{
+                    return Err(error(init.span(), &format!("Let {} is initialized with something that has no value", name)))
+                }
+                if let Some(ty) = &ty{
+                    if ty != init_ty{
+                        return Err(error(init.span(), &format!("Let {} is declared {} but initialized with {}", name, ty, init_ty)))
+                    }
+                }
+            }
+        }
+        self.scope.push((name, ty.or(init_ty)));
+        Ok(())
+    }
+
+    fn cond(&mut self, cond:&Expr, what:&str)->Result<(), TokenStream>{
+        if let Some(ty) = self.expr(cond)?{
+            if ty != "bool"{
+                return Err(error(cond.span(), &format!("{} condition has to be a bool, not {}", what, ty)))
+            }
+        }
+        Ok(())
+    }
+
+    // the type of an expression if the block alone tells what it is
+    fn expr(&mut self, expr:&Expr)->Result<Option<String>, TokenStream>{
+        match expr{
+            Expr::Lit(lit)=>Ok(match lit.lit{
+                Lit::Int(_)=>Some("int".to_string()),
+                Lit::Float(_)=>Some("float".to_string()),
+                Lit::Bool(_)=>Some("bool".to_string()),
+                _=>None
+            }),
+            Expr::Path(path)=>{
+                if path.path.segments.len() != 1{
+                    return Ok(None)
+                }
+                Ok(self.find(&path.path.segments[0].ident.to_string()))
+            }
+            Expr::Paren(x)=>self.expr(&x.expr),
+            Expr::Unary(x)=>{
+                let ty = if let Some(ty) = self.expr(&x.expr)?{ty}else{return Ok(None)};
+                let op = &x.op;
+                rule(x.span(), unary_ty(&quote!{#op}.to_string(), &ty))
+            }
+            Expr::Binary(x)=>{
+                let left = self.expr(&x.left)?;
+                let right = self.expr(&x.right)?;
+                if let (Some(left), Some(right)) = (left, right){
+                    let op = &x.op;
+                    return rule(x.span(), binary_ty(&quote!{#op}.to_string(), &left, &right))
+                }
+                Ok(None)
+            }
+            Expr::Assign(x)=>{
+                let left = self.expr(&x.left)?;
+                let right = self.expr(&x.right)?;
+                if let (Some(left), Some(right)) = (&left, &right){
+                    if left != right{
+                        return Err(error(x.span(), &format!("Cannot assign {} to {}", right, left)))
+                    }
+                }
+                Ok(left)
+            }
+            Expr::AssignOp(x)=>{
+                let left = self.expr(&x.left)?;
+                let right = self.expr(&x.right)?;
+                if let (Some(left), Some(right)) = (left, right){
+                    let op = &x.op;
+                    return rule(x.span(), assign_op_ty(&quote!{#op}.to_string(), &left, &right))
+                }
+                Ok(None)
+            }
+            Expr::Field(x)=>{
+                let base = self.expr(&x.base)?;
+                match (base, &x.member){
+                    (Some(base), Member::Named(member))=>self.field(&base, member),
+                    _=>Ok(None)
+                }
+            }
+            Expr::Index(x)=>{
+                let base = self.expr(&x.expr)?;
+                self.expr(&x.index)?;
+                let base = if let Some(base) = base{base}else{return Ok(None)};
+                // arrays give their element, matrices a column and vectors a float
+                if base.starts_with('['){
+                    return Ok(base.trim_start_matches('[').split(';').next().map(|elem| elem.to_string()))
+                }
+                rule(x.span(), index_ty(&base))
+            }
+            Expr::Call(x)=>self.call(x),
+            Expr::MethodCall(x)=>{
+                self.expr(&x.receiver)?;
+                for arg in &x.args{
+                    self.expr(arg)?;
+                }
+                Ok(None)
+            }
+            Expr::Struct(x)=>{
+                for field in &x.fields{
+                    self.expr(&field.expr)?;
+                }
+                if x.path.segments.len() != 1{
+                    return Ok(None)
+                }
+                Ok(Some(x.path.segments[0].ident.to_string()))
+            }
+            Expr::Array(x)=>{
+                for elem in &x.elems{
+                    self.expr(elem)?;
+                }
+                Ok(None)
+            }
+            Expr::Repeat(x)=>{
+                self.expr(&x.expr)?;
+                Ok(None)
+            }
+            Expr::If(x)=>{
+                self.cond(&x.cond, "if")?;
+                self.check_block(&x.then_branch)?;
+                if let Some((_, else_branch)) = &x.else_branch{
+                    self.expr(else_branch)?;
+                }
+                Ok(None)
+            }
+            Expr::While(x)=>{
+                self.cond(&x.cond, "while")?;
+                self.check_block(&x.body)?;
+                Ok(None)
+            }
+            Expr::ForLoop(x)=>{
+                if let Expr::Range(range) = &*x.expr{
+                    for bound in range.from.iter().chain(range.to.iter()){
+                        if let Some(ty) = self.expr(bound)?{
+                            if ty != "int"{
+                                return Err(error(bound.span(), &format!("For loop ranges are ints, not {}", ty)))
+                            }
+                        }
+                    }
+                }
+                let scope_len = self.scope.len();
+                if let Pat::Ident(pat) = &*x.pat{
+                    self.scope.push((pat.ident.to_string(), Some("int".to_string())));
+                }
+                self.check_block(&x.body)?;
+                self.scope.truncate(scope_len);
+                Ok(None)
+            }
+            Expr::Match(x)=>{
+                self.expr(&x.expr)?;
+                for arm in &x.arms{
+                    self.expr(&arm.body)?;
+                }
+                Ok(None)
+            }
+            Expr::Block(x)=>{
+                self.check_block(&x.block)?;
+                Ok(None)
+            }
+            Expr::Return(x)=>{
+                if let Some(ret_expr) = &x.expr{
+                    if let Some(ty) = self.expr(ret_expr)?{
+                        if self.ret == "void"{
+                            return Err(error(ret_expr.span(), &format!("fn {} has no return type but returns {}", self.in_fn, ty)))
+                        }
+                        if self.ret != "" && ty != self.ret{
+                            return Err(error(ret_expr.span(), &format!("fn {} returns {} but this returns {}", self.in_fn, self.ret, ty)))
+                        }
+                    }
+                }
+                Ok(None)
+            }
+            _=>Ok(None)
+        }
+    }
+
+    fn field(&mut self, base:&str, member:&Ident)->Result<Option<String>, TokenStream>{
+        let name = member.to_string();
+        if let Some((_, fields)) = self.structs.iter().find(|(n, _)| n == base){
+            if let Some((_, ty)) = fields.iter().find(|(n, _)| *n == name){
+                return Ok(Some(ty.clone()))
+            }
+            return Err(error(member.span(), &format!("{} has no member {}", base, name)))
+        }
+        // structs of other blocks and the matrix columns are checked when compiled
+        if base.starts_with(|c:char| c.is_ascii_uppercase()) || mat_size(base) != 0{
+            return Ok(None)
+        }
+        rule(member.span(), swizzle_ty(base, &name))
+    }
+
+    fn call(&mut self, x:&syn::ExprCall)->Result<Option<String>, TokenStream>{
+        let mut args = Vec::new();
+        for arg in &x.args{
+            args.push(self.expr(arg)?);
+        }
+        let name = match &*x.func{
+            Expr::Path(func) if func.path.segments.len() == 1=>func.path.segments[0].ident.to_string(),
+            _=>return Ok(None)
+        };
+        if let Some((_, fn_args, ret)) = self.fns.iter().find(|(n, _, _)| *n == name).cloned(){
+            if args.len() != fn_args.len(){
+                return Err(error(x.span(), &format!("fn {} takes {} arguments, got {}", name, fn_args.len(), args.len())))
+            }
+            for (i, (arg, (arg_name, arg_ty))) in args.iter().zip(fn_args.iter()).enumerate(){
+                if let Some(arg) = arg{
+                    if arg_ty != "" && arg != arg_ty{
+                        return Err(error(x.args[i].span(), &format!("Argument {} ({}) of fn {} is {}, got {}", i, arg_name, name, arg_ty, arg)))
+                    }
+                }
+            }
+            return Ok(if ret == ""{None}else{Some(ret)})
+        }
+        if !TYPES.contains(&name.as_ref()) || name == "texture2d"{
+            return Ok(None)
+        }
+        let args = if let Some(args) = args.into_iter().collect::<Option<Vec<String>>>(){args}else{return Ok(Some(name))};
+        rule(x.span(), construct_ty(&name, &args))
+    }
+}
+
+fn local_type_check(block:&Block)->Result<(), TokenStream>{
+    let mut cx = LocalCx{
+        ids:Vec::new(),
+        structs:Vec::new(),
+        fns:Vec::new(),
+        scope:Vec::new(),
+        in_fn:String::new(),
+        ret:String::new()
+    };
+    // first what the block declares, consts come before vars like in Shader::type_check
+    let mut vars = Vec::new();
+    for stmt in &block.stmts{
+        match stmt{
+            Stmt::Local(local)=>if let (Pat::Ident(pat), Some((_, ty))) = (&local.pats[0], &local.ty){
+                if let Type::Path(typath) = &**ty{
+                    if typath.path.segments.len() == 1{
+                        vars.push((pat.ident.to_string(), typath.path.segments[0].ident.to_string()));
+                    }
+                }
+            },
+            Stmt::Item(Item::Const(item))=>if let Ok(ty) = generate_type(&item.ty){
+                cx.ids.push((item.ident.to_string(), ty));
+            },
+            Stmt::Item(Item::Struct(item))=>if let Fields::Named(named) = &item.fields{
+                let fields = named.named.iter().filter_map(|field|{
+                    Some((field.ident.as_ref()?.to_string(), generate_type(&field.ty).ok()?))
+                }).collect();
+                cx.structs.push((item.ident.to_string(), fields));
+            },
+            Stmt::Item(Item::Fn(item))=>{
+                let args = item.decl.inputs.iter().map(|arg|{
+                    if let FnArg::Captured(arg) = arg{
+                        let name = if let Pat::Ident(pat) = &arg.pat{pat.ident.to_string()}else{String::new()};
+                        (name, generate_type(&arg.ty).unwrap_or_default())
+                    }
+                    else{
+                        (String::new(), String::new())
+                    }
+                }).collect();
+                let ret = match &item.decl.output{
+                    ReturnType::Type(_, ty)=>generate_type(ty).unwrap_or_default(),
+                    ReturnType::Default=>"void".to_string()
+                };
+                cx.fns.push((item.ident.to_string(), args, ret));
+            },
+            _=>()
+        }
+    }
+    cx.ids.append(&mut vars);
+
+    for stmt in &block.stmts{
+        match stmt{
+            Stmt::Item(Item::Const(item))=>{
+                cx.scope.clear();
+                if let (Ok(ty), Some(value)) = (generate_type(&item.ty), cx.expr(&item.expr)?){
+                    if ty != value{
+                        return Err(error(item.expr.span(), &format!("Constant {} is declared {} but is {}", item.ident, ty, value)))
+                    }
+                }
+            },
+            Stmt::Item(Item::Fn(item))=>{
+                cx.check_fn(item.ident.to_string(), &item.decl, &item.block, None)?;
+            },
+            Stmt::Item(Item::Impl(item))=>if let Ok(self_ty) = generate_type(&item.self_ty){
+                for impl_item in &item.items{
+                    if let ImplItem::Method(method) = impl_item{
+                        cx.check_fn(format!("{}_{}", self_ty, method.sig.ident), &method.sig.decl, &method.block, Some(&self_ty))?;
+                    }
+                }
+            },
+            _=>()
+        }
+    }
+    Ok(())
 }
 
 // Generate the ShAst rootnode
 fn generate_root(expr:Expr)->TokenStream{
+    if let Expr::Block(expr) = &expr{
+        if let Err(err) = local_type_check(&expr.block){
+            return err
+        }
+    }
     let mut vars = Vec::new();
     let mut fns = Vec::new();
     let mut consts = Vec::new();
@@ -527,7 +1126,12 @@ fn generate_root(expr:Expr)->TokenStream{
                                 consts.push(generate_const_def(item));
                             }
                             Item::Fn(item)=>{
-                                fns.push(generate_fn_def(item));
+                                fns.push(generate_fn_def(&item.ident, &item.decl, *item.block, None));
+                            }
+                            Item::Impl(item)=>{
+                                if let Err(err) = generate_impl_def(item, &mut fns){
+                                    return err
+                                }
                             }
                             _=>{
                                 return error(stmt.span(), "Unexpected statement")
@@ -549,7 +1153,8 @@ fn generate_root(expr:Expr)->TokenStream{
     };
     quote!{ 
         ShAst{
-            types:Vec::new(),//{let mut v=Vec::new();#(v.push(#types);)*v},
+            span:ShSpan{file:file!().to_string(), line:line!(), src:String::new()},
+            types:{let mut v=Vec::new();#(v.push(#structs);)*v},
             vars:{let mut v=Vec::new();#(v.push(#vars);)*v},
             consts:{let mut v=Vec::new();#(v.push(#consts);)*v},
             fns:{let mut v=Vec::new();#(v.push(#fns);)*v}
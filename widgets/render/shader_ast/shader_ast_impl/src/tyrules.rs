@@ -0,0 +1,157 @@
+// The operator and type rules of the shader language. shader_ast! checks a block with
+// them when it expands, and render includes this same file for Shader::type_check, so
+// both report the same thing. types are names like float, vec4 or mat3, operators are
+// written like in rust.
+#![allow(dead_code)]
+
+pub fn is_float_ty(ty:&str)->bool{
+    ty == "float" || ty == "vec2" || ty == "vec3" || ty == "vec4"
+}
+
+pub fn is_bool_ty(ty:&str)->bool{
+    ty == "bool" || ty == "bvec2" || ty == "bvec3" || ty == "bvec4"
+}
+
+pub fn vec_size(ty:&str)->usize{
+    match ty{
+        "float" | "int" | "bool"=>1,
+        "vec2" | "bvec2"=>2,
+        "vec3" | "bvec3"=>3,
+        "vec4" | "bvec4"=>4,
+        _=>0
+    }
+}
+
+pub fn mat_size(ty:&str)->usize{
+    match ty{
+        "mat2"=>2,
+        "mat3"=>3,
+        "mat4"=>4,
+        _=>0
+    }
+}
+
+// left op right
+pub fn binary_ty(op:&str, left:&str, right:&str)->Result<String, String>{
+    match op{
+        "+" | "-" | "*" | "/" | "%"=>{
+            if left == right && (is_float_ty(left) || left == "int" || mat_size(left) != 0){
+                return Ok(left.to_string())
+            }
+            if left == "float" && is_float_ty(right){
+                return Ok(right.to_string())
+            }
+            if right == "float" && is_float_ty(left){
+                return Ok(left.to_string())
+            }
+            if op == "*"{
+                if vec_size(left) > 1 && vec_size(left) == mat_size(right) && is_float_ty(left){
+                    return Ok(left.to_string())
+                }
+                if vec_size(right) > 1 && vec_size(right) == mat_size(left) && is_float_ty(right){
+                    return Ok(right.to_string())
+                }
+                if left == "float" && mat_size(right) != 0{
+                    return Ok(right.to_string())
+                }
+                if right == "float" && mat_size(left) != 0{
+                    return Ok(left.to_string())
+                }
+            }
+            Err(format!("Cannot {} {} and {}", op, left, right))
+        },
+        "==" | "!=" | "<" | "<=" | ">=" | ">"=>{
+            if left != right{
+                return Err(format!("Cannot compare {} with {}", left, right))
+            }
+            let ordered = left == "float" || left == "int";
+            let equatable = ordered || left == "bool";
+            match op{
+                "==" | "!=" if !equatable=>Err(format!("Compare {} with equal or not_equal", left)),
+                "<" | "<=" | ">=" | ">" if !ordered=>Err(format!("Compare {} with less_than and friends", left)),
+                _=>Ok("bool".to_string())
+            }
+        },
+        "&&" | "||"=>{
+            if left != "bool" || right != "bool"{
+                return Err(format!("{} needs bools, not {} and {}", op, left, right))
+            }
+            Ok("bool".to_string())
+        },
+        _=>Err(format!("Operator {} isn't supported by all shader targets", op))
+    }
+}
+
+// left op= right
+pub fn assign_op_ty(op:&str, left:&str, right:&str)->Result<String, String>{
+    if left != right{
+        return Err(format!("{} needs the same type on both sides, got {} and {}", op, left, right))
+    }
+    Ok(left.to_string())
+}
+
+// op is ! or -
+pub fn unary_ty(op:&str, ty:&str)->Result<String, String>{
+    if op == "!" && ty != "bool"{
+        return Err(format!("! needs a bool, not {}", ty))
+    }
+    if op == "-" && !is_float_ty(ty) && ty != "int" && mat_size(ty) == 0{
+        return Err(format!("Cannot negate {}", ty))
+    }
+    Ok(ty.to_string())
+}
+
+// a member of a vector, struct fields are looked up before this
+pub fn swizzle_ty(base:&str, member:&str)->Result<String, String>{
+    let size = vec_size(base);
+    if size < 2 || is_bool_ty(base) || base == "int"{
+        return Err(format!("{} has no member {}", base, member))
+    }
+    let xyzw = member.chars().all(|c| "xyzw".contains(c));
+    let rgba = member.chars().all(|c| "rgba".contains(c));
+    if !xyzw && !rgba || member.len() > 4{
+        return Err(format!("{} is not a swizzle of {}", member, base))
+    }
+    let comps = if xyzw{"xyzw"}else{"rgba"};
+    if member.chars().any(|c| comps.find(c).unwrap() >= size){
+        return Err(format!("Swizzle {} reads past the end of {}", member, base))
+    }
+    Ok(match member.len(){
+        1=>"float",
+        2=>"vec2",
+        3=>"vec3",
+        _=>"vec4"
+    }.to_string())
+}
+
+// base[i] of a matrix or vector, arrays are handled before this
+pub fn index_ty(base:&str)->Result<String, String>{
+    match mat_size(base){
+        2=>return Ok("vec2".to_string()),
+        3=>return Ok("vec3".to_string()),
+        4=>return Ok("vec4".to_string()),
+        _=>()
+    }
+    if !is_float_ty(base) || base == "float"{
+        return Err(format!("Can only index arrays, vec2, vec3, vec4 and matrices, not {}", base))
+    }
+    Ok("float".to_string())
+}
+
+// ty(args..) of a float, int, bool, vec or mat type
+pub fn construct_ty(ty:&str, args:&[String])->Result<String, String>{
+    let mut comps = 0;
+    for arg in args{
+        let size = if mat_size(arg) != 0{mat_size(arg) * mat_size(arg)}else{vec_size(arg)};
+        if size == 0{
+            return Err(format!("Cannot construct {} from {}", ty, arg))
+        }
+        comps += size;
+    }
+    let want = if mat_size(ty) != 0{mat_size(ty) * mat_size(ty)}else{vec_size(ty)};
+    // a single scalar fills every component
+    if comps != want && !(args.len() == 1 && comps == 1){
+        return Err(format!("{} has {} components, got {}", ty, want, comps))
+    }
+    Ok(ty.to_string())
+}
@@ -0,0 +1,94 @@
+use crate::cx::*;
+
+// draws a texture, like the one a texture backed View renders into
+#[derive(Clone)]
+pub struct Blit{
+    pub shader_id:usize,
+    pub do_scroll:bool,
+    pub alpha:f32
+}
+
+impl Style for Blit{
+    fn style(cx:&mut Cx)->Self{
+        let sh = Self::def_blit_shader(cx);
+        Self{
+            shader_id:cx.add_shader(sh, "Blit"),
+            do_scroll:true,
+            alpha:1.0
+        }
+    }
+}
+
+impl Blit{
+    pub fn def_blit_shader(cx:&mut Cx)->Shader{
+        let mut sh = cx.new_shader();
+        sh.geometry_vertices = vec![
+            0.0,0.0,
+            1.0,0.0,
+            1.0,1.0,
+            0.0,1.0
+        ];
+        sh.geometry_indices = vec![
+            0,1,2,
+            2,3,0
+        ];
+
+        sh.add_ast(shader_ast!({
+
+            let geom:vec2<Geometry>;
+            let x:float<Instance>;
+            let y:float<Instance>;
+            let w:float<Instance>;
+            let h:float<Instance>;
+            let alpha:float<Instance>;
+            let pos:vec2<Varying>;
+            let texture:texture2d<Texture>;
+            let draw_list_do_scroll:float<Uniform>;
+
+            fn vertex()->vec4{
+                let shift:vec2 = -draw_list_scroll * draw_list_do_scroll;
+                let clipped:vec2 = clamp(
+                    geom*vec2(w, h) + vec2(x, y) + shift,
+                    draw_list_clip.xy,
+                    draw_list_clip.zw
+                );
+                pos = (clipped - shift - vec2(x,y)) / vec2(w, h);
+                return vec4(clipped,0.,1.) * camera_projection;
+            }
+
+            fn pixel()->vec4{
+                // render targets hold premultiplied colors already
+                return sample2d(texture, pos.xy) * alpha;
+            }
+
+        }));
+        sh
+    }
+
+    pub fn draw_blit(&mut self, cx:&mut Cx, texture_id:usize, rect:Rect)->InstanceArea{
+        // a draw call binds one texture, so every blit gets its own
+        let inst = cx.new_aligned_instance_layer(self.shader_id, 1).inst;
+        inst.push_uniform_float(cx, if self.do_scroll{1.0}else{0.0});
+        inst.push_uniform_texture_2d(cx, texture_id);
+        let pos = cx.turtle_origin();
+        let data = [
+            /*x,y,w,h*/pos.x+rect.x,pos.y+rect.y,rect.w,rect.h,
+            /*alpha*/self.alpha
+        ];
+        inst.push_slice(cx, &data);
+        inst
+    }
+
+    pub fn draw_blit_walk(&mut self, cx:&mut Cx, texture_id:usize, w:Bounds, h:Bounds, margin:Margin)->InstanceArea{
+        let inst = cx.new_aligned_instance_layer(self.shader_id, 1).inst;
+        inst.push_uniform_float(cx, if self.do_scroll{1.0}else{0.0});
+        inst.push_uniform_texture_2d(cx, texture_id);
+        let geom = cx.walk_turtle(w, h, margin, None);
+        let data = [
+            /*x,y,w,h*/geom.x,geom.y,geom.w,geom.h,
+            /*alpha*/self.alpha
+        ];
+        inst.push_slice(cx, &data);
+        inst
+    }
+}
@@ -2,6 +2,11 @@ use std::collections::HashMap;
 use std::collections::BTreeMap;
 
 pub use crate::shadergen::*;
+pub use crate::cx_glsl::*;
+pub use crate::cx_mtlsl::*;
+pub use crate::cx_wgsl::*;
+pub use crate::shadercheck::*;
+pub use crate::shadertypecheck::*;
 pub use crate::cx_fonts::*;
 pub use crate::cx_turtle::*;
 pub use crate::cx_cursor::*;
@@ -14,22 +19,27 @@ pub use crate::elements::*;
 pub use crate::animator::*;
 pub use crate::area::*;
 pub use crate::view::*;
+pub use crate::cx_record::*;
+pub use crate::cx_shaderfile::*;
 
 #[cfg(feature = "ogl")]
 pub use crate::cx_ogl::*; 
 
 #[cfg(feature = "mtl")]
 pub use crate::cx_mtl::*; 
-#[cfg(feature = "mtl")]
-pub use crate::cx_mtlsl::*; 
 
 #[cfg(feature = "webgl")]
 pub use crate::cx_webgl::*; 
 
-#[cfg(any(feature = "webgl", feature = "ogl"))]
-pub use crate::cx_glsl::*; 
+#[cfg(feature = "headless")]
+pub use crate::cx_headless::*; 
+#[cfg(feature = "headless")]
+pub use crate::cx_cpusl::*; 
+#[cfg(feature = "headless")]
+pub use crate::cx_snapshot::*; 
+
 
-#[cfg(any(feature = "ogl", feature="mtl"))]
+#[cfg(any(feature = "ogl", feature="mtl", feature="headless"))]
 pub use crate::cx_desktop::*; 
 
 #[derive(Clone)]
@@ -49,6 +59,8 @@ pub struct Cx{
     pub compiled_shaders: Vec<CompiledShader>,
     pub shaders: Vec<Shader>,
     pub shader_map: HashMap<Shader, usize>,
+    pub shader_files: Vec<ShaderFile>,
+    pub shader_dirs: Vec<String>,
 
     pub redraw_areas:Vec<Area>,
     pub incr_areas:Vec<Area>,
@@ -76,6 +88,9 @@ pub struct Cx{
 
     pub user_events:Vec<Event>,
 
+    pub recording:Option<EventRecording>,
+    pub replay:Option<EventReplay>,
+
     pub playing_anim_areas:Vec<AnimArea>,
     pub ended_anim_areas:Vec<AnimArea>,
 
@@ -116,6 +131,8 @@ impl Default for Cx{
             compiled_shaders:Vec::new(),
             shaders:Vec::new(),
             shader_map:HashMap::new(),
+            shader_files:Vec::new(),
+            shader_dirs:Vec::new(),
 
             redraw_areas:Vec::new(),
             incr_areas:Vec::new(),
@@ -144,6 +161,9 @@ impl Default for Cx{
 
             user_events:Vec::new(),
 
+            recording:None,
+            replay:None,
+
             style_values:BTreeMap::new(),
 
             playing_anim_areas:Vec::new(),
@@ -180,6 +200,7 @@ impl Cx{
         let next_id = self.shaders.len();
         let store_id = self.shader_map.entry(sh.clone()).or_insert(next_id);
         if *store_id == next_id{
+            // the type check runs when the platform assembles it, see Shader::type_check
             self.shaders.push(Shader{
                 name:name.to_string(),
                 ..sh
@@ -253,6 +274,37 @@ impl Cx{
         &mut self.textures_2d[id]
     }
 
+    // the texture backed draw lists below draw_list_id, nested ones come first
+    // so a render target can sample the targets drawn inside it
+    pub fn collect_render_targets(&self, draw_list_id:usize, targets:&mut Vec<usize>){
+        let draw_list = &self.draw_lists[draw_list_id];
+        for draw_call in &draw_list.draw_calls[0..draw_list.draw_calls_len]{
+            if draw_call.sub_list_id != 0{
+                self.collect_render_targets(draw_call.sub_list_id, targets);
+            }
+        }
+        if draw_list_id != 0 && draw_list.texture_id.is_some(){
+            targets.push(draw_list_id);
+        }
+    }
+
+    // points the camera at the rect of a render target, returns its texture and size in pixels
+    // flip_y is for gl, where row 0 of a framebuffer is the bottom one
+    pub fn prepare_render_target(&mut self, draw_list_id:usize, flip_y:bool)->(usize, usize, usize){
+        let draw_list = &self.draw_lists[draw_list_id];
+        let rect = draw_list.rect;
+        let texture_id = draw_list.texture_id.unwrap();
+        let width = ((rect.w * self.target_dpi_factor) as usize).max(1);
+        let height = ((rect.h * self.target_dpi_factor) as usize).max(1);
+        let (top, bottom) = if flip_y{(rect.y + rect.h, rect.y)}else{(rect.y, rect.y + rect.h)};
+        let camera_projection = Mat4::ortho(
+                rect.x, rect.x + rect.w, top, bottom, -100.0, 100.0,
+                1.0,1.0
+        );
+        self.uniform_camera_projection(camera_projection);
+        (texture_id, width, height)
+    }
+
     pub fn prepare_frame(&mut self){
         let camera_projection = Mat4::ortho(
                 0.0, self.target_size.x, 0.0, self.target_size.y, -100.0, 100.0, 
@@ -336,6 +388,11 @@ impl Cx{
         self.key_focus = focus_area;
     }
 
+    // the platform stops running before it sends CloseRequested, call this from the handler to refuse
+    pub fn cancel_close(&mut self){
+        self.running = true;
+    }
+
 
     // event handler wrappers
 
@@ -343,6 +400,9 @@ impl Cx{
     where F: FnMut(&mut Cx, &mut Event)
     { 
         self.event_id += 1;
+        if self.recording.is_some(){
+            self.record_event(event);
+        }
         event_handler(self, event);
 
         if self.last_key_focus != self.key_focus{
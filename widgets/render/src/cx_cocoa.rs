@@ -213,14 +213,10 @@ impl CocoaWindow{
                     let is_repeat:bool = msg_send![ns_event, isARepeat];
                     let is_return = if let KeyCode::Return = key_code{true} else{false};
 
-                    // see if its is paste, ifso TextInput was_paste the text
+                    // see if its is paste, ifso send the clipboard as a Paste
                     let paste_text = if let KeyCode::KeyV = key_code{
                         if modifiers.logo || modifiers.control{
-                            // was a paste
-                            let nsstring:id = msg_send![self.pasteboard.unwrap(), stringForType:NSStringPboardType];
-                            let string = nsstring_to_string(nsstring);
-                            
-                            Some(string)
+                            cocoa_read_clipboard()
                         }
                         else{None}
                     }
@@ -238,10 +234,7 @@ impl CocoaWindow{
                             match &events[0]{
                                 Event::TextCopy(req)=>if let Some(response) = &req.response{
                                     // plug it into the apple clipboard
-                                    let nsstring:id = NSString::alloc(nil).init_str(&response);
-                                    let array: id = msg_send![class!(NSArray), arrayWithObject:NSStringPboardType];
-                                    msg_send![self.pasteboard.unwrap(), declareTypes:array owner:nil];
-                                    msg_send![self.pasteboard.unwrap(), setString:nsstring forType:NSStringPboardType];
+                                    cocoa_copy_to_clipboard(&response);
                                 },
                                 _=>()
                             };
@@ -269,10 +262,8 @@ impl CocoaWindow{
                     }
                     if let Some(paste_text) = paste_text{
                         self.do_callback(&mut vec![
-                            Event::TextInput(TextInputEvent{
-                                input:paste_text,
-                                was_paste:true,
-                                replace_last:false
+                            Event::Paste(PasteEvent{
+                                text:paste_text
                             })
                         ]);
                     }
@@ -1272,6 +1263,27 @@ fn load_webkit_cursor(cursor_name_str: &str) -> id {
     }
 }
 
+pub fn cocoa_copy_to_clipboard(text:&str){
+    unsafe{
+        let pasteboard:id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let nsstring:id = NSString::alloc(nil).init_str(text);
+        let array: id = msg_send![class!(NSArray), arrayWithObject:NSStringPboardType];
+        msg_send![pasteboard, declareTypes:array owner:nil];
+        msg_send![pasteboard, setString:nsstring forType:NSStringPboardType];
+    }
+}
+
+pub fn cocoa_read_clipboard()->Option<String>{
+    unsafe{
+        let pasteboard:id = msg_send![class!(NSPasteboard), generalPasteboard];
+        let nsstring:id = msg_send![pasteboard, stringForType:NSStringPboardType];
+        if nsstring == nil{
+            return None
+        }
+        Some(nsstring_to_string(nsstring))
+    }
+}
+
 unsafe fn nsstring_to_string(string:id)->String{
     let slice = std::slice::from_raw_parts(
         string.UTF8String() as *const std::os::raw::c_uchar,
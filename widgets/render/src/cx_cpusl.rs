@@ -0,0 +1,1207 @@
+// Runs the shader AST on the CPU for the headless renderer, vertex() per geometry vertex
+// and pixel() per covered pixel of the rasterized triangles
+
+use crate::cx::*;
+use std::collections::HashMap;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+// a float that carries its screen space derivatives along,
+// that way dfdx/dfdy work without running pixels in lockstep
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Dual{
+    pub v:f32,
+    pub dx:f32,
+    pub dy:f32
+}
+
+impl Dual{
+    pub fn new(v:f32)->Dual{
+        Dual{v:v, dx:0., dy:0.}
+    }
+
+    // f(self) given the value and the slope of f at self
+    pub fn chain(self, v:f32, slope:f32)->Dual{
+        Dual{v:v, dx:self.dx * slope, dy:self.dy * slope}
+    }
+
+    pub fn min(self, o:Dual)->Dual{
+        if self.v <= o.v{self}else{o}
+    }
+
+    pub fn max(self, o:Dual)->Dual{
+        if self.v >= o.v{self}else{o}
+    }
+
+    pub fn sqrt(self)->Dual{
+        let v = self.v.max(0.).sqrt();
+        self.chain(v, if v > 0.{0.5 / v}else{0.})
+    }
+}
+
+impl Add for Dual{
+    type Output = Dual;
+    fn add(self, o:Dual)->Dual{
+        Dual{v:self.v + o.v, dx:self.dx + o.dx, dy:self.dy + o.dy}
+    }
+}
+
+impl Sub for Dual{
+    type Output = Dual;
+    fn sub(self, o:Dual)->Dual{
+        Dual{v:self.v - o.v, dx:self.dx - o.dx, dy:self.dy - o.dy}
+    }
+}
+
+impl Mul for Dual{
+    type Output = Dual;
+    fn mul(self, o:Dual)->Dual{
+        Dual{v:self.v * o.v, dx:self.dx * o.v + self.v * o.dx, dy:self.dy * o.v + self.v * o.dy}
+    }
+}
+
+impl Div for Dual{
+    type Output = Dual;
+    fn div(self, o:Dual)->Dual{
+        let d = o.v * o.v;
+        Dual{v:self.v / o.v, dx:(self.dx * o.v - self.v * o.dx) / d, dy:(self.dy * o.v - self.v * o.dy) / d}
+    }
+}
+
+impl Neg for Dual{
+    type Output = Dual;
+    fn neg(self)->Dual{
+        Dual{v:-self.v, dx:-self.dx, dy:-self.dy}
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum CpuValue{
+    Void,
+    Bool(bool),
+    Int(i64),
+    Float(Dual),
+    Vec(usize, [Dual;4]),
+    Mat(usize, [f32;16]), // column major, like the uniform buffers
+    Texture(usize), // the texture slot of the draw call
+    Str(String),
+    Struct(String, Vec<(String, CpuValue)>),
+    Array(Vec<CpuValue>)
+}
+
+fn err<T>(msg:String)->Result<T, SlErr>{
+    Err(SlErr{msg:msg})
+}
+
+impl CpuValue{
+    pub fn zero(ty:&str)->CpuValue{
+        let d = Dual::default();
+        match ty{
+            "float"=>CpuValue::Float(d),
+            "int"=>CpuValue::Int(0),
+            "bool"=>CpuValue::Bool(false),
+            "vec2"=>CpuValue::Vec(2, [d;4]),
+            "vec3"=>CpuValue::Vec(3, [d;4]),
+            "vec4"=>CpuValue::Vec(4, [d;4]),
+            "mat2"=>CpuValue::Mat(2, [0.;16]),
+            "mat3"=>CpuValue::Mat(3, [0.;16]),
+            "mat4"=>CpuValue::Mat(4, [0.;16]),
+            "texture2d"=>CpuValue::Texture(0),
+            _=>CpuValue::Void
+        }
+    }
+
+    // reads a ty from packed floats, like the instance and uniform buffers
+    pub fn from_slots(ty:&str, slots:&[f32])->CpuValue{
+        let get = |i:usize| slots.get(i).cloned().unwrap_or(0.);
+        match CpuValue::zero(ty){
+            CpuValue::Float(_)=>CpuValue::Float(Dual::new(get(0))),
+            CpuValue::Int(_)=>CpuValue::Int(get(0) as i64),
+            CpuValue::Bool(_)=>CpuValue::Bool(get(0) != 0.),
+            CpuValue::Vec(n, mut c)=>{
+                for i in 0..n{
+                    c[i] = Dual::new(get(i));
+                }
+                CpuValue::Vec(n, c)
+            },
+            CpuValue::Mat(n, mut m)=>{
+                for i in 0..n * n{
+                    m[i] = get(i);
+                }
+                CpuValue::Mat(n, m)
+            },
+            other=>other
+        }
+    }
+
+    // the value as 1 to 4 float components
+    pub fn comps(&self)->Option<(usize, [Dual;4])>{
+        let mut c = [Dual::default();4];
+        match self{
+            CpuValue::Float(d)=>{
+                c[0] = *d;
+                Some((1, c))
+            },
+            CpuValue::Int(i)=>{
+                c[0] = Dual::new(*i as f32);
+                Some((1, c))
+            },
+            CpuValue::Vec(n, v)=>Some((*n, *v)),
+            _=>None
+        }
+    }
+
+    pub fn from_comps(n:usize, c:[Dual;4])->CpuValue{
+        if n == 1{
+            CpuValue::Float(c[0])
+        }
+        else{
+            CpuValue::Vec(n, c)
+        }
+    }
+
+    pub fn as_bool(&self)->Result<bool, SlErr>{
+        match self{
+            CpuValue::Bool(b)=>Ok(*b),
+            _=>err(format!("Expected a bool, got {:?}", self))
+        }
+    }
+
+    pub fn as_int(&self)->Result<i64, SlErr>{
+        match self{
+            CpuValue::Int(i)=>Ok(*i),
+            CpuValue::Float(d)=>Ok(d.v as i64),
+            _=>err(format!("Expected an int, got {:?}", self))
+        }
+    }
+
+    fn as_comps(&self)->Result<(usize, [Dual;4]), SlErr>{
+        if let Some(comps) = self.comps(){
+            return Ok(comps)
+        }
+        err(format!("Expected a float or vector, got {:?}", self))
+    }
+
+    pub fn map<F>(&self, f:F)->Result<CpuValue, SlErr>
+    where F: Fn(Dual)->Dual
+    {
+        let (n, mut c) = self.as_comps()?;
+        for i in 0..n{
+            c[i] = f(c[i]);
+        }
+        Ok(CpuValue::from_comps(n, c))
+    }
+
+    // componentwise with a scalar on either side spread over the other
+    pub fn zip<F>(&self, o:&CpuValue, f:F)->Result<CpuValue, SlErr>
+    where F: Fn(Dual, Dual)->Dual
+    {
+        let (na, a) = self.as_comps()?;
+        let (nb, b) = o.as_comps()?;
+        if na != nb && na != 1 && nb != 1{
+            return err(format!("Cannot combine a {} and a {} component value", na, nb))
+        }
+        let n = na.max(nb);
+        let mut c = [Dual::default();4];
+        for i in 0..n{
+            c[i] = f(a[if na == 1{0}else{i}], b[if nb == 1{0}else{i}]);
+        }
+        Ok(CpuValue::from_comps(n, c))
+    }
+
+    pub fn dot(&self, o:&CpuValue)->Result<Dual, SlErr>{
+        let (n, a) = self.as_comps()?;
+        let (_, b) = o.as_comps()?;
+        let mut sum = Dual::default();
+        for i in 0..n{
+            sum = sum + a[i] * b[i];
+        }
+        Ok(sum)
+    }
+
+    pub fn length(&self)->Result<Dual, SlErr>{
+        Ok(self.dot(self)?.sqrt())
+    }
+}
+
+fn swizzle_index(c:char)->Option<usize>{
+    match c{
+        'x' | 'r' | 's'=>Some(0),
+        'y' | 'g' | 't'=>Some(1),
+        'z' | 'b' | 'p'=>Some(2),
+        'w' | 'a' | 'q'=>Some(3),
+        _=>None
+    }
+}
+
+fn swizzle_indices(member:&str, n:usize)->Result<Vec<usize>, SlErr>{
+    let mut out = Vec::new();
+    for c in member.chars(){
+        match swizzle_index(c){
+            Some(i) if i < n && out.len() < 4=>out.push(i),
+            _=>return err(format!("Invalid swizzle .{} on a {} component value", member, n))
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Clone)]
+pub struct CpuSlot{
+    pub global:usize,
+    pub offset:usize,
+    pub ty:String
+}
+
+// A shader resolved for the interpreter, every variable it can see becomes a global slot
+#[derive(Clone, Default)]
+pub struct CpuProgram{
+    pub fns:HashMap<String, ShFn>,
+    pub consts:Vec<ShConst>,
+    pub global_ids:HashMap<String, usize>,
+    // the consts evaluated, everything else zero
+    pub init_globals:Vec<CpuValue>,
+    pub geometry_vertices:Vec<f32>,
+    pub geometry_indices:Vec<u32>,
+    pub geometry_slots:usize,
+    pub instance_slots:usize,
+    pub geometries:Vec<CpuSlot>,
+    pub instances:Vec<CpuSlot>,
+    pub uniforms_cx:Vec<CpuSlot>,
+    pub uniforms_dl:Vec<CpuSlot>,
+    pub uniforms_dr:Vec<CpuSlot>,
+    pub texture_slots:Vec<usize>,
+    // what the vertex stage hands to the pixel stage, interpolated over the triangle
+    pub interpolated:Vec<usize>
+}
+
+enum CpuFlow{
+    Next(CpuValue),
+    Return(CpuValue),
+    Break,
+    Continue
+}
+
+pub struct CpuEnv<'a>{
+    pub prog:&'a CpuProgram,
+    pub globals:Vec<CpuValue>,
+    pub textures:&'a [&'a Texture2D],
+    scope:Vec<(&'a str, CpuValue)>,
+    // where the locals of the function we are in start
+    frame:usize
+}
+
+impl<'a> CpuEnv<'a>{
+    pub fn new(prog:&'a CpuProgram, globals:Vec<CpuValue>, textures:&'a [&'a Texture2D])->Self{
+        Self{
+            prog:prog,
+            globals:globals,
+            textures:textures,
+            scope:Vec::new(),
+            frame:0
+        }
+    }
+
+    pub fn call_fn(&mut self, name:&str, args:Vec<CpuValue>)->Result<CpuValue, SlErr>{
+        let prog = self.prog;
+        let shfn = if let Some(shfn) = prog.fns.get(name){shfn}else{
+            return err(format!("Cannot find function {}", name))
+        };
+        if shfn.args.len() != args.len(){
+            return err(format!("{} takes {} arguments, got {}", name, shfn.args.len(), args.len()))
+        }
+        let frame = self.frame;
+        let base = self.scope.len();
+        self.frame = base;
+        for (arg, value) in shfn.args.iter().zip(args){
+            self.scope.push((&arg.name, value));
+        }
+        let flow = self.exec_block(shfn.block.as_ref().unwrap());
+        self.scope.truncate(base);
+        self.frame = frame;
+        match flow?{
+            CpuFlow::Return(value)=>Ok(value),
+            _=>Ok(CpuValue::Void)
+        }
+    }
+
+    fn get_var(&self, name:&str)->Result<CpuValue, SlErr>{
+        if let Some((_, value)) = self.scope[self.frame..].iter().rev().find(|(n, _)| *n == name){
+            return Ok(value.clone())
+        }
+        if let Some(id) = self.prog.global_ids.get(name){
+            return Ok(self.globals[*id].clone())
+        }
+        err(format!("Cannot find variable {}", name))
+    }
+
+    fn set_var(&mut self, name:&str, value:CpuValue)->Result<(), SlErr>{
+        let frame = self.frame;
+        if let Some((_, var)) = self.scope[frame..].iter_mut().rev().find(|(n, _)| *n == name){
+            *var = value;
+            return Ok(())
+        }
+        if let Some(id) = self.prog.global_ids.get(name){
+            self.globals[*id] = value;
+            return Ok(())
+        }
+        err(format!("Cannot find variable {}", name))
+    }
+
+    fn exec_block(&mut self, block:&'a ShBlock)->Result<CpuFlow, SlErr>{
+        let base = self.scope.len();
+        let mut flow = CpuFlow::Next(CpuValue::Void);
+        for stmt in &block.stmts{
+            match &**stmt{
+                ShStmt::ShLet(stmt)=>{
+                    let value = self.value(&stmt.init)?;
+                    self.scope.push((&stmt.name, value));
+                    flow = CpuFlow::Next(CpuValue::Void);
+                },
+                ShStmt::ShExpr(expr) | ShStmt::ShSemi(expr)=>{
+                    let next = self.eval(expr)?;
+                    if let CpuFlow::Next(_) = next{
+                        // a block ending in an expression has its value, for if and match values
+                        flow = if let ShStmt::ShExpr(_) = &**stmt{next}else{CpuFlow::Next(CpuValue::Void)};
+                    }
+                    else{
+                        flow = next;
+                        break;
+                    }
+                }
+            }
+        }
+        self.scope.truncate(base);
+        Ok(flow)
+    }
+
+    // the expressions that can change control flow
+    fn eval(&mut self, expr:&'a ShExpr)->Result<CpuFlow, SlErr>{
+        match expr{
+            ShExpr::ShBlock(block)=>self.exec_block(block),
+            ShExpr::ShIf(x)=>{
+                if self.value(&x.cond)?.as_bool()?{
+                    self.exec_block(&x.then_branch)
+                }
+                else if let Some(else_branch) = &x.else_branch{
+                    self.eval(else_branch)
+                }
+                else{
+                    Ok(CpuFlow::Next(CpuValue::Void))
+                }
+            },
+            ShExpr::ShWhile(x)=>{
+                while self.value(&x.cond)?.as_bool()?{
+                    match self.exec_block(&x.body)?{
+                        CpuFlow::Break=>break,
+                        CpuFlow::Return(value)=>return Ok(CpuFlow::Return(value)),
+                        _=>()
+                    }
+                }
+                Ok(CpuFlow::Next(CpuValue::Void))
+            },
+            ShExpr::ShForLoop(x)=>{
+                let from = self.value(&x.from)?.as_int()?;
+                let to = self.value(&x.to)?.as_int()?;
+                for i in from..to{
+                    let base = self.scope.len();
+                    self.scope.push((&x.iter, CpuValue::Int(i)));
+                    let flow = self.exec_block(&x.body)?;
+                    self.scope.truncate(base);
+                    match flow{
+                        CpuFlow::Break=>break,
+                        CpuFlow::Return(value)=>return Ok(CpuFlow::Return(value)),
+                        _=>()
+                    }
+                }
+                Ok(CpuFlow::Next(CpuValue::Void))
+            },
+            ShExpr::ShMatch(x)=>{
+                let value = self.value(&x.expr)?.as_int()?;
+                // the first arm with the value, or the _ arm before it
+                for arm in &x.arms{
+                    if arm.pats.len() == 0 || arm.pats.contains(&value){
+                        return self.exec_block(&arm.body)
+                    }
+                }
+                Ok(CpuFlow::Next(CpuValue::Void))
+            },
+            ShExpr::ShReturn(x)=>{
+                let value = if let Some(expr) = &x.expr{self.value(expr)?}else{CpuValue::Void};
+                Ok(CpuFlow::Return(value))
+            },
+            ShExpr::ShBreak(_)=>Ok(CpuFlow::Break),
+            ShExpr::ShContinue(_)=>Ok(CpuFlow::Continue),
+            _=>Ok(CpuFlow::Next(self.value(expr)?))
+        }
+    }
+
+    fn value(&mut self, expr:&'a ShExpr)->Result<CpuValue, SlErr>{
+        match expr{
+            ShExpr::ShId(x)=>self.get_var(&x.name),
+            ShExpr::ShLit(x)=>Ok(match x{
+                ShLit::Int(v)=>CpuValue::Int(*v),
+                ShLit::Float(v)=>CpuValue::Float(Dual::new(*v as f32)),
+                ShLit::Str(v)=>CpuValue::Str(v.clone()),
+                ShLit::Bool(v)=>CpuValue::Bool(*v)
+            }),
+            ShExpr::ShField(x)=>{
+                let base = self.value(&x.base)?;
+                if let CpuValue::Struct(ty, fields) = base{
+                    if let Some((_, value)) = fields.into_iter().find(|(name, _)| *name == x.member){
+                        return Ok(value)
+                    }
+                    return err(format!("{} has no field {}", ty, x.member))
+                }
+                let (n, c) = base.as_comps()?;
+                let mut out = [Dual::default();4];
+                let indices = swizzle_indices(&x.member, n)?;
+                for (i, index) in indices.iter().enumerate(){
+                    out[i] = c[*index];
+                }
+                Ok(CpuValue::from_comps(indices.len(), out))
+            },
+            ShExpr::ShIndex(x)=>{
+                let base = self.value(&x.base)?;
+                let index = self.value(&x.index)?.as_int()? as usize;
+                match base{
+                    CpuValue::Array(mut elems) if index < elems.len()=>Ok(elems.swap_remove(index)),
+                    CpuValue::Vec(n, c) if index < n=>Ok(CpuValue::Float(c[index])),
+                    CpuValue::Mat(n, m) if index < n=>{
+                        let mut c = [Dual::default();4];
+                        for i in 0..n{
+                            c[i] = Dual::new(m[index * n + i]);
+                        }
+                        Ok(CpuValue::Vec(n, c))
+                    },
+                    _=>err(format!("Cannot index {:?} with {}", base, index))
+                }
+            },
+            ShExpr::ShAssign(x)=>{
+                let value = self.value(&x.right)?;
+                self.assign(&x.left, value.clone())?;
+                Ok(value)
+            },
+            ShExpr::ShAssignOp(x)=>{
+                let left = self.value(&x.left)?;
+                let right = self.value(&x.right)?;
+                let op = match x.op{
+                    ShBinOp::AddEq=>ShBinOp::Add,
+                    ShBinOp::SubEq=>ShBinOp::Sub,
+                    ShBinOp::MulEq=>ShBinOp::Mul,
+                    ShBinOp::DivEq=>ShBinOp::Div,
+                    ShBinOp::RemEq=>ShBinOp::Rem,
+                    ShBinOp::BitXorEq=>ShBinOp::BitXor,
+                    ShBinOp::BitAndEq=>ShBinOp::BitAnd,
+                    ShBinOp::BitOrEq=>ShBinOp::BitOr,
+                    ShBinOp::ShlEq=>ShBinOp::Shl,
+                    ShBinOp::ShrEq=>ShBinOp::Shr,
+                    _=>return err(format!("{} is not an assignment operator", x.op.to_string()))
+                };
+                let value = Self::binary(&op, &left, &right)?;
+                self.assign(&x.left, value.clone())?;
+                Ok(value)
+            },
+            ShExpr::ShBinary(x)=>{
+                match x.op{ // these don't evaluate the right side when they don't have to
+                    ShBinOp::And=>Ok(CpuValue::Bool(self.value(&x.left)?.as_bool()? && self.value(&x.right)?.as_bool()?)),
+                    ShBinOp::Or=>Ok(CpuValue::Bool(self.value(&x.left)?.as_bool()? || self.value(&x.right)?.as_bool()?)),
+                    _=>{
+                        let left = self.value(&x.left)?;
+                        let right = self.value(&x.right)?;
+                        Self::binary(&x.op, &left, &right)
+                    }
+                }
+            },
+            ShExpr::ShUnary(x)=>{
+                let value = self.value(&x.expr)?;
+                match x.op{
+                    ShUnaryOp::Not=>Ok(CpuValue::Bool(!value.as_bool()?)),
+                    ShUnaryOp::Neg=>match value{
+                        CpuValue::Int(i)=>Ok(CpuValue::Int(-i)),
+                        CpuValue::Mat(n, mut m)=>{
+                            for v in m.iter_mut(){
+                                *v = -*v;
+                            }
+                            Ok(CpuValue::Mat(n, m))
+                        },
+                        _=>value.map(|d| -d)
+                    }
+                }
+            },
+            ShExpr::ShParen(x)=>self.value(&x.expr),
+            ShExpr::ShCall(x)=>{
+                let mut args = Vec::new();
+                for arg in &x.args{
+                    args.push(self.value(arg)?);
+                }
+                if self.prog.fns.contains_key(&x.call){
+                    self.call_fn(&x.call, args)
+                }
+                else{
+                    self.call_builtin(&x.call, &args)
+                }
+            },
+            ShExpr::ShMethodCall(x)=>{
+                let mut args = vec![self.value(&x.base)?];
+                for arg in &x.args{
+                    args.push(self.value(arg)?);
+                }
+                if let CpuValue::Struct(ty, _) = &args[0]{
+                    let method_fn = format!("{}_{}", ty, x.method);
+                    if self.prog.fns.contains_key(&method_fn){
+                        return self.call_fn(&method_fn, args)
+                    }
+                }
+                self.call_builtin(&x.method, &args)
+            },
+            ShExpr::ShStructLit(x)=>{
+                let mut fields = Vec::new();
+                for field in &x.fields{
+                    fields.push((field.name.clone(), self.value(&field.expr)?));
+                }
+                Ok(CpuValue::Struct(x.ty.clone(), fields))
+            },
+            ShExpr::ShArrayLit(x)=>{
+                let mut elems = Vec::new();
+                for elem in &x.elems{
+                    elems.push(self.value(elem)?);
+                }
+                Ok(CpuValue::Array(elems))
+            },
+            _=>match self.eval(expr)?{
+                CpuFlow::Next(value)=>Ok(value),
+                _=>err("return, break and continue cannot be used as a value".to_string())
+            }
+        }
+    }
+
+    fn assign(&mut self, left:&'a ShExpr, value:CpuValue)->Result<(), SlErr>{
+        match left{
+            ShExpr::ShId(x)=>self.set_var(&x.name, value),
+            ShExpr::ShParen(x)=>self.assign(&x.expr, value),
+            ShExpr::ShField(x)=>{ // writes through a swizzle, like pos.xy = ...
+                let base = self.value(&x.base)?;
+                if let CpuValue::Struct(ty, mut fields) = base{
+                    if let Some(field) = fields.iter_mut().find(|(name, _)| *name == x.member){
+                        field.1 = value;
+                        return self.assign(&x.base, CpuValue::Struct(ty, fields))
+                    }
+                    return err(format!("{} has no field {}", ty, x.member))
+                }
+                let (n, mut c) = base.as_comps()?;
+                let (vn, vc) = value.as_comps()?;
+                let indices = swizzle_indices(&x.member, n)?;
+                if vn != 1 && vn != indices.len(){
+                    return err(format!("Cannot assign a {} component value to .{}", vn, x.member))
+                }
+                for (i, index) in indices.iter().enumerate(){
+                    c[*index] = vc[if vn == 1{0}else{i}];
+                }
+                self.assign(&x.base, CpuValue::from_comps(n, c))
+            },
+            ShExpr::ShIndex(x)=>{
+                let base = self.value(&x.base)?;
+                let index = self.value(&x.index)?.as_int()? as usize;
+                if let CpuValue::Array(mut elems) = base{
+                    if index >= elems.len(){
+                        return err(format!("Index {} is past the end of an array of {}", index, elems.len()))
+                    }
+                    elems[index] = value;
+                    return self.assign(&x.base, CpuValue::Array(elems))
+                }
+                let (vn, vc) = value.as_comps()?;
+                match base{
+                    CpuValue::Vec(n, mut c) if index < n && vn == 1=>{
+                        c[index] = vc[0];
+                        self.assign(&x.base, CpuValue::Vec(n, c))
+                    },
+                    CpuValue::Mat(n, mut m) if index < n && vn == n=>{
+                        for i in 0..n{
+                            m[index * n + i] = vc[i].v;
+                        }
+                        self.assign(&x.base, CpuValue::Mat(n, m))
+                    },
+                    _=>err(format!("Cannot assign to index {} of {:?}", index, base))
+                }
+            },
+            _=>err("Can only assign to variables, fields and indices".to_string())
+        }
+    }
+
+    fn binary(op:&ShBinOp, left:&CpuValue, right:&CpuValue)->Result<CpuValue, SlErr>{
+        if let (CpuValue::Int(a), CpuValue::Int(b)) = (left, right){
+            let (a, b) = (*a, *b);
+            return Ok(match op{
+                ShBinOp::Add=>CpuValue::Int(a.wrapping_add(b)),
+                ShBinOp::Sub=>CpuValue::Int(a.wrapping_sub(b)),
+                ShBinOp::Mul=>CpuValue::Int(a.wrapping_mul(b)),
+                ShBinOp::Div=>CpuValue::Int(if b == 0{0}else{a / b}),
+                ShBinOp::Rem=>CpuValue::Int(if b == 0{0}else{a % b}),
+                ShBinOp::BitXor=>CpuValue::Int(a ^ b),
+                ShBinOp::BitAnd=>CpuValue::Int(a & b),
+                ShBinOp::BitOr=>CpuValue::Int(a | b),
+                ShBinOp::Shl=>CpuValue::Int(a << (b & 63)),
+                ShBinOp::Shr=>CpuValue::Int(a >> (b & 63)),
+                ShBinOp::Eq=>CpuValue::Bool(a == b),
+                ShBinOp::Ne=>CpuValue::Bool(a != b),
+                ShBinOp::Lt=>CpuValue::Bool(a < b),
+                ShBinOp::Le=>CpuValue::Bool(a <= b),
+                ShBinOp::Gt=>CpuValue::Bool(a > b),
+                ShBinOp::Ge=>CpuValue::Bool(a >= b),
+                _=>return err(format!("Cannot use {} on ints", op.to_string()))
+            })
+        }
+        // matrices add up and scale componentwise
+        match (op, left, right){
+            (ShBinOp::Add, CpuValue::Mat(n, a), CpuValue::Mat(bn, b)) |
+            (ShBinOp::Sub, CpuValue::Mat(n, a), CpuValue::Mat(bn, b)) if n == bn=>{
+                let mut m = *a;
+                for i in 0..n * n{
+                    m[i] = if let ShBinOp::Add = op{a[i] + b[i]}else{a[i] - b[i]};
+                }
+                return Ok(CpuValue::Mat(*n, m))
+            },
+            (ShBinOp::Mul, CpuValue::Mat(n, a), CpuValue::Float(f)) |
+            (ShBinOp::Mul, CpuValue::Float(f), CpuValue::Mat(n, a))=>{
+                let mut m = *a;
+                for i in 0..n * n{
+                    m[i] = a[i] * f.v;
+                }
+                return Ok(CpuValue::Mat(*n, m))
+            },
+            _=>()
+        }
+        match op{
+            ShBinOp::Add=>left.zip(right, |a, b| a + b),
+            ShBinOp::Sub=>left.zip(right, |a, b| a - b),
+            ShBinOp::Div=>left.zip(right, |a, b| a / b),
+            ShBinOp::Rem=>left.zip(right, |a, b| Self::fmod(a, b)),
+            ShBinOp::Mul=>match (left, right){
+                (CpuValue::Vec(n, v), CpuValue::Mat(mn, m)) if n == mn=>{ // row vector times matrix
+                    let mut c = [Dual::default();4];
+                    for i in 0..*n{
+                        for j in 0..*n{
+                            c[i] = c[i] + v[j] * Dual::new(m[i * n + j]);
+                        }
+                    }
+                    Ok(CpuValue::Vec(*n, c))
+                },
+                (CpuValue::Mat(mn, m), CpuValue::Vec(n, v)) if n == mn=>{
+                    let mut c = [Dual::default();4];
+                    for i in 0..*n{
+                        for j in 0..*n{
+                            c[i] = c[i] + v[j] * Dual::new(m[j * n + i]);
+                        }
+                    }
+                    Ok(CpuValue::Vec(*n, c))
+                },
+                (CpuValue::Mat(n, a), CpuValue::Mat(bn, b)) if n == bn=>{
+                    let mut m = [0.;16];
+                    for col in 0..*n{
+                        for row in 0..*n{
+                            for k in 0..*n{
+                                m[col * n + row] += a[k * n + row] * b[col * n + k];
+                            }
+                        }
+                    }
+                    Ok(CpuValue::Mat(*n, m))
+                },
+                _=>left.zip(right, |a, b| a * b)
+            },
+            ShBinOp::Eq | ShBinOp::Ne=>{
+                let equal = match (left, right){
+                    (CpuValue::Bool(a), CpuValue::Bool(b))=>a == b,
+                    _=>{
+                        let (na, a) = left.as_comps()?;
+                        let (nb, b) = right.as_comps()?;
+                        na == nb && (0..na).all(|i| a[i].v == b[i].v)
+                    }
+                };
+                Ok(CpuValue::Bool(if let ShBinOp::Eq = op{equal}else{!equal}))
+            },
+            ShBinOp::Lt | ShBinOp::Le | ShBinOp::Gt | ShBinOp::Ge=>{
+                let (na, a) = left.as_comps()?;
+                let (nb, b) = right.as_comps()?;
+                if na != 1 || nb != 1{
+                    return err(format!("Cannot compare vectors with {}", op.to_string()))
+                }
+                let (a, b) = (a[0].v, b[0].v);
+                Ok(CpuValue::Bool(match op{
+                    ShBinOp::Lt=>a < b,
+                    ShBinOp::Le=>a <= b,
+                    ShBinOp::Gt=>a > b,
+                    _=>a >= b
+                }))
+            },
+            ShBinOp::And=>Ok(CpuValue::Bool(left.as_bool()? && right.as_bool()?)),
+            ShBinOp::Or=>Ok(CpuValue::Bool(left.as_bool()? || right.as_bool()?)),
+            _=>err(format!("Cannot use {} on {:?} and {:?}", op.to_string(), left, right))
+        }
+    }
+
+    fn fmod(a:Dual, b:Dual)->Dual{
+        a - b * Dual::new((a.v / b.v).floor())
+    }
+
+    fn construct_vec(n:usize, args:&[CpuValue])->Result<CpuValue, SlErr>{
+        let mut flat = Vec::new();
+        for arg in args{
+            let (an, c) = arg.as_comps()?;
+            flat.extend_from_slice(&c[0..an]);
+        }
+        let mut c = [Dual::default();4];
+        if flat.len() == 1{
+            for i in 0..n{
+                c[i] = flat[0];
+            }
+        }
+        else if flat.len() >= n{
+            c[0..n].copy_from_slice(&flat[0..n]);
+        }
+        else{
+            return err(format!("vec{} needs {} components, got {}", n, n, flat.len()))
+        }
+        Ok(CpuValue::Vec(n, c))
+    }
+
+    fn construct_mat(n:usize, args:&[CpuValue])->Result<CpuValue, SlErr>{
+        let mut flat = Vec::new();
+        for arg in args{
+            match arg{
+                CpuValue::Mat(_, m)=>flat.extend_from_slice(&m[0..n * n]),
+                _=>{
+                    let (an, c) = arg.as_comps()?;
+                    flat.extend(c[0..an].iter().map(|d| d.v));
+                }
+            }
+        }
+        let mut m = [0.;16];
+        if flat.len() == 1{ // a diagonal matrix
+            for i in 0..n{
+                m[i * n + i] = flat[0];
+            }
+        }
+        else if flat.len() >= n * n{
+            m[0..n * n].copy_from_slice(&flat[0..n * n]);
+        }
+        else{
+            return err(format!("mat{} needs {} components, got {}", n, n * n, flat.len()))
+        }
+        Ok(CpuValue::Mat(n, m))
+    }
+
+    // bilinear and clamped to the edges, textures are packed as 0xAARRGGBB
+    fn sample2d(&self, slot:usize, coord:&CpuValue)->Result<CpuValue, SlErr>{
+        let (n, uv) = coord.as_comps()?;
+        if n < 2{
+            return err("sample2d needs a vec2 coordinate".to_string())
+        }
+        let mut out = [Dual::default();4];
+        let tex = if let Some(tex) = self.textures.get(slot){tex}else{
+            return Ok(CpuValue::Vec(4, out))
+        };
+        if tex.width == 0 || tex.height == 0 || tex.image.len() < tex.width * tex.height{
+            return Ok(CpuValue::Vec(4, out))
+        }
+        let fetch = |x:f32, y:f32|->[f32;4]{
+            let x = (x.max(0.) as usize).min(tex.width - 1);
+            let y = (y.max(0.) as usize).min(tex.height - 1);
+            let p = tex.image[y * tex.width + x];
+            [
+                ((p >> 16) & 0xff) as f32 / 255.,
+                ((p >> 8) & 0xff) as f32 / 255.,
+                (p & 0xff) as f32 / 255.,
+                ((p >> 24) & 0xff) as f32 / 255.
+            ]
+        };
+        let fx = uv[0].v * tex.width as f32 - 0.5;
+        let fy = uv[1].v * tex.height as f32 - 0.5;
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+        let (a, b, c, d) = (fetch(x0, y0), fetch(x0 + 1., y0), fetch(x0, y0 + 1.), fetch(x0 + 1., y0 + 1.));
+        for i in 0..4{
+            let top = a[i] + (b[i] - a[i]) * tx;
+            let bottom = c[i] + (d[i] - c[i]) * tx;
+            out[i] = Dual::new(top + (bottom - top) * ty);
+        }
+        Ok(CpuValue::Vec(4, out))
+    }
+
+    fn call_builtin(&self, name:&str, args:&[CpuValue])->Result<CpuValue, SlErr>{
+        let arg = |i:usize|->Result<&CpuValue, SlErr>{
+            if let Some(arg) = args.get(i){
+                return Ok(arg)
+            }
+            err(format!("{} needs at least {} arguments", name, i + 1))
+        };
+        match name{
+            "float"=>Ok(CpuValue::Float(arg(0)?.as_comps()?.1[0])),
+            "int"=>Ok(CpuValue::Int(arg(0)?.as_int()?)),
+            "bool"=>match arg(0)?{
+                CpuValue::Bool(b)=>Ok(CpuValue::Bool(*b)),
+                other=>Ok(CpuValue::Bool(other.as_comps()?.1[0].v != 0.))
+            },
+            "vec2"=>Self::construct_vec(2, args),
+            "vec3"=>Self::construct_vec(3, args),
+            "vec4"=>Self::construct_vec(4, args),
+            "mat2"=>Self::construct_mat(2, args),
+            "mat3"=>Self::construct_mat(3, args),
+            "mat4"=>Self::construct_mat(4, args),
+            "color"=>match arg(0)?{
+                CpuValue::Str(name)=>{
+                    let col = color(name);
+                    let mut c = [Dual::default();4];
+                    c[0] = Dual::new(col.r);
+                    c[1] = Dual::new(col.g);
+                    c[2] = Dual::new(col.b);
+                    c[3] = Dual::new(col.a);
+                    Ok(CpuValue::Vec(4, c))
+                },
+                other=>err(format!("color needs a string, got {:?}", other))
+            },
+            "radians"=>arg(0)?.map(|x| x * Dual::new(std::f32::consts::PI / 180.)),
+            "degrees"=>arg(0)?.map(|x| x * Dual::new(180. / std::f32::consts::PI)),
+            "sin"=>arg(0)?.map(|x| x.chain(x.v.sin(), x.v.cos())),
+            "cos"=>arg(0)?.map(|x| x.chain(x.v.cos(), -x.v.sin())),
+            "tan"=>arg(0)?.map(|x| x.chain(x.v.tan(), 1. / (x.v.cos() * x.v.cos()))),
+            "asin"=>arg(0)?.map(|x| x.chain(x.v.asin(), 1. / (1. - x.v * x.v).sqrt())),
+            "acos"=>arg(0)?.map(|x| x.chain(x.v.acos(), -1. / (1. - x.v * x.v).sqrt())),
+            "atan"=>if args.len() == 2{
+                arg(0)?.zip(arg(1)?, |y, x|{
+                    let r2 = x.v * x.v + y.v * y.v;
+                    Dual{
+                        v:y.v.atan2(x.v),
+                        dx:(x.v * y.dx - y.v * x.dx) / r2,
+                        dy:(x.v * y.dy - y.v * x.dy) / r2
+                    }
+                })
+            }
+            else{
+                arg(0)?.map(|x| x.chain(x.v.atan(), 1. / (1. + x.v * x.v)))
+            },
+            "pow"=>arg(0)?.zip(arg(1)?, |x, y|{
+                let v = x.v.powf(y.v);
+                let ln = if x.v > 0.{x.v.ln()}else{0.};
+                let slope = if x.v != 0.{y.v * x.v.powf(y.v - 1.)}else{0.};
+                Dual{
+                    v:v,
+                    dx:slope * x.dx + v * ln * y.dx,
+                    dy:slope * x.dy + v * ln * y.dy
+                }
+            }),
+            "exp"=>arg(0)?.map(|x| x.chain(x.v.exp(), x.v.exp())),
+            "exp2"=>arg(0)?.map(|x| x.chain(x.v.exp2(), x.v.exp2() * std::f32::consts::LN_2)),
+            "log"=>arg(0)?.map(|x| x.chain(x.v.ln(), 1. / x.v)),
+            "log2"=>arg(0)?.map(|x| x.chain(x.v.log2(), 1. / (x.v * std::f32::consts::LN_2))),
+            "sqrt"=>arg(0)?.map(|x| x.sqrt()),
+            "inversesqrt"=>arg(0)?.map(|x| Dual::new(1.) / x.sqrt()),
+            "abs"=>arg(0)?.map(|x| if x.v < 0.{-x}else{x}),
+            "sign"=>arg(0)?.map(|x| Dual::new(if x.v > 0.{1.}else if x.v < 0.{-1.}else{0.})),
+            "floor"=>arg(0)?.map(|x| Dual::new(x.v.floor())),
+            "ceil"=>arg(0)?.map(|x| Dual::new(x.v.ceil())),
+            "fract"=>arg(0)?.map(|x| x - Dual::new(x.v.floor())),
+            "fmod"=>arg(0)?.zip(arg(1)?, Self::fmod),
+            "min"=>arg(0)?.zip(arg(1)?, Dual::min),
+            "max"=>arg(0)?.zip(arg(1)?, Dual::max),
+            "clamp"=>arg(0)?.zip(arg(1)?, Dual::max)?.zip(arg(2)?, Dual::min),
+            "mix"=>{
+                let (a, b) = (arg(0)?, arg(1)?);
+                a.zip(&b.zip(a, |b, a| b - a)?.zip(arg(2)?, |d, t| d * t)?, |a, d| a + d)
+            },
+            "step"=>arg(0)?.zip(arg(1)?, |e, x| Dual::new(if x.v < e.v{0.}else{1.})),
+            "smoothstep"=>{
+                let (e0, e1, x) = (arg(0)?, arg(1)?, arg(2)?);
+                let t = x.zip(e0, |x, e0| x - e0)?.zip(&e1.zip(e0, |e1, e0| e1 - e0)?, |a, b| a / b)?;
+                t.map(|t|{
+                    let t = t.max(Dual::new(0.)).min(Dual::new(1.));
+                    t * t * (Dual::new(3.) - t * Dual::new(2.))
+                })
+            },
+            "length"=>Ok(CpuValue::Float(arg(0)?.length()?)),
+            "distance"=>Ok(CpuValue::Float(arg(0)?.zip(arg(1)?, |a, b| a - b)?.length()?)),
+            "dot"=>Ok(CpuValue::Float(arg(0)?.dot(arg(1)?)?)),
+            "normalize"=>{
+                let x = arg(0)?;
+                x.zip(&CpuValue::Float(x.length()?), |a, l| a / l)
+            },
+            "cross"=>{
+                let (na, a) = arg(0)?.as_comps()?;
+                let (nb, b) = arg(1)?.as_comps()?;
+                if na != 3 || nb != 3{
+                    return err("cross needs two vec3s".to_string())
+                }
+                let mut c = [Dual::default();4];
+                c[0] = a[1] * b[2] - a[2] * b[1];
+                c[1] = a[2] * b[0] - a[0] * b[2];
+                c[2] = a[0] * b[1] - a[1] * b[0];
+                Ok(CpuValue::Vec(3, c))
+            },
+            "reflect"=>{
+                let (i, n) = (arg(0)?, arg(1)?);
+                let d = n.dot(i)? * Dual::new(2.);
+                i.zip(&n.map(|n| n * d)?, |i, n| i - n)
+            },
+            "faceforward"=>{
+                let n = arg(0)?;
+                if arg(2)?.dot(arg(1)?)?.v < 0.{Ok(n.clone())}else{n.map(|n| -n)}
+            },
+            "dfdx"=>arg(0)?.map(|x| Dual::new(x.dx)),
+            "dfdy"=>arg(0)?.map(|x| Dual::new(x.dy)),
+            "fwidth"=>arg(0)?.map(|x| Dual::new(x.dx.abs() + x.dy.abs())),
+            "sample2d"=>match arg(0)?{
+                CpuValue::Texture(slot)=>self.sample2d(*slot, arg(1)?),
+                other=>err(format!("sample2d needs a texture, got {:?}", other))
+            },
+            _=>err(format!("{} is not supported by the headless renderer", name))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CpuVertex{
+    pub x:f32,
+    pub y:f32,
+    pub outputs:Vec<CpuValue>
+}
+
+// the RGBA framebuffer the headless renderer draws into
+#[derive(Clone, Default)]
+pub struct CpuFrame{
+    pub width:usize,
+    pub height:usize,
+    pub pixels:Vec<Color>
+}
+
+impl CpuFrame{
+    pub fn clear(&mut self, width:usize, height:usize, color:Color){
+        self.width = width;
+        self.height = height;
+        self.pixels.truncate(0);
+        self.pixels.resize(width * height, color);
+    }
+
+    // premultiplied alpha, blends like the gpu backends do with one, one minus source alpha
+    pub fn blend(&mut self, x:usize, y:usize, src:&CpuValue)->Result<(), SlErr>{
+        let (n, c) = src.as_comps()?;
+        if n != 4{
+            return err(format!("pixel() has to return a vec4, got {:?}", src))
+        }
+        let c:Vec<f32> = c.iter().map(|d| d.v.max(0.).min(1.)).collect();
+        let dst = &mut self.pixels[y * self.width + x];
+        let inv = 1. - c[3];
+        dst.r = c[0] + dst.r * inv;
+        dst.g = c[1] + dst.g * inv;
+        dst.b = c[2] + dst.b * inv;
+        dst.a = c[3] + dst.a * inv;
+        Ok(())
+    }
+}
+
+fn edge(a:&CpuVertex, b:&CpuVertex, x:f32, y:f32)->f32{
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+impl CpuProgram{
+    pub fn compile(sh:&Shader)->Result<CpuProgram, SlErr>{
+        let mut prog = CpuProgram{
+            geometry_vertices:sh.geometry_vertices.clone(),
+            geometry_indices:sh.geometry_indices.clone(),
+            ..Default::default()
+        };
+        // later asts override earlier ones, like Shader::find_fn does
+        for ast in &sh.asts{
+            for shfn in &ast.fns{
+                if shfn.block.is_some(){
+                    prog.fns.insert(shfn.name.clone(), shfn.clone());
+                }
+            }
+            for shconst in &ast.consts{
+                prog.add_global(&shconst.name, &shconst.ty);
+                prog.consts.push(shconst.clone());
+            }
+        }
+        for name in &["vertex", "pixel"]{
+            if !prog.fns.contains_key(*name){
+                return err(format!("Shader {} has no {} function", sh.name, name))
+            }
+        }
+
+        prog.uniforms_cx = prog.add_slots(sh, ShVarStore::UniformCx);
+        prog.uniforms_dl = prog.add_slots(sh, ShVarStore::UniformDl);
+        prog.uniforms_dr = prog.add_slots(sh, ShVarStore::Uniform);
+        for var in sh.flat_vars(ShVarStore::Texture){
+            let id = prog.add_global(&var.name, &var.ty);
+            prog.texture_slots.push(id);
+        }
+        prog.geometries = prog.add_slots(sh, ShVarStore::Geometry);
+        prog.instances = prog.add_slots(sh, ShVarStore::Instance);
+        prog.geometry_slots = sh.compute_slot_total(&sh.flat_vars(ShVarStore::Geometry));
+        prog.instance_slots = sh.compute_slot_total(&sh.flat_vars(ShVarStore::Instance));
+
+        prog.interpolated = prog.geometries.iter().map(|slot| slot.global).collect();
+        for var in sh.flat_vars(ShVarStore::Varying){
+            let id = prog.add_global(&var.name, &var.ty);
+            if !prog.interpolated.contains(&id){
+                prog.interpolated.push(id);
+            }
+        }
+        for var in sh.flat_vars(ShVarStore::Local){
+            prog.add_global(&var.name, &var.ty);
+        }
+
+        let globals = {
+            let mut env = CpuEnv::new(&prog, prog.init_globals.clone(), &[]);
+            for shconst in &prog.consts{
+                let value = env.value(&shconst.value)?;
+                env.set_var(&shconst.name, value)?;
+            }
+            env.globals
+        };
+        prog.init_globals = globals;
+        Ok(prog)
+    }
+
+    fn add_global(&mut self, name:&str, ty:&str)->usize{
+        if let Some(id) = self.global_ids.get(name){
+            return *id
+        }
+        let id = self.init_globals.len();
+        self.global_ids.insert(name.to_string(), id);
+        self.init_globals.push(CpuValue::zero(ty));
+        id
+    }
+
+    fn add_slots(&mut self, sh:&Shader, store:ShVarStore)->Vec<CpuSlot>{
+        let mut slots = Vec::new();
+        let mut offset = 0;
+        for var in sh.flat_vars(store){
+            slots.push(CpuSlot{
+                global:self.add_global(&var.name, &var.ty),
+                offset:offset,
+                ty:var.ty.clone()
+            });
+            offset += sh.get_type_slots(&var.ty);
+        }
+        slots
+    }
+
+    fn write_slots(globals:&mut [CpuValue], slots:&[CpuSlot], data:&[f32]){
+        for slot in slots{
+            let data = if slot.offset < data.len(){&data[slot.offset..]}else{&[]};
+            globals[slot.global] = CpuValue::from_slots(&slot.ty, data);
+        }
+    }
+
+    // runs the vertex shader over the geometry of every instance and
+    // blends the pixel shader output of the covered pixels into frame
+    pub fn draw(&self, frame:&mut CpuFrame, instances:&[f32], uniforms_cx:&[f32], uniforms_dl:&[f32], uniforms_dr:&[f32], textures:&[&Texture2D])->Result<(), SlErr>{
+        if self.instance_slots == 0 || self.geometry_slots == 0{
+            return Ok(())
+        }
+        let mut globals = self.init_globals.clone();
+        Self::write_slots(&mut globals, &self.uniforms_cx, uniforms_cx);
+        Self::write_slots(&mut globals, &self.uniforms_dl, uniforms_dl);
+        Self::write_slots(&mut globals, &self.uniforms_dr, uniforms_dr);
+        for (slot, id) in self.texture_slots.iter().enumerate(){
+            globals[*id] = CpuValue::Texture(slot);
+        }
+
+        let mut vertices = Vec::new();
+        for instance in instances.chunks_exact(self.instance_slots){
+            Self::write_slots(&mut globals, &self.instances, instance);
+            vertices.truncate(0);
+            for geometry in self.geometry_vertices.chunks_exact(self.geometry_slots){
+                let mut env = CpuEnv::new(self, globals.clone(), textures);
+                Self::write_slots(&mut env.globals, &self.geometries, geometry);
+                let pos = env.call_fn("vertex", Vec::new())?;
+                let (n, c) = pos.as_comps()?;
+                if n != 4{
+                    return err(format!("vertex() has to return a vec4, got {:?}", pos))
+                }
+                let w = if c[3].v != 0.{c[3].v}else{1.};
+                vertices.push(CpuVertex{
+                    x:(c[0].v / w * 0.5 + 0.5) * frame.width as f32,
+                    y:(0.5 - c[1].v / w * 0.5) * frame.height as f32,
+                    outputs:self.interpolated.iter().map(|id| env.globals[*id].clone()).collect()
+                });
+            }
+            for tri in self.geometry_indices.chunks_exact(3){
+                if let (Some(a), Some(b), Some(c)) = (vertices.get(tri[0] as usize), vertices.get(tri[1] as usize), vertices.get(tri[2] as usize)){
+                    self.draw_triangle(frame, &globals, textures, [a, b, c])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_triangle(&self, frame:&mut CpuFrame, globals:&[CpuValue], textures:&[&Texture2D], v:[&CpuVertex;3])->Result<(), SlErr>{
+        let area = edge(v[0], v[1], v[2].x, v[2].y);
+        if area == 0. || !area.is_finite(){
+            return Ok(())
+        }
+        let sign = area.signum();
+        // the edge opposite each vertex, its barycentric weight is the edge function over the area
+        let edges = [(v[1], v[2]), (v[2], v[0]), (v[0], v[1])];
+        let mut ddx = [0.;3];
+        let mut ddy = [0.;3];
+        let mut top_left = [false;3];
+        for (i, (a, b)) in edges.iter().enumerate(){
+            ddx[i] = -(b.y - a.y) / area;
+            ddy[i] = (b.x - a.x) / area;
+            // pixel centers exactly on an edge belong to one of the two triangles sharing it
+            let (dx, dy) = ((b.x - a.x) * sign, (b.y - a.y) * sign);
+            top_left[i] = dy < 0. || dy == 0. && dx > 0.;
+        }
+
+        let min_x = v.iter().fold(std::f32::MAX, |m, v| m.min(v.x)).floor().max(0.) as usize;
+        let min_y = v.iter().fold(std::f32::MAX, |m, v| m.min(v.y)).floor().max(0.) as usize;
+        let max_x = (v.iter().fold(std::f32::MIN, |m, v| m.max(v.x)).ceil().max(0.) as usize).min(frame.width);
+        let max_y = (v.iter().fold(std::f32::MIN, |m, v| m.max(v.y)).ceil().max(0.) as usize).min(frame.height);
+
+        for y in min_y..max_y{
+            for x in min_x..max_x{
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let mut weights = [0.;3];
+                let mut inside = true;
+                for (i, (a, b)) in edges.iter().enumerate(){
+                    let e = edge(a, b, px, py) * sign;
+                    if e < 0. || e == 0. && !top_left[i]{
+                        inside = false;
+                        break;
+                    }
+                    weights[i] = edge(a, b, px, py) / area;
+                }
+                if !inside{
+                    continue;
+                }
+                let mut env = CpuEnv::new(self, globals.to_vec(), textures);
+                for (k, id) in self.interpolated.iter().enumerate(){
+                    env.globals[*id] = Self::interpolate([&v[0].outputs[k], &v[1].outputs[k], &v[2].outputs[k]], &weights, &ddx, &ddy);
+                }
+                let color = env.call_fn("pixel", Vec::new())?;
+                frame.blend(x, y, &color)?;
+            }
+        }
+        Ok(())
+    }
+
+    // floats and vectors are interpolated with their screen space derivatives, the rest is flat
+    fn interpolate(values:[&CpuValue;3], weights:&[f32;3], ddx:&[f32;3], ddy:&[f32;3])->CpuValue{
+        match values[0]{
+            CpuValue::Float(_) | CpuValue::Vec(_, _)=>(),
+            other=>return other.clone()
+        }
+        let mut out = [Dual::default();4];
+        let mut n = 0;
+        for (k, value) in values.iter().enumerate(){
+            if let Some((vn, c)) = value.comps(){
+                n = vn;
+                for i in 0..vn{
+                    out[i].v += weights[k] * c[i].v;
+                    out[i].dx += ddx[k] * c[i].v;
+                    out[i].dy += ddy[k] * c[i].v;
+                }
+            }
+        }
+        CpuValue::from_comps(n, out)
+    }
+}
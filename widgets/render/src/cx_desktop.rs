@@ -9,23 +9,40 @@ pub struct FileReadRequest{
     path:String
 }
 
+#[derive(Clone)]
+pub struct FileWriteRequest{
+    id:u64,
+    path:String,
+    data:Vec<u8>
+}
+
 #[derive(Clone)]
 pub struct CxDesktop{
     pub file_read_id:u64,
     pub file_read_requests:Vec<FileReadRequest>,
+    pub file_write_id:u64,
+    pub file_write_requests:Vec<FileWriteRequest>,
+    pub paste_requested:bool
 }
 
 impl Default for CxDesktop{
     fn default()->CxDesktop{
         CxDesktop{
             file_read_id:1,
-            file_read_requests:Vec::new()
+            file_read_requests:Vec::new(),
+            file_write_id:1,
+            file_write_requests:Vec::new(),
+            paste_requested:false
         }
     }
 }
 
 impl Cx{
 
+    pub fn is_mac_platform(&self)->bool{
+        cfg!(target_os = "macos")
+    }
+
     pub fn read_file(&mut self, path:&str)->u64{
         let desktop = &mut self.platform.desktop;
         let id = desktop.file_read_id;
@@ -78,6 +95,77 @@ impl Cx{
         }
     }
 
+    pub fn write_file(&mut self, path:&str, data:&[u8])->u64{
+        let desktop = &mut self.platform.desktop;
+        let id = desktop.file_write_id;
+        desktop.file_write_id += 1;
+        desktop.file_write_requests.push(FileWriteRequest{
+            id:id,
+            path:path.to_string(),
+            data:data.to_vec()
+        });
+        id
+    }
+
+    pub fn process_desktop_file_write_requests<F>(&mut self, mut event_handler:F)
+    where F: FnMut(&mut Cx, &mut Event)
+    {
+        if self.platform.desktop.file_write_requests.len() == 0{
+            return
+        }
+
+        let file_write_requests = self.platform.desktop.file_write_requests.clone();
+        self.platform.desktop.file_write_requests.truncate(0);
+
+        for write_req in file_write_requests{
+            let file_result = File::create(&write_req.path);
+            if let Ok(mut file) = file_result{
+                // write the whole file
+                if file.write_all(&write_req.data).is_ok(){
+                    event_handler(self, &mut Event::FileWrite(FileWriteEvent{
+                        id:write_req.id,
+                        error:None
+                    }))
+                }
+                else{
+                    event_handler(self, &mut Event::FileWrite(FileWriteEvent{
+                        id:write_req.id,
+                        error:Some(format!("Failed to write {}", write_req.path))
+                    }))
+                }
+            }
+            else{
+                event_handler(self, &mut Event::FileWrite(FileWriteEvent{
+                    id:write_req.id,
+                    error:Some(format!("Failed to create {}", write_req.path))
+                }))
+            }
+        }
+
+        if self.platform.desktop.file_write_requests.len() != 0{
+            self.process_desktop_file_write_requests(event_handler);
+        }
+    }
+
+    // the clipboard text comes back as an Event::Paste, the web can only read it async
+    pub fn request_paste(&mut self){
+        self.platform.desktop.paste_requested = true;
+    }
+
+    pub fn process_desktop_paste_requests<F>(&mut self, mut event_handler:F)
+    where F: FnMut(&mut Cx, &mut Event)
+    {
+        if !self.platform.desktop.paste_requested{
+            return
+        }
+        self.platform.desktop.paste_requested = false;
+        if let Some(text) = self.read_clipboard(){
+            self.call_input_event_handler(&mut event_handler, &mut Event::Paste(PasteEvent{
+                text:text
+            }));
+        }
+    }
+
     pub fn process_to_wasm<F>(&mut self, _msg:u32, mut _event_handler:F)->u32{
         0
     }
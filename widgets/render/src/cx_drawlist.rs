@@ -79,6 +79,18 @@ impl Cx{
         }
     }
 
+    // like new_aligned_instance, but never appends to an earlier draw call of the shader
+    pub fn new_aligned_instance_layer(&mut self, shader_id:usize, instance_count:usize)->AlignedInstance{
+        let instance_area = self.new_instance_layer(shader_id, instance_count);
+
+        let align_index = self.align_list.len();
+        self.align_list.push(Area::Instance(instance_area.clone()));
+        AlignedInstance{
+            inst:instance_area,
+            index:align_index
+        }
+    }
+
     pub fn update_aligned_instance_count(&mut self,align:&AlignedInstance){
         if let Area::Instance(instance) = &mut self.align_list[align.index]{
             instance.instance_count = align.inst.instance_count;
@@ -137,7 +149,8 @@ pub struct DrawList{
     pub uniforms:Vec<f32>, // cmdlist uniforms
     pub platform:DrawListPlatform,
     pub rect:Rect,
-    pub clipped:bool
+    pub clipped:bool,
+    pub texture_id:Option<usize> // render target, drawn into this texture instead of its parent
 }
 
 impl DrawList{
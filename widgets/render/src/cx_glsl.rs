@@ -14,7 +14,8 @@ pub struct AssembledGLShader{
     pub fragment:String,
     pub vertex:String,
     pub named_instance_props: NamedInstanceProps,
-    pub rect_instance_props: RectInstanceProps
+    pub rect_instance_props: RectInstanceProps,
+    pub instance_packing: InstancePacking
 }
 
 pub enum GLShaderType{
@@ -115,19 +116,19 @@ impl Cx{
                 0=>{
                     out.push_str(base);
                     out.push_str(&id.to_string());
-                    out.push_str(".xy;\r\n");
+                    out.push_str(".xy;\n");
                     return out
                 }
                 1=>{
                     out.push_str(base);
                     out.push_str(&id.to_string());
-                    out.push_str(".yz;\r\n");
+                    out.push_str(".yz;\n");
                     return out
                 }
                 2=>{
                     out.push_str(base);
                     out.push_str(&id.to_string());
-                    out.push_str(".zw;\r\n");
+                    out.push_str(".zw;\n");
                     return out
                 }
                 _=>()            
@@ -138,13 +139,13 @@ impl Cx{
                 0=>{
                     out.push_str(base);
                     out.push_str(&id.to_string());
-                    out.push_str(".xyz;\r\n");
+                    out.push_str(".xyz;\n");
                     return out
                 }
                 1=>{
                     out.push_str(base);
                     out.push_str(&id.to_string());
-                    out.push_str(".yzw;\r\n");
+                    out.push_str(".yzw;\n");
                     return out
                 }            
                 _=>()            
@@ -154,7 +155,7 @@ impl Cx{
             if slot&3 == 0{
                 out.push_str(base);
                 out.push_str(&id.to_string());
-                out.push_str(".xyzw;\r\n");
+                out.push_str(".xyzw;\n");
                 return out
             }
         }          
@@ -287,13 +288,17 @@ impl Cx{
                     _=>".w"
                 }
             );
-            out.push_str(";\r\n");
+            out.push_str(";\n");
         }
         out
     }
 
     pub fn gl_assemble_shader(sh:&Shader, shtype:GLShaderType)->Result<AssembledGLShader, SlErr>{
-        
+        sh.type_check()?;
+        // widgets write the instances of the whole shader, the optimized one can read fewer
+        let all_instances = sh.flat_vars(ShVarStore::Instance);
+        let sh = &sh.optimize();
+
         let mut vtx_out = String::new();
         let mut pix_out = String::new();
         let mut pix_compat = String::new();
@@ -337,6 +342,7 @@ impl Cx{
 
         let mut const_cx = SlCx{
             depth:0,
+            lang:SlLang::Glsl,
             target:SlTarget::Constant,
             defargs_fn:"".to_string(),
             defargs_call:"".to_string(),
@@ -347,6 +353,7 @@ impl Cx{
             fn_done:Vec::new(),
             auto_vary:Vec::new()
         };
+        let structs_out = assemble_structs(&const_cx);
         let consts = sh.flat_consts();
         let mut consts_out = String::new();
         for cnst in &consts{
@@ -366,6 +373,7 @@ impl Cx{
 
         let mut vtx_cx = SlCx{
             depth:0,
+            lang:SlLang::Glsl,
             target:SlTarget::Vertex,
             defargs_fn:"".to_string(),
             defargs_call:"".to_string(),
@@ -380,6 +388,7 @@ impl Cx{
 
         let mut pix_cx = SlCx{
             depth:0,
+            lang:SlLang::Glsl,
             target:SlTarget::Pixel,
             defargs_fn:"".to_string(),
             defargs_call:"".to_string(),
@@ -401,6 +410,10 @@ impl Cx{
         let instance_slots = sh.compute_slot_total(&instances);
         let varying_slots = sh.compute_slot_total(&varyings);
         let mut shared = String::new();
+        if structs_out.len() != 0{
+            shared.push_str("// Structs\n");
+            shared.push_str(&structs_out);
+        }
         shared.push_str("// Consts\n");
         shared.push_str(&consts_out);
         shared.push_str("//Context uniforms\n");
@@ -466,8 +479,8 @@ impl Cx{
         }
 
         pix_main.push_str("\n    gl_FragColor = pixel();\n");
-        vtx_main.push_str("\n}\n\0");
-        pix_main.push_str("\n}\n\0");
+        vtx_main.push_str("\n}\n");
+        pix_main.push_str("\n}\n");
 
         vtx_out.push_str("//Vertex shader\n");
         vtx_out.push_str(&vtx_fns);
@@ -496,15 +509,40 @@ impl Cx{
             texture_slots:texture_slots,
             fragment:pix_out,
             vertex:vtx_out,
-            named_instance_props:NamedInstanceProps::construct(sh, &instances),
-            rect_instance_props:RectInstanceProps::construct(sh, &instances)
+            named_instance_props:NamedInstanceProps::construct(sh, &all_instances),
+            rect_instance_props:RectInstanceProps::construct(sh, &all_instances),
+            instance_packing:InstancePacking::construct(sh, &all_instances, &instances)
         })
     }
-}
 
+    // checks an assembled vertex or fragment shader against GLSL ES 1.0, see shadercheck
+    pub fn gl_check_shader(source:&str)->Result<(), SlErr>{
+        check_shader_source(source, &SlCheckLang{
+            types:&[
+                "void", "bool", "int", "float", "vec2", "vec3", "vec4", "bvec2", "bvec3", "bvec4",
+                "ivec2", "ivec3", "ivec4", "mat2", "mat3", "mat4", "sampler2D", "samplerCube"
+            ],
+            builtins:&[
+                "radians", "degrees", "sin", "cos", "tan", "asin", "acos", "atan", "pow", "exp", "log",
+                "exp2", "log2", "sqrt", "inversesqrt", "abs", "sign", "floor", "ceil", "fract", "mod",
+                "min", "max", "clamp", "mix", "step", "smoothstep", "length", "distance", "dot", "cross",
+                "normalize", "faceforward", "reflect", "refract", "matrixCompMult", "lessThan",
+                "lessThanEqual", "greaterThan", "greaterThanEqual", "equal", "notEqual", "any", "all",
+                "not", "texture2D", "texture2DProj", "texture2DLod", "texture2DProjLod", "textureCube",
+                "textureCubeLod", "dFdx", "dFdy", "fwidth"
+            ],
+            keywords:&[
+                "attribute", "const", "uniform", "varying", "break", "continue", "do", "for", "while",
+                "if", "else", "in", "out", "inout", "true", "false", "discard", "return", "struct",
+                "precision", "highp", "mediump", "lowp", "invariant"
+            ],
+            colon_types:false
+        })
+    }
+}
 
 impl<'a> SlCx<'a>{
-    pub fn map_call(&self, name:&str, args:&Vec<Sl>)->MapCallResult{
+    pub fn gl_map_call(&self, name:&str, args:&Vec<Sl>)->MapCallResult{
         match name{
             "matrix_comp_mult"=>return MapCallResult::Rename("matrixCompMult".to_string()),
             "less_than"=>return MapCallResult::Rename("less_than".to_string()),
@@ -535,14 +573,14 @@ impl<'a> SlCx<'a>{
         }
     }
 
-    pub fn map_type(&self, ty:&str)->String{
+    pub fn gl_map_type(&self, ty:&str)->String{
         match ty{
             "texture2d"=>return "sampler2D".to_string(),
             _=>return ty.to_string()
         }
     }
 
-    pub fn map_var(&mut self, var:&ShVar)->String{
+    pub fn gl_map_var(&mut self, var:&ShVar)->String{
         match var.store{
             ShVarStore::Instance=>{
                 if let SlTarget::Pixel = self.target{
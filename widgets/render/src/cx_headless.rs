@@ -0,0 +1,306 @@
+// A renderer without a gpu or window, draw lists are rasterized on the CPU by
+// interpreting the shader AST, so apps and widgets can be drawn in CI
+
+use crate::cx::*;
+
+impl Cx{
+
+    pub fn exec_draw_list(&mut self, draw_list_id: usize){
+
+        let draw_calls_len = self.draw_lists[draw_list_id].draw_calls_len;
+
+        for draw_call_id in 0..draw_calls_len{
+            let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
+            if sub_list_id != 0{
+                // render targets were drawn into their texture already
+                if self.draw_lists[sub_list_id].texture_id.is_none(){
+                    self.exec_draw_list(sub_list_id);
+                }
+            }
+            else{
+                let draw_list = &mut self.draw_lists[draw_list_id];
+
+                draw_list.set_clipping_uniforms();
+
+                let draw_call = &mut draw_list.draw_calls[draw_call_id];
+                draw_call.instance_dirty = false;
+                let csh = &self.compiled_shaders[draw_call.shader_id];
+
+                if let Some(program) = &csh.program{
+                    let instance = if csh.instance_packing.is_packed(){
+                        csh.instance_packing.pack(&draw_call.instance, &mut draw_call.platform.inst_packed);
+                        &draw_call.platform.inst_packed
+                    }
+                    else{
+                        &draw_call.instance
+                    };
+                    let textures_2d = &self.textures_2d;
+                    let textures:Vec<&Texture2D> = draw_call.textures_2d.iter().map(|id| &textures_2d[*id as usize]).collect();
+                    let result = program.draw(
+                        &mut self.platform.frame,
+                        instance,
+                        &self.uniforms,
+                        &draw_list.uniforms,
+                        &draw_call.uniforms,
+                        &textures
+                    );
+                    if let Err(err) = result{
+                        println!("Error drawing shader {}: {}", self.shaders[draw_call.shader_id].name, err.msg);
+                    }
+                }
+            }
+        }
+    }
+
+    // rasterizes a texture backed draw list into the pixels of its texture
+    pub fn exec_render_target(&mut self, draw_list_id:usize){
+        let (texture_id, width, height) = self.prepare_render_target(draw_list_id, false);
+        let mut frame = CpuFrame::default();
+        frame.clear(width, height, Color{r:0., g:0., b:0., a:0.});
+        std::mem::swap(&mut self.platform.frame, &mut frame);
+        self.exec_draw_list(draw_list_id);
+        std::mem::swap(&mut self.platform.frame, &mut frame);
+        let tex = &mut self.textures_2d[texture_id];
+        tex.width = width;
+        tex.height = height;
+        tex.image = HeadlessImage::pack_pixels(&frame.pixels);
+        tex.dirty = false;
+    }
+
+    pub fn repaint(&mut self){
+        if self.draw_lists.len() > 0{
+            let mut targets = Vec::new();
+            self.collect_render_targets(0, &mut targets);
+            for draw_list_id in targets{
+                self.exec_render_target(draw_list_id);
+            }
+        }
+        let width = (self.target_size.x * self.target_dpi_factor) as usize;
+        let height = (self.target_size.y * self.target_dpi_factor) as usize;
+        self.platform.frame.clear(width, height, self.clear_color);
+        self.prepare_frame();
+        if self.draw_lists.len() > 0{
+            self.exec_draw_list(0);
+        }
+    }
+
+    // draws what is in the draw lists now and hands back the pixels
+    pub fn render_to_image(&mut self)->HeadlessImage{
+        self.compile_all_headless_shaders();
+        self.repaint();
+        self.paint_dirty = false;
+        let frame = &self.platform.frame;
+        HeadlessImage{
+            width:frame.width,
+            height:frame.height,
+            pixels:HeadlessImage::pack_pixels(&frame.pixels)
+        }
+    }
+
+    // there are no platform events, so this constructs and draws the app
+    // until nothing is left to do and returns, use headless_event to send it more
+    pub fn event_loop<F>(&mut self, mut event_handler:F)
+    where F: FnMut(&mut Cx, &mut Event),
+    {
+        if self.target_size.x == 0. || self.target_size.y == 0.{
+            self.target_size = Vec2{x:640., y:480.};
+        }
+        if self.target_dpi_factor == 0.{
+            self.target_dpi_factor = 1.;
+        }
+
+        self.compile_all_headless_shaders();
+        self.load_binary_deps_from_file();
+
+        self.call_event_handler(&mut event_handler, &mut Event::Construct);
+
+        self.redraw_area(Area::All);
+        self.headless_settle(&mut event_handler);
+    }
+
+    pub fn headless_event<F>(&mut self, mut event_handler:F, event:&mut Event)
+    where F: FnMut(&mut Cx, &mut Event),
+    {
+        self.call_input_event_handler(&mut event_handler, event);
+        self.headless_settle(&mut event_handler);
+    }
+
+    pub fn headless_settle<F>(&mut self, mut event_handler:F)
+    where F: FnMut(&mut Cx, &mut Event),
+    {
+        self.reload_headless_shader_files();
+        // redraws and file reads can cause more of each, an app that keeps
+        // redrawing itself gets cut off instead of hanging the caller
+        for _i in 0..100{
+            self.process_desktop_file_read_requests(&mut event_handler);
+            self.process_desktop_file_write_requests(&mut event_handler);
+            self.process_desktop_paste_requests(&mut event_handler);
+            if self.redraw_areas.len() == 0{
+                break;
+            }
+            // a new root view would get a new draw list every time
+            let mut root_view = if let Some(root_view) = self.platform.root_view.take(){root_view}else{
+                View::<NoScrollBar>{
+                    ..Style::style(self)
+                }
+            };
+            self.call_draw_event(&mut event_handler, &mut root_view);
+            self.platform.root_view = Some(root_view);
+            self.paint_dirty = true;
+        }
+        self.compile_all_headless_shaders();
+        // hit testing reads the clip uniforms, the other platforms set them when painting
+        if self.paint_dirty{
+            for draw_list in &mut self.draw_lists{
+                draw_list.set_clipping_uniforms();
+            }
+        }
+    }
+
+    pub fn show_text_ime(&mut self, _x:f32, _y:f32){
+    }
+
+    pub fn hide_text_ime(&mut self){
+    }
+
+    // the clipboard only lives as long as the Cx
+    pub fn copy_to_clipboard(&mut self, text:&str){
+        self.platform.clipboard = text.to_string();
+    }
+
+    pub fn read_clipboard(&mut self)->Option<String>{
+        Some(self.platform.clipboard.clone())
+    }
+
+    // only compiles the shaders added since the last call
+    pub fn compile_all_headless_shaders(&mut self){
+        let start = self.compiled_shaders.len();
+        for i in start..self.shaders.len(){
+            let csh = Self::compile_headless_shader(&self.shaders[i]);
+            if let Ok(csh) = csh{
+                self.compiled_shaders.push(CompiledShader{
+                    shader_id:self.compiled_shaders.len(),
+                    ..csh
+                });
+            }
+            else if let Err(err) = csh{
+                self.log(&format!("Shader {}: {}\n", self.shaders[i].name, err.msg));
+                self.compiled_shaders.push(
+                    CompiledShader{..Default::default()}
+                )
+            }
+        };
+    }
+
+    // swaps in the shaders whose .shader file changed, see cx_shaderfile
+    pub fn reload_headless_shader_files(&mut self){
+        self.reload_shader_files(|cx, shader_id|{
+            Self::compile_headless_shader(&cx.shaders[shader_id])
+        });
+    }
+
+    pub fn compile_headless_shader(sh:&Shader)->Result<CompiledShader, SlErr>{
+        sh.type_check()?;
+        let instances = sh.flat_vars(ShVarStore::Instance);
+        // the program runs what the gpu targets run
+        let opt = sh.optimize();
+        Ok(CompiledShader{
+            shader_id:0,
+            instance_slots:sh.compute_slot_total(&instances),
+            instance_packing:InstancePacking::construct(sh, &instances, &opt.flat_vars(ShVarStore::Instance)),
+            uniforms_dr:sh.flat_vars(ShVarStore::Uniform),
+            uniforms_dl:sh.flat_vars(ShVarStore::UniformDl),
+            uniforms_cx:sh.flat_vars(ShVarStore::UniformCx),
+            texture_slots:sh.flat_vars(ShVarStore::Texture),
+            rect_instance_props:RectInstanceProps::construct(sh, &instances),
+            named_instance_props:NamedInstanceProps::construct(sh, &instances),
+            program:Some(CpuProgram::compile(&opt)?)
+        })
+    }
+}
+
+// pixels are packed as 0xAARRGGBB like Texture2D, rows top to bottom
+#[derive(Clone, Default, PartialEq)]
+pub struct HeadlessImage{
+    pub width:usize,
+    pub height:usize,
+    pub pixels:Vec<u32>
+}
+
+impl HeadlessImage{
+    pub fn pack_pixels(pixels:&Vec<Color>)->Vec<u32>{
+        pixels.iter().map(|c|{
+            let byte = |v:f32| (v.max(0.).min(1.) * 255. + 0.5) as u32;
+            (byte(c.a) << 24) | (byte(c.r) << 16) | (byte(c.g) << 8) | byte(c.b)
+        }).collect()
+    }
+
+    pub fn get_pixel(&self, x:usize, y:usize)->Color{
+        let p = self.pixels[y * self.width + x];
+        Color{
+            r:((p >> 16) & 0xff) as f32 / 255.,
+            g:((p >> 8) & 0xff) as f32 / 255.,
+            b:(p & 0xff) as f32 / 255.,
+            a:((p >> 24) & 0xff) as f32 / 255.
+        }
+    }
+}
+
+#[derive(Default,Clone)]
+pub struct CompiledShader{
+    pub shader_id: usize,
+    pub instance_slots:usize,
+    pub instance_packing:InstancePacking,
+    pub uniforms_dr: Vec<ShVar>,
+    pub uniforms_dl: Vec<ShVar>,
+    pub uniforms_cx: Vec<ShVar>,
+    pub texture_slots: Vec<ShVar>,
+    pub named_instance_props: NamedInstanceProps,
+    pub rect_instance_props: RectInstanceProps,
+    // None when the shader failed to compile, its draw calls are skipped
+    pub program: Option<CpuProgram>
+}
+
+#[derive(Clone, Default)]
+pub struct CxPlatform{
+    pub desktop:CxDesktop,
+    pub frame:CpuFrame,
+    pub root_view:Option<View<NoScrollBar>>,
+    // the input state of the scripted events, see cx_snapshot
+    pub finger_down:bool,
+    pub last_mouse_pos:Vec2,
+    pub time:f64,
+    pub clipboard:String
+}
+
+#[derive(Clone, Default)]
+pub struct DrawListPlatform{
+}
+
+#[derive(Default,Clone)]
+pub struct DrawCallPlatform{
+    pub inst_packed:Vec<f32>
+}
+
+#[derive(Default,Clone)]
+pub struct Texture2D{
+    pub texture_id: usize,
+    pub dirty:bool,
+    pub image: Vec<u32>,
+    pub width: usize,
+    pub height:usize
+}
+
+impl Texture2D{
+    pub fn resize(&mut self, width:usize, height:usize){
+        self.width = width;
+        self.height = height;
+        self.image.resize((width * height) as usize, 0);
+        self.dirty = true;
+    }
+
+    // the rasterizer samples the image directly
+    pub fn upload_to_device(&mut self){
+        self.dirty = false;
+    }
+}
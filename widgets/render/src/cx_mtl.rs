@@ -13,14 +13,17 @@ use crate::cx::*;
 
 impl Cx{
 
-    pub fn exec_draw_list(&mut self, draw_list_id: usize, device:&Device, encoder:&RenderCommandEncoderRef){
+    pub fn exec_draw_list(&mut self, draw_list_id: usize, device:&Device, encoder:&RenderCommandEncoderRef, uni_cx:&MultiMetalBuffer){
         
          // tad ugly otherwise the borrow checker locks 'self' and we can't recur
         let draw_calls_len = self.draw_lists[draw_list_id].draw_calls_len;
         for draw_call_id in 0..draw_calls_len{
             let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
             if sub_list_id != 0{
-                self.exec_draw_list(sub_list_id, device, encoder);
+                // render targets were drawn into their texture already
+                if self.draw_lists[sub_list_id].texture_id.is_none(){
+                    self.exec_draw_list(sub_list_id, device, encoder, uni_cx);
+                }
             }
             else{
                 let draw_list = &mut self.draw_lists[draw_list_id];
@@ -33,7 +36,13 @@ impl Cx{
                 if draw_call.instance_dirty{
                     draw_call.instance_dirty = false;
                     // update the instance buffer data
-                    draw_call.platform.inst_vbuf.update_with_f32_data(device, &draw_call.instance);
+                    if shc.instance_packing.is_packed(){
+                        shc.instance_packing.pack(&draw_call.instance, &mut draw_call.platform.inst_packed);
+                        draw_call.platform.inst_vbuf.update_with_f32_data(device, &draw_call.platform.inst_packed);
+                    }
+                    else{
+                        draw_call.platform.inst_vbuf.update_with_f32_data(device, &draw_call.instance);
+                    }
                     draw_call.platform.uni_dr.update_with_f32_data(device, &draw_call.uniforms);
                 }
 
@@ -46,14 +55,14 @@ impl Cx{
                     else{println!("Drawing error: geom_vbuf None")}
                     if let Some(buf) = &draw_call.platform.inst_vbuf.multi_buffer_read().buffer{encoder.set_vertex_buffer(1, Some(&buf), 0);}
                     else{println!("Drawing error: inst_vbuf None")}
-                    if let Some(buf) = &self.platform.uni_cx.multi_buffer_read().buffer{encoder.set_vertex_buffer(2, Some(&buf), 0);}
+                    if let Some(buf) = &uni_cx.buffer{encoder.set_vertex_buffer(2, Some(&buf), 0);}
                     else{println!("Drawing error: uni_cx None")}
                     if let Some(buf) = &draw_list.platform.uni_dl.multi_buffer_read().buffer{encoder.set_vertex_buffer(3, Some(&buf), 0);}
                     else{println!("Drawing error: uni_dl None")}
                     if let Some(buf) = &draw_call.platform.uni_dr.multi_buffer_read().buffer{encoder.set_vertex_buffer(4, Some(&buf), 0);}
                     else{println!("Drawing error: uni_dr None")}
 
-                    if let Some(buf) = &self.platform.uni_cx.multi_buffer_read().buffer{encoder.set_fragment_buffer(0, Some(&buf), 0);}
+                    if let Some(buf) = &uni_cx.buffer{encoder.set_fragment_buffer(0, Some(&buf), 0);}
                     else{println!("Drawing error: uni_cx None")}
                     if let Some(buf) = &draw_list.platform.uni_dl.multi_buffer_read().buffer{encoder.set_fragment_buffer(1, Some(&buf), 0);}
                     else{println!("Drawing error: uni_dl None")}
@@ -87,9 +96,40 @@ impl Cx{
         }
     }
  
+    // draws a texture backed draw list into its texture, with its own camera uniforms
+    pub fn exec_render_target(&mut self, draw_list_id:usize, device:&Device, command_buffer:&CommandBufferRef){
+        let (texture_id, width, height) = self.prepare_render_target(draw_list_id, false);
+        self.textures_2d[texture_id].update_render_target(device, width, height);
+        let draw_list = &mut self.draw_lists[draw_list_id];
+        draw_list.platform.uni_cx.update_with_f32_data(device, &self.uniforms);
+        let uni_cx = draw_list.platform.uni_cx.multi_buffer_read().clone();
+
+        let render_pass_descriptor = RenderPassDescriptor::new();
+        let color_attachment = render_pass_descriptor.color_attachments().object_at(0).unwrap();
+        if let Some(mtltex) = &self.textures_2d[texture_id].mtltexture{
+            color_attachment.set_texture(Some(&mtltex));
+        }
+        color_attachment.set_load_action(MTLLoadAction::Clear);
+        color_attachment.set_clear_color(MTLClearColor::new(0.0, 0.0, 0.0, 0.0));
+        color_attachment.set_store_action(MTLStoreAction::Store);
+
+        let encoder = command_buffer.new_render_command_encoder(&render_pass_descriptor);
+        self.exec_draw_list(draw_list_id, device, encoder, &uni_cx);
+        encoder.end_encoding();
+    }
+ 
     pub fn repaint(&mut self,layer:&CoreAnimationLayer, device:&Device, command_queue:&CommandQueue){
         let pool = unsafe { NSAutoreleasePool::new(cocoa::base::nil) };
         if let Some(drawable) = layer.next_drawable() {
+            let command_buffer = command_queue.new_command_buffer();
+
+            // the textures get drawn first so the window pass can sample them
+            let mut targets = Vec::new();
+            self.collect_render_targets(0, &mut targets);
+            for draw_list_id in targets{
+                self.exec_render_target(draw_list_id, device, command_buffer);
+            }
+
             self.prepare_frame();
             
             let render_pass_descriptor = RenderPassDescriptor::new();
@@ -102,8 +142,6 @@ impl Cx{
             ));
             color_attachment.set_store_action(MTLStoreAction::Store);
 
-            let command_buffer = command_queue.new_command_buffer();
-
             render_pass_descriptor.color_attachments().object_at(0).unwrap().set_load_action(MTLLoadAction::Clear);
 
             let parallel_encoder = command_buffer.new_parallel_render_command_encoder(&render_pass_descriptor);
@@ -112,7 +150,8 @@ impl Cx{
             self.platform.uni_cx.update_with_f32_data(&device, &self.uniforms);
 
             // ok now we should call our render thing
-            self.exec_draw_list(0, &device, encoder);
+            let uni_cx = self.platform.uni_cx.multi_buffer_read().clone();
+            self.exec_draw_list(0, &device, encoder, &uni_cx);
             /*
             match &self.debug_area{
                 Area::All=>self.debug_draw_tree_recur(0, 0),
@@ -201,7 +240,7 @@ impl Cx{
         while self.running{
             //println!("{}{} ",self.playing_anim_areas.len(), self.redraw_areas.len());
             cocoa_window.poll_events(
-                self.playing_anim_areas.len() == 0 && self.redraw_areas.len() == 0 && self.next_frame_callbacks.len() == 0,
+                self.playing_anim_areas.len() == 0 && self.redraw_areas.len() == 0 && self.next_frame_callbacks.len() == 0 && self.replay.is_none() && !self.is_watching_shader_files(),
                 |events|{
                     for mut event in events{
                         match &mut event{
@@ -234,7 +273,7 @@ impl Cx{
                             },
                             _=>{
                                 //let time_now = precise_time_ns();
-                                self.call_event_handler(&mut event_handler, &mut event); 
+                                self.call_input_event_handler(&mut event_handler, &mut event); 
                                 //let time_now_next = precise_time_ns();
                                 //println!("Animation took: {}", ((time_now_next - time_now) as f64) / 1_000_000_000.0);
                             }
@@ -243,16 +282,24 @@ impl Cx{
                 }
             );
             
-            if self.playing_anim_areas.len() != 0{
-                let time = cocoa_window.time_now(); // keeps the error as low as possible
-                self.call_animation_event(&mut event_handler, time);
+            if self.replay.is_some(){
+                // a replay runs on the clock of the recording
+                self.replay_step(&mut event_handler);
             }
+            else{
+                if self.playing_anim_areas.len() != 0{
+                    let time = cocoa_window.time_now(); // keeps the error as low as possible
+                    self.call_animation_event(&mut event_handler, time);
+                }
 
-            if self.next_frame_callbacks.len() != 0{
-                let time = cocoa_window.time_now(); // keeps the error as low as possible
-                self.call_frame_event(&mut event_handler, time);
+                if self.next_frame_callbacks.len() != 0{
+                    let time = cocoa_window.time_now(); // keeps the error as low as possible
+                    self.call_frame_event(&mut event_handler, time);
+                }
             }
 
+            self.mtl_reload_shader_files(&device);
+
             // call redraw event
             if self.redraw_areas.len()>0{
                 //let time_start = cocoa_window.time_now();
@@ -263,6 +310,8 @@ impl Cx{
             }
 
             self.process_desktop_file_read_requests(&mut event_handler);
+            self.process_desktop_file_write_requests(&mut event_handler);
+            self.process_desktop_paste_requests(&mut event_handler);
 
             // set a cursor
             if !self.down_mouse_cursor.is_none(){
@@ -286,6 +335,11 @@ impl Cx{
                 self.repaint_id += 1;
                 self.repaint(&layer, &device, &command_queue);
             }
+
+            // the shader files are checked every loop, so only nap instead of blocking on events
+            if self.is_watching_shader_files() && self.playing_anim_areas.len() == 0 && self.redraw_areas.len() == 0 && self.next_frame_callbacks.len() == 0 && self.replay.is_none(){
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
         }
     }
 
@@ -296,25 +350,118 @@ impl Cx{
     pub fn hide_text_ime(&mut self){
     }
 
+    pub fn copy_to_clipboard(&mut self, text:&str){
+        cocoa_copy_to_clipboard(text);
+    }
+
+    pub fn read_clipboard(&mut self)->Option<String>{
+        cocoa_read_clipboard()
+    }
+
+    pub fn mtl_compile_all_shaders(&mut self, device:&Device){
+        for i in 0..self.shaders.len(){
+            let mtlsh = Self::mtl_compile_shader(&self.shaders[i], device);
+            if let Ok(mtlsh) = mtlsh{
+                self.compiled_shaders.push(CompiledShader{
+                    shader_id:self.compiled_shaders.len(),
+                    ..mtlsh
+                });
+            }
+            else if let Err(err) = mtlsh{
+                self.log(&format!("Shader {}: {}\n", self.shaders[i].name, err.msg));
+                self.compiled_shaders.push(
+                    CompiledShader{..Default::default()}
+                )
+            }
+        };
+    }
+
+    // swaps in the shaders whose .shader file changed, see cx_shaderfile
+    pub fn mtl_reload_shader_files(&mut self, device:&Device){
+        self.reload_shader_files(|cx, shader_id|{
+            Self::mtl_compile_shader(&cx.shaders[shader_id], device)
+        });
+    }
+
+    pub fn mtl_compile_shader(sh:&Shader, device: &Device)->Result<CompiledShader, SlErr>{
+        let ash = Self::mtl_assemble_shader(sh)?;
+
+        let options = CompileOptions::new();
+        let library = device.new_library_with_source(&ash.mtlsl, &options);
+
+        match library{
+            Err(library)=>Err(SlErr{msg:library}),
+            Ok(library)=>Ok(CompiledShader{
+                shader_id:0,
+                pipeline_state:{
+                    let vert = library.get_function("_vertex_shader", None).unwrap();
+                    let frag = library.get_function("_fragment_shader", None).unwrap();
+                    let rpd = RenderPipelineDescriptor::new();
+                    rpd.set_vertex_function(Some(&vert));
+                    rpd.set_fragment_function(Some(&frag));
+                    let color = rpd.color_attachments().object_at(0).unwrap();
+                    color.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
+                    color.set_blending_enabled(true);
+                    color.set_source_rgb_blend_factor(MTLBlendFactor::One);
+                    color.set_destination_rgb_blend_factor(MTLBlendFactor::OneMinusSourceAlpha);
+                    color.set_source_alpha_blend_factor(MTLBlendFactor::One);
+                    color.set_destination_alpha_blend_factor(MTLBlendFactor::OneMinusSourceAlpha);
+                    color.set_rgb_blend_operation(MTLBlendOperation::Add);
+                    color.set_alpha_blend_operation(MTLBlendOperation::Add);
+                    Some(device.new_render_pipeline_state(&rpd).unwrap())
+                },
+                library:Some(library),
+                instance_slots:ash.instance_packing.total_slots,
+                instance_packing:ash.instance_packing.clone(),
+                named_instance_props:ash.named_instance_props.clone(),
+                rect_instance_props:ash.rect_instance_props.clone(),
+                //assembled_shader:ash,
+                geom_ibuf:{
+                    let mut geom_ibuf = MetalBuffer{..Default::default()};
+                    geom_ibuf.update_with_u32_data(device, &sh.geometry_indices);
+                    geom_ibuf
+                },
+                geom_vbuf:{
+                    let mut geom_vbuf = MetalBuffer{..Default::default()};
+                    geom_vbuf.update_with_f32_data(device, &sh.geometry_vertices);
+                    geom_vbuf
+                }
+            })
+        }
+    }
+}
+
+#[derive(Default,Clone)]
+pub struct CompiledShader{
+    pub library:Option<metal::Library>,
+    pub pipeline_state:Option<metal::RenderPipelineState>,
+    pub shader_id: usize,
+    pub geom_vbuf:MetalBuffer,
+    pub geom_ibuf:MetalBuffer,
+    pub instance_slots:usize,
+    pub instance_packing:InstancePacking,
+    pub rect_instance_props: RectInstanceProps,
+    pub named_instance_props: NamedInstanceProps,
 }
 
 #[derive(Clone, Default)]
 pub struct CxPlatform{
     pub uni_cx:MetalBuffer,
     pub set_ime_position:Option<Vec2>,
-    pub text_clipboard_response:Option<String>,
     pub desktop:CxDesktop
 }
 
 #[derive(Clone, Default)]
 pub struct DrawListPlatform{
-     pub uni_dl:MetalBuffer
+     pub uni_dl:MetalBuffer,
+     pub uni_cx:MetalBuffer // the camera of a render target
 }
 
 #[derive(Default,Clone,Debug)]
 pub struct DrawCallPlatform{
     pub uni_dr:MetalBuffer,
-    pub inst_vbuf:MetalBuffer
+    pub inst_vbuf:MetalBuffer,
+    pub inst_packed:Vec<f32>
 }
 
 #[derive(Default,Clone,Debug)]
@@ -445,4 +592,22 @@ impl Texture2D{
         self.dirty = false;
       
     }
+
+    // a private texture the gpu draws into and samples from, it has no image
+    pub fn update_render_target(&mut self, device:&Device, width:usize, height:usize){
+        if self.mtltexture.is_some() && self.width == width && self.height == height{
+            return
+        }
+        self.width = width;
+        self.height = height;
+        let desc = TextureDescriptor::new();
+        desc.set_texture_type(MTLTextureType::D2);
+        desc.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
+        desc.set_width(width as u64);
+        desc.set_height(height as u64);
+        desc.set_storage_mode(MTLStorageMode::Private);
+        desc.set_usage(MTLTextureUsage::RenderTarget | MTLTextureUsage::ShaderRead);
+        self.mtltexture = Some(device.new_texture(&desc));
+        self.dirty = false;
+    }
 }
\ No newline at end of file
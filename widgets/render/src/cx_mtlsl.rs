@@ -1,5 +1,4 @@
 
-use metal::*;
 use crate::cx::*;
 
 #[derive(Default,Clone)]
@@ -11,39 +10,12 @@ pub struct AssembledMtlShader{
     pub texture_slots:Vec<ShVar>,
     pub rect_instance_props: RectInstanceProps,
     pub named_instance_props: NamedInstanceProps,
+    pub instance_packing: InstancePacking,
     pub mtlsl:String,
 }
 
-#[derive(Default,Clone)]
-pub struct CompiledShader{
-    pub library:Option<metal::Library>,
-    pub pipeline_state:Option<metal::RenderPipelineState>,
-    pub shader_id: usize,
-    pub geom_vbuf:MetalBuffer,
-    pub geom_ibuf:MetalBuffer,
-    pub instance_slots:usize,
-    pub rect_instance_props: RectInstanceProps,
-    pub named_instance_props: NamedInstanceProps,
-}
 
 impl Cx{
-     pub fn mtl_compile_all_shaders(&mut self, device:&Device){
-        for sh in &self.shaders{
-            let mtlsh = Self::mtl_compile_shader(&sh, device);
-            if let Ok(mtlsh) = mtlsh{
-                self.compiled_shaders.push(CompiledShader{
-                    shader_id:self.compiled_shaders.len(),
-                    ..mtlsh
-                });
-            }
-            else if let Err(err) = mtlsh{
-                println!("GOT ERROR: {}", err.msg);
-                self.compiled_shaders.push(
-                    CompiledShader{..Default::default()}
-                )
-            }
-        };
-    }
     pub fn mtl_type_to_packed_metal(ty:&str)->String{
         match ty.as_ref(){
             "float"=>"float".to_string(),
@@ -109,7 +81,11 @@ impl Cx{
     }
 
     pub fn mtl_assemble_shader(sh:&Shader)->Result<AssembledMtlShader, SlErr>{
-        
+        sh.type_check()?;
+        // widgets write the instances of the whole shader, the optimized one can read fewer
+        let all_instances = sh.flat_vars(ShVarStore::Instance);
+        let sh = &sh.optimize();
+
         let mut mtl_out = "#include <metal_stdlib>\nusing namespace metal;\n".to_string();
 
         // ok now define samplers from our sh. 
@@ -127,6 +103,19 @@ impl Cx{
         let instance_slots = sh.compute_slot_total(&instances);
         //let varying_slots = sh.compute_slot_total(&varyings);
 
+        mtl_out.push_str(&assemble_structs(&SlCx{
+            depth:0,
+            lang:SlLang::Mtlsl,
+            target:SlTarget::Constant,
+            defargs_fn:"".to_string(),
+            defargs_call:"".to_string(),
+            call_prefix:"".to_string(),
+            shader:sh,
+            scope:Vec::new(),
+            fn_deps:Vec::new(),
+            fn_done:Vec::new(),
+            auto_vary:Vec::new()
+        }));
         mtl_out.push_str(&Self::mtl_assemble_struct("_Geom", &geometries, true, ""));
         mtl_out.push_str(&Self::mtl_assemble_struct("_Inst", &instances, true, ""));
         mtl_out.push_str(&Self::mtl_assemble_struct("_UniCx", &uniforms_cx, true, ""));
@@ -141,6 +130,7 @@ impl Cx{
        // mtl_out.push_str(&Self::assemble_constants(&texture_slots));
         let mut const_cx = SlCx{
             depth:0,
+            lang:SlLang::Mtlsl,
             target:SlTarget::Constant,
             defargs_fn:"".to_string(),
             defargs_call:"".to_string(),
@@ -164,6 +154,7 @@ impl Cx{
 
         let mut vtx_cx = SlCx{
             depth:0,
+            lang:SlLang::Mtlsl,
             target:SlTarget::Vertex,
             defargs_fn:"_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, thread _Geom &_geom, thread _Inst &_inst, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr".to_string(),
             defargs_call:"_tex, _loc, _vary, _geom, _inst, _uni_cx, _uni_dl, _uni_dr".to_string(),
@@ -177,6 +168,7 @@ impl Cx{
         let vtx_fns = assemble_fn_and_deps(sh, &mut vtx_cx)?;
         let mut pix_cx = SlCx{
             depth:0,
+            lang:SlLang::Mtlsl,
             target:SlTarget::Pixel,
             defargs_fn:"_Tex _tex, thread _Loc &_loc, thread _Vary &_vary, device _UniCx &_uni_cx, device _UniDl &_uni_dl, device _UniDr &_uni_dr".to_string(),
             defargs_call:"_tex, _loc, _vary, _uni_cx, _uni_dl, _uni_dr".to_string(),
@@ -250,62 +242,42 @@ impl Cx{
             uniforms_dl:uniforms_dl,
             uniforms_cx:uniforms_cx,
             texture_slots:texture_slots,
-            rect_instance_props:RectInstanceProps::construct(sh, &instances),
-            named_instance_props:NamedInstanceProps::construct(sh, &instances),
+            rect_instance_props:RectInstanceProps::construct(sh, &all_instances),
+            named_instance_props:NamedInstanceProps::construct(sh, &all_instances),
+            instance_packing:InstancePacking::construct(sh, &all_instances, &instances),
             mtlsl:mtl_out
         })
     }
 
-    pub fn mtl_compile_shader(sh:&Shader, device: &Device)->Result<CompiledShader, SlErr>{
-        let ash = Self::mtl_assemble_shader(sh)?;
-
-        let options = CompileOptions::new();
-        let library = device.new_library_with_source(&ash.mtlsl, &options);
-
-        match library{
-            Err(library)=>Err(SlErr{msg:library}),
-            Ok(library)=>Ok(CompiledShader{
-                shader_id:0,
-                pipeline_state:{
-                    let vert = library.get_function("_vertex_shader", None).unwrap();
-                    let frag = library.get_function("_fragment_shader", None).unwrap();
-                    let rpd = RenderPipelineDescriptor::new();
-                    rpd.set_vertex_function(Some(&vert));
-                    rpd.set_fragment_function(Some(&frag));
-                    let color = rpd.color_attachments().object_at(0).unwrap();
-                    color.set_pixel_format(MTLPixelFormat::BGRA8Unorm);
-                    color.set_blending_enabled(true);
-                    color.set_source_rgb_blend_factor(MTLBlendFactor::One);
-                    color.set_destination_rgb_blend_factor(MTLBlendFactor::OneMinusSourceAlpha);
-                    color.set_source_alpha_blend_factor(MTLBlendFactor::One);
-                    color.set_destination_alpha_blend_factor(MTLBlendFactor::OneMinusSourceAlpha);
-                    color.set_rgb_blend_operation(MTLBlendOperation::Add);
-                    color.set_alpha_blend_operation(MTLBlendOperation::Add);
-                    Some(device.new_render_pipeline_state(&rpd).unwrap())
-                },
-                library:Some(library),
-                instance_slots:ash.instance_slots,
-                named_instance_props:ash.named_instance_props.clone(),
-                rect_instance_props:ash.rect_instance_props.clone(),
-                //assembled_shader:ash,
-                geom_ibuf:{
-                    let mut geom_ibuf = MetalBuffer{..Default::default()};
-                    geom_ibuf.update_with_u32_data(device, &sh.geometry_indices);
-                    geom_ibuf
-                },
-                geom_vbuf:{
-                    let mut geom_vbuf = MetalBuffer{..Default::default()};
-                    geom_vbuf.update_with_f32_data(device, &sh.geometry_vertices);
-                    geom_vbuf
-                }
-            })
-        }
+    // checks an assembled metal shader, see shadercheck
+    pub fn mtl_check_shader(source:&str)->Result<(), SlErr>{
+        check_shader_source(source, &SlCheckLang{
+            types:&[
+                "void", "bool", "int", "uint", "float", "half", "float2", "float3", "float4",
+                "int2", "int3", "int4", "bool2", "bool3", "bool4", "float2x2", "float3x3", "float4x4",
+                "packed_float2", "packed_float3", "packed_float4", "packed_float2x2", "packed_float3x3",
+                "texture2d", "sampler", "array"
+            ],
+            builtins:&[
+                "sin", "cos", "tan", "asin", "acos", "atan", "atan2", "pow", "exp", "exp2", "log", "log2",
+                "sqrt", "rsqrt", "abs", "fabs", "sign", "floor", "ceil", "fract", "fmod", "min", "max",
+                "fmin", "fmax", "clamp", "mix", "step", "smoothstep", "saturate", "length", "distance",
+                "dot", "cross", "normalize", "faceforward", "reflect", "refract", "any", "all", "select",
+                "dfdx", "dfdy", "fwidth", "transpose", "determinant"
+            ],
+            keywords:&[
+                "using", "namespace", "struct", "const", "constant", "device", "thread", "threadgroup",
+                "vertex", "fragment", "kernel", "static", "inline", "break", "continue", "do", "for",
+                "while", "if", "else", "true", "false", "return", "discard_fragment", "switch", "case", "default"
+            ],
+            colon_types:false
+        })
     }
 }
 
 
 impl<'a> SlCx<'a>{
-    pub fn map_call(&self, name:&str, args:&Vec<Sl>)->MapCallResult{
+    pub fn mtl_map_call(&self, name:&str, args:&Vec<Sl>)->MapCallResult{
         match name{
             "sample2d"=>{ // transform call to
                 let base = &args[0];
@@ -326,11 +298,11 @@ impl<'a> SlCx<'a>{
         }
     }    
 
-    pub fn map_type(&self, ty:&str)->String{
+    pub fn mtl_map_type(&self, ty:&str)->String{
         Cx::mtl_type_to_metal(ty)
     }
 
-    pub fn map_var(&mut self, var:&ShVar)->String{
+    pub fn mtl_map_var(&mut self, var:&ShVar)->String{
         let mty = Cx::mtl_type_to_metal(&var.ty);
         match var.store{
             ShVarStore::Uniform=>return format!("{}(_uni_dr.{})", mty, var.name),
@@ -5,6 +5,8 @@ use glutin::GlProfile;
 use std::mem;
 use std::ptr;
 use std::ffi::CStr;
+use std::io::Write;
+use std::process::{Command, Stdio};
 use time::precise_time_ns;
 
 use crate::cx::*;
@@ -19,7 +21,10 @@ impl Cx{
         for draw_call_id in 0..draw_calls_len{
             let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
             if sub_list_id != 0{
-                self.exec_draw_list(sub_list_id);
+                // render targets were drawn into their texture already
+                if self.draw_lists[sub_list_id].texture_id.is_none(){
+                    self.exec_draw_list(sub_list_id);
+                }
             }
             else{
                 let draw_list = &mut self.draw_lists[draw_list_id];
@@ -36,10 +41,17 @@ impl Cx{
                     if draw_call.instance_dirty{
                         draw_call.instance_dirty = false;
                         // update the instance buffer data
+                        let instance = if csh.instance_packing.is_packed(){
+                            csh.instance_packing.pack(&draw_call.instance, &mut draw_call.platform.inst_packed);
+                            &draw_call.platform.inst_packed
+                        }
+                        else{
+                            &draw_call.instance
+                        };
                         gl::BindBuffer(gl::ARRAY_BUFFER, draw_call.platform.vb);
                         gl::BufferData(gl::ARRAY_BUFFER,
-                                        (draw_call.instance.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
-                                        draw_call.instance.as_ptr() as *const _, gl::STATIC_DRAW);
+                                        (instance.len() * mem::size_of::<f32>()) as gl::types::GLsizeiptr,
+                                        instance.as_ptr() as *const _, gl::STATIC_DRAW);
                    }
 
                     gl::UseProgram(csh.program);
@@ -63,6 +75,23 @@ impl Cx{
                                     .expect("gl_string: non-UTF8 string")
     }
     
+
+    // draws a texture backed draw list into the framebuffer of its texture
+    pub fn exec_render_target(&mut self, draw_list_id:usize){
+        let (texture_id, width, height) = self.prepare_render_target(draw_list_id, true);
+        let tex = &mut self.textures_2d[texture_id];
+        tex.update_render_target(width, height);
+        unsafe{
+            gl::BindFramebuffer(gl::FRAMEBUFFER, tex.gl_framebuffer.unwrap());
+            gl::Viewport(0, 0, width as i32, height as i32);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT|gl::DEPTH_BUFFER_BIT);
+        }
+        self.exec_draw_list(draw_list_id);
+        unsafe{
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
   
     pub fn repaint(&mut self, glutin_window:&glutin::GlWindow){
         unsafe{
@@ -71,6 +100,14 @@ impl Cx{
             gl::BlendEquationSeparate(gl::FUNC_ADD, gl::FUNC_ADD);
             gl::BlendFuncSeparate(gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
             gl::Enable(gl::BLEND);
+        }
+        let mut targets = Vec::new();
+        self.collect_render_targets(0, &mut targets);
+        for draw_list_id in targets{
+            self.exec_render_target(draw_list_id);
+        }
+        unsafe{
+            gl::Viewport(0, 0, (self.target_size.x * self.target_dpi_factor) as i32, (self.target_size.y * self.target_dpi_factor) as i32);
             gl::ClearColor(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
             gl::Clear(gl::COLOR_BUFFER_BIT|gl::DEPTH_BUFFER_BIT);
         }
@@ -132,6 +169,7 @@ impl Cx{
         
         self.redraw_area(Area::All);
 
+        let mut shader_watcher = None;
         while self.running{
             events_loop.poll_events(|winit_event|{
                 let mut events = self.map_winit_event(winit_event, &glutin_window);
@@ -145,17 +183,28 @@ impl Cx{
                         },
                         Event::None=>{},
                         _=>{
-                            self.call_event_handler(&mut event_handler, &mut event); 
+                            self.call_input_event_handler(&mut event_handler, &mut event); 
+                            if let Event::TextCopy(req) = &event{
+                                if let Some(response) = &req.response{
+                                    self.copy_to_clipboard(&response);
+                                }
+                            }
                         }
                     }
                 }
             });
-            if self.playing_anim_areas.len() != 0{
+            if self.replay.is_some(){
+                // a replay runs on the clock of the recording
+                self.replay_step(&mut event_handler);
+            }
+            else if self.playing_anim_areas.len() != 0{
                 let time_now = precise_time_ns();
                 let time = (time_now - start_time) as f64 / 1_000_000_000.0; // keeps the error as low as possible
                 self.call_animation_event(&mut event_handler, time);
             }
 
+            self.reload_ogl_shader_files();
+
             // call redraw event
             if self.redraw_areas.len()>0{
                 self.call_draw_event(&mut event_handler, &mut root_view);
@@ -163,6 +212,8 @@ impl Cx{
             }
              
              self.process_desktop_file_read_requests(&mut event_handler);
+             self.process_desktop_file_write_requests(&mut event_handler);
+             self.process_desktop_paste_requests(&mut event_handler);
 
             // set a cursor
             if !self.down_mouse_cursor.is_none(){
@@ -181,7 +232,17 @@ impl Cx{
             }
 
             // wait for the next event blockingly so it stops eating power
-            if self.playing_anim_areas.len() == 0 && self.redraw_areas.len() == 0{
+            if self.playing_anim_areas.len() == 0 && self.redraw_areas.len() == 0 && self.replay.is_none(){
+                // a changed shader file wakes the loop up, see cx_shaderfile
+                if self.is_watching_shader_files() && shader_watcher.is_none(){
+                    let proxy = events_loop.create_proxy();
+                    shader_watcher = Some(ShaderFileWatcher::new(move ||{
+                        let _ = proxy.wakeup();
+                    }));
+                }
+                if let Some(shader_watcher) = &shader_watcher{
+                    shader_watcher.update(self);
+                }
                 events_loop.run_forever(|winit_event|{
                     let mut events = self.map_winit_event(winit_event, &glutin_window);
                     for mut event in &mut events{
@@ -194,7 +255,7 @@ impl Cx{
                             },
                             Event::None=>{},
                             _=>{
-                                self.call_event_handler(&mut event_handler, &mut event);
+                                self.call_input_event_handler(&mut event_handler, &mut event);
                             }
                         }
                     }
@@ -292,6 +353,54 @@ impl Cx{
                 _=>()
             },
             winit::Event::WindowEvent{ event, .. } => match event {
+                winit::WindowEvent::KeyboardInput{input, ..}=>{
+                    // only the clipboard shortcuts are mapped for now
+                    if let winit::ElementState::Pressed = input.state{
+                        let mods = input.modifiers;
+                        let key_code = match input.virtual_keycode{
+                            Some(winit::VirtualKeyCode::C)=>KeyCode::KeyC,
+                            Some(winit::VirtualKeyCode::X)=>KeyCode::KeyX,
+                            Some(winit::VirtualKeyCode::V)=>KeyCode::KeyV,
+                            Some(winit::VirtualKeyCode::Insert)=>KeyCode::Insert,
+                            _=>return vec![Event::None]
+                        };
+                        let is_copy = match key_code{
+                            KeyCode::KeyC | KeyCode::KeyX=>mods.ctrl,
+                            KeyCode::Insert=>mods.ctrl,
+                            _=>false
+                        };
+                        let is_paste = match key_code{
+                            KeyCode::KeyV=>mods.ctrl,
+                            KeyCode::Insert=>mods.shift,
+                            _=>false
+                        };
+                        if !is_copy && !is_paste{
+                            return vec![Event::None]
+                        }
+                        let mut events = Vec::new();
+                        if is_copy{
+                            events.push(Event::TextCopy(TextCopyEvent{
+                                response:None
+                            }));
+                        }
+                        else{
+                            self.request_paste();
+                        }
+                        events.push(Event::KeyDown(KeyEvent{
+                            key_code:key_code,
+                            key_char:'\0',
+                            is_repeat:false,
+                            modifiers:KeyModifiers{
+                                shift:mods.shift,
+                                control:mods.ctrl,
+                                alt:mods.alt,
+                                logo:mods.logo
+                            },
+                            time:0.0
+                        }));
+                        return events;
+                    }
+                },
                 winit::WindowEvent::ReceivedCharacter(chr)=>{
                     //println!("GOT CHARACTER {}", chr);
                 },
@@ -440,9 +549,58 @@ impl Cx{
         //self.platform.from_wasm.hide_text_ime();
     }
 
+    // winit has no clipboard, so this goes through the tools of the display server
+    fn clipboard_commands(is_copy:bool)->Vec<(&'static str, Vec<&'static str>)>{
+        if std::env::var("WAYLAND_DISPLAY").is_ok(){
+            if is_copy{vec![("wl-copy", vec![])]}else{vec![("wl-paste", vec!["-n"])]}
+        }
+        else if is_copy{
+            vec![("xclip", vec!["-selection","clipboard"]), ("xsel", vec!["--clipboard","--input"])]
+        }
+        else{
+            vec![("xclip", vec!["-selection","clipboard","-o"]), ("xsel", vec!["--clipboard","--output"])]
+        }
+    }
+
+    pub fn copy_to_clipboard(&mut self, text:&str){
+        for (cmd, args) in Self::clipboard_commands(true){
+            let child = Command::new(cmd).args(&args).stdin(Stdio::piped()).spawn();
+            if let Ok(mut child) = child{
+                if let Some(mut stdin) = child.stdin.take(){
+                    let _ = stdin.write_all(text.as_bytes());
+                }
+                let _ = child.wait();
+                return
+            }
+        }
+        self.warn_no_clipboard();
+    }
+
+    pub fn read_clipboard(&mut self)->Option<String>{
+        for (cmd, args) in Self::clipboard_commands(false){
+            if let Ok(output) = Command::new(cmd).args(&args).output(){
+                if !output.status.success(){
+                    return None
+                }
+                return Some(String::from_utf8_lossy(&output.stdout).to_string())
+            }
+        }
+        self.warn_no_clipboard();
+        None
+    }
+
+    // once, instead of every copy and paste doing nothing without a word
+    fn warn_no_clipboard(&mut self){
+        if !self.platform.clipboard_warned{
+            self.platform.clipboard_warned = true;
+            let tools = if std::env::var("WAYLAND_DISPLAY").is_ok(){"wl-clipboard"}else{"xclip or xsel"};
+            self.log(&format!("No clipboard, copy and paste need {} to be installed\n", tools));
+        }
+    }
+
     pub fn compile_all_ogl_shaders(&mut self){
-        for sh in &self.shaders{
-            let glsh = Self::compile_ogl_shader(&sh);
+        for i in 0..self.shaders.len(){
+            let glsh = Self::compile_ogl_shader(&self.shaders[i]);
             if let Ok(glsh) = glsh{
                 self.compiled_shaders.push(CompiledShader{
                     shader_id:self.compiled_shaders.len(),
@@ -450,7 +608,7 @@ impl Cx{
                 });
             }
             else if let Err(err) = glsh{
-                println!("GOT ERROR: {}", err.msg);
+                self.log(&format!("Shader {}: {}\n", self.shaders[i].name, err.msg));
                 self.compiled_shaders.push(
                     CompiledShader{..Default::default()}
                 )
@@ -458,6 +616,28 @@ impl Cx{
         };
     }
 
+    // swaps in the shaders whose .shader file changed, see cx_shaderfile
+    pub fn reload_ogl_shader_files(&mut self){
+        self.reload_shader_files(|cx, shader_id|{
+            let csh = Self::compile_ogl_shader(&cx.shaders[shader_id])?;
+            let old = &cx.compiled_shaders[shader_id];
+            unsafe{
+                gl::DeleteProgram(old.program);
+                gl::DeleteBuffers(1, &old.geom_vb);
+                gl::DeleteBuffers(1, &old.geom_ib);
+            }
+            // the vaos have the attribute locations of the old program
+            for draw_list in &mut cx.draw_lists{
+                for draw_call in &mut draw_list.draw_calls{
+                    if draw_call.shader_id == shader_id{
+                        draw_call.platform.resource_shader_id = None;
+                    }
+                }
+            }
+            Ok(csh)
+        });
+    }
+
     pub fn compile_has_shader_error(compile:bool, shader:gl::types::GLuint, source:&str)->Option<String>{
         unsafe{
             let mut success = i32::from(gl::FALSE);
@@ -570,7 +750,7 @@ impl Cx{
         unsafe{
             
             let vs = gl::CreateShader(gl::VERTEX_SHADER);
-            gl::ShaderSource(vs, 1, [ash.vertex.as_ptr() as *const _].as_ptr(), ptr::null());
+            gl::ShaderSource(vs, 1, [ash.vertex.as_ptr() as *const _].as_ptr(), [ash.vertex.len() as i32].as_ptr());
             gl::CompileShader(vs);
             if let Some(error) = Self::compile_has_shader_error(true, vs, &ash.vertex){
                 return Err(SlErr{
@@ -579,7 +759,7 @@ impl Cx{
             }
 
             let fs = gl::CreateShader(gl::FRAGMENT_SHADER);
-            gl::ShaderSource(fs, 1, [ash.fragment.as_ptr() as *const _].as_ptr(), ptr::null());
+            gl::ShaderSource(fs, 1, [ash.fragment.as_ptr() as *const _].as_ptr(), [ash.fragment.len() as i32].as_ptr());
             gl::CompileShader(fs);
             if let Some(error) = Self::compile_has_shader_error(true, fs, &ash.fragment){
                 return Err(SlErr{
@@ -630,7 +810,8 @@ impl Cx{
                 texture_slots:Self::compile_get_texture_slots(program, &ash.texture_slots),
                 named_instance_props:ash.named_instance_props.clone(),
                 rect_instance_props:ash.rect_instance_props.clone(),
-                instance_slots:ash.instance_slots,
+                instance_slots:ash.instance_packing.total_slots,
+                instance_packing:ash.instance_packing.clone(),
                 //assembled_shader:ash,
                 ..Default::default()
             })
@@ -743,7 +924,8 @@ pub struct CompiledShader{
     pub uniforms_cx: Vec<GLUniform>,
     pub texture_slots: Vec<GLUniform>,
     pub named_instance_props: NamedInstanceProps,
-    pub rect_instance_props: RectInstanceProps
+    pub rect_instance_props: RectInstanceProps,
+    pub instance_packing: InstancePacking
 }
 
 #[derive(Default,Clone)]
@@ -762,7 +944,9 @@ pub struct CxPlatform{
     pub fingers_down:Vec<bool>,
     pub last_mouse_pos:Vec2,
     pub is_cursor_in_window:bool,
-    pub desktop:CxDesktop
+    pub desktop:CxDesktop,
+    // none of the clipboard tools could be run and we said so
+    pub clipboard_warned:bool
 }
 
 #[derive(Clone, Default)]
@@ -774,7 +958,8 @@ pub struct DrawListPlatform{
 pub struct DrawCallPlatform{
     pub resource_shader_id:Option<usize>,
     pub vao:gl::types::GLuint,
-    pub vb:gl::types::GLuint
+    pub vb:gl::types::GLuint,
+    pub inst_packed:Vec<f32>
 }
 
 impl DrawCallPlatform{
@@ -835,7 +1020,8 @@ pub struct Texture2D{
     pub image: Vec<u32>,
     pub width: usize,
     pub height:usize,
-    pub gl_texture: Option<gl::types::GLuint>
+    pub gl_texture: Option<gl::types::GLuint>,
+    pub gl_framebuffer: Option<gl::types::GLuint>
 }
 
 impl Texture2D{
@@ -869,4 +1055,45 @@ impl Texture2D{
 
         self.dirty = false;
     }
+
+    // (re)allocates the texture as the color attachment of its own framebuffer
+    pub fn update_render_target(&mut self, width:usize, height:usize){
+        if self.gl_framebuffer.is_some() && self.width == width && self.height == height{
+            return
+        }
+        self.width = width;
+        self.height = height;
+        unsafe{
+            let tex_handle = match self.gl_texture{
+                None=>{
+                    let mut tex_handle = mem::uninitialized();
+                    gl::GenTextures(1, &mut tex_handle);
+                    self.gl_texture = Some(tex_handle);
+                    tex_handle
+                }
+                Some(gl_texture)=>gl_texture
+            };
+            gl::BindTexture(gl::TEXTURE_2D, tex_handle);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, width as i32, height as i32, 0, gl::RGBA, gl::UNSIGNED_BYTE, ptr::null());
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            let fb_handle = match self.gl_framebuffer{
+                None=>{
+                    let mut fb_handle = mem::uninitialized();
+                    gl::GenFramebuffers(1, &mut fb_handle);
+                    self.gl_framebuffer = Some(fb_handle);
+                    fb_handle
+                }
+                Some(gl_framebuffer)=>gl_framebuffer
+            };
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fb_handle);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, tex_handle, 0);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        self.dirty = false;
+    }
 }
@@ -0,0 +1,399 @@
+// Records the events the platform delivers to the app, and plays them back on the clock
+// of the recording so a session replays the same way on every backend, headless included.
+// Events the app causes itself (draws, file reads, key focus, user events) are not recorded,
+// the replay causes them again.
+
+use crate::cx::*;
+use std::fs;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedEvent{
+    pub time:f64,
+    pub event:Event
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct EventRecording{
+    pub events:Vec<RecordedEvent>,
+    // the last time seen in an event, not every platform puts a time on its input events
+    pub time:f64
+}
+
+#[derive(Clone, Default)]
+pub struct EventReplay{
+    pub recording:EventRecording,
+    pub next:usize
+}
+
+impl Cx{
+
+    pub fn start_recording(&mut self){
+        self.recording = Some(EventRecording{..Default::default()});
+    }
+
+    pub fn stop_recording(&mut self)->Option<EventRecording>{
+        self.recording.take()
+    }
+
+    // the platform event loops play one recorded event per frame instead of their own clock
+    pub fn start_replay(&mut self, recording:EventRecording){
+        self.replay = Some(EventReplay{
+            recording:recording,
+            next:0
+        });
+        self.redraw_area(Area::All);
+    }
+
+    pub fn is_replaying(&self)->bool{
+        self.replay.is_some()
+    }
+
+    pub fn record_event(&mut self, event:&Event){
+        if let Some(recording) = &mut self.recording{
+            recording.record(event);
+        }
+    }
+
+    // the platform event loops send live input through here, a replay drops it
+    // so only the recording drives the app until it is done
+    pub fn call_input_event_handler<F>(&mut self, event_handler:F, event:&mut Event)
+    where F: FnMut(&mut Cx, &mut Event)
+    {
+        if self.replay.is_some() && is_input_event(event){
+            return
+        }
+        self.call_event_handler(event_handler, event);
+    }
+
+    // sends the next recorded event to the app, returns false when the replay is done
+    pub fn replay_step<F>(&mut self, mut event_handler:F)->bool
+    where F: FnMut(&mut Cx, &mut Event)
+    {
+        let recorded = if let Some(replay) = &mut self.replay{
+            if replay.next >= replay.recording.events.len(){
+                None
+            }
+            else{
+                replay.next += 1;
+                Some(replay.recording.events[replay.next - 1].clone())
+            }
+        }else{None};
+
+        let RecordedEvent{time, mut event} = if let Some(recorded) = recorded{recorded}else{
+            self.replay = None;
+            return false
+        };
+        match &event{
+            Event::Animate(_)=>self.call_animation_event(&mut event_handler, time),
+            Event::Frame(_)=>self.call_frame_event(&mut event_handler, time),
+            Event::Resized(re)=>{
+                self.target_size = re.new_size;
+                self.target_dpi_factor = re.new_dpi_factor;
+                self.redraw_area(Area::All);
+                self.call_event_handler(&mut event_handler, &mut event);
+            },
+            Event::FingerHover(_)=>{
+                self.hover_mouse_cursor = None;
+                self.call_event_handler(&mut event_handler, &mut event);
+            },
+            Event::FingerUp(_)=>{
+                self.down_mouse_cursor = None;
+                self.call_event_handler(&mut event_handler, &mut event);
+            },
+            _=>self.call_event_handler(&mut event_handler, &mut event)
+        }
+        true
+    }
+}
+
+impl EventRecording{
+
+    pub fn record(&mut self, event:&Event){
+        if let Some(time) = event_time(event){
+            if time > 0.{
+                self.time = time;
+            }
+        }
+        match event{
+            Event::None | Event::Construct | Event::Destruct | Event::Draw | Event::AnimationEnded(_) |
+            Event::FileRead(_) | Event::FileWrite(_) | Event::KeyFocus(_)=>(),
+            _=>self.events.push(RecordedEvent{
+                time:self.time,
+                event:event.clone()
+            })
+        }
+    }
+
+    pub fn save(&self, path:&str)->Result<(), String>{
+        fs::write(path, self.to_text()).map_err(|err| format!("Cannot write {}: {}", path, err))
+    }
+
+    pub fn load(path:&str)->Result<EventRecording, String>{
+        let data = fs::read(path).map_err(|err| format!("Cannot read {}: {}", path, err))?;
+        Self::parse(&String::from_utf8_lossy(&data))
+    }
+
+    // one event per line, the time first, then the event name and its fields
+    pub fn to_text(&self)->String{
+        let mut out = String::new();
+        for recorded in &self.events{
+            let fields = match &recorded.event{
+                Event::AppFocus(focus)=>format!("AppFocus {}", focus),
+                Event::Animate(_)=>"Animate".to_string(),
+                Event::Frame(_)=>"Frame".to_string(),
+                Event::CloseRequested=>"CloseRequested".to_string(),
+                Event::Resized(re)=>format!("Resized {} {} {} {} {} {}",
+                    re.old_size.x, re.old_size.y, re.old_dpi_factor, re.new_size.x, re.new_size.y, re.new_dpi_factor),
+                Event::FingerDown(fe)=>format!("FingerDown {} {} {} {} {} {}",
+                    fe.abs.x, fe.abs.y, fe.digit, fe.tap_count, fe.is_touch, modifiers_to_string(&fe.modifiers)),
+                Event::FingerMove(fe)=>format!("FingerMove {} {} {} {} {}",
+                    fe.abs.x, fe.abs.y, fe.digit, fe.is_touch, modifiers_to_string(&fe.modifiers)),
+                Event::FingerHover(fe)=>format!("FingerHover {} {} {}",
+                    fe.abs.x, fe.abs.y, modifiers_to_string(&fe.modifiers)),
+                Event::FingerUp(fe)=>format!("FingerUp {} {} {} {} {}",
+                    fe.abs.x, fe.abs.y, fe.digit, fe.is_touch, modifiers_to_string(&fe.modifiers)),
+                Event::FingerScroll(fe)=>format!("FingerScroll {} {} {} {} {} {}",
+                    fe.abs.x, fe.abs.y, fe.scroll.x, fe.scroll.y, fe.is_wheel, modifiers_to_string(&fe.modifiers)),
+                Event::KeyDown(ke)=>format!("KeyDown {:?} {} {} {}",
+                    ke.key_code, ke.key_char as u32, ke.is_repeat, modifiers_to_string(&ke.modifiers)),
+                Event::KeyUp(ke)=>format!("KeyUp {:?} {} {} {}",
+                    ke.key_code, ke.key_char as u32, ke.is_repeat, modifiers_to_string(&ke.modifiers)),
+                // the text goes last, it can contain spaces
+                Event::TextInput(te)=>format!("TextInput {} {} {}", te.replace_last, te.was_paste, escape_text(&te.input)),
+                Event::TextCopy(_)=>"TextCopy".to_string(),
+                Event::Paste(pe)=>format!("Paste {}", escape_text(&pe.text)),
+                _=>continue
+            };
+            out.push_str(&format!("{} {}\n", recorded.time, fields));
+        }
+        out
+    }
+
+    pub fn parse(data:&str)->Result<EventRecording, String>{
+        let mut recording = EventRecording{..Default::default()};
+        for (line_nr, line) in data.lines().enumerate(){
+            if line.trim().len() == 0{
+                continue;
+            }
+            let recorded = parse_recorded_event(line).map_err(|err| format!("Recording line {}: {}", line_nr + 1, err))?;
+            recording.time = recorded.time;
+            recording.events.push(recorded);
+        }
+        Ok(recording)
+    }
+}
+
+fn is_input_event(event:&Event)->bool{
+    match event{
+        Event::AppFocus(_) | Event::FingerDown(_) | Event::FingerMove(_) | Event::FingerHover(_) | Event::FingerUp(_) |
+        Event::FingerScroll(_) | Event::KeyDown(_) | Event::KeyUp(_) | Event::TextInput(_) | Event::TextCopy(_) | Event::Paste(_)=>true,
+        _=>false
+    }
+}
+
+fn event_time(event:&Event)->Option<f64>{
+    match event{
+        Event::Animate(ae)=>Some(ae.time),
+        Event::Frame(fe)=>Some(fe.time),
+        Event::FingerDown(fe)=>Some(fe.time),
+        Event::FingerMove(fe)=>Some(fe.time),
+        Event::FingerHover(fe)=>Some(fe.time),
+        Event::FingerUp(fe)=>Some(fe.time),
+        Event::FingerScroll(fe)=>Some(fe.time),
+        Event::KeyDown(ke) | Event::KeyUp(ke)=>Some(ke.time),
+        _=>None
+    }
+}
+
+fn modifiers_to_string(modifiers:&KeyModifiers)->String{
+    let flag = |on:bool, c:char| if on{c}else{'-'};
+    format!("{}{}{}{}", flag(modifiers.shift, 's'), flag(modifiers.control, 'c'), flag(modifiers.alt, 'a'), flag(modifiers.logo, 'l'))
+}
+
+fn escape_text(text:&str)->String{
+    text.replace("\\", "\\\\").replace("\n", "\\n").replace("\r", "\\r")
+}
+
+fn unescape_text(text:&str)->String{
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next(){
+        if c != '\\'{
+            out.push(c);
+            continue;
+        }
+        match chars.next(){
+            Some('n')=>out.push('\n'),
+            Some('r')=>out.push('\r'),
+            Some(c)=>out.push(c),
+            None=>()
+        }
+    }
+    out
+}
+
+struct RecordFields<'a>{
+    fields:std::str::SplitN<'a, char>
+}
+
+impl<'a> RecordFields<'a>{
+    fn next(&mut self)->Result<&'a str, String>{
+        self.fields.next().ok_or_else(|| "Not enough fields".to_string())
+    }
+
+    fn f32(&mut self)->Result<f32, String>{
+        let field = self.next()?;
+        field.parse().map_err(|_| format!("Invalid number {}", field))
+    }
+
+    fn f64(&mut self)->Result<f64, String>{
+        let field = self.next()?;
+        field.parse().map_err(|_| format!("Invalid number {}", field))
+    }
+
+    fn usize(&mut self)->Result<usize, String>{
+        let field = self.next()?;
+        field.parse().map_err(|_| format!("Invalid integer {}", field))
+    }
+
+    fn bool(&mut self)->Result<bool, String>{
+        match self.next()?{
+            "true"=>Ok(true),
+            "false"=>Ok(false),
+            field=>Err(format!("Invalid bool {}", field))
+        }
+    }
+
+    fn vec2(&mut self)->Result<Vec2, String>{
+        Ok(Vec2{x:self.f32()?, y:self.f32()?})
+    }
+
+    fn modifiers(&mut self)->Result<KeyModifiers, String>{
+        let field = self.next()?;
+        if field.len() != 4{
+            return Err(format!("Invalid modifiers {}", field))
+        }
+        let on = |c:char| field.contains(c);
+        Ok(KeyModifiers{shift:on('s'), control:on('c'), alt:on('a'), logo:on('l')})
+    }
+
+    fn key_code(&mut self)->Result<KeyCode, String>{
+        let field = self.next()?;
+        KeyCode::from_name(field).ok_or_else(|| format!("Unknown key {}", field))
+    }
+
+    fn key_char(&mut self)->Result<char, String>{
+        let field = self.next()?;
+        field.parse::<u32>().ok().and_then(std::char::from_u32).ok_or_else(|| format!("Invalid character {}", field))
+    }
+}
+
+fn parse_recorded_event(line:&str)->Result<RecordedEvent, String>{
+    let mut fields = RecordFields{fields:line.splitn(2, ' ')};
+    let time = fields.f64()?;
+    let rest = fields.next()?;
+    // TextInput and Paste text is the remainder of the line, so only split off as many fields as the event has
+    let name = rest.split(' ').next().unwrap_or("");
+    let field_count = match name{
+        "TextInput"=>4,
+        "Paste"=>2,
+        _=>usize::MAX
+    };
+    let mut fields = RecordFields{fields:rest.splitn(field_count, ' ')};
+    fields.next()?;
+    let event = match name{
+        "AppFocus"=>Event::AppFocus(fields.bool()?),
+        "Animate"=>Event::Animate(AnimateEvent{time:time, frame:0}),
+        "Frame"=>Event::Frame(FrameEvent{time:time, frame:0}),
+        "CloseRequested"=>Event::CloseRequested,
+        "Resized"=>Event::Resized(ResizedEvent{
+            old_size:fields.vec2()?,
+            old_dpi_factor:fields.f32()?,
+            new_size:fields.vec2()?,
+            new_dpi_factor:fields.f32()?
+        }),
+        "FingerDown"=>{
+            let abs = fields.vec2()?;
+            Event::FingerDown(FingerDownEvent{
+                abs:abs,
+                rel:abs,
+                digit:fields.usize()?,
+                tap_count:fields.usize()? as u32,
+                is_touch:fields.bool()?,
+                modifiers:fields.modifiers()?,
+                time:time,
+                ..Default::default()
+            })
+        },
+        "FingerMove"=>{
+            let abs = fields.vec2()?;
+            Event::FingerMove(FingerMoveEvent{
+                abs:abs,
+                rel:abs,
+                digit:fields.usize()?,
+                is_touch:fields.bool()?,
+                modifiers:fields.modifiers()?,
+                time:time,
+                ..Default::default()
+            })
+        },
+        "FingerHover"=>{
+            let abs = fields.vec2()?;
+            Event::FingerHover(FingerHoverEvent{
+                abs:abs,
+                rel:abs,
+                modifiers:fields.modifiers()?,
+                time:time,
+                ..Default::default()
+            })
+        },
+        "FingerUp"=>{
+            let abs = fields.vec2()?;
+            Event::FingerUp(FingerUpEvent{
+                abs:abs,
+                rel:abs,
+                digit:fields.usize()?,
+                is_touch:fields.bool()?,
+                modifiers:fields.modifiers()?,
+                time:time,
+                ..Default::default()
+            })
+        },
+        "FingerScroll"=>{
+            let abs = fields.vec2()?;
+            Event::FingerScroll(FingerScrollEvent{
+                abs:abs,
+                rel:abs,
+                scroll:fields.vec2()?,
+                is_wheel:fields.bool()?,
+                modifiers:fields.modifiers()?,
+                time:time,
+                ..Default::default()
+            })
+        },
+        "KeyDown" | "KeyUp"=>{
+            let key_event = KeyEvent{
+                key_code:fields.key_code()?,
+                key_char:fields.key_char()?,
+                is_repeat:fields.bool()?,
+                modifiers:fields.modifiers()?,
+                time:time
+            };
+            if name == "KeyDown"{Event::KeyDown(key_event)}else{Event::KeyUp(key_event)}
+        },
+        "TextInput"=>Event::TextInput(TextInputEvent{
+            replace_last:fields.bool()?,
+            was_paste:fields.bool()?,
+            input:unescape_text(fields.fields.next().unwrap_or(""))
+        }),
+        "TextCopy"=>Event::TextCopy(TextCopyEvent{response:None}),
+        "Paste"=>Event::Paste(PasteEvent{
+            text:unescape_text(fields.fields.next().unwrap_or(""))
+        }),
+        _=>return Err(format!("Unknown event {}", name))
+    };
+    Ok(RecordedEvent{
+        time:time,
+        event:event
+    })
+}
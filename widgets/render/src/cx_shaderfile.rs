@@ -0,0 +1,178 @@
+// Shaders can be tweaked without recompiling the app, a .shader file holds the inside of a
+// shader_ast! block and replaces the block a widget passes to add_shader. The files are checked
+// by the platform event loops, a changed file is parsed, type checked and compiled into the same
+// shader id so the draw calls keep their instance data. The webgl platform can't read files,
+// it can still use reload_shader_source.
+
+use crate::cx::*;
+use std::fs;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+#[derive(Clone)]
+pub struct ShaderFile{
+    pub name:String,
+    pub path:String,
+    pub modified:Option<SystemTime>
+}
+
+impl Cx{
+
+    // the shader is found by the name it was added with, it doesn't have to exist yet
+    pub fn watch_shader_file(&mut self, name:&str, path:&str){
+        self.shader_files.push(ShaderFile{
+            name:name.to_string(),
+            path:path.to_string(),
+            modified:None
+        });
+    }
+
+    // watches <dir>/<shader name>.shader for every shader, like dir/Quad.shader or dir/Editor.tab.shader
+    pub fn watch_shader_dir(&mut self, dir:&str){
+        self.shader_dirs.push(dir.to_string());
+    }
+
+    // the event loops can't wait for input alone while files can change
+    pub fn is_watching_shader_files(&self)->bool{
+        self.shader_files.len() != 0 || self.shader_dirs.len() != 0
+    }
+
+    // replaces the shaders own shader_ast! block, the one added last, with the source
+    pub fn reload_shader_source(&mut self, shader_id:usize, file:&str, source:&str)->Result<(), Vec<ShDiag>>{
+        let ast = ShAst::parse(file, source).map_err(|diag| vec![diag])?;
+        let old = &self.shaders[shader_id];
+        let mut sh = old.clone();
+        sh.asts.pop();
+        sh.asts.push(ast);
+        sh.type_check()?;
+        // widgets and draw calls have laid out their data for the running shader
+        let stores = [
+            (ShVarStore::Instance, "Instance"),
+            (ShVarStore::Geometry, "Geometry"),
+            (ShVarStore::Uniform, "Uniform"),
+            (ShVarStore::UniformDl, "UniformDl"),
+            (ShVarStore::UniformCx, "UniformCx"),
+            (ShVarStore::Texture, "Texture")
+        ];
+        for (store, store_name) in &stores{
+            if sh.flat_vars(store.clone()) != old.flat_vars(store.clone()){
+                return Err(vec![ShDiag{
+                    span:ShSpan{file:file.to_string(), line:1, src:String::new()},
+                    in_fn:String::new(),
+                    msg:format!("The {} variables changed, restart the app to change them", store_name)
+                }])
+            }
+        }
+        self.shaders[shader_id] = sh;
+        Ok(())
+    }
+
+    // compile makes the platform shader for cx.shaders[shader_id], a shader that fails
+    // to load or compile is reported and the old one keeps running
+    pub fn reload_shader_files<F>(&mut self, mut compile:F)->bool
+    where F: FnMut(&mut Cx, usize)->Result<CompiledShader, SlErr>
+    {
+        for dir in &self.shader_dirs{
+            for sh in &self.shaders{
+                if !self.shader_files.iter().any(|file| file.name == sh.name){
+                    self.shader_files.push(ShaderFile{
+                        name:sh.name.clone(),
+                        path:format!("{}/{}.shader", dir, sh.name),
+                        modified:None
+                    });
+                }
+            }
+        }
+
+        let mut reloaded = false;
+        for i in 0..self.shader_files.len(){
+            let file = self.shader_files[i].clone();
+            let shader_id = if let Some(shader_id) = self.shaders.iter().position(|sh| sh.name == file.name){shader_id}else{continue};
+            // it gets picked up after the platform compiled it the first time
+            if shader_id >= self.compiled_shaders.len(){
+                continue;
+            }
+            let modified = if let Ok(meta) = fs::metadata(&file.path){meta.modified().ok()}else{continue};
+            if modified == file.modified{
+                continue;
+            }
+            self.shader_files[i].modified = modified;
+
+            let source = match fs::read_to_string(&file.path){
+                Ok(source)=>source,
+                Err(err)=>{
+                    self.log(&format!("Shader {}: Cannot read {}: {}\n", file.name, file.path, err));
+                    continue;
+                }
+            };
+            let old = self.shaders[shader_id].clone();
+            if let Err(diags) = self.reload_shader_source(shader_id, &file.path, &source){
+                for diag in diags{
+                    self.log(&format!("Shader {}: {}\n", file.name, diag));
+                }
+                continue;
+            }
+            match compile(self, shader_id){
+                Ok(csh)=>{
+                    self.compiled_shaders[shader_id] = CompiledShader{
+                        shader_id:shader_id,
+                        ..csh
+                    };
+                    reloaded = true;
+                },
+                Err(err)=>{
+                    self.log(&format!("Shader {}: {}\n", file.name, err.msg));
+                    self.shaders[shader_id] = old;
+                }
+            }
+        }
+        if reloaded{
+            self.redraw_area(Area::All);
+        }
+        reloaded
+    }
+}
+
+// Checks the shader files from a thread so an event loop can block on its events,
+// wake is called when a file changed since the event loop last reloaded them.
+pub struct ShaderFileWatcher{
+    files:Arc<Mutex<Vec<ShaderFile>>>
+}
+
+impl ShaderFileWatcher{
+    pub fn new<W>(wake:W)->ShaderFileWatcher
+    where W: Fn() + Send + 'static
+    {
+        let files = Arc::new(Mutex::new(Vec::<ShaderFile>::new()));
+        let thread_files = files.clone();
+        std::thread::spawn(move ||{
+            // a file the reload skipped keeps its old time, it only wakes the loop once
+            let mut last_seen = HashMap::new();
+            // the watcher was dropped when the thread holds the last reference
+            while Arc::strong_count(&thread_files) > 1{
+                std::thread::sleep(Duration::from_millis(100));
+                let files = thread_files.lock().unwrap().clone();
+                let mut changed = false;
+                for file in files{
+                    let modified = if let Ok(meta) = fs::metadata(&file.path){meta.modified().ok()}else{None};
+                    if modified.is_some() && modified != file.modified && last_seen.get(&file.path) != Some(&modified){
+                        changed = true;
+                    }
+                    last_seen.insert(file.path, modified);
+                }
+                if changed{
+                    wake();
+                }
+            }
+        });
+        ShaderFileWatcher{
+            files:files
+        }
+    }
+
+    // hands the thread the files with the times the last reload saw
+    pub fn update(&self, cx:&Cx){
+        *self.files.lock().unwrap() = cx.shader_files.clone();
+    }
+}
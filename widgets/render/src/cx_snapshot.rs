@@ -0,0 +1,327 @@
+// Scripted input, replays of recorded sessions and golden file checks for testing apps on the headless renderer.
+// Golden files are written when they don't exist yet, or when MAKEPAD_UPDATE_GOLDEN is set.
+
+use crate::cx::*;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptEvent{
+    FingerDown(Vec2),
+    FingerMove(Vec2),
+    FingerUp(Vec2),
+    KeyDown(KeyCode, KeyModifiers),
+    KeyUp(KeyCode, KeyModifiers),
+    TextInput(String),
+    // advances the clock in frames, playing the animations that are running
+    Wait(f64)
+}
+
+impl Cx{
+
+    // plays a script into the app the way a platform event loop would, redrawing after every step
+    pub fn play_script<F>(&mut self, mut event_handler:F, script:&[ScriptEvent])
+    where F: FnMut(&mut Cx, &mut Event),
+    {
+        for step in script{
+            if let ScriptEvent::Wait(seconds) = step{
+                let end = self.platform.time + seconds;
+                while self.platform.time < end{
+                    self.platform.time = (self.platform.time + 1. / 60.).min(end);
+                    if self.playing_anim_areas.len() != 0{
+                        let time = self.platform.time;
+                        self.call_animation_event(&mut event_handler, time);
+                    }
+                    self.headless_settle(&mut event_handler);
+                }
+                continue;
+            }
+            for mut event in self.map_script_event(step){
+                self.headless_event(&mut event_handler, &mut event);
+                if let Event::TextCopy(req) = &event{
+                    if let Some(response) = &req.response{
+                        self.copy_to_clipboard(&response);
+                    }
+                }
+            }
+        }
+    }
+
+    // plays a recorded session, drawing after every event the way the platform loops do
+    pub fn play_recording<F>(&mut self, mut event_handler:F, recording:EventRecording)
+    where F: FnMut(&mut Cx, &mut Event),
+    {
+        self.start_replay(recording);
+        self.headless_settle(&mut event_handler);
+        while self.replay_step(&mut event_handler){
+            self.headless_settle(&mut event_handler);
+        }
+    }
+
+    fn map_script_event(&mut self, step:&ScriptEvent)->Vec<Event>{
+        let time = self.platform.time;
+        match step{
+            ScriptEvent::FingerDown(pos)=>{
+                self.platform.last_mouse_pos = *pos;
+                self.platform.finger_down = true;
+                vec![Event::FingerDown(FingerDownEvent{
+                    abs:*pos,
+                    rel:*pos,
+                    rect:Rect::zero(),
+                    digit:0,
+                    tap_count:self.process_tap_count(0, *pos, time),
+                    handled:false,
+                    is_touch:false,
+                    modifiers:KeyModifiers{..Default::default()},
+                    time:time
+                })]
+            },
+            ScriptEvent::FingerMove(pos)=>{
+                self.platform.last_mouse_pos = *pos;
+                self.hover_mouse_cursor = None;
+                let mut events = Vec::new();
+                if self.platform.finger_down{
+                    events.push(Event::FingerMove(FingerMoveEvent{
+                        abs:*pos,
+                        rel:*pos,
+                        rect:Rect::zero(),
+                        digit:0,
+                        abs_start:Vec2::zero(),
+                        rel_start:Vec2::zero(),
+                        is_over:false,
+                        is_touch:false,
+                        modifiers:KeyModifiers{..Default::default()},
+                        time:time
+                    }));
+                }
+                events.push(Event::FingerHover(FingerHoverEvent{
+                    abs:*pos,
+                    rel:*pos,
+                    rect:Rect::zero(),
+                    handled:false,
+                    hover_state:HoverState::Over,
+                    modifiers:KeyModifiers{..Default::default()},
+                    time:time
+                }));
+                events
+            },
+            ScriptEvent::FingerUp(pos)=>{
+                let mut events = Vec::new();
+                if self.platform.last_mouse_pos != *pos{
+                    events = self.map_script_event(&ScriptEvent::FingerMove(*pos));
+                }
+                self.platform.finger_down = false;
+                self.down_mouse_cursor = None;
+                events.push(Event::FingerUp(FingerUpEvent{
+                    abs:*pos,
+                    rel:*pos,
+                    rect:Rect::zero(),
+                    abs_start:Vec2::zero(),
+                    rel_start:Vec2::zero(),
+                    digit:0,
+                    is_over:false,
+                    is_touch:false,
+                    modifiers:KeyModifiers{..Default::default()},
+                    time:time
+                }));
+                events
+            },
+            ScriptEvent::KeyDown(key_code, modifiers)=>{
+                // the shortcuts go through the clipboard like they do on cocoa
+                let mut events = Vec::new();
+                let is_shortcut = modifiers.logo || modifiers.control;
+                match key_code{
+                    KeyCode::KeyX | KeyCode::KeyC if is_shortcut=>events.push(Event::TextCopy(TextCopyEvent{
+                        response:None
+                    })),
+                    KeyCode::KeyV if is_shortcut=>self.request_paste(),
+                    _=>()
+                }
+                events.push(Event::KeyDown(KeyEvent{
+                    key_code:key_code.clone(),
+                    key_char:'\0',
+                    is_repeat:false,
+                    modifiers:modifiers.clone(),
+                    time:time
+                }));
+                events
+            },
+            ScriptEvent::KeyUp(key_code, modifiers)=>vec![Event::KeyUp(KeyEvent{
+                key_code:key_code.clone(),
+                key_char:'\0',
+                is_repeat:false,
+                modifiers:modifiers.clone(),
+                time:time
+            })],
+            ScriptEvent::TextInput(input)=>vec![Event::TextInput(TextInputEvent{
+                input:input.clone(),
+                replace_last:false,
+                was_paste:false
+            })],
+            ScriptEvent::Wait(_)=>vec![]
+        }
+    }
+
+    // every instance in the draw lists as text, one line per instance with its named props
+    pub fn dump_draw_lists(&mut self)->String{
+        self.compile_all_headless_shaders();
+        let mut out = String::new();
+        if self.draw_lists.len() > 0{
+            self.dump_draw_list(0, 0, &mut out);
+        }
+        out
+    }
+
+    fn dump_draw_list(&self, draw_list_id:usize, depth:usize, out:&mut String){
+        let draw_list = &self.draw_lists[draw_list_id];
+        let indent = "  ".repeat(depth);
+        out.push_str(&format!("{}view {:.1} {:.1} {:.1} {:.1}", indent, draw_list.rect.x, draw_list.rect.y, draw_list.rect.w, draw_list.rect.h));
+        if let Some(texture_id) = draw_list.texture_id{
+            out.push_str(&format!(" texture {}", texture_id));
+        }
+        out.push_str("\n");
+        for draw_call in &draw_list.draw_calls[0..draw_list.draw_calls_len]{
+            if draw_call.sub_list_id != 0{
+                self.dump_draw_list(draw_call.sub_list_id, depth + 1, out);
+                continue;
+            }
+            let csh = &self.compiled_shaders[draw_call.shader_id];
+            out.push_str(&format!("{}  {}\n", indent, self.shaders[draw_call.shader_id].name));
+            if csh.instance_slots == 0{
+                continue;
+            }
+            for instance in draw_call.instance.chunks(csh.instance_slots){
+                let props:Vec<String> = csh.named_instance_props.props.iter().map(|prop|{
+                    let values:Vec<String> = instance[prop.offset..prop.offset + prop.slots].iter().map(|v| format!("{:.2}", v)).collect();
+                    format!("{}:{}", prop.name, values.join(","))
+                }).collect();
+                out.push_str(&format!("{}    {}\n", indent, props.join(" ")));
+            }
+        }
+    }
+
+    // renders the frame and compares it against the golden image at path,
+    // on a mismatch the frame and a diff image are written next to it
+    pub fn check_golden_image(&mut self, path:&str)->Result<(), String>{
+        let image = self.render_to_image();
+        if Self::should_write_golden(){
+            return Self::write_golden(path, &image.to_pam())
+        }
+        let golden = HeadlessImage::from_pam(&Self::read_golden(path)?)?;
+        if let Some(diff) = image.diff(&golden, 2){
+            let actual_path = Path::new(path).with_extension("actual.pam");
+            let diff_path = Path::new(path).with_extension("diff.pam");
+            Self::write_golden(&actual_path.to_string_lossy(), &image.to_pam())?;
+            Self::write_golden(&diff_path.to_string_lossy(), &diff.to_pam())?;
+            return Err(format!("{} does not match the frame, see {}", path, diff_path.to_string_lossy()))
+        }
+        Ok(())
+    }
+
+    // compares dump_draw_lists against the golden text at path
+    pub fn check_golden_draw_lists(&mut self, path:&str)->Result<(), String>{
+        let dump = self.dump_draw_lists();
+        if Self::should_write_golden(){
+            return Self::write_golden(path, dump.as_bytes())
+        }
+        let golden = String::from_utf8_lossy(&Self::read_golden(path)?).to_string();
+        if golden != dump{
+            let actual_path = Path::new(path).with_extension("actual.txt");
+            Self::write_golden(&actual_path.to_string_lossy(), dump.as_bytes())?;
+            let line = golden.lines().zip(dump.lines()).position(|(a, b)| a != b).unwrap_or(golden.lines().count().min(dump.lines().count()));
+            return Err(format!("{} differs from the draw lists at line {}, see {}", path, line + 1, actual_path.to_string_lossy()))
+        }
+        Ok(())
+    }
+
+    // goldens are only ever written on request, a missing one is a failure like a mismatch
+    fn should_write_golden()->bool{
+        std::env::var("MAKEPAD_UPDATE_GOLDEN").is_ok()
+    }
+
+    fn read_golden(path:&str)->Result<Vec<u8>, String>{
+        fs::read(path).map_err(|err| format!("Cannot read {}: {}, set MAKEPAD_UPDATE_GOLDEN=1 to record it", path, err))
+    }
+
+    fn write_golden(path:&str, data:&[u8])->Result<(), String>{
+        if let Some(dir) = Path::new(path).parent(){
+            fs::create_dir_all(dir).map_err(|err| format!("Cannot create {}: {}", dir.to_string_lossy(), err))?;
+        }
+        fs::write(path, data).map_err(|err| format!("Cannot write {}: {}", path, err))
+    }
+}
+
+impl HeadlessImage{
+
+    // a netpbm PAM file, RGBA without compression, most image viewers open it
+    pub fn to_pam(&self)->Vec<u8>{
+        let mut out = format!("P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n", self.width, self.height).into_bytes();
+        for p in &self.pixels{
+            out.push(((p >> 16) & 0xff) as u8);
+            out.push(((p >> 8) & 0xff) as u8);
+            out.push((p & 0xff) as u8);
+            out.push(((p >> 24) & 0xff) as u8);
+        }
+        out
+    }
+
+    pub fn from_pam(data:&[u8])->Result<HeadlessImage, String>{
+        let mut width = 0;
+        let mut height = 0;
+        let mut offset = 0;
+        loop{
+            let end = if let Some(end) = data[offset..].iter().position(|c| *c == b'\n'){offset + end}else{
+                return Err("PAM header has no ENDHDR".to_string())
+            };
+            let line = String::from_utf8_lossy(&data[offset..end]).to_string();
+            offset = end + 1;
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()){
+                (Some("ENDHDR"), _)=>break,
+                (Some("WIDTH"), Some(v))=>width = v.parse().map_err(|_| format!("Invalid PAM width {}", v))?,
+                (Some("HEIGHT"), Some(v))=>height = v.parse().map_err(|_| format!("Invalid PAM height {}", v))?,
+                (Some("DEPTH"), Some(v)) if v != "4"=>return Err(format!("Only RGBA PAM files are supported, got depth {}", v)),
+                _=>()
+            }
+        }
+        let bytes = &data[offset..];
+        if bytes.len() < width * height * 4{
+            return Err(format!("PAM file is {} bytes short", width * height * 4 - bytes.len()))
+        }
+        Ok(HeadlessImage{
+            width:width,
+            height:height,
+            pixels:bytes.chunks_exact(4).take(width * height).map(|c|{
+                ((c[3] as u32) << 24) | ((c[0] as u32) << 16) | ((c[1] as u32) << 8) | c[2] as u32
+            }).collect()
+        })
+    }
+
+    // None when every channel is within tolerance of golden, otherwise an image
+    // with the pixels that differ in red over a faded copy of this one
+    pub fn diff(&self, golden:&HeadlessImage, tolerance:u32)->Option<HeadlessImage>{
+        let same_size = self.width == golden.width && self.height == golden.height;
+        let mut differs = !same_size;
+        let mut out = HeadlessImage{
+            width:self.width,
+            height:self.height,
+            pixels:Vec::with_capacity(self.pixels.len())
+        };
+        for (i, p) in self.pixels.iter().enumerate(){
+            let g = if same_size{golden.pixels[i]}else{!*p};
+            let over = (0..4).any(|c|{
+                let (a, b) = ((p >> (c * 8)) & 0xff, (g >> (c * 8)) & 0xff);
+                a.max(b) - a.min(b) > tolerance
+            });
+            if over{
+                differs = true;
+                out.pixels.push(0xffff0000);
+            }
+            else{
+                let fade = |v:u32| (v & 0xff) / 4;
+                out.pixels.push(0xff000000 | (fade(p >> 16) << 16) | (fade(p >> 8) << 8) | fade(*p));
+            }
+        }
+        if differs{Some(out)}else{None}
+    }
+}
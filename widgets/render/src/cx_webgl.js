@@ -79,11 +79,12 @@
 		}
 
 		init(info){
-			let pos = this.fit(4);
+			let pos = this.fit(5);
 			this.mu32[pos++] = 3;
 			this.mf32[pos++] = info.width;
 			this.mf32[pos++] = info.height;
 			this.mf32[pos++] = info.dpi_factor;
+			this.mu32[pos++] = info.is_mac? 1: 0;
 		}
 
 		resize(info){
@@ -204,6 +205,19 @@
 			this.mu32[pos++] = 16;
 		}
 
+		write_file_result(id, is_ok){
+			let pos = this.fit(3);
+			this.mu32[pos++] = 17;
+			this.mu32[pos++] = id;
+			this.mu32[pos++] = is_ok?1:0;
+		}
+
+		paste(text){
+			let pos = this.fit(1);
+			this.mu32[pos++] = 18;
+			this.send_string(text);
+		}
+
 		end(){
 			let pos = this.fit(1);
 			this.mu32[pos] = 0;
@@ -224,6 +238,7 @@
 			this.array_buffers = [];
 			this.vaos = [];
 			this.textures = [];
+			this.framebuffers = [];
 			this.resources = [];
 			this.req_anim_frame_id = 0;
 			this.text_copy_response = "";
@@ -265,7 +280,9 @@
 				this.to_wasm.init({
 					width:this.width,
 					height:this.height,
-					dpi_factor:this.dpi_factor
+					dpi_factor:this.dpi_factor,
+					// the app picks its key bindings by this, cmd or ctrl
+					is_mac:/Mac|iPhone|iPad|iPod/.test(navigator.platform)
 				})
 				this.do_wasm_block = false;
 				this.do_wasm_io();
@@ -844,7 +861,6 @@
 			//ta.setAttribute('readonly','false')
 
 			//document.addEventListener('focusout', this.onFocusOut.bind(this))
-			var was_paste = false;
 			this.neutralize_ime = false;
 			var last_len = 0;
 			ta.addEventListener('cut', e=>{
@@ -860,7 +876,10 @@
 				},0)
 			})
 			ta.addEventListener('paste', e=>{
-				was_paste = true;
+				// hand the clipboard text over as a whole, instead of through input
+				e.preventDefault();
+				this.to_wasm.paste(e.clipboardData.getData('text/plain'));
+				this.do_wasm_io();
 			})
 			ta.addEventListener('select', e=>{
 				
@@ -868,34 +887,22 @@
 
 			ta.addEventListener('input', e=>{
 				if(ta.value.length>0){
-					if(was_paste){
-						was_paste = false;
-
-						this.to_wasm.text_input({
-							was_paste:true,
-							input:ta.value.substring(last_len),
-							replace_last:false,
-						})
-						ta.value = "";
+					var replace_last = false;
+					var text_value = ta.value;
+					if(ta.value.length >= 2){ // we want the second char
+						text_value = ta.value.substring(1,2);
+						ta.value = text_value;
 					}
-					else{
-						var replace_last = false;
-						var text_value = ta.value;
-						if(ta.value.length >= 2){ // we want the second char
-							text_value = ta.value.substring(1,2);
-							ta.value = text_value;
-						}
-						else if(ta.value.length == 1 && last_len == ta.value.length){ // its an IME replace
-							replace_last = true;
-							
-						}
-						// we should send a replace last
-						this.to_wasm.text_input({
-							was_paste:false,
-							input:text_value,
-							replace_last:replace_last,
-						})						
+					else if(ta.value.length == 1 && last_len == ta.value.length){ // its an IME replace
+						replace_last = true;
+						
 					}
+					// we should send a replace last
+					this.to_wasm.text_input({
+						was_paste:false,
+						input:text_value,
+						replace_last:replace_last,
+					})						
 					this.do_wasm_io();
 				}
 				last_len = ta.value.length;
@@ -1019,6 +1026,53 @@
 			this.textures[texture_id] = gl_tex;
 		}
 		
+		begin_render_target(texture_id, width, height){
+			var gl = this.gl;
+			var gl_tex = this.textures[texture_id];
+			if(!gl_tex || gl_tex.width != width || gl_tex.height != height){
+				gl_tex = gl_tex || gl.createTexture()
+				gl.bindTexture(gl.TEXTURE_2D, gl_tex)
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MAG_FILTER, gl.LINEAR)
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_MIN_FILTER, gl.LINEAR)
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_S, gl.CLAMP_TO_EDGE)
+				gl.texParameteri(gl.TEXTURE_2D, gl.TEXTURE_WRAP_T, gl.CLAMP_TO_EDGE)
+				gl.texImage2D(gl.TEXTURE_2D, 0, gl.RGBA, width, height, 0, gl.RGBA, gl.UNSIGNED_BYTE, null);
+				gl_tex.width = width;
+				gl_tex.height = height;
+				this.textures[texture_id] = gl_tex;
+
+				let gl_fb = this.framebuffers[texture_id] || gl.createFramebuffer()
+				gl.bindFramebuffer(gl.FRAMEBUFFER, gl_fb)
+				gl.framebufferTexture2D(gl.FRAMEBUFFER, gl.COLOR_ATTACHMENT0, gl.TEXTURE_2D, gl_tex, 0)
+				this.framebuffers[texture_id] = gl_fb;
+			}
+			gl.bindFramebuffer(gl.FRAMEBUFFER, this.framebuffers[texture_id])
+			gl.viewport(0, 0, width, height)
+			this.clear(0, 0, 0, 0)
+		}
+
+		end_render_target(){
+			var gl = this.gl;
+			gl.bindFramebuffer(gl.FRAMEBUFFER, null)
+			gl.viewport(0, 0, this.canvas.width, this.canvas.height)
+		}
+
+		copy_to_clipboard(text){
+			// a copy shortcut that is still running picks it up too
+			this.text_copy_response = text;
+			if(navigator.clipboard){
+				navigator.clipboard.writeText(text).catch(e=>console.log("Cannot write clipboard", e));
+			}
+		}
+
+		request_paste(){
+			if(!navigator.clipboard) return
+			navigator.clipboard.readText().then(text=>{
+				this.to_wasm.paste(text);
+				this.do_wasm_io();
+			}).catch(e=>console.log("Cannot read clipboard", e));
+		}
+
 		alloc_vao(shader_id, vao_id, geom_ib_id, geom_vb_id, inst_vb_id){
 			let gl = this.gl;
 
@@ -1128,6 +1182,18 @@
 			}, err=>{
 			})
 		}
+
+		write_file(id, file_path, buf_ptr, buf_len){
+			// copy it out now, the wasm heap can move before the post completes
+			let data = new Uint8Array(this.memory.buffer, buf_ptr, buf_len).slice();
+			post_path(file_path, data).then(result=>{
+				this.to_wasm.write_file_result(id, true);
+				this.do_wasm_io();
+			}, err=>{
+				this.to_wasm.write_file_result(id, false);
+				this.do_wasm_io();
+			})
+		}
 	}
 
 	// array of function id's wasm can call on us, self is pointer to WasmApp
@@ -1231,6 +1297,26 @@
 		},
 		function text_copy_response_16(self){
 			self.text_copy_response = self.parse_string();
+		},
+		function write_file_17(self){
+			let id = self.mu32[self.parse++];
+			let file_path = self.parse_string();
+			self.write_file(id, file_path, self.mu32[self.parse++], self.mu32[self.parse++]);
+		},
+		function begin_render_target_18(self){
+			let texture_id = self.mu32[self.parse++];
+			let width = self.mu32[self.parse++];
+			let height = self.mu32[self.parse++];
+			self.begin_render_target(texture_id, width, height);
+		},
+		function end_render_target_19(self){
+			self.end_render_target();
+		},
+		function copy_to_clipboard_20(self){
+			self.copy_to_clipboard(self.parse_string());
+		},
+		function request_paste_21(self){
+			self.request_paste();
 		}
 	]
 	
@@ -1334,6 +1420,25 @@
 		})
 	}
 
+	function post_path(file_path, data){
+		return new Promise(function(resolve, reject){
+			var req = new XMLHttpRequest()
+			req.addEventListener("error", function(){
+				reject(file_path)
+			})
+			req.addEventListener("load", function(){
+				if(req.status !== 200){
+					return reject(req.status)
+				}
+				resolve({
+					name:file_path
+				})
+			})
+			req.open("POST", file_path)
+			req.send(data)
+		})
+	}
+
 	function watchFileChange(){
 		var req = new XMLHttpRequest()
 		req.timeout = 60000
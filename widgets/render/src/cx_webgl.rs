@@ -5,7 +5,13 @@ use crate::cx::*;
 use std::alloc;
 
 impl Cx{
-     pub fn exec_draw_list(&mut self, draw_list_id: usize){
+    // the browser tells us in the init message, the app is styled before that
+    // so it has to pick its key bindings again on Event::Construct
+    pub fn is_mac_platform(&self)->bool{
+        self.platform.is_mac
+    }
+
+     pub fn exec_draw_list(&mut self, draw_list_id: usize, uniforms_cx:&Vec<f32>){
         // tad ugly otherwise the borrow checker locks 'self' and we can't recur
         let draw_calls_len = self.draw_lists[draw_list_id].draw_calls_len;
 
@@ -13,7 +19,10 @@ impl Cx{
 
             let sub_list_id = self.draw_lists[draw_list_id].draw_calls[draw_call_id].sub_list_id;
             if sub_list_id != 0{
-                self.exec_draw_list(sub_list_id);
+                // render targets were drawn into their texture already
+                if self.draw_lists[sub_list_id].texture_id.is_none(){
+                    self.exec_draw_list(sub_list_id, uniforms_cx);
+                }
             }
             else{
                 let draw_list = &mut self.draw_lists[draw_list_id];
@@ -28,10 +37,18 @@ impl Cx{
                     // update the instance buffer data
                     draw_call.platform.check_attached_vao(csh, &mut self.platform);
 
+                    // the packed instances stay alive until the js side read them
+                    let instance = if csh.instance_packing.is_packed(){
+                        csh.instance_packing.pack(&draw_call.instance, &mut draw_call.platform.inst_packed);
+                        &draw_call.platform.inst_packed
+                    }
+                    else{
+                        &draw_call.instance
+                    };
                     self.platform.from_wasm.alloc_array_buffer(
                         draw_call.platform.inst_vb_id,
-                        draw_call.instance.len(),
-                        draw_call.instance.as_ptr() as *const f32
+                        instance.len(),
+                        instance.as_ptr() as *const f32
                     );
                 }
 
@@ -45,7 +62,7 @@ impl Cx{
                 self.platform.from_wasm.draw_call(
                     draw_call.shader_id,
                     draw_call.platform.vao_id,
-                    &self.uniforms,
+                    uniforms_cx,
                     self.redraw_id as usize, // update once a frame
                     &draw_list.uniforms,
                     draw_list_id, // update on drawlist change
@@ -57,10 +74,35 @@ impl Cx{
         }
     }
 
+    // draws a texture backed draw list into the framebuffer of its texture
+    pub fn exec_render_target(&mut self, draw_list_id:usize){
+        let (texture_id, width, height) = self.prepare_render_target(draw_list_id, true);
+        let tex = &mut self.textures_2d[texture_id];
+        tex.width = width;
+        tex.height = height;
+        tex.dirty = false;
+        self.platform.from_wasm.begin_render_target(texture_id, width, height);
+        // js reads the uniforms after we return, so every target keeps its own camera
+        let draw_list = &mut self.draw_lists[draw_list_id];
+        draw_list.platform.uni_cx.truncate(0);
+        draw_list.platform.uni_cx.extend_from_slice(&self.uniforms);
+        let uniforms_cx = mem::replace(&mut draw_list.platform.uni_cx, Vec::new());
+        self.exec_draw_list(draw_list_id, &uniforms_cx);
+        self.draw_lists[draw_list_id].platform.uni_cx = uniforms_cx;
+        self.platform.from_wasm.end_render_target();
+    }
+
     pub fn repaint(&mut self){
+        let mut targets = Vec::new();
+        self.collect_render_targets(0, &mut targets);
+        for draw_list_id in targets{
+            self.exec_render_target(draw_list_id);
+        }
         self.platform.from_wasm.clear(self.clear_color.r, self.clear_color.g, self.clear_color.b, self.clear_color.a);
         self.prepare_frame();        
-        self.exec_draw_list(0);
+        let uniforms_cx = mem::replace(&mut self.uniforms, Vec::new());
+        self.exec_draw_list(0, &uniforms_cx);
+        self.uniforms = uniforms_cx;
     }
 
     // incoming to_wasm. There is absolutely no other entrypoint
@@ -128,6 +170,7 @@ impl Cx{
                 3=>{ // init
                     self.target_size = Vec2{x:to_wasm.mf32(), y:to_wasm.mf32()};
                     self.target_dpi_factor = to_wasm.mf32();
+                    self.platform.is_mac = to_wasm.mu32() > 0;
                     self.call_event_handler(&mut event_handler, &mut Event::Construct); 
                     self.redraw_area(Area::All);
                 },
@@ -142,11 +185,17 @@ impl Cx{
                     is_animation_frame = true;
                     let time = to_wasm.mf64();
                     //log!(self, "{} o clock",time);
-                    if self.playing_anim_areas.len() != 0{
-                        self.call_animation_event(&mut event_handler, time);
+                    if self.replay.is_some(){
+                        // a replay runs on the clock of the recording
+                        self.replay_step(&mut event_handler);
                     }
-                    if self.next_frame_callbacks.len() != 0{
-                        self.call_frame_event(&mut event_handler, time);
+                    else{
+                        if self.playing_anim_areas.len() != 0{
+                            self.call_animation_event(&mut event_handler, time);
+                        }
+                        if self.next_frame_callbacks.len() != 0{
+                            self.call_frame_event(&mut event_handler, time);
+                        }
                     }
                 },
                 6=>{ // finger down
@@ -157,7 +206,7 @@ impl Cx{
                     let modifiers = unpack_key_modifier(to_wasm.mu32());
                     let time = to_wasm.mf64();
                     let tap_count = self.process_tap_count(digit, abs, time);
-                    self.call_event_handler(&mut event_handler, &mut Event::FingerDown(FingerDownEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::FingerDown(FingerDownEvent{
                         abs:abs, 
                         rel:abs,
                         rect:Rect::zero(),
@@ -179,7 +228,7 @@ impl Cx{
                     let is_touch = to_wasm.mu32()>0;
                     let modifiers = unpack_key_modifier(to_wasm.mu32());
                     let time = to_wasm.mf64();
-                    self.call_event_handler(&mut event_handler, &mut Event::FingerUp(FingerUpEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::FingerUp(FingerUpEvent{
                         abs:abs, 
                         rel:abs,
                         rect:Rect::zero(),
@@ -198,7 +247,7 @@ impl Cx{
                     let is_touch = to_wasm.mu32()>0;
                     let modifiers = unpack_key_modifier(to_wasm.mu32());
                     let time = to_wasm.mf64();
-                    self.call_event_handler(&mut event_handler, &mut Event::FingerMove(FingerMoveEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::FingerMove(FingerMoveEvent{
                         abs:abs,
                         rel:abs,
                         rect:Rect::zero(),
@@ -216,7 +265,7 @@ impl Cx{
                     self.hover_mouse_cursor = None;
                     let modifiers = unpack_key_modifier(to_wasm.mu32());
                     let time = to_wasm.mf64();
-                    self.call_event_handler(&mut event_handler, &mut Event::FingerHover(FingerHoverEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::FingerHover(FingerHoverEvent{
                         abs:abs,
                         rel:abs,
                         rect:Rect::zero(),
@@ -235,7 +284,7 @@ impl Cx{
                     let is_wheel = to_wasm.mu32() != 0;
                     let modifiers = unpack_key_modifier(to_wasm.mu32());
                     let time = to_wasm.mf64();
-                    self.call_event_handler(&mut event_handler, &mut Event::FingerScroll(FingerScrollEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::FingerScroll(FingerScrollEvent{
                         abs:abs,
                         rel:abs,
                         rect:Rect::zero(),
@@ -250,7 +299,7 @@ impl Cx{
                     let abs = Vec2{x:to_wasm.mf32(),y:to_wasm.mf32()};
                     let modifiers = unpack_key_modifier(to_wasm.mu32());
                     let time = to_wasm.mf64();
-                    self.call_event_handler(&mut event_handler, &mut Event::FingerHover(FingerHoverEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::FingerHover(FingerHoverEvent{
                         abs:abs,
                         rel:abs,
                         rect:Rect::zero(),
@@ -261,7 +310,7 @@ impl Cx{
                     }));
                 },
                 12=>{ // key_down
-                    self.call_event_handler(&mut event_handler, &mut Event::KeyDown(KeyEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::KeyDown(KeyEvent{
                         key_code:web_to_key_code(to_wasm.mu32()),
                         key_char:if let Some(c) = std::char::from_u32(to_wasm.mu32()){c}else{'?'},
                         is_repeat:to_wasm.mu32() > 0,
@@ -270,7 +319,7 @@ impl Cx{
                     }));
                 },
                 13=>{ // key up
-                    self.call_event_handler(&mut event_handler, &mut Event::KeyUp(KeyEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::KeyUp(KeyEvent{
                         key_code:web_to_key_code(to_wasm.mu32()),
                         key_char:if let Some(c) = std::char::from_u32(to_wasm.mu32()){c}else{'?'},
                         is_repeat:to_wasm.mu32() > 0,
@@ -279,7 +328,7 @@ impl Cx{
                     }));
                 },
                 14=>{ // text input
-                    self.call_event_handler(&mut event_handler, &mut Event::TextInput(TextInputEvent{
+                    self.call_input_event_handler(&mut event_handler, &mut Event::TextInput(TextInputEvent{
                         was_paste:to_wasm.mu32()>0,
                         replace_last:to_wasm.mu32()>0,
                         input:to_wasm.parse_string(),
@@ -300,7 +349,7 @@ impl Cx{
                     let mut event = Event::TextCopy(TextCopyEvent{
                         response:None
                     });
-                    self.call_event_handler(&mut event_handler, &mut event);
+                    self.call_input_event_handler(&mut event_handler, &mut event);
                     match &event{
                         Event::TextCopy(req)=>if let Some(response) = &req.response{
                             self.platform.from_wasm.text_copy_response(&response);
@@ -308,6 +357,23 @@ impl Cx{
                         _=>()
                     };
                 },
+                17=>{ // file write result
+                    let id = to_wasm.mu32() as u64;
+                    let is_ok = to_wasm.mu32() > 0;
+                    // the data buffer can be freed now that JS has posted it
+                    if let Some(index) = self.platform.file_writes.iter().position(|fw| fw.id == id){
+                        let file_write = self.platform.file_writes.remove(index);
+                        self.call_event_handler(&mut event_handler, &mut Event::FileWrite(FileWriteEvent{
+                            id:id,
+                            error:if is_ok{None}else{Some(format!("Failed to write {}", file_write.path))}
+                        }));
+                    }
+                },
+                18=>{ // paste
+                    self.call_input_event_handler(&mut event_handler, &mut Event::Paste(PasteEvent{
+                        text:to_wasm.parse_string()
+                    }));
+                },
                 _=>{
                     panic!("Message unknown")
                 }
@@ -340,7 +406,7 @@ impl Cx{
         
         // request animation frame if still need to redraw, or repaint
         // we use request animation frame for that.
-        if self.redraw_areas.len() > 0 || self.playing_anim_areas.len()> 0 || self.paint_dirty || self.next_frame_callbacks.len() != 0{
+        if self.redraw_areas.len() > 0 || self.playing_anim_areas.len()> 0 || self.paint_dirty || self.next_frame_callbacks.len() != 0 || self.replay.is_some(){
             self.platform.from_wasm.request_animation_frame();
         }
 
@@ -368,6 +434,20 @@ impl Cx{
         id
     }
 
+    pub fn write_file(&mut self, path:&str, data:&[u8])->u64{
+        let id = self.platform.file_write_id;
+        self.platform.file_write_id += 1;
+        // keep the data alive until JS reports back, it reads it straight from our heap
+        let file_write = FileWrite{
+            id:id,
+            path:path.to_string(),
+            data:data.to_vec()
+        };
+        self.platform.from_wasm.write_file(id as u32, path, file_write.data.as_ptr(), file_write.data.len());
+        self.platform.file_writes.push(file_write);
+        id
+    }
+
     pub fn show_text_ime(&mut self, x:f32, y:f32){
         self.platform.from_wasm.show_text_ime(x,y);
     }
@@ -376,6 +456,15 @@ impl Cx{
         self.platform.from_wasm.hide_text_ime();
     }
 
+    pub fn copy_to_clipboard(&mut self, text:&str){
+        self.platform.from_wasm.copy_to_clipboard(text);
+    }
+
+    // the browser answers async, with a Paste message
+    pub fn request_paste(&mut self){
+        self.platform.from_wasm.request_paste();
+    }
+
     pub fn compile_all_webgl_shaders(&mut self){
         for sh in &self.shaders{
             let csh = Self::compile_webgl_shader(self.compiled_shaders.len(), &sh, &mut self.platform);
@@ -386,7 +475,7 @@ impl Cx{
                 });
             }
             else if let Err(err) = csh{
-                self.platform.from_wasm.log(&format!("GOT ERROR: {}", err.msg));
+                self.platform.from_wasm.log(&format!("Shader {}: {}\n", sh.name, err.msg));
                 self.compiled_shaders.push(
                     CompiledShader{..Default::default()}
                 )
@@ -417,7 +506,8 @@ impl Cx{
         let csh = CompiledShader{
             shader_id:0,
             geometry_slots:ash.geometry_slots,
-            instance_slots:ash.instance_slots,
+            instance_slots:ash.instance_packing.total_slots,
+            instance_packing:ash.instance_packing.clone(),
             geom_vb_id:geom_vb_id,
             geom_ib_id:geom_ib_id,
             uniforms_cx:ash.uniforms_cx.clone(),
@@ -578,6 +668,7 @@ pub struct CompiledShader{
     pub geom_vb_id: usize,
     pub geom_ib_id: usize,
     pub instance_slots:usize,
+    pub instance_packing:InstancePacking,
     pub geometry_slots:usize,
     pub uniforms_dr: Vec<ShVar>,
     pub uniforms_dl: Vec<ShVar>,
@@ -599,6 +690,13 @@ pub struct CxShaders{
     pub shaders: Vec<Shader>,
 }
 
+#[derive(Clone)]
+pub struct FileWrite{
+    id:u64,
+    path:String,
+    data:Vec<u8>
+}
+
 // storage buffers for graphics API related platform
 #[derive(Clone)]
 pub struct CxPlatform{
@@ -612,6 +710,10 @@ pub struct CxPlatform{
     pub root_view_ptr:u32,
     pub fingers_down:Vec<bool>,
     pub file_read_id:u64,
+    pub file_write_id:u64,
+    pub file_writes:Vec<FileWrite>,
+    // from navigator.platform, only known once the init message came in
+    pub is_mac:bool
 }
 
 impl Default for CxPlatform{
@@ -626,7 +728,10 @@ impl Default for CxPlatform{
             vaos_free:Vec::new(),
             root_view_ptr:0,
             file_read_id:1,
-            fingers_down:Vec::new()
+            file_write_id:1,
+            file_writes:Vec::new(),
+            fingers_down:Vec::new(),
+            is_mac:false
         }
     }
 }
@@ -663,13 +768,15 @@ impl CxPlatform{
 
 #[derive(Clone, Default)]
 pub struct DrawListPlatform{
+    pub uni_cx:Vec<f32> // the camera of a render target
 }
 
 #[derive(Default,Clone)]
 pub struct DrawCallPlatform{
     pub resource_shader_id:Option<usize>,
     pub vao_id:usize,
-    pub inst_vb_id:usize
+    pub inst_vb_id:usize,
+    pub inst_packed:Vec<f32>
 }
 
 impl DrawCallPlatform{
@@ -1037,6 +1144,40 @@ impl FromWasm{
         self.add_string(response);
     }
 
+    pub fn write_file(&mut self, id:u32, path:&str, data:*const u8, len:usize){
+        self.fit(2);
+        self.mu32(17);
+        self.mu32(id);
+        self.add_string(path);
+        self.fit(2);
+        self.mu32(data as u32);
+        self.mu32(len as u32);
+    }
+
+    pub fn begin_render_target(&mut self, texture_id:usize, width:usize, height:usize){
+        self.fit(4);
+        self.mu32(18);
+        self.mu32(texture_id as u32);
+        self.mu32(width as u32);
+        self.mu32(height as u32);
+    }
+
+    pub fn end_render_target(&mut self){
+        self.fit(1);
+        self.mu32(19);
+    }
+
+    pub fn copy_to_clipboard(&mut self, text:&str){
+        self.fit(1);
+        self.mu32(20);
+        self.add_string(text);
+    }
+
+    pub fn request_paste(&mut self){
+        self.fit(1);
+        self.mu32(21);
+    }
+
     fn add_string(&mut self, msg:&str){
         let len = msg.chars().count();
         self.fit(len + 1);
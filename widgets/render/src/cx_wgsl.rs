@@ -0,0 +1,431 @@
+use crate::cx::*;
+
+// WGSL output for a wgpu class renderer. geometry and instances come in as vec4 attribute
+// chunks of the same slot packing the gl backend uses, the uniforms as storage buffers of
+// tightly packed floats in declaration order, and both are unpacked into private structs.
+// bindings: group 0 has _uni_cx, _uni_dl and _uni_dr at 0, 1 and 2,
+// group 1 has the sampler at 0 and the textures from 1 on
+
+#[derive(Default,Clone)]
+pub struct AssembledWgslShader{
+    pub geometry_slots:usize,
+    pub instance_slots:usize,
+    pub uniforms_dr: Vec<ShVar>,
+    pub uniforms_dl: Vec<ShVar>,
+    pub uniforms_cx: Vec<ShVar>,
+    pub texture_slots:Vec<ShVar>,
+    pub rect_instance_props: RectInstanceProps,
+    pub named_instance_props: NamedInstanceProps,
+    pub instance_packing: InstancePacking,
+    pub wgsl:String,
+}
+
+impl Cx{
+    pub fn wgsl_type(ty:&str)->String{
+        match ty.as_ref(){
+            "float"=>"f32".to_string(),
+            "int"=>"i32".to_string(),
+            "vec2"=>"vec2<f32>".to_string(),
+            "vec3"=>"vec3<f32>".to_string(),
+            "vec4"=>"vec4<f32>".to_string(),
+            "bvec2"=>"vec2<bool>".to_string(),
+            "bvec3"=>"vec3<bool>".to_string(),
+            "bvec4"=>"vec4<bool>".to_string(),
+            "mat2"=>"mat2x2<f32>".to_string(),
+            "mat3"=>"mat3x3<f32>".to_string(),
+            "mat4"=>"mat4x4<f32>".to_string(),
+            "texture2d"=>"texture_2d<f32>".to_string(),
+            ty=>ty.to_string()
+        }
+    }
+
+    pub fn wgsl_assemble_struct(name:&str, vars:&Vec<ShVar>, field:&str)->String{
+        let mut out = String::new();
+        out.push_str("struct ");
+        out.push_str(name);
+        out.push_str("{\n");
+        out.push_str(field);
+        for var in vars{
+            out.push_str("  ");
+            out.push_str(&var.name);
+            out.push_str(": ");
+            out.push_str(&Self::wgsl_type(&var.ty));
+            out.push_str(",\n");
+        };
+        out.push_str("};\n\n");
+        out
+    }
+
+    pub fn wgsl_assemble_private(name:&str, ty:&str, vars:&Vec<ShVar>)->String{
+        // wgsl has no empty structs, nothing refers to one either
+        if vars.len() == 0{
+            return String::new()
+        }
+        let mut out = Self::wgsl_assemble_struct(ty, vars, "");
+        out.push_str(&format!("var<private> {}: {};\n\n", name, ty));
+        out
+    }
+
+    pub fn wgsl_assemble_attrs(name:&str, base:&str, location:usize, slots:usize)->String{
+        let mut out = String::new();
+        if slots == 0{
+            return out
+        }
+        out.push_str("struct ");
+        out.push_str(name);
+        out.push_str("{\n");
+        let total = Self::ceil_div4(slots);
+        for i in 0..total{
+            out.push_str(&format!("  @location({}) {}{}: {},\n", location + i, base, i,
+                Self::wgsl_type(&Self::gl_assemble_vartype(i, total, slots&3))
+            ));
+        }
+        out.push_str("};\n\n");
+        out
+    }
+
+    pub fn wgsl_assemble_uniform_buf(name:&str, binding:usize, vars:&Vec<ShVar>)->String{
+        if vars.len() == 0{
+            return String::new()
+        }
+        format!("@group(0) @binding({}) var<storage, read> {}_buf: array<f32>;\n", binding, name)
+    }
+
+    // unpacks vars that are packed slot after slot, slot_src gives the source of one float
+    pub fn wgsl_assemble_unpack(sh:&Shader, target:&str, vars:&Vec<ShVar>, slot_src:&dyn Fn(usize)->String)->String{
+        let mut out = String::new();
+        let mut slot = 0;
+        for var in vars{
+            let slots = sh.get_type_slots(&var.ty);
+            out.push_str("  ");
+            out.push_str(target);
+            out.push_str(".");
+            out.push_str(&var.name);
+            out.push_str(" = ");
+            if var.ty == "float"{
+                out.push_str(&slot_src(slot));
+            }
+            else{
+                out.push_str(&Self::wgsl_type(&var.ty));
+                out.push_str("(");
+                for i in 0..slots{
+                    if i != 0{
+                        out.push_str(", ");
+                    }
+                    out.push_str(&slot_src(slot + i));
+                }
+                out.push_str(")");
+            }
+            out.push_str(";\n");
+            slot += slots;
+        }
+        out
+    }
+
+    pub fn wgsl_attr_slot(base:&str, slot:usize, total_slots:usize)->String{
+        // the last chunk is a plain f32 when a single slot is left
+        if slot>>2 == (total_slots - 1)>>2 && total_slots&3 == 1{
+            return format!("{}{}", base, slot>>2)
+        }
+        format!("{}{}.{}", base, slot>>2, ["x","y","z","w"][slot&3])
+    }
+
+    pub fn wgsl_assemble_shader(sh:&Shader)->Result<AssembledWgslShader, SlErr>{
+        sh.type_check()?;
+        // widgets write the instances of the whole shader, the optimized one can read fewer
+        let all_instances = sh.flat_vars(ShVarStore::Instance);
+        let sh = &sh.optimize();
+
+        let mut wgsl_out = assemble_structs(&SlCx{
+            depth:0,
+            lang:SlLang::Wgsl,
+            target:SlTarget::Constant,
+            defargs_fn:"".to_string(),
+            defargs_call:"".to_string(),
+            call_prefix:"".to_string(),
+            shader:sh,
+            scope:Vec::new(),
+            fn_deps:Vec::new(),
+            fn_done:Vec::new(),
+            auto_vary:Vec::new()
+        });
+
+        let texture_slots = sh.flat_vars(ShVarStore::Texture);
+        let geometries = sh.flat_vars(ShVarStore::Geometry);
+        let instances = sh.flat_vars(ShVarStore::Instance);
+        let mut varyings = sh.flat_vars(ShVarStore::Varying);
+        let locals = sh.flat_vars(ShVarStore::Local);
+        let uniforms_cx = sh.flat_vars(ShVarStore::UniformCx);
+        let uniforms_dl = sh.flat_vars(ShVarStore::UniformDl);
+        let uniforms_dr = sh.flat_vars(ShVarStore::Uniform);
+
+        let geometry_slots = sh.compute_slot_total(&geometries);
+        let instance_slots = sh.compute_slot_total(&instances);
+
+        wgsl_out.push_str(&Self::wgsl_assemble_attrs("_GeomAttr", "geomattr", 0, geometry_slots));
+        wgsl_out.push_str(&Self::wgsl_assemble_attrs("_InstAttr", "instattr", Self::ceil_div4(geometry_slots), instance_slots));
+
+        wgsl_out.push_str(&Self::wgsl_assemble_private("_geom", "_Geom", &geometries));
+        wgsl_out.push_str(&Self::wgsl_assemble_private("_inst", "_Inst", &instances));
+        wgsl_out.push_str(&Self::wgsl_assemble_private("_uni_cx", "_UniCx", &uniforms_cx));
+        wgsl_out.push_str(&Self::wgsl_assemble_private("_uni_dl", "_UniDl", &uniforms_dl));
+        wgsl_out.push_str(&Self::wgsl_assemble_private("_uni_dr", "_UniDr", &uniforms_dr));
+        wgsl_out.push_str(&Self::wgsl_assemble_private("_loc", "_Loc", &locals));
+
+        wgsl_out.push_str(&Self::wgsl_assemble_uniform_buf("_uni_cx", 0, &uniforms_cx));
+        wgsl_out.push_str(&Self::wgsl_assemble_uniform_buf("_uni_dl", 1, &uniforms_dl));
+        wgsl_out.push_str(&Self::wgsl_assemble_uniform_buf("_uni_dr", 2, &uniforms_dr));
+        wgsl_out.push_str("@group(1) @binding(0) var _sampler: sampler;\n");
+        for (i, tex) in texture_slots.iter().enumerate(){
+            wgsl_out.push_str(&format!("@group(1) @binding({}) var {}: {};\n", i + 1, tex.name, Self::wgsl_type(&tex.ty)));
+        }
+        wgsl_out.push_str("\n");
+
+        let mut const_cx = SlCx{
+            depth:0,
+            lang:SlLang::Wgsl,
+            target:SlTarget::Constant,
+            defargs_fn:"".to_string(),
+            defargs_call:"".to_string(),
+            call_prefix:"_".to_string(),
+            shader:sh,
+            scope:Vec::new(),
+            fn_deps:Vec::new(),
+            fn_done:Vec::new(),
+            auto_vary:Vec::new()
+        };
+        let consts = sh.flat_consts();
+        for cnst in &consts{
+            let const_init = assemble_const_init(cnst, &mut const_cx)?;
+            wgsl_out.push_str("const ");
+            wgsl_out.push_str(&cnst.name);
+            wgsl_out.push_str(": ");
+            wgsl_out.push_str(&Self::wgsl_type(&cnst.ty));
+            wgsl_out.push_str(" = ");
+            wgsl_out.push_str(&const_init.sl);
+            wgsl_out.push_str(";\n");
+        }
+
+        // the storage structs are module scope privates, so no default args
+        let mut vtx_cx = SlCx{
+            depth:0,
+            lang:SlLang::Wgsl,
+            target:SlTarget::Vertex,
+            defargs_fn:"".to_string(),
+            defargs_call:"".to_string(),
+            call_prefix:"_".to_string(),
+            shader:sh,
+            scope:Vec::new(),
+            fn_deps:vec!["vertex".to_string()],
+            fn_done:Vec::new(),
+            auto_vary:Vec::new()
+        };
+        let vtx_fns = assemble_fn_and_deps(sh, &mut vtx_cx)?;
+        let mut pix_cx = SlCx{
+            depth:0,
+            lang:SlLang::Wgsl,
+            target:SlTarget::Pixel,
+            defargs_fn:"".to_string(),
+            defargs_call:"".to_string(),
+            call_prefix:"_".to_string(),
+            shader:sh,
+            scope:Vec::new(),
+            fn_deps:vec!["pixel".to_string()],
+            fn_done:vtx_cx.fn_done,
+            auto_vary:Vec::new()
+        };
+
+        let pix_fns = assemble_fn_and_deps(sh, &mut pix_cx)?;
+
+        for auto in &pix_cx.auto_vary{
+            varyings.push(auto.clone());
+        }
+        wgsl_out.push_str("struct _Vary{\n");
+        wgsl_out.push_str("  @builtin(position) wgsl_position: vec4<f32>,\n");
+        for (i, vary) in varyings.iter().enumerate(){
+            wgsl_out.push_str(&format!("  @location({}) {}: {},\n", i, vary.name, Self::wgsl_type(&vary.ty)));
+        }
+        wgsl_out.push_str("};\n\n");
+        wgsl_out.push_str("var<private> _vary: _Vary;\n\n");
+
+        wgsl_out.push_str("//Vertex shader\n");
+        wgsl_out.push_str(&vtx_fns);
+        wgsl_out.push_str("//Pixel shader\n");
+        wgsl_out.push_str(&pix_fns);
+
+        let unpack_uniforms = |out:&mut String|{
+            out.push_str(&Self::wgsl_assemble_unpack(sh, "_uni_cx", &uniforms_cx, &|slot| format!("_uni_cx_buf[{}]", slot)));
+            out.push_str(&Self::wgsl_assemble_unpack(sh, "_uni_dl", &uniforms_dl, &|slot| format!("_uni_dl_buf[{}]", slot)));
+            out.push_str(&Self::wgsl_assemble_unpack(sh, "_uni_dr", &uniforms_dr, &|slot| format!("_uni_dr_buf[{}]", slot)));
+        };
+
+        wgsl_out.push_str("@vertex\n");
+        wgsl_out.push_str("fn _vertex_shader(_geom_attr: _GeomAttr");
+        if instance_slots != 0{
+            wgsl_out.push_str(", _inst_attr: _InstAttr");
+        }
+        wgsl_out.push_str(")->_Vary{\n");
+        wgsl_out.push_str(&Self::wgsl_assemble_unpack(sh, "_geom", &geometries, &|slot| Self::wgsl_attr_slot("_geom_attr.geomattr", slot, geometry_slots)));
+        wgsl_out.push_str(&Self::wgsl_assemble_unpack(sh, "_inst", &instances, &|slot| Self::wgsl_attr_slot("_inst_attr.instattr", slot, instance_slots)));
+        unpack_uniforms(&mut wgsl_out);
+        wgsl_out.push_str("  _vary.wgsl_position = _vertex();\n");
+        for auto in &pix_cx.auto_vary{
+            if let ShVarStore::Geometry = auto.store{
+                wgsl_out.push_str(&format!("  _vary.{} = _geom.{};\n", auto.name, auto.name));
+            }
+            else if let ShVarStore::Instance = auto.store{
+                wgsl_out.push_str(&format!("  _vary.{} = _inst.{};\n", auto.name, auto.name));
+            }
+        }
+        wgsl_out.push_str("  return _vary;\n");
+        wgsl_out.push_str("}\n");
+
+        wgsl_out.push_str("@fragment\n");
+        wgsl_out.push_str("fn _fragment_shader(_vary_in: _Vary)->@location(0) vec4<f32>{\n");
+        wgsl_out.push_str("  _vary = _vary_in;\n");
+        unpack_uniforms(&mut wgsl_out);
+        wgsl_out.push_str("  return _pixel();\n");
+        wgsl_out.push_str("}\n");
+
+        if sh.log != 0{
+            println!("---- WGSL shader -----\n{}",wgsl_out);
+        }
+
+        Ok(AssembledWgslShader{
+            geometry_slots:geometry_slots,
+            instance_slots:instance_slots,
+            uniforms_dr:uniforms_dr,
+            uniforms_dl:uniforms_dl,
+            uniforms_cx:uniforms_cx,
+            texture_slots:texture_slots,
+            rect_instance_props:RectInstanceProps::construct(sh, &all_instances),
+            named_instance_props:NamedInstanceProps::construct(sh, &all_instances),
+            instance_packing:InstancePacking::construct(sh, &all_instances, &instances),
+            wgsl:wgsl_out
+        })
+    }
+
+    // checks an assembled wgsl shader, see shadercheck
+    pub fn wgsl_check_shader(source:&str)->Result<(), SlErr>{
+        check_shader_source(source, &SlCheckLang{
+            types:&[
+                "bool", "i32", "u32", "f32", "vec2", "vec3", "vec4", "mat2x2", "mat3x3", "mat4x4",
+                "array", "texture_2d", "sampler"
+            ],
+            builtins:&[
+                "radians", "degrees", "sin", "cos", "tan", "asin", "acos", "atan", "atan2", "pow", "exp",
+                "exp2", "log", "log2", "sqrt", "inverseSqrt", "abs", "sign", "floor", "ceil", "fract",
+                "min", "max", "clamp", "mix", "step", "smoothstep", "saturate", "length", "distance",
+                "dot", "cross", "normalize", "faceForward", "reflect", "refract", "any", "all", "select",
+                "dpdx", "dpdy", "fwidth", "transpose", "determinant", "textureSample", "textureSampleLevel"
+            ],
+            keywords:&[
+                "fn", "var", "let", "const", "struct", "private", "storage", "uniform", "read", "function",
+                "break", "continue", "loop", "for", "while", "if", "else", "true", "false", "return",
+                "discard", "switch", "case", "default"
+            ],
+            colon_types:true
+        })
+    }
+}
+
+impl<'a> SlCx<'a>{
+    pub fn wgsl_map_call(&self, name:&str, args:&Vec<Sl>)->MapCallResult{
+        match name{
+            "sample2d"=>{
+                // explicit lod so it can sample in the vertex shader and in non uniform control flow too
+                return MapCallResult::Rewrite(
+                    format!("textureSampleLevel({}, _sampler, {}, 0.0)", args[0].sl, args[1].sl),
+                    "vec4".to_string()
+                )
+            },
+            "atan"=>{
+                if args.len() == 2{
+                    return MapCallResult::Rename("atan2".to_string())
+                }
+                return MapCallResult::None
+            },
+            "fmod"=>{
+                return MapCallResult::Rewrite(
+                    format!("({} % {})", args[0].sl, args[1].sl),
+                    args[0].ty.clone()
+                )
+            },
+            "inversesqrt"=>return MapCallResult::Rename("inverseSqrt".to_string()),
+            "faceforward"=>return MapCallResult::Rename("faceForward".to_string()),
+            "dfdx"=>return MapCallResult::Rename("dpdx".to_string()),
+            "dfdy"=>return MapCallResult::Rename("dpdy".to_string()),
+            "less_than"|"less_than_equal"|"greater_than"|"greater_than_equal"|"equal"|"not_equal"=>{
+                let op = match name{
+                    "less_than"=>"<",
+                    "less_than_equal"=>"<=",
+                    "greater_than"=>">",
+                    "greater_than_equal"=>">=",
+                    "equal"=>"==",
+                    _=>"!="
+                };
+                let ty = match args[0].ty.as_ref(){
+                    "vec2"=>"bvec2",
+                    "vec3"=>"bvec3",
+                    "vec4"=>"bvec4",
+                    _=>"bool"
+                };
+                return MapCallResult::Rewrite(
+                    format!("({} {} {})", args[0].sl, op, args[1].sl),
+                    ty.to_string()
+                )
+            },
+            "not"=>{
+                return MapCallResult::Rewrite(
+                    format!("!({})", args[0].sl),
+                    args[0].ty.clone()
+                )
+            },
+            "color"=>{
+                let col = color(&args[0].sl);
+                return MapCallResult::Rewrite(
+                    format!("vec4<f32>({},{},{},{})", col.r, col.g, col.b, col.a),
+                    "vec4".to_string()
+                );
+            },
+            _=>return MapCallResult::None
+        }
+    }
+
+    pub fn wgsl_map_type(&self, ty:&str)->String{
+        Cx::wgsl_type(ty)
+    }
+
+    pub fn wgsl_map_var(&mut self, var:&ShVar)->String{
+        match var.store{
+            ShVarStore::Uniform=>return format!("_uni_dr.{}", var.name),
+            ShVarStore::UniformDl=>return format!("_uni_dl.{}", var.name),
+            ShVarStore::UniformCx=>return format!("_uni_cx.{}", var.name),
+            ShVarStore::Instance=>{
+                if let SlTarget::Pixel = self.target{
+                    if self.auto_vary.iter().find(|v|v.name == var.name).is_none(){
+                        self.auto_vary.push(var.clone());
+                    }
+                    return format!("_vary.{}",var.name);
+                }
+                else{
+                    return format!("_inst.{}", var.name);
+                }
+            },
+            ShVarStore::Geometry=>{
+                if let SlTarget::Pixel = self.target{
+                    if self.auto_vary.iter().find(|v|v.name == var.name).is_none(){
+                        self.auto_vary.push(var.clone());
+                    }
+                    return format!("_vary.{}",var.name);
+                }
+                else{
+                    return format!("_geom.{}", var.name);
+                }
+            },
+            ShVarStore::Texture=>return var.name.clone(),
+            ShVarStore::Local=>return format!("_loc.{}",var.name),
+            ShVarStore::Varying=>return format!("_vary.{}",var.name),
+        }
+    }
+}
@@ -124,8 +124,8 @@ pub struct FileReadEvent{
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct FileWriteEvent{
-    id:u64,
-    error:Option<String>
+    pub id:u64,
+    pub error:Option<String>
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -156,6 +156,12 @@ pub struct TextCopyEvent{
     pub response:Option<String>
 }
 
+// the clipboard text, sent after a paste shortcut or cx.request_paste
+#[derive(Clone, Debug, PartialEq)]
+pub struct PasteEvent{
+    pub text:String
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Event{
     None,
@@ -179,7 +185,8 @@ pub enum Event{
     KeyDown(KeyEvent),
     KeyUp(KeyEvent),
     TextInput(TextInputEvent),
-    TextCopy(TextCopyEvent)
+    TextCopy(TextCopyEvent),
+    Paste(PasteEvent)
 }
 
 impl Default for Event{
@@ -272,6 +279,11 @@ impl Event{
                     );
                 }
             },
+            Event::Paste(_)=>{
+                if area == cx.key_focus{
+                    return self.clone();
+                }
+            },
             Event::Animate(_)=>{
                 for anim in &cx.playing_anim_areas{
                     if anim.area == area{
@@ -584,3 +596,38 @@ pub enum KeyCode {
     Unknown
 }
 
+impl KeyCode{
+    // the key with this variant name, like KeyA or ArrowUp
+    pub fn from_name(name:&str)->Option<KeyCode>{
+        KEY_CODES.iter().find(|key_code| format!("{:?}", key_code) == name).cloned()
+    }
+}
+
+pub const KEY_CODES:[KeyCode;105] = [
+    KeyCode::Escape, KeyCode::Backtick,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Minus, KeyCode::Equals, KeyCode::Backspace, KeyCode::Tab,
+    KeyCode::KeyQ, KeyCode::KeyW, KeyCode::KeyE, KeyCode::KeyR, KeyCode::KeyT, KeyCode::KeyY,
+    KeyCode::KeyU, KeyCode::KeyI, KeyCode::KeyO, KeyCode::KeyP,
+    KeyCode::LBracket, KeyCode::RBracket, KeyCode::Return,
+    KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD, KeyCode::KeyF, KeyCode::KeyG, KeyCode::KeyH,
+    KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL,
+    KeyCode::Semicolon, KeyCode::Quote, KeyCode::Backslash,
+    KeyCode::KeyZ, KeyCode::KeyX, KeyCode::KeyC, KeyCode::KeyV, KeyCode::KeyB, KeyCode::KeyN, KeyCode::KeyM,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Slash,
+    KeyCode::LeftControl, KeyCode::LeftAlt, KeyCode::LeftShift, KeyCode::LeftLogo,
+    KeyCode::RightControl, KeyCode::RightShift, KeyCode::RightAlt, KeyCode::RightLogo,
+    KeyCode::Space, KeyCode::Capslock,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::PrintScreen, KeyCode::Scrolllock, KeyCode::Pause,
+    KeyCode::Insert, KeyCode::Delete, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::NumpadEquals, KeyCode::NumpadSubtract, KeyCode::NumpadAdd, KeyCode::NumpadDecimal,
+    KeyCode::NumpadMultiply, KeyCode::NumpadDivide, KeyCode::Numlock, KeyCode::NumpadEnter,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Unknown
+];
+
@@ -7,21 +7,26 @@ mod cx_ogl;
 #[cfg(feature = "mtl")]
 mod cx_mtl; 
 #[cfg(feature = "mtl")]
-mod cx_mtlsl; 
-#[cfg(feature = "mtl")]
 mod cx_cocoa; 
 
 #[cfg(feature = "webgl")]
 mod cx_webgl; 
 
-#[cfg(any(feature = "webgl", feature = "ogl"))]
-mod cx_glsl; 
+#[cfg(feature = "headless")]
+mod cx_headless; 
+#[cfg(feature = "headless")]
+mod cx_cpusl; 
+#[cfg(feature = "headless")]
+mod cx_snapshot; 
+
 
-#[cfg(any(feature = "ogl", feature="mtl"))]
+#[cfg(any(feature = "ogl", feature="mtl", feature="headless"))]
 mod cx_desktop; 
 
 // shared modules
 mod cx; 
+mod cx_record;
+mod cx_shaderfile;
 mod cx_turtle;
 mod cx_fonts;
 mod cx_cursor;
@@ -34,11 +39,23 @@ mod shader;
 mod area;
 mod view;
 mod shadergen;
+mod cx_glsl;
+mod cx_mtlsl;
+mod cx_wgsl;
+mod shadercheck;
+mod shadertypecheck;
+// the type rules shader_ast! checks with when it expands
+#[path = "../shader_ast/shader_ast_impl/src/tyrules.rs"]
+mod shadertyrules;
+mod shaderopt;
+mod shaderparse;
 mod quad;
+mod blit;
 mod text;
 mod events;
 
 pub use crate::cx::*;
 pub use crate::quad::*;
+pub use crate::blit::*;
 pub use crate::text::*;
 pub use crate::elements::*;
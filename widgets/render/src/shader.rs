@@ -6,9 +6,19 @@ pub use crate::math::*;
 pub use crate::colors::*;
 use std::hash::{Hash, Hasher};
 
+// where a piece of shader code came from, shader_ast! fills it in
+// so diagnostics can point back at the rust source
+#[derive(Clone, Default, Hash, PartialEq)]
+pub struct ShSpan{
+    pub file:String,
+    pub line:u32,
+    pub src:String
+}
+
 // The AST block
 #[derive(Clone, Hash, PartialEq)]
 pub struct ShAst{
+    pub span:ShSpan,
     pub types:Vec<ShType>,
     pub vars:Vec<ShVar>,
     pub consts:Vec<ShConst>,
@@ -107,7 +117,11 @@ pub enum ShExpr{
     ShForLoop(ShForLoop),
     ShReturn(ShReturn),
     ShBreak(ShBreak),
-    ShContinue(ShContinue)
+    ShContinue(ShContinue),
+    ShMatch(ShMatch),
+    ShMethodCall(ShMethodCall),
+    ShStructLit(ShStructLit),
+    ShArrayLit(ShArrayLit)
 }
 
 #[derive(Clone, Hash, PartialEq)]
@@ -270,7 +284,9 @@ pub enum ShStmt{
 
 #[derive(Clone, Hash, PartialEq)]
 pub struct ShBlock{
-    pub stmts:Vec<Box<ShStmt>>
+    pub stmts:Vec<Box<ShStmt>>,
+    // one per statement
+    pub spans:Vec<ShSpan>
 }
 
 #[derive(Clone, Hash, PartialEq)]
@@ -313,6 +329,43 @@ pub struct ShBreak{
 pub struct ShContinue{
 }
 
+#[derive(Clone, Hash, PartialEq)]
+pub struct ShMatchArm{
+    // an empty pattern list is the _ arm
+    pub pats:Vec<i64>,
+    pub body:ShBlock
+}
+
+#[derive(Clone, Hash, PartialEq)]
+pub struct ShMatch{
+    pub expr:Box<ShExpr>,
+    pub arms:Vec<ShMatchArm>
+}
+
+#[derive(Clone, Hash, PartialEq)]
+pub struct ShMethodCall{
+    pub base:Box<ShExpr>,
+    pub method:String,
+    pub args:Vec<Box<ShExpr>>
+}
+
+#[derive(Clone, Hash, PartialEq)]
+pub struct ShStructLitField{
+    pub name:String,
+    pub expr:Box<ShExpr>
+}
+
+#[derive(Clone, Hash, PartialEq)]
+pub struct ShStructLit{
+    pub ty:String,
+    pub fields:Vec<ShStructLitField>
+}
+
+#[derive(Clone, Hash, PartialEq)]
+pub struct ShArrayLit{
+    pub elems:Vec<Box<ShExpr>>
+}
+
 #[derive(Clone, Hash, PartialEq)]
 pub struct ShLet{
     pub name:String,
@@ -425,6 +478,39 @@ impl Shader{
         None
     }
 
+    // array types are written [float;4]
+    pub fn array_type(ty:&str)->Option<(String, usize)>{
+        if !ty.starts_with('[') || !ty.ends_with(']'){
+            return None
+        }
+        let inner = &ty[1..ty.len()-1];
+        let semi = inner.rfind(';')?;
+        let len = inner[semi+1..].trim().parse::<usize>().ok()?;
+        Some((inner[0..semi].trim().to_string(), len))
+    }
+
+    // structs declared in the shader, in the order they can be declared in
+    pub fn flat_structs(&self)->Vec<ShType>{
+        let mut ret:Vec<ShType> = Vec::new();
+        let mut todo = Vec::new();
+        for ast in self.asts.iter(){
+            for shtype in &ast.types{
+                if !shtype.prim{
+                    todo.push(shtype.clone());
+                }
+            }
+        }
+        while todo.len() != 0{
+            let ready = todo.iter().position(|shtype| shtype.fields.iter().all(|field|{
+                !todo.iter().any(|other| other.name == field.ty)
+            }));
+            // recursive structs are reported by the type checker
+            let i = if let Some(i) = ready{i}else{0};
+            ret.push(todo.remove(i));
+        }
+        ret
+    }
+
     pub fn get_type_slots(&self, name:&str)->usize{
         if let Some(ty) = self.find_type(name){
             return ty.slots;
@@ -443,6 +529,7 @@ impl Shader{
     pub fn def_builtins(&mut self){
         self.asts.push(
             ShAst{
+                span:ShSpan{file:file!().to_string(), line:line!(), src:String::new()},
                 types:vec![
                     ShType{name:"float".to_string(), slots:1, prim:true, fields:Vec::new()},
                     ShType{name:"int".to_string(), slots:1, prim:true, fields:Vec::new()},
@@ -763,7 +850,7 @@ impl Shader{
 
             fn df_hsv2rgb(c:vec4)->vec4 { //http://gamedev.stackexchange.com/questions/59797/glsl-shader-change-hue-saturation-brightness
                 let K:vec4 = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
-                let p:vec4 = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
+                let p:vec3 = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
                 return vec4(c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y), c.w);
             }
 
@@ -0,0 +1,165 @@
+// A light checker for generated shader source, it doesn't compile anything but catches what
+// codegen tends to break: unbalanced brackets, calls to functions that don't exist in the
+// target language (an unmapped shader AST builtin) and declarations with types it doesn't have
+
+use crate::cx::*;
+
+pub struct SlCheckLang{
+    pub types:&'static [&'static str],
+    pub builtins:&'static [&'static str],
+    pub keywords:&'static [&'static str],
+    // declarations are written name:type, as in wgsl
+    pub colon_types:bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum SlTok{
+    Ident(String),
+    Number,
+    Punct(char)
+}
+
+fn tokenize(source:&str)->Result<Vec<(SlTok, usize)>, SlErr>{
+    let chars:Vec<char> = source.chars().collect();
+    let mut toks = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < chars.len(){
+        let c = chars[i];
+        let next = if i + 1 < chars.len(){chars[i + 1]}else{'\0'};
+        if c == '\n'{
+            line += 1;
+            i += 1;
+        }
+        else if c.is_whitespace(){
+            i += 1;
+        }
+        else if c == '/' && next == '/' || c == '#'{
+            while i < chars.len() && chars[i] != '\n'{
+                i += 1;
+            }
+        }
+        else if c == '[' && next == '['{
+            // metal attributes like [[buffer(0)]] aren't code
+            while i < chars.len() && !(chars[i] == ']' && i + 1 < chars.len() && chars[i + 1] == ']'){
+                i += 1;
+            }
+            i += 2;
+        }
+        else if c == '/' && next == '*'{
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && i + 1 < chars.len() && chars[i + 1] == '/'){
+                if chars[i] == '\n'{
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len(){
+                return Err(SlErr{msg:format!("Unterminated comment at line {}", line)})
+            }
+            i += 2;
+        }
+        else if c.is_alphabetic() || c == '_'{
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_'){
+                i += 1;
+            }
+            toks.push((SlTok::Ident(chars[start..i].iter().collect()), line));
+        }
+        else if c.is_ascii_digit() || c == '.' && next.is_ascii_digit(){
+            // also eats exponents and suffixes like 1.0e-5 or 2u
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.' ||
+                (chars[i] == '-' || chars[i] == '+') && (chars[i - 1] == 'e' || chars[i - 1] == 'E')){
+                i += 1;
+            }
+            toks.push((SlTok::Number, line));
+        }
+        else{
+            toks.push((SlTok::Punct(c), line));
+            i += 1;
+        }
+    }
+    Ok(toks)
+}
+
+pub fn check_shader_source(source:&str, lang:&SlCheckLang)->Result<(), SlErr>{
+    let toks = tokenize(source)?;
+    let ident = |i:usize|->Option<&str>{
+        if let Some((SlTok::Ident(name), _)) = toks.get(i){Some(name)}else{None}
+    };
+    let punct = |i:usize|->Option<char>{
+        if let Some((SlTok::Punct(c), _)) = toks.get(i){Some(*c)}else{None}
+    };
+
+    // brackets have to nest
+    let mut stack = Vec::new();
+    for (tok, line) in &toks{
+        if let SlTok::Punct(c) = tok{
+            match c{
+                '(' | '[' | '{'=>stack.push((*c, *line)),
+                ')' | ']' | '}'=>{
+                    let open = match c{')'=>'(', ']'=>'[', _=>'{'};
+                    match stack.pop(){
+                        Some((o, _)) if o == open=>(),
+                        Some((o, oline))=>return Err(SlErr{msg:format!("{} at line {} closes {} from line {}", c, line, o, oline)}),
+                        None=>return Err(SlErr{msg:format!("Unmatched {} at line {}", c, line)})
+                    }
+                },
+                _=>()
+            }
+        }
+    }
+    if let Some((o, oline)) = stack.pop(){
+        return Err(SlErr{msg:format!("Unclosed {} from line {}", o, oline)})
+    }
+
+    // the structs and functions the source declares itself
+    let mut structs = Vec::new();
+    let mut fns = Vec::new();
+    for i in 0..toks.len(){
+        if ident(i) == Some("struct"){
+            if let Some(name) = ident(i + 1){
+                structs.push(name.to_string());
+            }
+        }
+        if let (Some(ty), Some(name), Some('(')) = (ident(i), ident(i + 1), punct(i + 2)){
+            if !lang.keywords.contains(&ty) || ty == "fn"{
+                fns.push(name.to_string());
+            }
+        }
+    }
+    let is_type = |name:&str| lang.types.contains(&name) || structs.iter().any(|s| s == name);
+
+    for (i, (_, line)) in toks.iter().enumerate(){
+        let name = if let Some(name) = ident(i){name}else{continue};
+        if lang.keywords.contains(&name){
+            continue;
+        }
+        let prev_ident = if i > 0{ident(i - 1)}else{None};
+        // a type after name: or after ->
+        if lang.colon_types && i > 1 && (punct(i - 1) == Some(':') || punct(i - 1) == Some('>') && punct(i - 2) == Some('-')){
+            if !is_type(name){
+                return Err(SlErr{msg:format!("Unknown type {} at line {}", name, line)})
+            }
+            continue;
+        }
+        // methods, namespaced names and attributes belong to someone else
+        if i > 0 && (punct(i - 1) == Some('.') || punct(i - 1) == Some(':') || punct(i - 1) == Some('@')){
+            continue;
+        }
+        if punct(i + 1) == Some('('){
+            let is_decl = if let Some(prev) = prev_ident{!lang.keywords.contains(&prev)}else{false};
+            if !is_decl && !is_type(name) && !lang.builtins.contains(&name) && !fns.iter().any(|f| f == name){
+                return Err(SlErr{msg:format!("Call to unknown function {} at line {}", name, line)})
+            }
+        }
+        // a declaration is a type followed by a name
+        if let Some(next) = ident(i + 1){
+            let decl_end = matches!(punct(i + 2), Some(';') | Some('=') | Some(',') | Some(')') | Some('[') | Some('('));
+            if decl_end && !lang.keywords.contains(&next) && !is_type(name){
+                return Err(SlErr{msg:format!("Unknown type {} for {} at line {}", name, next, line)})
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,4 +1,5 @@
-// Shared shader-compiler code for generating GLSL and Metal shading language
+// Shared shader-compiler code for generating GLSL, Metal shading language and WGSL
+// on every platform, so the generated code can be checked without a gpu
 
 use crate::cx::*;
 
@@ -18,6 +19,14 @@ pub struct SlDecl{
     pub ty:String
 }
 
+// the shading language the code is generated for, see cx_glsl, cx_mtlsl and cx_wgsl
+#[derive(Clone, PartialEq)]
+pub enum SlLang{
+    Glsl,
+    Mtlsl,
+    Wgsl
+}
+
 #[derive(Clone)]
 pub enum SlTarget{
     Pixel,
@@ -27,6 +36,7 @@ pub enum SlTarget{
 
 pub struct SlCx<'a>{
     pub depth:usize,
+    pub lang:SlLang,
     pub target:SlTarget,
     pub defargs_fn:String,
     pub defargs_call:String,
@@ -57,6 +67,50 @@ impl<'a> SlCx<'a>{
         }
         Err(SlErr{msg:format!("Cannot find type {}", name)})
     }
+
+    pub fn map_call(&self, name:&str, args:&Vec<Sl>)->MapCallResult{
+        match self.lang{
+            SlLang::Glsl=>self.gl_map_call(name, args),
+            SlLang::Mtlsl=>self.mtl_map_call(name, args),
+            SlLang::Wgsl=>self.wgsl_map_call(name, args)
+        }
+    }
+
+    pub fn map_type(&self, ty:&str)->String{
+        if let Some((elem, len)) = Shader::array_type(ty){
+            return match self.lang{
+                SlLang::Glsl=>format!("{}[{}]", self.map_type(&elem), len),
+                _=>format!("array<{}, {}>", self.map_type(&elem), len)
+            }
+        }
+        match self.lang{
+            SlLang::Glsl=>self.gl_map_type(ty),
+            SlLang::Mtlsl=>self.mtl_map_type(ty),
+            SlLang::Wgsl=>self.wgsl_map_type(ty)
+        }
+    }
+
+    // a typed name for a let, fn arg or struct field, glsl puts the array size after the name
+    pub fn decl(&self, ty:&str, name:&str)->String{
+        match self.lang{
+            SlLang::Glsl=>if let Some((elem, len)) = Shader::array_type(ty){
+                format!("{} {}[{}]", self.map_type(&elem), name, len)
+            }
+            else{
+                format!("{} {}", self.map_type(ty), name)
+            },
+            SlLang::Mtlsl=>format!("{} {}", self.map_type(ty), name),
+            SlLang::Wgsl=>format!("{}:{}", name, self.map_type(ty))
+        }
+    }
+
+    pub fn map_var(&mut self, var:&ShVar)->String{
+        match self.lang{
+            SlLang::Glsl=>self.gl_map_var(var),
+            SlLang::Mtlsl=>self.mtl_map_var(var),
+            SlLang::Wgsl=>self.wgsl_map_var(var)
+        }
+    }
 }
 
 impl ShExpr{
@@ -69,7 +123,7 @@ impl ShExpr{
             ShExpr::ShBinary(x)=>x.sl(cx),
             ShExpr::ShUnary(x)=>x.sl(cx),
             ShExpr::ShAssignOp(x)=>x.sl(cx),
-            ShExpr::ShIf(x)=>x.sl(cx),
+            ShExpr::ShIf(x)=>x.sl_value(cx),
             ShExpr::ShWhile(x)=>x.sl(cx),
             ShExpr::ShForLoop(x)=>x.sl(cx),
             ShExpr::ShBlock(x)=>x.sl(cx),
@@ -79,6 +133,29 @@ impl ShExpr{
             ShExpr::ShReturn(x)=>x.sl(cx),
             ShExpr::ShBreak(x)=>x.sl(cx),
             ShExpr::ShContinue(x)=>x.sl(cx),
+            ShExpr::ShMatch(x)=>x.sl_value(cx),
+            ShExpr::ShMethodCall(x)=>x.sl(cx),
+            ShExpr::ShStructLit(x)=>x.sl(cx),
+            ShExpr::ShArrayLit(_)=>Err(SlErr{
+                msg:"Array literals can only initialize a let".to_string()
+            })
+        }
+    }
+
+    // if and match are statements in a block and values everywhere else
+    pub fn sl_stmt(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        match self{
+            ShExpr::ShIf(x)=>x.sl(cx),
+            ShExpr::ShMatch(x)=>x.sl(cx),
+            _=>self.sl(cx)
+        }
+    }
+
+    // a value if or match takes the value of its else branch or arm
+    fn sl_branch(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        match self{
+            ShExpr::ShBlock(x)=>x.sl_value(cx),
+            _=>self.sl(cx)
         }
     }
 }
@@ -209,18 +286,25 @@ impl ShIndex{
     pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
         let base = self.base.sl(cx)?;
         let index = self.index.sl(cx)?;
-        // limit base type to vec2/3/4
-        if base.ty != "vec2" && base.ty != "vec3" && base.ty != "vec4"{
-             Err(SlErr{
-                msg:format!("index on unsupported type {}", base.ty)
-            })
-        }
-        else {
-            Ok(Sl{
-                sl:format!("{}[{}]", base.sl, index.sl),
-                ty:"float".to_string()
-            })
+        // arrays give their element, matrices a column and vectors a float
+        let ty = if let Some((elem, _)) = Shader::array_type(&base.ty){
+            elem
         }
+        else{
+            match base.ty.as_ref(){
+                "vec2" | "vec3" | "vec4"=>"float".to_string(),
+                "mat2"=>"vec2".to_string(),
+                "mat3"=>"vec3".to_string(),
+                "mat4"=>"vec4".to_string(),
+                _=>return Err(SlErr{
+                    msg:format!("index on unsupported type {}", base.ty)
+                })
+            }
+        };
+        Ok(Sl{
+            sl:format!("{}[{}]", base.sl, index.sl),
+            ty:ty
+        })
     }
 }
 
@@ -235,6 +319,19 @@ impl ShAssign{
                 msg:format!("Left type {} not the same as right {} in assign {}={}", left.ty, right.ty, left.sl, right.sl)
             })
         }
+        else if Shader::array_type(&left.ty).is_some(){
+            Err(SlErr{
+                msg:format!("Arrays can't be assigned whole, assign the elements of {}", left.sl)
+            })
+        }
+        else if let (SlLang::Wgsl, ShExpr::ShAssign(inner)) = (&cx.lang, &*self.right){
+            // wgsl assignments are statements, so a=b=c becomes b=c; a=b
+            let inner_left = inner.left.sl(cx)?;
+            Ok(Sl{
+                sl:format!("{};\n{}{} = {}", right.sl, "  ".repeat(cx.depth), left.sl, inner_left.sl),
+                ty:left.ty
+            })
+        }
         else{
             Ok(Sl{
                 sl:format!("{} = {}", left.sl, right.sl),
@@ -298,6 +395,28 @@ impl ShBinary{
                     ty:left.ty
                 })
             }
+            else if let (ShBinOp::Mul, true) = (&self.op, left.ty.starts_with("mat")){
+                // mat * vec gives a vec, mat * float a mat
+                let ty = match (left.ty.as_ref(), right.ty.as_ref()){
+                    ("mat2", "vec2")=>"vec2",
+                    ("mat3", "vec3")=>"vec3",
+                    ("mat4", "vec4")=>"vec4",
+                    (_, "float")=>&left.ty,
+                    _=>return Err(SlErr{
+                        msg:format!("Left type {} not the same as right {} in binary op {}{}{}", left.ty, right.ty, left.sl, self.op.to_string(), right.sl)
+                    })
+                };
+                Ok(Sl{
+                    sl:format!("{}{}{}", left.sl, self.op.to_string(), right.sl),
+                    ty:ty.to_string()
+                })
+            }
+            else if let (ShBinOp::Mul, "float", true) = (&self.op, left.ty.as_ref(), right.ty.starts_with("mat")){
+                Ok(Sl{
+                    sl:format!("{}{}{}", left.sl, self.op.to_string(), right.sl),
+                    ty:right.ty
+                })
+            }
             else{
                 Err(SlErr{
                     msg:format!("Left type {} not the same as right {} in binary op {}{}{}", left.ty, right.ty, left.sl, self.op.to_string(), right.sl)
@@ -348,11 +467,11 @@ impl ShBlock{
                     sl.push_str(&out.sl);
                 },
                 ShStmt::ShExpr(stmt) => {
-                    let out = stmt.sl(cx)?;
+                    let out = stmt.sl_stmt(cx)?;
                     sl.push_str(&out.sl);
                 }
                 ShStmt::ShSemi(stmt) => {
-                    let out = stmt.sl(cx)?;
+                    let out = stmt.sl_stmt(cx)?;
                     sl.push_str(&out.sl);
                 }
             }
@@ -365,6 +484,38 @@ impl ShBlock{
             ty:"void".to_string()
         })
     }
+
+    // the branch of an if or the arm of a match used as a value
+    pub fn sl_value(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        if self.stmts.len() == 1{
+            if let ShStmt::ShExpr(expr) = &*self.stmts[0]{
+                return expr.sl(cx)
+            }
+        }
+        Err(SlErr{
+            msg:"An if or match used as a value needs a single expression in every branch".to_string()
+        })
+    }
+
+    // a break or continue that leaves a loop around the block
+    fn exits_loop(&self)->bool{
+        self.stmts.iter().any(|stmt| match &**stmt{
+            ShStmt::ShLet(_)=>false,
+            ShStmt::ShExpr(expr) | ShStmt::ShSemi(expr)=>expr.exits_loop()
+        })
+    }
+}
+
+impl ShExpr{
+    fn exits_loop(&self)->bool{
+        match self{
+            ShExpr::ShBreak(_) | ShExpr::ShContinue(_)=>true,
+            ShExpr::ShBlock(x)=>x.exits_loop(),
+            ShExpr::ShIf(x)=>x.then_branch.exits_loop() || x.else_branch.as_ref().map_or(false, |e| e.exits_loop()),
+            ShExpr::ShMatch(x)=>x.arms.iter().any(|arm| arm.body.exits_loop()),
+            _=>false
+        }
+    }
 }
 
 impl ShCall{
@@ -496,6 +647,12 @@ impl ShCall{
         else{
             // its a constructor call
             if let Some(glty) = cx.shader.find_type(&self.call){
+                // metal makes structs with braces, the literal generates the right one
+                if !glty.prim{
+                    return Err(SlErr{
+                        msg:format!("Use a struct literal {}{{..}} to make a {}", self.call, self.call)
+                    })
+                }
                 out.push_str(&cx.map_type(&self.call));
                 out.push_str("(");
                 // TODO check args
@@ -534,7 +691,7 @@ impl ShIf{
         
         out.push_str(&then.sl);
         if let Some(else_branch) = &self.else_branch{
-            let else_gl = else_branch.sl(cx)?;
+            let else_gl = else_branch.sl_stmt(cx)?;
             out.push_str("else ");
             out.push_str(&else_gl.sl);
         }
@@ -544,6 +701,245 @@ impl ShIf{
             ty:"void".to_string()
         })
     }
+
+    // an if used as a value becomes a ternary, wgsl has select for that
+    pub fn sl_value(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let cond = self.cond.sl(cx)?;
+        let then = self.then_branch.sl_value(cx)?;
+        let else_branch = if let Some(else_branch) = &self.else_branch{else_branch}else{
+            return Err(SlErr{
+                msg:"An if used as a value needs an else".to_string()
+            })
+        };
+        let else_sl = else_branch.sl_branch(cx)?;
+        if then.ty != else_sl.ty{
+            return Err(SlErr{
+                msg:format!("If branches have different types {} and {}", then.ty, else_sl.ty)
+            })
+        }
+        Ok(Sl{
+            sl:sl_select(cx, &cond.sl, &then.sl, &else_sl.sl),
+            ty:then.ty
+        })
+    }
+}
+
+fn sl_select(cx:&SlCx, cond:&str, then:&str, else_sl:&str)->String{
+    if cx.lang == SlLang::Wgsl{
+        format!("select({}, {}, {})", else_sl, then, cond)
+    }
+    else{
+        format!("({} ? {} : {})", cond, then, else_sl)
+    }
+}
+
+impl ShMatch{
+    // the arms before the _ arm, the _ arm and the condition of each arm
+    fn sl_arms(&self, scrutinee:&str)->(Vec<(String, &ShMatchArm)>, Option<&ShMatchArm>){
+        let mut arms = Vec::new();
+        for arm in &self.arms{
+            if arm.pats.len() == 0{
+                return (arms, Some(arm))
+            }
+            let cond:Vec<String> = arm.pats.iter().map(|pat| format!("{} == {}", scrutinee, pat)).collect();
+            arms.push((cond.join(" || "), arm));
+        }
+        (arms, None)
+    }
+
+    fn sl_scrutinee(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let scrutinee = self.expr.sl(cx)?;
+        if scrutinee.ty != "int"{
+            return Err(SlErr{
+                msg:format!("Can only match on an int, not {}", scrutinee.ty)
+            })
+        }
+        Ok(scrutinee)
+    }
+
+    // metal and wgsl get a switch, glsl 1.0 doesn't have one so it gets an if chain.
+    // a break or continue in an arm is meant for a loop around the match, not the switch
+    pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let scrutinee = self.sl_scrutinee(cx)?;
+        let indent = "  ".repeat(cx.depth);
+        let mut out = String::new();
+        if cx.lang != SlLang::Glsl && !self.arms.iter().any(|arm| arm.body.exits_loop()){
+            let wgsl = cx.lang == SlLang::Wgsl;
+            if wgsl{
+                out.push_str(&format!("switch {} {{\n", scrutinee.sl));
+            }
+            else{
+                out.push_str(&format!("switch({}){{\n", scrutinee.sl));
+            }
+            cx.depth += 1;
+            let mut has_default = false;
+            for arm in &self.arms{
+                out.push_str(&indent);
+                out.push_str("  ");
+                if arm.pats.len() == 0{
+                    out.push_str("default:");
+                    has_default = true;
+                }
+                else if wgsl{
+                    let pats:Vec<String> = arm.pats.iter().map(|pat| pat.to_string()).collect();
+                    out.push_str(&format!("case {}:", pats.join(", ")));
+                }
+                else{
+                    for (i, pat) in arm.pats.iter().enumerate(){
+                        if i != 0{
+                            out.push_str(" ");
+                        }
+                        out.push_str(&format!("case {}:", pat));
+                    }
+                }
+                let body = arm.body.sl(cx)?;
+                out.push_str(&body.sl);
+                if !wgsl{
+                    out.push_str("break;");
+                }
+                out.push_str("\n");
+                if has_default{
+                    break;
+                }
+            }
+            cx.depth -= 1;
+            if wgsl && !has_default{
+                out.push_str(&indent);
+                out.push_str("  default:{}\n");
+            }
+            out.push_str(&indent);
+            out.push_str("}");
+            return Ok(Sl{
+                sl:out,
+                ty:"void".to_string()
+            })
+        }
+        // anything but a name is evaluated once, into a block of its own
+        let hoist = if let ShExpr::ShId(_) = &*self.expr{false}else{true};
+        let name = if hoist{"_match".to_string()}else{scrutinee.sl.clone()};
+        if hoist{
+            out.push_str("{\n");
+            out.push_str(&indent);
+            out.push_str("  ");
+            out.push_str(&cx.decl("int", &name));
+            out.push_str(&format!(" = {};\n", scrutinee.sl));
+            out.push_str(&indent);
+            out.push_str("  ");
+            cx.depth += 1;
+        }
+        let (arms, default) = self.sl_arms(&name);
+        for (i, (cond, arm)) in arms.iter().enumerate(){
+            if i != 0{
+                out.push_str("else ");
+            }
+            out.push_str(&format!("if({})", cond));
+            out.push_str(&arm.body.sl(cx)?.sl);
+        }
+        if let Some(default) = default{
+            if arms.len() != 0{
+                out.push_str("else");
+            }
+            out.push_str(&default.body.sl(cx)?.sl);
+        }
+        if hoist{
+            cx.depth -= 1;
+            out.push_str(";\n");
+            out.push_str(&indent);
+            out.push_str("}");
+        }
+        Ok(Sl{
+            sl:out,
+            ty:"void".to_string()
+        })
+    }
+
+    // a match used as a value becomes nested ternaries, it needs a _ arm
+    pub fn sl_value(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let scrutinee = self.sl_scrutinee(cx)?;
+        let scrutinee = if let ShExpr::ShId(_) = &*self.expr{scrutinee.sl}else{format!("({})", scrutinee.sl)};
+        let (arms, default) = self.sl_arms(&scrutinee);
+        let default = if let Some(default) = default{default}else{
+            return Err(SlErr{
+                msg:"A match on an int needs a _ arm".to_string()
+            })
+        };
+        let mut out = default.body.sl_value(cx)?;
+        for (cond, arm) in arms.iter().rev(){
+            let value = arm.body.sl_value(cx)?;
+            if value.ty != out.ty{
+                return Err(SlErr{
+                    msg:format!("Match arms have different types {} and {}", value.ty, out.ty)
+                })
+            }
+            out.sl = sl_select(cx, cond, &value.sl, &out.sl);
+        }
+        Ok(out)
+    }
+}
+
+impl ShMethodCall{
+    // a method of an impl is called as Type_method(self, ..), otherwise the
+    // method is a builtin that takes self as first arg, like v.length()
+    pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let base = self.base.sl(cx)?;
+        let method_fn = format!("{}_{}", base.ty, self.method);
+        let call = if cx.shader.find_fn(&method_fn).map_or(false, |shfn| shfn.block.is_some()){
+            method_fn
+        }
+        else if cx.shader.find_fn(&self.method).map_or(false, |shfn| shfn.block.is_none()){
+            self.method.clone()
+        }
+        else{
+            return Err(SlErr{
+                msg:format!("No method {} on type {}", self.method, base.ty)
+            })
+        };
+        let mut args = vec![self.base.clone()];
+        args.extend(self.args.iter().cloned());
+        ShCall{call:call, args:args}.sl(cx)
+    }
+}
+
+impl ShStructLit{
+    pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let shty = cx.get_type(&self.ty)?.clone();
+        if shty.prim{
+            return Err(SlErr{
+                msg:format!("{} is not a struct", self.ty)
+            })
+        }
+        if let Some(field) = self.fields.iter().find(|field| !shty.fields.iter().any(|f| f.name == field.name)){
+            return Err(SlErr{
+                msg:format!("No field {} on {}", field.name, self.ty)
+            })
+        }
+        // the fields go in declaration order
+        let mut fields = Vec::new();
+        for field in &shty.fields{
+            let lit = if let Some(lit) = self.fields.iter().find(|f| f.name == field.name){lit}else{
+                return Err(SlErr{
+                    msg:format!("Missing field {} of {}", field.name, self.ty)
+                })
+            };
+            let value = lit.expr.sl(cx)?;
+            if value.ty != field.ty{
+                return Err(SlErr{
+                    msg:format!("Field {} of {} is {}, got {}", field.name, self.ty, field.ty, value.ty)
+                })
+            }
+            fields.push(value.sl);
+        }
+        let sl = if cx.lang == SlLang::Mtlsl{
+            format!("{}{{{}}}", self.ty, fields.join(", "))
+        }
+        else{
+            format!("{}({})", self.ty, fields.join(", "))
+        };
+        Ok(Sl{
+            sl:sl,
+            ty:self.ty.clone()
+        })
+    }
 }
 
 impl ShWhile{
@@ -569,9 +965,16 @@ impl ShForLoop{
     pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
         let mut out = "".to_string();
 
-        out.push_str("for(int ");
-        out.push_str(&self.iter);
-        out.push_str("=");
+        if cx.lang == SlLang::Wgsl{
+            out.push_str("for(var ");
+            out.push_str(&self.iter);
+            out.push_str(":i32=");
+        }
+        else{
+            out.push_str("for(int ");
+            out.push_str(&self.iter);
+            out.push_str("=");
+        }
         
         let from = self.from.sl(cx)?;
         out.push_str(&from.sl);
@@ -637,6 +1040,9 @@ impl ShContinue{
 impl ShLet{
     pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
         let mut out = "".to_string();
+        if let ShExpr::ShArrayLit(arr) = &*self.init{
+            return self.sl_array(arr, cx)
+        }
         let init = self.init.sl(cx)?;
 
         let ty = init.ty.clone();
@@ -645,10 +1051,16 @@ impl ShLet{
                 msg:format!("Let definition {} type {} is different from initializer {}", self.name, self.ty, init.ty)
             })
         }
+        if Shader::array_type(&ty).is_some(){
+            return Err(SlErr{
+                msg:format!("Arrays can't be copied, index the elements of {}", init.sl)
+            })
+        }
 
-        out.push_str(&cx.map_type(&ty));
-        out.push_str(" ");
-        out.push_str(&self.name);
+        if cx.lang == SlLang::Wgsl{
+            out.push_str("var ");
+        }
+        out.push_str(&cx.decl(&ty, &self.name));
         out.push_str(" = ");
         
         // lets define our identifier on scope
@@ -665,11 +1077,77 @@ impl ShLet{
     }
 }
 
+impl ShLet{
+    // glsl 1.0 has no array initializers, the elements are assigned one by one
+    fn sl_array(&self, arr:&ShArrayLit, cx:&mut SlCx)->Result<Sl,SlErr>{
+        let mut elems = Vec::new();
+        for elem in &arr.elems{
+            elems.push(elem.sl(cx)?);
+        }
+        let elem_ty = if let Some(first) = elems.first(){first.ty.clone()}else{
+            return Err(SlErr{
+                msg:format!("Array {} needs at least one element", self.name)
+            })
+        };
+        if let Some(elem) = elems.iter().find(|elem| elem.ty != elem_ty){
+            return Err(SlErr{
+                msg:format!("Array {} has elements of type {} and {}", self.name, elem_ty, elem.ty)
+            })
+        }
+        let ty = format!("[{};{}]", elem_ty, elems.len());
+        if self.ty != "" && self.ty != ty{
+            return Err(SlErr{
+                msg:format!("Let definition {} type {} is different from initializer {}", self.name, self.ty, ty)
+            })
+        }
+        let values:Vec<String> = elems.iter().map(|elem| elem.sl.clone()).collect();
+        let mut out = String::new();
+        match cx.lang{
+            SlLang::Glsl=>{
+                out.push_str(&cx.decl(&ty, &self.name));
+                for (i, value) in values.iter().enumerate(){
+                    out.push_str(";\n");
+                    out.push_str(&"  ".repeat(cx.depth));
+                    out.push_str(&format!("{}[{}] = {}", self.name, i, value));
+                }
+            },
+            SlLang::Mtlsl=>{
+                out.push_str(&cx.decl(&ty, &self.name));
+                out.push_str(&format!(" = {{{}}}", values.join(", ")));
+            },
+            SlLang::Wgsl=>{
+                out.push_str("var ");
+                out.push_str(&cx.decl(&ty, &self.name));
+                out.push_str(&format!(" = {}({})", cx.map_type(&ty), values.join(", ")));
+            }
+        }
+        cx.scope.push(SlDecl{
+            name:self.name.clone(),
+            ty:ty
+        });
+        Ok(Sl{
+            sl:out,
+            ty:"void".to_string()
+        })
+    }
+}
+
 impl ShFn{
     pub fn sl(&self, cx:&mut SlCx)->Result<Sl,SlErr>{
         let mut out = "".to_string();
-        out.push_str(&cx.map_type(&self.ret));
-        out.push_str(" ");
+        let wgsl = cx.lang == SlLang::Wgsl;
+        if Shader::array_type(&self.ret).is_some(){
+            return Err(SlErr{
+                msg:format!("fn {} can't return an array", self.name)
+            })
+        }
+        if wgsl{
+            out.push_str("fn ");
+        }
+        else{
+            out.push_str(&cx.map_type(&self.ret));
+            out.push_str(" ");
+        }
         out.push_str(&cx.call_prefix);
         out.push_str(&self.name);
         out.push_str("(");
@@ -677,9 +1155,7 @@ impl ShFn{
             if i != 0{
                 out.push_str(", ");
             }
-            out.push_str(&cx.map_type(&arg.ty));
-            out.push_str(" ");
-            out.push_str(&arg.name);
+            out.push_str(&cx.decl(&arg.ty, &arg.name));
             cx.scope.push(SlDecl{
                 name:arg.name.clone(),
                 ty:arg.ty.clone()
@@ -692,6 +1168,10 @@ impl ShFn{
             out.push_str(&cx.defargs_fn);
         }
         out.push_str(")");
+        if wgsl && self.ret != "void"{
+            out.push_str("->");
+            out.push_str(&cx.map_type(&self.ret));
+        }
         if let Some(block) = &self.block{
             let block = block.sl(cx)?;
             out.push_str(&block.sl);
@@ -746,6 +1226,23 @@ pub fn assemble_fn_and_deps(sh:&Shader, cx:&mut SlCx)->Result<String, SlErr>{
     Ok(out)
 }
 
+// the structs the shader declares, before anything that can use them
+pub fn assemble_structs(cx:&SlCx)->String{
+    let mut out = String::new();
+    for shty in cx.shader.flat_structs(){
+        out.push_str("struct ");
+        out.push_str(&shty.name);
+        out.push_str("{\n");
+        for field in &shty.fields{
+            out.push_str("  ");
+            out.push_str(&cx.decl(&field.ty, &field.name));
+            out.push_str(if cx.lang == SlLang::Wgsl{",\n"}else{";\n"});
+        }
+        out.push_str("};\n");
+    }
+    out
+}
+
 pub fn assemble_const_init(cnst:&ShConst, cx:&mut SlCx)->Result<Sl, SlErr>{
     // lets process the expr of a constant
     let result = cnst.value.sl(cx)?;
@@ -816,4 +1313,50 @@ impl NamedInstanceProps{
             props:props
         }
     }
-}
\ No newline at end of file
+}
+// widgets write every instance variable of a shader, the optimized shader can read fewer.
+// the runs of slots it reads are packed together before the instances go to the gpu
+#[derive(Default,Clone)]
+pub struct InstancePacking{
+    pub total_slots:usize,
+    pub slots:usize,
+    pub ranges:Vec<(usize, usize)>
+}
+
+impl InstancePacking{
+    pub fn construct(sh:&Shader, instances:&Vec<ShVar>, used:&Vec<ShVar>)->InstancePacking{
+        let mut ranges:Vec<(usize, usize)> = Vec::new();
+        let mut slot = 0;
+        for inst in instances{
+            let slots = sh.get_type_slots(&inst.ty);
+            if used.iter().any(|var| var.name == inst.name){
+                match ranges.last_mut(){
+                    Some(last) if last.0 + last.1 == slot=>last.1 += slots,
+                    _=>ranges.push((slot, slots))
+                }
+            }
+            slot += slots;
+        };
+        InstancePacking{
+            total_slots:slot,
+            slots:ranges.iter().map(|range| range.1).sum(),
+            ranges:ranges
+        }
+    }
+
+    pub fn is_packed(&self)->bool{
+        self.slots != self.total_slots
+    }
+
+    pub fn pack(&self, data:&[f32], out:&mut Vec<f32>){
+        out.truncate(0);
+        if self.total_slots == 0{
+            return
+        }
+        for inst in data.chunks_exact(self.total_slots){
+            for (offset, len) in &self.ranges{
+                out.extend_from_slice(&inst[*offset..*offset + *len]);
+            }
+        }
+    }
+}
@@ -0,0 +1,625 @@
+// Optimizes a shader before it is generated for a target. constant expressions are folded,
+// functions that only return an expression of their args are inlined, and the functions,
+// consts, locals and instance variables the entry points never reach are left out.
+// widgets keep writing the instances of the whole shader, see InstancePacking in shadergen
+
+use crate::cx::*;
+
+struct OptCx<'a>{
+    sh:&'a Shader,
+    // args, lets and loop indices, they hide consts of the same name
+    scope:Vec<String>,
+    // the consts that fold to a literal
+    consts:Vec<(String, ShLit)>
+}
+
+// gpus compute in f32, a folded float is written as the shortest text of that f32
+fn float_lit(v:f32)->Option<ShLit>{
+    if !v.is_finite(){
+        return None
+    }
+    v.to_string().parse::<f64>().ok().map(ShLit::Float)
+}
+
+fn int_lit(v:Option<i64>)->Option<ShLit>{
+    // ints are 32 bits on the gpu
+    let v = v?;
+    if v as i32 as i64 != v{
+        return None
+    }
+    Some(ShLit::Int(v))
+}
+
+fn fold_unary(op:&ShUnaryOp, lit:&ShLit)->Option<ShLit>{
+    match (op, lit){
+        (ShUnaryOp::Neg, ShLit::Float(v))=>Some(ShLit::Float(-v)),
+        (ShUnaryOp::Neg, ShLit::Int(v))=>int_lit(v.checked_neg()),
+        (ShUnaryOp::Not, ShLit::Bool(v))=>Some(ShLit::Bool(!v)),
+        _=>None
+    }
+}
+
+fn fold_binary(op:&ShBinOp, left:&ShLit, right:&ShLit)->Option<ShLit>{
+    match (left, right){
+        (ShLit::Float(a), ShLit::Float(b))=>{
+            let (a, b) = (*a as f32, *b as f32);
+            match op{
+                ShBinOp::Add=>float_lit(a + b),
+                ShBinOp::Sub=>float_lit(a - b),
+                ShBinOp::Mul=>float_lit(a * b),
+                ShBinOp::Div=>float_lit(a / b),
+                ShBinOp::Eq=>Some(ShLit::Bool(a == b)),
+                ShBinOp::Ne=>Some(ShLit::Bool(a != b)),
+                ShBinOp::Lt=>Some(ShLit::Bool(a < b)),
+                ShBinOp::Le=>Some(ShLit::Bool(a <= b)),
+                ShBinOp::Gt=>Some(ShLit::Bool(a > b)),
+                ShBinOp::Ge=>Some(ShLit::Bool(a >= b)),
+                _=>None
+            }
+        },
+        (ShLit::Int(a), ShLit::Int(b))=>{
+            let (a, b) = (*a, *b);
+            match op{
+                ShBinOp::Add=>int_lit(a.checked_add(b)),
+                ShBinOp::Sub=>int_lit(a.checked_sub(b)),
+                ShBinOp::Mul=>int_lit(a.checked_mul(b)),
+                // negative int division differs between targets
+                ShBinOp::Div if a >= 0 && b > 0=>int_lit(Some(a / b)),
+                ShBinOp::Rem if a >= 0 && b > 0=>int_lit(Some(a % b)),
+                ShBinOp::Eq=>Some(ShLit::Bool(a == b)),
+                ShBinOp::Ne=>Some(ShLit::Bool(a != b)),
+                ShBinOp::Lt=>Some(ShLit::Bool(a < b)),
+                ShBinOp::Le=>Some(ShLit::Bool(a <= b)),
+                ShBinOp::Gt=>Some(ShLit::Bool(a > b)),
+                ShBinOp::Ge=>Some(ShLit::Bool(a >= b)),
+                _=>None
+            }
+        },
+        (ShLit::Bool(a), ShLit::Bool(b))=>{
+            match op{
+                ShBinOp::And=>Some(ShLit::Bool(*a && *b)),
+                ShBinOp::Or=>Some(ShLit::Bool(*a || *b)),
+                ShBinOp::Eq=>Some(ShLit::Bool(a == b)),
+                ShBinOp::Ne=>Some(ShLit::Bool(a != b)),
+                _=>None
+            }
+        },
+        _=>None
+    }
+}
+
+// the value of a literal, also when it is negated or in parens
+fn lit_of(expr:&ShExpr)->Option<ShLit>{
+    match expr{
+        ShExpr::ShLit(ShLit::Str(_))=>None,
+        ShExpr::ShLit(lit)=>Some(lit.clone()),
+        ShExpr::ShParen(x)=>lit_of(&x.expr),
+        ShExpr::ShUnary(x)=>fold_unary(&x.op, &lit_of(&x.expr)?),
+        _=>None
+    }
+}
+
+// binary ops are generated without parens, so a negative literal gets them
+fn lit_expr(lit:ShLit)->ShExpr{
+    let negative = match &lit{
+        ShLit::Float(v)=>v.is_sign_negative(),
+        ShLit::Int(v)=>*v < 0,
+        _=>false
+    };
+    if negative{
+        ShExpr::ShParen(ShParen{expr:Box::new(ShExpr::ShLit(lit))})
+    }
+    else{
+        ShExpr::ShLit(lit)
+    }
+}
+
+// an expression that can go where a call was without changing what binds to what
+fn atomic(expr:ShExpr)->ShExpr{
+    match expr{
+        ShExpr::ShId(_) | ShExpr::ShParen(_) | ShExpr::ShCall(_) | ShExpr::ShField(_) | ShExpr::ShIndex(_) |
+        ShExpr::ShMethodCall(_) | ShExpr::ShStructLit(_)=>expr,
+        ShExpr::ShLit(lit)=>lit_expr(lit),
+        _=>ShExpr::ShParen(ShParen{expr:Box::new(expr)})
+    }
+}
+
+// calls f on the expression and every expression in it
+fn visit(expr:&ShExpr, f:&mut dyn FnMut(&ShExpr)){
+    f(expr);
+    match expr{
+        ShExpr::ShId(_) | ShExpr::ShLit(_) | ShExpr::ShBreak(_) | ShExpr::ShContinue(_)=>(),
+        ShExpr::ShField(x)=>visit(&x.base, f),
+        ShExpr::ShIndex(x)=>{
+            visit(&x.base, f);
+            visit(&x.index, f);
+        },
+        ShExpr::ShAssign(x)=>{
+            visit(&x.left, f);
+            visit(&x.right, f);
+        },
+        ShExpr::ShAssignOp(x)=>{
+            visit(&x.left, f);
+            visit(&x.right, f);
+        },
+        ShExpr::ShBinary(x)=>{
+            visit(&x.left, f);
+            visit(&x.right, f);
+        },
+        ShExpr::ShUnary(x)=>visit(&x.expr, f),
+        ShExpr::ShParen(x)=>visit(&x.expr, f),
+        ShExpr::ShBlock(x)=>visit_block(x, f),
+        ShExpr::ShCall(x)=>for arg in &x.args{
+            visit(arg, f);
+        },
+        ShExpr::ShIf(x)=>{
+            visit(&x.cond, f);
+            visit_block(&x.then_branch, f);
+            if let Some(else_branch) = &x.else_branch{
+                visit(else_branch, f);
+            }
+        },
+        ShExpr::ShWhile(x)=>{
+            visit(&x.cond, f);
+            visit_block(&x.body, f);
+        },
+        ShExpr::ShForLoop(x)=>{
+            visit(&x.from, f);
+            visit(&x.to, f);
+            visit_block(&x.body, f);
+        },
+        ShExpr::ShReturn(x)=>if let Some(expr) = &x.expr{
+            visit(expr, f);
+        },
+        ShExpr::ShMatch(x)=>{
+            visit(&x.expr, f);
+            for arm in &x.arms{
+                visit_block(&arm.body, f);
+            }
+        },
+        ShExpr::ShMethodCall(x)=>{
+            visit(&x.base, f);
+            for arg in &x.args{
+                visit(arg, f);
+            }
+        },
+        ShExpr::ShStructLit(x)=>for field in &x.fields{
+            visit(&field.expr, f);
+        },
+        ShExpr::ShArrayLit(x)=>for elem in &x.elems{
+            visit(elem, f);
+        }
+    }
+}
+
+fn visit_block(block:&ShBlock, f:&mut dyn FnMut(&ShExpr)){
+    for stmt in &block.stmts{
+        match &**stmt{
+            ShStmt::ShLet(x)=>visit(&x.init, f),
+            ShStmt::ShExpr(x) | ShStmt::ShSemi(x)=>visit(x, f)
+        }
+    }
+}
+
+// puts the args of an inlined fn in its return expression
+fn subst(expr:&ShExpr, fnargs:&Vec<ShFnArg>, args:&Vec<ShExpr>)->ShExpr{
+    let sub = |expr:&ShExpr| Box::new(subst(expr, fnargs, args));
+    match expr{
+        ShExpr::ShId(x)=>{
+            if let Some(i) = fnargs.iter().position(|fnarg| fnarg.name == x.name){
+                return atomic(args[i].clone())
+            }
+            expr.clone()
+        },
+        ShExpr::ShField(x)=>ShExpr::ShField(ShField{base:sub(&x.base), member:x.member.clone()}),
+        ShExpr::ShIndex(x)=>ShExpr::ShIndex(ShIndex{base:sub(&x.base), index:sub(&x.index)}),
+        ShExpr::ShBinary(x)=>ShExpr::ShBinary(ShBinary{left:sub(&x.left), right:sub(&x.right), op:x.op.clone()}),
+        ShExpr::ShUnary(x)=>ShExpr::ShUnary(ShUnary{expr:sub(&x.expr), op:x.op.clone()}),
+        ShExpr::ShParen(x)=>ShExpr::ShParen(ShParen{expr:sub(&x.expr)}),
+        ShExpr::ShCall(x)=>ShExpr::ShCall(ShCall{call:x.call.clone(), args:x.args.iter().map(|arg| sub(arg)).collect()}),
+        ShExpr::ShStructLit(x)=>ShExpr::ShStructLit(ShStructLit{
+            ty:x.ty.clone(),
+            fields:x.fields.iter().map(|field| ShStructLitField{name:field.name.clone(), expr:sub(&field.expr)}).collect()
+        }),
+        _=>expr.clone()
+    }
+}
+
+fn add_name(names:&mut Vec<String>, name:&str){
+    if !names.iter().any(|n| n == name){
+        names.push(name.to_string());
+    }
+}
+
+impl<'a> OptCx<'a>{
+    fn is_user_fn(&self, name:&str)->bool{
+        self.sh.find_fn(name).map_or(false, |shfn| shfn.block.is_some())
+    }
+
+    // evaluating it changes nothing, so it can be moved or left out
+    fn is_pure(&self, expr:&ShExpr)->bool{
+        let mut pure = true;
+        visit(expr, &mut |expr| match expr{
+            ShExpr::ShCall(x)=>if self.is_user_fn(&x.call){
+                pure = false;
+            },
+            ShExpr::ShAssign(_) | ShExpr::ShAssignOp(_) | ShExpr::ShMethodCall(_) | ShExpr::ShReturn(_) |
+            ShExpr::ShBreak(_) | ShExpr::ShContinue(_) | ShExpr::ShWhile(_) | ShExpr::ShForLoop(_)=>{
+                pure = false;
+            },
+            _=>()
+        });
+        pure
+    }
+
+    // what an inlined fn can return, calls of other fns could write locals
+    fn is_inlinable(&self, expr:&ShExpr)->bool{
+        let mut ok = true;
+        visit(expr, &mut |expr| match expr{
+            ShExpr::ShId(_) | ShExpr::ShLit(_) | ShExpr::ShField(_) | ShExpr::ShIndex(_) | ShExpr::ShBinary(_) |
+            ShExpr::ShUnary(_) | ShExpr::ShParen(_) | ShExpr::ShStructLit(_)=>(),
+            ShExpr::ShCall(x) if !self.is_user_fn(&x.call)=>(),
+            _=>{
+                ok = false;
+            }
+        });
+        ok
+    }
+
+    fn const_lit(&self, name:&str)->Option<ShLit>{
+        if self.scope.iter().any(|n| n == name){
+            return None
+        }
+        self.consts.iter().find(|(n, _)| n == name).map(|(_, lit)| lit.clone())
+    }
+
+    fn block(&mut self, block:&ShBlock)->ShBlock{
+        let scope_len = self.scope.len();
+        let mut out = ShBlock{stmts:Vec::new(), spans:Vec::new()};
+        for (i, stmt) in block.stmts.iter().enumerate(){
+            let stmt = match &**stmt{
+                ShStmt::ShLet(x)=>{
+                    let init = self.expr(&x.init);
+                    self.scope.push(x.name.clone());
+                    Some(ShStmt::ShLet(ShLet{name:x.name.clone(), ty:x.ty.clone(), init:Box::new(init)}))
+                },
+                ShStmt::ShExpr(x)=>self.stmt(x).map(ShStmt::ShExpr),
+                ShStmt::ShSemi(x)=>self.stmt(x).map(ShStmt::ShSemi)
+            };
+            if let Some(stmt) = stmt{
+                out.stmts.push(Box::new(stmt));
+                if let Some(span) = block.spans.get(i){
+                    out.spans.push(span.clone());
+                }
+            }
+        }
+        self.scope.truncate(scope_len);
+        out
+    }
+
+    // the branch of an if or the arm of a match used as a value
+    fn value_block(&mut self, block:&ShBlock)->ShBlock{
+        if block.stmts.len() == 1{
+            if let ShStmt::ShExpr(expr) = &*block.stmts[0]{
+                return ShBlock{stmts:vec![Box::new(ShStmt::ShExpr(self.expr(expr)))], spans:block.spans.clone()}
+            }
+        }
+        self.block(block)
+    }
+
+    fn value_branch(&mut self, expr:&ShExpr)->ShExpr{
+        if let ShExpr::ShBlock(block) = expr{
+            return ShExpr::ShBlock(self.value_block(block))
+        }
+        self.expr(expr)
+    }
+
+    // the value a folded if or match takes
+    fn branch_value(&mut self, block:&ShBlock)->Option<ShExpr>{
+        if block.stmts.len() == 1{
+            if let ShStmt::ShExpr(expr) = &*block.stmts[0]{
+                return Some(atomic(self.expr(expr)))
+            }
+        }
+        None
+    }
+
+    fn match_arm(x:&ShMatch, value:i64)->Option<&ShMatchArm>{
+        x.arms.iter().find(|arm| arm.pats.len() == 0 || arm.pats.contains(&value))
+    }
+
+    // a statement leaves out the branches that never run, None when nothing is left of it
+    fn stmt(&mut self, expr:&ShExpr)->Option<ShExpr>{
+        match expr{
+            ShExpr::ShIf(x)=>{
+                let cond = self.expr(&x.cond);
+                match lit_of(&cond){
+                    Some(ShLit::Bool(true))=>Some(ShExpr::ShBlock(self.block(&x.then_branch))),
+                    Some(ShLit::Bool(false))=>{
+                        let else_branch = x.else_branch.as_ref()?;
+                        self.stmt(else_branch)
+                    },
+                    _=>{
+                        let then_branch = self.block(&x.then_branch);
+                        let else_branch = match &x.else_branch{
+                            Some(else_branch)=>self.stmt(else_branch).map(Box::new),
+                            None=>None
+                        };
+                        Some(ShExpr::ShIf(ShIf{cond:Box::new(cond), then_branch:then_branch, else_branch:else_branch}))
+                    }
+                }
+            },
+            ShExpr::ShMatch(x)=>{
+                let scrutinee = self.expr(&x.expr);
+                if let Some(ShLit::Int(value)) = lit_of(&scrutinee){
+                    let arm = Self::match_arm(x, value)?;
+                    return Some(ShExpr::ShBlock(self.block(&arm.body)))
+                }
+                Some(ShExpr::ShMatch(ShMatch{
+                    expr:Box::new(scrutinee),
+                    arms:x.arms.iter().map(|arm| ShMatchArm{pats:arm.pats.clone(), body:self.block(&arm.body)}).collect()
+                }))
+            },
+            ShExpr::ShWhile(x)=>{
+                let cond = self.expr(&x.cond);
+                if let Some(ShLit::Bool(false)) = lit_of(&cond){
+                    return None
+                }
+                Some(ShExpr::ShWhile(ShWhile{cond:Box::new(cond), body:self.block(&x.body)}))
+            },
+            _=>Some(self.expr(expr))
+        }
+    }
+
+    fn expr(&mut self, expr:&ShExpr)->ShExpr{
+        match expr{
+            ShExpr::ShId(x)=>{
+                if let Some(lit) = self.const_lit(&x.name){
+                    return lit_expr(lit)
+                }
+                expr.clone()
+            },
+            ShExpr::ShLit(_) | ShExpr::ShBreak(_) | ShExpr::ShContinue(_)=>expr.clone(),
+            ShExpr::ShField(x)=>ShExpr::ShField(ShField{base:Box::new(self.expr(&x.base)), member:x.member.clone()}),
+            ShExpr::ShIndex(x)=>ShExpr::ShIndex(ShIndex{base:Box::new(self.expr(&x.base)), index:Box::new(self.expr(&x.index))}),
+            ShExpr::ShAssign(x)=>ShExpr::ShAssign(ShAssign{left:Box::new(self.expr(&x.left)), right:Box::new(self.expr(&x.right))}),
+            ShExpr::ShAssignOp(x)=>ShExpr::ShAssignOp(ShAssignOp{
+                left:Box::new(self.expr(&x.left)),
+                right:Box::new(self.expr(&x.right)),
+                op:x.op.clone()
+            }),
+            ShExpr::ShBinary(x)=>{
+                let left = self.expr(&x.left);
+                let right = self.expr(&x.right);
+                if let (Some(a), Some(b)) = (lit_of(&left), lit_of(&right)){
+                    if let Some(lit) = fold_binary(&x.op, &a, &b){
+                        return lit_expr(lit)
+                    }
+                }
+                ShExpr::ShBinary(ShBinary{left:Box::new(left), right:Box::new(right), op:x.op.clone()})
+            },
+            ShExpr::ShUnary(x)=>{
+                let inner = self.expr(&x.expr);
+                // -1. is as short as it gets
+                let neg_lit = if let (ShUnaryOp::Neg, ShExpr::ShLit(_)) = (&x.op, &inner){true}else{false};
+                if !neg_lit{
+                    if let Some(lit) = lit_of(&inner).and_then(|lit| fold_unary(&x.op, &lit)){
+                        return lit_expr(lit)
+                    }
+                }
+                ShExpr::ShUnary(ShUnary{expr:Box::new(inner), op:x.op.clone()})
+            },
+            ShExpr::ShParen(x)=>{
+                let inner = self.expr(&x.expr);
+                if let Some(lit) = lit_of(&inner){
+                    return lit_expr(lit)
+                }
+                ShExpr::ShParen(ShParen{expr:Box::new(inner)})
+            },
+            ShExpr::ShBlock(x)=>ShExpr::ShBlock(self.block(x)),
+            ShExpr::ShCall(x)=>{
+                let args:Vec<ShExpr> = x.args.iter().map(|arg| self.expr(arg)).collect();
+                if let Some(body) = self.inline(&x.call, &args){
+                    return body
+                }
+                ShExpr::ShCall(ShCall{call:x.call.clone(), args:args.into_iter().map(Box::new).collect()})
+            },
+            ShExpr::ShIf(x)=>{
+                let cond = self.expr(&x.cond);
+                match (lit_of(&cond), &x.else_branch){
+                    (Some(ShLit::Bool(true)), Some(_))=>if let Some(value) = self.branch_value(&x.then_branch){
+                        return value
+                    },
+                    (Some(ShLit::Bool(false)), Some(else_branch))=>match &**else_branch{
+                        ShExpr::ShBlock(block)=>if let Some(value) = self.branch_value(block){
+                            return value
+                        },
+                        else_branch=>return atomic(self.expr(else_branch))
+                    },
+                    _=>()
+                }
+                ShExpr::ShIf(ShIf{
+                    cond:Box::new(cond),
+                    then_branch:self.value_block(&x.then_branch),
+                    else_branch:x.else_branch.as_ref().map(|else_branch| Box::new(self.value_branch(else_branch)))
+                })
+            },
+            ShExpr::ShMatch(x)=>{
+                let scrutinee = self.expr(&x.expr);
+                if let Some(ShLit::Int(value)) = lit_of(&scrutinee){
+                    if let Some(arm) = Self::match_arm(x, value){
+                        if let Some(value) = self.branch_value(&arm.body){
+                            return value
+                        }
+                    }
+                }
+                ShExpr::ShMatch(ShMatch{
+                    expr:Box::new(scrutinee),
+                    arms:x.arms.iter().map(|arm| ShMatchArm{pats:arm.pats.clone(), body:self.value_block(&arm.body)}).collect()
+                })
+            },
+            ShExpr::ShWhile(x)=>ShExpr::ShWhile(ShWhile{cond:Box::new(self.expr(&x.cond)), body:self.block(&x.body)}),
+            ShExpr::ShForLoop(x)=>{
+                let from = self.expr(&x.from);
+                let to = self.expr(&x.to);
+                self.scope.push(x.iter.clone());
+                let body = self.block(&x.body);
+                self.scope.pop();
+                ShExpr::ShForLoop(ShForLoop{iter:x.iter.clone(), from:Box::new(from), to:Box::new(to), body:body})
+            },
+            ShExpr::ShReturn(x)=>ShExpr::ShReturn(ShReturn{expr:x.expr.as_ref().map(|expr| Box::new(self.expr(expr)))}),
+            ShExpr::ShMethodCall(x)=>ShExpr::ShMethodCall(ShMethodCall{
+                base:Box::new(self.expr(&x.base)),
+                method:x.method.clone(),
+                args:x.args.iter().map(|arg| Box::new(self.expr(arg))).collect()
+            }),
+            ShExpr::ShStructLit(x)=>ShExpr::ShStructLit(ShStructLit{
+                ty:x.ty.clone(),
+                fields:x.fields.iter().map(|field| ShStructLitField{name:field.name.clone(), expr:Box::new(self.expr(&field.expr))}).collect()
+            }),
+            ShExpr::ShArrayLit(x)=>ShExpr::ShArrayLit(ShArrayLit{elems:x.elems.iter().map(|elem| Box::new(self.expr(elem))).collect()})
+        }
+    }
+
+    // a fn that only returns an expression of builtins goes in place of its call,
+    // as long as the args can be moved there and nothing at the call hides what it uses
+    fn inline(&mut self, name:&str, args:&Vec<ShExpr>)->Option<ShExpr>{
+        let sh = self.sh;
+        let shfn = sh.find_fn(name)?;
+        let block = shfn.block.as_ref()?;
+        if block.stmts.len() != 1 || shfn.args.len() != args.len(){
+            return None
+        }
+        let ret = match &*block.stmts[0]{
+            ShStmt::ShExpr(ShExpr::ShReturn(x)) | ShStmt::ShSemi(ShExpr::ShReturn(x))=>x.expr.as_ref()?,
+            _=>return None
+        };
+        if !self.is_inlinable(ret) || args.iter().any(|arg| !self.is_pure(arg)){
+            return None
+        }
+        let mut names = Vec::new();
+        visit(ret, &mut |expr| if let ShExpr::ShId(x) = expr{
+            names.push(x.name.clone());
+        });
+        for name in &names{
+            if self.scope.contains(name) && !shfn.args.iter().any(|fnarg| fnarg.name == *name){
+                return None
+            }
+        }
+        for (fnarg, arg) in shfn.args.iter().zip(args){
+            if Shader::array_type(&fnarg.ty).is_some(){
+                return None
+            }
+            // an arg that is used twice is computed twice
+            let uses = names.iter().filter(|name| **name == fnarg.name).count();
+            match arg{
+                ShExpr::ShId(_) | ShExpr::ShLit(_)=>(),
+                _ if uses > 1=>return None,
+                _=>()
+            }
+        }
+        let body = subst(ret, &shfn.args, args);
+        Some(atomic(self.expr(&body)))
+    }
+}
+
+impl Shader{
+    pub fn optimize(&self)->Shader{
+        let mut cx = OptCx{
+            sh:self,
+            scope:Vec::new(),
+            consts:Vec::new()
+        };
+
+        // consts can use each other, fold until no more of them turn into literals
+        let consts = self.flat_consts();
+        loop{
+            let mut folded = false;
+            for cnst in &consts{
+                if cx.consts.iter().any(|(name, _)| *name == cnst.name) || self.find_const(&cnst.name) != Some(cnst){
+                    continue;
+                }
+                if let Some(lit) = lit_of(&cx.expr(&cnst.value)){
+                    cx.consts.push((cnst.name.clone(), lit));
+                    folded = true;
+                }
+            }
+            if !folded{
+                break;
+            }
+        }
+
+        let mut opt = self.clone();
+        for ast in &mut opt.asts{
+            for shfn in &mut ast.fns{
+                if let Some(block) = &shfn.block{
+                    cx.scope = shfn.args.iter().map(|fnarg| fnarg.name.clone()).collect();
+                    shfn.block = Some(cx.block(block));
+                }
+            }
+            cx.scope.clear();
+            for cnst in &mut ast.consts{
+                cnst.value = cx.expr(&cnst.value);
+            }
+        }
+        opt.strip_unused();
+        opt
+    }
+
+    // leaves out what the entry points never reach
+    fn strip_unused(&mut self){
+        let mut fns = vec!["vertex".to_string(), "pixel".to_string()];
+        let mut ids = Vec::new();
+        let mut done = 0;
+        while done < fns.len(){
+            let mut calls = Vec::new();
+            let mut methods = Vec::new();
+            if let Some(ShFn{block:Some(block), ..}) = self.find_fn(&fns[done]){
+                visit_block(block, &mut |expr| match expr{
+                    ShExpr::ShId(x)=>add_name(&mut ids, &x.name),
+                    ShExpr::ShCall(x)=>calls.push(x.call.clone()),
+                    ShExpr::ShMethodCall(x)=>methods.push(format!("_{}", x.method)),
+                    _=>()
+                });
+            }
+            for call in &calls{
+                add_name(&mut fns, call);
+            }
+            // the struct a method is called on isn't known here, it reaches all methods of that name
+            for ast in &self.asts{
+                for shfn in &ast.fns{
+                    if shfn.block.is_some() && methods.iter().any(|method| shfn.name.ends_with(method)){
+                        add_name(&mut fns, &shfn.name);
+                    }
+                }
+            }
+            done += 1;
+        }
+        let mut done = 0;
+        while done < ids.len(){
+            if let Some(cnst) = self.find_const(&ids[done]){
+                let mut used = Vec::new();
+                visit(&cnst.value, &mut |expr| if let ShExpr::ShId(x) = expr{
+                    used.push(x.name.clone());
+                });
+                for name in &used{
+                    add_name(&mut ids, name);
+                }
+            }
+            done += 1;
+        }
+
+        // a shader that reads no instances keeps them, so there is something to draw with
+        let instances = self.flat_vars(ShVarStore::Instance);
+        let keep_instances = !instances.iter().any(|var| ids.contains(&var.name));
+        for ast in &mut self.asts{
+            ast.fns.retain(|shfn| shfn.block.is_none() || fns.contains(&shfn.name));
+            ast.consts.retain(|cnst| ids.contains(&cnst.name));
+            ast.vars.retain(|var| match var.store{
+                ShVarStore::Local=>ids.contains(&var.name),
+                ShVarStore::Instance=>keep_instances || ids.contains(&var.name),
+                _=>true
+            });
+        }
+    }
+}
@@ -0,0 +1,1035 @@
+// Parses the body of a shader_ast! block from a string at runtime, into the same ShAst
+// the macro generates at compile time. It's the same Rust subset with the same errors,
+// reported as diagnostics on the line of the file instead of as compile errors.
+
+use crate::shader::*;
+use crate::shadertypecheck::*;
+
+const TYPES:[&str;10] = ["float", "int", "bool", "vec2", "vec3", "vec4", "mat2", "mat3", "mat4", "texture2d"];
+const STORES:[&str;8] = ["Uniform", "UniformDl", "UniformCx", "Instance", "Geometry", "Texture", "Local", "Varying"];
+
+// longest first so the tokenizer can take the first match
+const PUNCTS:[&str;43] = [
+    "<<=", ">>=", "...", "..=",
+    "->", "=>", "::", "..", "==", "!=", "<=", ">=", "&&", "||",
+    "+=", "-=", "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>",
+    "+", "-", "*", "/", "%", "^", "&", "|", "!", "=", "<", ">",
+    ".", ",", ";", ":", "(", ")", "#"
+];
+const BRACKETS:[&str;4] = ["{", "}", "[", "]"];
+
+#[derive(Clone, PartialEq)]
+enum ShTok{
+    Ident(String),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Punct(&'static str),
+    End
+}
+
+#[derive(Clone)]
+struct ShToken{
+    tok:ShTok,
+    line:u32,
+    start:usize,
+    end:usize
+}
+
+fn line_src(source:&str, line:u32)->String{
+    if let Some(src) = source.lines().nth(line as usize - 1){
+        return src.trim().to_string()
+    }
+    String::new()
+}
+
+fn tokenize(file:&str, source:&str)->Result<Vec<ShToken>, ShDiag>{
+    let chars:Vec<(usize, char)> = source.char_indices().collect();
+    let mut toks = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    let at = |i:usize|->char{if i < chars.len(){chars[i].1}else{'\0'}};
+    let offset = |i:usize|->usize{if i < chars.len(){chars[i].0}else{source.len()}};
+    let error = |line:u32, msg:&str|->ShDiag{
+        ShDiag{
+            span:ShSpan{file:file.to_string(), line:line, src:line_src(source, line)},
+            in_fn:String::new(),
+            msg:msg.to_string()
+        }
+    };
+    while i < chars.len(){
+        let c = at(i);
+        let start = i;
+        let start_line = line;
+        let tok;
+        if c == '\n'{
+            line += 1;
+            i += 1;
+            continue;
+        }
+        else if c.is_whitespace(){
+            i += 1;
+            continue;
+        }
+        else if c == '/' && at(i + 1) == '/'{
+            while i < chars.len() && at(i) != '\n'{
+                i += 1;
+            }
+            continue;
+        }
+        else if c == '/' && at(i + 1) == '*'{
+            i += 2;
+            while i < chars.len() && !(at(i) == '*' && at(i + 1) == '/'){
+                if at(i) == '\n'{
+                    line += 1;
+                }
+                i += 1;
+            }
+            if i >= chars.len(){
+                return Err(error(start_line, "Unterminated block comment"))
+            }
+            i += 2;
+            continue;
+        }
+        else if c.is_alphabetic() || c == '_'{
+            while at(i).is_alphanumeric() || at(i) == '_'{
+                i += 1;
+            }
+            tok = ShTok::Ident(source[offset(start)..offset(i)].to_string());
+        }
+        else if c.is_ascii_digit(){
+            let mut is_float = false;
+            while at(i).is_ascii_digit() || at(i) == '_'{
+                i += 1;
+            }
+            // 1. is a float but 1..2 is a range and 1.max() a method call
+            if at(i) == '.' && at(i + 1) != '.' && !at(i + 1).is_alphabetic() && at(i + 1) != '_'{
+                is_float = true;
+                i += 1;
+                while at(i).is_ascii_digit() || at(i) == '_'{
+                    i += 1;
+                }
+            }
+            if (at(i) == 'e' || at(i) == 'E') && (at(i + 1).is_ascii_digit() || ((at(i + 1) == '-' || at(i + 1) == '+') && at(i + 2).is_ascii_digit())){
+                is_float = true;
+                i += 2;
+                while at(i).is_ascii_digit(){
+                    i += 1;
+                }
+            }
+            let num:String = source[offset(start)..offset(i)].chars().filter(|c| *c != '_').collect();
+            // a suffix like f32 or u32 picks the kind of literal
+            let suffix_start = i;
+            while at(i).is_alphanumeric() || at(i) == '_'{
+                i += 1;
+            }
+            if source[offset(suffix_start)..offset(i)].starts_with('f'){
+                is_float = true;
+            }
+            if is_float{
+                tok = match num.parse::<f64>(){
+                    Ok(value)=>ShTok::Float(value),
+                    Err(_)=>return Err(error(line, &format!("Invalid float literal {}", num)))
+                };
+            }
+            else{
+                tok = match num.parse::<i64>(){
+                    Ok(value)=>ShTok::Int(value),
+                    Err(_)=>return Err(error(line, &format!("Invalid integer literal {}", num)))
+                };
+            }
+        }
+        else if c == '"'{
+            let mut value = String::new();
+            i += 1;
+            while i < chars.len() && at(i) != '"'{
+                if at(i) == '\\' && i + 1 < chars.len(){
+                    i += 1;
+                    value.push(match at(i){'n'=>'\n', 't'=>'\t', c=>c});
+                }
+                else{
+                    if at(i) == '\n'{
+                        line += 1;
+                    }
+                    value.push(at(i));
+                }
+                i += 1;
+            }
+            if i >= chars.len(){
+                return Err(error(start_line, "Unterminated string literal"))
+            }
+            i += 1;
+            tok = ShTok::Str(value);
+        }
+        else if let Some(punct) = PUNCTS.iter().chain(BRACKETS.iter()).find(|p| source[offset(i)..].starts_with(*p)){
+            i += punct.len();
+            tok = ShTok::Punct(punct);
+        }
+        else{
+            return Err(error(line, &format!("Unexpected character {}", c)))
+        }
+        toks.push(ShToken{
+            tok:tok,
+            line:start_line,
+            start:offset(start),
+            end:offset(i)
+        });
+    }
+    toks.push(ShToken{
+        tok:ShTok::End,
+        line:line,
+        start:source.len(),
+        end:source.len()
+    });
+    Ok(toks)
+}
+
+// the same check as shader_ast!, a member made of only swizzle letters has to be a valid swizzle
+fn check_swizzle(member:&str)->Result<(), String>{
+    if !member.chars().all(|c| "xyzwrgba".contains(c)){
+        return Ok(())
+    }
+    if !member.chars().all(|c| "xyzw".contains(c)) && !member.chars().all(|c| "rgba".contains(c)){
+        return Err(format!("Swizzle {} mixes xyzw and rgba", member))
+    }
+    if member.len() > 4{
+        return Err(format!("Swizzle {} is longer than 4", member))
+    }
+    Ok(())
+}
+
+fn binop_prec(punct:&str)->Option<(usize, ShBinOp)>{
+    Some(match punct{
+        "||"=>(1, ShBinOp::Or),
+        "&&"=>(2, ShBinOp::And),
+        "=="=>(3, ShBinOp::Eq),
+        "!="=>(3, ShBinOp::Ne),
+        "<"=>(3, ShBinOp::Lt),
+        "<="=>(3, ShBinOp::Le),
+        ">"=>(3, ShBinOp::Gt),
+        ">="=>(3, ShBinOp::Ge),
+        "|"=>(4, ShBinOp::BitOr),
+        "^"=>(5, ShBinOp::BitXor),
+        "&"=>(6, ShBinOp::BitAnd),
+        "<<"=>(7, ShBinOp::Shl),
+        ">>"=>(7, ShBinOp::Shr),
+        "+"=>(8, ShBinOp::Add),
+        "-"=>(8, ShBinOp::Sub),
+        "*"=>(9, ShBinOp::Mul),
+        "/"=>(9, ShBinOp::Div),
+        "%"=>(9, ShBinOp::Rem),
+        _=>return None
+    })
+}
+
+fn assignop(punct:&str)->Option<ShBinOp>{
+    Some(match punct{
+        "+="=>ShBinOp::AddEq,
+        "-="=>ShBinOp::SubEq,
+        "*="=>ShBinOp::MulEq,
+        "/="=>ShBinOp::DivEq,
+        "%="=>ShBinOp::RemEq,
+        "^="=>ShBinOp::BitXorEq,
+        "&="=>ShBinOp::BitAndEq,
+        "|="=>ShBinOp::BitOrEq,
+        "<<="=>ShBinOp::ShlEq,
+        ">>="=>ShBinOp::ShrEq,
+        _=>return None
+    })
+}
+
+struct ShParser<'a>{
+    file:&'a str,
+    source:&'a str,
+    toks:Vec<ShToken>,
+    pos:usize,
+    in_fn:String,
+    // like rust, Name{ in the condition of an if, while or match starts the block
+    no_struct:bool
+}
+
+impl<'a> ShParser<'a>{
+
+    fn peek(&self)->&ShTok{
+        &self.toks[self.pos].tok
+    }
+
+    fn next(&mut self)->ShTok{
+        let tok = self.toks[self.pos].tok.clone();
+        if self.pos < self.toks.len() - 1{
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn is_punct(&self, punct:&str)->bool{
+        if let ShTok::Punct(p) = self.peek(){
+            return *p == punct
+        }
+        false
+    }
+
+    fn is_keyword(&self, keyword:&str)->bool{
+        if let ShTok::Ident(id) = self.peek(){
+            return id == keyword
+        }
+        false
+    }
+
+    fn error(&self, msg:&str)->ShDiag{
+        let line = self.toks[self.pos].line;
+        ShDiag{
+            span:ShSpan{file:self.file.to_string(), line:line, src:line_src(self.source, line)},
+            in_fn:self.in_fn.clone(),
+            msg:msg.to_string()
+        }
+    }
+
+    fn expect_punct(&mut self, punct:&str)->Result<(), ShDiag>{
+        if !self.is_punct(punct){
+            return Err(self.error(&format!("Expected {}", punct)))
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn expect_ident(&mut self)->Result<String, ShDiag>{
+        if let ShTok::Ident(id) = self.peek(){
+            let id = id.clone();
+            self.next();
+            return Ok(id)
+        }
+        Err(self.error("Expected an identifier"))
+    }
+
+    fn parse_type(&mut self)->Result<String, ShDiag>{
+        if self.is_punct("["){
+            self.next();
+            let elem = self.parse_type()?;
+            if elem.starts_with('['){
+                return Err(self.error("Arrays of arrays are not supported"))
+            }
+            self.expect_punct(";")?;
+            let len = if let ShTok::Int(len) = self.peek(){*len}else{0};
+            if len <= 0{
+                return Err(self.error("Array length has to be an integer above 0"))
+            }
+            self.next();
+            self.expect_punct("]")?;
+            return Ok(format!("[{};{}]", elem, len))
+        }
+        let ty = self.expect_ident()?;
+        // struct names start with a capital, the type checker finds out if the struct exists
+        if !TYPES.contains(&ty.as_ref()) && !ty.starts_with(|c:char| c.is_ascii_uppercase()){
+            self.pos -= 1;
+            return Err(self.error(&format!("Unknown type {}, use one of {} or a struct", ty, TYPES.join(", "))))
+        }
+        if self.is_punct("::"){
+            return Err(self.error("Only simple typenames such as float or vec4 are supported"))
+        }
+        Ok(ty)
+    }
+
+    // parses with struct literals allowed or not, brackets allow them again
+    fn with_structs<T, F>(&mut self, allow:bool, f:F)->Result<T, ShDiag>
+    where F: FnOnce(&mut Self)->Result<T, ShDiag>
+    {
+        let old = self.no_struct;
+        self.no_struct = !allow;
+        let ret = f(self);
+        self.no_struct = old;
+        ret
+    }
+
+    // the source of the tokens from start up to here, for diagnostics like shader_ast! makes them
+    fn span(&self, start:usize)->ShSpan{
+        let mut src = String::new();
+        for i in start..self.pos{
+            let tok = &self.toks[i];
+            if i != start && tok.start > self.toks[i - 1].end{
+                src.push_str(" ");
+            }
+            src.push_str(&self.source[tok.start..tok.end]);
+        }
+        if let Some(pos) = src.find('{'){
+            src.truncate(pos);
+            src.push_str("{...}");
+        }
+        ShSpan{
+            file:self.file.to_string(),
+            line:self.toks[start].line,
+            src:src
+        }
+    }
+
+    fn parse_root(&mut self)->Result<ShAst, ShDiag>{
+        let mut ast = ShAst{
+            span:ShSpan{file:self.file.to_string(), line:1, src:String::new()},
+            types:Vec::new(),
+            vars:Vec::new(),
+            consts:Vec::new(),
+            fns:Vec::new()
+        };
+        // the whole block copied out of shader_ast!({...}) is fine too
+        let braced = self.is_punct("{");
+        if braced{
+            self.next();
+        }
+        loop{
+            if braced && self.is_punct("}"){
+                self.next();
+                if *self.peek() != ShTok::End{
+                    return Err(self.error("Unexpected tokens after the shader block"))
+                }
+                break;
+            }
+            if *self.peek() == ShTok::End{
+                if braced{
+                    return Err(self.error("Expected }"))
+                }
+                break;
+            }
+            if self.is_keyword("let"){
+                ast.vars.push(self.parse_var_def()?);
+            }
+            else if self.is_keyword("const"){
+                ast.consts.push(self.parse_const_def()?);
+            }
+            else if self.is_keyword("fn"){
+                self.next();
+                ast.fns.push(self.parse_fn_def(None)?);
+            }
+            else if self.is_keyword("struct"){
+                ast.types.push(self.parse_struct_def()?);
+            }
+            else if self.is_keyword("impl"){
+                self.next();
+                let self_ty = self.parse_type()?;
+                if self.is_keyword("for"){
+                    return Err(self.error("Trait impls are not supported"))
+                }
+                self.expect_punct("{")?;
+                while !self.is_punct("}"){
+                    if !self.is_keyword("fn"){
+                        return Err(self.error("Only methods are supported in an impl"))
+                    }
+                    self.next();
+                    ast.fns.push(self.parse_fn_def(Some(&self_ty))?);
+                }
+                self.next();
+            }
+            else if self.is_punct(";"){
+                self.next();
+            }
+            else if let ShTok::Ident(_) = self.peek(){
+                return Err(self.error("Unexpected statement"))
+            }
+            else{
+                return Err(self.error("Statement not expected here"))
+            }
+        }
+        Ok(ast)
+    }
+
+    fn parse_var_def(&mut self)->Result<ShVar, ShDiag>{
+        self.next();
+        let name = self.expect_ident()?;
+        if !self.is_punct(":"){
+            return Err(self.error("Please give the variable a type of the form float<Local> or vec4<Uniform>"))
+        }
+        self.next();
+        let ty = self.parse_type()?;
+        if !self.is_punct("<"){
+            return Err(self.error("type should have storage specifier like float<Uniform> or float<Local>"))
+        }
+        self.next();
+        let store_name = self.expect_ident()?;
+        let store = match store_name.as_ref(){
+            "Uniform"=>ShVarStore::Uniform,
+            "UniformDl"=>ShVarStore::UniformDl,
+            "UniformCx"=>ShVarStore::UniformCx,
+            "Instance"=>ShVarStore::Instance,
+            "Geometry"=>ShVarStore::Geometry,
+            "Texture"=>ShVarStore::Texture,
+            "Local"=>ShVarStore::Local,
+            "Varying"=>ShVarStore::Varying,
+            _=>{
+                self.pos -= 1;
+                return Err(self.error(&format!("Unknown storage {}, use one of {}", store_name, STORES.join(", "))))
+            }
+        };
+        if !self.is_punct(">"){
+            return Err(self.error("Please pass one storage arg like float<Uniform> or float<Local>"))
+        }
+        self.next();
+        // shader_ast! ignores an initializer here as well
+        if self.is_punct("="){
+            self.next();
+            self.parse_expr()?;
+        }
+        self.expect_punct(";")?;
+        Ok(ShVar{
+            name:name,
+            ty:ty,
+            store:store
+        })
+    }
+
+    fn parse_const_def(&mut self)->Result<ShConst, ShDiag>{
+        self.next();
+        let name = self.expect_ident()?;
+        self.expect_punct(":")?;
+        let ty = self.parse_type()?;
+        self.expect_punct("=")?;
+        let value = self.parse_expr()?;
+        self.expect_punct(";")?;
+        Ok(ShConst{
+            name:name,
+            ty:ty,
+            value:value
+        })
+    }
+
+    // structs can't be instance or uniform data so they take no slots
+    fn parse_struct_def(&mut self)->Result<ShType, ShDiag>{
+        self.next();
+        let name = self.expect_ident()?;
+        if !name.starts_with(|c:char| c.is_ascii_uppercase()){
+            self.pos -= 1;
+            return Err(self.error(&format!("Unknown type {}, use one of {} or a struct", name, TYPES.join(", "))))
+        }
+        if !self.is_punct("{"){
+            return Err(self.error("Structs need named fields"))
+        }
+        self.next();
+        let mut fields = Vec::new();
+        while !self.is_punct("}"){
+            let field_name = self.expect_ident()?;
+            self.expect_punct(":")?;
+            let field_ty = self.parse_type()?;
+            fields.push(ShTypeField::new(&field_name, &field_ty));
+            if !self.is_punct("}"){
+                self.expect_punct(",")?;
+            }
+        }
+        self.next();
+        Ok(ShType{
+            name:name,
+            slots:0,
+            prim:false,
+            fields:fields
+        })
+    }
+
+    // methods of an impl become Type_method with self passed as the first arg _self
+    fn parse_fn_def(&mut self, self_ty:Option<&str>)->Result<ShFn, ShDiag>{
+        let name = self.expect_ident()?;
+        let name = if let Some(self_ty) = self_ty{format!("{}_{}", self_ty, name)}else{name};
+        self.in_fn = name.clone();
+        self.expect_punct("(")?;
+        let mut args = Vec::new();
+        while !self.is_punct(")"){
+            if let Some(self_ty) = self_ty{
+                let start = self.pos;
+                if self.is_punct("&"){
+                    self.next();
+                }
+                if self.is_keyword("mut"){
+                    self.next();
+                    if self.is_keyword("self"){
+                        return Err(self.error("Methods can't change self, return the changed struct instead"))
+                    }
+                }
+                if self.is_keyword("self"){
+                    self.next();
+                    args.push(ShFnArg::new("_self", self_ty));
+                    if !self.is_punct(")"){
+                        self.expect_punct(",")?;
+                    }
+                    continue;
+                }
+                self.pos = start;
+            }
+            let arg_name = self.expect_ident()?;
+            if !self.is_punct(":"){
+                return Err(self.error("arg type not simple"))
+            }
+            self.next();
+            let arg_ty = self.parse_type()?;
+            args.push(ShFnArg::new(&arg_name, &arg_ty));
+            if !self.is_punct(")"){
+                self.expect_punct(",")?;
+            }
+        }
+        self.next();
+        let ret = if self.is_punct("->"){
+            self.next();
+            self.parse_type()?
+        }
+        else{
+            "void".to_string()
+        };
+        let block = self.parse_block()?;
+        self.in_fn = String::new();
+        Ok(ShFn{
+            name:name,
+            args:args,
+            ret:ret,
+            block:Some(block)
+        })
+    }
+
+    fn parse_block(&mut self)->Result<ShBlock, ShDiag>{
+        self.with_structs(true, |p| p.parse_block_inner())
+    }
+
+    fn parse_block_inner(&mut self)->Result<ShBlock, ShDiag>{
+        self.expect_punct("{")?;
+        let mut stmts = Vec::new();
+        let mut spans = Vec::new();
+        loop{
+            if self.is_punct("}"){
+                self.next();
+                break;
+            }
+            if *self.peek() == ShTok::End{
+                return Err(self.error("Expected }"))
+            }
+            if self.is_punct(";"){
+                self.next();
+                continue;
+            }
+            let start = self.pos;
+            let stmt = if self.is_keyword("let"){
+                let stmt = ShStmt::ShLet(self.parse_let()?);
+                self.expect_punct(";")?;
+                stmt
+            }
+            else if self.is_keyword("fn") || self.is_keyword("const") || self.is_keyword("struct") || self.is_keyword("impl"){
+                return Err(self.error("Shader functions don't support items"))
+            }
+            else{
+                // if, while, for and blocks end a statement without a ;
+                let block_like = self.is_keyword("if") || self.is_keyword("while") || self.is_keyword("for") || self.is_keyword("match") || self.is_punct("{");
+                let expr = if block_like{self.parse_primary()?}else{self.parse_expr()?};
+                if self.is_punct(";"){
+                    self.next();
+                    ShStmt::ShSemi(expr)
+                }
+                else if block_like || self.is_punct("}"){
+                    ShStmt::ShExpr(expr)
+                }
+                else{
+                    return Err(self.error("Expected ;"))
+                }
+            };
+            stmts.push(Box::new(stmt));
+            spans.push(self.span(start));
+        }
+        Ok(ShBlock{
+            stmts:stmts,
+            spans:spans
+        })
+    }
+
+    fn parse_let(&mut self)->Result<ShLet, ShDiag>{
+        self.next();
+        if self.is_keyword("mut"){
+            self.next();
+        }
+        let name = self.expect_ident()?;
+        let ty = if self.is_punct(":"){
+            self.next();
+            self.parse_type()?
+        }
+        else{
+            "".to_string()
+        };
+        if !self.is_punct("="){
+            return Err(self.error("let pattern misses initializer"))
+        }
+        self.next();
+        let init = self.parse_expr()?;
+        Ok(ShLet{
+            name:name,
+            ty:ty,
+            init:Box::new(init)
+        })
+    }
+
+    // assignment is right associative and binds the weakest
+    fn parse_expr(&mut self)->Result<ShExpr, ShDiag>{
+        let left = self.parse_binary(1)?;
+        if self.is_punct("="){
+            self.next();
+            let right = self.parse_expr()?;
+            return Ok(ShExpr::ShAssign(ShAssign{left:Box::new(left), right:Box::new(right)}))
+        }
+        if let ShTok::Punct(punct) = self.peek(){
+            if let Some(op) = assignop(punct){
+                self.next();
+                let right = self.parse_expr()?;
+                return Ok(ShExpr::ShAssignOp(ShAssignOp{left:Box::new(left), op:op, right:Box::new(right)}))
+            }
+        }
+        Ok(left)
+    }
+
+    fn peek_binop(&self)->Option<(usize, ShBinOp)>{
+        if let ShTok::Punct(punct) = self.peek(){
+            return binop_prec(punct)
+        }
+        None
+    }
+
+    fn parse_binary(&mut self, min_prec:usize)->Result<ShExpr, ShDiag>{
+        let mut left = self.parse_unary()?;
+        while let Some((prec, op)) = self.peek_binop(){
+            if prec < min_prec{
+                break;
+            }
+            self.next();
+            let right = self.parse_binary(prec + 1)?;
+            left = ShExpr::ShBinary(ShBinary{left:Box::new(left), op:op, right:Box::new(right)});
+            // comparisons don't chain in rust
+            if let Some((3, _)) = self.peek_binop(){
+                if prec == 3{
+                    return Err(self.error("Comparison operators cannot be chained"))
+                }
+            }
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self)->Result<ShExpr, ShDiag>{
+        let op = if self.is_punct("!"){
+            ShUnaryOp::Not
+        }
+        else if self.is_punct("-"){
+            ShUnaryOp::Neg
+        }
+        else if self.is_punct("*"){
+            return Err(self.error("Deref not implemented"))
+        }
+        else{
+            return self.parse_postfix()
+        };
+        self.next();
+        let expr = self.parse_unary()?;
+        Ok(ShExpr::ShUnary(ShUnary{op:op, expr:Box::new(expr)}))
+    }
+
+    fn parse_postfix(&mut self)->Result<ShExpr, ShDiag>{
+        let mut expr = self.parse_primary()?;
+        loop{
+            if self.is_punct("."){
+                self.next();
+                let member = if let ShTok::Ident(member) = self.peek(){member.clone()}else{
+                    return Err(self.error("No unnamed members supported"))
+                };
+                self.next();
+                if self.is_punct("("){
+                    let args = self.parse_args()?;
+                    expr = ShExpr::ShMethodCall(ShMethodCall{base:Box::new(expr), method:member, args:args});
+                    continue;
+                }
+                if let Err(msg) = check_swizzle(&member){
+                    self.pos -= 1;
+                    return Err(self.error(&msg))
+                }
+                expr = ShExpr::ShField(ShField{base:Box::new(expr), member:member});
+            }
+            else if self.is_punct("["){
+                self.next();
+                let index = self.with_structs(true, |p| p.parse_expr())?;
+                self.expect_punct("]")?;
+                expr = ShExpr::ShIndex(ShIndex{base:Box::new(expr), index:Box::new(index)});
+            }
+            else if self.is_punct("("){
+                let call = if let ShExpr::ShId(id) = &expr{id.name.clone()}else{
+                    return Err(self.error("call identifier not simple"))
+                };
+                let args = self.parse_args()?;
+                expr = ShExpr::ShCall(ShCall{call:call, args:args});
+            }
+            else{
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    fn parse_args(&mut self)->Result<Vec<Box<ShExpr>>, ShDiag>{
+        self.expect_punct("(")?;
+        let mut args = Vec::new();
+        while !self.is_punct(")"){
+            args.push(Box::new(self.with_structs(true, |p| p.parse_expr())?));
+            if !self.is_punct(")"){
+                self.expect_punct(",")?;
+            }
+        }
+        self.next();
+        Ok(args)
+    }
+
+    fn parse_primary(&mut self)->Result<ShExpr, ShDiag>{
+        match self.peek().clone(){
+            ShTok::Int(value)=>{
+                self.next();
+                Ok(ShExpr::ShLit(ShLit::Int(value)))
+            }
+            ShTok::Float(value)=>{
+                self.next();
+                Ok(ShExpr::ShLit(ShLit::Float(value)))
+            }
+            ShTok::Str(value)=>{
+                self.next();
+                Ok(ShExpr::ShLit(ShLit::Str(value)))
+            }
+            ShTok::Punct("(")=>{
+                self.next();
+                let expr = self.with_structs(true, |p| p.parse_expr())?;
+                if self.is_punct(","){
+                    return Err(self.error("Unsupported syntax for shader"))
+                }
+                self.expect_punct(")")?;
+                Ok(ShExpr::ShParen(ShParen{expr:Box::new(expr)}))
+            }
+            ShTok::Punct("{")=>{
+                Ok(ShExpr::ShBlock(self.parse_block()?))
+            }
+            ShTok::Punct("[")=>{
+                self.next();
+                self.with_structs(true, |p| p.parse_array())
+            }
+            ShTok::Ident(id)=>match id.as_ref(){
+                "true"|"false"=>{
+                    self.next();
+                    Ok(ShExpr::ShLit(ShLit::Bool(id == "true")))
+                }
+                "if"=>self.parse_if(),
+                "while"=>{
+                    self.next();
+                    let cond = self.with_structs(false, |p| p.parse_expr())?;
+                    let body = self.parse_block()?;
+                    Ok(ShExpr::ShWhile(ShWhile{cond:Box::new(cond), body:body}))
+                }
+                "for"=>self.parse_for(),
+                "match"=>self.parse_match(),
+                "return"=>{
+                    self.next();
+                    if self.is_punct(";") || self.is_punct("}"){
+                        return Ok(ShExpr::ShReturn(ShReturn{expr:None}))
+                    }
+                    let expr = self.parse_expr()?;
+                    Ok(ShExpr::ShReturn(ShReturn{expr:Some(Box::new(expr))}))
+                }
+                "break"=>{
+                    self.next();
+                    Ok(ShExpr::ShBreak(ShBreak{}))
+                }
+                "continue"=>{
+                    self.next();
+                    Ok(ShExpr::ShContinue(ShContinue{}))
+                }
+                "let"=>Err(self.error("Not implemented Expr::Let")),
+                "loop"|"fn"|"const"|"struct"|"mut"|"as"|"in"=>Err(self.error("Unsupported syntax for shader")),
+                // self is a reserved word in some shader languages
+                "self"=>{
+                    self.next();
+                    Ok(ShExpr::ShId(ShId{name:"_self".to_string()}))
+                }
+                _=>{
+                    self.next();
+                    // Type::fn(..) calls a fn of an impl without self
+                    if self.is_punct("::"){
+                        self.next();
+                        let name = self.expect_ident()?;
+                        if !self.is_punct("("){
+                            return Err(self.error("type not simple"))
+                        }
+                        return Ok(ShExpr::ShId(ShId{name:format!("{}_{}", id, name)}))
+                    }
+                    if self.is_punct("{") && !self.no_struct{
+                        return self.parse_struct_lit(id)
+                    }
+                    Ok(ShExpr::ShId(ShId{name:id}))
+                }
+            },
+            _=>Err(self.error("Unsupported syntax for shader"))
+        }
+    }
+
+    fn parse_struct_lit(&mut self, ty:String)->Result<ShExpr, ShDiag>{
+        self.next();
+        let mut fields = Vec::new();
+        while !self.is_punct("}"){
+            if self.is_punct(".."){
+                return Err(self.error("Struct update syntax is not supported, give every field"))
+            }
+            let name = self.expect_ident()?;
+            self.expect_punct(":")?;
+            let expr = self.with_structs(true, |p| p.parse_expr())?;
+            fields.push(ShStructLitField{name:name, expr:Box::new(expr)});
+            if !self.is_punct("}"){
+                self.expect_punct(",")?;
+            }
+        }
+        self.next();
+        Ok(ShExpr::ShStructLit(ShStructLit{ty:ty, fields:fields}))
+    }
+
+    // [a, b] or [a; 4], which is written out as [a, a, a, a]
+    fn parse_array(&mut self)->Result<ShExpr, ShDiag>{
+        let mut elems = Vec::new();
+        while !self.is_punct("]"){
+            let elem = self.parse_expr()?;
+            if elems.len() == 0 && self.is_punct(";"){
+                self.next();
+                let len = if let ShTok::Int(len) = self.peek(){*len}else{0};
+                if len <= 0{
+                    return Err(self.error("Array length has to be an integer above 0"))
+                }
+                self.next();
+                self.expect_punct("]")?;
+                return Ok(ShExpr::ShArrayLit(ShArrayLit{elems:vec![Box::new(elem); len as usize]}))
+            }
+            elems.push(Box::new(elem));
+            if !self.is_punct("]"){
+                self.expect_punct(",")?;
+            }
+        }
+        self.next();
+        Ok(ShExpr::ShArrayLit(ShArrayLit{elems:elems}))
+    }
+
+    fn parse_match(&mut self)->Result<ShExpr, ShDiag>{
+        self.next();
+        let expr = self.with_structs(false, |p| p.parse_expr())?;
+        self.expect_punct("{")?;
+        let mut arms = Vec::new();
+        while !self.is_punct("}"){
+            let mut pats = Vec::new();
+            let mut wild = false;
+            loop{
+                if self.is_keyword("_"){
+                    wild = true;
+                    self.next();
+                }
+                else{
+                    let neg = self.is_punct("-");
+                    if neg{
+                        self.next();
+                    }
+                    if let ShTok::Int(value) = self.peek(){
+                        pats.push(if neg{-*value}else{*value});
+                        self.next();
+                    }
+                    else{
+                        return Err(self.error("Match patterns have to be integers or _"))
+                    }
+                }
+                if !self.is_punct("|"){
+                    break;
+                }
+                self.next();
+            }
+            if wild && pats.len() != 0{
+                return Err(self.error("Don't mix _ with other patterns"))
+            }
+            if self.is_keyword("if"){
+                return Err(self.error("Match guards are not supported"))
+            }
+            self.expect_punct("=>")?;
+            // an arm without a block is a block with the expression as value
+            let body = if self.is_punct("{"){
+                let body = self.parse_block()?;
+                if self.is_punct(","){
+                    self.next();
+                }
+                body
+            }
+            else{
+                let start = self.pos;
+                let body = self.with_structs(true, |p| p.parse_expr())?;
+                let span = self.span(start);
+                if !self.is_punct("}"){
+                    self.expect_punct(",")?;
+                }
+                ShBlock{stmts:vec![Box::new(ShStmt::ShExpr(body))], spans:vec![span]}
+            };
+            arms.push(ShMatchArm{pats:pats, body:body});
+        }
+        self.next();
+        Ok(ShExpr::ShMatch(ShMatch{expr:Box::new(expr), arms:arms}))
+    }
+
+    fn parse_if(&mut self)->Result<ShExpr, ShDiag>{
+        self.next();
+        let cond = self.with_structs(false, |p| p.parse_expr())?;
+        let then_branch = self.parse_block()?;
+        let else_branch = if self.is_keyword("else"){
+            self.next();
+            if self.is_keyword("if"){
+                Some(Box::new(self.parse_if()?))
+            }
+            else{
+                Some(Box::new(ShExpr::ShBlock(self.parse_block()?)))
+            }
+        }
+        else{
+            None
+        };
+        Ok(ShExpr::ShIf(ShIf{
+            cond:Box::new(cond),
+            then_branch:then_branch,
+            else_branch:else_branch
+        }))
+    }
+
+    fn parse_for(&mut self)->Result<ShExpr, ShDiag>{
+        self.next();
+        let iter = if let ShTok::Ident(iter) = self.peek(){iter.clone()}else{
+            return Err(self.error("Use simple identifier for for loop"))
+        };
+        self.next();
+        if !self.is_keyword("in"){
+            return Err(self.error("Expected in"))
+        }
+        self.next();
+        if self.is_punct(".."){
+            return Err(self.error("Must provide from range expression"))
+        }
+        let from = self.with_structs(false, |p| p.parse_binary(1))?;
+        if !self.is_punct(".."){
+            return Err(self.error("Must provide range expression"))
+        }
+        self.next();
+        if self.is_punct("{"){
+            return Err(self.error("Must provide to range expression"))
+        }
+        let to = self.with_structs(false, |p| p.parse_binary(1))?;
+        let body = self.parse_block()?;
+        Ok(ShExpr::ShForLoop(ShForLoop{
+            iter:iter,
+            from:Box::new(from),
+            to:Box::new(to),
+            body:body
+        }))
+    }
+}
+
+impl ShAst{
+    // file is only used to point diagnostics at, the source is the inside of a shader_ast! block
+    pub fn parse(file:&str, source:&str)->Result<ShAst, ShDiag>{
+        let mut parser = ShParser{
+            file:file,
+            source:source,
+            toks:tokenize(file, source)?,
+            pos:0,
+            in_fn:String::new(),
+            no_struct:false
+        };
+        parser.parse_root()
+    }
+}
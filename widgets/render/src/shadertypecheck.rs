@@ -0,0 +1,700 @@
+// Type checks a shader before it is generated for any target, so mistakes are reported
+// with the rust source they came from instead of as a gpu compiler log. it accepts what
+// shadergen can generate for all targets, and walks the functions each entry point reaches
+
+use crate::cx::*;
+use crate::shadertyrules::*;
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+pub struct ShDiag{
+    pub span:ShSpan,
+    pub in_fn:String,
+    pub msg:String
+}
+
+impl fmt::Display for ShDiag{
+    fn fmt(&self, f:&mut fmt::Formatter)->fmt::Result{
+        write!(f, "{}:{}: {}", self.span.file, self.span.line, self.msg)?;
+        if self.in_fn.len() != 0{
+            write!(f, "\n    in fn {}", self.in_fn)?;
+        }
+        if self.span.src.len() != 0{
+            write!(f, ": {}", self.span.src)?;
+        }
+        Ok(())
+    }
+}
+
+// the shader compilers return the diagnostics as one error
+impl From<Vec<ShDiag>> for SlErr{
+    fn from(diags:Vec<ShDiag>)->SlErr{
+        SlErr{msg:diags.iter().map(|diag| diag.to_string()).collect::<Vec<String>>().join("\n")}
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TyStage{
+    Constant,
+    Vertex,
+    Pixel
+}
+
+#[derive(Clone, PartialEq)]
+enum TyDecl{
+    Arg,
+    Let,
+    LoopIndex
+}
+
+struct TyCx<'a>{
+    sh:&'a Shader,
+    stage:TyStage,
+    in_fn:String,
+    ret:String,
+    span:ShSpan,
+    scope:Vec<(String, String, TyDecl)>,
+    fn_todo:Vec<String>,
+    diags:Vec<ShDiag>
+}
+
+fn store_name(store:&ShVarStore)->&'static str{
+    match store{
+        ShVarStore::Uniform=>"Uniform",
+        ShVarStore::UniformDl=>"UniformDl",
+        ShVarStore::UniformCx=>"UniformCx",
+        ShVarStore::Instance=>"Instance",
+        ShVarStore::Geometry=>"Geometry",
+        ShVarStore::Texture=>"Texture",
+        ShVarStore::Local=>"Local",
+        ShVarStore::Varying=>"Varying"
+    }
+}
+
+impl<'a> TyCx<'a>{
+    fn err(&mut self, msg:String)->Option<String>{
+        let diag = ShDiag{span:self.span.clone(), in_fn:self.in_fn.clone(), msg:msg};
+        // functions reached from both entry points report the same thing twice
+        if !self.diags.contains(&diag){
+            self.diags.push(diag);
+        }
+        None
+    }
+
+    // the result of one of the shared rules in shadertyrules
+    fn rule(&mut self, ty:Result<String, String>)->Option<String>{
+        match ty{
+            Ok(ty)=>Some(ty),
+            Err(msg)=>self.err(msg)
+        }
+    }
+
+    // textures and bool vectors have no ShType, they have no slots
+    fn is_type(&self, ty:&str)->bool{
+        if let Some((elem, _)) = Shader::array_type(ty){
+            return Shader::array_type(&elem).is_none() && self.is_type(&elem)
+        }
+        self.sh.find_type(ty).is_some() || ty == "texture2d" || is_bool_ty(ty)
+    }
+
+    fn is_struct(&self, ty:&str)->bool{
+        self.sh.find_type(ty).map_or(false, |shty| !shty.prim)
+    }
+
+    // a struct that has itself as a field, maybe through another struct
+    fn contains_struct(&self, ty:&str, name:&str, depth:usize)->bool{
+        if depth > 16{
+            return true
+        }
+        if let Some(shty) = self.sh.find_type(ty){
+            if !shty.prim{
+                return shty.fields.iter().any(|field| field.ty == name || self.contains_struct(&field.ty, name, depth + 1))
+            }
+        }
+        false
+    }
+
+    fn find_scope(&self, name:&str)->Option<&(String, String, TyDecl)>{
+        self.scope.iter().rev().find(|(n, _, _)| n == name)
+    }
+
+    fn check_fn(&mut self, shfn:&ShFn){
+        self.in_fn = shfn.name.clone();
+        self.ret = shfn.ret.clone();
+        self.scope.clear();
+        if let Some(span) = shfn.block.as_ref().and_then(|block| block.spans.first()){
+            self.span = ShSpan{src:String::new(), ..span.clone()};
+        }
+        for arg in &shfn.args{
+            if !self.is_type(&arg.ty){
+                self.err(format!("Argument {} has unknown type {}", arg.name, arg.ty));
+            }
+            self.scope.push((arg.name.clone(), arg.ty.clone(), TyDecl::Arg));
+        }
+        if shfn.ret != "void" && !self.is_type(&shfn.ret){
+            self.err(format!("Unknown return type {}", shfn.ret));
+        }
+        if Shader::array_type(&shfn.ret).is_some(){
+            self.err(format!("fn {} can't return an array", shfn.name));
+        }
+        if let Some(block) = &shfn.block{
+            self.check_block(block);
+        }
+    }
+
+    fn check_block(&mut self, block:&ShBlock){
+        let scope_len = self.scope.len();
+        let outer_span = self.span.clone();
+        for (i, stmt) in block.stmts.iter().enumerate(){
+            if let Some(span) = block.spans.get(i){
+                self.span = span.clone();
+            }
+            match &**stmt{
+                ShStmt::ShLet(stmt)=>self.check_let(stmt),
+                ShStmt::ShExpr(expr) | ShStmt::ShSemi(expr)=>{
+                    self.stmt(expr);
+                }
+            }
+        }
+        self.span = outer_span;
+        self.scope.truncate(scope_len);
+    }
+
+    // if and match are statements in a block and values everywhere else
+    fn stmt(&mut self, expr:&ShExpr){
+        match expr{
+            ShExpr::ShIf(x)=>{
+                self.cond(&x.cond, "if");
+                self.check_block(&x.then_branch);
+                if let Some(else_branch) = &x.else_branch{
+                    self.stmt(else_branch);
+                }
+            },
+            ShExpr::ShMatch(x)=>{
+                self.scrutinee(x);
+                for arm in &x.arms{
+                    self.check_block(&arm.body);
+                }
+            },
+            _=>{
+                self.expr(expr);
+            }
+        }
+    }
+
+    // the branch of an if or the arm of a match used as a value
+    fn branch(&mut self, block:&ShBlock)->Option<String>{
+        if block.stmts.len() == 1{
+            if let ShStmt::ShExpr(expr) = &*block.stmts[0]{
+                let outer_span = self.span.clone();
+                if let Some(span) = block.spans.first(){
+                    self.span = span.clone();
+                }
+                let ty = self.expr(expr);
+                self.span = outer_span;
+                return ty
+            }
+        }
+        self.err("An if or match used as a value needs a single expression in every branch".to_string())
+    }
+
+    fn scrutinee(&mut self, x:&ShMatch){
+        if let Some(ty) = self.expr(&x.expr){
+            if ty != "int"{
+                self.err(format!("Can only match on an int, not {}", ty));
+            }
+        }
+        if !x.arms.iter().any(|arm| arm.pats.len() == 0){
+            self.err("A match on an int needs a _ arm".to_string());
+        }
+    }
+
+    fn array_lit(&mut self, x:&ShArrayLit)->Option<String>{
+        let mut elem_ty:Option<String> = None;
+        for elem in &x.elems{
+            let ty = self.expr(elem)?;
+            if Shader::array_type(&ty).is_some(){
+                return self.err("Arrays of arrays are not supported".to_string())
+            }
+            if let Some(elem_ty) = &elem_ty{
+                if *elem_ty != ty{
+                    return self.err(format!("Array elements have to be the same type, got {} and {}", elem_ty, ty))
+                }
+            }
+            elem_ty = Some(ty);
+        }
+        if let Some(elem_ty) = elem_ty{
+            return Some(format!("[{};{}]", elem_ty, x.elems.len()))
+        }
+        self.err("Arrays need at least one element".to_string())
+    }
+
+    fn check_let(&mut self, stmt:&ShLet){
+        if stmt.ty != "" && !self.is_type(&stmt.ty){
+            self.err(format!("Let {} has unknown type {}", stmt.name, stmt.ty));
+            return
+        }
+        let init = if let ShExpr::ShArrayLit(x) = &*stmt.init{
+            self.array_lit(x)
+        }
+        else{
+            self.expr(&stmt.init)
+        };
+        if let Some(init) = init{
+            if init == "void"{
+                self.err(format!("Let {} is initialized with something that has no value", stmt.name));
+            }
+            else if Shader::array_type(&init).is_some() && !matches!(&*stmt.init, ShExpr::ShArrayLit(_)){
+                self.err(format!("Arrays can't be copied, index the elements of {}", init));
+            }
+            else if stmt.ty != "" && stmt.ty != init{
+                self.err(format!("Let {} is declared {} but initialized with {}", stmt.name, stmt.ty, init));
+            }
+            let ty = if stmt.ty != ""{stmt.ty.clone()}else{init};
+            self.scope.push((stmt.name.clone(), ty, TyDecl::Let));
+        }
+        else{
+            // keep going without cascading unknown identifiers
+            self.scope.push((stmt.name.clone(), stmt.ty.clone(), TyDecl::Let));
+        }
+    }
+
+    fn expr(&mut self, expr:&ShExpr)->Option<String>{
+        match expr{
+            ShExpr::ShId(x)=>self.id(x),
+            ShExpr::ShLit(x)=>Some(match x{
+                ShLit::Int(_)=>"int",
+                ShLit::Float(_)=>"float",
+                ShLit::Str(_)=>"string",
+                ShLit::Bool(_)=>"bool"
+            }.to_string()),
+            ShExpr::ShField(x)=>self.field(x),
+            ShExpr::ShIndex(x)=>{
+                let base = self.expr(&x.base);
+                let index = self.expr(&x.index);
+                let (base, index) = (base?, index?);
+                if index != "int"{
+                    return self.err(format!("Index has to be an int, not {}", index))
+                }
+                // arrays give their element, matrices a column and vectors a float
+                if let Some((elem, _)) = Shader::array_type(&base){
+                    return Some(elem)
+                }
+                self.rule(index_ty(&base))
+            },
+            ShExpr::ShAssign(x)=>{
+                let left = self.assign_target(&x.left);
+                let right = self.expr(&x.right);
+                let (left, right) = (left?, right?);
+                if left != right{
+                    return self.err(format!("Cannot assign {} to {}", right, left))
+                }
+                if Shader::array_type(&left).is_some(){
+                    return self.err(format!("Arrays can't be assigned whole, assign the elements of {}", left))
+                }
+                Some(left)
+            },
+            ShExpr::ShAssignOp(x)=>{
+                let left = self.assign_target(&x.left);
+                let right = self.expr(&x.right);
+                self.rule(assign_op_ty(x.op.to_string(), &left?, &right?))
+            },
+            ShExpr::ShBinary(x)=>{
+                let left = self.expr(&x.left);
+                let right = self.expr(&x.right);
+                self.rule(binary_ty(x.op.to_string(), &left?, &right?))
+            },
+            ShExpr::ShUnary(x)=>{
+                let ty = self.expr(&x.expr)?;
+                let op = match x.op{
+                    ShUnaryOp::Not=>"!",
+                    ShUnaryOp::Neg=>"-"
+                };
+                self.rule(unary_ty(op, &ty))
+            },
+            ShExpr::ShParen(x)=>self.expr(&x.expr),
+            ShExpr::ShBlock(x)=>{
+                self.check_block(x);
+                Some("void".to_string())
+            },
+            ShExpr::ShCall(x)=>self.call(x),
+            ShExpr::ShIf(x)=>{
+                self.cond(&x.cond, "if");
+                let then = self.branch(&x.then_branch);
+                let else_ty = match x.else_branch.as_ref().map(|e| &**e){
+                    Some(ShExpr::ShBlock(block))=>self.branch(block),
+                    Some(else_branch)=>self.expr(else_branch),
+                    None=>return self.err("An if used as a value needs an else".to_string())
+                };
+                let (then, else_ty) = (then?, else_ty?);
+                if then != else_ty{
+                    return self.err(format!("If branches have different types {} and {}", then, else_ty))
+                }
+                Some(then)
+            },
+            ShExpr::ShMatch(x)=>{
+                self.scrutinee(x);
+                let mut ty:Option<String> = None;
+                for arm in &x.arms{
+                    let arm_ty = self.branch(&arm.body)?;
+                    if let Some(ty) = &ty{
+                        if *ty != arm_ty{
+                            return self.err(format!("Match arms have different types {} and {}", ty, arm_ty))
+                        }
+                    }
+                    ty = Some(arm_ty);
+                }
+                ty
+            },
+            ShExpr::ShMethodCall(x)=>self.method_call(x),
+            ShExpr::ShStructLit(x)=>self.struct_lit(x),
+            ShExpr::ShArrayLit(_)=>self.err("Array literals can only initialize a let".to_string()),
+            ShExpr::ShWhile(x)=>{
+                self.cond(&x.cond, "while");
+                self.check_block(&x.body);
+                Some("void".to_string())
+            },
+            ShExpr::ShForLoop(x)=>{
+                for bound in &[&x.from, &x.to]{
+                    if let Some(ty) = self.expr(bound){
+                        if ty != "int"{
+                            self.err(format!("For loop ranges are ints, not {}", ty));
+                        }
+                    }
+                }
+                self.scope.push((x.iter.clone(), "int".to_string(), TyDecl::LoopIndex));
+                self.check_block(&x.body);
+                self.scope.pop();
+                Some("void".to_string())
+            },
+            ShExpr::ShReturn(x)=>{
+                if let Some(expr) = &x.expr{
+                    let ty = self.expr(expr)?;
+                    if self.ret == "void"{
+                        return self.err(format!("fn {} has no return type but returns {}", self.in_fn, ty))
+                    }
+                    if ty != self.ret{
+                        return self.err(format!("fn {} returns {} but this returns {}", self.in_fn, self.ret, ty))
+                    }
+                }
+                else if self.ret != "void"{
+                    return self.err(format!("fn {} has to return {}", self.in_fn, self.ret))
+                }
+                Some("void".to_string())
+            },
+            ShExpr::ShBreak(_) | ShExpr::ShContinue(_)=>Some("void".to_string())
+        }
+    }
+
+    fn id(&mut self, x:&ShId)->Option<String>{
+        if let Some((_, ty, _)) = self.find_scope(&x.name){
+            return Some(ty.clone())
+        }
+        if let Some(cnst) = self.sh.find_const(&x.name){
+            return Some(cnst.ty.clone())
+        }
+        if let Some(var) = self.sh.find_var(&x.name){
+            if self.stage == TyStage::Constant{
+                return self.err(format!("Constants can't use {} variable {}", store_name(&var.store), x.name))
+            }
+            return Some(var.ty.clone())
+        }
+        self.err(format!("Unknown identifier {}", x.name))
+    }
+
+    fn field(&mut self, x:&ShField)->Option<String>{
+        let base = self.expr(&x.base)?;
+        if let Some(shty) = self.sh.find_type(&base){
+            if let Some(field) = shty.fields.iter().find(|f| f.name == x.member){
+                return Some(field.ty.clone())
+            }
+        }
+        self.rule(swizzle_ty(&base, &x.member))
+    }
+
+    // the type of what is assigned to, if it can be written
+    fn assign_target(&mut self, expr:&ShExpr)->Option<String>{
+        let ty = self.expr(expr)?;
+        let mut root = expr;
+        loop{
+            root = match root{
+                ShExpr::ShField(x)=>&x.base,
+                ShExpr::ShIndex(x)=>&x.base,
+                ShExpr::ShParen(x)=>&x.expr,
+                _=>break
+            }
+        }
+        let name = if let ShExpr::ShId(id) = root{&id.name}else{
+            return self.err("Can only assign to a variable, a member or an index".to_string())
+        };
+        if let Some((_, _, decl)) = self.find_scope(name){
+            return match decl{
+                // wgsl function arguments are immutable
+                TyDecl::Arg=>self.err(format!("Argument {} can't be written, copy it into a let", name)),
+                TyDecl::LoopIndex=>self.err(format!("Loop index {} can't be written", name)),
+                TyDecl::Let=>Some(ty)
+            }
+        }
+        if self.sh.find_const(name).is_some(){
+            return self.err(format!("Constant {} can't be written", name))
+        }
+        if let Some(var) = self.sh.find_var(name){
+            match var.store{
+                ShVarStore::Local=>(),
+                ShVarStore::Varying=>if self.stage == TyStage::Pixel{
+                    return self.err(format!("Varying {} can only be written by the vertex shader", name))
+                },
+                _=>return self.err(format!("{} variable {} is read only", store_name(&var.store), name))
+            }
+        }
+        Some(ty)
+    }
+
+    fn cond(&mut self, cond:&ShExpr, what:&str){
+        if let Some(ty) = self.expr(cond){
+            if ty != "bool"{
+                self.err(format!("{} condition has to be a bool, not {}", what, ty));
+            }
+        }
+    }
+
+    fn call(&mut self, x:&ShCall)->Option<String>{
+        let mut args = Vec::new();
+        for arg in &x.args{
+            args.push(self.expr(arg));
+        }
+        let args:Vec<String> = args.into_iter().collect::<Option<Vec<String>>>()?;
+
+        if let Some(shfn) = self.sh.find_fn(&x.call){
+            if shfn.block.is_some(){
+                return self.call_fn(shfn, &args)
+            }
+            return self.call_builtin(shfn, &args)
+        }
+        if self.sh.find_type(&x.call).is_some(){
+            return self.construct(&x.call, &args)
+        }
+        self.err(format!("Unknown function {}", x.call))
+    }
+
+    // a method of an impl is Type_method(self, ..), otherwise a builtin taking self first
+    fn method_call(&mut self, x:&ShMethodCall)->Option<String>{
+        let mut args = vec![self.expr(&x.base)];
+        for arg in &x.args{
+            args.push(self.expr(arg));
+        }
+        let args:Vec<String> = args.into_iter().collect::<Option<Vec<String>>>()?;
+        if let Some(shfn) = self.sh.find_fn(&format!("{}_{}", args[0], x.method)){
+            if shfn.block.is_some(){
+                return self.call_fn(shfn, &args)
+            }
+        }
+        if let Some(shfn) = self.sh.find_fn(&x.method){
+            if shfn.block.is_none(){
+                return self.call_builtin(shfn, &args)
+            }
+        }
+        self.err(format!("{} has no method {}", args[0], x.method))
+    }
+
+    fn struct_lit(&mut self, x:&ShStructLit)->Option<String>{
+        let mut values = Vec::new();
+        for field in &x.fields{
+            values.push(self.expr(&field.expr));
+        }
+        let shty = if let Some(shty) = self.sh.find_type(&x.ty){shty}else{
+            return self.err(format!("Unknown struct {}", x.ty))
+        };
+        if shty.prim{
+            return self.err(format!("{} is not a struct, construct it like {}(..)", x.ty, x.ty))
+        }
+        for (i, field) in x.fields.iter().enumerate(){
+            if x.fields[0..i].iter().any(|f| f.name == field.name){
+                return self.err(format!("Field {} of {} is given twice", field.name, x.ty))
+            }
+            let shfield = if let Some(shfield) = shty.fields.iter().find(|f| f.name == field.name){shfield}else{
+                return self.err(format!("{} has no field {}", x.ty, field.name))
+            };
+            if let Some(value) = &values[i]{
+                if *value != shfield.ty{
+                    return self.err(format!("Field {} of {} is {}, got {}", field.name, x.ty, shfield.ty, value))
+                }
+            }
+        }
+        if let Some(missing) = shty.fields.iter().find(|f| !x.fields.iter().any(|field| field.name == f.name)){
+            return self.err(format!("Missing field {} of {}", missing.name, x.ty))
+        }
+        Some(x.ty.clone())
+    }
+
+    fn call_fn(&mut self, shfn:&ShFn, args:&Vec<String>)->Option<String>{
+        if shfn.name == self.in_fn{
+            return self.err(format!("fn {} calls itself, shaders can't recurse", shfn.name))
+        }
+        if args.len() != shfn.args.len(){
+            return self.err(format!("fn {} takes {} arguments, got {}", shfn.name, shfn.args.len(), args.len()))
+        }
+        for (i, (arg, fnarg)) in args.iter().zip(shfn.args.iter()).enumerate(){
+            if *arg != fnarg.ty{
+                return self.err(format!("Argument {} ({}) of fn {} is {}, got {}", i, fnarg.name, shfn.name, fnarg.ty, arg))
+            }
+        }
+        if !self.fn_todo.contains(&shfn.name){
+            self.fn_todo.push(shfn.name.clone());
+        }
+        Some(shfn.ret.clone())
+    }
+
+    // builtins are generic over T (any type, the same for every T), F (float types),
+    // B (bool types) and O (optional)
+    fn call_builtin(&mut self, shfn:&ShFn, args:&Vec<String>)->Option<String>{
+        if self.stage == TyStage::Vertex && (shfn.name == "dfdx" || shfn.name == "dfdy" || shfn.name == "fwidth"){
+            return self.err(format!("{} only exists in the pixel shader", shfn.name))
+        }
+        if args.len() > shfn.args.len(){
+            return self.err(format!("{} takes at most {} arguments, got {}", shfn.name, shfn.args.len(), args.len()))
+        }
+        let mut gen_t:Option<String> = None;
+        let mut gen_b:Option<String> = None;
+        for (i, fnarg) in shfn.args.iter().enumerate(){
+            let arg = if let Some(arg) = args.get(i){arg}else{
+                if fnarg.ty != "O"{
+                    return self.err(format!("{} needs argument {} ({})", shfn.name, i, fnarg.name))
+                }
+                continue
+            };
+            match fnarg.ty.as_ref(){
+                "T"=>{
+                    if let Some(t) = &gen_t{
+                        if t != arg{
+                            return self.err(format!("Arguments of {} have to be the same type, got {} and {}", shfn.name, t, arg))
+                        }
+                    }
+                    gen_t = Some(arg.clone());
+                },
+                "F"=>if !is_float_ty(arg){
+                    return self.err(format!("Argument {} of {} has to be a float type, got {}", i, shfn.name, arg))
+                },
+                "B"=>{
+                    if !is_bool_ty(arg){
+                        return self.err(format!("Argument {} of {} has to be a bool type, got {}", i, shfn.name, arg))
+                    }
+                    gen_b = Some(arg.clone());
+                },
+                "O"=>(),
+                ty=>if ty != arg.as_str(){
+                    return self.err(format!("Argument {} of {} has to be {}, got {}", i, shfn.name, ty, arg))
+                }
+            }
+        }
+        Some(match shfn.ret.as_ref(){
+            "T"=>gen_t.unwrap_or_default(),
+            // the comparisons return a bool vector the size of T
+            "B"=>gen_b.unwrap_or_else(|| match gen_t.as_ref().map(|t| vec_size(t)){
+                Some(2)=>"bvec2",
+                Some(3)=>"bvec3",
+                Some(4)=>"bvec4",
+                _=>"bool"
+            }.to_string()),
+            ret=>ret.to_string()
+        })
+    }
+
+    fn construct(&mut self, ty:&str, args:&Vec<String>)->Option<String>{
+        // metal makes structs with braces, the literal generates the right one
+        if self.is_struct(ty){
+            return self.err(format!("Use a struct literal {}{{..}} to make a {}", ty, ty))
+        }
+        self.rule(construct_ty(ty, args))
+    }
+}
+
+impl Shader{
+    pub fn type_check(&self)->Result<(), Vec<ShDiag>>{
+        let ast_span = self.asts.last().map(|ast| ast.span.clone()).unwrap_or_default();
+        let mut cx = TyCx{
+            sh:self,
+            stage:TyStage::Constant,
+            in_fn:String::new(),
+            ret:String::new(),
+            span:ast_span.clone(),
+            scope:Vec::new(),
+            fn_todo:Vec::new(),
+            diags:Vec::new()
+        };
+
+        for ast in &self.asts{
+            for shty in ast.types.iter().filter(|shty| !shty.prim){
+                cx.span = ShSpan{src:format!("struct {}", shty.name), ..ast.span.clone()};
+                for field in &shty.fields{
+                    if Shader::array_type(&field.ty).is_some(){
+                        cx.err(format!("Field {} of struct {} can't be an array", field.name, shty.name));
+                    }
+                    else if !cx.is_type(&field.ty) || field.ty == "texture2d"{
+                        cx.err(format!("Field {} of struct {} has unknown type {}", field.name, shty.name, field.ty));
+                    }
+                }
+                if cx.contains_struct(&shty.name, &shty.name, 0){
+                    cx.err(format!("Struct {} contains itself", shty.name));
+                }
+            }
+            for var in &ast.vars{
+                cx.span = ShSpan{src:format!("let {}:{}<{}>", var.name, var.ty, store_name(&var.store)), ..ast.span.clone()};
+                if !cx.is_type(&var.ty){
+                    cx.err(format!("Variable {} has unknown type {}", var.name, var.ty));
+                    continue;
+                }
+                let texture = var.ty == "texture2d";
+                match var.store{
+                    ShVarStore::Texture=>if !texture{
+                        cx.err(format!("Texture {} has to be a texture2d", var.name));
+                    },
+                    _ if texture=>{
+                        cx.err(format!("texture2d {} has to be stored as Texture", var.name));
+                    },
+                    ShVarStore::Local=>(),
+                    // geometry, instances, uniforms and varyings are packed in float slots
+                    _=>if !is_float_ty(&var.ty) && mat_size(&var.ty) == 0{
+                        cx.err(format!("{} variable {} has to be a float, vec or mat type", store_name(&var.store), var.name));
+                    }
+                }
+            }
+            for cnst in &ast.consts{
+                cx.span = ShSpan{src:format!("const {}:{}", cnst.name, cnst.ty), ..ast.span.clone()};
+                if let Some(ty) = cx.expr(&cnst.value){
+                    if ty != cnst.ty{
+                        cx.err(format!("Constant {} is declared {} but is {}", cnst.name, cnst.ty, ty));
+                    }
+                }
+            }
+        }
+
+        for (stage, entry) in &[(TyStage::Vertex, "vertex"), (TyStage::Pixel, "pixel")]{
+            cx.stage = *stage;
+            cx.in_fn = String::new();
+            cx.span = ast_span.clone();
+            match self.find_fn(entry){
+                Some(shfn) if shfn.args.len() != 0 || shfn.ret != "vec4"=>{
+                    cx.err(format!("fn {} has to be fn {}()->vec4", entry, entry));
+                },
+                None=>{
+                    cx.err(format!("Shader has no fn {}", entry));
+                },
+                _=>()
+            }
+            cx.fn_todo = vec![entry.to_string()];
+            let mut done = 0;
+            while done < cx.fn_todo.len(){
+                let name = cx.fn_todo[done].clone();
+                done += 1;
+                if let Some(shfn) = self.find_fn(&name){
+                    cx.check_fn(shfn);
+                }
+            }
+        }
+        if cx.diags.len() != 0{
+            return Err(cx.diags)
+        }
+        Ok(())
+    }
+}
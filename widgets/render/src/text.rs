@@ -131,6 +131,19 @@ impl Text{
             self.font_id = 0;
         }
         let aligned = cx.new_aligned_instance(self.shader_id, 0);
+        self.push_text_uniforms(cx, aligned)
+    }
+
+    // like begin_text, but in a draw call of its own so it goes over everything drawn before
+    pub fn begin_text_layer(&mut self, cx:&mut Cx)->AlignedInstance{
+        if !cx.fonts[self.font_id].loaded{
+            self.font_id = 0;
+        }
+        let aligned = cx.new_aligned_instance_layer(self.shader_id, 0);
+        self.push_text_uniforms(cx, aligned)
+    }
+
+    fn push_text_uniforms(&self, cx:&mut Cx, aligned:AlignedInstance)->AlignedInstance{
         if aligned.inst.need_uniforms_now(cx){
             //texture,
             aligned.inst.push_uniform_texture_2d(cx, cx.fonts[self.font_id].texture_id);
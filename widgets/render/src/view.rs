@@ -22,6 +22,8 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
     pub draw_list_id:Option<usize>,
     pub is_clipped:bool,
     pub is_overlay:bool,// this view is an overlay, rendered last
+    pub is_texture:bool,// draws into texture_id instead of its parent, sample it with push_uniform_texture_2d
+    pub texture_id:Option<usize>,
     pub scroll_h:Option<TScrollBar>,
     pub scroll_v:Option<TScrollBar>,
 }
@@ -33,6 +35,8 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
         Self{
             is_clipped:true,
             is_overlay:false,
+            is_texture:false,
+            texture_id:None,
             draw_list_id:None,
             scroll_h:None,
             scroll_v:None
@@ -93,6 +97,10 @@ where TScrollBar: ScrollBarLike<TScrollBar> + Clone + ElementLife
             draw_list.redraw_id = cx.redraw_id;
             draw_list.draw_calls_len = 0;
         }
+        if self.is_texture && self.texture_id.is_none(){
+            self.texture_id = Some(cx.new_empty_texture_2d().texture_id);
+        }
+        cx.draw_lists[self.draw_list_id.unwrap()].texture_id = if self.is_texture{self.texture_id}else{None};
         let draw_list_id = self.draw_list_id.unwrap();
         
         let nesting_draw_list_id = cx.current_draw_list_id;
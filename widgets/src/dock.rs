@@ -81,6 +81,7 @@ pub struct DockTab<TItem>
 where TItem: Clone
 {
     pub closeable:bool,
+    pub dirty:bool,
     pub title:String,
     pub item:TItem
 }
@@ -332,7 +333,7 @@ where TItem: Clone
                         let tab_control = self.tab_controls.get_draw(cx, stack_top.uid, |_cx,tmpl| tmpl.clone());
                         tab_control.begin_tabs(cx);
                         for (id,tab) in tabs.iter().enumerate(){
-                            tab_control.draw_tab(cx, &tab.title, *current == id, tab.closeable);
+                            tab_control.draw_tab(cx, &tab.title, *current == id, tab.closeable, tab.dirty);
                         }
                         tab_control.end_tabs(cx);
                         tab_control.begin_tab_page(cx);
@@ -326,6 +326,32 @@ impl FileTree{
         paths
     }
 
+    // every file in the tree including the ones in closed folders, in the same form as current_path
+    pub fn get_all_file_paths(&self)->Vec<String>{
+        let mut paths = Vec::new();
+        Self::collect_file_paths(&self.root_node, None, &mut paths);
+        paths
+    }
+
+    fn collect_file_paths(node:&FileNode, parent_path:Option<&str>, paths:&mut Vec<String>){
+        let path = if let Some(parent_path) = parent_path{
+            format!("{}/{}", parent_path, node.name())
+        }
+        else{
+            node.name()
+        };
+        match node{
+            FileNode::File{..}=>{
+                paths.push(path);
+            },
+            FileNode::Folder{folder, ..}=>{
+                for child in folder{
+                    Self::collect_file_paths(child, Some(&path), paths);
+                }
+            }
+        }
+    }
+
     pub fn handle_file_tree(&mut self, cx:&mut Cx, event:&mut Event)->FileTreeEvent{
         // alright. someone clicking on the tree items.
         let mut file_walker = FileWalker::new(&mut self.root_node);
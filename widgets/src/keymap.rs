@@ -0,0 +1,188 @@
+use render::*;
+use serde::*;
+
+// A table from keys to the named commands of a widget. A binding is a chord like "primary+shift+d",
+// or a sequence of them split by spaces like "primary+k primary+c". primary is cmd on macOS and ctrl
+// elsewhere, so one table can serve both platforms. Widgets feed their KeyDown events to handle_key.
+#[derive(Clone)]
+pub struct KeyMap<C>
+where C: KeyCommand
+{
+    pub is_mac:bool,
+    pub bindings:Vec<KeyBinding<C>>,
+    // the chords of a sequence typed so far
+    pub _pending:Vec<KeyChord>
+}
+
+// the commands a keymap binds to, named so user keymap files can refer to them
+pub trait KeyCommand: Clone + PartialEq{
+    fn name(&self)->&'static str;
+    fn from_name(name:&str)->Option<Self>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyChord{
+    pub key_code:KeyCode,
+    pub modifiers:KeyModifiers
+}
+
+#[derive(Clone)]
+pub struct KeyBinding<C>{
+    pub keys:Vec<KeyChord>,
+    pub command:C
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyMapResult<C>{
+    None,
+    // the key started or continued a sequence
+    Pending,
+    Command(C)
+}
+
+// an entry of a keymap file, a command starting with - removes that binding.
+// when names the keymap it goes into, so one file can configure several widgets
+#[derive(Deserialize, Debug)]
+struct JsonKeyBinding{
+    key:String,
+    command:String,
+    when:Option<String>
+}
+
+impl<C> KeyMap<C>
+where C: KeyCommand
+{
+    pub fn new(is_mac:bool)->Self{
+        KeyMap{
+            is_mac:is_mac,
+            bindings:Vec::new(),
+            _pending:Vec::new()
+        }
+    }
+
+    pub fn parse_keys(&self, keys:&str)->Result<Vec<KeyChord>, String>{
+        let mut chords = Vec::new();
+        for chord in keys.split_whitespace(){
+            let mut modifiers = KeyModifiers{..Default::default()};
+            let mut key_code = None;
+            for part in chord.split('+'){
+                match part.to_lowercase().as_ref(){
+                    "shift"=>modifiers.shift = true,
+                    "ctrl" | "control"=>modifiers.control = true,
+                    "alt" | "option"=>modifiers.alt = true,
+                    "cmd" | "logo" | "super" | "meta"=>modifiers.logo = true,
+                    "primary"=>if self.is_mac{modifiers.logo = true}else{modifiers.control = true},
+                    _=>key_code = Some(Self::parse_key_code(part).ok_or_else(|| format!("Unknown key {} in {}", part, keys))?)
+                }
+            }
+            chords.push(KeyChord{
+                key_code:key_code.ok_or_else(|| format!("No key in {}", keys))?,
+                modifiers:modifiers
+            });
+        }
+        if chords.len() == 0{
+            return Err("Empty key binding".to_string())
+        }
+        Ok(chords)
+    }
+
+    // single letters and digits, or a KeyCode name in any case with or without its Key or Arrow prefix
+    fn parse_key_code(name:&str)->Option<KeyCode>{
+        let lower = name.to_lowercase();
+        KEY_CODES.iter().find(|key_code|{
+            let key_name = format!("{:?}", key_code).to_lowercase();
+            key_name == lower || key_name == format!("key{}", lower) || key_name == format!("arrow{}", lower)
+        }).cloned()
+    }
+
+    // later bindings win over earlier ones for the same keys
+    pub fn bind(&mut self, keys:&str, command:C)->Result<(), String>{
+        let keys = self.parse_keys(keys)?;
+        self.bindings.push(KeyBinding{
+            keys:keys,
+            command:command
+        });
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, keys:&str, command:&C)->Result<(), String>{
+        let keys = self.parse_keys(keys)?;
+        self.bindings.retain(|binding| binding.keys != keys || binding.command != *command);
+        Ok(())
+    }
+
+    // applies a keymap file over the bindings, a list of {"key":"primary+d", "command":"duplicate", "when":"code_editor"}.
+    // only entries for this keymap are used, entries without a when are for the default one
+    pub fn load_json(&mut self, json:&str, when:&str, is_default:bool)->Result<(), String>{
+        let entries:Vec<JsonKeyBinding> = serde_json::from_str(json).map_err(|err| format!("Invalid keymap: {}", err))?;
+        for entry in entries{
+            let is_ours = match &entry.when{
+                Some(entry_when)=>entry_when == when,
+                None=>is_default
+            };
+            if !is_ours{
+                continue
+            }
+            let (remove, name) = if entry.command.starts_with('-'){(true, &entry.command[1..])}else{(false, &entry.command[..])};
+            let command = C::from_name(name).ok_or_else(|| format!("Unknown command {}", name))?;
+            if remove{
+                self.unbind(&entry.key, &command)?;
+            }
+            else{
+                self.bind(&entry.key, command)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn handle_key(&mut self, ke:&KeyEvent)->KeyMapResult<C>{
+        // modifiers are pressed in between the chords of a sequence
+        match ke.key_code{
+            KeyCode::LeftShift | KeyCode::RightShift | KeyCode::LeftControl | KeyCode::RightControl |
+            KeyCode::LeftAlt | KeyCode::RightAlt | KeyCode::LeftLogo | KeyCode::RightLogo=>{
+                return if self._pending.len() > 0{KeyMapResult::Pending}else{KeyMapResult::None}
+            },
+            _=>()
+        }
+        self._pending.push(KeyChord{
+            key_code:ke.key_code.clone(),
+            modifiers:ke.modifiers.clone()
+        });
+        let result = self.match_pending();
+        if let KeyMapResult::None = result{
+            // a sequence that went nowhere, the last key can still start something
+            if self._pending.len() > 1{
+                self._pending = vec![self._pending.pop().unwrap()];
+                return self.match_pending()
+            }
+            self._pending.truncate(0);
+        }
+        result
+    }
+
+    fn match_pending(&mut self)->KeyMapResult<C>{
+        let mut is_prefix = false;
+        for binding in self.bindings.iter().rev(){
+            if binding.keys.len() < self._pending.len() || binding.keys[0..self._pending.len()] != self._pending[..]{
+                continue
+            }
+            if binding.keys.len() == self._pending.len(){
+                self._pending.truncate(0);
+                return KeyMapResult::Command(binding.command.clone())
+            }
+            is_prefix = true;
+        }
+        if is_prefix{
+            return KeyMapResult::Pending
+        }
+        if self._pending.len() == 1{
+            self._pending.truncate(0);
+        }
+        KeyMapResult::None
+    }
+
+    // the keys bound to a command, for showing in menus and tooltips
+    pub fn get_keys(&self, command:&C)->Option<&Vec<KeyChord>>{
+        self.bindings.iter().rev().find(|binding| binding.command == *command).map(|binding| &binding.keys)
+    }
+}
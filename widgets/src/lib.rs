@@ -17,6 +17,8 @@ mod style;
 pub use crate::style::*;
 mod filetree;
 pub use crate::filetree::*;
+mod keymap;
+pub use crate::keymap::*;
 //mod textbuffer;
 //pub use crate::textbuffer::*;
 //mod editor;
@@ -9,6 +9,7 @@ pub struct Tab{
     pub tab_close: TabClose,
     pub label:String,
     pub is_closeable:bool,
+    pub is_dirty:bool,
     pub animator:Animator,
 
     pub _is_selected:bool,
@@ -32,6 +33,7 @@ impl Style for Tab{
         let mut tab = Self{
             label:"Tab".to_string(),
             is_closeable:true,
+            is_dirty:false,
             bg:Quad{
                 shader_id:cx.add_shader(bg_sh,"Tab.bg"),
                 ..Style::style(cx)
@@ -306,7 +308,12 @@ impl Tab{
             }
             // push the 2 vars we added to bg shader
             self.text.color = self.animator.last_color("text.color");
-            self._text_area = self.text.draw_text(cx, &self.label);
+            if self.is_dirty{ // mark unsaved changes
+                self._text_area = self.text.draw_text(cx, &format!("{}*", self.label));
+            }
+            else{
+                self._text_area = self.text.draw_text(cx, &self.label);
+            }
 
             self._bg_area = self.bg.end_quad(cx, &bg_inst);
         }
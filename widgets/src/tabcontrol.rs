@@ -183,12 +183,13 @@ impl TabControl{
         self._tab_id_alloc = 0;
     }
 
-    pub fn draw_tab(&mut self, cx:&mut Cx, label:&str, selected:bool, closeable:bool){
+    pub fn draw_tab(&mut self, cx:&mut Cx, label:&str, selected:bool, closeable:bool, dirty:bool){
         let new_tab = self.tabs.get(self._tab_id_alloc).is_none();
         let tab = self.tabs.get_draw(cx, self._tab_id_alloc, |_cx, tmpl| tmpl.clone());
         self._tab_id_alloc += 1;
         tab.label = label.to_string();
         tab.is_closeable = closeable;
+        tab.is_dirty = dirty;
         if new_tab{
             tab.set_tab_state(cx, selected, self._focussed);
         }
@@ -211,6 +212,7 @@ impl TabControl{
             let origin_tab = self.tabs.get_draw(cx, *id, |_cx, tmpl| tmpl.clone());
             self.drag_tab.label = origin_tab.label.clone();
             self.drag_tab.is_closeable = origin_tab.is_closeable;
+            self.drag_tab.is_dirty = origin_tab.is_dirty;
             self.drag_tab.draw_tab(cx);
 
             self.drag_tab_view.end_view(cx);
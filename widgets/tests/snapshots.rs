@@ -0,0 +1,338 @@
+// Golden image and draw list snapshots of the widgets, drawn by the headless renderer.
+// run with: cargo test --features headless --test snapshots
+// a missing or different golden file fails, set MAKEPAD_UPDATE_GOLDEN=1 to record them all again
+#![cfg(feature = "headless")]
+
+use widgets::*;
+
+fn new_cx()->Cx{
+    // the fonts load relative to the repository root
+    std::env::set_current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/..")).unwrap();
+    let mut cx = Cx{
+        title:"snapshots".to_string(),
+        target_size:Vec2{x:400., y:300.},
+        target_dpi_factor:1.,
+        ..Default::default()
+    };
+    set_dark_style(&mut cx);
+    cx
+}
+
+fn check_golden(cx:&mut Cx, name:&str){
+    cx.check_golden_image(&format!("widgets/tests/golden/{}.pam", name)).unwrap();
+    cx.check_golden_draw_lists(&format!("widgets/tests/golden/{}.txt", name)).unwrap();
+}
+
+struct SplitterApp{
+    splitter:Splitter,
+    quad:Quad
+}
+
+impl SplitterApp{
+    fn handle(&mut self, cx:&mut Cx, event:&mut Event){
+        if let Event::Draw = event{
+            return self.draw(cx)
+        }
+        match self.splitter.handle_splitter(cx, event){
+            SplitterEvent::Moving{new_pos}=>{
+                self.splitter.set_splitter_state(SplitterAlign::First, new_pos, Axis::Vertical);
+                cx.redraw_area(Area::All);
+            },
+            SplitterEvent::MovingEnd{new_align, new_pos}=>{
+                self.splitter.set_splitter_state(new_align, new_pos, Axis::Vertical);
+                cx.redraw_area(Area::All);
+            },
+            _=>()
+        }
+    }
+
+    fn draw(&mut self, cx:&mut Cx){
+        self.splitter.begin_splitter(cx);
+        self.quad.color = color("#4");
+        self.quad.draw_quad_walk(cx, Bounds::Fill, Bounds::Fill, Margin::zero());
+        self.splitter.mid_splitter(cx);
+        self.quad.color = color("#6");
+        self.quad.draw_quad_walk(cx, Bounds::Fill, Bounds::Fill, Margin::zero());
+        self.splitter.end_splitter(cx);
+    }
+}
+
+#[test]
+fn splitter_drag(){
+    let mut cx = new_cx();
+    let mut app = SplitterApp{
+        splitter:Splitter{..Style::style(&mut cx)},
+        quad:Quad{..Style::style(&mut cx)}
+    };
+    app.splitter.set_splitter_state(SplitterAlign::First, 100., Axis::Vertical);
+    cx.event_loop(|cx, event| app.handle(cx, event));
+    check_golden(&mut cx, "splitter_start");
+
+    cx.play_script(|cx, event| app.handle(cx, event), &[
+        ScriptEvent::FingerMove(Vec2{x:101., y:150.}),
+        ScriptEvent::FingerDown(Vec2{x:101., y:150.}),
+        ScriptEvent::FingerMove(Vec2{x:180., y:150.}),
+        ScriptEvent::FingerUp(Vec2{x:250., y:150.}),
+        ScriptEvent::Wait(1.)
+    ]);
+    // let go past the middle, so it realigns to the last pane
+    assert!(app.splitter.align == SplitterAlign::Last);
+    assert_eq!(app.splitter.pos, 400. - 249.);
+    check_golden(&mut cx, "splitter_dragged");
+}
+
+#[test]
+fn splitter_replay(){
+    let script = [
+        ScriptEvent::FingerMove(Vec2{x:101., y:150.}),
+        ScriptEvent::FingerDown(Vec2{x:101., y:150.}),
+        ScriptEvent::FingerMove(Vec2{x:60., y:150.}),
+        ScriptEvent::FingerUp(Vec2{x:60., y:150.}),
+        ScriptEvent::Wait(0.5)
+    ];
+    let new_app = |cx:&mut Cx|{
+        let mut app = SplitterApp{
+            splitter:Splitter{..Style::style(cx)},
+            quad:Quad{..Style::style(cx)}
+        };
+        app.splitter.set_splitter_state(SplitterAlign::First, 100., Axis::Vertical);
+        app
+    };
+
+    let mut cx = new_cx();
+    let mut app = new_app(&mut cx);
+    cx.event_loop(|cx, event| app.handle(cx, event));
+    cx.start_recording();
+    cx.play_script(|cx, event| app.handle(cx, event), &script);
+    let recording = cx.stop_recording().unwrap();
+    assert_eq!(EventRecording::parse(&recording.to_text()).unwrap().to_text(), recording.to_text());
+
+    // the replay goes through the text form, like a recording loaded from a file
+    let mut replay_cx = new_cx();
+    let mut replay_app = new_app(&mut replay_cx);
+    replay_cx.event_loop(|cx, event| replay_app.handle(cx, event));
+    replay_cx.play_recording(|cx, event| replay_app.handle(cx, event), EventRecording::parse(&recording.to_text()).unwrap());
+    assert!(!replay_cx.is_replaying());
+    assert_eq!(replay_app.splitter.pos, 59.);
+    assert_eq!(replay_cx.dump_draw_lists(), cx.dump_draw_lists());
+    assert!(replay_cx.render_to_image() == cx.render_to_image());
+}
+
+struct DockApp{
+    dock:Dock<Color>,
+    quad:Quad
+}
+
+impl DockApp{
+    fn handle(&mut self, cx:&mut Cx, event:&mut Event){
+        if let Event::Draw = event{
+            return self.draw(cx)
+        }
+        let mut dock_walker = self.dock.walker();
+        while let Some(_item) = dock_walker.walk_handle_dock(cx, event){}
+        self.dock.handle_dock(cx, event);
+    }
+
+    fn draw(&mut self, cx:&mut Cx){
+        self.dock.draw_dock(cx);
+        let mut dock_walker = self.dock.walker();
+        while let Some(item) = dock_walker.walk_draw_dock(cx){
+            self.quad.color = *item;
+            self.quad.draw_quad_walk(cx, Bounds::Fill, Bounds::Fill, Margin::zero());
+        }
+    }
+}
+
+fn tab(title:&str, color:Color)->DockTab<Color>{
+    DockTab{
+        closeable:false,
+        dirty:false,
+        title:title.to_string(),
+        item:color
+    }
+}
+
+#[test]
+fn dock_tabs(){
+    let mut cx = new_cx();
+    let mut app = DockApp{
+        dock:Dock{
+            dock_items:Some(DockItem::Splitter{
+                axis:Axis::Horizontal,
+                align:SplitterAlign::First,
+                pos:120.0,
+                first:Box::new(DockItem::TabControl{
+                    current:0,
+                    tabs:vec![tab("Red", color("#822")), tab("Green", color("#282"))]
+                }),
+                last:Box::new(DockItem::TabControl{
+                    current:0,
+                    tabs:vec![tab("Blue", color("#228"))]
+                })
+            }),
+            ..Style::style(&mut cx)
+        },
+        quad:Quad{..Style::style(&mut cx)}
+    };
+    cx.event_loop(|cx, event| app.handle(cx, event));
+    check_golden(&mut cx, "dock_start");
+
+    // select the Green tab
+    cx.play_script(|cx, event| app.handle(cx, event), &[
+        ScriptEvent::FingerMove(Vec2{x:90., y:10.}),
+        ScriptEvent::FingerDown(Vec2{x:90., y:10.}),
+        ScriptEvent::FingerUp(Vec2{x:90., y:10.}),
+        ScriptEvent::Wait(1.)
+    ]);
+    check_golden(&mut cx, "dock_tab_selected");
+}
+
+struct FileTreeApp{
+    file_tree:FileTree
+}
+
+impl FileTreeApp{
+    fn handle(&mut self, cx:&mut Cx, event:&mut Event){
+        if let Event::Draw = event{
+            return self.file_tree.draw_file_tree(cx)
+        }
+        self.file_tree.handle_file_tree(cx, event);
+    }
+}
+
+fn file(name:&str)->FileNode{
+    FileNode::File{name:name.to_string(), draw:None}
+}
+
+fn folder(name:&str, state:NodeState, folder:Vec<FileNode>)->FileNode{
+    FileNode::Folder{name:name.to_string(), draw:None, state:state, folder:folder}
+}
+
+#[test]
+fn file_tree_open_folder(){
+    let mut cx = new_cx();
+    let mut app = FileTreeApp{
+        file_tree:FileTree{..Style::style(&mut cx)}
+    };
+    app.file_tree.root_node = folder("", NodeState::Open, vec![
+        folder("src", NodeState::Closed, vec![file("main.rs"), file("lib.rs")]),
+        file("Cargo.toml")
+    ]);
+    cx.event_loop(|cx, event| app.handle(cx, event));
+    check_golden(&mut cx, "file_tree_start");
+
+    cx.play_script(|cx, event| app.handle(cx, event), &[
+        ScriptEvent::FingerMove(Vec2{x:30., y:10.}),
+        ScriptEvent::FingerDown(Vec2{x:30., y:10.}),
+        ScriptEvent::FingerUp(Vec2{x:30., y:10.}),
+        ScriptEvent::Wait(1.)
+    ]);
+    check_golden(&mut cx, "file_tree_opened");
+}
+
+struct TextureApp{
+    view:View<NoScrollBar>,
+    quad:Quad,
+    blit:Blit
+}
+
+impl TextureApp{
+    fn draw(&mut self, cx:&mut Cx){
+        // the panel only draws into its texture, the blits show it twice
+        self.view.begin_view(cx, &Layout{
+            width:Bounds::Fix(100.),
+            height:Bounds::Fix(50.),
+            ..Default::default()
+        });
+        self.quad.color = color("red");
+        self.quad.draw_quad(cx, Rect{x:0., y:0., w:50., h:50.});
+        self.quad.color = color("blue");
+        self.quad.draw_quad(cx, Rect{x:50., y:0., w:50., h:50.});
+        self.view.end_view(cx);
+        let texture_id = self.view.texture_id.unwrap();
+        self.blit.draw_blit(cx, texture_id, Rect{x:200., y:100., w:100., h:50.});
+        self.blit.alpha = 0.5;
+        self.blit.draw_blit(cx, texture_id, Rect{x:200., y:200., w:50., h:25.});
+        self.blit.alpha = 1.0;
+    }
+}
+
+#[test]
+fn texture_view(){
+    let mut cx = new_cx();
+    let mut app = TextureApp{
+        view:View{
+            is_texture:true,
+            ..Style::style(&mut cx)
+        },
+        quad:Quad{..Style::style(&mut cx)},
+        blit:Blit{..Style::style(&mut cx)}
+    };
+    cx.event_loop(|cx, event| if let Event::Draw = event{app.draw(cx)});
+    check_golden(&mut cx, "texture_view");
+
+    let image = cx.render_to_image();
+    let texture = &cx.textures_2d[app.view.texture_id.unwrap()];
+    assert_eq!((texture.width, texture.height), (100, 50));
+    let near = |a:Color, b:Color| (a.r - b.r).abs() < 0.02 && (a.g - b.g).abs() < 0.02 && (a.b - b.b).abs() < 0.02;
+    // nothing of the panel itself ends up in the window
+    assert!(near(image.get_pixel(25, 25), cx.clear_color));
+    assert!(near(image.get_pixel(225, 125), color("red")));
+    assert!(near(image.get_pixel(275, 125), color("blue")));
+    // the thumbnail is scaled down and blended at half alpha
+    let half = |c:Color| Color{r:c.r * 0.5 + cx.clear_color.r * 0.5, g:c.g * 0.5 + cx.clear_color.g * 0.5, b:c.b * 0.5 + cx.clear_color.b * 0.5, a:1.};
+    assert!(near(image.get_pixel(210, 210), half(color("red"))));
+    assert!(near(image.get_pixel(240, 210), half(color("blue"))));
+}
+
+#[test]
+fn clipboard_shortcuts(){
+    let ctrl = KeyModifiers{control:true, ..Default::default()};
+    let script = [
+        ScriptEvent::KeyDown(KeyCode::KeyV, ctrl.clone()),
+        ScriptEvent::KeyDown(KeyCode::KeyC, ctrl.clone()),
+        ScriptEvent::KeyDown(KeyCode::KeyV, ctrl.clone())
+    ];
+    let mut pasted = Vec::new();
+    let mut cx = new_cx();
+    cx.event_loop(|_cx, _event|{});
+    cx.copy_to_clipboard("one\ntwo");
+    cx.start_recording();
+    cx.play_script(|_cx, event| match event{
+        Event::TextCopy(req)=>req.response = Some("copied".to_string()),
+        Event::Paste(pe)=>pasted.push(pe.text.clone()),
+        _=>()
+    }, &script);
+    assert_eq!(pasted, vec!["one\ntwo".to_string(), "copied".to_string()]);
+
+    // the pastes are recorded, so a replay doesn't need the clipboard
+    let recording = cx.stop_recording().unwrap();
+    assert_eq!(EventRecording::parse(&recording.to_text()).unwrap().to_text(), recording.to_text());
+    let mut replayed = Vec::new();
+    let mut replay_cx = new_cx();
+    replay_cx.event_loop(|_cx, _event|{});
+    replay_cx.play_recording(|_cx, event| if let Event::Paste(pe) = event{
+        replayed.push(pe.text.clone());
+    }, recording);
+    assert_eq!(replayed, pasted);
+}
+
+#[test]
+fn replay_drops_live_input(){
+    let none = KeyModifiers{..Default::default()};
+    let mut cx = new_cx();
+    cx.event_loop(|_cx, _event|{});
+    cx.start_recording();
+    cx.play_script(|_cx, _event|{}, &[ScriptEvent::KeyDown(KeyCode::KeyA, none.clone())]);
+    let recording = cx.stop_recording().unwrap();
+
+    let mut keys = Vec::new();
+    let mut replay_cx = new_cx();
+    replay_cx.event_loop(|_cx, _event|{});
+    replay_cx.start_replay(recording);
+    // the user typing while the recording plays doesn't reach the app
+    replay_cx.play_script(|_cx, event| if let Event::KeyDown(ke) = event{keys.push(ke.key_code.clone())}, &[ScriptEvent::KeyDown(KeyCode::KeyB, none.clone())]);
+    while replay_cx.replay_step(|_cx, event| if let Event::KeyDown(ke) = event{keys.push(ke.key_code.clone())}){}
+    replay_cx.play_script(|_cx, event| if let Event::KeyDown(ke) = event{keys.push(ke.key_code.clone())}, &[ScriptEvent::KeyDown(KeyCode::KeyC, none.clone())]);
+    assert_eq!(keys, vec![KeyCode::KeyA, KeyCode::KeyC]);
+}